@@ -601,13 +601,15 @@ pub async fn check_database_lock(_app: AppHandle, working_dir: String) -> Result
     use std::fs::OpenOptions;
     use std::path::PathBuf;
 
-    let db_path = PathBuf::from(&working_dir)
-        .join("data")
-        .join("duck_client.db");
-
-    if !db_path.exists() {
+    // 旧版 duck-cli 的数据库在新版本 nuwax-cli 首次运行时才会迁移为 nuwax_client.db
+    let data_dir = PathBuf::from(&working_dir).join("data");
+    let Some(db_path) = ["nuwax_client.db", "duck_client.db"]
+        .iter()
+        .map(|name| data_dir.join(name))
+        .find(|path| path.exists())
+    else {
         return Ok(false); // 文件不存在，没有锁定问题
-    }
+    };
 
     // 尝试以独占模式打开文件来检测锁定
     match OpenOptions::new().read(true).write(true).open(&db_path) {
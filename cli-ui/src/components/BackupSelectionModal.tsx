@@ -4,7 +4,7 @@ import { DuckCliManager } from '../utils/tauri';
 
 interface BackupRecord {
   id: number;
-  backup_type: 'Manual' | 'PreUpgrade';
+  backup_type: 'Manual' | 'PreUpgrade' | 'Scheduled' | 'Snapshot';
   created_at: string;
   service_version: string;
   file_path: string;
@@ -47,7 +47,7 @@ const BackupSelectionModal: React.FC<BackupSelectionModalProps> = ({
         // 直接使用返回的结构化数据
         const backupList = result.backups.map(backup => ({
           id: backup.id,
-          backup_type: backup.backup_type as 'Manual' | 'PreUpgrade',
+          backup_type: backup.backup_type as BackupRecord['backup_type'],
           created_at: backup.created_at,
           service_version: backup.service_version,
           file_path: backup.file_path,
@@ -88,14 +88,30 @@ const BackupSelectionModal: React.FC<BackupSelectionModalProps> = ({
 
   // 格式化备份类型
   const formatBackupType = (type: string): string => {
-    return type === 'Manual' ? '手动备份' : '升级前备份';
+    switch (type) {
+      case 'Manual':
+        return '手动备份';
+      case 'Scheduled':
+        return '定时备份';
+      case 'Snapshot':
+        return '快照备份';
+      default:
+        return '升级前备份';
+    }
   };
 
   // 获取备份类型颜色
   const getBackupTypeColor = (type: string): string => {
-    return type === 'Manual' 
-      ? 'bg-blue-100 text-blue-800' 
-      : 'bg-purple-100 text-purple-800';
+    switch (type) {
+      case 'Manual':
+        return 'bg-blue-100 text-blue-800';
+      case 'Scheduled':
+        return 'bg-green-100 text-green-800';
+      case 'Snapshot':
+        return 'bg-yellow-100 text-yellow-800';
+      default:
+        return 'bg-purple-100 text-purple-800';
+    }
   };
 
   // 格式化时间显示
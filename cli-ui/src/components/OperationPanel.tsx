@@ -35,7 +35,7 @@ interface ActionButton {
 
 interface BackupRecord {
   id: number;
-  backup_type: 'Manual' | 'PreUpgrade';
+  backup_type: 'Manual' | 'PreUpgrade' | 'Scheduled' | 'Snapshot';
   created_at: string;
   service_version: string;
   file_path: string;
@@ -132,7 +132,7 @@ const OperationPanel: React.FC<OperationPanelProps> = ({
     
     try {
       onLogMessage(`🔄 开始回滚到备份 #${backupId}...`, 'info');
-      onLogMessage(`📋 备份信息: ${backupInfo.backup_type === 'Manual' ? '手动' : '升级前'}备份, 版本 ${backupInfo.service_version}`, 'info');
+      onLogMessage(`📋 备份信息: ${({ Manual: '手动', PreUpgrade: '升级前', Scheduled: '定时', Snapshot: '快照' } as const)[backupInfo.backup_type]}备份, 版本 ${backupInfo.service_version}`, 'info');
       
       // 使用统一的命令执行方式，获得实时输出（就像其他按钮一样）
       const args = ['rollback', backupId.toString(), '--force'];
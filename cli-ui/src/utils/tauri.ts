@@ -719,10 +719,12 @@ export class DuckCliManager {
   static async checkCliUpdate(workingDir: string): Promise<{ success: boolean; output: string; error?: string }> {
     try {
       const result = await this.executeSmart(['check-update', 'check'], workingDir);
+      // 退出码 10/11 表示有可用更新，不是执行失败
+      const success = result.success || result.exit_code === 10 || result.exit_code === 11;
       return {
-        success: result.success,
+        success,
         output: result.stdout,
-        error: result.success ? undefined : result.stderr
+        error: success ? undefined : result.stderr
       };
     } catch (error) {
       return {
@@ -169,6 +169,8 @@ CREATE TABLE IF NOT EXISTS backup_records (
     file_count INTEGER, -- 备份文件数量
     compression_type VARCHAR DEFAULT 'gzip', -- 压缩类型
     backup_hash VARCHAR, -- 备份文件哈希值
+    namespace VARCHAR, -- 备份所属的项目命名空间，为空表示早期未区分命名空间的备份
+    verified BOOLEAN, -- 创建后校验结果，为空表示未校验
     description TEXT, -- 备份描述
     
     -- 备份元数据
@@ -181,6 +183,12 @@ CREATE TABLE IF NOT EXISTS backup_records (
 CREATE INDEX IF NOT EXISTS idx_backup_records_created_at ON backup_records(created_at);
 CREATE INDEX IF NOT EXISTS idx_backup_records_type ON backup_records(backup_type);
 
+-- 早期创建的数据库没有 namespace 列
+ALTER TABLE backup_records ADD COLUMN IF NOT EXISTS namespace VARCHAR;
+
+-- 早期创建的数据库没有 verified 列
+ALTER TABLE backup_records ADD COLUMN IF NOT EXISTS verified BOOLEAN;
+
 -- ========================================
 -- 升级管理表
 -- ========================================
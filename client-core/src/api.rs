@@ -1,19 +1,26 @@
 use crate::api_config::ApiConfig;
 use crate::api_types::*;
 use crate::authenticated_client::AuthenticatedClient;
+use crate::clock;
+use crate::constants::api::http::CLIENT_ID_HEADER;
 use crate::downloader::{DownloadProgress, DownloaderConfig, FileDownloader};
 use crate::error::DuckError;
+use crate::events::EventSender;
+use crate::http_cache::{self, CachedResponse};
+use crate::http_trace;
+use crate::rate_limit;
+use crate::upgrade_session;
+use crate::upgrade_strategy::UpgradeStrategy;
 use crate::version::Version;
 use anyhow::Result;
 use futures::stream::StreamExt;
 use reqwest::Client;
-use sha2::{Digest, Sha256};
 use std::io::{self, Write};
 use std::path::Path;
 use std::sync::Arc;
 use tokio::fs::File;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 
 /// API 客户端
 #[derive(Debug, Clone)]
@@ -50,11 +57,19 @@ impl ApiClient {
         &self.config
     }
 
+    /// 当前的客户端ID，认证客户端刷新过凭据时使用新的客户端ID
+    fn current_client_id(&self) -> Option<String> {
+        self.authenticated_client
+            .as_ref()
+            .and_then(|auth| auth.client_id_now())
+            .or_else(|| self.client_id.clone())
+    }
+
     /// 构建带客户端ID的请求
     fn build_request(&self, url: &str) -> reqwest::RequestBuilder {
         let mut request = self.client.get(url);
-        if let Some(ref client_id) = self.client_id {
-            request = request.header("X-Client-ID", client_id);
+        if let Some(client_id) = self.current_client_id() {
+            request = request.header(CLIENT_ID_HEADER, client_id);
         }
         request
     }
@@ -62,19 +77,81 @@ impl ApiClient {
     /// 构建POST请求
     fn build_post_request(&self, url: &str) -> reqwest::RequestBuilder {
         let mut request = self.client.post(url);
-        if let Some(ref client_id) = self.client_id {
-            request = request.header("X-Client-ID", client_id);
+        if let Some(client_id) = self.current_client_id() {
+            request = request.header(CLIENT_ID_HEADER, client_id);
         }
         request
     }
 
+    /// 发送请求，被服务器限流（429 / Retry-After）时按 Retry-After 或指数退避自动重试
+    ///
+    /// 需要等待太久或重试次数用尽时返回 [`DuckError::RateLimited`]，由调用方安排稍后重试。
+    /// 认证失败 (401) 时通过认证客户端刷新凭据，并用新的客户端ID重试一次
+    async fn send_with_rate_limit(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        let mut request = request;
+        let mut attempt = 0;
+        let mut reauthorized = false;
+        loop {
+            let next = request.try_clone();
+            let response = http_trace::send(request).await?;
+            clock::record_server_date(response.headers());
+            let status = response.status();
+            if status == reqwest::StatusCode::UNAUTHORIZED && !reauthorized {
+                let auth = self
+                    .authenticated_client
+                    .as_ref()
+                    .filter(|auth| auth.requires_auth(response.url().as_str()));
+                if let (Some(auth), Some(next)) = (auth, next) {
+                    warn!("API请求认证失败 (401)，尝试刷新客户端凭据...");
+                    request = auth
+                        .reauthorize(next)
+                        .await
+                        .map_err(|e| anyhow::anyhow!("认证失败且无法重新注册: {e}"))?;
+                    reauthorized = true;
+                    continue;
+                }
+                return Ok(response);
+            }
+            if !rate_limit::is_rate_limited(status, response.headers()) {
+                return Ok(response);
+            }
+
+            // Retry-After 中的日期按服务器时间计算，本地时间不准确时也能得到正确的等待时间
+            let delay = rate_limit::retry_delay(response.headers(), attempt, clock::server_now());
+            match next {
+                Some(next) if rate_limit::should_wait(delay, attempt) => {
+                    attempt += 1;
+                    warn!(
+                        "⏳ 服务器繁忙 ({})，{} 秒后重试 ({}/{})",
+                        status,
+                        delay.as_secs(),
+                        attempt,
+                        crate::constants::api::http::RATE_LIMIT_MAX_RETRIES
+                    );
+                    tokio::time::sleep(delay).await;
+                    request = next;
+                }
+                _ => {
+                    let error = rate_limit::rate_limited_error(delay);
+                    warn!("⏳ {}", error);
+                    return Err(error.into());
+                }
+            }
+        }
+    }
+
     /// 注册客户端
     pub async fn register_client(&self, request: ClientRegisterRequest) -> Result<String> {
         let url = self
             .config
             .get_endpoint_url(&self.config.endpoints.client_register);
 
-        let response = self.client.post(&url).json(&request).send().await?;
+        let response = self
+            .send_with_rate_limit(self.client.post(&url).json(&request))
+            .await?;
 
         if response.status().is_success() {
             let register_response: RegisterClientResponse = response.json().await?;
@@ -101,38 +178,104 @@ impl ApiClient {
             url = format!("{url}?since={since_time}");
         }
 
-        let response = self.build_request(&url).send().await?;
+        let text = self.get_text_with_cache(&url, "公告").await?;
+        upgrade_session::record_api_response(&url, &text);
+        let announcements = serde_json::from_str(&text)
+            .map_err(|e| DuckError::Api(format!("公告JSON解析失败: {e}")))?;
+        Ok(announcements)
+    }
+
+    /// 发送带条件请求头（If-None-Match / If-Modified-Since）的 GET 请求并返回响应正文
+    ///
+    /// 服务器返回 304 时使用本地缓存；网络错误或服务器错误时退回到缓存的副本
+    async fn get_text_with_cache(&self, url: &str, what: &str) -> Result<String> {
+        let cached = http_cache::load_cached_response(url).await;
+
+        let mut request = self.build_request(url);
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = match self.send_with_rate_limit(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                return match cached {
+                    Some(cached) => {
+                        warn_using_stale_cache(what, &cached, &e.to_string());
+                        Ok(cached.body)
+                    }
+                    None => Err(e),
+                };
+            }
+        };
 
-        if response.status().is_success() {
-            let announcements = response.json().await?;
-            Ok(announcements)
-        } else {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            error!("获取公告失败: {} - {}", status, text);
-            Err(anyhow::anyhow!("获取公告失败: {status} - {text}"))
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                debug!("{}未变化，使用本地缓存", what);
+                return Ok(cached.body);
+            }
         }
+
+        if status.is_success() {
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string)
+            };
+            let etag = header(reqwest::header::ETAG);
+            let last_modified = header(reqwest::header::LAST_MODIFIED);
+            let body = response.text().await?;
+            http_cache::store_cached_response(
+                url,
+                &CachedResponse {
+                    etag,
+                    last_modified,
+                    body: body.clone(),
+                    fetched_at: chrono::Utc::now(),
+                },
+            )
+            .await;
+            return Ok(body);
+        }
+
+        let text = response.text().await.unwrap_or_default();
+        if status.is_server_error() {
+            if let Some(cached) = cached {
+                warn_using_stale_cache(what, &cached, &format!("{status} - {text}"));
+                return Ok(cached.body);
+            }
+        }
+        error!("获取{}失败: {} - {}", what, status, text);
+        Err(anyhow::anyhow!("获取{what}失败: {status} - {text}"))
     }
 
-    /// 检查Docker服务版本
+    /// 检查Docker服务版本，服务器版本高于当前版本时才视为有更新
     pub async fn check_docker_version(
         &self,
-        current_version: &str,
+        current_version: &Version,
     ) -> Result<DockerVersionResponse> {
         let url = self
             .config
             .get_endpoint_url(&self.config.endpoints.docker_check_version);
 
-        let response = self.build_request(&url).send().await?;
+        let response = self.send_with_rate_limit(self.build_request(&url)).await?;
 
         if response.status().is_success() {
             let manifest: ServiceManifest = response.json().await?;
 
             // 从ServiceManifest构造DockerVersionResponse
-            let has_update = manifest.version != current_version;
+            let has_update = manifest.version > *current_version;
             let docker_version_response = DockerVersionResponse {
                 current_version: current_version.to_string(),
-                latest_version: manifest.version,
+                latest_version: manifest.version.to_string(),
                 has_update,
                 release_notes: Some(manifest.release_notes),
             };
@@ -152,7 +295,7 @@ impl ApiClient {
             .config
             .get_endpoint_url(&self.config.endpoints.docker_update_version_list);
 
-        let response = self.build_request(&url).send().await?;
+        let response = self.send_with_rate_limit(self.build_request(&url)).await?;
 
         if response.status().is_success() {
             let version_list = response.json().await?;
@@ -201,13 +344,13 @@ impl ApiClient {
                 Ok(request_builder) => auth_client.send(request_builder, url).await?,
                 Err(e) => {
                     warn!("使用AuthenticatedClient失败，回退到普通请求: {}", e);
-                    self.build_request(url).send().await?
+                    self.send_with_rate_limit(self.build_request(url)).await?
                 }
             }
         } else {
             // 使用普通客户端（直接URL下载）
             info!("使用普通HTTP客户端下载");
-            self.build_request(url).send().await?
+            self.send_with_rate_limit(self.build_request(url)).await?
         };
 
         if !response.status().is_success() {
@@ -304,7 +447,9 @@ impl ApiClient {
             .config
             .get_service_upgrade_history_url(&request.service_name);
 
-        let response = self.build_post_request(&url).json(&request).send().await?;
+        let response = self
+            .send_with_rate_limit(self.build_post_request(&url).json(&request))
+            .await?;
 
         if response.status().is_success() {
             info!("服务升级历史上报成功");
@@ -318,6 +463,26 @@ impl ApiClient {
         }
     }
 
+    /// 查询服务器记录的服务升级历史
+    pub async fn get_service_upgrade_history(
+        &self,
+        service_name: &str,
+    ) -> Result<Vec<ServiceUpgradeHistoryEntry>> {
+        let url = self.config.get_service_upgrade_history_url(service_name);
+
+        let response = self.send_with_rate_limit(self.build_request(&url)).await?;
+
+        if response.status().is_success() {
+            let history: ServiceUpgradeHistoryResponse = response.json().await?;
+            Ok(history.history)
+        } else {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            error!("获取服务升级历史失败: {} - {}", status, text);
+            Err(anyhow::anyhow!("获取服务升级历史失败: {status} - {text}"))
+        }
+    }
+
     /// 上报客户端自升级历史
     pub async fn report_client_self_upgrade_history(
         &self,
@@ -327,7 +492,9 @@ impl ApiClient {
             .config
             .get_endpoint_url(&self.config.endpoints.client_self_upgrade_history);
 
-        let response = self.build_post_request(&url).json(&request).send().await?;
+        let response = self
+            .send_with_rate_limit(self.build_post_request(&url).json(&request))
+            .await?;
 
         if response.status().is_success() {
             info!("客户端自升级历史上报成功");
@@ -347,7 +514,9 @@ impl ApiClient {
             .config
             .get_endpoint_url(&self.config.endpoints.telemetry);
 
-        let response = self.build_post_request(&url).json(&request).send().await?;
+        let response = self
+            .send_with_rate_limit(self.build_post_request(&url).json(&request))
+            .await?;
 
         if response.status().is_success() {
             info!("遥测数据上报成功");
@@ -368,33 +537,9 @@ impl ApiClient {
             .get_endpoint_url(&self.config.endpoints.docker_download_full)
     }
 
-    /// 计算文件的SHA256哈希值
+    /// 计算文件的SHA256哈希值（大文件并行读取与计算，结果按文件指纹缓存）
     pub async fn calculate_file_hash(file_path: &Path) -> Result<String> {
-        if !file_path.exists() {
-            return Err(anyhow::anyhow!("文件不存在: {}", file_path.display()));
-        }
-
-        let mut file = File::open(file_path).await.map_err(|e| {
-            DuckError::Custom(format!("无法打开文件 {}: {}", file_path.display(), e))
-        })?;
-
-        let mut hasher = Sha256::new();
-        let mut buffer = vec![0u8; 8192]; // 8KB buffer
-
-        loop {
-            let bytes_read = file.read(&mut buffer).await.map_err(|e| {
-                DuckError::Custom(format!("读取文件失败 {}: {}", file_path.display(), e))
-            })?;
-
-            if bytes_read == 0 {
-                break;
-            }
-
-            hasher.update(&buffer[..bytes_read]);
-        }
-
-        let hash = hasher.finalize();
-        Ok(format!("{hash:x}"))
+        crate::file_hash::sha256_file(file_path).await
     }
 
     /// 保存文件哈希信息到.hash文件
@@ -600,59 +745,107 @@ impl ApiClient {
             .config
             .get_endpoint_url(&self.config.endpoints.docker_check_version);
 
-        let response = self.build_request(&url).send().await?;
+        // 录制升级会话时保存清单原文，回放时按同样的规则重新解析
+        let text = self.get_text_with_cache(&url, "增强服务清单").await?;
+        upgrade_session::record_api_response(&url, &text);
+        let manifest = Self::parse_enhanced_service_manifest(&text)?;
+        upgrade_session::record_manifest(&text);
+        Ok(manifest)
+    }
 
-        if response.status().is_success() {
-            // 先获取原始json文本，解析为serde_json::Value，判断根对象是否有 platforms 字段
-            let text = response.text().await?;
-            let json_value: serde_json::Value = serde_json::from_str(&text)
-                .map_err(|e| DuckError::Api(format!("服务清单JSON解析失败: {e}")))?;
-
-            let has_platforms = match &json_value {
-                serde_json::Value::Object(map) => map.contains_key("platforms"),
-                _ => false,
+    /// 获取升级策略中未内联在服务清单里的补丁操作清单
+    ///
+    /// 操作清单只有几 KB，在下载补丁包之前获取；变更预览、受保护路径冲突检查和升级前备份的范围
+    /// 都依赖它，不必等待补丁包下载完成。清单中已内联 `operations` 时不再请求
+    pub async fn fetch_patch_operations(&self, strategy: &mut UpgradeStrategy) -> Result<()> {
+        let patches: Vec<&mut PatchPackageInfo> = match strategy {
+            UpgradeStrategy::PatchUpgrade { patch_info, .. } => vec![patch_info],
+            UpgradeStrategy::PatchChainUpgrade { steps, .. } => {
+                steps.iter_mut().map(|step| &mut step.patch_info).collect()
+            }
+            UpgradeStrategy::FullUpgrade { .. } | UpgradeStrategy::NoUpgrade { .. } => Vec::new(),
+        };
+        for patch_info in patches {
+            let Some(operations_url) = &patch_info.operations_url else {
+                continue;
             };
+            if !patch_info.operations.is_empty() {
+                continue;
+            }
+            let url = if operations_url.starts_with('/') {
+                self.config.get_endpoint_url(operations_url)
+            } else {
+                operations_url.clone()
+            };
+            let text = self.get_text_with_cache(&url, "补丁操作清单").await?;
+            upgrade_session::record_api_response(&url, &text);
+            patch_info.operations = Self::parse_patch_operations(&text)?;
+            info!(
+                "📋 已获取补丁操作清单: {} 项操作",
+                patch_info.operations.total_operations()
+            );
+        }
+        Ok(())
+    }
 
-            if has_platforms {
-                // 有 platforms 字段，按增强格式解析
-                match serde_json::from_value::<EnhancedServiceManifest>(json_value) {
-                    Ok(manifest) => {
-                        info!("📋 成功解析增强服务清单");
-                        manifest.validate()?; // 进行数据验证
-                        Ok(manifest)
-                    }
-                    Err(e) => {
-                        error!("💥 应用服务升级解析失败 - 增强格式: {}", e);
-                        Err(anyhow::anyhow!("应用服务升级解析失败 - 增强格式: {}", e))
-                    }
+    /// 解析补丁操作清单：operations 对象本身，或包含 `operations` 字段的补丁包信息
+    pub fn parse_patch_operations(text: &str) -> Result<PatchOperations> {
+        let mut value: serde_json::Value = serde_json::from_str(text)
+            .map_err(|e| DuckError::Api(format!("补丁操作清单JSON解析失败: {e}")))?;
+        if let Some(operations) = value.get_mut("operations") {
+            value = operations.take();
+        }
+        let operations: PatchOperations = serde_json::from_value(value)
+            .map_err(|e| DuckError::Api(format!("补丁操作清单格式无效: {e}")))?;
+        operations.validate()?;
+        Ok(operations)
+    }
+
+    /// 解析服务清单JSON：有 platforms 字段时按增强格式解析，否则按旧格式解析并转换
+    pub fn parse_enhanced_service_manifest(text: &str) -> Result<EnhancedServiceManifest> {
+        let json_value: serde_json::Value = serde_json::from_str(text)
+            .map_err(|e| DuckError::Api(format!("服务清单JSON解析失败: {e}")))?;
+
+        let has_platforms = match &json_value {
+            serde_json::Value::Object(map) => map.contains_key("platforms"),
+            _ => false,
+        };
+
+        if has_platforms {
+            // 有 platforms 字段，按增强格式解析
+            match serde_json::from_value::<EnhancedServiceManifest>(json_value) {
+                Ok(manifest) => {
+                    info!("📋 成功解析增强服务清单");
+                    manifest.validate()?; // 进行数据验证
+                    Ok(manifest)
                 }
-            } else {
-                // 没有 platforms 字段，按旧格式解析并转换
-                match serde_json::from_value::<ServiceManifest>(json_value) {
-                    Ok(old_manifest) => {
-                        info!("📋 成功解析旧版服务清单，转换为增强格式");
-                        let enhanced_manifest = EnhancedServiceManifest {
-                            version: old_manifest.version.parse::<Version>()?,
-                            release_date: old_manifest.release_date,
-                            release_notes: old_manifest.release_notes,
-                            packages: Some(old_manifest.packages),
-                            platforms: None,
-                            patch: None,
-                        };
-                        enhanced_manifest.validate()?;
-                        Ok(enhanced_manifest)
-                    }
-                    Err(e) => {
-                        error!("💥 应用服务升级解析失败 - 旧格式: {}", e);
-                        Err(anyhow::anyhow!("应用服务升级解析失败 - 旧格式: {}", e))
-                    }
+                Err(e) => {
+                    error!("💥 应用服务升级解析失败 - 增强格式: {}", e);
+                    Err(anyhow::anyhow!("应用服务升级解析失败 - 增强格式: {}", e))
                 }
             }
         } else {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            error!("获取增强服务清单失败: {} - {}", status, text);
-            Err(anyhow::anyhow!("获取增强服务清单失败: {status} - {text}"))
+            // 没有 platforms 字段，按旧格式解析并转换
+            match serde_json::from_value::<ServiceManifest>(json_value) {
+                Ok(old_manifest) => {
+                    info!("📋 成功解析旧版服务清单，转换为增强格式");
+                    let enhanced_manifest = EnhancedServiceManifest {
+                        version: old_manifest.version,
+                        release_date: old_manifest.release_date,
+                        release_notes: old_manifest.release_notes,
+                        packages: Some(old_manifest.packages),
+                        platforms: None,
+                        patch: None,
+                        patch_chain: Vec::new(),
+                    };
+                    enhanced_manifest.validate()?;
+                    Ok(enhanced_manifest)
+                }
+                Err(e) => {
+                    error!("💥 应用服务升级解析失败 - 旧格式: {}", e);
+                    Err(anyhow::anyhow!("应用服务升级解析失败 - 旧格式: {}", e))
+                }
+            }
         }
     }
 
@@ -664,6 +857,45 @@ impl ApiClient {
         download_url: &str,
         progress_callback: Option<F>,
     ) -> Result<()>
+    where
+        F: Fn(DownloadProgress) + Send + Sync + 'static,
+    {
+        self.download_service_update_inner(
+            download_path,
+            version,
+            download_url,
+            progress_callback,
+            EventSender::default(),
+        )
+        .await
+    }
+
+    /// 下载服务更新包（带哈希验证和优化），通过 `events` 上报下载阶段、进度和重试
+    pub async fn download_service_update_with_events(
+        &self,
+        download_path: &Path,
+        version: Option<&str>,
+        download_url: &str,
+        events: EventSender,
+    ) -> Result<()> {
+        self.download_service_update_inner::<fn(DownloadProgress)>(
+            download_path,
+            version,
+            download_url,
+            None,
+            events,
+        )
+        .await
+    }
+
+    async fn download_service_update_inner<F>(
+        &self,
+        download_path: &Path,
+        version: Option<&str>,
+        download_url: &str,
+        progress_callback: Option<F>,
+        events: EventSender,
+    ) -> Result<()>
     where
         F: Fn(DownloadProgress) + Send + Sync + 'static,
     {
@@ -730,7 +962,10 @@ impl ApiClient {
 
         // 7. 执行下载
         // 使用新的下载器模块
-        let config = DownloaderConfig::default();
+        let config = DownloaderConfig {
+            events,
+            ..Default::default()
+        };
 
         let downloader = FileDownloader::new(config);
 
@@ -799,6 +1034,19 @@ impl ApiClient {
     }
 }
 
+/// 服务器暂时无法访问时提示正在使用缓存的副本
+fn warn_using_stale_cache(what: &str, cached: &CachedResponse, reason: &str) {
+    warn!(
+        "⚠️ 服务器暂时无法访问（{}），使用 {} 缓存的{}",
+        reason,
+        cached
+            .fetched_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S"),
+        what
+    );
+}
+
 /// 系统信息模块
 /// 用于获取操作系统类型和版本等信息
 #[allow(dead_code)]
@@ -972,4 +1220,32 @@ mod tests {
         println!("   - ✅ 文件操作功能正常");
         println!("   - ✅ 单元测试覆盖充分");
     }
+
+    #[test]
+    fn test_patch_operations_url() {
+        let bare = r#"{"replace": {"files": ["app/app.jar"], "directories": []}}"#;
+        let operations = ApiClient::parse_patch_operations(bare).unwrap();
+        assert_eq!(operations.total_operations(), 1);
+
+        let wrapped = r#"{"operations": {"delete": {"files": [], "directories": ["temp/"]}}}"#;
+        let operations = ApiClient::parse_patch_operations(wrapped).unwrap();
+        assert_eq!(operations.total_operations(), 1);
+
+        assert!(
+            ApiClient::parse_patch_operations(
+                r#"{"replace": {"files": ["../x"], "directories": []}}"#
+            )
+            .is_err()
+        );
+
+        let mut info: PatchPackageInfo = serde_json::from_str(
+            r#"{"url": "https://example.com/patch.tar.gz", "operations_url": "/api/patch/ops.json"}"#,
+        )
+        .unwrap();
+        assert!(info.operations.is_empty());
+        assert!(info.validate().is_ok());
+
+        info.operations_url = Some("ftp://example.com/ops.json".to_string());
+        assert!(info.validate().is_err());
+    }
 }
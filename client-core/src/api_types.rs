@@ -1,3 +1,4 @@
+use crate::architecture::Architecture;
 use crate::version::Version;
 use anyhow::Result;
 use chrono;
@@ -43,7 +44,8 @@ pub struct AnnouncementsResponse {
 /// 服务更新清单响应（传统格式）
 #[derive(Debug, Deserialize)]
 pub struct ServiceManifest {
-    pub version: String,
+    #[serde(deserialize_with = "crate::version::version_from_str")]
+    pub version: Version,
     pub release_date: String,
     pub release_notes: String,
     pub packages: ServicePackages,
@@ -70,6 +72,8 @@ impl From<PackageInfo> for PlatformPackageInfo {
         PlatformPackageInfo {
             url: package_info.url,
             signature: package_info.signature,
+            size: Some(package_info.size),
+            hash: Some(package_info.hash),
         }
     }
 }
@@ -95,6 +99,10 @@ pub struct EnhancedServiceManifest {
 
     /// 新增：增量升级支持
     pub patch: Option<PatchInfo>,
+
+    /// 相邻版本之间的补丁链，客户端落后多个版本时按顺序逐个应用
+    #[serde(default)]
+    pub patch_chain: Vec<PatchChainLink>,
 }
 
 /// 平台特定的包信息
@@ -106,11 +114,28 @@ pub struct PlatformPackages {
     pub aarch64: Option<PlatformPackageInfo>,
 }
 
+impl PlatformPackages {
+    /// 指定架构的全量包，没有发布该架构时返回 None
+    pub fn for_architecture(&self, architecture: &Architecture) -> Option<&PlatformPackageInfo> {
+        match architecture {
+            Architecture::X86_64 => self.x86_64.as_ref(),
+            Architecture::Aarch64 => self.aarch64.as_ref(),
+            Architecture::Unsupported(_) => None,
+        }
+    }
+}
+
 /// 平台包信息
 #[derive(Debug, Deserialize, Clone)]
 pub struct PlatformPackageInfo {
     pub signature: String,
     pub url: String,
+    /// 服务包大小（字节），用于解压前校验下载是否完整
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// 服务包 SHA-256 哈希
+    #[serde(default)]
+    pub hash: Option<String>,
 }
 
 /// 增量升级信息
@@ -122,13 +147,31 @@ pub struct PatchInfo {
     pub aarch64: Option<PatchPackageInfo>,
 }
 
+/// 补丁链中的一环：从 `from_version` 升级到 `to_version` 的补丁
+#[derive(Debug, Deserialize, Clone)]
+pub struct PatchChainLink {
+    #[serde(deserialize_with = "crate::version::version_from_str")]
+    pub from_version: Version,
+    #[serde(deserialize_with = "crate::version::version_from_str")]
+    pub to_version: Version,
+    #[serde(rename = "x86_64")]
+    pub x86_64: Option<PatchPackageInfo>,
+    #[serde(rename = "aarch64")]
+    pub aarch64: Option<PatchPackageInfo>,
+}
+
 /// 增量升级包信息
 #[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct PatchPackageInfo {
     pub url: String,
     pub hash: Option<String>,
     pub signature: Option<String>,
+    /// 补丁操作，清单中只给出 `operations_url` 时为空，在选择升级策略后单独获取
+    #[serde(default)]
     pub operations: PatchOperations,
+    /// 补丁操作清单的地址，清单未内联 `operations` 时使用，无需下载补丁包即可得到操作清单
+    #[serde(default)]
+    pub operations_url: Option<String>,
     /// 补丁说明
     pub notes: Option<String>,
 }
@@ -153,7 +196,10 @@ impl PatchPackageInfo {
 }
 
 /// 补丁操作集合
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+///
+/// 结构见 `schemas/patch-operations.schema.json`，未知字段会被拒绝
+#[derive(Debug, Deserialize, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
 pub struct PatchOperations {
     ///替换
     pub replace: Option<ReplaceOperations>,
@@ -163,6 +209,7 @@ pub struct PatchOperations {
 
 /// 替换操作
 #[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
 pub struct ReplaceOperations {
     pub files: Vec<String>,
     pub directories: Vec<String>,
@@ -256,6 +303,25 @@ pub struct ServiceUpgradeHistoryRequest {
     pub details: Option<String>,
 }
 
+/// 服务升级历史查询响应
+#[derive(Debug, Deserialize)]
+pub struct ServiceUpgradeHistoryResponse {
+    #[serde(default)]
+    pub history: Vec<ServiceUpgradeHistoryEntry>,
+}
+
+/// 服务器记录的一次服务升级
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceUpgradeHistoryEntry {
+    pub from_version: String,
+    pub to_version: String,
+    pub status: String,
+    #[serde(default)]
+    pub details: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
 /// 客户端自升级历史上报请求
 #[derive(Serialize)]
 pub struct ClientSelfUpgradeHistoryRequest {
@@ -321,6 +387,11 @@ impl EnhancedServiceManifest {
             patch.validate()?;
         }
 
+        // 验证补丁链
+        for link in &self.patch_chain {
+            link.validate()?;
+        }
+
         Ok(())
     }
 
@@ -444,6 +515,29 @@ impl PatchInfo {
     }
 }
 
+impl PatchChainLink {
+    /// 验证补丁链中的一环
+    pub fn validate(&self) -> Result<()> {
+        if self.to_version <= self.from_version {
+            return Err(anyhow::anyhow!(
+                "补丁链版本无效: {} -> {}",
+                self.from_version,
+                self.to_version
+            ));
+        }
+
+        if let Some(ref x86_64) = self.x86_64 {
+            x86_64.validate()?;
+        }
+
+        if let Some(ref aarch64) = self.aarch64 {
+            aarch64.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
 impl PatchPackageInfo {
     /// 验证补丁包信息
     pub fn validate(&self) -> Result<()> {
@@ -464,6 +558,15 @@ impl PatchPackageInfo {
             }
         }
 
+        if let Some(operations_url) = &self.operations_url {
+            if !operations_url.starts_with("http://")
+                && !operations_url.starts_with("https://")
+                && !operations_url.starts_with("/")
+            {
+                return Err(anyhow::anyhow!("补丁操作清单URL格式无效"));
+            }
+        }
+
         self.operations.validate()?;
 
         Ok(())
@@ -482,9 +585,22 @@ impl PatchOperations {
             delete.validate()?;
         }
 
+        // 重复条目、replace 与 delete 路径重叠等问题在应用补丁前报告
+        if let Some(issue) = crate::patch_manifest::check_operations(self)
+            .into_iter()
+            .next()
+        {
+            return Err(anyhow::anyhow!("补丁操作无效: {}", issue));
+        }
+
         Ok(())
     }
 
+    /// 是否没有任何操作
+    pub fn is_empty(&self) -> bool {
+        self.total_operations() == 0
+    }
+
     /// 计算补丁操作总数
     pub fn total_operations(&self) -> usize {
         let mut total_operations = 0;
@@ -586,12 +702,12 @@ mod tests {
                     },
                     "delete": {
                         "files": [
-                            "app/app.jar",
-                            "config/application.yml"
+                            "app/old.jar",
+                            "config/legacy.yml"
                         ],
                         "directories": [
-                            "front/",
-                            "plugins/"
+                            "front_old/",
+                            "plugins_old/"
                         ]
                     }
                 }
@@ -613,12 +729,12 @@ mod tests {
                     },
                     "delete": {
                         "files": [
-                            "app/app.jar",
-                            "config/application.yml"
+                            "app/old.jar",
+                            "config/legacy.yml"
                         ],
                         "directories": [
-                            "front/",
-                            "plugins/"
+                            "front_old/",
+                            "plugins_old/"
                         ]
                     }
                 }
@@ -734,12 +850,13 @@ mod tests {
 
         // 转换为增强格式
         let enhanced_manifest = EnhancedServiceManifest {
-            version: legacy_manifest.version.parse::<Version>().unwrap(),
+            version: legacy_manifest.version,
             release_date: legacy_manifest.release_date,
             release_notes: legacy_manifest.release_notes,
             packages: Some(legacy_manifest.packages),
             platforms: None,
             patch: None,
+            patch_chain: Vec::new(),
         };
 
         // 验证转换后的格式
@@ -798,6 +915,8 @@ mod tests {
         let valid_platform_pkg = PlatformPackageInfo {
             signature: "valid_signature".to_string(),
             url: "https://example.com/package.zip".to_string(),
+            size: None,
+            hash: None,
         };
 
         valid_platform_pkg
@@ -807,6 +926,8 @@ mod tests {
         let invalid_platform_pkg = PlatformPackageInfo {
             signature: "signature".to_string(),
             url: "".to_string(), // 空URL
+            size: None,
+            hash: None,
         };
 
         assert!(invalid_platform_pkg.validate().is_err(), "空URL应该被拒绝");
@@ -869,12 +990,13 @@ mod tests {
 
         // 验证legacy到enhanced的转换
         let converted = EnhancedServiceManifest {
-            version: legacy_manifest.version.parse::<Version>().unwrap(),
+            version: legacy_manifest.version,
             release_date: legacy_manifest.release_date,
             release_notes: legacy_manifest.release_notes,
             packages: Some(legacy_manifest.packages),
             platforms: None,
             patch: None,
+            patch_chain: Vec::new(),
         };
 
         // 验证转换后的功能（向后兼容）
@@ -6,6 +6,8 @@
 //! - 架构支持检查
 //! - 友好的错误处理
 
+use crate::archive_format::ArchiveFormat;
+use crate::constants::file_format::ZIP_EXTENSION;
 use crate::constants::upgrade::{DOCKER_SERVICE_AARCH64_PACKAGE, DOCKER_SERVICE_X86_64_PACKAGE};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -63,6 +65,25 @@ impl Architecture {
         }
     }
 
+    /// 获取指定压缩格式的 Docker 服务包文件名
+    ///
+    /// # 示例
+    /// ```
+    /// use client_core::architecture::Architecture;
+    /// use client_core::archive_format::ArchiveFormat;
+    ///
+    /// let arch = Architecture::Aarch64;
+    /// assert_eq!(
+    ///     arch.get_docker_package_name(ArchiveFormat::TarZst),
+    ///     "docker-aarch64.tar.zst"
+    /// );
+    /// ```
+    pub fn get_docker_package_name(&self, format: ArchiveFormat) -> String {
+        let zip_name = self.get_docker_file_name();
+        let stem = zip_name.strip_suffix(ZIP_EXTENSION).unwrap_or(&zip_name);
+        format!("{stem}{}", format.extension())
+    }
+
     /// 转换为字符串表示
     ///
     /// # 示例
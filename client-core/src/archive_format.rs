@@ -0,0 +1,188 @@
+//! # 服务包格式模块
+//!
+//! 识别服务包的压缩格式，支持：
+//! - ZIP（历史格式，支持随机访问）
+//! - tar.gz（gzip 压缩的 tar 包）
+//! - tar.zst（zstd 压缩的 tar 包，压缩率和解压速度更好）
+//!
+//! 格式识别优先使用文件头魔术字节，无法识别时再回退到扩展名，
+//! 这样即使上游更换了格式而本地文件名仍沿用旧扩展名，也能正确解压。
+
+use crate::constants::file_format::{
+    GZIP_MAGIC, TAR_GZ_EXTENSION, TAR_ZST_EXTENSION, TGZ_EXTENSION, ZIP_EXTENSION,
+    ZIP_MAGIC_PK_PREFIX, ZSTD_MAGIC,
+};
+use anyhow::Result;
+use std::fmt::{self, Display};
+use std::io::Read;
+use std::path::Path;
+
+/// 服务包压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArchiveFormat {
+    /// ZIP 格式
+    Zip,
+    /// gzip 压缩的 tar 包
+    TarGz,
+    /// zstd 压缩的 tar 包
+    TarZst,
+}
+
+impl ArchiveFormat {
+    /// 所有支持的格式，按优先级排列
+    pub const ALL: [ArchiveFormat; 3] = [Self::Zip, Self::TarZst, Self::TarGz];
+
+    /// 格式对应的标准文件扩展名（包含前导点）
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Zip => ZIP_EXTENSION,
+            Self::TarGz => TAR_GZ_EXTENSION,
+            Self::TarZst => TAR_ZST_EXTENSION,
+        }
+    }
+
+    /// 是否为 tar 系列格式（只能顺序读取）
+    pub fn is_tar(&self) -> bool {
+        matches!(self, Self::TarGz | Self::TarZst)
+    }
+
+    /// 根据文件名或 URL 的扩展名识别格式
+    ///
+    /// URL 中的查询参数和片段会被忽略
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let name = name
+            .split(['?', '#'])
+            .next()
+            .unwrap_or(name)
+            .to_ascii_lowercase();
+
+        if name.ends_with(TAR_ZST_EXTENSION) || name.ends_with(".tzst") {
+            Some(Self::TarZst)
+        } else if name.ends_with(TAR_GZ_EXTENSION) || name.ends_with(TGZ_EXTENSION) {
+            Some(Self::TarGz)
+        } else if name.ends_with(ZIP_EXTENSION) {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+
+    /// 根据文件头魔术字节识别格式
+    pub fn from_magic_bytes(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&ZSTD_MAGIC) {
+            Some(Self::TarZst)
+        } else if header.starts_with(&GZIP_MAGIC) {
+            Some(Self::TarGz)
+        } else if header.starts_with(&ZIP_MAGIC_PK_PREFIX) {
+            Some(Self::Zip)
+        } else {
+            None
+        }
+    }
+
+    /// 检测文件格式：优先魔术字节，其次扩展名
+    pub fn detect(path: &Path) -> Result<Self> {
+        let mut header = [0u8; 4];
+        let mut file = std::fs::File::open(path)?;
+        let read = file.read(&mut header)?;
+
+        if let Some(format) = Self::from_magic_bytes(&header[..read]) {
+            return Ok(format);
+        }
+
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Self::from_file_name(&file_name)
+            .ok_or_else(|| anyhow::anyhow!("无法识别的服务包格式: {}", path.display()))
+    }
+
+    /// 打开 tar 系列服务包，返回可顺序读取条目的 tar 归档
+    pub fn open_tar(&self, path: &Path) -> Result<tar::Archive<Box<dyn Read>>> {
+        let file = std::fs::File::open(path)?;
+        let reader: Box<dyn Read> = match self {
+            Self::TarGz => Box::new(flate2::read::GzDecoder::new(file)),
+            Self::TarZst => Box::new(zstd::stream::read::Decoder::new(file)?),
+            Self::Zip => {
+                return Err(anyhow::anyhow!("ZIP 格式不是 tar 归档: {}", path.display()));
+            }
+        };
+        Ok(tar::Archive::new(reader))
+    }
+}
+
+impl Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Zip => write!(f, "zip"),
+            Self::TarGz => write!(f, "tar.gz"),
+            Self::TarZst => write!(f, "tar.zst"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_from_file_name() {
+        assert_eq!(
+            ArchiveFormat::from_file_name("docker-x86_64.zip"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            ArchiveFormat::from_file_name("docker-x86_64.tar.gz"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_file_name("docker.tgz"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_file_name("https://cdn.example.com/docker-aarch64.TAR.ZST?sig=abc"),
+            Some(ArchiveFormat::TarZst)
+        );
+        assert_eq!(ArchiveFormat::from_file_name("docker.rar"), None);
+    }
+
+    #[test]
+    fn test_from_magic_bytes() {
+        assert_eq!(
+            ArchiveFormat::from_magic_bytes(&[0x50, 0x4B, 0x03, 0x04]),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            ArchiveFormat::from_magic_bytes(&[0x1F, 0x8B, 0x08, 0x00]),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_magic_bytes(&[0x28, 0xB5, 0x2F, 0xFD]),
+            Some(ArchiveFormat::TarZst)
+        );
+        assert_eq!(ArchiveFormat::from_magic_bytes(&[0x00]), None);
+    }
+
+    #[test]
+    fn test_detect_prefers_magic_bytes_over_extension() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // 文件名沿用 .zip，但内容是 gzip
+        let path = temp_dir.path().join("docker-x86_64.zip");
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(&[0x1F, 0x8B, 0x08, 0x00, 0x00]).unwrap();
+        drop(file);
+
+        assert_eq!(ArchiveFormat::detect(&path).unwrap(), ArchiveFormat::TarGz);
+    }
+
+    #[test]
+    fn test_detect_falls_back_to_extension() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("docker.tar.zst");
+        std::fs::write(&path, b"").unwrap();
+
+        assert_eq!(ArchiveFormat::detect(&path).unwrap(), ArchiveFormat::TarZst);
+    }
+}
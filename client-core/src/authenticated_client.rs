@@ -1,19 +1,29 @@
+use crate::constants::api::http::{CLIENT_ID_HEADER, REREGISTER_COOLDOWN_SECS};
+use crate::http_trace;
 use crate::{ClientRegisterRequest, database::Database};
 use anyhow::Result;
+use reqwest::header::HeaderValue;
 use reqwest::{Client, Method, RequestBuilder, Response};
 use serde::Serialize;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
 use tracing::{error, info, warn};
 
 /// 认证客户端包装器
 /// 自动处理client_id的设置和认证失败时的重新注册
+///
+/// 服务器轮换客户端凭据后请求会返回 401：此时重新注册获得新的 client_id 并保存到数据库，
+/// 再用新的 client_id 重试一次原请求。并发请求同时失败时只重新注册一次，
+/// 重新注册后冷却期内仍被拒绝时不再注册，避免反复注册。
 #[derive(Debug, Clone)]
 pub struct AuthenticatedClient {
     client: Client,
     database: Arc<Database>,
     server_base_url: String,
     client_id: Arc<RwLock<Option<String>>>,
+    /// 最近一次重新注册的时间，同时保证同一时间只有一个请求在重新注册
+    last_register: Arc<Mutex<Option<Instant>>>,
 }
 
 impl AuthenticatedClient {
@@ -29,6 +39,7 @@ impl AuthenticatedClient {
             database,
             server_base_url,
             client_id: Arc::new(RwLock::new(client_id)),
+            last_register: Arc::new(Mutex::new(None)),
         })
     }
 
@@ -42,15 +53,23 @@ impl AuthenticatedClient {
         url.contains("/clients/register")
     }
 
+    /// 请求是否需要携带 client_id（我们的服务器且非注册接口）
+    pub fn requires_auth(&self, url: &str) -> bool {
+        self.is_our_server(url) && !self.is_register_endpoint(url)
+    }
+
     /// 获取当前的client_id
-    async fn get_client_id(&self) -> Option<String> {
-        self.client_id.read().await.clone()
+    fn get_client_id(&self) -> Option<String> {
+        self.client_id
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
     }
 
     /// 更新client_id
     async fn set_client_id(&self, new_client_id: String) -> Result<()> {
         // 更新内存中的值
-        *self.client_id.write().await = Some(new_client_id.clone());
+        *self.client_id.write().unwrap_or_else(|e| e.into_inner()) = Some(new_client_id.clone());
 
         // 保存到数据库
         self.database.update_client_id(&new_client_id).await?;
@@ -73,12 +92,7 @@ impl AuthenticatedClient {
             self.server_base_url,
             crate::constants::api::endpoints::CLIENT_REGISTER
         );
-        let response = self
-            .client
-            .post(&register_url)
-            .json(&request)
-            .send()
-            .await?;
+        let response = http_trace::send(self.client.post(&register_url).json(&request)).await?;
 
         if response.status().is_success() {
             let register_response: serde_json::Value = response.json().await?;
@@ -101,16 +115,57 @@ impl AuthenticatedClient {
         }
     }
 
+    /// 请求因认证失败 (401) 被拒绝后刷新客户端凭据，返回应使用的 client_id
+    ///
+    /// `rejected` 为被拒绝的请求携带的 client_id。其他请求已经刷新过凭据时直接返回新的 client_id；
+    /// 冷却期内已重新注册过仍被拒绝时返回错误，不再注册
+    pub async fn refresh_credentials(&self, rejected: Option<&str>) -> Result<String> {
+        let mut last_register = self.last_register.lock().await;
+
+        if let Some(current) = self
+            .get_client_id()
+            .filter(|current| rejected != Some(current.as_str()))
+        {
+            info!("客户端凭据已被其他请求刷新，使用新的客户端ID重试");
+            return Ok(current);
+        }
+
+        let cooldown = Duration::from_secs(REREGISTER_COOLDOWN_SECS);
+        if let Some(at) = last_register.filter(|at| at.elapsed() < cooldown) {
+            return Err(anyhow::anyhow!(
+                "客户端在 {} 秒前已重新注册，服务器仍拒绝认证 (401)。请检查服务器状态，或运行 'nuwax-cli init --force' 重新初始化",
+                at.elapsed().as_secs()
+            ));
+        }
+
+        // 注册失败同样进入冷却期，避免每个请求都去注册
+        *last_register = Some(Instant::now());
+        self.auto_register().await
+    }
+
+    /// 刷新凭据并返回使用新 client_id 的请求（保留原请求的方法、请求头和请求体）
+    pub async fn reauthorize(&self, request_builder: RequestBuilder) -> Result<RequestBuilder> {
+        let (client, request) = request_builder.build_split();
+        let mut request = request?;
+        let rejected = request
+            .headers()
+            .get(CLIENT_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        let client_id = self.refresh_credentials(rejected.as_deref()).await?;
+        request
+            .headers_mut()
+            .insert(CLIENT_ID_HEADER, HeaderValue::from_str(&client_id)?);
+        Ok(RequestBuilder::from_parts(client, request))
+    }
+
     /// 为请求添加认证头
-    async fn add_auth_header(
-        &self,
-        mut request_builder: RequestBuilder,
-        url: &str,
-    ) -> RequestBuilder {
+    fn add_auth_header(&self, mut request_builder: RequestBuilder, url: &str) -> RequestBuilder {
         // 只对我们的服务器且非注册接口添加认证头
-        if self.is_our_server(url) && !self.is_register_endpoint(url) {
-            if let Some(client_id) = self.get_client_id().await {
-                request_builder = request_builder.header("X-Client-ID", client_id);
+        if self.requires_auth(url) {
+            if let Some(client_id) = self.get_client_id() {
+                request_builder = request_builder.header(CLIENT_ID_HEADER, client_id);
             }
         }
         request_builder
@@ -119,7 +174,7 @@ impl AuthenticatedClient {
     /// 执行请求，自动处理认证
     async fn execute_request(&self, method: Method, url: &str) -> Result<RequestBuilder> {
         let request_builder = self.client.request(method, url);
-        Ok(self.add_auth_header(request_builder, url).await)
+        Ok(self.add_auth_header(request_builder, url))
     }
 
     /// 执行带JSON body的请求
@@ -130,47 +185,44 @@ impl AuthenticatedClient {
         json: &T,
     ) -> Result<RequestBuilder> {
         let request_builder = self.client.request(method, url).json(json);
-        Ok(self.add_auth_header(request_builder, url).await)
+        Ok(self.add_auth_header(request_builder, url))
     }
 
-    /// 发送请求并处理认证失败
+    /// 发送请求并处理认证失败：刷新凭据后只重试一次
     async fn send_with_retry(
         &self,
         request_builder: RequestBuilder,
         original_url: &str,
     ) -> Result<Response> {
-        let response = request_builder.send().await?;
+        let retry_request = request_builder.try_clone();
+        let response = http_trace::send(request_builder).await?;
 
         // 检查是否是认证失败
-        if response.status() == reqwest::StatusCode::UNAUTHORIZED
-            && self.is_our_server(original_url)
-            && !self.is_register_endpoint(original_url)
+        if response.status() != reqwest::StatusCode::UNAUTHORIZED
+            || !self.requires_auth(original_url)
         {
-            warn!("API请求认证失败 (401)，尝试自动重新注册...");
-
-            // 尝试自动注册
-            match self.auto_register().await {
-                Ok(new_client_id) => {
-                    info!("自动重新注册成功，客户端ID: {}，重试请求...", new_client_id);
-
-                    // 重新从头构建请求，使用新的client_id
-                    // 我们需要重新创建请求，因为原来的RequestBuilder已经被消费
-                    let retry_request_builder = self
-                        .client
-                        .get(original_url)
-                        .header("X-Client-ID", new_client_id);
-
-                    let retry_response = retry_request_builder.send().await?;
-                    Ok(retry_response)
-                }
-                Err(e) => {
-                    error!("自动重新注册失败: {}", e);
-                    Err(anyhow::anyhow!("认证失败且无法重新注册: {e}"))
-                }
+            return Ok(response);
+        }
+        warn!("API请求认证失败 (401)，尝试刷新客户端凭据...");
+
+        // 请求体为流时无法重放，刷新凭据供后续请求使用
+        let Some(retry_request) = retry_request else {
+            if let Err(e) = self.refresh_credentials(None).await {
+                error!("刷新客户端凭据失败: {}", e);
             }
-        } else {
-            Ok(response)
+            return Ok(response);
+        };
+
+        let retry_request = self.reauthorize(retry_request).await.map_err(|e| {
+            error!("自动重新注册失败: {}", e);
+            anyhow::anyhow!("认证失败且无法重新注册: {e}")
+        })?;
+        info!("已刷新客户端凭据，重试请求...");
+        let retry_response = http_trace::send(retry_request).await?;
+        if retry_response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            warn!("刷新客户端凭据后仍认证失败 (401)");
         }
+        Ok(retry_response)
     }
 
     /// GET请求
@@ -221,6 +273,44 @@ impl AuthenticatedClient {
 
     /// 获取当前的client_id（只读）
     pub async fn current_client_id(&self) -> Option<String> {
-        self.get_client_id().await
+        self.get_client_id()
+    }
+
+    /// 获取当前的client_id（同步版本，用于构建请求）
+    pub fn client_id_now(&self) -> Option<String> {
+        self.get_client_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_refresh_credentials_is_guarded() {
+        let database = Database::connect_memory().await.unwrap();
+        database.init_database().await.unwrap();
+        let client = AuthenticatedClient::new(Arc::new(database), "http://127.0.0.1:9".to_string())
+            .await
+            .unwrap();
+        client.set_client_id("new-id".to_string()).await.unwrap();
+
+        // 其他请求已刷新过凭据，直接使用新的 client_id，不重新注册
+        assert_eq!(
+            client.refresh_credentials(Some("old-id")).await.unwrap(),
+            "new-id"
+        );
+        assert_eq!(
+            client.database.get_client_id().await.unwrap().as_deref(),
+            Some("new-id")
+        );
+
+        // 冷却期内新的 client_id 仍被拒绝时不再注册
+        *client.last_register.lock().await = Some(Instant::now());
+        let error = client
+            .refresh_credentials(Some("new-id"))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("init --force"), "{error}");
     }
 }
@@ -1,13 +1,51 @@
 use crate::{
+    backup_catalog::{
+        BackupCatalog, BackupCatalogEntry, BackupFileName, CatalogImportReport,
+        ImageArchiveCatalogEntry, parse_backup_file_name,
+    },
+    backup_dedup::{
+        DedupFile, DedupManifest, DedupStats, DedupStore, DedupVerifyReport, GC_GRACE_PERIOD,
+        append_manifest, export_plain_archive, read_manifest,
+    },
+    backup_hooks::{BackupHooks, PreparedHooks},
+    backup_manifest::{
+        BackupManifest, append_backup_manifest, is_backup_manifest_path,
+        parse_backup_manifest_entry, read_backup_manifest, validate_backup_archive,
+    },
+    backup_permissions::{
+        OwnershipMapping, PathPermissions, copy_ownership, restore_path_permissions,
+    },
+    backup_snapshot::{FilesystemSnapshot, create_snapshot},
+    backup_storage::BackupStorage,
+    config::{
+        BackupConfig, BackupHookConfig, BackupRetentionConfig, BackupSnapshotConfig,
+        BackupStagingMode,
+    },
+    constants::{
+        backup::{
+            DEDUP_MANIFEST_NAME, DEDUP_STORE_DIR_NAME, RESTORE_CLEARED_DIR_NAMES,
+            SNAPSHOT_NAME_PREFIX, STAGING_DIR_PREFIX, SYSTEM_BACKUP_DIR_NAME, VERIFY_SAMPLE_FILES,
+        },
+        legacy,
+        telemetry::METRICS_TARGET,
+    },
     container::DockerManager,
-    database::{BackupRecord, BackupStatus, BackupType, Database},
+    database::{BackupPage, BackupQuery, BackupRecord, BackupStatus, BackupType, Database},
     error::DuckError,
+    events::{EventSender, OperationKind, ProgressReporter},
+    file_hash::sha256_file_cached,
+    fs_probe::available_space,
+    image_archive::{is_image_archive_file_name, read_image_archive_manifest},
+    restore_preview::RestorePreview,
+    safe_path::{long_path, resolve_entry_path},
+    symlink::{SymlinkExtractor, relative_link_target},
 };
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use flate2::Compression;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::{fs::File, sync::Arc};
 use tar::Archive;
@@ -21,6 +59,73 @@ pub struct BackupManager {
     storage_dir: PathBuf,
     database: Arc<Database>,
     docker_manager: Arc<DockerManager>,
+    /// 备份存储后端，None 表示备份只保存在 `storage_dir` 中
+    storage: Option<Arc<dyn BackupStorage>>,
+    /// 按服务名配置的备份钩子，复制文件前后在服务容器中执行
+    hooks: BTreeMap<String, BackupHookConfig>,
+    /// 快照暂存使用的文件系统快照配置
+    snapshot: BackupSnapshotConfig,
+    /// 写入备份前的大小上限和可用空间检查
+    size_guard: BackupSizeGuard,
+    /// 恢复时的属主映射
+    restore_ownership: OwnershipMapping,
+}
+
+/// 备份写入前的大小检查
+///
+/// 按源文件总大小（压缩前）预估备份大小：超过上限，或存储目录所在文件系统的可用空间不足以容纳
+/// 预估大小加安全余量时，在写入任何文件之前中止备份，避免写满磁盘后留下不完整的大文件
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackupSizeGuard {
+    /// 预估大小上限（字节），None 表示不限制
+    pub max_size_bytes: Option<u64>,
+    /// 可用空间需在预估大小之外预留的比例（百分比）
+    pub free_space_margin_percent: u64,
+}
+
+impl BackupSizeGuard {
+    /// 按 `[backup]` 配置创建
+    pub fn from_config(config: &BackupConfig) -> Self {
+        Self {
+            max_size_bytes: (config.max_size_mb > 0).then(|| config.max_size_mb * 1024 * 1024),
+            free_space_margin_percent: config.free_space_margin_percent,
+        }
+    }
+
+    /// 检查预估大小，`required` 为写入需要的空间，`available` 为存储目录的可用空间（无法获取时为 None）
+    pub fn check(
+        &self,
+        estimated: u64,
+        required: u64,
+        available: Option<u64>,
+        storage_dir: &Path,
+    ) -> Result<()> {
+        if let Some(max_size) = self.max_size_bytes.filter(|max_size| estimated > *max_size) {
+            return Err(DuckError::Backup(format!(
+                "预计备份大小 {:.1} MB 超过上限 {:.1} MB（backup.max_size_mb），已中止备份",
+                to_mb(estimated),
+                to_mb(max_size)
+            ))
+            .into());
+        }
+        let required =
+            required.saturating_add(required.saturating_mul(self.free_space_margin_percent) / 100);
+        if let Some(available) = available.filter(|available| *available < required) {
+            return Err(DuckError::Backup(format!(
+                "备份存储目录 {} 可用空间不足：预计需要 {:.1} MB（含 {}% 余量），当前可用 {:.1} MB，已中止备份",
+                storage_dir.display(),
+                to_mb(required),
+                self.free_space_margin_percent,
+                to_mb(available)
+            ))
+            .into());
+        }
+        Ok(())
+    }
+}
+
+fn to_mb(bytes: u64) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0
 }
 
 /// 备份选项
@@ -30,12 +135,24 @@ pub struct BackupOptions {
     pub backup_type: BackupType,
     /// 服务版本
     pub service_version: String,
+    /// 备份所属的项目命名空间，写入备份文件名和备份记录
+    pub namespace: Option<String>,
     /// 工作目录
     pub work_dir: PathBuf,
     /// 要备份的文件或目录列表
     pub source_paths: Vec<PathBuf>,
+    /// CLI 自身状态文件（config.toml、数据库等），归档到 `_system/` 目录下，为空表示不备份
+    pub system_paths: Vec<PathBuf>,
     /// 压缩级别 (0-9)
     pub compression_level: u32,
+    /// 文件内容分块去重存储，归档只保存分块清单
+    pub dedup: bool,
+    /// 写入后重新读取归档并与备份清单核对，结果记录到备份记录中，未通过时备份失败
+    pub verify: bool,
+    /// 操作事件发送端，备份过程中上报阶段和进度
+    pub events: EventSender,
+    /// 创建备份的客户端及版本，写入备份格式清单
+    pub created_by: String,
 }
 
 /// 恢复选项
@@ -62,37 +179,307 @@ impl BackupManager {
             storage_dir,
             database,
             docker_manager,
+            storage: None,
+            hooks: BTreeMap::new(),
+            snapshot: BackupSnapshotConfig::default(),
+            size_guard: BackupSizeGuard::default(),
+            restore_ownership: OwnershipMapping::default(),
         })
     }
 
+    /// 设置备份存储后端：新备份保存到后端后删除本地副本，恢复时按需取回
+    pub fn with_storage(mut self, storage: Option<Arc<dyn BackupStorage>>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// 备份存储后端，未配置时返回 None
+    pub fn storage(&self) -> Option<&Arc<dyn BackupStorage>> {
+        self.storage.as_ref()
+    }
+
+    /// 设置备份钩子：创建备份和暂存备份时，在复制文件前后于服务容器中执行
+    pub fn with_hooks(mut self, hooks: BTreeMap<String, BackupHookConfig>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// 设置快照暂存（[`BackupStagingMode::Snapshot`]）使用的文件系统快照
+    pub fn with_snapshot(mut self, snapshot: BackupSnapshotConfig) -> Self {
+        self.snapshot = snapshot;
+        self
+    }
+
+    /// 设置写入备份前的大小上限和可用空间检查
+    pub fn with_size_guard(mut self, size_guard: BackupSizeGuard) -> Self {
+        self.size_guard = size_guard;
+        self
+    }
+
+    /// 设置恢复时的属主处理：是否恢复属主以及 uid/gid 映射
+    pub fn with_restore_ownership(mut self, restore_ownership: OwnershipMapping) -> Self {
+        self.restore_ownership = restore_ownership;
+        self
+    }
+
+    /// 预估备份大小并检查大小上限和存储目录的可用空间
+    ///
+    /// 复制方式暂存时暂存副本和归档同时存在，需要两倍的空间
+    async fn ensure_backup_fits(
+        &self,
+        options: &BackupOptions,
+        staging: Option<BackupStagingMode>,
+    ) -> Result<()> {
+        let paths: Vec<PathBuf> = options
+            .source_paths
+            .iter()
+            .chain(&options.system_paths)
+            .cloned()
+            .collect();
+        let estimated = tokio::task::spawn_blocking(move || source_paths_size(&paths)).await?;
+        let required = if staging == Some(BackupStagingMode::Copy) {
+            estimated.saturating_mul(2)
+        } else {
+            estimated
+        };
+        let available = available_space(&self.storage_dir);
+        info!(
+            "预计备份大小 {:.1} MB（压缩前），存储目录可用空间 {}",
+            to_mb(estimated),
+            available
+                .map(|available| format!("{:.1} MB", to_mb(available)))
+                .unwrap_or_else(|| "未知".to_string())
+        );
+        self.size_guard
+            .check(estimated, required, available, &self.storage_dir)
+    }
+
+    /// 按配置执行前置备份钩子，返回的钩子需在复制完成后调用 [`BackupManager::finish_hooks`]
+    async fn prepare_hooks(&self) -> Result<Option<(BackupHooks, PreparedHooks)>> {
+        if self.hooks.is_empty() {
+            return Ok(None);
+        }
+        let hooks = BackupHooks::new(
+            self.docker_manager.get_compose_project_name(),
+            self.hooks.clone(),
+        );
+        let prepared = hooks.run_pre().await?;
+        Ok(Some((hooks, prepared)))
+    }
+
+    /// 执行后置备份钩子
+    async fn finish_hooks(&self, hooks: Option<(BackupHooks, PreparedHooks)>) {
+        if let Some((hooks, prepared)) = hooks {
+            hooks.run_post(prepared).await;
+        }
+    }
+
     /// 创建备份
     pub async fn create_backup(&self, options: BackupOptions) -> Result<BackupRecord> {
-        // 检查所有源路径是否存在
-        let need_backup_paths = options.source_paths;
-
-        // 生成备份文件名（人类易读格式）
-        let timestamp = Utc::now().format("%Y-%m-%d_%H-%M-%S");
-        let backup_type_str = match options.backup_type {
-            BackupType::Manual => "manual",
-            BackupType::PreUpgrade => "pre-upgrade",
+        let backup_path = self.new_backup_path(&options, Utc::now());
+
+        info!("开始创建备份: {}", backup_path.display());
+        self.ensure_backup_fits(&options, None).await?;
+
+        // 执行备份，前后执行备份钩子
+        let hooks = self.prepare_hooks().await?;
+        let result = self.perform_backup(&options, &backup_path).await;
+        self.finish_hooks(hooks).await;
+
+        self.record_backup_result(options, &backup_path, result)
+            .await
+    }
+
+    /// 暂存备份：先生成文件清单，再把文件复制（或硬链接）到暂存目录
+    ///
+    /// 暂存完成后即可继续修改源文件，之后调用 [`BackupManager::create_backup_from_staging`]
+    /// 压缩暂存副本。硬链接与源文件共享数据，原地写入文件的服务（如数据库）必须等压缩完成后再启动。
+    /// 快照方式为目录创建文件系统快照，不复制文件，压缩时从快照读取
+    pub async fn stage_backup(
+        &self,
+        options: BackupOptions,
+        mode: BackupStagingMode,
+    ) -> Result<StagedBackup> {
+        let created_at = Utc::now();
+        let staging_dir = self.storage_dir.join(format!(
+            "{STAGING_DIR_PREFIX}{}",
+            created_at.format("%Y-%m-%d_%H-%M-%S%.3f")
+        ));
+
+        info!(
+            "开始暂存备份文件 ({}): {}",
+            mode.as_str(),
+            staging_dir.display()
+        );
+        let started = std::time::Instant::now();
+        self.remove_stale_staging_dirs();
+        self.ensure_backup_fits(&options, Some(mode)).await?;
+
+        let source_paths = options.source_paths.clone();
+        let system_paths = options.system_paths.clone();
+        let dir = staging_dir.clone();
+        let events = options.events.clone();
+        let snapshot_config = self.snapshot.clone();
+        let snapshot_name = format!(
+            "{SNAPSHOT_NAME_PREFIX}{}",
+            created_at.format("%Y%m%d%H%M%S")
+        );
+        // 备份钩子只需覆盖复制阶段，压缩暂存副本时服务可以恢复写入
+        let hooks = self.prepare_hooks().await?;
+        let staged = tokio::task::spawn_blocking(move || {
+            events.phase_started(OperationKind::Backup, "暂存备份文件");
+            let staged = if mode == BackupStagingMode::Snapshot {
+                snapshot_backup_entries(
+                    &source_paths,
+                    &system_paths,
+                    &dir,
+                    &snapshot_config,
+                    &snapshot_name,
+                )?
+            } else {
+                let entries = collect_backup_entries(&source_paths, &system_paths)?;
+                (stage_backup_entries(entries, &dir, mode)?, Vec::new())
+            };
+            events.phase_completed(OperationKind::Backup, "暂存备份文件");
+            Ok::<_, anyhow::Error>(staged)
+        })
+        .await;
+        self.finish_hooks(hooks).await;
+        let staged = staged?;
+
+        let (entries, snapshots) = match staged {
+            Ok(staged) => staged,
+            Err(e) => {
+                error!("暂存备份文件失败: {}", e);
+                remove_staging_dir(&staging_dir);
+                return Err(e);
+            }
         };
 
-        let backup_filename = format!(
-            "backup_{}_v{}_{}.tar.gz",
-            backup_type_str, options.service_version, timestamp
+        info!(
+            "备份文件暂存完成: {} 个条目，耗时 {:.1} 秒",
+            entries.len(),
+            started.elapsed().as_secs_f64()
         );
 
-        let backup_path = self.storage_dir.join(&backup_filename);
+        Ok(StagedBackup {
+            options,
+            created_at,
+            staging_dir,
+            entries,
+            snapshots,
+        })
+    }
+
+    /// 压缩暂存的备份并记录到数据库，完成后删除暂存目录
+    ///
+    /// 备份文件名使用暂存时间，与直接备份的命名一致
+    pub async fn create_backup_from_staging(&self, staged: StagedBackup) -> Result<BackupRecord> {
+        let StagedBackup {
+            options,
+            created_at,
+            staging_dir,
+            entries,
+            snapshots,
+        } = staged;
+        let backup_path = self.new_backup_path(&options, created_at);
+
+        info!("开始压缩暂存的备份: {}", backup_path.display());
+
+        let archive_path = backup_path.clone();
+        let compression_level = options.compression_level;
+        let dedup_store = self.use_dedup(&options).then(|| self.dedup_store());
+        let created_by = options.created_by.clone();
+        let events = options.events.clone();
+        let verify = options.verify;
+        let result = tokio::task::spawn_blocking(move || {
+            // 暂存副本和快照删除之前完成校验
+            let result = write_backup_archive(
+                &entries,
+                &archive_path,
+                compression_level,
+                dedup_store.as_ref(),
+                &created_by,
+                &events,
+            )
+            .map(|()| {
+                verify
+                    .then(|| verify_written_archive(&entries, &archive_path, dedup_store.as_ref()))
+            });
+            for snapshot in snapshots {
+                snapshot.remove();
+            }
+            result
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|result| result);
 
-        info!("开始创建备份: {}", backup_path.display());
+        remove_staging_dir(&staging_dir);
 
-        // 执行备份
-        match self
-            .perform_backup(&need_backup_paths, &backup_path, options.compression_level)
+        self.record_backup_result(options, &backup_path, result)
             .await
-        {
-            Ok(_) => {
+    }
+
+    /// 是否使用去重存储：分块只保存在本地存储目录，配置了存储后端时创建普通备份
+    fn use_dedup(&self, options: &BackupOptions) -> bool {
+        if options.dedup && self.storage.is_some() {
+            warn!("已配置备份存储后端，不使用去重存储");
+            return false;
+        }
+        options.dedup
+    }
+
+    /// 清理上次中断（进程退出）后遗留的暂存目录
+    fn remove_stale_staging_dirs(&self) {
+        let Ok(read_dir) = std::fs::read_dir(&self.storage_dir) else {
+            return;
+        };
+        for entry in read_dir.flatten() {
+            let is_staging = entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with(STAGING_DIR_PREFIX);
+            if is_staging && entry.path().is_dir() {
+                info!("清理遗留的备份暂存目录: {}", entry.path().display());
+                remove_staging_dir(&entry.path());
+            }
+        }
+    }
+
+    /// 生成备份文件路径（人类易读格式）
+    fn new_backup_path(&self, options: &BackupOptions, created_at: DateTime<Utc>) -> PathBuf {
+        let backup_filename = BackupFileName {
+            namespace: options.namespace.clone(),
+            backup_type: options.backup_type,
+            service_version: options.service_version.clone(),
+            created_at,
+        };
+
+        self.storage_dir.join(backup_filename.to_string())
+    }
+
+    /// 将备份结果记录到数据库
+    ///
+    /// `result` 为创建后的校验结果，未校验时为 None；校验未通过的备份保留在本地并记录，但返回错误
+    async fn record_backup_result(
+        &self,
+        options: BackupOptions,
+        backup_path: &Path,
+        result: Result<Option<bool>>,
+    ) -> Result<BackupRecord> {
+        match result {
+            Ok(verified) => {
                 info!("备份创建成功: {}", backup_path.display());
+                if let Ok(metadata) = std::fs::metadata(backup_path) {
+                    tracing::trace!(
+                        target: METRICS_TARGET,
+                        histogram.backup_size_bytes = metadata.len(),
+                    );
+                }
+                if verified != Some(false) {
+                    self.upload_backup_file(backup_path).await;
+                }
 
                 // 记录到数据库
                 let record_id = self
@@ -102,8 +489,21 @@ impl BackupManager {
                         options.service_version,
                         options.backup_type,
                         BackupStatus::Completed,
+                        options.namespace,
                     )
                     .await?;
+                if let Some(verified) = verified {
+                    self.database
+                        .set_backup_verified(record_id, verified)
+                        .await?;
+                }
+                if verified == Some(false) {
+                    return Err(DuckError::Backup(format!(
+                        "备份创建后校验未通过，备份不可用于恢复: {}",
+                        backup_path.display()
+                    ))
+                    .into());
+                }
 
                 // 获取创建的记录
                 self.database
@@ -121,6 +521,7 @@ impl BackupManager {
                         options.service_version,
                         options.backup_type,
                         BackupStatus::Failed,
+                        options.namespace,
                     )
                     .await?;
 
@@ -129,72 +530,115 @@ impl BackupManager {
         }
     }
 
+    /// 把新建的备份保存到存储后端并删除本地副本，上传失败时备份保留在本地
+    async fn upload_backup_file(&self, backup_path: &Path) {
+        let (Some(storage), Some(name)) = (&self.storage, backup_file_name(backup_path)) else {
+            return;
+        };
+        info!("上传备份到 {}: {}", storage.location(), name);
+        match storage.upload(backup_path, &name).await {
+            Ok(()) => {
+                if let Err(e) = tokio::fs::remove_file(backup_path).await {
+                    warn!("删除本地备份副本失败 {}: {}", backup_path.display(), e);
+                }
+            }
+            Err(e) => warn!(
+                "上传备份到 {} 失败，备份保留在本地: {}",
+                storage.location(),
+                e
+            ),
+        }
+    }
+
+    /// 获取本地可读的备份文件，本地不存在时从存储后端取回，用完即删除
+    async fn local_backup_file(&self, backup_record: &BackupRecord) -> Result<LocalBackupFile> {
+        let backup_path = PathBuf::from(&backup_record.file_path);
+        if backup_path.exists() {
+            return Ok(LocalBackupFile {
+                path: backup_path,
+                fetched: false,
+            });
+        }
+        let (Some(storage), Some(name)) = (&self.storage, backup_file_name(&backup_path)) else {
+            return Err(anyhow::anyhow!("备份文件不存在: {}", backup_path.display()));
+        };
+
+        info!("从 {} 取回备份: {}", storage.location(), name);
+        if let Some(parent) = backup_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // 先创建守卫，取回失败时删除不完整的文件
+        let file = LocalBackupFile {
+            path: backup_path,
+            fetched: true,
+        };
+        storage
+            .download(&name, &file.path)
+            .await
+            .map_err(|e| anyhow::anyhow!("从 {} 取回备份失败: {e}", storage.location()))?;
+        Ok(file)
+    }
+
+    /// 返回可用（本地存在或已保存到存储后端）的备份 ID
+    pub async fn available_backup_ids(&self, backups: &[BackupRecord]) -> Result<HashSet<i64>> {
+        let stored: HashSet<String> = match &self.storage {
+            Some(storage) if backups.iter().any(|b| !Path::new(&b.file_path).exists()) => {
+                storage.list().await?.into_iter().collect()
+            }
+            _ => HashSet::new(),
+        };
+        Ok(backups
+            .iter()
+            .filter(|backup| {
+                let path = Path::new(&backup.file_path);
+                path.exists() || backup_file_name(path).is_some_and(|name| stored.contains(&name))
+            })
+            .map(|backup| backup.id)
+            .collect())
+    }
+
     /// 执行实际的备份操作
     ///
     /// 支持备份目录和单个文件：
     /// - 当传入目录路径时，将递归备份该目录下的所有文件
     /// - 当传入文件路径时，将直接备份该文件
+    ///
+    /// 开启 [`BackupOptions::verify`] 时返回创建后的校验结果，否则返回 None
     async fn perform_backup(
         &self,
-        source_paths: &[PathBuf],
+        options: &BackupOptions,
         backup_path: &Path,
-        compression_level: u32,
-    ) -> Result<()> {
-        // 确保备份目录存在
-        if let Some(parent) = backup_path.parent() {
-            tokio::fs::create_dir_all(parent).await?;
-        }
-
+    ) -> Result<Option<bool>> {
         // 在后台线程中执行压缩操作，避免阻塞异步运行时
-        let source_paths = source_paths.to_vec();
+        let source_paths = options.source_paths.clone();
+        let system_paths = options.system_paths.clone();
         let backup_path = backup_path.to_path_buf();
-
-        tokio::task::spawn_blocking(move || {
-            let file = File::create(&backup_path)?;
-            let compression = Compression::new(compression_level);
-            let encoder = GzEncoder::new(file, compression);
-            let mut archive = Builder::new(encoder);
-
-            // 遍历所有源路径并添加到归档中
-            for source_path in &source_paths {
-                if source_path.is_file() {
-                    // 直接处理单个文件
-                    add_file_to_archive(&mut archive, source_path, None)?;
-                } else if source_path.is_dir() {
-                    let dir_name = source_path
-                        .file_name()
-                        .ok_or_else(|| anyhow::anyhow!("无法获取目录名"))?
-                        .to_string_lossy()
-                        .to_string();
-
-                    // 递归处理目录
-                    for entry in WalkDir::new(source_path) {
-                        let entry = entry.map_err(|e| anyhow::anyhow!("遍历目录失败: {e}"))?;
-                        let path = entry.path();
-
-                        if path.is_file() {
-                            add_file_to_archive(
-                                &mut archive,
-                                path,
-                                Some((source_path, &dir_name)),
-                            )?;
-                        }
-                    }
-                } else {
-                    //可能是新增的文件或者目录,这里无法备份,只打印日志
-                    info!("文件或者目录不存在,无需备份: {}", source_path.display());
-                }
-            }
-
-            archive
-                .finish()
-                .map_err(|e| anyhow::anyhow!("完成归档失败: {e}"))?;
-
-            Ok::<(), anyhow::Error>(())
+        let compression_level = options.compression_level;
+        let dedup_store = self.use_dedup(options).then(|| self.dedup_store());
+        let created_by = options.created_by.clone();
+        let events = options.events.clone();
+        let verify = options.verify;
+
+        let verified = tokio::task::spawn_blocking(move || {
+            events.phase_started(OperationKind::Backup, "收集备份文件");
+            let entries = collect_backup_entries(&source_paths, &system_paths)?;
+            events.phase_completed(OperationKind::Backup, "收集备份文件");
+            write_backup_archive(
+                &entries,
+                &backup_path,
+                compression_level,
+                dedup_store.as_ref(),
+                &created_by,
+                &events,
+            )?;
+            Ok::<_, anyhow::Error>(
+                verify
+                    .then(|| verify_written_archive(&entries, &backup_path, dedup_store.as_ref())),
+            )
         })
         .await??;
 
-        Ok(())
+        Ok(verified)
     }
 
     /// 只恢复数据文件，保留配置文件的智能恢复
@@ -212,24 +656,28 @@ impl BackupManager {
             .await?
             .ok_or_else(|| anyhow::anyhow!("备份记录不存在: {backup_id}"))?;
 
-        let backup_path = PathBuf::from(&backup_record.file_path);
-        if !backup_path.exists() {
-            return Err(anyhow::anyhow!("备份文件不存在: {}", backup_path.display()));
-        }
+        let backup_file = self.local_backup_file(&backup_record).await?;
+        let backup_path = backup_file.path();
 
         info!("开始智能数据恢复: {}", backup_path.display());
         info!("目标目录: {}", target_dir.display());
 
+        // 新版本客户端创建的备份在停止服务之前就拒绝
+        self.ensure_restorable(backup_path).await?;
+
         // 停止服务，准备恢复
         info!("正在停止服务...");
         self.docker_manager.stop_services().await?;
 
+        // 去重备份先还原为普通归档，失败时不影响现有数据
+        let archive = self.plain_archive(backup_path).await?;
+
         // 清理现有数据目录，但保留配置文件
         self.clear_data_directories(target_dir, dirs_to_exculde)
             .await?;
 
         // 执行恢复
-        self.perform_restore(&backup_path, target_dir, dirs_to_exculde)
+        self.perform_restore(archive.path(), target_dir, dirs_to_exculde)
             .await?;
 
         // 根据参数决定是否启动服务
@@ -260,23 +708,27 @@ impl BackupManager {
             .await?
             .ok_or_else(|| anyhow::anyhow!("备份记录不存在: {backup_id}"))?;
 
-        let backup_path = PathBuf::from(&backup_record.file_path);
-        if !backup_path.exists() {
-            return Err(anyhow::anyhow!("备份文件不存在: {}", backup_path.display()));
-        }
+        let backup_file = self.local_backup_file(&backup_record).await?;
+        let backup_path = backup_file.path();
 
         info!("开始 data 目录恢复: {}", backup_path.display());
         info!("目标目录: {}", target_dir.display());
 
+        // 新版本客户端创建的备份在停止服务之前就拒绝
+        self.ensure_restorable(backup_path).await?;
+
         // 停止服务，准备恢复
         info!("正在停止服务...");
         self.docker_manager.stop_services().await?;
 
+        // 去重备份先还原为普通归档，失败时不影响现有数据
+        let archive = self.plain_archive(backup_path).await?;
+
         // 只清理 data 目录，保留 app 目录和配置文件
         self.clear_data_directory_only(target_dir).await?;
 
         // 执行选择性恢复：只恢复 data 目录
-        self.perform_selective_restore(&backup_path, target_dir, dirs_to_restore)
+        self.perform_selective_restore(archive.path(), target_dir, dirs_to_restore)
             .await?;
 
         // 根据参数决定是否启动服务
@@ -292,13 +744,64 @@ impl BackupManager {
         Ok(())
     }
 
+    /// 预览从备份恢复到 `target_dir` 的变化，不停止服务、不改动工作目录
+    ///
+    /// `dirs_to_exculde` 与 [`Self::restore_data_from_backup_with_exculde`] 的排除目录一致
+    pub async fn preview_restore(
+        &self,
+        backup_id: i64,
+        target_dir: &Path,
+        dirs_to_exculde: &[&str],
+    ) -> Result<RestorePreview> {
+        let backup_file = self.get_backup_file(backup_id).await?;
+        let backup_path = backup_file.path().to_path_buf();
+        self.ensure_restorable(&backup_path).await?;
+
+        let target_dir = target_dir.to_path_buf();
+        let dirs_to_exclude: Vec<String> = dirs_to_exculde.iter().map(|s| s.to_string()).collect();
+        tokio::task::spawn_blocking(move || {
+            // 去重备份的文件列表和大小记录在清单中，不需要还原归档
+            let files: Vec<(String, u64)> = match read_manifest(&backup_path)? {
+                Some(manifest) => manifest
+                    .files
+                    .into_iter()
+                    .map(|file| (file.path, file.size))
+                    .collect(),
+                None => {
+                    let mut archive = Archive::new(GzDecoder::new(File::open(&backup_path)?));
+                    let mut files = Vec::new();
+                    for entry in archive.entries()? {
+                        let entry = entry?;
+                        if entry.header().entry_type().is_dir() {
+                            continue;
+                        }
+                        files.push((
+                            entry.path()?.to_string_lossy().to_string(),
+                            entry.header().size()?,
+                        ));
+                    }
+                    files
+                }
+            };
+            let files = files
+                .into_iter()
+                .filter(|(path, _)| !is_backup_manifest_path(Path::new(path)));
+            let dirs_to_exclude: Vec<&str> = dirs_to_exclude.iter().map(String::as_str).collect();
+            Ok::<_, anyhow::Error>(RestorePreview::build(files, &target_dir, &dirs_to_exclude))
+        })
+        .await?
+    }
+
     /// 清理数据目录
     async fn clear_data_directories(
         &self,
         docker_dir: &Path,
         dirs_to_exculde: &[&str],
     ) -> Result<()> {
-        let mut data_dirs_to_clear: Vec<String> = vec!["data".to_string(), "app".to_string()];
+        let mut data_dirs_to_clear: Vec<String> = RESTORE_CLEARED_DIR_NAMES
+            .iter()
+            .map(|dir| dir.to_string())
+            .collect();
         // Filter out directories that should be excluded from clearing
         data_dirs_to_clear.retain(|dir| !dirs_to_exculde.contains(&dir.as_str()));
 
@@ -405,17 +908,23 @@ impl BackupManager {
         let backup_path = backup_path.to_path_buf();
         let target_dir = target_dir.to_path_buf();
         let dirs_to_restore: Vec<String> = dirs_to_restore.iter().map(|s| s.to_string()).collect();
+        let ownership = self.restore_ownership.clone();
 
         // 在后台线程中执行解压操作
         tokio::task::spawn_blocking(move || {
             let file = File::open(&backup_path)?;
             let decoder = GzDecoder::new(file);
             let mut archive = Archive::new(decoder);
+            let mut links = SymlinkExtractor::new(&target_dir);
+            let mut restorer = PermissionRestorer::new(&ownership);
 
             // 遍历归档中的所有条目
             for entry in archive.entries()? {
                 let mut entry =
                     entry.map_err(|e| DuckError::Backup(format!("读取归档条目失败: {e}")))?;
+                if restorer.read_manifest(&mut entry)? {
+                    continue;
+                }
 
                 // 获取条目路径
                 let entry_path = entry
@@ -430,22 +939,17 @@ impl BackupManager {
 
                 if should_restore {
                     // 计算解压到的目标路径
-                    let target_path = target_dir.join(&*entry_path);
-
-                    // 确保父目录存在
-                    if let Some(parent) = target_path.parent() {
-                        std::fs::create_dir_all(parent)?;
-                    }
-
-                    // 解压文件
-                    entry.unpack(&target_path).map_err(|e| {
-                        DuckError::Backup(format!("解压文件失败 {}: {e}", target_path.display()))
-                    })?;
-
-                    debug!("恢复文件: {}", target_path.display());
+                    let target_path = resolve_entry_path(&target_dir, &entry_path_str)?;
+                    restore_entry(&mut entry, &target_path, &mut links, restorer.mapping())?;
                 }
             }
 
+            links.finish()?;
+            restorer.finish(&target_dir, |path| {
+                dirs_to_restore
+                    .iter()
+                    .any(|dir| path == dir || path.starts_with(&format!("{dir}/")))
+            });
             Ok::<(), DuckError>(())
         })
         .await??;
@@ -466,66 +970,154 @@ impl BackupManager {
         let backup_path = backup_path.to_path_buf();
         let target_dir = target_dir.to_path_buf();
         let dirs_to_exclude: Vec<String> = dirs_to_exculde.iter().map(|s| s.to_string()).collect();
+        let ownership = self.restore_ownership.clone();
 
         // 在后台线程中执行解压操作
         tokio::task::spawn_blocking(move || {
-            let file = File::open(&backup_path)?;
-            let decoder = GzDecoder::new(file);
-            let mut archive = Archive::new(decoder);
+            extract_backup_archive(&backup_path, &target_dir, &dirs_to_exclude, &ownership)
+        })
+        .await??;
 
-            let mut debug_dirs = std::collections::HashSet::new();
+        Ok(())
+    }
 
-            // 遍历归档中的所有条目
+    /// 检查备份中是否包含 CLI 自身状态（config.toml、数据库）
+    pub async fn backup_contains_system_state(&self, backup_id: i64) -> Result<bool> {
+        let backup_file = self.get_backup_file(backup_id).await?;
+        let backup_path = backup_file.path().to_path_buf();
+
+        tokio::task::spawn_blocking(move || {
+            let system_dir = Path::new(SYSTEM_BACKUP_DIR_NAME);
+            if let Some(manifest) = read_manifest(&backup_path)? {
+                return Ok(manifest
+                    .files
+                    .iter()
+                    .any(|file| Path::new(&file.path).starts_with(system_dir)));
+            }
+            let mut archive = Archive::new(GzDecoder::new(File::open(&backup_path)?));
             for entry in archive.entries()? {
-                let mut entry =
-                    entry.map_err(|e| DuckError::Backup(format!("读取归档条目失败: {e}")))?;
+                let entry = entry?;
+                if entry.path()?.starts_with(system_dir) {
+                    return Ok(true);
+                }
+            }
+            Ok::<bool, anyhow::Error>(false)
+        })
+        .await?
+    }
 
-                // 获取条目路径
-                let entry_path = entry
-                    .path()
-                    .map_err(|e| DuckError::Backup(format!("获取条目路径失败: {e}")))?;
-                let entry_path_str = entry_path.to_string_lossy();
+    /// 检查备份是否记录了属主和目录权限，旧版本创建的备份恢复后需要按固定规则修正权限
+    pub async fn backup_preserves_permissions(&self, backup_id: i64) -> Result<bool> {
+        let backup_file = self.get_backup_file(backup_id).await?;
+        let backup_path = backup_file.path().to_path_buf();
 
-                // Split path into components
-                let path_components: Vec<&str> = entry_path_str.split('/').collect();
+        tokio::task::spawn_blocking(move || {
+            Ok::<bool, anyhow::Error>(
+                read_backup_manifest(&backup_path)?
+                    .is_some_and(|manifest| manifest.preserves_ownership),
+            )
+        })
+        .await?
+    }
 
-                // Check if this is a directory we want to exclude (first level)
-                let should_exclude = if !path_components.is_empty() {
-                    let first_level_dir = path_components[0];
-                    debug_dirs.insert(first_level_dir.to_string());
+    /// 从备份恢复 CLI 自身状态（config.toml、数据库）
+    ///
+    /// 按文件名将 `_system/` 下的条目映射回 `config_path` 与 `database_path`
+    /// （数据库的 `.wal` 等附属文件写到数据库同目录）。每个文件先写入临时文件再原子替换，
+    /// 恢复数据库前会删除现有的 WAL 文件，避免旧日志回放到恢复后的数据库上。
+    /// 返回实际恢复的文件路径列表。
+    pub async fn restore_system_state(
+        &self,
+        backup_id: i64,
+        config_path: &Path,
+        database_path: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        let backup_file = self.get_backup_file(backup_id).await?;
+        let backup_path = backup_file.path().to_path_buf();
+        let config_path = config_path.to_path_buf();
+        let database_path = database_path.to_path_buf();
+
+        info!("开始恢复 CLI 系统状态: {}", backup_path.display());
+        self.ensure_restorable(&backup_path).await?;
+        let archive = self.plain_archive(&backup_path).await?;
+        let archive_path = archive.path().to_path_buf();
+
+        let restored = tokio::task::spawn_blocking(move || {
+            let config_name = config_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let database_name = database_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let mut archive = Archive::new(GzDecoder::new(File::open(&archive_path)?));
+            let mut restored = Vec::new();
 
-                    dirs_to_exclude
-                        .iter()
-                        .any(|dir| dir.as_str() == first_level_dir)
+            for entry in archive.entries()? {
+                let mut entry =
+                    entry.map_err(|e| DuckError::Backup(format!("读取归档条目失败: {e}")))?;
+                let entry_path = entry.path()?.to_path_buf();
+                let Ok(relative) = entry_path.strip_prefix(SYSTEM_BACKUP_DIR_NAME) else {
+                    continue;
+                };
+                let file_name = relative.to_string_lossy().to_string();
+
+                let target_path = if file_name == config_name {
+                    config_path.clone()
+                } else if let Some(suffix) = file_name
+                    .strip_prefix(database_name.as_str())
+                    .or_else(|| file_name.strip_prefix(legacy::DATABASE_FILE_NAME))
+                {
+                    // 旧版 duck-cli 备份中的数据库按新的文件名恢复
+                    database_path.with_file_name(format!("{database_name}{suffix}"))
                 } else {
-                    false // Not enough path components, don't exclude
+                    warn!("未知的系统状态文件，跳过: {}", file_name);
+                    continue;
                 };
 
-                if !should_exclude {
-                    // 计算解压到的目标路径
-                    let target_path = target_dir.join(&*entry_path);
-
-                    // 确保父目录存在
-                    if let Some(parent) = target_path.parent() {
-                        std::fs::create_dir_all(parent)?;
+                if target_path == database_path {
+                    // 旧的 WAL 与恢复后的数据库不匹配，必须清理
+                    let wal_path = database_path.with_file_name(format!("{database_name}.wal"));
+                    if wal_path.exists() {
+                        std::fs::remove_file(&wal_path)?;
                     }
+                }
 
-                    // 解压文件
-                    entry.unpack(&target_path).map_err(|e| {
-                        DuckError::Backup(format!("解压文件失败 {}: {e}", target_path.display()))
-                    })?;
-
-                    debug!("恢复文件: {}", target_path.display());
+                if let Some(parent) = target_path.parent() {
+                    std::fs::create_dir_all(parent)?;
                 }
+                let temp_path = target_path.with_file_name(format!("{file_name}.restoring"));
+                entry.unpack(&temp_path).map_err(|e| {
+                    DuckError::Backup(format!("解压文件失败 {}: {e}", temp_path.display()))
+                })?;
+                std::fs::rename(&temp_path, &target_path)?;
+
+                info!("恢复系统状态文件: {}", target_path.display());
+                restored.push(target_path);
             }
 
-            debug!("测试日志,恢复目录: {:?}", debug_dirs);
-
-            Ok::<(), DuckError>(())
+            Ok::<Vec<PathBuf>, anyhow::Error>(restored)
         })
         .await??;
 
-        Ok(())
+        if restored.is_empty() {
+            warn!("备份 {} 中不包含 CLI 系统状态", backup_id);
+        }
+
+        Ok(restored)
+    }
+
+    /// 获取本地可读的备份文件，本地不存在时从存储后端取回
+    async fn get_backup_file(&self, backup_id: i64) -> Result<LocalBackupFile> {
+        let backup_record = self
+            .database
+            .get_backup_by_id(backup_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("备份记录不存在: {backup_id}"))?;
+
+        self.local_backup_file(&backup_record).await
     }
 
     /// 获取所有备份记录
@@ -533,8 +1125,39 @@ impl BackupManager {
         self.database.get_all_backups().await
     }
 
-    /// 删除备份
+    /// 按条件查询备份记录，`only_valid` 时只保留可用（本地存在或已保存到存储后端）的备份
+    ///
+    /// 备份是否可用无法在数据库中判断，此时先查出所有满足条件的记录，筛选后再分页
+    pub async fn query_backups(&self, query: &BackupQuery, only_valid: bool) -> Result<BackupPage> {
+        if !only_valid {
+            return self.database.query_backups(query).await;
+        }
+
+        let unpaged = BackupQuery {
+            limit: None,
+            offset: 0,
+            ..query.clone()
+        };
+        let mut backups = self.database.query_backups(&unpaged).await?.backups;
+        let available = self.available_backup_ids(&backups).await?;
+        backups.retain(|backup| available.contains(&backup.id));
+        let total = backups.len() as u64;
+        let backups = backups
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.limit.map_or(usize::MAX, |limit| limit as usize))
+            .collect();
+        Ok(BackupPage { backups, total })
+    }
+
+    /// 删除备份，之后清理不再被引用的去重分块
     pub async fn delete_backup(&self, backup_id: i64) -> Result<()> {
+        self.delete_backup_file_and_record(backup_id).await?;
+        self.gc_dedup_store().await;
+        Ok(())
+    }
+
+    async fn delete_backup_file_and_record(&self, backup_id: i64) -> Result<()> {
         // 获取备份记录
         let backup_record = self
             .database
@@ -549,6 +1172,10 @@ impl BackupManager {
             tokio::fs::remove_file(&backup_path).await?;
             info!("删除备份文件: {}", backup_path.display());
         }
+        if let (Some(storage), Some(name)) = (&self.storage, backup_file_name(&backup_path)) {
+            storage.delete(&name).await?;
+            info!("删除 {} 中的备份文件: {}", storage.location(), name);
+        }
 
         // 从数据库中删除记录
         self.database.delete_backup_record(backup_id).await?;
@@ -556,26 +1183,285 @@ impl BackupManager {
         Ok(())
     }
 
-    /// 检查并迁移备份存储目录
-    pub async fn migrate_storage_directory(&self, new_storage_dir: &Path) -> Result<()> {
-        if new_storage_dir == self.storage_dir {
-            return Ok(()); // 没有变化
+    /// 按保留策略清理各类型的旧备份，返回被删除的备份记录
+    ///
+    /// 只清理属于 `namespace` 的备份，共享备份目录中登记的其他项目备份不受影响
+    pub async fn prune_backups(
+        &self,
+        retention: &BackupRetentionConfig,
+        namespace: &str,
+    ) -> Result<Vec<BackupRecord>> {
+        let mut backups = self.list_backups().await?;
+        backups.retain(|backup| backup.belongs_to_namespace(namespace));
+        let mut pruned = Vec::new();
+
+        for backup in select_backups_to_prune(&backups, retention) {
+            match self.delete_backup_file_and_record(backup.id).await {
+                Ok(()) => {
+                    info!(
+                        "🧹 按保留策略清理{}备份: ID {} ({})",
+                        backup.backup_type.display_name(),
+                        backup.id,
+                        backup.file_path
+                    );
+                    pruned.push(backup.clone());
+                }
+                Err(e) => warn!("清理备份 {} 失败: {}", backup.id, e),
+            }
         }
 
-        info!(
-            "开始迁移备份存储目录: {} -> {}",
-            self.storage_dir.display(),
-            new_storage_dir.display()
-        );
+        if !pruned.is_empty() {
+            self.gc_dedup_store().await;
+        }
+        Ok(pruned)
+    }
 
-        // 创建新目录
-        tokio::fs::create_dir_all(new_storage_dir).await?;
+    /// 导出备份目录，包含每个备份文件的大小和 SHA-256
+    pub async fn export_catalog(&self) -> Result<BackupCatalog> {
+        let mut entries = Vec::new();
+        for backup in self.list_backups().await? {
+            let mut entry = BackupCatalogEntry::from_record(&backup);
+            let backup_path = Path::new(&backup.file_path);
+            if let Ok(metadata) = tokio::fs::metadata(backup_path).await {
+                entry.file_exists = true;
+                entry.size_bytes = Some(metadata.len());
+                match sha256_file_cached(backup_path, Some(self.database.as_ref())).await {
+                    Ok(hash) => entry.sha256 = Some(hash),
+                    Err(e) => warn!("计算备份文件哈希失败 {}: {}", backup.file_path, e),
+                }
+            }
+            entries.push(entry);
+        }
+        let mut catalog = BackupCatalog::new(entries);
+        catalog.images = self.image_archive_entries().await?;
+        Ok(catalog)
+    }
 
-        // 获取所有备份记录
-        let backups = self.list_backups().await?;
+    /// 备份目录中的镜像归档，无法读取清单的文件会被跳过
+    async fn image_archive_entries(&self) -> Result<Vec<ImageArchiveCatalogEntry>> {
+        let mut entries = Vec::new();
+        let Ok(mut dir) = tokio::fs::read_dir(&self.storage_dir).await else {
+            return Ok(entries);
+        };
+        while let Some(item) = dir.next_entry().await? {
+            let path = item.path();
+            let is_archive = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(is_image_archive_file_name);
+            if !is_archive || !path.is_file() {
+                continue;
+            }
+            let manifest_path = path.clone();
+            let manifest = match tokio::task::spawn_blocking(move || {
+                read_image_archive_manifest(&manifest_path)
+            })
+            .await?
+            {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    warn!("跳过无法读取的镜像归档 {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let sha256 = match sha256_file_cached(&path, Some(self.database.as_ref())).await {
+                Ok(hash) => Some(hash),
+                Err(e) => {
+                    warn!("计算镜像归档哈希失败 {}: {}", path.display(), e);
+                    None
+                }
+            };
+            entries.push(ImageArchiveCatalogEntry {
+                file_path: path.to_string_lossy().to_string(),
+                service_version: manifest.service_version,
+                created_at: manifest.created_at,
+                size_bytes: item.metadata().await.ok().map(|metadata| metadata.len()),
+                sha256,
+                images: manifest
+                    .images
+                    .into_iter()
+                    .map(|image| image.reference)
+                    .collect(),
+            });
+        }
+        entries.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+        Ok(entries)
+    }
 
-        for backup in backups {
-            let old_path = PathBuf::from(&backup.file_path);
+    /// 按备份目录重新登记备份记录
+    ///
+    /// 已登记、文件缺失或哈希不一致的条目会被跳过，不会修改备份文件
+    pub async fn import_catalog(&self, catalog: &BackupCatalog) -> Result<CatalogImportReport> {
+        let mut known = self.known_backup_paths().await?;
+        let mut report = CatalogImportReport::default();
+
+        for entry in &catalog.backups {
+            let backup_path = Path::new(&entry.file_path);
+            let backup_type = match entry.parsed_backup_type() {
+                Ok(backup_type) => backup_type,
+                Err(e) => {
+                    report
+                        .skipped
+                        .push((entry.file_path.clone(), e.to_string()));
+                    continue;
+                }
+            };
+            if !backup_path.is_file() {
+                report
+                    .skipped
+                    .push((entry.file_path.clone(), "备份文件不存在".to_string()));
+                continue;
+            }
+            let normalized = normalize_backup_path(backup_path);
+            if known.contains(&normalized) {
+                report
+                    .skipped
+                    .push((entry.file_path.clone(), "已在备份列表中".to_string()));
+                continue;
+            }
+
+            let hash = sha256_file_cached(backup_path, Some(self.database.as_ref())).await?;
+            if let Some(expected) = &entry.sha256 {
+                if !expected.eq_ignore_ascii_case(&hash) {
+                    report.skipped.push((
+                        entry.file_path.clone(),
+                        format!("SHA-256 不一致（期望 {expected}，实际 {hash}）"),
+                    ));
+                    continue;
+                }
+            }
+
+            let record = self
+                .register_backup_file(
+                    backup_path,
+                    entry.service_version.clone(),
+                    backup_type,
+                    entry.created_at,
+                    hash,
+                    entry.namespace.clone(),
+                )
+                .await?;
+            known.insert(normalized);
+            report.imported.push(record);
+        }
+
+        Ok(report)
+    }
+
+    /// 扫描目录中符合命名规则的备份归档，登记尚未在备份列表中的文件
+    pub async fn adopt_directory(&self, dir: &Path) -> Result<CatalogImportReport> {
+        if !dir.is_dir() {
+            return Err(DuckError::Backup(format!("目录不存在: {}", dir.display())).into());
+        }
+
+        let mut known = self.known_backup_paths().await?;
+        let mut report = CatalogImportReport::default();
+
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        files.sort();
+
+        for path in files {
+            let display = path.display().to_string();
+            let file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let Some(parsed) = parse_backup_file_name(&file_name) else {
+                report
+                    .skipped
+                    .push((display, "文件名不符合备份命名规则".to_string()));
+                continue;
+            };
+            if known.contains(&normalize_backup_path(&path)) {
+                report.skipped.push((display, "已在备份列表中".to_string()));
+                continue;
+            }
+            if let Err(e) = verify_backup_archive(&path) {
+                report
+                    .skipped
+                    .push((display, format!("不是有效的备份归档: {e}")));
+                continue;
+            }
+
+            let path = std::fs::canonicalize(&path).unwrap_or(path);
+            let hash = sha256_file_cached(&path, Some(self.database.as_ref())).await?;
+            let record = self
+                .register_backup_file(
+                    &path,
+                    parsed.service_version,
+                    parsed.backup_type,
+                    parsed.created_at,
+                    hash,
+                    parsed.namespace,
+                )
+                .await?;
+            known.insert(normalize_backup_path(&path));
+            report.imported.push(record);
+        }
+
+        Ok(report)
+    }
+
+    /// 已登记备份文件的规范化路径
+    async fn known_backup_paths(&self) -> Result<HashSet<PathBuf>> {
+        Ok(self
+            .list_backups()
+            .await?
+            .iter()
+            .map(|backup| normalize_backup_path(Path::new(&backup.file_path)))
+            .collect())
+    }
+
+    async fn register_backup_file(
+        &self,
+        backup_path: &Path,
+        service_version: String,
+        backup_type: BackupType,
+        created_at: DateTime<Utc>,
+        hash: String,
+        namespace: Option<String>,
+    ) -> Result<BackupRecord> {
+        let record_id = self
+            .database
+            .import_backup_record(
+                backup_path.to_string_lossy().to_string(),
+                service_version,
+                backup_type,
+                created_at,
+                Some(hash),
+                namespace,
+            )
+            .await?;
+
+        self.database
+            .get_backup_by_id(record_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("无法获取刚登记的备份记录"))
+    }
+
+    /// 检查并迁移备份存储目录
+    pub async fn migrate_storage_directory(&self, new_storage_dir: &Path) -> Result<()> {
+        if new_storage_dir == self.storage_dir {
+            return Ok(()); // 没有变化
+        }
+
+        info!(
+            "开始迁移备份存储目录: {} -> {}",
+            self.storage_dir.display(),
+            new_storage_dir.display()
+        );
+
+        // 创建新目录
+        tokio::fs::create_dir_all(new_storage_dir).await?;
+
+        // 获取所有备份记录
+        let backups = self.list_backups().await?;
+
+        for backup in backups {
+            let old_path = PathBuf::from(&backup.file_path);
             if old_path.exists() {
                 let filename = old_path
                     .file_name()
@@ -583,7 +1469,7 @@ impl BackupManager {
                 let new_path = new_storage_dir.join(filename);
 
                 // 移动文件
-                tokio::fs::rename(&old_path, &new_path).await?;
+                move_path(&old_path, &new_path).await?;
                 info!(
                     "迁移备份文件: {} -> {}",
                     old_path.display(),
@@ -597,10 +1483,201 @@ impl BackupManager {
             }
         }
 
+        let dedup_root = self.storage_dir.join(DEDUP_STORE_DIR_NAME);
+        if dedup_root.exists() {
+            let new_dedup_root = new_storage_dir.join(DEDUP_STORE_DIR_NAME);
+            move_path(&dedup_root, &new_dedup_root).await?;
+            info!(
+                "迁移去重存储: {} -> {}",
+                dedup_root.display(),
+                new_dedup_root.display()
+            );
+        }
+
         info!("备份存储目录迁移完成");
         Ok(())
     }
 
+    /// 分块去重存储
+    pub fn dedup_store(&self) -> DedupStore {
+        DedupStore::new(self.storage_dir.join(DEDUP_STORE_DIR_NAME))
+    }
+
+    /// 校验备份格式清单，当前客户端无法恢复该备份时返回错误
+    async fn ensure_restorable(&self, backup_path: &Path) -> Result<()> {
+        let backup_path = backup_path.to_path_buf();
+        let manifest =
+            tokio::task::spawn_blocking(move || validate_backup_archive(&backup_path)).await??;
+        if let Some(manifest) = manifest {
+            debug!(
+                "备份格式版本 {}，由 {} 创建",
+                manifest.format_version, manifest.created_by
+            );
+        }
+        Ok(())
+    }
+
+    /// 获取可直接解压的归档：去重备份还原为暂存目录下的临时普通归档，普通备份直接使用原文件
+    async fn plain_archive(&self, backup_path: &Path) -> Result<PlainArchive> {
+        let backup_path = backup_path.to_path_buf();
+        let store = self.dedup_store();
+        let temp_dir = self.storage_dir.join(format!(
+            "{STAGING_DIR_PREFIX}restore_{}",
+            Utc::now().format("%Y-%m-%d_%H-%M-%S%.3f")
+        ));
+
+        tokio::task::spawn_blocking(move || {
+            if read_manifest(&backup_path)?.is_none() {
+                return Ok(PlainArchive {
+                    path: backup_path,
+                    temp_dir: None,
+                });
+            }
+
+            info!("还原去重备份为普通归档: {}", backup_path.display());
+            std::fs::create_dir_all(&temp_dir)?;
+            let archive = PlainArchive {
+                path: temp_dir.join("backup.tar.gz"),
+                temp_dir: Some(temp_dir),
+            };
+            export_plain_archive(&backup_path, &store, &archive.path, Compression::none())?;
+            Ok::<PlainArchive, anyhow::Error>(archive)
+        })
+        .await?
+    }
+
+    /// 清理不再被任何备份引用的去重分块，失败只记录警告
+    async fn gc_dedup_store(&self) {
+        let store = self.dedup_store();
+        if !store.root().exists() {
+            return;
+        }
+        let backups = match self.list_backups().await {
+            Ok(backups) => backups,
+            Err(e) => {
+                warn!("清理去重分块失败，无法获取备份列表: {}", e);
+                return;
+            }
+        };
+
+        let result = tokio::task::spawn_blocking(move || {
+            let mut referenced = HashSet::new();
+            for backup in &backups {
+                let path = Path::new(&backup.file_path);
+                if backup.status != BackupStatus::Completed || !path.exists() {
+                    continue;
+                }
+                // 无法读取清单时放弃清理，避免误删仍被引用的分块
+                if let Some(manifest) = read_manifest(path)
+                    .map_err(|e| anyhow::anyhow!("读取备份清单失败 {}: {e}", path.display()))?
+                {
+                    referenced.extend(manifest.chunk_hashes().into_iter().map(str::to_string));
+                }
+            }
+            store.gc(&referenced, GC_GRACE_PERIOD)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(stats)) if stats.removed_chunks > 0 => info!(
+                "清理未引用的去重分块 {} 个，释放 {:.1} MB",
+                stats.removed_chunks,
+                stats.freed_bytes as f64 / 1024.0 / 1024.0
+            ),
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!("清理去重分块失败: {}", e),
+            Err(e) => warn!("清理去重分块失败: {}", e),
+        }
+    }
+
+    /// 校验备份完整性：普通备份完整读取归档，去重备份还会检查引用的每个分块
+    pub async fn verify_backup(&self, backup_id: i64) -> Result<BackupVerification> {
+        let backup_file = self.get_backup_file(backup_id).await?;
+        let backup_path = backup_file.path().to_path_buf();
+        let store = self.dedup_store();
+
+        let verification = tokio::task::spawn_blocking(move || {
+            let mut verification = BackupVerification {
+                backup_id,
+                deduplicated: false,
+                dedup: None,
+                error: None,
+            };
+            if let Err(e) = validate_backup_archive(&backup_path) {
+                verification.error = Some(e.to_string());
+                return verification;
+            }
+            match read_manifest(&backup_path) {
+                Ok(Some(manifest)) => {
+                    verification.deduplicated = true;
+                    verification.dedup = Some(store.verify(&manifest));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    verification.error = Some(e.to_string());
+                    return verification;
+                }
+            }
+            if let Err(e) = read_full_archive(&backup_path) {
+                verification.error = Some(e.to_string());
+            }
+            verification
+        })
+        .await?;
+
+        Ok(verification)
+    }
+
+    /// 把去重备份还原为普通归档
+    ///
+    /// 指定 `output` 时导出副本，原备份不变；否则原地替换备份文件并清理不再引用的分块。
+    /// 返回普通归档的路径
+    pub async fn repack_backup(&self, backup_id: i64, output: Option<&Path>) -> Result<PathBuf> {
+        let backup_file = self.get_backup_file(backup_id).await?;
+        let backup_path = backup_file.path().to_path_buf();
+        let store = self.dedup_store();
+        let output = output.map(Path::to_path_buf);
+        let in_place = output.is_none();
+
+        let plain_path = tokio::task::spawn_blocking(move || {
+            if read_manifest(&backup_path)?.is_none() {
+                return Err(DuckError::Backup(format!(
+                    "备份 {backup_id} 不是去重备份，可直接复制备份文件"
+                ))
+                .into());
+            }
+
+            let export_path = output.clone().unwrap_or_else(|| {
+                let mut name = backup_path.as_os_str().to_os_string();
+                name.push(".repacking");
+                PathBuf::from(name)
+            });
+            info!(
+                "还原去重备份为普通归档: {} -> {}",
+                backup_path.display(),
+                export_path.display()
+            );
+            if let Err(e) =
+                export_plain_archive(&backup_path, &store, &export_path, Compression::new(6))
+            {
+                let _ = std::fs::remove_file(&export_path);
+                return Err(e);
+            }
+
+            if output.is_some() {
+                return Ok(export_path);
+            }
+            std::fs::rename(&export_path, &backup_path)?;
+            Ok::<PathBuf, anyhow::Error>(backup_path)
+        })
+        .await??;
+
+        if in_place {
+            self.gc_dedup_store().await;
+        }
+        Ok(plain_path)
+    }
+
     /// 获取存储目录
     pub fn get_storage_dir(&self) -> &Path {
         &self.storage_dir
@@ -630,28 +1707,779 @@ impl BackupManager {
     }
 }
 
-// 用于将文件添加到归档中
-fn add_file_to_archive(
-    archive: &mut Builder<GzEncoder<File>>,
-    file_path: &Path,
-    base_info: Option<(&Path, &str)>,
+/// 备份清单中的条目
+#[derive(Debug, Clone)]
+enum BackupEntry {
+    /// 普通文件，`source` 为实际读取的路径（暂存后指向暂存副本）
+    File {
+        source: PathBuf,
+        archive_path: String,
+    },
+    /// 符号链接，`target` 已换算为工作目录内的相对路径
+    Symlink {
+        archive_path: String,
+        target: PathBuf,
+        mtime: Option<u64>,
+    },
+    /// 目录，归档中不包含目录条目，属主和权限记录在格式清单中
+    Directory(PathPermissions),
+}
+
+/// 已暂存、等待压缩的备份
+#[derive(Debug)]
+pub struct StagedBackup {
+    options: BackupOptions,
+    created_at: DateTime<Utc>,
+    staging_dir: PathBuf,
+    entries: Vec<BackupEntry>,
+    /// 清单中的文件所在的文件系统快照，压缩完成后删除
+    snapshots: Vec<FilesystemSnapshot>,
+}
+
+impl StagedBackup {
+    /// 清单中的条目数量
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 暂存目录
+    pub fn staging_dir(&self) -> &Path {
+        &self.staging_dir
+    }
+}
+
+/// 恢复使用的普通归档，临时还原的归档在释放时删除
+struct PlainArchive {
+    path: PathBuf,
+    temp_dir: Option<PathBuf>,
+}
+
+impl PlainArchive {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for PlainArchive {
+    fn drop(&mut self) {
+        if let Some(temp_dir) = &self.temp_dir {
+            remove_staging_dir(temp_dir);
+        }
+    }
+}
+
+/// 本地可读的备份文件，从存储后端取回的副本在释放时删除
+struct LocalBackupFile {
+    path: PathBuf,
+    fetched: bool,
+}
+
+impl LocalBackupFile {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for LocalBackupFile {
+    fn drop(&mut self) {
+        if self.fetched {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// 备份文件名，存储后端按文件名保存备份
+fn backup_file_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+/// 备份完整性校验结果
+#[derive(Debug)]
+pub struct BackupVerification {
+    pub backup_id: i64,
+    /// 是否为去重备份
+    pub deduplicated: bool,
+    /// 去重备份的分块校验结果
+    pub dedup: Option<DedupVerifyReport>,
+    /// 读取归档时的错误
+    pub error: Option<String>,
+}
+
+impl BackupVerification {
+    /// 是否通过校验
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none() && self.dedup.as_ref().is_none_or(DedupVerifyReport::is_ok)
+    }
+}
+
+/// 源路径中文件的总大小（不跟随符号链接），用于预估备份大小
+fn source_paths_size(paths: &[PathBuf]) -> u64 {
+    paths
+        .iter()
+        .flat_map(|path| WalkDir::new(path).into_iter().flatten())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// 遍历源路径生成备份清单
+///
+/// 目录递归收集其中的文件和符号链接，单个文件直接加入；CLI 自身状态文件统一放到 `_system/` 目录下
+pub(crate) fn collect_backup_entries(
+    source_paths: &[PathBuf],
+    system_paths: &[PathBuf],
+) -> Result<Vec<BackupEntry>> {
+    let mut entries = Vec::new();
+
+    for source_path in source_paths {
+        if source_path.is_file() {
+            // 直接处理单个文件
+            entries.push(file_entry(source_path, None)?);
+        } else if source_path.is_dir() {
+            // 深层目录中的文件路径可能超过 Windows 的 MAX_PATH
+            let source_path = &long_path(source_path);
+            let dir_name = directory_name(source_path)?;
+
+            // 链接目标必须位于工作目录（源目录的上级）内
+            let link_root = source_path.parent().unwrap_or(source_path);
+            collect_directory_entries(source_path, &dir_name, link_root, &mut entries)?;
+        } else {
+            //可能是新增的文件或者目录,这里无法备份,只打印日志
+            info!("文件或者目录不存在,无需备份: {}", source_path.display());
+        }
+    }
+
+    for system_path in system_paths {
+        if !system_path.is_file() {
+            info!("系统状态文件不存在,跳过: {}", system_path.display());
+            continue;
+        }
+        let file_name = system_path
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("无法获取文件名"))?
+            .to_string_lossy()
+            .to_string();
+        entries.push(BackupEntry::File {
+            source: system_path.clone(),
+            archive_path: format!("{SYSTEM_BACKUP_DIR_NAME}/{file_name}"),
+        });
+    }
+
+    Ok(entries)
+}
+
+fn directory_name(dir: &Path) -> Result<String> {
+    Ok(dir
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("无法获取目录名"))?
+        .to_string_lossy()
+        .to_string())
+}
+
+// 递归收集目录中的文件、符号链接和目录权限，归档路径以 dir_name 开头
+fn collect_directory_entries(
+    dir: &Path,
+    dir_name: &str,
+    link_root: &Path,
+    entries: &mut Vec<BackupEntry>,
+) -> Result<()> {
+    for entry in WalkDir::new(dir) {
+        let entry = entry.map_err(|e| anyhow::anyhow!("遍历目录失败: {e}"))?;
+        let path = entry.path();
+        let base_info = (dir, dir_name);
+
+        if entry.file_type().is_symlink() {
+            entries.extend(symlink_entry(path, link_root, base_info)?);
+        } else if entry.file_type().is_file() {
+            entries.push(file_entry(path, Some(base_info))?);
+        } else if entry.file_type().is_dir() {
+            let archive_path = archive_entry_name(path, Some(base_info))?;
+            entries.extend(
+                PathPermissions::read(path, archive_path.trim_end_matches('/'))
+                    .map(BackupEntry::Directory),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// 为源目录创建文件系统快照并从快照生成清单，不支持快照的路径复制到暂存目录
+///
+/// 快照中的符号链接只能指向快照（即源目录）内部；生成清单失败时删除已创建的快照
+fn snapshot_backup_entries(
+    source_paths: &[PathBuf],
+    system_paths: &[PathBuf],
+    staging_dir: &Path,
+    config: &BackupSnapshotConfig,
+    name: &str,
+) -> Result<(Vec<BackupEntry>, Vec<FilesystemSnapshot>)> {
+    let mut snapshots = Vec::new();
+    let mut dir_names = Vec::new();
+    let mut remaining = Vec::new();
+    for (index, source_path) in source_paths.iter().enumerate() {
+        if !source_path.is_dir() {
+            remaining.push(source_path.clone());
+            continue;
+        }
+        match create_snapshot(source_path, &format!("{name}-{index}"), config) {
+            Some(snapshot) => {
+                dir_names.push(directory_name(source_path)?);
+                snapshots.push(snapshot);
+            }
+            None => {
+                warn!(
+                    "⚠️ {} 所在的文件系统不支持快照（{}），改为复制",
+                    source_path.display(),
+                    config.provider.as_str()
+                );
+                remaining.push(source_path.clone());
+            }
+        }
+    }
+
+    let collect = || -> Result<Vec<BackupEntry>> {
+        let mut entries = Vec::new();
+        for (snapshot, dir_name) in snapshots.iter().zip(&dir_names) {
+            collect_directory_entries(snapshot.path(), dir_name, snapshot.path(), &mut entries)?;
+        }
+        let copied = collect_backup_entries(&remaining, system_paths)?;
+        entries.extend(stage_backup_entries(
+            copied,
+            staging_dir,
+            BackupStagingMode::Copy,
+        )?);
+        Ok(entries)
+    };
+    match collect() {
+        Ok(entries) => Ok((entries, snapshots)),
+        Err(e) => {
+            for snapshot in snapshots {
+                snapshot.remove();
+            }
+            Err(e)
+        }
+    }
+}
+
+// 生成普通文件的清单条目
+fn file_entry(file_path: &Path, base_info: Option<(&Path, &str)>) -> Result<BackupEntry> {
+    Ok(BackupEntry::File {
+        source: file_path.to_path_buf(),
+        archive_path: archive_entry_name(file_path, base_info)?,
+    })
+}
+
+// 生成符号链接的清单条目，指向 link_root 之外的链接会被跳过
+fn symlink_entry(
+    link_path: &Path,
+    link_root: &Path,
+    base_info: (&Path, &str),
+) -> Result<Option<BackupEntry>> {
+    let Some(target) = relative_link_target(link_root, link_path) else {
+        warn!(
+            "⚠️ 符号链接指向工作目录之外，跳过备份: {}",
+            link_path.display()
+        );
+        return Ok(None);
+    };
+    let mtime = std::fs::symlink_metadata(link_path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs());
+
+    Ok(Some(BackupEntry::Symlink {
+        archive_path: archive_entry_name(link_path, Some(base_info))?,
+        target,
+        mtime,
+    }))
+}
+
+/// 按清单写入 tar.gz 归档
+///
+/// 指定去重存储时，文件内容先分块写入存储，归档中只保存分块清单和符号链接
+pub(crate) fn write_backup_archive(
+    entries: &[BackupEntry],
+    backup_path: &Path,
+    compression_level: u32,
+    dedup_store: Option<&DedupStore>,
+    created_by: &str,
+    events: &EventSender,
 ) -> Result<()> {
+    events.phase_started(OperationKind::Backup, "压缩归档");
+    let mut reporter = events.progress_reporter(OperationKind::Backup, entries.len() as u64);
+
+    // 确保备份目录存在
+    if let Some(parent) = backup_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let manifest = dedup_store
+        .map(|store| store_backup_files(entries, store, &mut reporter))
+        .transpose()?;
+
+    let file = File::create(backup_path)?;
+    let compression = Compression::new(compression_level);
+    let encoder = GzEncoder::new(file, compression);
+    let mut archive = Builder::new(encoder);
+    // 符号链接按链接本身归档，不读取链接指向的内容
+    archive.follow_symlinks(false);
+    // 去重清单必须是第一个条目，格式清单紧随其后
+    if let Some(manifest) = &manifest {
+        append_manifest(&mut archive, manifest)?;
+    }
+    append_backup_manifest(
+        &mut archive,
+        &BackupManifest::new(created_by, included_paths(entries), manifest.is_some())
+            .with_permissions(entry_permissions(entries)),
+    )?;
+
+    for (index, entry) in entries.iter().enumerate() {
+        match entry {
+            // 去重备份的文件内容已在清单中
+            BackupEntry::File { .. } if manifest.is_some() => {}
+            BackupEntry::File {
+                source,
+                archive_path,
+            } => {
+                debug!("添加文件到归档: {} -> {}", source.display(), archive_path);
+                archive
+                    .append_path_with_name(source, archive_path)
+                    .map_err(|e| DuckError::Backup(format!("添加文件到归档失败: {e}")))?;
+            }
+            BackupEntry::Symlink {
+                archive_path,
+                target,
+                mtime,
+            } => {
+                debug!(
+                    "添加符号链接到归档: {} -> {}",
+                    archive_path,
+                    target.display()
+                );
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_size(0);
+                header.set_mode(0o777);
+                if let Some(mtime) = mtime {
+                    header.set_mtime(*mtime);
+                }
+                archive
+                    .append_link(&mut header, archive_path, target)
+                    .map_err(|e| DuckError::Backup(format!("添加符号链接到归档失败: {e}")))?;
+            }
+            // 目录的属主和权限已记录在格式清单中
+            BackupEntry::Directory(_) => {}
+        }
+        if manifest.is_none() {
+            reporter.update(index as u64 + 1);
+        }
+    }
+
+    archive
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(|e| anyhow::anyhow!("完成归档失败: {e}"))?;
+
+    events.phase_completed(OperationKind::Backup, "压缩归档");
+    Ok(())
+}
+
+/// 条目在归档中的顶层路径，去重后按字母排序
+fn included_paths(entries: &[BackupEntry]) -> Vec<String> {
+    let mut paths: Vec<String> = entries
+        .iter()
+        .filter_map(|entry| {
+            let (BackupEntry::File { archive_path, .. }
+            | BackupEntry::Symlink { archive_path, .. }
+            | BackupEntry::Directory(PathPermissions {
+                path: archive_path, ..
+            })) = entry;
+            archive_path.split('/').find(|part| !part.is_empty())
+        })
+        .map(str::to_string)
+        .collect();
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// 需要记录到格式清单中的权限：目录，以及带扩展属性的文件
+///
+/// 暂存副本已保留源文件的扩展属性，从暂存副本读取即可
+fn entry_permissions(entries: &[BackupEntry]) -> Vec<PathPermissions> {
+    entries
+        .iter()
+        .filter_map(|entry| match entry {
+            BackupEntry::Directory(permissions) => Some(permissions.clone()),
+            BackupEntry::File {
+                source,
+                archive_path,
+            } => PathPermissions::read(source, archive_path)
+                .filter(|permissions| !permissions.xattrs.is_empty()),
+            BackupEntry::Symlink { .. } => None,
+        })
+        .collect()
+}
+
+/// 把清单中的文件分块写入去重存储，返回归档使用的分块清单
+fn store_backup_files(
+    entries: &[BackupEntry],
+    store: &DedupStore,
+    reporter: &mut ProgressReporter,
+) -> Result<DedupManifest> {
+    let mut manifest = DedupManifest::default();
+    let mut stats = DedupStats::default();
+
+    for (index, entry) in entries.iter().enumerate() {
+        if let BackupEntry::File {
+            source,
+            archive_path,
+        } = entry
+        {
+            let metadata = std::fs::metadata(source).map_err(|e| {
+                DuckError::Backup(format!("读取文件失败 {}: {e}", source.display()))
+            })?;
+            let read_before = stats.total_bytes;
+            let chunks = store.store_file(source, &mut stats)?;
+            let (uid, gid) = file_owner(&metadata);
+            manifest.files.push(DedupFile {
+                path: archive_path.clone(),
+                // 按实际读取的字节数记录，文件在备份期间被修改时与分块保持一致
+                size: stats.total_bytes - read_before,
+                mode: file_mode(&metadata),
+                uid,
+                gid,
+                mtime: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs())
+                    .unwrap_or_default(),
+                chunks,
+            });
+        }
+        reporter.update(index as u64 + 1);
+    }
+
+    info!(
+        "去重备份: 共 {:.1} MB，新增分块 {} 个 ({:.1} MB)，复用分块 {} 个",
+        stats.total_bytes as f64 / 1024.0 / 1024.0,
+        stats.new_chunks,
+        stats.new_bytes as f64 / 1024.0 / 1024.0,
+        stats.reused_chunks
+    );
+    Ok(manifest)
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &std::fs::Metadata) -> u32 {
+    if metadata.permissions().readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+#[cfg(unix)]
+fn file_owner(metadata: &std::fs::Metadata) -> (Option<u32>, Option<u32>) {
+    use std::os::unix::fs::MetadataExt;
+    (Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn file_owner(_metadata: &std::fs::Metadata) -> (Option<u32>, Option<u32>) {
+    (None, None)
+}
+
+/// 把清单中的文件复制（或硬链接）到暂存目录，返回指向暂存副本的清单
+///
+/// 暂存副本按序号平铺存放，归档名保持不变；符号链接和目录无需暂存
+fn stage_backup_entries(
+    entries: Vec<BackupEntry>,
+    staging_dir: &Path,
+    mode: BackupStagingMode,
+) -> Result<Vec<BackupEntry>> {
+    std::fs::create_dir_all(staging_dir)?;
+
+    let mut linked = 0usize;
+    let mut copied = 0usize;
+    let mut staged = Vec::with_capacity(entries.len());
+    for (index, entry) in entries.into_iter().enumerate() {
+        match entry {
+            BackupEntry::File {
+                source,
+                archive_path,
+            } => {
+                let staged_path = staging_dir.join(index.to_string());
+                let hard_linked = stage_file(&source, &staged_path, mode).map_err(|e| {
+                    DuckError::Backup(format!("暂存文件失败 {}: {e}", source.display()))
+                })?;
+                if hard_linked {
+                    linked += 1;
+                } else {
+                    copied += 1;
+                }
+                staged.push(BackupEntry::File {
+                    source: staged_path,
+                    archive_path,
+                });
+            }
+            // 符号链接只记录目标，目录只记录权限
+            entry @ (BackupEntry::Symlink { .. } | BackupEntry::Directory(_)) => staged.push(entry),
+        }
+    }
+
+    debug!("暂存完成: 硬链接 {} 个文件，复制 {} 个文件", linked, copied);
+    if mode == BackupStagingMode::Hardlink && copied > 0 {
+        warn!(
+            "⚠️ {} 个文件无法硬链接（可能与备份目录不在同一文件系统），已改为复制",
+            copied
+        );
+    }
+
+    Ok(staged)
+}
+
+// 暂存单个文件，返回是否使用了硬链接
+//
+// 复制时使用 std::fs::copy，Linux 上会走 copy_file_range，在支持 reflink 的文件系统
+// （btrfs、XFS 等）上由内核完成写时复制
+fn stage_file(source: &Path, staged: &Path, mode: BackupStagingMode) -> std::io::Result<bool> {
+    if mode == BackupStagingMode::Hardlink {
+        match std::fs::hard_link(source, staged) {
+            Ok(()) => return Ok(true),
+            Err(e) => debug!("硬链接失败，改为复制: {} - {}", source.display(), e),
+        }
+    }
+
+    std::fs::copy(source, staged)?;
+    // 保留属主和扩展属性，归档中的条目头记录的是暂存副本的属主
+    copy_ownership(source, staged);
+    // 保留修改时间，使归档中的文件时间与原文件一致
+    if let Ok(modified) = std::fs::metadata(source).and_then(|m| m.modified()) {
+        let _ = File::options()
+            .write(true)
+            .open(staged)
+            .and_then(|file| file.set_modified(modified));
+    }
+    Ok(false)
+}
+
+// 删除暂存目录，失败只记录警告
+fn remove_staging_dir(staging_dir: &Path) {
+    if staging_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(staging_dir) {
+            warn!("⚠️ 删除备份暂存目录失败: {} - {}", staging_dir.display(), e);
+        }
+    }
+}
+
+/// 把普通（非去重）备份归档解压到 `target_dir`
+///
+/// 跳过 `_system/` 和 `dirs_to_exclude` 中的顶层目录，不清理目标目录中已有的文件。
+/// 备份记录了属主时按 `ownership` 恢复属主，最后按格式清单设置目录权限和扩展属性
+pub(crate) fn extract_backup_archive(
+    backup_path: &Path,
+    target_dir: &Path,
+    dirs_to_exclude: &[String],
+    ownership: &OwnershipMapping,
+) -> Result<(), DuckError> {
+    let file = File::open(backup_path)?;
+    let decoder = GzDecoder::new(file);
+    let mut archive = Archive::new(decoder);
+    let mut links = SymlinkExtractor::new(target_dir);
+    let mut restorer = PermissionRestorer::new(ownership);
+
+    let mut debug_dirs = std::collections::HashSet::new();
+
+    // 遍历归档中的所有条目
+    for entry in archive.entries()? {
+        let mut entry = entry.map_err(|e| DuckError::Backup(format!("读取归档条目失败: {e}")))?;
+        if restorer.read_manifest(&mut entry)? {
+            continue;
+        }
+
+        // 获取条目路径
+        let entry_path = entry
+            .path()
+            .map_err(|e| DuckError::Backup(format!("获取条目路径失败: {e}")))?;
+        let entry_path_str = entry_path.to_string_lossy();
+
+        // Split path into components
+        let path_components: Vec<&str> = entry_path_str.split('/').collect();
+
+        // Check if this is a directory we want to exclude (first level)
+        let should_exclude = if !path_components.is_empty() {
+            let first_level_dir = path_components[0];
+            debug_dirs.insert(first_level_dir.to_string());
+
+            // CLI 自身状态只能通过 restore_system_state 显式恢复
+            first_level_dir == SYSTEM_BACKUP_DIR_NAME
+                || dirs_to_exclude
+                    .iter()
+                    .any(|dir| dir.as_str() == first_level_dir)
+        } else {
+            false // Not enough path components, don't exclude
+        };
+
+        if !should_exclude {
+            // 计算解压到的目标路径
+            let target_path = resolve_entry_path(target_dir, &entry_path_str)?;
+            restore_entry(&mut entry, &target_path, &mut links, restorer.mapping())?;
+        }
+    }
+    links.finish()?;
+    restorer.finish(target_dir, |path| {
+        let first_level_dir = path.split('/').next().unwrap_or_default();
+        first_level_dir != SYSTEM_BACKUP_DIR_NAME
+            && !dirs_to_exclude.iter().any(|dir| dir == first_level_dir)
+    });
+
+    debug!("测试日志,恢复目录: {:?}", debug_dirs);
+
+    Ok(())
+}
+
+/// 恢复时的属主和目录权限
+///
+/// 从格式清单读取权限记录；清单表明条目头记录了属主时才按映射恢复文件属主，
+/// 所有文件解压后再设置目录权限和扩展属性
+struct PermissionRestorer {
+    configured: OwnershipMapping,
+    mapping: OwnershipMapping,
+    permissions: Vec<PathPermissions>,
+}
+
+impl PermissionRestorer {
+    fn new(ownership: &OwnershipMapping) -> Self {
+        Self {
+            configured: ownership.clone(),
+            mapping: OwnershipMapping {
+                skip_owner: true,
+                ..OwnershipMapping::default()
+            },
+            permissions: Vec::new(),
+        }
+    }
+
+    /// 条目为格式清单时读取其中的权限记录并返回 true
+    fn read_manifest<R: std::io::Read>(
+        &mut self,
+        entry: &mut tar::Entry<R>,
+    ) -> Result<bool, DuckError> {
+        let manifest = parse_backup_manifest_entry(entry)
+            .map_err(|e| DuckError::Backup(format!("读取备份清单失败: {e}")))?;
+        let Some(manifest) = manifest else {
+            return Ok(false);
+        };
+        if manifest.preserves_ownership {
+            self.mapping = self.configured.clone();
+        }
+        self.permissions = manifest.permissions;
+        Ok(true)
+    }
+
+    /// 恢复文件属主使用的映射
+    fn mapping(&self) -> &OwnershipMapping {
+        &self.mapping
+    }
+
+    /// 设置已恢复路径中 `selected` 选中的目录权限和扩展属性
+    fn finish(self, target_dir: &Path, selected: impl Fn(&str) -> bool) {
+        let stats =
+            restore_path_permissions(target_dir, &self.permissions, &self.mapping, selected);
+        if stats.applied > 0 {
+            debug!("已恢复 {} 个目录或文件的权限", stats.applied);
+        }
+    }
+}
+
+// 恢复单个归档条目：符号链接经过校验后重建，其他条目按条目头的权限解压并恢复属主
+fn restore_entry<R: std::io::Read>(
+    entry: &mut tar::Entry<R>,
+    target_path: &Path,
+    links: &mut SymlinkExtractor,
+    ownership: &OwnershipMapping,
+) -> Result<(), DuckError> {
+    // 确保父目录存在
+    if let Some(parent) = target_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    if entry.header().entry_type().is_symlink() {
+        let link_target = entry
+            .link_name()
+            .map_err(|e| DuckError::Backup(format!("读取符号链接目标失败: {e}")))?
+            .ok_or_else(|| {
+                DuckError::Backup(format!("符号链接缺少目标: {}", target_path.display()))
+            })?;
+        links.create(target_path, &link_target)?;
+        debug!(
+            "恢复符号链接: {} -> {}",
+            target_path.display(),
+            link_target.display()
+        );
+        return Ok(());
+    }
+
+    // 解压文件，保留 setuid、setgid 等权限位
+    entry.set_preserve_permissions(true);
+    entry
+        .unpack(target_path)
+        .map_err(|e| DuckError::Backup(format!("解压文件失败 {}: {e}", target_path.display())))?;
+    if let Err(e) = restore_owner(entry.header(), target_path, ownership) {
+        warn!("⚠️ 恢复属主失败: {} - {}", target_path.display(), e);
+    }
+
+    debug!("恢复文件: {}", target_path.display());
+    Ok(())
+}
+
+// 按条目头恢复文件属主；修改属主会清除 setuid/setgid 位，需要重新设置权限
+fn restore_owner(
+    header: &tar::Header,
+    path: &Path,
+    ownership: &OwnershipMapping,
+) -> std::io::Result<()> {
+    if !ownership.changes_owner() {
+        return Ok(());
+    }
+    ownership.apply_owner(path, header.uid()? as u32, header.gid()? as u32)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = header.mode()? & 0o7777;
+        if mode & 0o6000 != 0 {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        }
+    }
+    Ok(())
+}
+
+// 计算文件在归档中的路径
+fn archive_entry_name(file_path: &Path, base_info: Option<(&Path, &str)>) -> Result<String> {
     let archive_path = if let Some((base_dir, dir_name)) = base_info {
         // 文件是目录的一部分，计算相对路径
         let relative_path = file_path
             .strip_prefix(base_dir)
             .map_err(|e| DuckError::Backup(format!("计算相对路径失败: {e}")))?;
 
-        // 格式：{dir_name}/{relative_path}
-        if cfg!(windows) {
-            format!(
-                "{}/{}",
-                dir_name,
-                relative_path.display().to_string().replace('\\', "/")
-            )
-        } else {
-            format!("{}/{}", dir_name, relative_path.display())
-        }
+        // 格式：{dir_name}/{relative_path}，按路径组件拼接，与平台分隔符无关
+        let relative_path = relative_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        format!("{dir_name}/{relative_path}")
     } else {
         // 直接处理单个文件，保持原有路径结构
         let path_str = file_path.to_string_lossy().to_string();
@@ -671,15 +2499,742 @@ fn add_file_to_archive(
         }
     };
 
-    debug!(
-        "添加文件到归档: {} -> {}",
-        file_path.display(),
-        archive_path
-    );
+    Ok(archive_path)
+}
 
-    archive
-        .append_path_with_name(file_path, archive_path)
-        .map_err(|e| DuckError::Backup(format!("添加文件到归档失败: {e}")))?;
+/// 移动文件或目录，源和目标不在同一磁盘（文件系统）时改为复制后删除
+async fn move_path(from: &Path, to: &Path) -> Result<()> {
+    let from = from.to_path_buf();
+    let to = to.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        match std::fs::rename(&from, &to) {
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                debug!(
+                    "跨磁盘移动，改为复制: {} -> {}",
+                    from.display(),
+                    to.display()
+                );
+                if from.is_dir() {
+                    let options = fs_extra::dir::CopyOptions::new().copy_inside(true);
+                    fs_extra::dir::copy(&from, &to, &options)
+                        .map_err(|e| DuckError::Backup(format!("复制目录失败: {e}")))?;
+                    std::fs::remove_dir_all(&from)?;
+                } else {
+                    std::fs::copy(&from, &to)?;
+                    std::fs::remove_file(&from)?;
+                }
+            }
+            result => result?,
+        }
+        Ok::<(), anyhow::Error>(())
+    })
+    .await??;
+    Ok(())
+}
 
+/// 规范化备份路径用于比较，文件不存在时使用原路径
+fn normalize_backup_path(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// 完整读取归档的所有条目，校验压缩数据是否完整，返回条目数量
+fn read_full_archive(path: &Path) -> Result<usize> {
+    let mut archive = Archive::new(GzDecoder::new(File::open(path)?));
+    let mut count = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry.map_err(|e| DuckError::Backup(format!("读取归档条目失败: {e}")))?;
+        std::io::copy(&mut entry, &mut std::io::sink())
+            .map_err(|e| DuckError::Backup(format!("读取归档内容失败: {e}")))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// 创建后校验的统计
+#[derive(Debug, Default, PartialEq, Eq)]
+struct WrittenArchiveCheck {
+    /// 核对了大小的文件数
+    files: usize,
+    /// 核对了链接目标的符号链接数
+    symlinks: usize,
+    /// 抽样比对了内容哈希的文件数
+    sampled: usize,
+}
+
+/// 校验刚写入的归档并记录日志，返回是否通过
+fn verify_written_archive(
+    entries: &[BackupEntry],
+    backup_path: &Path,
+    dedup_store: Option<&DedupStore>,
+) -> bool {
+    info!("校验刚创建的备份: {}", backup_path.display());
+    match check_written_archive(entries, backup_path, dedup_store) {
+        Ok(check) => {
+            info!(
+                "✅ 备份校验通过: {} 个文件、{} 个符号链接，抽样比对 {} 个文件的内容哈希",
+                check.files, check.symlinks, check.sampled
+            );
+            true
+        }
+        Err(e) => {
+            error!("❌ 备份校验失败: {}", e);
+            false
+        }
+    }
+}
+
+/// 重新打开刚写入的归档，按备份清单核对文件和符号链接是否齐全、文件大小是否一致，并抽样比对内容哈希
+///
+/// 与源文件（暂存时为暂存副本）比较，必须在源文件发生变化、暂存目录删除之前调用
+fn check_written_archive(
+    entries: &[BackupEntry],
+    backup_path: &Path,
+    dedup_store: Option<&DedupStore>,
+) -> Result<WrittenArchiveCheck> {
+    let mut files: HashMap<&str, &Path> = HashMap::new();
+    let mut symlinks: HashMap<&str, &Path> = HashMap::new();
+    for entry in entries {
+        match entry {
+            BackupEntry::File {
+                source,
+                archive_path,
+            } => {
+                files.insert(archive_path, source);
+            }
+            BackupEntry::Symlink {
+                archive_path,
+                target,
+                ..
+            } => {
+                symlinks.insert(archive_path, target);
+            }
+            BackupEntry::Directory(_) => {}
+        }
+    }
+    let samples = sample_archive_paths(entries);
+    let mut check = WrittenArchiveCheck::default();
+
+    let mut archive = Archive::new(GzDecoder::new(File::open(backup_path)?));
+    for entry in archive.entries()? {
+        let mut entry = entry.map_err(|e| DuckError::Backup(format!("读取归档条目失败: {e}")))?;
+        let path = entry.path()?.to_string_lossy().to_string();
+        if path == DEDUP_MANIFEST_NAME || is_backup_manifest_path(Path::new(&path)) {
+            continue;
+        }
+
+        if entry.header().entry_type().is_symlink() {
+            let expected = symlinks
+                .remove(path.as_str())
+                .ok_or_else(|| DuckError::Backup(format!("归档中有清单之外的条目: {path}")))?;
+            let target = entry.link_name()?.unwrap_or_default();
+            if target != expected {
+                return Err(DuckError::Backup(format!("符号链接目标不一致: {path}")).into());
+            }
+            check.symlinks += 1;
+            continue;
+        }
+
+        let source = files
+            .remove(path.as_str())
+            .ok_or_else(|| DuckError::Backup(format!("归档中有清单之外的条目: {path}")))?;
+        check_file_size(&path, entry.header().size()?, source)?;
+        if samples.contains(path.as_str()) {
+            compare_sample_hash(&path, &mut entry, source)?;
+            check.sampled += 1;
+        } else {
+            // 读取全部内容，校验压缩数据完整
+            std::io::copy(&mut entry, &mut std::io::sink())
+                .map_err(|e| DuckError::Backup(format!("读取归档内容失败 {path}: {e}")))?;
+        }
+        check.files += 1;
+    }
+
+    // 去重备份的文件内容在分块存储中，按分块清单核对
+    if let Some(store) = dedup_store {
+        let manifest = read_manifest(backup_path)?
+            .ok_or_else(|| DuckError::Backup("去重备份缺少分块清单".to_string()))?;
+        for file in &manifest.files {
+            let source = files.remove(file.path.as_str()).ok_or_else(|| {
+                DuckError::Backup(format!("分块清单中有清单之外的文件: {}", file.path))
+            })?;
+            check_file_size(&file.path, file.size, source)?;
+            if samples.contains(file.path.as_str()) {
+                compare_sample_hash(&file.path, store.open_file(&file.chunks), source)?;
+                check.sampled += 1;
+            }
+            check.files += 1;
+        }
+        let report = store.verify(&manifest);
+        if !report.is_ok() {
+            return Err(DuckError::Backup(format!(
+                "去重分块校验失败: 缺失 {} 个、损坏 {} 个，{} 个文件大小不一致",
+                report.missing.len(),
+                report.corrupt.len(),
+                report.size_mismatch.len()
+            ))
+            .into());
+        }
+    }
+
+    if let Some(path) = files.keys().min() {
+        return Err(
+            DuckError::Backup(format!("归档缺少 {} 个文件，如 {path}", files.len())).into(),
+        );
+    }
+    if let Some(path) = symlinks.keys().min() {
+        return Err(DuckError::Backup(format!(
+            "归档缺少 {} 个符号链接，如 {path}",
+            symlinks.len()
+        ))
+        .into());
+    }
+    Ok(check)
+}
+
+/// 抽样比对内容哈希的文件：按清单顺序等间隔选取，最多 [`VERIFY_SAMPLE_FILES`] 个
+fn sample_archive_paths(entries: &[BackupEntry]) -> HashSet<&str> {
+    let files: Vec<&str> = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            BackupEntry::File { archive_path, .. } => Some(archive_path.as_str()),
+            _ => None,
+        })
+        .collect();
+    let step = files.len().div_ceil(VERIFY_SAMPLE_FILES).max(1);
+    files.into_iter().step_by(step).collect()
+}
+
+/// 比较归档中记录的文件大小与源文件大小
+fn check_file_size(path: &str, size: u64, source: &Path) -> Result<()> {
+    let expected = std::fs::metadata(source)?.len();
+    if size != expected {
+        return Err(DuckError::Backup(format!(
+            "文件大小不一致: {path}（归档 {size} 字节，源文件 {expected} 字节）"
+        ))
+        .into());
+    }
     Ok(())
 }
+
+/// 比较归档中的文件内容与源文件的 SHA-256
+fn compare_sample_hash(path: &str, content: impl std::io::Read, source: &Path) -> Result<()> {
+    let archived = sha256_reader(content)
+        .map_err(|e| DuckError::Backup(format!("读取归档内容失败 {path}: {e}")))?;
+    if archived != sha256_reader(File::open(source)?)? {
+        return Err(DuckError::Backup(format!("文件内容与源文件不一致: {path}")).into());
+    }
+    Ok(())
+}
+
+fn sha256_reader(mut reader: impl std::io::Read) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// 检查文件是否为可读取的 tar.gz 归档
+fn verify_backup_archive(path: &Path) -> Result<()> {
+    let mut archive = Archive::new(GzDecoder::new(File::open(path)?));
+    match archive.entries()?.next() {
+        Some(entry) => {
+            entry?;
+            Ok(())
+        }
+        None => Err(anyhow::anyhow!("归档为空")),
+    }
+}
+
+/// 选出超出保留数量的备份：每种类型按创建时间从新到旧保留前 N 个
+pub fn select_backups_to_prune<'a>(
+    backups: &'a [BackupRecord],
+    retention: &BackupRetentionConfig,
+) -> Vec<&'a BackupRecord> {
+    let mut to_prune = Vec::new();
+
+    for backup_type in BackupType::ALL {
+        let Some(keep) = retention.keep_for(backup_type) else {
+            continue;
+        };
+
+        let mut same_type: Vec<&BackupRecord> = backups
+            .iter()
+            .filter(|backup| backup.backup_type == backup_type)
+            .collect();
+        same_type.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(b.id.cmp(&a.id)));
+        to_prune.extend(same_type.into_iter().skip(keep));
+    }
+
+    to_prune
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup_manifest::read_backup_manifest;
+    use chrono::Duration;
+
+    fn record(id: i64, backup_type: BackupType, hours_ago: i64) -> BackupRecord {
+        BackupRecord {
+            id,
+            file_path: format!("backup_{id}.tar.gz"),
+            service_version: "1.0.0".to_string(),
+            backup_type,
+            status: BackupStatus::Completed,
+            created_at: Utc::now() - Duration::hours(hours_ago),
+            namespace: None,
+            verified: None,
+        }
+    }
+
+    #[test]
+    fn test_select_backups_to_prune_per_type() {
+        let backups = vec![
+            record(1, BackupType::PreUpgrade, 40),
+            record(2, BackupType::PreUpgrade, 30),
+            record(3, BackupType::PreUpgrade, 20),
+            record(4, BackupType::PreUpgrade, 10),
+            record(5, BackupType::Scheduled, 48),
+            record(6, BackupType::Scheduled, 24),
+            record(7, BackupType::Manual, 100),
+            record(8, BackupType::Snapshot, 200),
+        ];
+        let retention = BackupRetentionConfig {
+            keep_manual: 0,
+            keep_pre_upgrade: 2,
+            keep_scheduled: 14,
+            keep_snapshot: 0,
+        };
+
+        let mut pruned: Vec<i64> = select_backups_to_prune(&backups, &retention)
+            .iter()
+            .map(|backup| backup.id)
+            .collect();
+        pruned.sort();
+
+        assert_eq!(pruned, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_backup_size_guard() {
+        let temp = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(temp.path().join("app/static")).unwrap();
+        std::fs::write(temp.path().join("app/static/index.html"), vec![0u8; 3000]).unwrap();
+        std::fs::write(temp.path().join("config.toml"), vec![0u8; 1000]).unwrap();
+        let estimated = source_paths_size(&[
+            temp.path().join("app"),
+            temp.path().join("config.toml"),
+            temp.path().join("missing"),
+        ]);
+        assert_eq!(estimated, 4000);
+
+        let guard = BackupSizeGuard {
+            max_size_bytes: Some(3000),
+            free_space_margin_percent: 10,
+        };
+        let error = guard
+            .check(estimated, estimated, None, temp.path())
+            .unwrap_err();
+        assert!(error.to_string().contains("超过上限"), "{error}");
+
+        let guard = BackupSizeGuard {
+            max_size_bytes: None,
+            free_space_margin_percent: 10,
+        };
+        assert!(guard.check(4000, 4000, Some(4400), temp.path()).is_ok());
+        let error = guard
+            .check(4000, 4000, Some(4399), temp.path())
+            .unwrap_err();
+        assert!(error.to_string().contains("可用空间不足"), "{error}");
+        // 无法获取可用空间时只检查大小上限
+        assert!(guard.check(4000, 8000, None, temp.path()).is_ok());
+    }
+
+    #[test]
+    fn test_backup_type_parse_roundtrip() {
+        for backup_type in BackupType::ALL {
+            assert_eq!(backup_type.as_str().parse::<BackupType>(), Ok(backup_type));
+        }
+        assert_eq!(
+            "pre_upgrade".parse::<BackupType>(),
+            Ok(BackupType::PreUpgrade)
+        );
+        assert!("weekly".parse::<BackupType>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_archived_and_restored_without_escaping() {
+        let work_dir = tempfile::TempDir::new().unwrap();
+        let app_dir = work_dir.path().join("app");
+        std::fs::create_dir_all(app_dir.join("v2")).unwrap();
+        std::fs::write(app_dir.join("v2/app.conf"), "v2").unwrap();
+        std::os::unix::fs::symlink("v2", app_dir.join("current")).unwrap();
+        std::os::unix::fs::symlink("/etc", app_dir.join("etc")).unwrap();
+
+        let backup_path = work_dir.path().join("backup.tar.gz");
+        let entries = collect_backup_entries(&[app_dir.clone()], &[]).unwrap();
+        write_backup_archive(
+            &entries,
+            &backup_path,
+            1,
+            None,
+            "test",
+            &EventSender::default(),
+        )
+        .unwrap();
+
+        let restore_dir = tempfile::TempDir::new().unwrap();
+        let mut links = SymlinkExtractor::new(restore_dir.path());
+        let mut archive = Archive::new(GzDecoder::new(File::open(&backup_path).unwrap()));
+        let mut names = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().to_string();
+            let target_path = resolve_entry_path(restore_dir.path(), &name).unwrap();
+            restore_entry(
+                &mut entry,
+                &target_path,
+                &mut links,
+                &OwnershipMapping::default(),
+            )
+            .unwrap();
+            names.push(name);
+        }
+        assert_eq!(links.finish().unwrap(), 0);
+
+        assert!(names.contains(&"app/current".to_string()));
+        assert!(
+            !names.contains(&"app/etc".to_string()),
+            "逃逸的链接不应被备份"
+        );
+        let restored_link = restore_dir.path().join("app/current");
+        assert_eq!(std::fs::read_link(&restored_link).unwrap(), Path::new("v2"));
+        assert_eq!(
+            std::fs::read_to_string(restored_link.join("app.conf")).unwrap(),
+            "v2"
+        );
+    }
+
+    #[test]
+    fn test_check_written_archive() {
+        let work_dir = tempfile::TempDir::new().unwrap();
+        let data_dir = work_dir.path().join("data");
+        std::fs::create_dir_all(data_dir.join("mysql")).unwrap();
+        for index in 0..40 {
+            std::fs::write(data_dir.join(format!("mysql/{index}.ibd")), "page").unwrap();
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("mysql", data_dir.join("current")).unwrap();
+        let entries = collect_backup_entries(&[data_dir.clone()], &[]).unwrap();
+        let symlinks = if cfg!(unix) { 1 } else { 0 };
+
+        let store = DedupStore::new(work_dir.path().join("store"));
+        for dedup_store in [None, Some(&store)] {
+            let backup_path = work_dir.path().join("backup.tar.gz");
+            write_backup_archive(
+                &entries,
+                &backup_path,
+                1,
+                dedup_store,
+                "test",
+                &EventSender::default(),
+            )
+            .unwrap();
+            let check = check_written_archive(&entries, &backup_path, dedup_store).unwrap();
+            assert_eq!(
+                check,
+                WrittenArchiveCheck {
+                    files: 40,
+                    symlinks,
+                    // 每 3 个文件抽样 1 个
+                    sampled: 14,
+                }
+            );
+        }
+
+        // 源文件在写入后被改动（大小不变）时抽样哈希不一致，大小改变时直接报错
+        let backup_path = work_dir.path().join("backup.tar.gz");
+        write_backup_archive(
+            &entries,
+            &backup_path,
+            1,
+            None,
+            "test",
+            &EventSender::default(),
+        )
+        .unwrap();
+        for index in 0..40 {
+            std::fs::write(data_dir.join(format!("mysql/{index}.ibd")), "PAGE").unwrap();
+        }
+        let err = check_written_archive(&entries, &backup_path, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("文件内容与源文件不一致"), "{err}");
+        std::fs::write(data_dir.join("mysql/0.ibd"), "page-v2").unwrap();
+        let err = check_written_archive(&entries, &backup_path, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("文件大小不一致"), "{err}");
+
+        // 清单中的文件不在归档中
+        let mut missing = entries.clone();
+        missing.push(BackupEntry::File {
+            source: data_dir.join("mysql/1.ibd"),
+            archive_path: "data/extra.ibd".to_string(),
+        });
+        let err = check_written_archive(&missing, &backup_path, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("归档缺少 1 个文件"), "{err}");
+    }
+
+    #[test]
+    fn test_deep_paths_archived_and_restored() {
+        let work_dir = tempfile::TempDir::new().unwrap();
+        let data_dir = work_dir.path().join("data");
+        let segment = "volume".repeat(8);
+        let relative = format!("{}/db.conf", vec![segment.as_str(); 6].join("/"));
+
+        // 目录深度超过 Windows 的 MAX_PATH
+        let deep_file = resolve_entry_path(&data_dir, &relative).unwrap();
+        assert!(deep_file.as_os_str().len() > 260);
+        std::fs::create_dir_all(deep_file.parent().unwrap()).unwrap();
+        std::fs::write(&deep_file, "deep").unwrap();
+
+        let backup_path = work_dir.path().join("backup.tar.gz");
+        let entries = collect_backup_entries(&[data_dir], &[]).unwrap();
+        write_backup_archive(
+            &entries,
+            &backup_path,
+            1,
+            None,
+            "test",
+            &EventSender::default(),
+        )
+        .unwrap();
+
+        // 归档中的路径统一使用 `/` 分隔
+        let archive_name = format!("data/{relative}");
+        assert_eq!(
+            read_archive(&backup_path),
+            vec![(archive_name.clone(), "deep".to_string())]
+        );
+
+        let restore_dir = tempfile::TempDir::new().unwrap();
+        let mut links = SymlinkExtractor::new(restore_dir.path());
+        let mut archive = Archive::new(GzDecoder::new(File::open(&backup_path).unwrap()));
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            if is_backup_manifest_path(&entry.path().unwrap()) {
+                continue;
+            }
+            let name = entry.path().unwrap().to_string_lossy().to_string();
+            let target_path = resolve_entry_path(restore_dir.path(), &name).unwrap();
+            restore_entry(
+                &mut entry,
+                &target_path,
+                &mut links,
+                &OwnershipMapping::default(),
+            )
+            .unwrap();
+        }
+        assert_eq!(links.finish().unwrap(), 0);
+
+        let restored = resolve_entry_path(restore_dir.path(), &archive_name).unwrap();
+        assert_eq!(std::fs::read_to_string(restored).unwrap(), "deep");
+    }
+
+    #[tokio::test]
+    async fn test_move_path() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source_dir = temp_dir.path().join("backups");
+        std::fs::create_dir_all(source_dir.join("chunks")).unwrap();
+        std::fs::write(source_dir.join("chunks/a"), "a").unwrap();
+
+        // CI 中指向另一块磁盘上的目录，覆盖跨磁盘复制的分支
+        let target_root = std::env::var_os("NUWAX_TEST_OTHER_DRIVE_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| temp_dir.path().to_path_buf());
+        std::fs::create_dir_all(&target_root).unwrap();
+        let target_dir = target_root.join(format!("moved-{}", std::process::id()));
+        move_path(&source_dir, &target_dir).await.unwrap();
+        assert!(!source_dir.exists());
+        assert_eq!(
+            std::fs::read_to_string(target_dir.join("chunks/a")).unwrap(),
+            "a"
+        );
+        std::fs::remove_dir_all(&target_dir).unwrap();
+    }
+
+    fn read_archive(path: &Path) -> Vec<(String, String)> {
+        let mut archive = Archive::new(GzDecoder::new(File::open(path).unwrap()));
+        let mut files = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            // 格式清单包含创建时间，不参与内容比较
+            if is_backup_manifest_path(&entry.path().unwrap()) {
+                continue;
+            }
+            let name = entry.path().unwrap().to_string_lossy().to_string();
+            let mut content = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut content).unwrap();
+            files.push((name, content));
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_staged_backup_matches_direct_backup() {
+        let work_dir = tempfile::TempDir::new().unwrap();
+        let data_dir = work_dir.path().join("data");
+        std::fs::create_dir_all(data_dir.join("mysql")).unwrap();
+        std::fs::write(data_dir.join("mysql/ibdata1"), "v1").unwrap();
+        let env_file = work_dir.path().join(".env");
+        std::fs::write(&env_file, "PORT=80").unwrap();
+        let sources = vec![data_dir.clone(), env_file.clone()];
+
+        let direct_path = work_dir.path().join("direct.tar.gz");
+        let entries = collect_backup_entries(&sources, &[]).unwrap();
+        write_backup_archive(
+            &entries,
+            &direct_path,
+            1,
+            None,
+            "test",
+            &EventSender::default(),
+        )
+        .unwrap();
+        let expected = read_archive(&direct_path);
+
+        for mode in [BackupStagingMode::Copy, BackupStagingMode::Hardlink] {
+            let staging_dir = work_dir.path().join(format!("staging_{}", mode.as_str()));
+            let entries = collect_backup_entries(&sources, &[]).unwrap();
+            let staged = stage_backup_entries(entries, &staging_dir, mode).unwrap();
+
+            // 暂存完成后服务替换文件，不影响暂存副本
+            std::fs::remove_file(&env_file).unwrap();
+            std::fs::write(&env_file, "PORT=8080").unwrap();
+
+            let staged_path = work_dir.path().join(format!("{}.tar.gz", mode.as_str()));
+            write_backup_archive(
+                &staged,
+                &staged_path,
+                1,
+                None,
+                "test",
+                &EventSender::default(),
+            )
+            .unwrap();
+            assert_eq!(read_archive(&staged_path), expected, "mode: {mode:?}");
+
+            std::fs::write(&env_file, "PORT=80").unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_permissions_archived_and_restored() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let work_dir = tempfile::TempDir::new().unwrap();
+        let data_dir = work_dir.path().join("data");
+        std::fs::create_dir_all(data_dir.join("mysql")).unwrap();
+        std::fs::write(data_dir.join("mysql/ibdata1"), "v1").unwrap();
+        let set_mode = |path: &Path, mode: u32| {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).unwrap()
+        };
+        set_mode(&data_dir.join("mysql/ibdata1"), 0o600);
+        set_mode(&data_dir.join("mysql"), 0o750);
+
+        // 复制方式暂存后目录权限和文件权限仍来自源文件
+        let entries = collect_backup_entries(&[data_dir], &[]).unwrap();
+        let staging_dir = work_dir.path().join("staging");
+        let staged = stage_backup_entries(entries, &staging_dir, BackupStagingMode::Copy).unwrap();
+        let backup_path = work_dir.path().join("backup.tar.gz");
+        write_backup_archive(
+            &staged,
+            &backup_path,
+            1,
+            None,
+            "test",
+            &EventSender::default(),
+        )
+        .unwrap();
+        let manifest = read_backup_manifest(&backup_path).unwrap().unwrap();
+        assert!(manifest.preserves_ownership);
+        assert!(
+            manifest
+                .permissions
+                .iter()
+                .any(|record| record.path == "data/mysql" && record.mode == 0o750)
+        );
+
+        let restore_dir = tempfile::TempDir::new().unwrap();
+        extract_backup_archive(
+            &backup_path,
+            restore_dir.path(),
+            &[],
+            &OwnershipMapping::default(),
+        )
+        .unwrap();
+        let mode = |path: &str| {
+            std::fs::metadata(restore_dir.path().join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o7777
+        };
+        assert_eq!(mode("data/mysql"), 0o750);
+        assert_eq!(mode("data/mysql/ibdata1"), 0o600);
+    }
+
+    #[test]
+    fn test_dedup_backup_exports_same_content() {
+        let work_dir = tempfile::TempDir::new().unwrap();
+        let data_dir = work_dir.path().join("data");
+        std::fs::create_dir_all(data_dir.join("mysql")).unwrap();
+        std::fs::write(data_dir.join("mysql/ibdata1"), "v1".repeat(1000)).unwrap();
+        std::fs::write(data_dir.join("mysql/ibdata2"), "v1".repeat(1000)).unwrap();
+        std::fs::write(data_dir.join("empty"), "").unwrap();
+        let entries = collect_backup_entries(&[data_dir], &[]).unwrap();
+
+        let direct_path = work_dir.path().join("direct.tar.gz");
+        write_backup_archive(
+            &entries,
+            &direct_path,
+            1,
+            None,
+            "test",
+            &EventSender::default(),
+        )
+        .unwrap();
+
+        let store = DedupStore::new(work_dir.path().join("store"));
+        let dedup_path = work_dir.path().join("dedup.tar.gz");
+        write_backup_archive(
+            &entries,
+            &dedup_path,
+            1,
+            Some(&store),
+            "test",
+            &EventSender::default(),
+        )
+        .unwrap();
+        let manifest = read_manifest(&dedup_path).unwrap().unwrap();
+        assert_eq!(manifest.files.len(), 3);
+        // 相同内容只存储一份分块
+        assert_eq!(manifest.chunk_hashes().len(), 1);
+        assert!(store.verify(&manifest).is_ok());
+
+        let plain_path = work_dir.path().join("plain.tar.gz");
+        export_plain_archive(&dedup_path, &store, &plain_path, Compression::fast()).unwrap();
+        assert_eq!(read_archive(&plain_path), read_archive(&direct_path));
+        // 3 个文件和格式清单
+        assert_eq!(read_full_archive(&plain_path).unwrap(), 4);
+        let exported = read_backup_manifest(&plain_path).unwrap().unwrap();
+        assert!(!exported.dedup);
+        assert_eq!(exported.included_paths, vec!["data".to_string()]);
+    }
+}
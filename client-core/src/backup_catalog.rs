@@ -0,0 +1,460 @@
+//! 备份目录（catalog）的导出与导入
+//!
+//! 导出的目录包含每个备份的 ID、类型、版本、命名空间、大小、哈希和文件位置，供外部备份清单系统
+//! 跟踪备份归档；导入和 `adopt` 用于在本地数据库重建后重新登记磁盘上已有的备份文件。
+//! 备份目录中的镜像归档（`docker-service export-images`）作为可选内容列在 JSON 目录的 `images` 中。
+
+use crate::database::{BackupRecord, BackupType};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 目录文件格式版本
+pub const CATALOG_FORMAT_VERSION: u32 = 1;
+
+/// CSV 表头
+const CSV_HEADER: [&str; 9] = [
+    "id",
+    "backup_type",
+    "service_version",
+    "created_at",
+    "file_path",
+    "size_bytes",
+    "sha256",
+    "file_exists",
+    "namespace",
+];
+
+/// 目录导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogFormat {
+    Json,
+    Csv,
+}
+
+impl CatalogFormat {
+    /// 根据文件扩展名推断格式，`.csv` 为 CSV，其余按 JSON 处理
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => CatalogFormat::Csv,
+            _ => CatalogFormat::Json,
+        }
+    }
+}
+
+impl std::str::FromStr for CatalogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(CatalogFormat::Json),
+            "csv" => Ok(CatalogFormat::Csv),
+            _ => Err(format!("无效的目录格式: {s}（支持 json、csv）")),
+        }
+    }
+}
+
+/// 目录中的一条备份信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupCatalogEntry {
+    /// 本地数据库中的备份 ID，导入时忽略
+    #[serde(default)]
+    pub id: Option<i64>,
+    /// 备份类型（manual、pre-upgrade、scheduled、snapshot）
+    pub backup_type: String,
+    pub service_version: String,
+    pub created_at: DateTime<Utc>,
+    pub file_path: String,
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub file_exists: bool,
+    /// 备份所属的项目命名空间，早期备份为空
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+impl BackupCatalogEntry {
+    /// 由备份记录生成目录条目，大小和哈希由调用方补充
+    pub fn from_record(record: &BackupRecord) -> Self {
+        Self {
+            id: Some(record.id),
+            backup_type: record.backup_type.as_str().to_string(),
+            service_version: record.service_version.clone(),
+            created_at: record.created_at,
+            file_path: record.file_path.clone(),
+            size_bytes: None,
+            sha256: None,
+            file_exists: false,
+            namespace: record.namespace.clone(),
+        }
+    }
+
+    /// 解析备份类型
+    pub fn parsed_backup_type(&self) -> Result<BackupType> {
+        self.backup_type.parse().map_err(|e: String| anyhow!(e))
+    }
+}
+
+/// 目录中的一个镜像归档，不登记到本地数据库
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageArchiveCatalogEntry {
+    pub file_path: String,
+    pub service_version: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub size_bytes: Option<u64>,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// 归档中的镜像引用
+    #[serde(default)]
+    pub images: Vec<String>,
+}
+
+/// 备份目录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupCatalog {
+    pub format_version: u32,
+    pub generated_at: DateTime<Utc>,
+    pub backups: Vec<BackupCatalogEntry>,
+    /// 镜像归档，CSV 格式不包含此项
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageArchiveCatalogEntry>,
+}
+
+impl BackupCatalog {
+    pub fn new(backups: Vec<BackupCatalogEntry>) -> Self {
+        Self {
+            format_version: CATALOG_FORMAT_VERSION,
+            generated_at: Utc::now(),
+            backups,
+            images: Vec::new(),
+        }
+    }
+
+    /// 按指定格式序列化
+    pub fn render(&self, format: CatalogFormat) -> Result<String> {
+        match format {
+            CatalogFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            CatalogFormat::Csv => Ok(self.to_csv()),
+        }
+    }
+
+    /// 按指定格式解析
+    pub fn parse(content: &str, format: CatalogFormat) -> Result<Self> {
+        match format {
+            CatalogFormat::Json => {
+                let catalog: Self = serde_json::from_str(content)?;
+                if catalog.format_version > CATALOG_FORMAT_VERSION {
+                    return Err(anyhow!(
+                        "不支持的备份目录格式版本: {}（当前支持 {}）",
+                        catalog.format_version,
+                        CATALOG_FORMAT_VERSION
+                    ));
+                }
+                Ok(catalog)
+            }
+            CatalogFormat::Csv => Self::from_csv(content),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let mut output = CSV_HEADER.join(",");
+        output.push('\n');
+        for entry in &self.backups {
+            let fields = [
+                entry.id.map(|id| id.to_string()).unwrap_or_default(),
+                entry.backup_type.clone(),
+                entry.service_version.clone(),
+                entry.created_at.to_rfc3339(),
+                entry.file_path.clone(),
+                entry
+                    .size_bytes
+                    .map(|size| size.to_string())
+                    .unwrap_or_default(),
+                entry.sha256.clone().unwrap_or_default(),
+                entry.file_exists.to_string(),
+                entry.namespace.clone().unwrap_or_default(),
+            ];
+            let fields: Vec<String> = fields.iter().map(|field| csv_escape(field)).collect();
+            output.push_str(&fields.join(","));
+            output.push('\n');
+        }
+        output
+    }
+
+    fn from_csv(content: &str) -> Result<Self> {
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+        let header = lines.next().ok_or_else(|| anyhow!("备份目录 CSV 为空"))?;
+        let header = split_csv_line(header.trim_start_matches('\u{feff}'))?;
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|column| column.trim() == name)
+                .ok_or_else(|| anyhow!("备份目录 CSV 缺少列: {name}"))
+        };
+        let id_col = column("id").ok();
+        let type_col = column("backup_type")?;
+        let version_col = column("service_version")?;
+        let created_col = column("created_at")?;
+        let path_col = column("file_path")?;
+        let size_col = column("size_bytes").ok();
+        let hash_col = column("sha256").ok();
+        let namespace_col = column("namespace").ok();
+
+        let mut backups = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let fields = split_csv_line(line)?;
+            let field = |col: usize| fields.get(col).map(|f| f.trim()).unwrap_or("");
+            let optional = |col: Option<usize>| col.map(field).filter(|f| !f.is_empty());
+            let created_at = DateTime::parse_from_rfc3339(field(created_col))
+                .map_err(|e| anyhow!("第 {} 行 created_at 无效: {}", index + 2, e))?
+                .with_timezone(&Utc);
+
+            backups.push(BackupCatalogEntry {
+                id: optional(id_col).and_then(|id| id.parse().ok()),
+                backup_type: field(type_col).to_string(),
+                service_version: field(version_col).to_string(),
+                created_at,
+                file_path: field(path_col).to_string(),
+                size_bytes: optional(size_col).and_then(|size| size.parse().ok()),
+                sha256: optional(hash_col).map(str::to_string),
+                file_exists: false,
+                namespace: optional(namespace_col).map(str::to_string),
+            });
+        }
+
+        Ok(Self::new(backups))
+    }
+}
+
+/// 导入或登记备份的结果
+#[derive(Debug, Default)]
+pub struct CatalogImportReport {
+    /// 新登记的备份记录
+    pub imported: Vec<BackupRecord>,
+    /// 跳过的文件及原因
+    pub skipped: Vec<(String, String)>,
+}
+
+/// 备份文件名
+///
+/// 格式为 `backup_{命名空间}_{类型}_v{版本}_{%Y-%m-%d_%H-%M-%S}.tar.gz`，时间为 UTC；
+/// 早期备份的文件名没有命名空间部分
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupFileName {
+    pub namespace: Option<String>,
+    pub backup_type: BackupType,
+    pub service_version: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl std::fmt::Display for BackupFileName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("backup_")?;
+        if let Some(namespace) = &self.namespace {
+            write!(f, "{namespace}_")?;
+        }
+        write!(
+            f,
+            "{}_v{}_{}.tar.gz",
+            self.backup_type.as_str(),
+            self.service_version,
+            self.created_at.format("%Y-%m-%d_%H-%M-%S")
+        )
+    }
+}
+
+/// 从备份文件名解析命名空间、类型、版本和创建时间
+pub fn parse_backup_file_name(file_name: &str) -> Option<BackupFileName> {
+    let stem = file_name.strip_prefix("backup_")?.strip_suffix(".tar.gz")?;
+    let (head, rest) = stem.split_once("_v")?;
+    let (namespace, backup_type) = match head.split_once('_') {
+        Some((namespace, backup_type)) if !namespace.is_empty() => {
+            (Some(namespace.to_string()), backup_type)
+        }
+        Some(_) => return None,
+        None => (None, head),
+    };
+    let backup_type = backup_type.parse().ok()?;
+
+    let mut parts = rest.rsplitn(3, '_');
+    let time = parts.next()?;
+    let date = parts.next()?;
+    let version = parts.next().filter(|version| !version.is_empty())?;
+    let created_at =
+        NaiveDateTime::parse_from_str(&format!("{date}_{time}"), "%Y-%m-%d_%H-%M-%S").ok()?;
+
+    Some(BackupFileName {
+        namespace,
+        backup_type,
+        service_version: version.to_string(),
+        created_at: created_at.and_utc(),
+    })
+}
+
+/// 规范化备份命名空间：只保留字母、数字和 `-`，其余字符替换为 `-`
+///
+/// 命名空间是备份文件名的一部分，不能包含 `_`，否则无法从文件名解析
+pub fn normalize_backup_namespace(name: &str) -> String {
+    let normalized: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let normalized = normalized.trim_matches('-');
+    if normalized.is_empty() {
+        "default".to_string()
+    } else {
+        normalized.to_string()
+    }
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn split_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(anyhow!("CSV 行引号不匹配: {line}"));
+    }
+    fields.push(current);
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(id: i64, file_path: &str) -> BackupCatalogEntry {
+        BackupCatalogEntry {
+            id: Some(id),
+            backup_type: "pre-upgrade".to_string(),
+            service_version: "1.2.3".to_string(),
+            created_at: Utc.with_ymd_and_hms(2025, 3, 1, 8, 30, 0).unwrap(),
+            file_path: file_path.to_string(),
+            size_bytes: Some(1024),
+            sha256: Some("abc123".to_string()),
+            file_exists: true,
+            namespace: Some("prod".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_parse_backup_file_name() {
+        let parsed =
+            parse_backup_file_name("backup_pre-upgrade_v1.2.3_2025-03-01_08-30-00.tar.gz").unwrap();
+        assert_eq!(parsed.namespace, None);
+        assert_eq!(parsed.backup_type, BackupType::PreUpgrade);
+        assert_eq!(parsed.service_version, "1.2.3");
+        assert_eq!(
+            parsed.created_at,
+            Utc.with_ymd_and_hms(2025, 3, 1, 8, 30, 0).unwrap()
+        );
+
+        let named = BackupFileName {
+            namespace: Some("vault-prod".to_string()),
+            ..parsed
+        };
+        let file_name = named.to_string();
+        assert_eq!(
+            file_name,
+            "backup_vault-prod_pre-upgrade_v1.2.3_2025-03-01_08-30-00.tar.gz"
+        );
+        assert_eq!(parse_backup_file_name(&file_name), Some(named));
+
+        assert!(parse_backup_file_name("backup_manual_v1.0.0_2025-03-01.tar.gz").is_none());
+        assert!(parse_backup_file_name("data.tar.gz").is_none());
+        assert!(
+            parse_backup_file_name("backup_weekly_v1.0.0_2025-03-01_08-30-00.tar.gz").is_none()
+        );
+        assert!(
+            parse_backup_file_name("backup__manual_v1.0.0_2025-03-01_08-30-00.tar.gz").is_none()
+        );
+
+        assert_eq!(normalize_backup_namespace("My_Project.1"), "My-Project-1");
+        assert_eq!(normalize_backup_namespace(" _ "), "default");
+    }
+
+    #[test]
+    fn test_catalog_csv_roundtrip() {
+        let catalog = BackupCatalog::new(vec![
+            entry(1, "/backups/backup_a.tar.gz"),
+            entry(2, "/backups/with, \"comma\".tar.gz"),
+        ]);
+
+        let csv = catalog.render(CatalogFormat::Csv).unwrap();
+        let parsed = BackupCatalog::parse(&csv, CatalogFormat::Csv).unwrap();
+
+        assert_eq!(parsed.backups.len(), 2);
+        assert_eq!(
+            parsed.backups[1].file_path,
+            "/backups/with, \"comma\".tar.gz"
+        );
+        assert_eq!(parsed.backups[0].created_at, catalog.backups[0].created_at);
+        assert_eq!(parsed.backups[0].sha256.as_deref(), Some("abc123"));
+        assert_eq!(parsed.backups[0].size_bytes, Some(1024));
+        assert_eq!(parsed.backups[0].namespace.as_deref(), Some("prod"));
+    }
+
+    #[test]
+    fn test_catalog_json_roundtrip() {
+        let catalog = BackupCatalog::new(vec![entry(1, "/backups/backup_a.tar.gz")]);
+
+        let json = catalog.render(CatalogFormat::Json).unwrap();
+        let parsed = BackupCatalog::parse(&json, CatalogFormat::Json).unwrap();
+
+        assert_eq!(parsed.backups, catalog.backups);
+        assert_eq!(
+            parsed.backups[0].parsed_backup_type().unwrap(),
+            BackupType::PreUpgrade
+        );
+        assert_eq!(
+            CatalogFormat::from_path(Path::new("catalog.CSV")),
+            CatalogFormat::Csv
+        );
+    }
+
+    #[test]
+    fn test_catalog_json_images() {
+        let mut catalog = BackupCatalog::new(vec![entry(1, "/backups/backup_a.tar.gz")]);
+        let json = catalog.render(CatalogFormat::Json).unwrap();
+        assert!(!json.contains("\"images\""));
+
+        catalog.images.push(ImageArchiveCatalogEntry {
+            file_path: "/backups/images_v1.2.3_2025-03-01_08-30-00.tar.zst".to_string(),
+            service_version: "1.2.3".to_string(),
+            created_at: Utc.with_ymd_and_hms(2025, 3, 1, 8, 30, 0).unwrap(),
+            size_bytes: Some(2048),
+            sha256: None,
+            images: vec!["mysql:8.0".to_string()],
+        });
+        let json = catalog.render(CatalogFormat::Json).unwrap();
+        let parsed = BackupCatalog::parse(&json, CatalogFormat::Json).unwrap();
+        assert_eq!(parsed.images, catalog.images);
+    }
+}
@@ -0,0 +1,628 @@
+//! # 备份分块去重
+//!
+//! 连续的备份中 data 目录的大部分文件没有变化。开启去重后，文件内容按内容定义分块
+//! （Gear 滚动哈希）切分，分块以 SHA-256 命名、zstd 压缩后存入备份目录下的去重存储，
+//! 备份归档只保存分块清单和符号链接，相同的分块在多个备份之间只保存一份。
+//!
+//! 去重备份仍是 tar.gz 文件，第一个条目为 [`DEDUP_MANIFEST_NAME`]；恢复前先用
+//! [`export_plain_archive`] 还原为普通归档。
+
+use crate::backup_manifest::{append_backup_manifest, parse_backup_manifest_entry};
+use crate::constants::backup::DEDUP_MANIFEST_NAME;
+use crate::error::DuckError;
+use anyhow::Result;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tar::{Archive, Builder, EntryType, Header};
+use tracing::{debug, warn};
+
+/// 最小分块大小
+const MIN_CHUNK_SIZE: usize = 256 * 1024;
+
+/// 最大分块大小
+const MAX_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// 滚动哈希低 20 位为 0 时切分，平均分块约 1MB
+const CHUNK_BOUNDARY_MASK: u64 = (1 << 20) - 1;
+
+/// 分块的 zstd 压缩级别
+const CHUNK_COMPRESSION_LEVEL: i32 = 3;
+
+/// 清单格式版本
+const MANIFEST_FORMAT_VERSION: u32 = 1;
+
+/// 新写入或刚被复用的分块在该时间内不会被清理，避免删除正在进行的备份所需的分块
+pub const GC_GRACE_PERIOD: Duration = Duration::from_secs(3600);
+
+/// 去重备份的分块清单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupManifest {
+    pub version: u32,
+    pub files: Vec<DedupFile>,
+}
+
+/// 清单中的单个文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupFile {
+    /// 归档中的路径
+    pub path: String,
+    pub size: u64,
+    pub mode: u32,
+    /// 文件属主，旧版本创建的清单和非 Unix 平台上没有记录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    pub mtime: u64,
+    /// 按顺序排列的分块哈希
+    pub chunks: Vec<String>,
+}
+
+impl Default for DedupManifest {
+    fn default() -> Self {
+        Self {
+            version: MANIFEST_FORMAT_VERSION,
+            files: Vec::new(),
+        }
+    }
+}
+
+impl DedupManifest {
+    /// 清单引用的所有分块
+    pub fn chunk_hashes(&self) -> HashSet<&str> {
+        self.files
+            .iter()
+            .flat_map(|file| file.chunks.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// 清单中文件的原始总大小
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+}
+
+/// 写入去重存储时的统计
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DedupStats {
+    /// 处理的原始字节数
+    pub total_bytes: u64,
+    /// 新写入的分块数
+    pub new_chunks: usize,
+    /// 新写入分块的原始字节数
+    pub new_bytes: u64,
+    /// 复用已有分块的数量
+    pub reused_chunks: usize,
+}
+
+/// 去重备份的校验结果
+#[derive(Debug, Default)]
+pub struct DedupVerifyReport {
+    /// 校验的分块数量
+    pub chunks: usize,
+    /// 缺失的分块
+    pub missing: Vec<String>,
+    /// 内容与哈希不一致或无法解压的分块
+    pub corrupt: Vec<String>,
+    /// 分块总长度与记录大小不一致的文件
+    pub size_mismatch: Vec<String>,
+}
+
+impl DedupVerifyReport {
+    /// 是否通过校验
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty() && self.size_mismatch.is_empty()
+    }
+}
+
+/// 清理未引用分块的统计
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DedupGcStats {
+    pub removed_chunks: usize,
+    pub freed_bytes: u64,
+}
+
+/// 分块去重存储，分块按哈希前两位分目录存放
+#[derive(Debug, Clone)]
+pub struct DedupStore {
+    root: PathBuf,
+}
+
+impl DedupStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// 存储根目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn chunks_dir(&self) -> PathBuf {
+        self.root.join("chunks")
+    }
+
+    fn chunk_path(&self, hash: &str) -> Result<PathBuf> {
+        if !is_valid_chunk_hash(hash) {
+            return Err(DuckError::Backup(format!("无效的分块哈希: {hash}")).into());
+        }
+        Ok(self.chunks_dir().join(&hash[..2]).join(hash))
+    }
+
+    /// 分块写入存储，返回文件的分块哈希列表
+    pub fn store_file(&self, source: &Path, stats: &mut DedupStats) -> Result<Vec<String>> {
+        let file = File::open(source)
+            .map_err(|e| DuckError::Backup(format!("读取文件失败 {}: {e}", source.display())))?;
+        let mut chunks = Vec::new();
+        split_chunks(file, |data| {
+            chunks.push(self.store_chunk(data, stats)?);
+            Ok(())
+        })?;
+        Ok(chunks)
+    }
+
+    fn store_chunk(&self, data: &[u8], stats: &mut DedupStats) -> Result<String> {
+        let hash = format!("{:x}", Sha256::digest(data));
+        let path = self.chunk_path(&hash)?;
+        stats.total_bytes += data.len() as u64;
+
+        if path.exists() {
+            // 更新修改时间，清理时视为刚被引用
+            let _ = File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            stats.reused_chunks += 1;
+            return Ok(hash);
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let compressed = zstd::bulk::compress(data, CHUNK_COMPRESSION_LEVEL)?;
+        let temp_path = path.with_extension(format!("tmp-{}", std::process::id()));
+        std::fs::write(&temp_path, compressed)?;
+        std::fs::rename(&temp_path, &path)?;
+
+        stats.new_chunks += 1;
+        stats.new_bytes += data.len() as u64;
+        Ok(hash)
+    }
+
+    /// 读取分块并校验哈希
+    pub fn read_chunk(&self, hash: &str) -> Result<Vec<u8>> {
+        let path = self.chunk_path(hash)?;
+        let compressed = std::fs::read(&path)
+            .map_err(|e| DuckError::Backup(format!("读取分块失败 {hash}: {e}")))?;
+        let data = zstd::stream::decode_all(compressed.as_slice())
+            .map_err(|e| DuckError::Backup(format!("解压分块失败 {hash}: {e}")))?;
+        if format!("{:x}", Sha256::digest(&data)) != hash {
+            return Err(DuckError::Backup(format!("分块内容与哈希不一致: {hash}")).into());
+        }
+        Ok(data)
+    }
+
+    /// 按顺序读取文件的全部分块
+    pub fn open_file<'a>(&'a self, chunks: &'a [String]) -> ChunkedFileReader<'a> {
+        ChunkedFileReader {
+            store: self,
+            chunks,
+            next: 0,
+            current: Cursor::new(Vec::new()),
+        }
+    }
+
+    /// 校验清单引用的分块是否齐全、内容是否完整
+    pub fn verify(&self, manifest: &DedupManifest) -> DedupVerifyReport {
+        let mut report = DedupVerifyReport::default();
+        let mut lengths: HashMap<&str, u64> = HashMap::new();
+
+        let mut hashes: Vec<_> = manifest.chunk_hashes().into_iter().collect();
+        hashes.sort_unstable();
+        for hash in hashes {
+            report.chunks += 1;
+            match self.chunk_path(hash) {
+                Ok(path) if !path.exists() => report.missing.push(hash.to_string()),
+                Ok(_) => match self.read_chunk(hash) {
+                    Ok(data) => {
+                        lengths.insert(hash, data.len() as u64);
+                    }
+                    Err(e) => {
+                        debug!("分块校验失败: {}", e);
+                        report.corrupt.push(hash.to_string());
+                    }
+                },
+                Err(_) => report.corrupt.push(hash.to_string()),
+            }
+        }
+
+        for file in &manifest.files {
+            let size: Option<u64> = file
+                .chunks
+                .iter()
+                .map(|hash| lengths.get(hash.as_str()).copied())
+                .sum();
+            if size.is_some_and(|size| size != file.size) {
+                report.size_mismatch.push(file.path.clone());
+            }
+        }
+        report
+    }
+
+    /// 删除未被任何备份引用的分块，`grace` 内写入或复用过的分块保留
+    pub fn gc(&self, referenced: &HashSet<String>, grace: Duration) -> Result<DedupGcStats> {
+        let mut stats = DedupGcStats::default();
+        let chunks_dir = self.chunks_dir();
+        if !chunks_dir.is_dir() {
+            return Ok(stats);
+        }
+        let now = SystemTime::now();
+
+        for prefix_dir in std::fs::read_dir(&chunks_dir)?.flatten() {
+            if !prefix_dir.path().is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(prefix_dir.path())?.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if referenced.contains(&name) {
+                    continue;
+                }
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                let recent = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| now.duration_since(modified).ok())
+                    .is_none_or(|age| age < grace);
+                if recent {
+                    continue;
+                }
+                match std::fs::remove_file(entry.path()) {
+                    Ok(()) => {
+                        stats.removed_chunks += 1;
+                        stats.freed_bytes += metadata.len();
+                    }
+                    Err(e) => warn!("删除分块失败 {}: {}", entry.path().display(), e),
+                }
+            }
+            // 目录为空时删除，失败说明仍有分块
+            let _ = std::fs::remove_dir(prefix_dir.path());
+        }
+
+        Ok(stats)
+    }
+}
+
+/// 顺序读取一个文件所有分块的读取器，每个分块读取时校验哈希
+pub struct ChunkedFileReader<'a> {
+    store: &'a DedupStore,
+    chunks: &'a [String],
+    next: usize,
+    current: Cursor<Vec<u8>>,
+}
+
+impl Read for ChunkedFileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let read = self.current.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            let Some(hash) = self.chunks.get(self.next) else {
+                return Ok(0);
+            };
+            let data = self
+                .store
+                .read_chunk(hash)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            self.current = Cursor::new(data);
+            self.next += 1;
+        }
+    }
+}
+
+/// 读取去重备份的清单，普通归档返回 None
+pub fn read_manifest(archive_path: &Path) -> Result<Option<DedupManifest>> {
+    let mut archive = Archive::new(GzDecoder::new(File::open(archive_path)?));
+    let mut entries = archive.entries()?;
+    let Some(entry) = entries.next() else {
+        return Ok(None);
+    };
+    parse_manifest_entry(entry?)
+}
+
+fn parse_manifest_entry<R: Read>(mut entry: tar::Entry<R>) -> Result<Option<DedupManifest>> {
+    if entry.path()?.as_ref() != Path::new(DEDUP_MANIFEST_NAME) {
+        return Ok(None);
+    }
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    let manifest: DedupManifest = serde_json::from_str(&content)
+        .map_err(|e| DuckError::Backup(format!("解析去重清单失败: {e}")))?;
+    if manifest.version > MANIFEST_FORMAT_VERSION {
+        return Err(DuckError::Backup(format!(
+            "不支持的去重清单版本: {}（当前支持 {}）",
+            manifest.version, MANIFEST_FORMAT_VERSION
+        ))
+        .into());
+    }
+    Ok(Some(manifest))
+}
+
+/// 把清单写入归档，必须是归档的第一个条目
+pub fn append_manifest<W: Write>(builder: &mut Builder<W>, manifest: &DedupManifest) -> Result<()> {
+    let content = serde_json::to_vec(manifest)?;
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Regular);
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default(),
+    );
+    builder
+        .append_data(&mut header, DEDUP_MANIFEST_NAME, content.as_slice())
+        .map_err(|e| DuckError::Backup(format!("写入去重清单失败: {e}")))?;
+    Ok(())
+}
+
+/// 把去重备份还原为普通 tar.gz 归档，文件内容从去重存储读取
+pub fn export_plain_archive(
+    source: &Path,
+    store: &DedupStore,
+    output: &Path,
+    compression: Compression,
+) -> Result<()> {
+    let mut archive = Archive::new(GzDecoder::new(File::open(source)?));
+    let mut entries = archive.entries()?;
+    let manifest = match entries.next() {
+        Some(entry) => parse_manifest_entry(entry?)?,
+        None => None,
+    }
+    .ok_or_else(|| DuckError::Backup(format!("不是去重备份: {}", source.display())))?;
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut builder = Builder::new(GzEncoder::new(File::create(output)?, compression));
+
+    // 格式清单紧跟在去重清单之后，导出时标记为普通备份并放在第一个条目
+    let mut pending = None;
+    if let Some(entry) = entries.next() {
+        let mut entry = entry?;
+        match parse_backup_manifest_entry(&mut entry)? {
+            Some(mut backup_manifest) => {
+                backup_manifest.dedup = false;
+                append_backup_manifest(&mut builder, &backup_manifest)?;
+            }
+            None => pending = Some(entry),
+        }
+    }
+
+    for file in &manifest.files {
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(file.size);
+        header.set_mode(file.mode);
+        header.set_uid(file.uid.unwrap_or_default().into());
+        header.set_gid(file.gid.unwrap_or_default().into());
+        header.set_mtime(file.mtime);
+        builder
+            .append_data(&mut header, &file.path, store.open_file(&file.chunks))
+            .map_err(|e| DuckError::Backup(format!("还原文件失败 {}: {e}", file.path)))?;
+    }
+
+    // 清单之后的条目（符号链接）原样复制
+    for entry in pending.into_iter().map(Ok).chain(entries) {
+        let entry = entry?;
+        let mut header = entry.header().clone();
+        let path = entry.path()?.to_path_buf();
+        if header.entry_type().is_symlink() {
+            let target = entry
+                .link_name()?
+                .ok_or_else(|| DuckError::Backup(format!("符号链接缺少目标: {}", path.display())))?
+                .to_path_buf();
+            builder.append_link(&mut header, &path, &target)?;
+        } else {
+            builder.append_data(&mut header, &path, entry)?;
+        }
+    }
+
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .map_err(|e| anyhow::anyhow!("完成归档失败: {e}"))?;
+    Ok(())
+}
+
+/// 按内容定义分块（Gear 滚动哈希），对每个分块调用 `on_chunk`，返回总字节数
+///
+/// 分块边界只取决于附近的内容，文件中间插入或删除数据只影响相邻的分块
+fn split_chunks<R: Read>(
+    mut reader: R,
+    mut on_chunk: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<u64> {
+    let gear = gear_table();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut chunk = Vec::with_capacity(MAX_CHUNK_SIZE);
+    let mut hash = 0u64;
+    let mut total = 0u64;
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        total += read as u64;
+        for &byte in &buffer[..read] {
+            chunk.push(byte);
+            hash = (hash << 1).wrapping_add(gear[byte as usize]);
+            let at_boundary = chunk.len() >= MIN_CHUNK_SIZE && hash & CHUNK_BOUNDARY_MASK == 0;
+            if at_boundary || chunk.len() >= MAX_CHUNK_SIZE {
+                on_chunk(&chunk)?;
+                chunk.clear();
+                hash = 0;
+            }
+        }
+    }
+    if !chunk.is_empty() {
+        on_chunk(&chunk)?;
+    }
+    Ok(total)
+}
+
+/// Gear 哈希表，由固定种子生成，保证不同版本的分块边界一致
+fn gear_table() -> &'static [u64; 256] {
+    static TABLE: OnceLock<[u64; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        // splitmix64
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        let mut table = [0u64; 256];
+        for value in table.iter_mut() {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            *value = z ^ (z >> 31);
+        }
+        table
+    })
+}
+
+fn is_valid_chunk_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn chunk_hashes(data: &[u8]) -> Vec<String> {
+        let mut hashes = Vec::new();
+        split_chunks(data, |chunk| {
+            assert!(chunk.len() <= MAX_CHUNK_SIZE);
+            hashes.push(format!("{:x}", Sha256::digest(chunk)));
+            Ok(())
+        })
+        .unwrap();
+        hashes
+    }
+
+    #[test]
+    fn test_insertion_only_changes_nearby_chunks() {
+        let original = pseudo_random(8 * 1024 * 1024, 42);
+        let modified = [
+            &original[..3_000_000],
+            b"inserted bytes",
+            &original[3_000_000..],
+        ]
+        .concat();
+
+        let before = chunk_hashes(&original);
+        let after = chunk_hashes(&modified);
+        assert!(before.len() > 2);
+        let before: HashSet<_> = before.into_iter().collect();
+        let changed = after.iter().filter(|hash| !before.contains(*hash)).count();
+        assert!(changed <= 2, "{changed} 个分块发生变化");
+    }
+
+    #[test]
+    fn test_store_export_and_verify() {
+        let dir = TempDir::new().unwrap();
+        let store = DedupStore::new(dir.path().join("store"));
+        let data = pseudo_random(3 * 1024 * 1024, 7);
+        let source = dir.path().join("data.bin");
+        std::fs::write(&source, &data).unwrap();
+
+        let mut stats = DedupStats::default();
+        let first = store.store_file(&source, &mut stats).unwrap();
+        let second = store.store_file(&source, &mut stats).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(stats.reused_chunks, first.len());
+        assert_eq!(stats.new_bytes, data.len() as u64);
+
+        let manifest = DedupManifest {
+            files: vec![DedupFile {
+                path: "data/data.bin".to_string(),
+                size: data.len() as u64,
+                mode: 0o644,
+                uid: None,
+                gid: None,
+                mtime: 0,
+                chunks: first.clone(),
+            }],
+            ..Default::default()
+        };
+        let archive_path = dir.path().join("backup.tar.gz");
+        let mut builder = Builder::new(GzEncoder::new(
+            File::create(&archive_path).unwrap(),
+            Compression::fast(),
+        ));
+        append_manifest(&mut builder, &manifest).unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+
+        assert_eq!(
+            read_manifest(&archive_path).unwrap(),
+            Some(manifest.clone())
+        );
+        assert!(store.verify(&manifest).is_ok());
+
+        let plain_path = dir.path().join("plain.tar.gz");
+        export_plain_archive(&archive_path, &store, &plain_path, Compression::fast()).unwrap();
+        assert_eq!(read_manifest(&plain_path).unwrap(), None);
+        let mut plain = Archive::new(GzDecoder::new(File::open(&plain_path).unwrap()));
+        let mut entry = plain.entries().unwrap().next().unwrap().unwrap();
+        let mut restored = Vec::new();
+        entry.read_to_end(&mut restored).unwrap();
+        assert_eq!(restored, data);
+
+        // 损坏一个分块后校验失败
+        let chunk_path = store.chunk_path(&first[0]).unwrap();
+        std::fs::write(&chunk_path, zstd::bulk::compress(b"broken", 3).unwrap()).unwrap();
+        let report = store.verify(&manifest);
+        assert_eq!(report.corrupt, vec![first[0].clone()]);
+    }
+
+    #[test]
+    fn test_gc_removes_unreferenced_chunks() {
+        let dir = TempDir::new().unwrap();
+        let store = DedupStore::new(dir.path().join("store"));
+        let mut stats = DedupStats::default();
+        let keep = store.store_chunk(b"keep", &mut stats).unwrap();
+        let unused = store.store_chunk(b"unused", &mut stats).unwrap();
+
+        let referenced: HashSet<String> = [keep.clone()].into_iter().collect();
+        let gc = store.gc(&referenced, GC_GRACE_PERIOD).unwrap();
+        assert_eq!(gc.removed_chunks, 0);
+
+        let gc = store.gc(&referenced, Duration::ZERO).unwrap();
+        assert_eq!(gc.removed_chunks, 1);
+        assert!(store.read_chunk(&keep).is_ok());
+        assert!(store.read_chunk(&unused).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! # 备份钩子
+//!
+//! 部分服务（MinIO、Redis 等）在复制数据文件前需要先落盘或暂停写入，备份才能得到一致的数据。
+//! 钩子按服务名配置在 `[backup.hooks.<服务名>]` 中，由 [`crate::backup::BackupManager`]
+//! 在复制文件前后通过 Docker API 在服务的运行中容器内执行：
+//!
+//! - 前置钩子按服务名顺序执行，失败（含超时、非零退出码）时按 `on_failure` 中止或继续备份
+//! - 后置钩子只对前置钩子已执行（或没有前置钩子）的服务执行，按相反顺序执行；备份失败时同样执行，
+//!   失败只记录警告
+//! - 服务没有运行中的容器时跳过该服务的钩子（冷备份时服务均已停止，文件本身已是一致状态）
+
+use crate::config::{BackupHookConfig, BackupHookFailurePolicy};
+use anyhow::Result;
+use bollard::Docker;
+use bollard::container::ListContainersOptions;
+use bollard::exec::{CreateExecOptions, StartExecResults};
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// 钩子输出在日志中最多保留的字符数
+const MAX_OUTPUT_CHARS: usize = 2000;
+
+/// 钩子阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    Pre,
+    Post,
+}
+
+impl HookStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookStage::Pre => "前置",
+            HookStage::Post => "后置",
+        }
+    }
+}
+
+/// 单个钩子命令的执行结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookOutcome {
+    /// 命令执行成功
+    Succeeded,
+    /// 服务没有运行中的容器，未执行
+    Skipped,
+    /// 命令失败、超时或无法执行
+    Failed(String),
+}
+
+/// 前置钩子执行后需要执行后置钩子的服务
+#[derive(Debug, Default)]
+pub struct PreparedHooks {
+    services: Vec<String>,
+}
+
+impl PreparedHooks {
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty()
+    }
+}
+
+/// 备份钩子执行器
+#[derive(Debug, Clone)]
+pub struct BackupHooks {
+    project_name: String,
+    hooks: BTreeMap<String, BackupHookConfig>,
+}
+
+impl BackupHooks {
+    pub fn new(project_name: impl Into<String>, hooks: BTreeMap<String, BackupHookConfig>) -> Self {
+        Self {
+            project_name: project_name.into(),
+            hooks,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// 执行前置钩子
+    ///
+    /// 返回需要执行后置钩子的服务；按失败策略中止时，先对已执行前置钩子的服务执行后置钩子再返回错误
+    pub async fn run_pre(&self) -> Result<PreparedHooks> {
+        let mut prepared = PreparedHooks::default();
+        if self.is_empty() {
+            return Ok(prepared);
+        }
+        let docker = connect_docker()?;
+
+        for (service, hook) in &self.hooks {
+            if hook.pre.is_empty() {
+                prepared.services.push(service.clone());
+                continue;
+            }
+            match self.run_hook(&docker, service, hook, HookStage::Pre).await {
+                HookOutcome::Succeeded => prepared.services.push(service.clone()),
+                HookOutcome::Skipped => {}
+                HookOutcome::Failed(reason) => {
+                    if hook.on_failure == BackupHookFailurePolicy::Continue {
+                        warn!(
+                            "⚠️ 服务 {} 的前置备份钩子失败，按配置继续备份: {}",
+                            service, reason
+                        );
+                        continue;
+                    }
+                    self.run_post(prepared).await;
+                    return Err(anyhow::anyhow!(
+                        "服务 {service} 的前置备份钩子失败，已中止备份: {reason}"
+                    ));
+                }
+            }
+        }
+        Ok(prepared)
+    }
+
+    /// 对前置钩子已执行的服务按相反顺序执行后置钩子，失败只记录警告
+    pub async fn run_post(&self, prepared: PreparedHooks) {
+        if prepared.is_empty() {
+            return;
+        }
+        let docker = match connect_docker() {
+            Ok(docker) => docker,
+            Err(e) => {
+                warn!("⚠️ 无法执行后置备份钩子: {}", e);
+                return;
+            }
+        };
+        for service in prepared.services.iter().rev() {
+            let Some(hook) = self.hooks.get(service) else {
+                continue;
+            };
+            if hook.post.is_empty() {
+                continue;
+            }
+            if let HookOutcome::Failed(reason) =
+                self.run_hook(&docker, service, hook, HookStage::Post).await
+            {
+                warn!("⚠️ 服务 {} 的后置备份钩子失败: {}", service, reason);
+            }
+        }
+    }
+
+    async fn run_hook(
+        &self,
+        docker: &Docker,
+        service: &str,
+        hook: &BackupHookConfig,
+        stage: HookStage,
+    ) -> HookOutcome {
+        let command = match stage {
+            HookStage::Pre => &hook.pre,
+            HookStage::Post => &hook.post,
+        };
+        let container = match find_running_container(docker, &self.project_name, service).await {
+            Ok(Some(container)) => container,
+            Ok(None) => {
+                debug!(
+                    "服务 {} 没有运行中的容器，跳过{}备份钩子",
+                    service,
+                    stage.as_str()
+                );
+                return HookOutcome::Skipped;
+            }
+            Err(e) => return HookOutcome::Failed(format!("查找服务容器失败: {e}")),
+        };
+
+        info!(
+            "🪝 执行服务 {} 的{}备份钩子: {}",
+            service,
+            stage.as_str(),
+            command.join(" ")
+        );
+        let timeout = Duration::from_secs(hook.timeout_secs);
+        match tokio::time::timeout(timeout, exec_command(docker, &container, command)).await {
+            Err(_) => HookOutcome::Failed(format!("执行超过 {} 秒", hook.timeout_secs)),
+            Ok(Err(e)) => HookOutcome::Failed(format!("执行失败: {e}")),
+            Ok(Ok((exit_code, output))) => {
+                let output = truncate_output(&output);
+                match exit_code {
+                    Some(0) => {
+                        if !output.is_empty() {
+                            debug!("钩子输出: {}", output);
+                        }
+                        HookOutcome::Succeeded
+                    }
+                    code => HookOutcome::Failed(format!(
+                        "退出码 {}，输出: {}",
+                        code.map_or_else(|| "未知".to_string(), |code| code.to_string()),
+                        output
+                    )),
+                }
+            }
+        }
+    }
+}
+
+fn connect_docker() -> Result<Docker> {
+    Docker::connect_with_local_defaults().map_err(|e| anyhow::anyhow!("连接 Docker 失败: {e}"))
+}
+
+/// 查找 compose 服务的运行中容器
+async fn find_running_container(
+    docker: &Docker,
+    project_name: &str,
+    service: &str,
+) -> Result<Option<String>> {
+    let mut filters = HashMap::new();
+    filters.insert(
+        "label".to_string(),
+        vec![
+            format!("com.docker.compose.project={project_name}"),
+            format!("com.docker.compose.service={service}"),
+        ],
+    );
+    filters.insert("status".to_string(), vec!["running".to_string()]);
+    let containers = docker
+        .list_containers(Some(ListContainersOptions::<String> {
+            filters,
+            ..Default::default()
+        }))
+        .await?;
+    Ok(containers.into_iter().find_map(|container| container.id))
+}
+
+/// 在容器内执行命令，返回退出码和合并的标准输出、标准错误
+async fn exec_command(
+    docker: &Docker,
+    container: &str,
+    command: &[String],
+) -> Result<(Option<i64>, String)> {
+    let exec = docker
+        .create_exec(
+            container,
+            CreateExecOptions::<String> {
+                cmd: Some(command.to_vec()),
+                attach_stdout: Some(true),
+                attach_stderr: Some(true),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    let mut output = String::new();
+    if let StartExecResults::Attached {
+        output: mut stream, ..
+    } = docker.start_exec(&exec.id, None).await?
+    {
+        while let Some(chunk) = stream.next().await {
+            output.push_str(&chunk?.to_string());
+        }
+    }
+    let inspect = docker.inspect_exec(&exec.id).await?;
+    Ok((inspect.exit_code, output))
+}
+
+/// 钩子输出只保留末尾部分，避免大量输出刷屏
+fn truncate_output(output: &str) -> String {
+    let output = output.trim();
+    let count = output.chars().count();
+    if count <= MAX_OUTPUT_CHARS {
+        return output.to_string();
+    }
+    let tail: String = output.chars().skip(count - MAX_OUTPUT_CHARS).collect();
+    format!("...{tail}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hooks_without_pre_commands_prepare_post_without_docker() {
+        // 只有后置钩子时不执行前置命令，服务直接进入后置钩子列表（Docker 客户端按需连接）
+        let mut hooks = BTreeMap::new();
+        hooks.insert(
+            "minio".to_string(),
+            BackupHookConfig {
+                pre: Vec::new(),
+                post: vec!["true".to_string()],
+                timeout_secs: 5,
+                on_failure: BackupHookFailurePolicy::Abort,
+            },
+        );
+        let hooks = BackupHooks::new("docker", hooks);
+        let prepared = hooks.run_pre().await.unwrap();
+        assert_eq!(prepared.services, vec!["minio".to_string()]);
+
+        let prepared = BackupHooks::new("docker", BTreeMap::new())
+            .run_pre()
+            .await
+            .unwrap();
+        assert!(prepared.is_empty());
+    }
+
+    #[test]
+    fn test_truncate_output_keeps_tail() {
+        assert_eq!(truncate_output("  ok\n"), "ok");
+        let long = "a".repeat(MAX_OUTPUT_CHARS) + "end";
+        let truncated = truncate_output(&long);
+        assert!(truncated.starts_with("..."));
+        assert!(truncated.ends_with("end"));
+        assert_eq!(truncated.chars().count(), MAX_OUTPUT_CHARS + 3);
+    }
+}
@@ -0,0 +1,221 @@
+//! # 备份格式清单
+//!
+//! 每个备份归档都带有 [`BACKUP_MANIFEST_NAME`] 条目，记录归档格式版本、创建备份的客户端、
+//! 包含的路径以及压缩、加密和哈希算法。恢复前先校验清单，新版本客户端创建的备份
+//! 在旧客户端上给出明确的升级提示，而不是解压出不完整的数据。
+//!
+//! 普通备份中清单是第一个条目；去重备份的第一个条目必须是去重清单，格式清单紧随其后。
+//! 引入清单之前创建的备份没有该条目，按格式版本 1 处理。
+
+use crate::backup_permissions::PathPermissions;
+use crate::constants::backup::BACKUP_MANIFEST_NAME;
+use crate::error::DuckError;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use tar::{Archive, Builder, EntryType, Header};
+
+/// 当前写入的备份格式版本，归档结构不兼容地变化时递增
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+/// 归档压缩算法
+pub const CODEC_GZIP: &str = "gzip";
+
+/// 未加密
+pub const ENCRYPTION_NONE: &str = "none";
+
+/// 文件与分块使用的哈希算法
+pub const HASH_SHA256: &str = "sha256";
+
+/// 格式清单最多位于归档的第几个条目（去重清单之后）
+const MANIFEST_MAX_POSITION: usize = 2;
+
+/// 备份格式清单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    /// 创建备份的客户端及版本，如 `nuwax-cli 1.2.0`
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    /// 归档中的顶层路径
+    pub included_paths: Vec<String>,
+    pub codec: String,
+    pub encryption: String,
+    pub hash: String,
+    /// 文件内容是否保存在去重存储中
+    #[serde(default)]
+    pub dedup: bool,
+    /// 文件条目头中的 uid/gid 是否为源文件的属主，旧版本创建的去重备份没有记录属主
+    #[serde(default)]
+    pub preserves_ownership: bool,
+    /// 目录以及带扩展属性的文件的属主、权限和扩展属性，文件的属主和权限记录在条目头中
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<PathPermissions>,
+}
+
+impl BackupManifest {
+    /// 按当前格式创建清单
+    pub fn new(created_by: &str, included_paths: Vec<String>, dedup: bool) -> Self {
+        Self {
+            format_version: BACKUP_FORMAT_VERSION,
+            created_by: created_by.to_string(),
+            created_at: Utc::now(),
+            included_paths,
+            codec: CODEC_GZIP.to_string(),
+            encryption: ENCRYPTION_NONE.to_string(),
+            hash: HASH_SHA256.to_string(),
+            dedup,
+            preserves_ownership: cfg!(unix),
+            permissions: Vec::new(),
+        }
+    }
+
+    /// 记录目录和带扩展属性文件的权限
+    pub fn with_permissions(mut self, permissions: Vec<PathPermissions>) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// 检查当前客户端能否恢复该备份
+    pub fn check_supported(&self) -> Result<(), DuckError> {
+        if self.format_version > BACKUP_FORMAT_VERSION {
+            return Err(DuckError::Backup(format!(
+                "此备份由更新版本的客户端创建（{}，格式版本 {}），当前客户端仅支持格式版本 {}，请先升级 nuwax-cli",
+                self.created_by, self.format_version, BACKUP_FORMAT_VERSION
+            )));
+        }
+        let unsupported = [
+            ("压缩算法", &self.codec, CODEC_GZIP),
+            ("加密方式", &self.encryption, ENCRYPTION_NONE),
+            ("哈希算法", &self.hash, HASH_SHA256),
+        ]
+        .into_iter()
+        .find(|(_, value, supported)| value.as_str() != *supported);
+        if let Some((label, value, _)) = unsupported {
+            return Err(DuckError::Backup(format!(
+                "此备份使用了当前客户端不支持的{label}: {value}（由 {} 创建），请先升级 nuwax-cli",
+                self.created_by
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 把格式清单写入归档
+pub fn append_backup_manifest<W: Write>(
+    builder: &mut Builder<W>,
+    manifest: &BackupManifest,
+) -> Result<()> {
+    let content = serde_json::to_vec_pretty(manifest)?;
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Regular);
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created_at.timestamp().max(0) as u64);
+    builder
+        .append_data(&mut header, BACKUP_MANIFEST_NAME, content.as_slice())
+        .map_err(|e| DuckError::Backup(format!("写入备份清单失败: {e}")))?;
+    Ok(())
+}
+
+/// 条目为格式清单时解析，否则返回 `None` 且不读取条目内容
+pub fn parse_backup_manifest_entry<R: Read>(
+    entry: &mut tar::Entry<R>,
+) -> Result<Option<BackupManifest>> {
+    if !is_backup_manifest_path(&entry.path()?) {
+        return Ok(None);
+    }
+    let mut content = String::new();
+    entry.read_to_string(&mut content)?;
+    let manifest = serde_json::from_str(&content)
+        .map_err(|e| DuckError::Backup(format!("解析备份清单失败: {e}")))?;
+    Ok(Some(manifest))
+}
+
+/// 归档路径是否为格式清单
+pub fn is_backup_manifest_path(path: &Path) -> bool {
+    path == Path::new(BACKUP_MANIFEST_NAME)
+}
+
+/// 读取归档中的格式清单，旧版本创建的备份返回 `None`
+pub fn read_backup_manifest(archive_path: &Path) -> Result<Option<BackupManifest>> {
+    let mut archive = Archive::new(GzDecoder::new(File::open(archive_path)?));
+    for entry in archive.entries()?.take(MANIFEST_MAX_POSITION) {
+        if let Some(manifest) = parse_backup_manifest_entry(&mut entry?)? {
+            return Ok(Some(manifest));
+        }
+    }
+    Ok(None)
+}
+
+/// 读取并校验格式清单，当前客户端无法恢复该备份时返回错误
+pub fn validate_backup_archive(archive_path: &Path) -> Result<Option<BackupManifest>> {
+    let manifest = read_backup_manifest(archive_path)?;
+    if let Some(manifest) = &manifest {
+        manifest.check_supported()?;
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    fn write_archive(path: &Path, manifest: Option<&BackupManifest>) {
+        let mut builder = Builder::new(GzEncoder::new(
+            File::create(path).unwrap(),
+            Compression::fast(),
+        ));
+        if let Some(manifest) = manifest {
+            append_backup_manifest(&mut builder, manifest).unwrap();
+        }
+        let mut header = Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "data/a.txt", b"data".as_slice())
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_read_and_validate_backup_manifest() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let legacy = temp_dir.path().join("legacy.tar.gz");
+        write_archive(&legacy, None);
+        assert!(validate_backup_archive(&legacy).unwrap().is_none());
+
+        let current = temp_dir.path().join("current.tar.gz");
+        let manifest = BackupManifest::new("nuwax-cli 1.0.0", vec!["data".to_string()], false);
+        write_archive(&current, Some(&manifest));
+        assert_eq!(
+            validate_backup_archive(&current).unwrap(),
+            Some(manifest.clone())
+        );
+
+        // 新版本写入的未知字段被忽略，但更高的格式版本必须拒绝
+        let mut value = serde_json::to_value(&manifest).unwrap();
+        value["format_version"] = (BACKUP_FORMAT_VERSION + 1).into();
+        value["future_field"] = "x".into();
+        let newer: BackupManifest = serde_json::from_value(value).unwrap();
+        let newer_path = temp_dir.path().join("newer.tar.gz");
+        write_archive(&newer_path, Some(&newer));
+        let err = validate_backup_archive(&newer_path)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("更新版本的客户端"), "{err}");
+
+        let encrypted = BackupManifest {
+            encryption: "aes-256-gcm".to_string(),
+            ..manifest
+        };
+        assert!(encrypted.check_supported().is_err());
+    }
+}
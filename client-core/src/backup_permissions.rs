@@ -0,0 +1,295 @@
+//! # 备份中的属主、权限和扩展属性
+//!
+//! 普通文件的 uid、gid 和权限记录在归档条目头中（去重备份记录在分块清单中）；归档不包含目录条目，
+//! 目录以及带扩展属性的文件的 uid、gid、权限和扩展属性记录在备份格式清单的 `permissions` 中。
+//! 在 Unix 上恢复时按记录设置，MySQL、MinIO 等数据目录恢复后无需再按固定规则修正权限。
+//!
+//! 只有 root 用户能修改属主，其他用户恢复时跳过属主。目标主机上服务用户的 uid/gid 与备份主机
+//! 不同时，通过 `[backup.restore_ownership]` 的 `uid_map` / `gid_map` 映射。
+
+use crate::config::RestoreOwnershipConfig;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::{debug, warn};
+
+/// 单个路径的属主、权限和扩展属性
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathPermissions {
+    /// 归档中的路径
+    pub path: String,
+    pub uid: u32,
+    pub gid: u32,
+    /// 权限位，包括 setuid、setgid 和 sticky 位
+    pub mode: u32,
+    /// 扩展属性，值为 base64 编码
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub xattrs: BTreeMap<String, String>,
+}
+
+impl PathPermissions {
+    /// 读取路径（不跟随符号链接）的属主、权限和扩展属性，非 Unix 平台或读取失败时返回 None
+    #[cfg(unix)]
+    pub fn read(path: &Path, archive_path: &str) -> Option<Self> {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = match std::fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                debug!("读取权限失败: {} - {}", path.display(), e);
+                return None;
+            }
+        };
+        Some(Self {
+            path: archive_path.to_string(),
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+            mode: metadata.mode() & 0o7777,
+            xattrs: read_xattrs(path),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn read(_path: &Path, _archive_path: &str) -> Option<Self> {
+        None
+    }
+}
+
+/// 恢复时的属主处理方式
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OwnershipMapping {
+    /// 为 true 时不恢复属主，恢复的文件属于当前用户
+    pub skip_owner: bool,
+    pub uid_map: BTreeMap<u32, u32>,
+    pub gid_map: BTreeMap<u32, u32>,
+}
+
+impl OwnershipMapping {
+    /// 从 `[backup.restore_ownership]` 配置创建，映射格式错误时返回错误
+    pub fn from_config(config: &RestoreOwnershipConfig) -> Result<Self> {
+        Ok(Self {
+            skip_owner: !config.preserve,
+            uid_map: parse_id_map(&config.uid_map, "uid_map")?,
+            gid_map: parse_id_map(&config.gid_map, "gid_map")?,
+        })
+    }
+
+    pub fn map_uid(&self, uid: u32) -> u32 {
+        self.uid_map.get(&uid).copied().unwrap_or(uid)
+    }
+
+    pub fn map_gid(&self, gid: u32) -> u32 {
+        self.gid_map.get(&gid).copied().unwrap_or(gid)
+    }
+
+    /// 是否会修改属主：未关闭属主恢复且以 root 用户运行
+    pub fn changes_owner(&self) -> bool {
+        #[cfg(unix)]
+        {
+            !self.skip_owner && unsafe { libc::geteuid() } == 0
+        }
+        #[cfg(not(unix))]
+        {
+            false
+        }
+    }
+
+    /// 按映射设置路径（不跟随符号链接）的属主，不修改属主时直接返回
+    pub fn apply_owner(&self, path: &Path, uid: u32, gid: u32) -> std::io::Result<()> {
+        if !self.changes_owner() {
+            return Ok(());
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::lchown(path, Some(self.map_uid(uid)), Some(self.map_gid(gid)))?;
+        #[cfg(not(unix))]
+        let _ = (path, uid, gid);
+        Ok(())
+    }
+}
+
+/// 解析 `from:to` 形式的 id 映射
+fn parse_id_map(entries: &[String], field: &str) -> Result<BTreeMap<u32, u32>> {
+    entries
+        .iter()
+        .map(|entry| {
+            entry
+                .split_once(':')
+                .and_then(|(from, to)| Some((from.trim().parse().ok()?, to.trim().parse().ok()?)))
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "backup.restore_ownership.{field} 中的映射无效: {entry}（应为 备份中的id:恢复后的id，如 999:1001）"
+                    )
+                })
+        })
+        .collect()
+}
+
+/// 恢复属主、权限和扩展属性的统计
+#[derive(Debug, Default)]
+pub struct PermissionRestoreStats {
+    /// 已按记录设置的路径数
+    pub applied: usize,
+    /// 设置失败的路径数
+    pub failed: usize,
+}
+
+/// 按清单中的记录设置 `target_dir` 下已恢复路径的属主、权限和扩展属性
+///
+/// 只处理 `selected` 返回 true 且已存在的路径；在文件全部解压后调用，
+/// 避免目录先被设为只读导致其中的文件无法写入
+pub fn restore_path_permissions(
+    target_dir: &Path,
+    permissions: &[PathPermissions],
+    mapping: &OwnershipMapping,
+    selected: impl Fn(&str) -> bool,
+) -> PermissionRestoreStats {
+    let mut stats = PermissionRestoreStats::default();
+    for record in permissions.iter().filter(|record| selected(&record.path)) {
+        let path = target_dir.join(&record.path);
+        if std::fs::symlink_metadata(&path).is_err() {
+            continue;
+        }
+        match apply_path_permissions(&path, record, mapping) {
+            Ok(()) => stats.applied += 1,
+            Err(e) => {
+                debug!("恢复权限失败: {} - {}", path.display(), e);
+                stats.failed += 1;
+            }
+        }
+    }
+    if stats.failed > 0 {
+        warn!(
+            "⚠️ {} 个路径的属主、权限或扩展属性未能恢复（扩展属性需要文件系统支持，security.* 需要 root 权限）",
+            stats.failed
+        );
+    }
+    stats
+}
+
+#[cfg(unix)]
+fn apply_path_permissions(
+    path: &Path,
+    record: &PathPermissions,
+    mapping: &OwnershipMapping,
+) -> std::io::Result<()> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    use std::os::unix::fs::PermissionsExt;
+
+    // 先设置属主：修改属主会清除 setuid/setgid 位
+    mapping.apply_owner(path, record.uid, record.gid)?;
+    if !std::fs::symlink_metadata(path)?.file_type().is_symlink() {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(record.mode))?;
+    }
+    for (name, value) in &record.xattrs {
+        let value = STANDARD
+            .decode(value)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        xattr::set(path, name, &value)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn apply_path_permissions(
+    _path: &Path,
+    _record: &PathPermissions,
+    _mapping: &OwnershipMapping,
+) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// 读取路径的扩展属性，文件系统不支持时返回空
+#[cfg(unix)]
+fn read_xattrs(path: &Path) -> BTreeMap<String, String> {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+    let Ok(names) = xattr::list(path) else {
+        return BTreeMap::new();
+    };
+    names
+        .filter_map(|name| {
+            let value = xattr::get(path, &name).ok()??;
+            Some((name.to_string_lossy().to_string(), STANDARD.encode(value)))
+        })
+        .collect()
+}
+
+/// 把源文件的属主和扩展属性复制到暂存副本，权限已由复制保留；无权修改属主时只记录调试日志
+#[cfg(unix)]
+pub fn copy_ownership(source: &Path, copy: &Path) {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    use std::os::unix::fs::MetadataExt;
+
+    let owned = std::fs::symlink_metadata(source).and_then(|metadata| {
+        std::os::unix::fs::lchown(copy, Some(metadata.uid()), Some(metadata.gid()))
+    });
+    if let Err(e) = owned {
+        debug!("暂存副本保留属主失败: {} - {}", copy.display(), e);
+    }
+    for (name, value) in read_xattrs(source) {
+        if let Ok(value) = STANDARD.decode(value) {
+            let _ = xattr::set(copy, &name, &value);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn copy_ownership(_source: &Path, _copy: &Path) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ownership_mapping_from_config() {
+        let config = RestoreOwnershipConfig {
+            preserve: true,
+            uid_map: vec!["999:1001".to_string()],
+            gid_map: vec![" 999 : 1002 ".to_string()],
+        };
+        let mapping = OwnershipMapping::from_config(&config).unwrap();
+        assert_eq!(mapping.map_uid(999), 1001);
+        assert_eq!(mapping.map_uid(0), 0);
+        assert_eq!(mapping.map_gid(999), 1002);
+
+        let invalid = RestoreOwnershipConfig {
+            uid_map: vec!["mysql:1001".to_string()],
+            ..config
+        };
+        let err = OwnershipMapping::from_config(&invalid)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("uid_map"), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_restore_path_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let source = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir(source.path().join("mysql")).unwrap();
+        std::fs::set_permissions(
+            source.path().join("mysql"),
+            std::fs::Permissions::from_mode(0o750),
+        )
+        .unwrap();
+        let record = PathPermissions::read(&source.path().join("mysql"), "data/mysql").unwrap();
+        assert_eq!(record.mode, 0o750);
+
+        let target = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(target.path().join("data/mysql")).unwrap();
+        let stats = restore_path_permissions(
+            target.path(),
+            &[record],
+            &OwnershipMapping::default(),
+            |path| path.starts_with("data/"),
+        );
+        assert_eq!(stats.applied, 1);
+        let mode = std::fs::metadata(target.path().join("data/mysql"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, 0o750);
+    }
+}
@@ -0,0 +1,428 @@
+//! # 文件系统快照
+//!
+//! 升级前备份的暂存方式为 `snapshot` 时，服务停止后为数据目录创建只读的文件系统快照，
+//! 快照创建完成即可继续升级，压缩在后台从快照中读取，完成后删除快照。支持的快照方式：
+//!
+//! - btrfs：数据目录需为独立子卷，快照创建在数据目录旁的 `.nuwax-backup-<时间>` 子卷中
+//! - ZFS：数据目录位于 ZFS 数据集内，通过数据集挂载点下的 `.zfs/snapshot/<快照名>` 读取
+//! - LVM：数据目录所在逻辑卷创建快照卷，只读挂载到临时目录后读取，需要 root 权限
+//!
+//! 快照方式通过 [`SnapshotProvider`] 接入，命令行工具不存在或目录不满足条件时返回 None，
+//! 调用方改为复制文件。快照中指向数据目录之外的符号链接不会备份。
+
+use crate::config::{BackupSnapshotConfig, SnapshotProviderKind};
+use anyhow::Result;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+/// 文件系统快照方式
+pub trait SnapshotProvider: Send + Sync + fmt::Debug {
+    /// 快照方式名称，用于日志
+    fn name(&self) -> &'static str;
+
+    /// 为目录创建只读快照，返回快照中该目录的可读路径和删除快照所需的标识
+    fn create(&self, source: &Path, name: &str) -> Result<SnapshotHandle>;
+
+    /// 删除快照
+    fn remove(&self, handle: &SnapshotHandle) -> Result<()>;
+}
+
+/// 快照方式创建快照后返回的信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotHandle {
+    /// 快照中源目录的可读路径
+    pub path: PathBuf,
+    /// 删除快照时使用的标识（子卷路径、快照全名或快照卷）
+    pub id: String,
+    /// 快照挂载目录，删除快照前需要卸载
+    pub mount_dir: Option<PathBuf>,
+}
+
+/// 已创建的文件系统快照
+#[derive(Debug)]
+pub struct FilesystemSnapshot {
+    provider: Arc<dyn SnapshotProvider>,
+    /// 创建快照的源目录
+    pub source: PathBuf,
+    handle: SnapshotHandle,
+}
+
+impl FilesystemSnapshot {
+    /// 快照中源目录的可读路径
+    pub fn path(&self) -> &Path {
+        &self.handle.path
+    }
+
+    /// 删除快照，失败只记录警告（快照需手动清理）
+    pub fn remove(self) {
+        match self.provider.remove(&self.handle) {
+            Ok(()) => debug!("已删除 {} 快照: {}", self.provider.name(), self.handle.id),
+            Err(e) => warn!(
+                "⚠️ 删除 {} 快照 {} 失败，请手动清理: {}",
+                self.provider.name(),
+                self.handle.id,
+                e
+            ),
+        }
+    }
+}
+
+/// 为目录创建快照，目录不支持快照时返回 None
+pub fn create_snapshot(
+    source: &Path,
+    name: &str,
+    config: &BackupSnapshotConfig,
+) -> Option<FilesystemSnapshot> {
+    let source = source.canonicalize().ok()?;
+    let candidates: Vec<Arc<dyn SnapshotProvider>> = match config.provider {
+        SnapshotProviderKind::Auto => vec![
+            Arc::new(BtrfsSnapshot),
+            Arc::new(ZfsSnapshot),
+            Arc::new(LvmSnapshot::new(config.lvm_size.clone())),
+        ],
+        SnapshotProviderKind::Btrfs => vec![Arc::new(BtrfsSnapshot)],
+        SnapshotProviderKind::Zfs => vec![Arc::new(ZfsSnapshot)],
+        SnapshotProviderKind::Lvm => vec![Arc::new(LvmSnapshot::new(config.lvm_size.clone()))],
+    };
+
+    for provider in candidates {
+        match provider.create(&source, name) {
+            Ok(handle) => {
+                info!(
+                    "📸 已创建 {} 快照: {} -> {}",
+                    provider.name(),
+                    source.display(),
+                    handle.path.display()
+                );
+                return Some(FilesystemSnapshot {
+                    provider,
+                    source,
+                    handle,
+                });
+            }
+            Err(e) => debug!("{} 快照不可用: {}", provider.name(), e),
+        }
+    }
+    None
+}
+
+/// btrfs 子卷快照
+#[derive(Debug)]
+pub struct BtrfsSnapshot;
+
+impl SnapshotProvider for BtrfsSnapshot {
+    fn name(&self) -> &'static str {
+        "btrfs"
+    }
+
+    fn create(&self, source: &Path, name: &str) -> Result<SnapshotHandle> {
+        // 只有子卷才能创建快照，非子卷目录 show 会报错
+        run_command("btrfs", &[os("subvolume"), os("show"), source.as_os_str()])?;
+        let parent = source
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("数据目录没有上级目录"))?;
+        let snapshot = parent.join(format!(".{name}"));
+        run_command(
+            "btrfs",
+            &[
+                os("subvolume"),
+                os("snapshot"),
+                os("-r"),
+                source.as_os_str(),
+                snapshot.as_os_str(),
+            ],
+        )?;
+        Ok(SnapshotHandle {
+            path: snapshot.clone(),
+            id: snapshot.to_string_lossy().to_string(),
+            mount_dir: None,
+        })
+    }
+
+    fn remove(&self, handle: &SnapshotHandle) -> Result<()> {
+        run_command("btrfs", &[os("subvolume"), os("delete"), os(&handle.id)])?;
+        Ok(())
+    }
+}
+
+/// ZFS 数据集快照
+#[derive(Debug)]
+pub struct ZfsSnapshot;
+
+impl SnapshotProvider for ZfsSnapshot {
+    fn name(&self) -> &'static str {
+        "zfs"
+    }
+
+    fn create(&self, source: &Path, name: &str) -> Result<SnapshotHandle> {
+        let output = run_command(
+            "zfs",
+            &[
+                os("list"),
+                os("-H"),
+                os("-o"),
+                os("name,mountpoint"),
+                source.as_os_str(),
+            ],
+        )?;
+        let (dataset, mountpoint) = parse_zfs_list(&output)
+            .ok_or_else(|| anyhow::anyhow!("无法解析 zfs list 输出: {output}"))?;
+        let relative = source
+            .strip_prefix(&mountpoint)
+            .map_err(|_| anyhow::anyhow!("数据目录不在数据集挂载点 {} 下", mountpoint.display()))?;
+
+        let id = format!("{dataset}@{name}");
+        run_command("zfs", &[os("snapshot"), os(&id)])?;
+        Ok(SnapshotHandle {
+            path: mountpoint
+                .join(".zfs")
+                .join("snapshot")
+                .join(name)
+                .join(relative),
+            id,
+            mount_dir: None,
+        })
+    }
+
+    fn remove(&self, handle: &SnapshotHandle) -> Result<()> {
+        run_command("zfs", &[os("destroy"), os(&handle.id)])?;
+        Ok(())
+    }
+}
+
+/// LVM 逻辑卷快照
+#[derive(Debug)]
+pub struct LvmSnapshot {
+    size: String,
+}
+
+impl LvmSnapshot {
+    pub fn new(size: String) -> Self {
+        Self { size }
+    }
+}
+
+impl SnapshotProvider for LvmSnapshot {
+    fn name(&self) -> &'static str {
+        "lvm"
+    }
+
+    fn create(&self, source: &Path, name: &str) -> Result<SnapshotHandle> {
+        let output = run_command(
+            "findmnt",
+            &[
+                os("-n"),
+                os("-o"),
+                os("SOURCE,TARGET,FSTYPE"),
+                os("--target"),
+                source.as_os_str(),
+            ],
+        )?;
+        let mount = parse_findmnt(&output)
+            .ok_or_else(|| anyhow::anyhow!("无法解析 findmnt 输出: {output}"))?;
+        let output = run_command(
+            "lvs",
+            &[
+                os("--noheadings"),
+                os("-o"),
+                os("vg_name,lv_name"),
+                os(&mount.device),
+            ],
+        )?;
+        let mut fields = output.split_whitespace();
+        let (Some(vg), Some(lv)) = (fields.next(), fields.next()) else {
+            return Err(anyhow::anyhow!("{} 不是 LVM 逻辑卷", mount.device));
+        };
+        let relative = source
+            .strip_prefix(&mount.target)
+            .map_err(|_| anyhow::anyhow!("数据目录不在挂载点 {} 下", mount.target.display()))?;
+
+        let id = format!("{vg}/{lv}-{name}");
+        run_command(
+            "lvcreate",
+            &[
+                os("--snapshot"),
+                os("--name"),
+                os(&format!("{lv}-{name}")),
+                os("--size"),
+                os(&self.size),
+                os(&format!("{vg}/{lv}")),
+            ],
+        )?;
+
+        let mount_dir = std::env::temp_dir().join(name);
+        let mounted = std::fs::create_dir_all(&mount_dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| {
+                // XFS 拒绝挂载与原卷 UUID 相同的文件系统
+                let options = if mount.fstype == "xfs" {
+                    "ro,nouuid"
+                } else {
+                    "ro"
+                };
+                run_command(
+                    "mount",
+                    &[
+                        os("-o"),
+                        os(options),
+                        os(&format!("/dev/{id}")),
+                        mount_dir.as_os_str(),
+                    ],
+                )
+            });
+        if let Err(e) = mounted {
+            let _ = run_command("lvremove", &[os("-f"), os(&id)]);
+            let _ = std::fs::remove_dir(&mount_dir);
+            return Err(e);
+        }
+
+        Ok(SnapshotHandle {
+            path: mount_dir.join(relative),
+            id,
+            mount_dir: Some(mount_dir),
+        })
+    }
+
+    fn remove(&self, handle: &SnapshotHandle) -> Result<()> {
+        if let Some(mount_dir) = &handle.mount_dir {
+            run_command("umount", &[mount_dir.as_os_str()])?;
+            let _ = std::fs::remove_dir(mount_dir);
+        }
+        run_command("lvremove", &[os("-f"), os(&handle.id)])?;
+        Ok(())
+    }
+}
+
+/// `findmnt -o SOURCE,TARGET,FSTYPE` 的一行输出
+#[derive(Debug, PartialEq, Eq)]
+struct MountInfo {
+    device: String,
+    target: PathBuf,
+    fstype: String,
+}
+
+fn parse_findmnt(output: &str) -> Option<MountInfo> {
+    let mut fields = output.lines().next()?.split_whitespace();
+    Some(MountInfo {
+        device: fields.next()?.to_string(),
+        target: PathBuf::from(fields.next()?),
+        fstype: fields.next()?.to_string(),
+    })
+}
+
+/// 解析 `zfs list -H -o name,mountpoint` 的输出（制表符分隔）
+fn parse_zfs_list(output: &str) -> Option<(String, PathBuf)> {
+    let (dataset, mountpoint) = output.lines().next()?.split_once('\t')?;
+    let mountpoint = mountpoint.trim();
+    if !mountpoint.starts_with('/') {
+        // legacy、none 等挂载方式无法通过 .zfs 目录读取快照
+        return None;
+    }
+    Some((dataset.to_string(), PathBuf::from(mountpoint)))
+}
+
+fn os(value: &str) -> &std::ffi::OsStr {
+    std::ffi::OsStr::new(value)
+}
+
+/// 执行命令，返回标准输出；命令不存在或退出码非零时返回错误
+fn run_command(program: &str, args: &[&std::ffi::OsStr]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow::anyhow!("无法执行 {program}: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "{program} 执行失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_parse_command_output() {
+        assert_eq!(
+            parse_findmnt("/dev/mapper/vg0-data /srv xfs\n"),
+            Some(MountInfo {
+                device: "/dev/mapper/vg0-data".to_string(),
+                target: PathBuf::from("/srv"),
+                fstype: "xfs".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_zfs_list("tank/nuwax\t/tank/nuwax\n"),
+            Some(("tank/nuwax".to_string(), PathBuf::from("/tank/nuwax")))
+        );
+        assert_eq!(parse_zfs_list("tank/nuwax\tlegacy\n"), None);
+    }
+
+    /// 把目录复制到快照目录的模拟快照方式
+    #[derive(Debug, Default)]
+    struct CopySnapshot {
+        removed: Mutex<Vec<String>>,
+    }
+
+    impl SnapshotProvider for CopySnapshot {
+        fn name(&self) -> &'static str {
+            "copy"
+        }
+
+        fn create(&self, source: &Path, name: &str) -> Result<SnapshotHandle> {
+            let snapshot = source.parent().unwrap().join(name);
+            std::fs::create_dir_all(&snapshot)?;
+            for entry in std::fs::read_dir(source)? {
+                let entry = entry?;
+                std::fs::copy(entry.path(), snapshot.join(entry.file_name()))?;
+            }
+            Ok(SnapshotHandle {
+                path: snapshot.clone(),
+                id: name.to_string(),
+                mount_dir: None,
+            })
+        }
+
+        fn remove(&self, handle: &SnapshotHandle) -> Result<()> {
+            std::fs::remove_dir_all(&handle.path)?;
+            self.removed.lock().unwrap().push(handle.id.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_snapshot_is_removed_through_provider() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let data = temp.path().join("data");
+        std::fs::create_dir_all(&data).unwrap();
+        std::fs::write(data.join("ibdata1"), "v1").unwrap();
+
+        let provider = Arc::new(CopySnapshot::default());
+        let handle = provider.create(&data, "nuwax-backup-test").unwrap();
+        let snapshot = FilesystemSnapshot {
+            provider: provider.clone(),
+            source: data.clone(),
+            handle,
+        };
+        std::fs::write(data.join("ibdata1"), "v2").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(snapshot.path().join("ibdata1")).unwrap(),
+            "v1"
+        );
+
+        let path = snapshot.path().to_path_buf();
+        snapshot.remove();
+        assert!(!path.exists());
+        assert_eq!(
+            *provider.removed.lock().unwrap(),
+            vec!["nuwax-backup-test".to_string()]
+        );
+    }
+}
@@ -0,0 +1,508 @@
+//! # 备份存储后端
+//!
+//! 备份先在本地存储目录（`backup.storage_dir`）中生成，配置了 `[backup.storage]` 时再保存到
+//! [`BackupStorage`] 后端，并删除本地副本：
+//! - [`LocalDirStorage`]：本地目录，适用于已挂载的 NFS/SMB 共享
+//! - [`SftpStorage`]：通过系统自带的 `ssh` / `scp` 保存到远程主机
+//! - [`WebDavStorage`]：通过 HTTP `PUT` / `GET` / `PROPFIND` / `DELETE` 保存到 WebDAV 服务
+//!
+//! 备份记录中的文件路径仍指向本地存储目录，后端按文件名保存备份；
+//! 恢复、校验时按文件名取回到本地，用完即删除。
+
+use crate::config::BackupStorageConfig;
+use crate::remote::{RemoteHost, SshTarget, shell_quote};
+use anyhow::{Context, Result, anyhow};
+use futures::StreamExt;
+use futures::future::{BoxFuture, FutureExt};
+use regex::Regex;
+use reqwest::{Method, StatusCode};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+/// 上传过程中使用的临时文件后缀，上传完成后再改名，避免留下不完整的备份
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// 备份存储后端，按文件名保存备份文件
+pub trait BackupStorage: fmt::Debug + Send + Sync {
+    /// 存储位置描述，用于日志和列表显示
+    fn location(&self) -> String;
+
+    /// 保存本地备份文件，已存在同名文件时覆盖
+    fn upload<'a>(&'a self, local_path: &'a Path, name: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    /// 取回备份文件到本地路径
+    fn download<'a>(&'a self, name: &'a str, local_path: &'a Path) -> BoxFuture<'a, Result<()>>;
+
+    /// 列出已保存的备份文件名
+    fn list(&self) -> BoxFuture<'_, Result<Vec<String>>>;
+
+    /// 删除备份文件，文件不存在时不报错
+    fn delete<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<()>>;
+}
+
+/// 根据配置创建存储后端，未配置时返回 None（备份只保存在本地存储目录）
+pub fn backup_storage_from_config(
+    config: Option<&BackupStorageConfig>,
+) -> Result<Option<Arc<dyn BackupStorage>>> {
+    let Some(config) = config else {
+        return Ok(None);
+    };
+    let storage: Arc<dyn BackupStorage> = match config {
+        BackupStorageConfig::Local { path } => Arc::new(LocalDirStorage::new(path)),
+        BackupStorageConfig::Sftp {
+            target,
+            remote_dir,
+            identity_file,
+        } => {
+            let target: SshTarget = target.parse()?;
+            let identity_file = identity_file
+                .as_deref()
+                .map(|path| PathBuf::from(shellexpand::tilde(path).as_ref()));
+            Arc::new(SftpStorage::new(
+                RemoteHost::new(target).with_identity_file(identity_file),
+                remote_dir.clone(),
+            ))
+        }
+        BackupStorageConfig::Webdav {
+            url,
+            username,
+            password_env,
+        } => {
+            let password = match password_env {
+                Some(name) => Some(
+                    std::env::var(name)
+                        .map_err(|_| anyhow!("WebDAV 备份存储的密码环境变量 {name} 未设置"))?,
+                ),
+                None => None,
+            };
+            Arc::new(WebDavStorage::new(url, username.clone(), password)?)
+        }
+    };
+    Ok(Some(storage))
+}
+
+/// 是否为上传中的临时文件
+fn is_partial_name(name: &str) -> bool {
+    name.ends_with(PARTIAL_SUFFIX)
+}
+
+/// 本地目录存储，目录可以是已挂载的网络共享
+#[derive(Debug, Clone)]
+pub struct LocalDirStorage {
+    dir: PathBuf,
+}
+
+impl LocalDirStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl BackupStorage for LocalDirStorage {
+    fn location(&self) -> String {
+        self.dir.display().to_string()
+    }
+
+    fn upload<'a>(&'a self, local_path: &'a Path, name: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            tokio::fs::create_dir_all(&self.dir)
+                .await
+                .with_context(|| format!("无法创建备份存储目录: {}", self.dir.display()))?;
+            let partial = self.dir.join(format!("{name}{PARTIAL_SUFFIX}"));
+            if let Err(e) = tokio::fs::copy(local_path, &partial).await {
+                let _ = tokio::fs::remove_file(&partial).await;
+                return Err(anyhow!("复制备份到 {} 失败: {e}", self.dir.display()));
+            }
+            tokio::fs::rename(&partial, self.dir.join(name)).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn download<'a>(&'a self, name: &'a str, local_path: &'a Path) -> BoxFuture<'a, Result<()>> {
+        async move {
+            let source = self.dir.join(name);
+            tokio::fs::copy(&source, local_path)
+                .await
+                .with_context(|| format!("无法读取备份文件: {}", source.display()))?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        async move {
+            let mut names = Vec::new();
+            let mut dir = match tokio::fs::read_dir(&self.dir).await {
+                Ok(dir) => dir,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+                Err(e) => return Err(e.into()),
+            };
+            while let Some(entry) = dir.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if entry.file_type().await?.is_file() && !is_partial_name(&name) {
+                    names.push(name);
+                }
+            }
+            Ok(names)
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            match tokio::fs::remove_file(self.dir.join(name)).await {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            }
+        }
+        .boxed()
+    }
+}
+
+/// 通过 SSH 保存到远程主机的目录，认证依赖 SSH 密钥或 ssh-agent
+#[derive(Debug, Clone)]
+pub struct SftpStorage {
+    host: RemoteHost,
+    remote_dir: String,
+}
+
+impl SftpStorage {
+    pub fn new(host: RemoteHost, remote_dir: impl Into<String>) -> Self {
+        Self {
+            host,
+            remote_dir: remote_dir.into(),
+        }
+    }
+
+    fn remote_path(&self, name: &str) -> String {
+        format!("{}/{name}", self.remote_dir.trim_end_matches('/'))
+    }
+
+    /// 执行远程命令，返回输出行；退出码非 0 时返回错误
+    async fn run(&self, command: &str) -> Result<Vec<String>> {
+        let mut lines = Vec::new();
+        let code = self
+            .host
+            .run(command, |line| lines.push(line.to_string()))
+            .await?;
+        if code != 0 {
+            return Err(anyhow!(
+                "[{}] 远程命令执行失败 (退出码 {code}): {}",
+                self.host.target(),
+                lines.join(" ")
+            ));
+        }
+        Ok(lines)
+    }
+}
+
+impl BackupStorage for SftpStorage {
+    fn location(&self) -> String {
+        format!("{}:{}", self.host.target(), self.remote_dir)
+    }
+
+    fn upload<'a>(&'a self, local_path: &'a Path, name: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            let path = self.remote_path(name);
+            let partial = format!("{path}{PARTIAL_SUFFIX}");
+            self.run(&format!("mkdir -p {}", shell_quote(&self.remote_dir)))
+                .await?;
+            self.host.upload(local_path, &partial).await?;
+            self.run(&format!(
+                "mv -f {} {}",
+                shell_quote(&partial),
+                shell_quote(&path)
+            ))
+            .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn download<'a>(&'a self, name: &'a str, local_path: &'a Path) -> BoxFuture<'a, Result<()>> {
+        async move {
+            self.host
+                .download(&self.remote_path(name), local_path)
+                .await
+        }
+        .boxed()
+    }
+
+    fn list(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        async move {
+            let dir = shell_quote(&self.remote_dir);
+            let lines = self
+                .run(&format!("if [ -d {dir} ]; then ls -1A {dir}; fi"))
+                .await?;
+            Ok(lines
+                .into_iter()
+                .filter(|name| !name.is_empty() && !is_partial_name(name))
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            self.run(&format!(
+                "rm -f -- {}",
+                shell_quote(&self.remote_path(name))
+            ))
+            .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// WebDAV 存储，备份保存在 `url` 指向的集合（目录）中
+#[derive(Debug, Clone)]
+pub struct WebDavStorage {
+    client: reqwest::Client,
+    base_url: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl WebDavStorage {
+    pub fn new(url: &str, username: Option<String>, password: Option<String>) -> Result<Self> {
+        let parsed =
+            reqwest::Url::parse(url).map_err(|e| anyhow!("无效的 WebDAV 地址 {url}: {e}"))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(anyhow!("WebDAV 地址必须以 http:// 或 https:// 开头: {url}"));
+        }
+        Ok(Self {
+            client: reqwest::Client::new(),
+            base_url: format!("{}/", url.trim_end_matches('/')),
+            username,
+            password,
+        })
+    }
+
+    fn file_url(&self, name: &str) -> String {
+        format!("{}{}", self.base_url, percent_encode(name))
+    }
+
+    fn request(&self, method: Method, url: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_deref()),
+            None => request,
+        }
+    }
+
+    /// 发送请求，状态码不在 `allowed` 中且不是成功状态时返回错误
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        allowed: &[StatusCode],
+    ) -> Result<reqwest::Response> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() || allowed.contains(&status) {
+            Ok(response)
+        } else {
+            Err(anyhow!(
+                "WebDAV 请求失败 ({}): {status}",
+                response.url().clone()
+            ))
+        }
+    }
+}
+
+impl BackupStorage for WebDavStorage {
+    fn location(&self) -> String {
+        self.base_url.clone()
+    }
+
+    fn upload<'a>(&'a self, local_path: &'a Path, name: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            // 集合已存在时返回 405
+            let mkcol = Method::from_bytes(b"MKCOL")?;
+            self.send(
+                self.request(mkcol, &self.base_url),
+                &[StatusCode::METHOD_NOT_ALLOWED],
+            )
+            .await?;
+
+            let partial_url = self.file_url(&format!("{name}{PARTIAL_SUFFIX}"));
+            let file = tokio::fs::File::open(local_path).await?;
+            let length = file.metadata().await?.len();
+            self.send(
+                self.request(Method::PUT, &partial_url)
+                    .header(reqwest::header::CONTENT_LENGTH, length)
+                    .body(file),
+                &[],
+            )
+            .await?;
+
+            let move_method = Method::from_bytes(b"MOVE")?;
+            self.send(
+                self.request(move_method, &partial_url)
+                    .header("Destination", self.file_url(name))
+                    .header("Overwrite", "T"),
+                &[],
+            )
+            .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn download<'a>(&'a self, name: &'a str, local_path: &'a Path) -> BoxFuture<'a, Result<()>> {
+        async move {
+            let response = self
+                .send(self.request(Method::GET, &self.file_url(name)), &[])
+                .await?;
+            let mut file = tokio::fs::File::create(local_path).await?;
+            let mut stream = response.bytes_stream();
+            while let Some(chunk) = stream.next().await {
+                file.write_all(&chunk?).await?;
+            }
+            file.flush().await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        async move {
+            let propfind = Method::from_bytes(b"PROPFIND")?;
+            let response = self
+                .send(
+                    self.request(propfind, &self.base_url).header("Depth", "1"),
+                    &[StatusCode::NOT_FOUND],
+                )
+                .await?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Ok(Vec::new());
+            }
+            let body = response.text().await?;
+            Ok(parse_propfind_names(&body)
+                .into_iter()
+                .filter(|name| !is_partial_name(name))
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<()>> {
+        async move {
+            self.send(
+                self.request(Method::DELETE, &self.file_url(name)),
+                &[StatusCode::NOT_FOUND],
+            )
+            .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+/// 从 PROPFIND 响应中提取集合内的文件名（跳过集合自身和子集合）
+fn parse_propfind_names(body: &str) -> Vec<String> {
+    let href = Regex::new(r"<(?:[A-Za-z0-9_]+:)?href>\s*([^<]+?)\s*</(?:[A-Za-z0-9_]+:)?href>")
+        .expect("href 正则表达式有效");
+    href.captures_iter(body)
+        .filter_map(|captures| {
+            let href = captures.get(1)?.as_str();
+            if href.ends_with('/') {
+                return None;
+            }
+            let name = href.rsplit('/').next()?;
+            Some(percent_decode(name))
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// URL 路径段编码，只保留非保留字符
+fn percent_encode(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| segment.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_dir_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalDirStorage::new(dir.path().join("nfs"));
+        assert!(storage.list().await.unwrap().is_empty());
+
+        let local = dir.path().join("backup.tar.gz");
+        std::fs::write(&local, b"backup").unwrap();
+        storage.upload(&local, "backup.tar.gz").await.unwrap();
+        assert_eq!(storage.list().await.unwrap(), vec!["backup.tar.gz"]);
+
+        let fetched = dir.path().join("fetched.tar.gz");
+        storage.download("backup.tar.gz", &fetched).await.unwrap();
+        assert_eq!(std::fs::read(&fetched).unwrap(), b"backup");
+
+        storage.delete("backup.tar.gz").await.unwrap();
+        storage.delete("backup.tar.gz").await.unwrap();
+        assert!(storage.list().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_propfind_names() {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response><d:href>/dav/nuwax/</d:href></d:response>
+  <d:response><d:href>/dav/nuwax/backup_manual_v1.2.0_2025-01-02_03-04-05.tar.gz</d:href></d:response>
+  <d:response><d:href>/dav/nuwax/my%20backup.tar.gz</d:href></d:response>
+  <d:response><d:href>/dav/nuwax/old/</d:href></d:response>
+</d:multistatus>"#;
+        assert_eq!(
+            parse_propfind_names(body),
+            vec![
+                "backup_manual_v1.2.0_2025-01-02_03-04-05.tar.gz",
+                "my backup.tar.gz"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_percent_encode_roundtrip() {
+        let name = "backup prod_v1.2.0.tar.gz";
+        assert_eq!(percent_encode(name), "backup%20prod_v1.2.0.tar.gz");
+        assert_eq!(percent_decode(&percent_encode(name)), name);
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+}
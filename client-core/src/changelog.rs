@@ -0,0 +1,108 @@
+//! # 服务版本更新说明
+//!
+//! 从版本列表接口（`/api/v1/docker/updateVersionList`）返回的版本中，挑出当前版本之后、
+//! 目标版本及之前的版本，按版本号从低到高排列，升级前逐个展示各版本的更新说明。
+//! 版本号无法解析的条目会被忽略；当前版本无法解析时（如首次部署）展示目标版本及之前的全部版本。
+
+use crate::api_types::DockerVersion;
+use crate::version::Version;
+
+/// 单个版本的更新说明
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseNote {
+    pub version: String,
+    /// 发布日期，未知时为空
+    pub release_date: String,
+    pub notes: String,
+}
+
+impl ReleaseNote {
+    /// 按显示格式展开为多行：版本标题后接更新说明的每一行
+    pub fn lines(&self) -> Vec<String> {
+        let title = if self.release_date.is_empty() {
+            format!("── {} ──", self.version)
+        } else {
+            format!("── {} ({}) ──", self.version, self.release_date)
+        };
+        let mut lines = vec![title];
+        if self.notes.trim().is_empty() {
+            lines.push("  （没有更新说明）".to_string());
+        } else {
+            lines.extend(self.notes.trim().lines().map(|line| format!("  {line}")));
+        }
+        lines
+    }
+}
+
+/// 挑出 `current` 之后到 `target`（含）之间的版本，按版本号从低到高排列
+///
+/// `current` 为 None 时（当前版本未知）挑出 `target` 及之前的全部版本
+pub fn release_notes_between(
+    versions: Vec<DockerVersion>,
+    current: Option<&Version>,
+    target: &Version,
+) -> Vec<ReleaseNote> {
+    let mut notes: Vec<(Version, ReleaseNote)> = versions
+        .into_iter()
+        .filter_map(|entry| {
+            let version = entry.version.parse::<Version>().ok()?;
+            let note = ReleaseNote {
+                version: entry.version,
+                release_date: entry.release_date,
+                notes: entry.notes,
+            };
+            Some((version, note))
+        })
+        .filter(|(version, _)| version <= target && current.is_none_or(|current| version > current))
+        .collect();
+    notes.sort_by(|a, b| a.0.cmp(&b.0));
+    notes.dedup_by(|a, b| a.0 == b.0);
+    notes.into_iter().map(|(_, note)| note).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str, notes: &str) -> DockerVersion {
+        DockerVersion {
+            version: version.to_string(),
+            release_date: "2025-06-01".to_string(),
+            notes: notes.to_string(),
+            is_latest: false,
+        }
+    }
+
+    #[test]
+    fn test_release_notes_between() {
+        let versions = vec![
+            version("0.0.15", "未发布"),
+            version("0.0.13.2", "修复登录问题"),
+            version("0.0.12", "旧版本"),
+            version("0.0.14", "新增知识库\n优化检索"),
+            version("latest", "无效版本号"),
+            version("0.0.13", "当前版本"),
+        ];
+
+        let notes = release_notes_between(
+            versions,
+            Some(&"0.0.13".parse().unwrap()),
+            &"0.0.14".parse().unwrap(),
+        );
+        let versions: Vec<&str> = notes.iter().map(|note| note.version.as_str()).collect();
+        assert_eq!(versions, ["0.0.13.2", "0.0.14"]);
+        assert_eq!(
+            notes[1].lines(),
+            ["── 0.0.14 (2025-06-01) ──", "  新增知识库", "  优化检索"]
+        );
+
+        // 当前版本未知时展示目标版本及之前的全部版本
+        let notes = release_notes_between(
+            vec![version("0.0.12", "")],
+            None,
+            &"0.0.14".parse().unwrap(),
+        );
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].lines()[1], "  （没有更新说明）");
+    }
+}
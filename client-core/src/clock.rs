@@ -0,0 +1,160 @@
+//! # 系统时间校验
+//!
+//! 定时任务、下载元数据和缓存都使用系统时间记录时间戳，系统时间不准确时
+//! 任务会被安排到很久以后，元数据也会被误判为过期。这里从服务器响应的 `Date`
+//! 头计算本地时间与服务器时间的偏差，偏差超过 [`MAX_SKEW_SECS`] 时警告一次，
+//! 并提供按服务器时间校正的当前时间；等待类任务用单调时钟计时，
+//! 结束后检查系统时间在等待期间是否被调整过。
+
+use crate::constants::clock::MAX_SKEW_SECS;
+use chrono::{DateTime, TimeDelta, Utc};
+use reqwest::header::{DATE, HeaderMap};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// 最近一次观察到的服务器时间减本地时间
+static SERVER_OFFSET: Mutex<Option<TimeDelta>> = Mutex::new(None);
+
+/// 是否已经警告过时间偏差，避免每个请求都警告
+static SKEW_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// 解析响应的 `Date` 头
+pub fn server_date(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    let value = headers.get(DATE)?.to_str().ok()?;
+    DateTime::parse_from_rfc2822(value.trim())
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// 根据响应的 `Date` 头记录本地时间与服务器时间的偏差，偏差过大时警告一次
+pub fn record_server_date(headers: &HeaderMap) {
+    let Some(server_time) = server_date(headers) else {
+        return;
+    };
+    let offset = server_time - Utc::now();
+    if let Ok(mut current) = SERVER_OFFSET.lock() {
+        *current = Some(offset);
+    }
+    match skew_warning(offset) {
+        Some(message) if !SKEW_WARNED.swap(true, Ordering::Relaxed) => warn!("⚠️ {}", message),
+        Some(_) => {}
+        None => debug!("本地时间与服务器时间相差 {} 秒", offset.num_seconds()),
+    }
+}
+
+/// 最近一次观察到的服务器时间减本地时间，尚未收到带 `Date` 头的响应时返回 None
+pub fn server_offset() -> Option<TimeDelta> {
+    SERVER_OFFSET.lock().ok().and_then(|offset| *offset)
+}
+
+/// 按服务器时间校正后的当前时间，没有服务器时间时返回本地时间
+pub fn server_now() -> DateTime<Utc> {
+    Utc::now() + server_offset().unwrap_or_default()
+}
+
+/// 偏差超过阈值时返回警告信息
+pub fn skew_warning(offset: TimeDelta) -> Option<String> {
+    let seconds = offset.num_seconds();
+    if seconds.abs() <= MAX_SKEW_SECS {
+        return None;
+    }
+    let direction = if seconds > 0 { "慢" } else { "快" };
+    Some(format!(
+        "本地系统时间比服务器{} {}，定时任务和过期判断可能不准确，请检查系统时间同步（NTP）",
+        direction,
+        format_offset(offset)
+    ))
+}
+
+/// 时间戳是否晚于 `now` 超过允许的偏差，通常说明系统时间被往回调整过
+pub fn is_in_future(timestamp: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    timestamp - now > TimeDelta::seconds(MAX_SKEW_SECS)
+}
+
+/// 等待期间系统时间的变化量减去单调时钟经过的时间，即系统时间被调整的幅度
+pub fn wall_clock_drift(
+    wall_start: DateTime<Utc>,
+    wall_now: DateTime<Utc>,
+    monotonic_elapsed: Duration,
+) -> TimeDelta {
+    (wall_now - wall_start) - TimeDelta::from_std(monotonic_elapsed).unwrap_or_default()
+}
+
+/// 系统时间调整幅度超过允许的偏差时返回警告信息
+pub fn drift_warning(drift: TimeDelta) -> Option<String> {
+    if drift.num_seconds().abs() <= MAX_SKEW_SECS {
+        return None;
+    }
+    let direction = if drift > TimeDelta::zero() {
+        "向后"
+    } else {
+        "向前"
+    };
+    Some(format!(
+        "等待期间系统时间被{}调整了 {}，任务已按实际经过的时间执行",
+        direction,
+        format_offset(drift)
+    ))
+}
+
+/// 格式化时间偏差，如 "2天3小时"、"5分钟"
+fn format_offset(offset: TimeDelta) -> String {
+    let seconds = offset.num_seconds().unsigned_abs();
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{seconds}秒"),
+        (0, 0, _) => format!("{minutes}分钟"),
+        (0, _, _) => format!("{hours}小时{minutes}分钟"),
+        _ => format!("{days}天{hours}小时"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_server_date_and_skew() {
+        let mut headers = HeaderMap::new();
+        assert!(server_date(&headers).is_none());
+        headers.insert(
+            DATE,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(
+            server_date(&headers).unwrap().to_rfc3339(),
+            "2015-10-21T07:28:00+00:00"
+        );
+
+        assert!(skew_warning(TimeDelta::seconds(MAX_SKEW_SECS)).is_none());
+        let warning = skew_warning(TimeDelta::days(-400)).unwrap();
+        assert!(warning.contains("快"), "{warning}");
+        assert!(warning.contains("400天"), "{warning}");
+        assert!(
+            skew_warning(TimeDelta::minutes(10))
+                .unwrap()
+                .contains("慢 10分钟")
+        );
+    }
+
+    #[test]
+    fn test_wall_clock_drift() {
+        let start = Utc::now();
+        let elapsed = Duration::from_secs(3600);
+
+        let drift = wall_clock_drift(start, start + TimeDelta::hours(1), elapsed);
+        assert_eq!(drift, TimeDelta::zero());
+        assert!(drift_warning(drift).is_none());
+
+        // 等待期间系统时间被往回调整了一天
+        let drift = wall_clock_drift(start, start - TimeDelta::hours(23), elapsed);
+        assert_eq!(drift, TimeDelta::days(-1));
+        assert!(drift_warning(drift).unwrap().contains("向前"));
+
+        assert!(is_in_future(start + TimeDelta::days(1), start));
+        assert!(!is_in_future(start + TimeDelta::seconds(10), start));
+    }
+}
@@ -1,9 +1,17 @@
 use crate::architecture::Architecture;
-use crate::constants::{backup, config, docker, updates, version};
+use crate::archive_format::ArchiveFormat;
+use crate::constants::{
+    backup, config, docker, notifications, telemetry, timeout, updates, upgrade, version, watchdog,
+};
+use crate::database::BackupType;
+use crate::paths;
+use crate::service_preset::ServicePreset;
+use crate::sql_diff::SqlDialect;
 use crate::version::Version; // 新增：导入Version类型
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc};
@@ -17,6 +25,23 @@ pub struct AppConfig {
     pub backup: BackupConfig,
     pub cache: CacheConfig,
     pub updates: UpdatesConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    #[serde(default)]
+    pub upgrade_approval: UpgradeApprovalConfig,
+    #[serde(default)]
+    pub package_limits: PackageLimitsConfig,
+    #[serde(default)]
+    pub log_redaction: LogRedactionConfig,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    /// 用户自定义的服务主机端口，键为 compose 服务名（或 `服务名:容器端口`），值为主机端口
+    ///
+    /// 部署时写入 .env / docker-compose.yml，升级覆盖服务包后会重新应用
+    #[serde(default)]
+    pub ports: BTreeMap<String, u16>,
 }
 
 /// 版本配置结构（支持增量版本管理）
@@ -111,6 +136,15 @@ impl VersionConfig {
         );
     }
 
+    /// 解析基础 Docker 服务版本
+    pub fn docker_service_version(&self) -> Result<Version> {
+        self.docker_service.parse::<Version>().map_err(|e| {
+            anyhow::anyhow!(
+                "config.toml 中 versions.docker_service 的版本号无效: {e}，请修正后重试"
+            )
+        })
+    }
+
     /// 获取当前完整版本
     pub fn get_current_version(&self) -> Result<Version> {
         if !self.full_version_with_patches.is_empty() {
@@ -151,6 +185,7 @@ impl VersionConfig {
         if self.docker_service.is_empty() {
             return Err(anyhow::anyhow!("docker_service不能为空"));
         }
+        self.docker_service_version()?;
 
         // 验证完整版本号格式
         if !self.full_version_with_patches.is_empty() {
@@ -228,6 +263,95 @@ pub struct DockerConfig {
     pub compose_file: String,
     #[serde(default = "default_env_file_path")]
     pub env_file: String,
+    /// Docker服务工作目录，未配置时为当前目录下的 `docker`（命令行 `--work-dir` 优先）
+    #[serde(default)]
+    pub work_dir: Option<String>,
+    /// docker-compose 项目名称，未配置时从compose文件读取或使用 `docker`（命令行 `--project` 优先）
+    #[serde(default)]
+    pub project_name: Option<String>,
+    /// 停止服务的顺序和宽限期
+    #[serde(default)]
+    pub stop: ServiceStopConfig,
+    /// 执行数据库升级前等待数据库就绪的最长时间（秒）
+    #[serde(default = "default_mysql_ready_timeout")]
+    pub mysql_ready_timeout_secs: u64,
+    /// 数据库方言（mysql / postgres），未配置时根据 compose 文件中的数据库服务判断
+    #[serde(default)]
+    pub sql_dialect: Option<SqlDialect>,
+    /// 镜像文件加载失败时拉取镜像使用的仓库地址（如 `registry.example.com/nuwax`），
+    /// 替换镜像名称中原有的仓库地址；未配置时按镜像原名称拉取
+    #[serde(default)]
+    pub image_registry: Option<String>,
+    /// 服务规格预设（small / medium / large），部署时生成 compose 覆盖文件调整资源限制和可选服务
+    #[serde(default)]
+    pub preset: Option<ServicePreset>,
+    /// 迁移后的数据目录（绝对路径，见 `docker-service relocate-data`），部署时把 compose 和 .env 中
+    /// `./data` 开头的挂载路径改写到该目录；未配置时使用工作目录下的 `data`
+    #[serde(default)]
+    pub data_dir: Option<String>,
+}
+
+fn default_mysql_ready_timeout() -> u64 {
+    timeout::MYSQL_READY_TIMEOUT
+}
+
+/// 停止服务配置
+///
+/// 未配置顺序的服务按 depends_on 先停依赖方、后停被依赖的服务（如先停 backend 再停 mysql）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ServiceStopConfig {
+    /// 优先停止的服务，按列表顺序依次停止，其余服务在之后按依赖关系停止
+    #[serde(default)]
+    pub order: Vec<String>,
+    /// 默认停止宽限期（秒），超时后容器被强制终止
+    #[serde(default = "default_stop_grace_period")]
+    pub grace_period_secs: u64,
+    /// 按服务覆盖停止宽限期（秒），如数据库需要更长时间落盘
+    #[serde(default)]
+    pub grace_periods: BTreeMap<String, u64>,
+}
+
+fn default_stop_grace_period() -> u64 {
+    timeout::DEFAULT_STOP_GRACE_PERIOD
+}
+
+impl Default for ServiceStopConfig {
+    fn default() -> Self {
+        Self {
+            order: Vec::new(),
+            grace_period_secs: default_stop_grace_period(),
+            grace_periods: BTreeMap::new(),
+        }
+    }
+}
+
+impl ServiceStopConfig {
+    /// 获取服务的停止宽限期（秒）
+    pub fn grace_period_for(&self, service_name: &str) -> u64 {
+        self.grace_periods
+            .get(service_name)
+            .copied()
+            .unwrap_or(self.grace_period_secs)
+    }
+}
+
+impl DockerConfig {
+    /// 工作目录被重新指定时，把仍指向默认 `./docker` 下的 compose/.env 路径改到新的工作目录
+    pub fn rebase_default_paths(&mut self) {
+        if is_default_docker_path(&self.compose_file, docker::COMPOSE_FILE_NAME) {
+            self.compose_file = docker::get_compose_file_path_str();
+        }
+        if is_default_docker_path(&self.env_file, docker::ENV_FILE_NAME) {
+            self.env_file = docker::get_env_file_path_str();
+        }
+    }
+}
+
+/// 路径是否为 `docker/<file_name>` 或 `./docker/<file_name>`
+fn is_default_docker_path(path: &str, file_name: &str) -> bool {
+    let path = Path::new(path);
+    let path = path.strip_prefix(".").unwrap_or(path);
+    path == Path::new(docker::DOCKER_DIR_NAME).join(file_name)
 }
 // 默认值函数, 用于获取默认的环境文件路径
 fn default_env_file_path() -> String {
@@ -242,6 +366,301 @@ fn default_compose_file_path() -> String {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BackupConfig {
     pub storage_dir: String,
+    /// 按备份类型的保留策略
+    #[serde(default)]
+    pub retention: BackupRetentionConfig,
+    /// 升级前备份的暂存方式，开启后服务停止期间只复制文件，压缩在后台进行
+    #[serde(default)]
+    pub staging: BackupStagingMode,
+    /// 分块去重存储，相同内容在多个备份间只保存一份
+    #[serde(default)]
+    pub dedup: bool,
+    /// 创建后重新读取归档并与备份清单核对的备份范围
+    #[serde(default)]
+    pub verify: BackupVerifyMode,
+    /// 备份命名空间，多个项目共用备份目录时用于区分备份归属，为空时使用 docker-compose 项目名
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// 备份存储后端，未配置时备份只保存在 `storage_dir` 中
+    #[serde(default)]
+    pub storage: Option<BackupStorageConfig>,
+    /// 按服务名配置的备份钩子，在复制文件前后于服务容器中执行
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hooks: BTreeMap<String, BackupHookConfig>,
+    /// 快照暂存（`staging = "snapshot"`）使用的文件系统快照
+    #[serde(default)]
+    pub snapshot: BackupSnapshotConfig,
+    /// 备份大小上限（MB），按源文件总大小预估，超过时在写入前中止备份，0 表示不限制
+    #[serde(default)]
+    pub max_size_mb: u64,
+    /// 检查可用空间时在预估大小之外预留的比例（百分比）
+    #[serde(default = "default_free_space_margin_percent")]
+    pub free_space_margin_percent: u64,
+    /// 从备份恢复时的属主处理
+    #[serde(default)]
+    pub restore_ownership: RestoreOwnershipConfig,
+}
+
+fn default_free_space_margin_percent() -> u64 {
+    backup::DEFAULT_FREE_SPACE_MARGIN_PERCENT
+}
+
+/// 从备份恢复时的属主处理配置（仅 Unix，需要 root 权限）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RestoreOwnershipConfig {
+    /// 按备份中记录的 uid/gid 恢复属主，关闭时恢复的文件属于当前用户
+    #[serde(default = "default_restore_preserve_owner")]
+    pub preserve: bool,
+    /// uid 映射，格式为 `备份中的uid:恢复后的uid`，用于服务用户的 uid 在两台主机上不同的场景
+    #[serde(default)]
+    pub uid_map: Vec<String>,
+    /// gid 映射，格式为 `备份中的gid:恢复后的gid`
+    #[serde(default)]
+    pub gid_map: Vec<String>,
+}
+
+fn default_restore_preserve_owner() -> bool {
+    true
+}
+
+impl Default for RestoreOwnershipConfig {
+    fn default() -> Self {
+        Self {
+            preserve: default_restore_preserve_owner(),
+            uid_map: Vec::new(),
+            gid_map: Vec::new(),
+        }
+    }
+}
+
+/// 文件系统快照配置
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BackupSnapshotConfig {
+    /// 快照方式，auto 按目录所在的文件系统自动选择
+    #[serde(default)]
+    pub provider: SnapshotProviderKind,
+    /// LVM 快照预留的写时复制空间（lvcreate -L 的取值），快照存在期间原卷的写入量不能超过该值
+    #[serde(default = "default_lvm_snapshot_size")]
+    pub lvm_size: String,
+}
+
+impl Default for BackupSnapshotConfig {
+    fn default() -> Self {
+        Self {
+            provider: SnapshotProviderKind::default(),
+            lvm_size: default_lvm_snapshot_size(),
+        }
+    }
+}
+
+fn default_lvm_snapshot_size() -> String {
+    backup::DEFAULT_LVM_SNAPSHOT_SIZE.to_string()
+}
+
+/// 文件系统快照方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotProviderKind {
+    /// 依次尝试 btrfs、ZFS、LVM
+    #[default]
+    Auto,
+    /// btrfs 子卷快照，数据目录需为独立子卷
+    Btrfs,
+    /// ZFS 数据集快照
+    Zfs,
+    /// LVM 逻辑卷快照，需要 root 权限挂载
+    Lvm,
+}
+
+impl SnapshotProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SnapshotProviderKind::Auto => "auto",
+            SnapshotProviderKind::Btrfs => "btrfs",
+            SnapshotProviderKind::Zfs => "zfs",
+            SnapshotProviderKind::Lvm => "lvm",
+        }
+    }
+}
+
+/// 备份钩子配置：复制文件前让服务落盘并暂停写入（如 `redis-cli SAVE`），复制后恢复
+///
+/// 命令以参数列表形式给出，在服务的运行中容器内执行；服务未运行时跳过
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BackupHookConfig {
+    /// 复制文件前执行的命令
+    #[serde(default)]
+    pub pre: Vec<String>,
+    /// 复制文件后执行的命令（前置钩子已执行时，备份失败也会执行）
+    #[serde(default)]
+    pub post: Vec<String>,
+    /// 单个命令的超时时间（秒）
+    #[serde(default = "default_backup_hook_timeout")]
+    pub timeout_secs: u64,
+    /// 前置钩子失败（含超时）时的处理方式
+    #[serde(default)]
+    pub on_failure: BackupHookFailurePolicy,
+}
+
+fn default_backup_hook_timeout() -> u64 {
+    backup::DEFAULT_HOOK_TIMEOUT_SECS
+}
+
+/// 备份钩子失败时的处理方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupHookFailurePolicy {
+    /// 中止备份（已执行的前置钩子对应的后置钩子仍会执行）
+    #[default]
+    Abort,
+    /// 记录警告并继续备份
+    Continue,
+}
+
+/// 备份存储后端配置
+///
+/// 备份仍先在 `storage_dir` 中生成，完成后保存到后端并删除本地副本，恢复时按需取回
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BackupStorageConfig {
+    /// 本地目录，适用于已挂载的 NFS/SMB 共享
+    Local { path: String },
+    /// 通过 SSH 保存到远程主机（使用系统 ssh/scp，认证依赖密钥或 ssh-agent）
+    #[serde(alias = "scp")]
+    Sftp {
+        /// `[user@]host[:port]`
+        target: String,
+        remote_dir: String,
+        #[serde(default)]
+        identity_file: Option<String>,
+    },
+    /// WebDAV 服务，`url` 为保存备份的集合地址
+    Webdav {
+        url: String,
+        #[serde(default)]
+        username: Option<String>,
+        /// 保存密码的环境变量名，密码不写入配置文件
+        #[serde(default)]
+        password_env: Option<String>,
+    },
+}
+
+impl BackupStorageConfig {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            BackupStorageConfig::Local { .. } => "local",
+            BackupStorageConfig::Sftp { .. } => "sftp",
+            BackupStorageConfig::Webdav { .. } => "webdav",
+        }
+    }
+}
+
+/// 升级前备份的暂存方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupStagingMode {
+    /// 不暂存，服务停止期间完成整个压缩
+    #[default]
+    Off,
+    /// 复制到暂存目录（支持 reflink 的文件系统上由内核完成写时复制）
+    Copy,
+    /// 硬链接到暂存目录，跨设备时退回复制；服务需在压缩完成后才能重新启动
+    Hardlink,
+    /// 为数据目录创建文件系统快照（btrfs/ZFS/LVM），从快照压缩，不支持快照的路径退回复制
+    Snapshot,
+}
+
+impl BackupStagingMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupStagingMode::Off => "off",
+            BackupStagingMode::Copy => "copy",
+            BackupStagingMode::Hardlink => "hardlink",
+            BackupStagingMode::Snapshot => "snapshot",
+        }
+    }
+
+    /// 是否开启暂存
+    pub fn is_enabled(&self) -> bool {
+        *self != BackupStagingMode::Off
+    }
+}
+
+/// 创建备份后校验归档的范围
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackupVerifyMode {
+    /// 不校验
+    Off,
+    /// 只校验升级前备份，回滚依赖这些备份
+    #[default]
+    PreUpgrade,
+    /// 校验所有备份
+    All,
+}
+
+impl BackupVerifyMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupVerifyMode::Off => "off",
+            BackupVerifyMode::PreUpgrade => "pre-upgrade",
+            BackupVerifyMode::All => "all",
+        }
+    }
+
+    /// 指定类型的备份创建后是否校验
+    pub fn applies_to(&self, backup_type: BackupType) -> bool {
+        match self {
+            BackupVerifyMode::Off => false,
+            BackupVerifyMode::PreUpgrade => backup_type == BackupType::PreUpgrade,
+            BackupVerifyMode::All => true,
+        }
+    }
+}
+
+/// 备份保留策略：每种类型保留最近 N 个，0 表示不自动清理
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BackupRetentionConfig {
+    #[serde(default)]
+    pub keep_manual: usize,
+    #[serde(default = "default_keep_pre_upgrade")]
+    pub keep_pre_upgrade: usize,
+    #[serde(default = "default_keep_scheduled")]
+    pub keep_scheduled: usize,
+    #[serde(default)]
+    pub keep_snapshot: usize,
+}
+
+fn default_keep_pre_upgrade() -> usize {
+    backup::DEFAULT_KEEP_PRE_UPGRADE
+}
+
+fn default_keep_scheduled() -> usize {
+    backup::DEFAULT_KEEP_SCHEDULED
+}
+
+impl Default for BackupRetentionConfig {
+    fn default() -> Self {
+        Self {
+            keep_manual: 0,
+            keep_pre_upgrade: default_keep_pre_upgrade(),
+            keep_scheduled: default_keep_scheduled(),
+            keep_snapshot: 0,
+        }
+    }
+}
+
+impl BackupRetentionConfig {
+    /// 指定类型需要保留的数量，None 表示不限制
+    pub fn keep_for(&self, backup_type: BackupType) -> Option<usize> {
+        let keep = match backup_type {
+            BackupType::Manual => self.keep_manual,
+            BackupType::PreUpgrade => self.keep_pre_upgrade,
+            BackupType::Scheduled => self.keep_scheduled,
+            BackupType::Snapshot => self.keep_snapshot,
+        };
+        (keep > 0).then_some(keep)
+    }
 }
 
 /// 缓存相关配置
@@ -249,6 +668,19 @@ pub struct BackupConfig {
 pub struct CacheConfig {
     pub cache_dir: String,
     pub download_dir: String,
+    /// 升级成功后自动删除 `keep_versions` 之外的下载缓存
+    #[serde(default)]
+    pub auto_clean_after_upgrade: bool,
+    /// 自动清理时保留的版本：`current`、`previous` 或具体版本号
+    #[serde(default = "default_cache_keep_versions")]
+    pub keep_versions: Vec<String>,
+}
+
+fn default_cache_keep_versions() -> Vec<String> {
+    config::DEFAULT_CACHE_KEEP_VERSIONS
+        .iter()
+        .map(|version| version.to_string())
+        .collect()
 }
 
 /// 更新相关配置
@@ -257,6 +689,203 @@ pub struct UpdatesConfig {
     pub check_frequency: String,
 }
 
+/// 遥测（OpenTelemetry）相关配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP 接收端地址（如 `http://otel-collector:4318`），未配置时不导出
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    /// 上报的服务名
+    #[serde(default = "default_telemetry_service_name")]
+    pub service_name: String,
+}
+
+fn default_telemetry_service_name() -> String {
+    telemetry::DEFAULT_SERVICE_NAME.to_string()
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: default_telemetry_service_name(),
+        }
+    }
+}
+
+impl TelemetryConfig {
+    /// 获取有效的 OTLP 地址（忽略空字符串）
+    pub fn endpoint(&self) -> Option<&str> {
+        self.otlp_endpoint
+            .as_deref()
+            .map(str::trim)
+            .filter(|endpoint| !endpoint.is_empty())
+    }
+}
+
+/// 服务看门狗配置
+///
+/// compose 的 restart 策略只能处理容器退出，看门狗根据健康检查结果重启卡死的常驻服务
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WatchdogConfig {
+    /// 监控的服务名，为空时监控所有常驻服务
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// 健康检查间隔（秒）
+    #[serde(default = "default_watchdog_check_interval")]
+    pub check_interval_secs: u64,
+    /// 连续失败多少次后重启服务
+    #[serde(default = "default_watchdog_failure_threshold")]
+    pub failure_threshold: u32,
+    /// 首次重启后的退避时间（秒），之后每次重启翻倍
+    #[serde(default = "default_watchdog_restart_backoff")]
+    pub restart_backoff_secs: u64,
+    /// 最大退避时间（秒）
+    #[serde(default = "default_watchdog_max_restart_backoff")]
+    pub max_restart_backoff_secs: u64,
+    /// 重启事件通知的 Webhook 地址（POST JSON），未配置时只记录日志
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+fn default_watchdog_check_interval() -> u64 {
+    watchdog::DEFAULT_CHECK_INTERVAL_SECS
+}
+
+fn default_watchdog_failure_threshold() -> u32 {
+    watchdog::DEFAULT_FAILURE_THRESHOLD
+}
+
+fn default_watchdog_restart_backoff() -> u64 {
+    watchdog::DEFAULT_RESTART_BACKOFF_SECS
+}
+
+fn default_watchdog_max_restart_backoff() -> u64 {
+    watchdog::DEFAULT_MAX_RESTART_BACKOFF_SECS
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            services: Vec::new(),
+            check_interval_secs: default_watchdog_check_interval(),
+            failure_threshold: default_watchdog_failure_threshold(),
+            restart_backoff_secs: default_watchdog_restart_backoff(),
+            max_restart_backoff_secs: default_watchdog_max_restart_backoff(),
+            webhook_url: None,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// 是否监控指定服务
+    pub fn watches(&self, service_name: &str) -> bool {
+        self.services.is_empty() || self.services.iter().any(|s| s == service_name)
+    }
+}
+
+/// 升级审批配置
+///
+/// 开启后 `auto-upgrade-deploy run` 必须通过 `--plan` 指定经过审批的升级计划
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct UpgradeApprovalConfig {
+    /// 升级是否必须经过审批
+    #[serde(default)]
+    pub required: bool,
+    /// 受信任的审批公钥（base64 编码的 Ed25519 公钥）
+    #[serde(default)]
+    pub trusted_keys: Vec<String>,
+}
+
+/// 服务包解压限制，解压前按 ZIP 目录或 tar 头检查，超过时拒绝解压
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PackageLimitsConfig {
+    /// 解压后总大小上限（MB）
+    #[serde(default = "default_max_extracted_size_mb")]
+    pub max_extracted_size_mb: u64,
+    /// 条目数量上限
+    #[serde(default = "default_max_package_entries")]
+    pub max_entries: usize,
+}
+
+impl Default for PackageLimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_extracted_size_mb: default_max_extracted_size_mb(),
+            max_entries: default_max_package_entries(),
+        }
+    }
+}
+
+fn default_max_extracted_size_mb() -> u64 {
+    upgrade::DEFAULT_MAX_EXTRACTED_SIZE_MB
+}
+
+fn default_max_package_entries() -> usize {
+    upgrade::DEFAULT_MAX_PACKAGE_ENTRIES
+}
+
+/// 日志脱敏配置，开启后终端和文件日志中的令牌、密码和签名 URL 参数会被遮盖
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LogRedactionConfig {
+    /// 是否开启日志脱敏
+    #[serde(default = "default_log_redaction_enabled")]
+    pub enabled: bool,
+    /// 额外需要遮盖的键名（不区分大小写，按包含匹配），如 `license`
+    #[serde(default)]
+    pub extra_keys: Vec<String>,
+}
+
+impl Default for LogRedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_log_redaction_enabled(),
+            extra_keys: Vec::new(),
+        }
+    }
+}
+
+fn default_log_redaction_enabled() -> bool {
+    true
+}
+
+/// 长时间操作完成通知配置
+///
+/// 下载、升级和备份命令结束时提醒离开终端的操作人员，只在交互式终端会话中生效
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NotificationsConfig {
+    /// 是否发送桌面通知
+    #[serde(default)]
+    pub desktop: bool,
+    /// 是否响终端铃声
+    #[serde(default)]
+    pub bell: bool,
+    /// 命令耗时超过该值（秒）时才通知，避免短命令也打扰
+    #[serde(default = "default_notification_min_duration")]
+    pub min_duration_secs: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            desktop: false,
+            bell: false,
+            min_duration_secs: default_notification_min_duration(),
+        }
+    }
+}
+
+fn default_notification_min_duration() -> u64 {
+    notifications::DEFAULT_MIN_DURATION_SECS
+}
+
+impl NotificationsConfig {
+    /// 是否开启了任意一种通知方式
+    pub fn enabled(&self) -> bool {
+        self.desktop || self.bell
+    }
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
@@ -264,11 +893,30 @@ impl Default for AppConfig {
             docker: DockerConfig {
                 compose_file: docker::get_compose_file_path_str(),
                 env_file: docker::get_env_file_path_str(),
+                work_dir: None,
+                project_name: None,
+                stop: ServiceStopConfig::default(),
+                mysql_ready_timeout_secs: default_mysql_ready_timeout(),
+                sql_dialect: None,
+                image_registry: None,
+                preset: None,
+                data_dir: None,
             },
             backup: BackupConfig {
                 storage_dir: backup::get_default_storage_dir()
                     .to_string_lossy()
                     .to_string(),
+                retention: BackupRetentionConfig::default(),
+                staging: BackupStagingMode::default(),
+                dedup: false,
+                verify: BackupVerifyMode::default(),
+                namespace: None,
+                storage: None,
+                hooks: BTreeMap::new(),
+                snapshot: BackupSnapshotConfig::default(),
+                max_size_mb: 0,
+                free_space_margin_percent: default_free_space_margin_percent(),
+                restore_ownership: RestoreOwnershipConfig::default(),
             },
             cache: CacheConfig {
                 cache_dir: config::get_default_cache_dir()
@@ -277,10 +925,19 @@ impl Default for AppConfig {
                 download_dir: config::get_default_download_dir()
                     .to_string_lossy()
                     .to_string(),
+                auto_clean_after_upgrade: false,
+                keep_versions: default_cache_keep_versions(),
             },
             updates: UpdatesConfig {
                 check_frequency: updates::DEFAULT_CHECK_FREQUENCY.to_string(),
             },
+            telemetry: TelemetryConfig::default(),
+            watchdog: WatchdogConfig::default(),
+            upgrade_approval: UpgradeApprovalConfig::default(),
+            package_limits: PackageLimitsConfig::default(),
+            log_redaction: LogRedactionConfig::default(),
+            notifications: NotificationsConfig::default(),
+            ports: BTreeMap::new(),
         }
     }
 }
@@ -291,9 +948,16 @@ impl AppConfig {
         self.versions.docker_service.clone()
     }
 
+    /// 获取解析后的 docker 应用版本，版本号格式错误时返回错误
+    ///
+    /// 比较版本时使用，避免按字符串比较（如 `0.9.10` 与 `0.9.9`、`0.9.9` 与 `0.9.9.0`）
+    pub fn get_docker_version(&self) -> Result<Version> {
+        self.versions.docker_service_version()
+    }
+
     /// 写入 docker 应用版本配置
-    pub fn write_docker_versions(&mut self, docker_service: String) {
-        self.versions.docker_service = docker_service;
+    pub fn write_docker_versions(&mut self, docker_service: &Version) {
+        self.versions.docker_service = docker_service.to_string();
     }
 
     /// 智能查找并加载配置文件
@@ -318,7 +982,8 @@ impl AppConfig {
     /// 从指定文件加载配置
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = fs::read_to_string(&path)?;
-        let config: AppConfig = toml::from_str(&content)?;
+        let mut config: AppConfig = toml::from_str(&content)?;
+        config.docker.rebase_default_paths();
 
         Ok(config)
     }
@@ -339,6 +1004,103 @@ impl AppConfig {
         let backup_storage_dir = self.backup.storage_dir.replace('\\', "/");
         let cache_dir = self.cache.cache_dir.replace('\\', "/");
         let download_dir = self.cache.download_dir.replace('\\', "/");
+        let work_dir_line = match &self.docker.work_dir {
+            Some(work_dir) => format!("work_dir = \"{}\"", work_dir.replace('\\', "/")),
+            None => "# work_dir = \"./docker\"".to_string(),
+        };
+        let data_dir_line = match &self.docker.data_dir {
+            Some(data_dir) => format!("data_dir = \"{}\"", data_dir.replace('\\', "/")),
+            None => "# data_dir = \"/mnt/bigdisk/nuwax-data\"".to_string(),
+        };
+        let preset_line = match self.docker.preset {
+            Some(preset) => format!("preset = \"{preset}\""),
+            None => "# preset = \"small\"".to_string(),
+        };
+        let restore_uid_map = toml::Value::try_from(&self.backup.restore_ownership.uid_map)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|_| "[]".to_string());
+        let restore_gid_map = toml::Value::try_from(&self.backup.restore_ownership.gid_map)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|_| "[]".to_string());
+        let cache_keep_versions = toml::Value::try_from(&self.cache.keep_versions)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|_| "[]".to_string());
+        let log_redaction_extra_keys = toml::Value::try_from(&self.log_redaction.extra_keys)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|_| "[]".to_string());
+        let stop_order = toml::Value::try_from(&self.docker.stop.order)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|_| "[]".to_string());
+        let stop_grace_periods_lines = if self.docker.stop.grace_periods.is_empty() {
+            "# mysql = 60".to_string()
+        } else {
+            self.docker
+                .stop
+                .grace_periods
+                .iter()
+                .map(|(service, secs)| format!("{} = {secs}", toml_key(service)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let backup_namespace_line = match &self.backup.namespace {
+            Some(namespace) => format!("namespace = \"{namespace}\""),
+            None => "# namespace = \"prod\"".to_string(),
+        };
+        let backup_storage_section = match &self.backup.storage {
+            Some(storage) => format!(
+                "[backup.storage]\n{}",
+                toml::to_string(storage).unwrap_or_default().trim_end()
+            ),
+            None => [
+                "# [backup.storage]",
+                "# type = \"sftp\"",
+                "# target = \"backup@nas.example.com:22\"",
+                "# remote_dir = \"/volume1/nuwax-backups\"",
+            ]
+            .join("\n"),
+        };
+        let backup_hooks_section = if self.backup.hooks.is_empty() {
+            [
+                "# [backup.hooks.redis]",
+                "# pre = [\"redis-cli\", \"SAVE\"]",
+                "# timeout_secs = 60",
+                "# on_failure = \"abort\"",
+            ]
+            .join("\n")
+        } else {
+            self.backup
+                .hooks
+                .iter()
+                .map(|(service, hook)| {
+                    format!(
+                        "[backup.hooks.{}]\n{}",
+                        toml_key(service),
+                        toml::to_string(hook).unwrap_or_default().trim_end()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        };
+        let otlp_endpoint_line = match self.telemetry.endpoint() {
+            Some(endpoint) => format!("otlp_endpoint = \"{endpoint}\""),
+            None => "# otlp_endpoint = \"http://localhost:4318\"".to_string(),
+        };
+        let watchdog_services = toml::Value::try_from(&self.watchdog.services)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|_| "[]".to_string());
+        let watchdog_webhook_url_line = match &self.watchdog.webhook_url {
+            Some(url) => format!("webhook_url = \"{url}\""),
+            None => "# webhook_url = \"https://example.com/hooks/nuwax\"".to_string(),
+        };
+        let ports_lines = if self.ports.is_empty() {
+            "# frontend = 8080\n# \"minio:9001\" = 19001".to_string()
+        } else {
+            self.ports
+                .iter()
+                .map(|(key, port)| format!("{} = {port}", toml_key(key)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
 
         TEMPLATE
             .replace(
@@ -346,29 +1108,136 @@ impl AppConfig {
                 &self.get_docker_versions()
             )
             .replace("{compose_file}", &compose_file)
+            .replace("{work_dir_line}", &work_dir_line)
+            .replace("{data_dir_line}", &data_dir_line)
+            .replace("{preset_line}", &preset_line)
+            .replace("{stop_order}", &stop_order)
+            .replace(
+                "{stop_grace_period_secs}",
+                &self.docker.stop.grace_period_secs.to_string(),
+            )
+            .replace("{stop_grace_periods_lines}", &stop_grace_periods_lines)
             .replace("{backup_storage_dir}", &backup_storage_dir)
+            .replace(
+                "{keep_manual}",
+                &self.backup.retention.keep_manual.to_string(),
+            )
+            .replace(
+                "{keep_pre_upgrade}",
+                &self.backup.retention.keep_pre_upgrade.to_string(),
+            )
+            .replace(
+                "{keep_scheduled}",
+                &self.backup.retention.keep_scheduled.to_string(),
+            )
+            .replace(
+                "{keep_snapshot}",
+                &self.backup.retention.keep_snapshot.to_string(),
+            )
+            .replace("{backup_staging}", self.backup.staging.as_str())
+            .replace("{backup_dedup}", &self.backup.dedup.to_string())
+            .replace("{backup_verify}", self.backup.verify.as_str())
+            .replace("{backup_max_size_mb}", &self.backup.max_size_mb.to_string())
+            .replace(
+                "{backup_free_space_margin_percent}",
+                &self.backup.free_space_margin_percent.to_string(),
+            )
+            .replace(
+                "{snapshot_provider}",
+                self.backup.snapshot.provider.as_str(),
+            )
+            .replace("{snapshot_lvm_size}", &self.backup.snapshot.lvm_size)
+            .replace(
+                "{restore_preserve_owner}",
+                &self.backup.restore_ownership.preserve.to_string(),
+            )
+            .replace("{restore_uid_map}", &restore_uid_map)
+            .replace("{restore_gid_map}", &restore_gid_map)
+            .replace("{backup_namespace_line}", &backup_namespace_line)
+            .replace("{backup_storage_section}", &backup_storage_section)
+            .replace("{backup_hooks_section}", &backup_hooks_section)
             .replace("{cache_dir}", &cache_dir)
             .replace("{download_dir}", &download_dir)
+            .replace(
+                "{cache_auto_clean_after_upgrade}",
+                &self.cache.auto_clean_after_upgrade.to_string(),
+            )
+            .replace("{cache_keep_versions}", &cache_keep_versions)
             .replace("{check_frequency}", &self.updates.check_frequency)
+            .replace(
+                "{max_extracted_size_mb}",
+                &self.package_limits.max_extracted_size_mb.to_string(),
+            )
+            .replace(
+                "{max_package_entries}",
+                &self.package_limits.max_entries.to_string(),
+            )
+            .replace(
+                "{log_redaction_enabled}",
+                &self.log_redaction.enabled.to_string(),
+            )
+            .replace("{log_redaction_extra_keys}", &log_redaction_extra_keys)
+            .replace(
+                "{notifications_desktop}",
+                &self.notifications.desktop.to_string(),
+            )
+            .replace("{notifications_bell}", &self.notifications.bell.to_string())
+            .replace(
+                "{notifications_min_duration_secs}",
+                &self.notifications.min_duration_secs.to_string(),
+            )
+            .replace("{otlp_endpoint_line}", &otlp_endpoint_line)
+            .replace("{telemetry_service_name}", &self.telemetry.service_name)
+            .replace("{watchdog_services}", &watchdog_services)
+            .replace(
+                "{watchdog_check_interval_secs}",
+                &self.watchdog.check_interval_secs.to_string(),
+            )
+            .replace(
+                "{watchdog_failure_threshold}",
+                &self.watchdog.failure_threshold.to_string(),
+            )
+            .replace(
+                "{watchdog_restart_backoff_secs}",
+                &self.watchdog.restart_backoff_secs.to_string(),
+            )
+            .replace(
+                "{watchdog_max_restart_backoff_secs}",
+                &self.watchdog.max_restart_backoff_secs.to_string(),
+            )
+            .replace("{watchdog_webhook_url_line}", &watchdog_webhook_url_line)
+            .replace("{ports_lines}", &ports_lines)
     }
 
     /// 确保缓存目录存在
     pub fn ensure_cache_dirs(&self) -> Result<()> {
-        fs::create_dir_all(&self.cache.cache_dir)?;
-        fs::create_dir_all(&self.cache.download_dir)?;
+        fs::create_dir_all(self.get_cache_dir())?;
+        fs::create_dir_all(self.get_download_dir())?;
         Ok(())
     }
 
-    /// 获取下载目录路径
+    /// 获取缓存目录路径，`NUWAX_CACHE_DIR` 优先
+    pub fn get_cache_dir(&self) -> PathBuf {
+        paths::env_path(paths::CACHE_DIR_ENV)
+            .unwrap_or_else(|| PathBuf::from(&self.cache.cache_dir))
+    }
+
+    /// 获取下载目录路径，设置了 `NUWAX_CACHE_DIR` 时为其中的 `download` 目录
     pub fn get_download_dir(&self) -> PathBuf {
-        PathBuf::from(&self.cache.download_dir)
+        match paths::env_path(paths::CACHE_DIR_ENV) {
+            Some(cache_dir) => cache_dir.join(config::DOWNLOAD_DIR_NAME),
+            None => PathBuf::from(&self.cache.download_dir),
+        }
+    }
+
+    /// 获取服务包内容寻址存储目录路径
+    pub fn get_package_store_dir(&self) -> PathBuf {
+        self.get_cache_dir().join(config::PACKAGE_STORE_DIR_NAME)
     }
 
     /// 获取指定版本的全量下载目录路径
     pub fn get_version_download_dir(&self, version: &str, download_type: &str) -> PathBuf {
-        PathBuf::from(&self.cache.download_dir)
-            .join(version)
-            .join(download_type)
+        self.get_download_dir().join(version).join(download_type)
     }
 
     /// 获取指定版本的全量下载文件路径
@@ -384,10 +1253,15 @@ impl AppConfig {
                 .join(filename),
             None => {
                 //根据当前系统架构,使用不同的docker全量升级包的文件名
-                let docker_file_name = Architecture::detect().get_docker_file_name();
-
-                self.get_version_download_dir(version, download_type)
-                    .join(docker_file_name)
+                let arch = Architecture::detect();
+                let download_dir = self.get_version_download_dir(version, download_type);
+
+                // 优先返回已下载的服务包（支持 zip / tar.zst / tar.gz），默认使用 zip 文件名
+                ArchiveFormat::ALL
+                    .iter()
+                    .map(|format| download_dir.join(arch.get_docker_package_name(*format)))
+                    .find(|path| path.exists())
+                    .unwrap_or_else(|| download_dir.join(arch.get_docker_file_name()))
             }
         }
     }
@@ -403,9 +1277,23 @@ impl AppConfig {
         Ok(dir)
     }
 
-    /// 获取备份目录路径
+    /// 获取备份目录路径，`NUWAX_BACKUP_DIR` 优先
     pub fn get_backup_dir(&self) -> PathBuf {
-        PathBuf::from(&self.backup.storage_dir)
+        paths::env_path(paths::BACKUP_DIR_ENV)
+            .unwrap_or_else(|| PathBuf::from(&self.backup.storage_dir))
+    }
+}
+
+/// 生成 TOML 键，非裸键（如 `minio:9001`）加引号
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        toml::Value::String(key.to_string()).to_string()
     }
 }
 
@@ -413,6 +1301,207 @@ impl AppConfig {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_ports_config_roundtrip() {
+        let config = AppConfig::default();
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert!(parsed.ports.is_empty());
+
+        let mut config = AppConfig::default();
+        config.ports.insert("frontend".to_string(), 8443);
+        config.ports.insert("minio:9001".to_string(), 19001);
+
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.ports, config.ports);
+    }
+
+    #[test]
+    fn test_watchdog_config_roundtrip() {
+        let mut config = AppConfig::default();
+        config.watchdog.services = vec!["backend".to_string(), "frontend".to_string()];
+        config.watchdog.failure_threshold = 5;
+        config.watchdog.webhook_url = Some("https://example.com/hook".to_string());
+
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.watchdog, config.watchdog);
+        assert!(parsed.watchdog.watches("backend"));
+        assert!(!parsed.watchdog.watches("mysql"));
+        assert!(WatchdogConfig::default().watches("mysql"));
+    }
+
+    #[test]
+    fn test_backup_hooks_config_roundtrip() {
+        let mut config = AppConfig::default();
+        config.backup.hooks.insert(
+            "redis".to_string(),
+            BackupHookConfig {
+                pre: vec!["redis-cli".to_string(), "SAVE".to_string()],
+                post: Vec::new(),
+                timeout_secs: 30,
+                on_failure: BackupHookFailurePolicy::Continue,
+            },
+        );
+
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.backup.hooks, config.backup.hooks);
+
+        let hook: BackupHookConfig = toml::from_str(r#"pre = ["sync"]"#).unwrap();
+        assert_eq!(hook.timeout_secs, backup::DEFAULT_HOOK_TIMEOUT_SECS);
+        assert_eq!(hook.on_failure, BackupHookFailurePolicy::Abort);
+    }
+
+    #[test]
+    fn test_service_stop_config_roundtrip() {
+        let mut config = AppConfig::default();
+        config.docker.stop.order = vec!["frontend".to_string()];
+        config.docker.stop.grace_periods.insert("mysql".to_string(), 60);
+
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.docker.stop, config.docker.stop);
+        assert_eq!(parsed.docker.stop.grace_period_for("mysql"), 60);
+        assert_eq!(
+            parsed.docker.stop.grace_period_for("backend"),
+            timeout::DEFAULT_STOP_GRACE_PERIOD
+        );
+    }
+
+    #[test]
+    fn test_backup_staging_config_roundtrip() {
+        let config = AppConfig::default();
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.backup.staging, BackupStagingMode::Off);
+        assert!(!parsed.backup.dedup);
+        assert_eq!(parsed.backup.verify, BackupVerifyMode::PreUpgrade);
+        assert_eq!(parsed.backup.namespace, None);
+
+        let mut config = AppConfig::default();
+        config.backup.staging = BackupStagingMode::Hardlink;
+        config.backup.dedup = true;
+        config.backup.verify = BackupVerifyMode::All;
+        config.backup.namespace = Some("prod".to_string());
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.backup.staging, BackupStagingMode::Hardlink);
+        assert!(parsed.backup.dedup);
+        assert_eq!(parsed.backup.verify, BackupVerifyMode::All);
+        assert_eq!(parsed.backup.namespace.as_deref(), Some("prod"));
+
+        let mut config = AppConfig::default();
+        config.backup.staging = BackupStagingMode::Snapshot;
+        config.backup.snapshot.provider = SnapshotProviderKind::Lvm;
+        config.backup.snapshot.lvm_size = "20G".to_string();
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.backup.staging, BackupStagingMode::Snapshot);
+        assert_eq!(parsed.backup.snapshot, config.backup.snapshot);
+    }
+
+    #[test]
+    fn test_cache_config_roundtrip() {
+        let parsed: AppConfig =
+            toml::from_str(&AppConfig::default().to_toml_with_comments()).unwrap();
+        assert!(!parsed.cache.auto_clean_after_upgrade);
+        assert_eq!(parsed.cache.keep_versions, ["current", "previous"]);
+
+        let mut config = AppConfig::default();
+        config.cache.auto_clean_after_upgrade = true;
+        config.cache.keep_versions = vec!["current".to_string(), "0.0.13".to_string()];
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert!(parsed.cache.auto_clean_after_upgrade);
+        assert_eq!(parsed.cache.keep_versions, config.cache.keep_versions);
+    }
+
+    #[test]
+    fn test_log_redaction_config_roundtrip() {
+        let parsed: AppConfig =
+            toml::from_str(&AppConfig::default().to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.log_redaction, LogRedactionConfig::default());
+        assert!(parsed.log_redaction.enabled);
+
+        let mut config = AppConfig::default();
+        config.log_redaction.enabled = false;
+        config.log_redaction.extra_keys = vec!["license".to_string()];
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.log_redaction, config.log_redaction);
+    }
+
+    #[test]
+    fn test_notifications_config_roundtrip() {
+        let parsed: AppConfig =
+            toml::from_str(&AppConfig::default().to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.notifications, NotificationsConfig::default());
+        assert!(!parsed.notifications.enabled());
+
+        let mut config = AppConfig::default();
+        config.notifications.desktop = true;
+        config.notifications.min_duration_secs = 0;
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.notifications, config.notifications);
+    }
+
+    #[test]
+    fn test_backup_size_guard_config_roundtrip() {
+        let parsed: AppConfig =
+            toml::from_str(&AppConfig::default().to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.backup.max_size_mb, 0);
+        assert_eq!(
+            parsed.backup.free_space_margin_percent,
+            backup::DEFAULT_FREE_SPACE_MARGIN_PERCENT
+        );
+
+        let mut config = AppConfig::default();
+        config.backup.max_size_mb = 20480;
+        config.backup.free_space_margin_percent = 25;
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.backup.max_size_mb, 20480);
+        assert_eq!(parsed.backup.free_space_margin_percent, 25);
+    }
+
+    #[test]
+    fn test_restore_ownership_config_roundtrip() {
+        let parsed: AppConfig =
+            toml::from_str(&AppConfig::default().to_toml_with_comments()).unwrap();
+        assert_eq!(
+            parsed.backup.restore_ownership,
+            RestoreOwnershipConfig::default()
+        );
+
+        let mut config = AppConfig::default();
+        config.backup.restore_ownership.uid_map = vec!["999:1001".to_string()];
+        config.backup.restore_ownership.gid_map = vec!["999:1001".to_string()];
+        let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+        assert_eq!(
+            parsed.backup.restore_ownership,
+            config.backup.restore_ownership
+        );
+    }
+
+    #[test]
+    fn test_backup_storage_config_roundtrip() {
+        let parsed: AppConfig =
+            toml::from_str(&AppConfig::default().to_toml_with_comments()).unwrap();
+        assert_eq!(parsed.backup.storage, None);
+
+        for storage in [
+            BackupStorageConfig::Local {
+                path: "/mnt/nfs/nuwax".to_string(),
+            },
+            BackupStorageConfig::Sftp {
+                target: "backup@nas:2222".to_string(),
+                remote_dir: "/volume1/nuwax".to_string(),
+                identity_file: Some("~/.ssh/id_ed25519".to_string()),
+            },
+            BackupStorageConfig::Webdav {
+                url: "https://dav.example.com/nuwax".to_string(),
+                username: None,
+                password_env: Some("NUWAX_WEBDAV_PASSWORD".to_string()),
+            },
+        ] {
+            let mut config = AppConfig::default();
+            config.backup.storage = Some(storage);
+            let parsed: AppConfig = toml::from_str(&config.to_toml_with_comments()).unwrap();
+            assert_eq!(parsed.backup.storage, config.backup.storage);
+        }
+    }
+
     #[test]
     fn test_version_config_new() {
         let config = VersionConfig::new();
@@ -442,6 +1531,19 @@ mod tests {
         assert!(config.last_full_upgrade.is_some());
     }
 
+    #[test]
+    fn test_docker_service_version() {
+        let mut config = AppConfig::default();
+        config.write_docker_versions(&"0.9.10".parse().unwrap());
+        assert_eq!(config.get_docker_versions(), "0.9.10.0");
+        assert!(config.get_docker_version().unwrap() > "0.9.9".parse().unwrap());
+
+        config.versions.docker_service = "latest".to_string();
+        let err = config.get_docker_version().unwrap_err().to_string();
+        assert!(err.contains("versions.docker_service"), "{err}");
+        assert!(config.versions.validate().is_err());
+    }
+
     #[test]
     fn test_apply_patch() {
         let mut config = VersionConfig::new();
@@ -657,4 +1759,21 @@ mod tests {
         println!("   - ✅ get_current_version方法正常工作");
         println!("   - ✅ 配置迁移逻辑（向后兼容）正常工作");
     }
+
+    #[test]
+    fn test_is_default_docker_path() {
+        assert!(is_default_docker_path(
+            "./docker/docker-compose.yml",
+            docker::COMPOSE_FILE_NAME
+        ));
+        assert!(is_default_docker_path("docker/.env", docker::ENV_FILE_NAME));
+        assert!(!is_default_docker_path(
+            "/opt/nuwax/docker/docker-compose.yml",
+            docker::COMPOSE_FILE_NAME
+        ));
+        assert!(!is_default_docker_path(
+            "./custom/docker-compose.yml",
+            docker::COMPOSE_FILE_NAME
+        ));
+    }
 }
@@ -1,6 +1,7 @@
 /// Docker相关路径常量
 pub mod docker {
     use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
 
     /// docker-compose.yml文件名
     pub const COMPOSE_FILE_NAME: &str = "docker-compose.yml";
@@ -11,9 +12,18 @@ pub mod docker {
     /// 环境变量文件名
     pub const ENV_FILE_NAME: &str = ".env";
 
+    /// .env 模板变量说明文件名（随服务包发布，与 .env 同目录）
+    pub const ENV_META_FILE_NAME: &str = ".env.meta.toml";
+
+    /// 服务规格预设生成的 compose 覆盖文件名（与 docker-compose.yml 同目录）
+    pub const PRESET_OVERRIDE_FILE_NAME: &str = "docker-compose.preset.yml";
+
     /// Docker镜像目录名
     pub const IMAGES_DIR_NAME: &str = "images";
 
+    /// 镜像清单文件名（位于镜像目录，记录镜像文件对应的镜像名称和 digest）
+    pub const IMAGE_MANIFEST_FILE_NAME: &str = "images.json";
+
     /// 数据目录名
     pub const DATA_DIR_NAME: &str = "data";
 
@@ -32,6 +42,21 @@ pub mod docker {
     /// 日志目录名
     pub const LOGS_DIR_NAME: &str = "logs";
 
+    /// Compose 标记容器、网络和数据卷所属项目的标签
+    pub const COMPOSE_PROJECT_LABEL: &str = "com.docker.compose.project";
+
+    /// Compose 标记数据卷在 compose 文件中名称的标签
+    pub const COMPOSE_VOLUME_LABEL: &str = "com.docker.compose.volume";
+
+    /// Compose 标记容器所属服务的标签
+    pub const COMPOSE_SERVICE_LABEL: &str = "com.docker.compose.service";
+
+    /// Compose 标记 `compose run` 一次性容器的标签
+    pub const COMPOSE_ONEOFF_LABEL: &str = "com.docker.compose.oneoff";
+
+    /// 迁移项目时复制数据卷内容使用的临时容器镜像
+    pub const VOLUME_MIGRATION_IMAGE: &str = "alpine:latest";
+
     /// 服务数据目录结构
     pub mod data_dirs {
         /// MySQL数据目录
@@ -152,14 +177,29 @@ pub mod docker {
     #[cfg(windows)]
     pub const DOCKER_SOCKET_PATH: &str = r"\\.\pipe\docker_engine";
 
+    /// 运行时指定的Docker工作目录
+    static DOCKER_WORK_DIR: OnceLock<PathBuf> = OnceLock::new();
+
     /// 获取默认的docker-compose.yml文件路径（跨平台）
     pub fn get_compose_file_path() -> PathBuf {
-        Path::new(".").join(DOCKER_DIR_NAME).join(COMPOSE_FILE_NAME)
+        get_docker_work_dir().join(COMPOSE_FILE_NAME)
     }
 
     /// 获取Docker工作目录路径（跨平台）
+    ///
+    /// 优先使用启动时通过 [`set_docker_work_dir`] 指定的目录，否则为当前目录下的 `docker`
     pub fn get_docker_work_dir() -> PathBuf {
-        Path::new(".").join(DOCKER_DIR_NAME)
+        DOCKER_WORK_DIR
+            .get()
+            .cloned()
+            .unwrap_or_else(|| Path::new(".").join(DOCKER_DIR_NAME))
+    }
+
+    /// 指定Docker工作目录（`--work-dir` 或配置文件中的 `docker.work_dir`）
+    ///
+    /// 只能在进程启动时设置一次，重复设置返回 false
+    pub fn set_docker_work_dir(path: impl Into<PathBuf>) -> bool {
+        DOCKER_WORK_DIR.set(path.into()).is_ok()
     }
 
     /// 获取默认compose文件路径的字符串表示（用于向后兼容）
@@ -169,7 +209,7 @@ pub mod docker {
 
     /// 获取环境变量文件路径（跨平台）
     pub fn get_env_file_path() -> PathBuf {
-        Path::new(".").join(DOCKER_DIR_NAME).join(ENV_FILE_NAME)
+        get_docker_work_dir().join(ENV_FILE_NAME)
     }
 
     /// 获取环境变量文件路径的字符串表示（用于向后兼容）
@@ -179,39 +219,50 @@ pub mod docker {
 
     /// 获取Docker镜像目录路径（跨平台）
     pub fn get_images_dir_path() -> PathBuf {
-        Path::new(".").join(DOCKER_DIR_NAME).join(IMAGES_DIR_NAME)
+        get_docker_work_dir().join(IMAGES_DIR_NAME)
     }
 
     /// 获取数据目录路径（跨平台）
     pub fn get_data_dir_path() -> PathBuf {
-        Path::new(".").join(DOCKER_DIR_NAME).join(DATA_DIR_NAME)
+        get_docker_work_dir().join(DATA_DIR_NAME)
     }
 
     /// 获取应用程序目录路径（跨平台）
     pub fn get_app_dir_path() -> PathBuf {
-        Path::new(".").join(DOCKER_DIR_NAME).join(APP_DIR_NAME)
+        get_docker_work_dir().join(APP_DIR_NAME)
     }
 
     /// 获取配置目录路径（跨平台）
     pub fn get_config_dir_path() -> PathBuf {
-        Path::new(".").join(DOCKER_DIR_NAME).join(CONFIG_DIR_NAME)
+        get_docker_work_dir().join(CONFIG_DIR_NAME)
     }
 
     /// 获取上传目录路径（跨平台）
     pub fn get_upload_dir_path() -> PathBuf {
-        Path::new(".").join(DOCKER_DIR_NAME).join(UPLOAD_DIR_NAME)
+        get_docker_work_dir().join(UPLOAD_DIR_NAME)
     }
 
     /// 获取备份目录路径（跨平台）
     pub fn get_backups_dir_path() -> PathBuf {
-        Path::new(".").join(DOCKER_DIR_NAME).join(BACKUPS_DIR_NAME)
+        get_docker_work_dir().join(BACKUPS_DIR_NAME)
     }
 
     /// 获取日志目录路径（跨平台）
     pub fn get_logs_dir_path() -> PathBuf {
-        Path::new(".").join(DOCKER_DIR_NAME).join(LOGS_DIR_NAME)
+        get_docker_work_dir().join(LOGS_DIR_NAME)
     }
 
+    /// 全量升级清理工作目录时保留的用户数据目录
+    pub const PRESERVED_DIR_NAMES: [&str; 7] = [
+        UPLOAD_DIR_NAME,
+        "project_workspace",
+        "project_zips",
+        "project_nginx",
+        "project_init",
+        "uv_cache",
+        DATA_DIR_NAME,
+    ];
+
     /// 获取所有必需的Docker服务目录列表
     pub fn get_all_required_directories() -> Vec<&'static str> {
         vec![
@@ -286,6 +337,21 @@ pub mod api {
 
         /// User-Agent头
         pub const USER_AGENT: &str = "nuwax-cli/1.0";
+
+        /// 被限流（429）后在同一请求内自动重试的最大次数
+        pub const RATE_LIMIT_MAX_RETRIES: u32 = 3;
+
+        /// 在同一请求内等待重试的最长时间（秒），超过后交由调用方安排稍后重试
+        pub const RATE_LIMIT_MAX_WAIT_SECS: u64 = 60;
+
+        /// 服务器未给出 Retry-After 时的初始退避时间（秒），每次重试翻倍
+        pub const RATE_LIMIT_BACKOFF_SECS: u64 = 5;
+
+        /// 携带客户端ID的请求头
+        pub const CLIENT_ID_HEADER: &str = "X-Client-ID";
+
+        /// 认证失败 (401) 后重新注册的冷却时间（秒），冷却期内仍被拒绝时不再重新注册
+        pub const REREGISTER_COOLDOWN_SECS: u64 = 60;
     }
 }
 
@@ -308,6 +374,48 @@ pub mod backup {
     /// 最小有效ZIP文件大小（字节）
     pub const MIN_ZIP_FILE_SIZE: u64 = 100;
 
+    /// 备份归档中存放 CLI 自身状态（config.toml、数据库）的顶层目录名
+    pub const SYSTEM_BACKUP_DIR_NAME: &str = "_system";
+
+    /// 从备份恢复前清空的工作目录下的目录（未被排除时）
+    pub const RESTORE_CLEARED_DIR_NAMES: [&str; 2] = ["data", "app"];
+
+    /// 备份存储目录下暂存目录的前缀，暂存的文件压缩完成后即删除
+    pub const STAGING_DIR_PREFIX: &str = ".staging_";
+
+    /// 备份存储目录下的分块去重存储目录名
+    pub const DEDUP_STORE_DIR_NAME: &str = ".dedup";
+
+    /// 导出、导入镜像归档时临时目录的前缀（位于备份存储目录或归档所在目录下）
+    pub const IMAGE_STAGING_PREFIX: &str = ".nuwax-images-";
+
+    /// 去重备份归档的第一个条目，记录每个文件引用的分块
+    pub const DEDUP_MANIFEST_NAME: &str = ".dedup-manifest.json";
+
+    /// 备份格式清单，普通备份为第一个条目，去重备份紧跟在去重清单之后
+    pub const BACKUP_MANIFEST_NAME: &str = ".backup-manifest.json";
+
+    /// 默认保留的升级前备份数量
+    pub const DEFAULT_KEEP_PRE_UPGRADE: usize = 3;
+
+    /// 默认保留的定时备份数量（每日一次约两周）
+    pub const DEFAULT_KEEP_SCHEDULED: usize = 14;
+
+    /// 备份钩子命令的默认超时时间（秒）
+    pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 60;
+
+    /// LVM 快照默认预留的写时复制空间
+    pub const DEFAULT_LVM_SNAPSHOT_SIZE: &str = "5G";
+
+    /// 检查备份目标可用空间时，默认在预估大小之外预留的比例（百分比）
+    pub const DEFAULT_FREE_SPACE_MARGIN_PERCENT: u64 = 10;
+
+    /// 文件系统快照名称前缀，后接创建时间
+    pub const SNAPSHOT_NAME_PREFIX: &str = "nuwax-backup-";
+
+    /// 创建后校验备份时最多抽样比对内容哈希的文件数
+    pub const VERIFY_SAMPLE_FILES: usize = 16;
+
     /// 获取默认备份目录路径（跨平台）
     pub fn get_backup_dir() -> PathBuf {
         Path::new(".").join(DATA_DIR_NAME).join(BACKUP_DIR_NAME)
@@ -332,6 +440,9 @@ pub mod upgrade {
     /// 临时目录名
     pub const TEMP_DIR_NAME: &str = "temp";
 
+    /// 上报和查询服务升级历史时使用的服务名
+    pub const UPGRADE_HISTORY_SERVICE_NAME: &str = "docker-service";
+
     /// 下载的docker服务包文件名,老版本的文件名,包含x86_64 和 arm64 的docker镜像
     pub const DOCKER_SERVICE_PACKAGE: &str = "docker.zip";
     /// 下载的docker服务包文件名（arm64）
@@ -342,6 +453,45 @@ pub mod upgrade {
     /// 默认更新包文件名
     pub const DEFAULT_UPDATE_PACKAGE: &str = "update.zip";
 
+    /// 全量升级清理 docker 目录前，数据目录在系统临时目录下的备份目录前缀
+    pub const TEMP_DATA_BACKUP_PREFIX: &str = "duck_data_backup_";
+
+    /// 升级时保存新旧数据库初始化脚本和差异SQL的目录名（位于工作目录下）
+    pub const TEMP_SQL_DIR_NAME: &str = "temp_sql";
+
+    /// 临时文件超过该时间（小时）未修改视为中断遗留，启动时和 `cache gc` 清理
+    pub const STALE_TEMP_ARTIFACT_HOURS: u64 = 24;
+
+    /// 自动升级部署检查点文件名
+    pub const DEPLOY_CHECKPOINT_FILE_NAME: &str = "deploy_checkpoint.json";
+
+    /// 服务包中数据目录迁移描述文件所在的目录名（位于 docker 目录下）
+    pub const MIGRATIONS_DIR_NAME: &str = "migrations";
+
+    /// 数据目录迁移日志文件名
+    pub const DATA_MIGRATION_JOURNAL_FILE_NAME: &str = "data_migrations.json";
+
+    /// 补丁替换受保护目录内容前的备份目录名（位于备份存储目录下）
+    pub const PROTECTED_CONFLICT_BACKUP_DIR_NAME: &str = "protected_conflicts";
+
+    /// 全量升级前保存用户修改过的服务文件的目录名（位于备份存储目录下）
+    pub const USER_MODIFICATIONS_DIR_NAME: &str = "user-modifications";
+
+    /// 只重启部分服务的补丁升级前保存被替换文件的目录名（位于备份存储目录下）
+    pub const PATCHED_FILES_BACKUP_DIR_NAME: &str = "patched_files";
+
+    /// 升级服务器限流时自动升级部署重新安排执行的最大次数
+    pub const RATE_LIMIT_MAX_RESCHEDULES: u32 = 5;
+
+    /// 最近一次服务启动失败诊断报告文件名
+    pub const STARTUP_FAILURE_REPORT_FILE_NAME: &str = "startup_failure_report.json";
+
+    /// 服务包解压后总大小的默认上限（MB），超过时视为压缩炸弹拒绝解压
+    pub const DEFAULT_MAX_EXTRACTED_SIZE_MB: u64 = 50 * 1024;
+
+    /// 服务包条目数量的默认上限
+    pub const DEFAULT_MAX_PACKAGE_ENTRIES: usize = 200_000;
+
     /// 获取下载文件保存目录（跨平台）
     pub fn get_download_dir() -> PathBuf {
         Path::new(".").join(DATA_DIR_NAME).join(DOWNLOAD_DIR_NAME)
@@ -351,6 +501,68 @@ pub mod upgrade {
     pub fn get_temp_extract_dir() -> PathBuf {
         Path::new(".").join(DATA_DIR_NAME).join(TEMP_DIR_NAME)
     }
+
+    /// 获取自动升级部署检查点文件路径
+    pub fn get_deploy_checkpoint_path() -> PathBuf {
+        Path::new(".")
+            .join(DATA_DIR_NAME)
+            .join(DEPLOY_CHECKPOINT_FILE_NAME)
+    }
+
+    /// 获取数据目录迁移日志文件路径
+    pub fn get_data_migration_journal_path() -> PathBuf {
+        Path::new(".")
+            .join(DATA_DIR_NAME)
+            .join(DATA_MIGRATION_JOURNAL_FILE_NAME)
+    }
+
+    /// 获取最近一次服务启动失败诊断报告的路径
+    pub fn get_startup_failure_report_path() -> PathBuf {
+        Path::new(".")
+            .join(DATA_DIR_NAME)
+            .join(STARTUP_FAILURE_REPORT_FILE_NAME)
+    }
+}
+
+/// 遥测相关常量
+pub mod telemetry {
+    /// 指标事件的 tracing target，OTLP 指标层只采集该 target 下的事件
+    ///
+    /// 事件字段按 tracing-opentelemetry 约定命名：`monotonic_counter.*`、`histogram.*`
+    pub const METRICS_TARGET: &str = "nuwax_metrics";
+
+    /// 默认上报的服务名
+    pub const DEFAULT_SERVICE_NAME: &str = "nuwax-cli";
+}
+
+/// 服务看门狗相关常量
+pub mod watchdog {
+    /// 默认健康检查间隔（秒）
+    pub const DEFAULT_CHECK_INTERVAL_SECS: u64 = 30;
+
+    /// 默认连续失败多少次后重启服务
+    pub const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+
+    /// 默认首次重启后的退避时间（秒），之后每次重启翻倍
+    pub const DEFAULT_RESTART_BACKOFF_SECS: u64 = 30;
+
+    /// 默认最大退避时间（秒）
+    pub const DEFAULT_MAX_RESTART_BACKOFF_SECS: u64 = 600;
+}
+
+/// 完成通知相关常量
+pub mod notifications {
+    /// 默认只有耗时超过该值（秒）的命令结束时才发送通知
+    pub const DEFAULT_MIN_DURATION_SECS: u64 = 60;
+}
+
+/// 系统时间校验相关常量
+pub mod clock {
+    /// 本地时间与服务器时间相差超过该值（秒）时警告
+    pub const MAX_SKEW_SECS: i64 = 300;
+
+    /// 延迟执行的任务最多可以安排在多少天之后
+    pub const MAX_SCHEDULE_DELAY_DAYS: u64 = 30;
 }
 
 /// 文件格式相关常量
@@ -358,6 +570,15 @@ pub mod file_format {
     /// ZIP文件扩展名
     pub const ZIP_EXTENSION: &str = ".zip";
 
+    /// tar.gz 文件扩展名
+    pub const TAR_GZ_EXTENSION: &str = ".tar.gz";
+
+    /// tgz 文件扩展名（tar.gz 的简写）
+    pub const TGZ_EXTENSION: &str = ".tgz";
+
+    /// tar.zst 文件扩展名
+    pub const TAR_ZST_EXTENSION: &str = ".tar.zst";
+
     /// TOML配置文件扩展名
     pub const TOML_EXTENSION: &str = ".toml";
 
@@ -375,6 +596,12 @@ pub mod file_format {
 
     /// ZIP文件通用魔术字节前缀（PK）
     pub const ZIP_MAGIC_PK_PREFIX: [u8; 2] = [0x50, 0x4B];
+
+    /// gzip 魔术字节
+    pub const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+    /// zstd 帧魔术字节
+    pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
 }
 
 /// 超时时间常量（秒）
@@ -391,6 +618,9 @@ pub mod timeout {
     /// Docker服务状态检查间隔时间
     pub const SERVICE_CHECK_INTERVAL: u64 = 2;
 
+    /// 只读命令探测 Docker 服务是否可用的等待时间
+    pub const DOCKER_PROBE_TIMEOUT: u64 = 5;
+
     /// Docker服务健康检查超时时间（用于启动后的健康检查）
     pub const HEALTH_CHECK_TIMEOUT: u64 = 180;
 
@@ -402,6 +632,24 @@ pub mod timeout {
 
     /// 服务验证前等待时间（让服务稳定）
     pub const SERVICE_VERIFY_WAIT: u64 = 5;
+
+    /// 服务停止宽限期默认值（与 docker compose 默认值一致），超时后容器被 SIGKILL
+    pub const DEFAULT_STOP_GRACE_PERIOD: u64 = 10;
+
+    /// 宽限期之外等待容器退出的额外时间
+    pub const STOP_EXIT_WAIT_MARGIN: u64 = 10;
+
+    /// 执行数据库升级前等待 MySQL 就绪的默认超时时间
+    pub const MYSQL_READY_TIMEOUT: u64 = 120;
+
+    /// MySQL 就绪检查的首次重试间隔，之后按指数退避
+    pub const MYSQL_READY_INITIAL_DELAY: u64 = 1;
+
+    /// MySQL 就绪检查的最大重试间隔
+    pub const MYSQL_READY_MAX_DELAY: u64 = 10;
+
+    /// 单次 TCP 连接检查的超时时间
+    pub const MYSQL_READY_PROBE_TIMEOUT: u64 = 3;
 }
 
 /// 网络相关常量
@@ -417,6 +665,24 @@ pub mod network {
 
     /// Docker端口映射格式示例
     pub const PORT_MAPPING_EXAMPLES: [&str; 3] = ["8080:80", "127.0.0.1:8080:80", "8080:80/tcp"];
+
+    /// 连通性诊断中 DNS 解析和 TCP 连接的超时时间（秒）
+    pub const DIAGNOSTIC_CONNECT_TIMEOUT_SECS: u64 = 5;
+
+    /// 连通性诊断中试探请求的超时时间（秒）
+    pub const DIAGNOSTIC_REQUEST_TIMEOUT_SECS: u64 = 10;
+
+    /// 影响 HTTP 请求的代理环境变量
+    pub const PROXY_ENV_VARS: [&str; 8] = [
+        "HTTPS_PROXY",
+        "https_proxy",
+        "HTTP_PROXY",
+        "http_proxy",
+        "ALL_PROXY",
+        "all_proxy",
+        "NO_PROXY",
+        "no_proxy",
+    ];
 }
 
 /// 日志和输出相关常量
@@ -432,7 +698,16 @@ pub mod logging {
     /// 日志目录名
     pub const LOG_DIR_NAME: &str = "logs";
 
-    /// 获取日志文件保存目录（跨平台）
+    /// 日志环境变量前缀，如 `NUWAX_LOG_FILE`
+    pub const ENV_PREFIX: &str = "NUWAX_";
+
+    /// 日志环境变量名（不含前缀）
+    pub const LOG_ENV_VARS: [&str; 4] = ["LOG_FILE", "LOG_ROTATION", "LOG_MAX_FILES", "LOG_SPLIT"];
+
+    /// `--debug-http` 未指定文件时的 HTTP 调试记录文件名
+    pub const HTTP_DEBUG_LOG_FILE_NAME: &str = "http-debug.log";
+
+    /// 无法确定用户目录时的默认日志目录（跨平台）
     pub fn get_log_dir() -> PathBuf {
         Path::new(".").join(DATA_DIR_NAME).join(LOG_DIR_NAME)
     }
@@ -447,6 +722,24 @@ pub mod cron {
     pub const CRON_FIELDS_COUNT: usize = 5;
 }
 
+/// 旧版 duck-cli 布局相关常量，用于启动时自动迁移
+pub mod legacy {
+    /// 旧数据库文件名（位于数据目录下）
+    pub const DATABASE_FILE_NAME: &str = "duck_client.db";
+
+    /// 旧默认缓存目录名
+    pub const CACHE_DIR_NAME: &str = "cacheDuckData";
+
+    /// 旧日志环境变量前缀，如 `DUCK_LOG_FILE`
+    pub const ENV_PREFIX: &str = "DUCK_";
+
+    /// 旧文档示例中的日志文件名
+    pub const LOG_FILE_NAME: &str = "duck.log";
+
+    /// 迁移完成后留在数据目录下的说明文件名
+    pub const MIGRATION_NOTE_FILE_NAME: &str = "MIGRATED_FROM_DUCK_CLI.txt";
+}
+
 /// 应用配置相关常量
 pub mod config {
     use std::path::{Path, PathBuf};
@@ -458,14 +751,26 @@ pub mod config {
     pub const CONFIG_FILE_NAME: &str = "config.toml";
 
     /// 数据库文件名
-    pub const DATABASE_FILE_NAME: &str = "duck_client.db";
+    pub const DATABASE_FILE_NAME: &str = "nuwax_client.db";
+
+    /// 数据库被其他进程（如图形界面和命令行同时运行）占用时，等待其释放的默认时间（秒）
+    pub const DATABASE_BUSY_TIMEOUT_SECS: u64 = 30;
+
+    /// 覆盖数据库等待时间（秒）的环境变量
+    pub const DATABASE_BUSY_TIMEOUT_ENV: &str = "NUWAX_DB_BUSY_TIMEOUT";
 
     /// 缓存目录名
-    pub const CACHE_DIR_NAME: &str = "cacheDuckData";
+    pub const CACHE_DIR_NAME: &str = "cacheNuwaxData";
 
     /// 下载目录名
     pub const DOWNLOAD_DIR_NAME: &str = "download";
 
+    /// 服务包内容寻址存储目录名（位于缓存目录下）
+    pub const PACKAGE_STORE_DIR_NAME: &str = "store";
+
+    /// 下载缓存默认保留的版本：当前部署版本和升级前的版本
+    pub const DEFAULT_CACHE_KEEP_VERSIONS: [&str; 2] = ["current", "previous"];
+
     /// 获取默认配置文件路径（跨平台）
     pub fn get_config_file_path() -> PathBuf {
         Path::new(".").join(DATA_DIR_NAME).join(CONFIG_FILE_NAME)
@@ -476,6 +781,15 @@ pub mod config {
         Path::new(".").join(DATA_DIR_NAME).join(DATABASE_FILE_NAME)
     }
 
+    /// 数据库被其他进程占用时的等待时间，环境变量优先
+    pub fn get_database_busy_timeout() -> std::time::Duration {
+        let secs = std::env::var(DATABASE_BUSY_TIMEOUT_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(DATABASE_BUSY_TIMEOUT_SECS);
+        std::time::Duration::from_secs(secs)
+    }
+
     /// 获取默认缓存目录（跨平台）
     pub fn get_default_cache_dir() -> PathBuf {
         Path::new(".").join(CACHE_DIR_NAME)
@@ -1,6 +1,9 @@
 use super::types::DockerManager;
+use crate::constants::timeout::DOCKER_PROBE_TIMEOUT;
+use crate::service_preset;
 use anyhow::Result;
 use std::process::Stdio;
+use std::time::Duration;
 use tokio::process::Command;
 use tracing::{debug, info, warn};
 
@@ -41,6 +44,27 @@ impl DockerManager {
         Ok(())
     }
 
+    /// 探测 Docker 服务是否可用，返回服务端版本
+    ///
+    /// 不输出日志且限制等待时间，供 `status` 等只读命令在没有 Docker 时跳过相关信息
+    pub async fn probe_docker_daemon(&self) -> Result<String> {
+        let probe = Command::new("docker")
+            .args(["info", "--format", "{{.ServerVersion}}"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .output();
+        let output = tokio::time::timeout(Duration::from_secs(DOCKER_PROBE_TIMEOUT), probe)
+            .await
+            .map_err(|_| anyhow::anyhow!("Docker 服务 {DOCKER_PROBE_TIMEOUT} 秒内无响应"))?
+            .map_err(|e| anyhow::anyhow!("Docker 未安装或不在 PATH 中: {e}"))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("Docker 服务未运行: {}", stderr.trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
     /// 检查 Docker 和 Docker Compose 是否可用
     pub async fn check_prerequisites(&self) -> Result<()> {
         self.check_prerequisites_with_path(None).await
@@ -106,6 +130,7 @@ impl DockerManager {
     /// 使用 docker compose 子命令
     async fn run_docker_compose_subcommand(&self, args: &[&str]) -> Result<std::process::Output> {
         let compose_path = self.compose_file.to_string_lossy().to_string();
+        let preset_path = self.preset_override_path();
         let mut cmd_args = vec!["compose"];
 
         // 如果指定了项目名称，添加 -p 参数
@@ -114,6 +139,9 @@ impl DockerManager {
         }
 
         cmd_args.extend(&["-f", &compose_path]);
+        if let Some(ref preset_path) = preset_path {
+            cmd_args.extend(&["-f", preset_path]);
+        }
         cmd_args.extend(args);
 
         debug!("尝试使用docker compose子命令: {:?}", cmd_args);
@@ -123,6 +151,7 @@ impl DockerManager {
     /// 使用独立的 docker-compose 命令
     async fn run_docker_compose_standalone(&self, args: &[&str]) -> Result<std::process::Output> {
         let compose_path = self.compose_file.to_string_lossy().to_string();
+        let preset_path = self.preset_override_path();
         let mut cmd_args: Vec<&str> = vec![];
 
         // 如果指定了项目名称，添加 -p 参数
@@ -131,6 +160,9 @@ impl DockerManager {
         }
 
         cmd_args.extend(&["-f", &compose_path]);
+        if let Some(ref preset_path) = preset_path {
+            cmd_args.extend(&["-f", preset_path]);
+        }
         cmd_args.extend(args);
 
         debug!("尝试使用docker-compose独立命令: {:?}", cmd_args);
@@ -144,6 +176,12 @@ impl DockerManager {
         Ok(output)
     }
 
+    /// 服务规格预设的 compose 覆盖文件，存在时与 compose 文件一起加载
+    fn preset_override_path(&self) -> Option<String> {
+        let path = service_preset::override_path(&self.compose_file);
+        path.exists().then(|| path.to_string_lossy().to_string())
+    }
+
     /// 执行 docker 命令
     pub(crate) async fn run_docker_command(&self, args: &[&str]) -> Result<std::process::Output> {
         debug!("执行docker命令: {:?}", args);
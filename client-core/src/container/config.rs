@@ -1,5 +1,6 @@
 use super::types::{DockerManager, ServiceConfig};
 use crate::DuckError;
+use crate::service_preset;
 use anyhow::Result;
 use docker_compose_types as dct;
 use quick_cache::sync::Cache;
@@ -161,14 +162,18 @@ impl DockerManager {
         Ok(ServiceConfig { restart })
     }
 
-    /// 获取 docker-compose.yml 中定义的所有服务名称
+    /// 获取 docker-compose.yml 中定义的所有服务名称（不含服务规格预设停用的服务）
     pub async fn get_compose_service_names(&self) -> Result<HashSet<String>> {
         // 使用已加载的compose_config，无需重新解析
         let services = &self.load_compose_config()?.services;
+        let disabled =
+            service_preset::disabled_services(&service_preset::override_path(&self.compose_file));
         let mut service_names = HashSet::new();
 
         for (service_name, _) in services.0.iter() {
-            service_names.insert(service_name.to_string());
+            if !disabled.contains(service_name) {
+                service_names.insert(service_name.to_string());
+            }
         }
 
         Ok(service_names)
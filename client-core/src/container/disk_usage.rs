@@ -0,0 +1,213 @@
+use super::types::DockerManager;
+use crate::DuckError;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// 单个 Docker 资源（镜像或数据卷）的磁盘占用
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DockerResourceUsage {
+    pub name: String,
+    pub size_bytes: u64,
+}
+
+/// compose 项目相关的 Docker 磁盘占用
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DockerDiskUsage {
+    pub images: Vec<DockerResourceUsage>,
+    pub volumes: Vec<DockerResourceUsage>,
+}
+
+impl DockerDiskUsage {
+    /// 镜像和数据卷的总占用
+    pub fn total_bytes(&self) -> u64 {
+        self.images
+            .iter()
+            .chain(&self.volumes)
+            .map(|usage| usage.size_bytes)
+            .sum()
+    }
+}
+
+impl DockerManager {
+    /// 统计 compose 项目使用的镜像和数据卷占用
+    ///
+    /// 镜像按 compose 文件中声明的镜像匹配，数据卷按 compose 项目标签匹配
+    pub async fn project_disk_usage(&self) -> Result<DockerDiskUsage> {
+        let compose = self.load_compose_config()?;
+        let images: HashSet<String> = compose
+            .services
+            .0
+            .values()
+            .filter_map(|service| service.as_ref().and_then(|s| s.image.as_deref()))
+            .map(normalize_image_ref)
+            .collect();
+        let project = self.get_compose_project_name();
+
+        let output = self
+            .run_docker_command(&["system", "df", "-v", "--format", "{{json .}}"])
+            .await?;
+        if !output.status.success() {
+            return Err(DuckError::Docker(format!(
+                "获取Docker磁盘占用失败: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+            .into());
+        }
+
+        parse_system_df(&String::from_utf8_lossy(&output.stdout), &project, &images)
+    }
+}
+
+/// 解析 `docker system df -v --format '{{json .}}'` 的输出，只保留属于项目的镜像和数据卷
+pub fn parse_system_df(
+    output: &str,
+    project: &str,
+    images: &HashSet<String>,
+) -> Result<DockerDiskUsage> {
+    let value: Value = serde_json::from_str(output.trim())
+        .map_err(|e| DuckError::Docker(format!("解析 docker system df 输出失败: {e}")))?;
+
+    let mut usage = DockerDiskUsage::default();
+
+    for image in entries(&value, "Images") {
+        let name = format!("{}:{}", field(image, "Repository"), field(image, "Tag"));
+        if images.contains(&name) {
+            usage.images.push(DockerResourceUsage {
+                name,
+                size_bytes: parse_docker_size(field(image, "Size")).unwrap_or(0),
+            });
+        }
+    }
+
+    let project_label = format!("com.docker.compose.project={project}");
+    for volume in entries(&value, "Volumes") {
+        let in_project = field(volume, "Labels")
+            .split(',')
+            .any(|label| label == project_label);
+        if in_project {
+            usage.volumes.push(DockerResourceUsage {
+                name: field(volume, "Name").to_string(),
+                size_bytes: parse_docker_size(field(volume, "Size")).unwrap_or(0),
+            });
+        }
+    }
+
+    usage.images.sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes));
+    usage
+        .volumes
+        .sort_by(|a, b| b.size_bytes.cmp(&a.size_bytes));
+    Ok(usage)
+}
+
+/// 解析 Docker 输出的大小（如 `187MB`、`1.2GB`、`0B`）
+///
+/// Docker 使用十进制单位（1kB = 1000B），同时兼容 `KiB`、`MiB` 等二进制单位
+pub fn parse_docker_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number: f64 = number.trim().parse().ok()?;
+
+    let multiplier: f64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "kb" => 1e3,
+        "mb" => 1e6,
+        "gb" => 1e9,
+        "tb" => 1e12,
+        "pb" => 1e15,
+        "kib" => 1024.0,
+        "mib" => 1024.0 * 1024.0,
+        "gib" => 1024.0 * 1024.0 * 1024.0,
+        "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+
+    Some((number * multiplier).round() as u64)
+}
+
+/// 补全镜像引用的默认标签，与 `docker system df` 输出的 `仓库:标签` 对齐
+fn normalize_image_ref(image: &str) -> String {
+    let image = image.split('@').next().unwrap_or(image);
+    let name_start = image.rfind('/').map_or(0, |pos| pos + 1);
+    if image[name_start..].contains(':') {
+        image.to_string()
+    } else {
+        format!("{image}:latest")
+    }
+}
+
+fn entries<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_docker_size() {
+        assert_eq!(parse_docker_size("0B"), Some(0));
+        assert_eq!(parse_docker_size("12.5kB"), Some(12_500));
+        assert_eq!(parse_docker_size("187MB"), Some(187_000_000));
+        assert_eq!(parse_docker_size("1.2GB"), Some(1_200_000_000));
+        assert_eq!(parse_docker_size("2MiB"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_docker_size("N/A"), None);
+    }
+
+    #[test]
+    fn test_normalize_image_ref() {
+        assert_eq!(normalize_image_ref("mysql"), "mysql:latest");
+        assert_eq!(normalize_image_ref("mysql:8.0"), "mysql:8.0");
+        assert_eq!(
+            normalize_image_ref("registry.example.com:5000/nuwax/backend"),
+            "registry.example.com:5000/nuwax/backend:latest"
+        );
+    }
+
+    #[test]
+    fn test_parse_system_df_filters_project_resources() {
+        let output = r#"{
+            "Images": [
+                {"Repository": "mysql", "Tag": "8.0", "Size": "603MB"},
+                {"Repository": "redis", "Tag": "latest", "Size": "117MB"},
+                {"Repository": "postgres", "Tag": "16", "Size": "432MB"}
+            ],
+            "Volumes": [
+                {"Name": "nuwax_minio", "Labels": "com.docker.compose.project=nuwax,com.docker.compose.volume=minio", "Size": "2.5GB"},
+                {"Name": "other_data", "Labels": "com.docker.compose.project=other", "Size": "1GB"},
+                {"Name": "anonymous", "Labels": "", "Size": "10MB"}
+            ]
+        }"#;
+        let images: HashSet<String> = ["mysql:8.0", "redis:latest"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let usage = parse_system_df(output, "nuwax", &images).unwrap();
+        let image_names: Vec<_> = usage.images.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(image_names, ["mysql:8.0", "redis:latest"]);
+        assert_eq!(
+            usage.volumes,
+            vec![DockerResourceUsage {
+                name: "nuwax_minio".to_string(),
+                size_bytes: 2_500_000_000,
+            }]
+        );
+        assert_eq!(
+            usage.total_bytes(),
+            603_000_000 + 117_000_000 + 2_500_000_000
+        );
+    }
+}
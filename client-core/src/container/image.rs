@@ -54,6 +54,60 @@ impl DockerManager {
         Err(anyhow::anyhow!("无法解析docker load输出: {stdout}"))
     }
 
+    /// 拉取单个镜像，`expected_digest`（`sha256:...`）不为空时校验仓库 digest
+    ///
+    /// digest 不一致时删除拉取的镜像，避免使用与服务包不同的镜像
+    pub async fn pull_image_verified(
+        &self,
+        reference: &str,
+        expected_digest: Option<&str>,
+    ) -> Result<()> {
+        info!("执行docker pull命令: docker pull {}", reference);
+        let output = self.run_docker_command(&["pull", reference]).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "拉取镜像 {reference} 失败: {}",
+                stderr.trim()
+            ));
+        }
+
+        let Some(expected) = expected_digest else {
+            return Ok(());
+        };
+        let output = self
+            .run_docker_command(&[
+                "image",
+                "inspect",
+                "--format",
+                "{{range .RepoDigests}}{{println .}}{{end}}",
+                reference,
+            ])
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "无法读取镜像 {reference} 的 digest: {}",
+                stderr.trim()
+            ));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let digests: Vec<&str> = stdout
+            .lines()
+            .filter_map(|line| line.trim().rsplit_once('@').map(|(_, digest)| digest))
+            .collect();
+        debug!("镜像 {} 的 digest: {:?}", reference, digests);
+
+        if !digests.contains(&expected) {
+            let _ = self.run_docker_command(&["rmi", reference]).await;
+            return Err(anyhow::anyhow!(
+                "镜像 {reference} 的 digest 与清单不一致: 期望 {expected}, 实际 {}",
+                digests.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
     /// 拉取最新镜像
     pub async fn pull_images(&self) -> Result<()> {
         self.check_prerequisites().await?;
@@ -67,4 +121,87 @@ impl DockerManager {
 
         Ok(())
     }
+
+    /// compose 文件引用的全部镜像（已展开环境变量），按名称排序去重
+    pub async fn compose_images(&self) -> Result<Vec<String>> {
+        let output = self.run_compose_command(&["config", "--images"]).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "无法读取compose文件中的镜像: {}",
+                stderr.trim()
+            ));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut images: Vec<String> = stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        images.sort();
+        images.dedup();
+        Ok(images)
+    }
+
+    /// 查询本地镜像的 ID 和平台（如 `linux/amd64`），镜像不存在时返回 None
+    pub async fn inspect_image_platform(
+        &self,
+        reference: &str,
+    ) -> Result<Option<(String, String)>> {
+        let output = self
+            .run_docker_command(&[
+                "image",
+                "inspect",
+                "--format",
+                "{{.Id}} {{.Os}}/{{.Architecture}}",
+                reference,
+            ])
+            .await?;
+        if !output.status.success() {
+            debug!(
+                "镜像 {} 不存在: {}",
+                reference,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return Ok(None);
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .trim()
+            .split_once(' ')
+            .map(|(id, platform)| (id.to_string(), platform.to_string())))
+    }
+
+    /// 使用 docker save 把镜像（保留标签）导出到一个 tar 文件
+    pub async fn save_images(&self, references: &[String], output_path: &Path) -> Result<()> {
+        let output_path = output_path.to_string_lossy().to_string();
+        let mut args = vec!["save", "-o", output_path.as_str()];
+        args.extend(references.iter().map(String::as_str));
+        info!("执行docker save命令: 导出 {} 个镜像", references.len());
+
+        let output = self.run_docker_command(&args).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("导出镜像失败: {}", stderr.trim()));
+        }
+        Ok(())
+    }
+
+    /// 加载 docker save 导出的 tar 文件，返回加载的全部镜像名称
+    pub async fn load_images_from_tar(&self, tar_path: &Path) -> Result<Vec<String>> {
+        let output = self
+            .run_docker_command(&["load", "-i", &tar_path.to_string_lossy()])
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("加载镜像失败: {}", stderr.trim()));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .lines()
+            .filter_map(|line| line.strip_prefix("Loaded image:"))
+            .map(|name| name.trim().to_string())
+            .collect())
+    }
 }
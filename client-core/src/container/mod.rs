@@ -1,6 +1,7 @@
 // 模块声明
 mod command;
 mod config;
+pub mod disk_usage;
 mod image;
 mod service;
 pub mod types;
@@ -9,9 +10,11 @@ pub mod volumes;
 #[cfg(test)]
 mod config_test;
 mod modern_docker;
+pub mod orphans;
+pub mod project_migration;
 
 // 重新导出公共API
-pub use types::{DockerManager, ServiceConfig, ServiceInfo, ServiceStatus};
+pub use types::{ContainerExitState, DockerManager, ServiceConfig, ServiceInfo, ServiceStatus};
 
 // 导入测试模块
 #[cfg(test)]
@@ -0,0 +1,138 @@
+//! 孤立容器检测
+//!
+//! 新版本服务包删除某个服务后，旧版本创建的容器仍带着当前项目的标签，可能继续运行，
+//! 干扰健康检查和冷备份。带有当前项目标签、但服务已不在 compose 文件中的容器视为孤立容器；
+//! `compose run` 创建的一次性容器不计入。
+
+use super::project_migration::docker_stdout;
+use super::types::DockerManager;
+use crate::constants::docker::{
+    COMPOSE_ONEOFF_LABEL, COMPOSE_PROJECT_LABEL, COMPOSE_SERVICE_LABEL,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// 服务已从 compose 文件中删除的容器
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrphanContainer {
+    /// 容器名称
+    pub name: String,
+    /// 容器标签中记录的服务名
+    pub service: String,
+    pub image: String,
+    /// 容器状态（running、exited 等）
+    pub state: String,
+}
+
+impl OrphanContainer {
+    pub fn is_running(&self) -> bool {
+        self.state == "running"
+    }
+}
+
+/// `docker ps` 的输出格式：名称、服务名、是否一次性容器、镜像、状态，以制表符分隔
+fn listing_format() -> String {
+    format!(
+        "{{{{.Names}}}}\t{{{{.Label \"{COMPOSE_SERVICE_LABEL}\"}}}}\t{{{{.Label \"{COMPOSE_ONEOFF_LABEL}\"}}}}\t{{{{.Image}}}}\t{{{{.State}}}}"
+    )
+}
+
+/// 从 `docker ps` 输出中找出服务不在 `services` 中的容器
+pub fn parse_orphan_listing(output: &str, services: &HashSet<String>) -> Vec<OrphanContainer> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().split('\t');
+            let name = fields.next()?.trim();
+            let service = fields.next()?.trim();
+            let oneoff = fields.next()?.trim();
+            let image = fields.next().unwrap_or_default().trim();
+            let state = fields.next().unwrap_or_default().trim();
+            let orphan = !name.is_empty()
+                && !service.is_empty()
+                && !oneoff.eq_ignore_ascii_case("true")
+                && !services.contains(service);
+            orphan.then(|| OrphanContainer {
+                name: name.to_string(),
+                service: service.to_string(),
+                image: image.to_string(),
+                state: state.to_string(),
+            })
+        })
+        .collect()
+}
+
+impl DockerManager {
+    /// 查找当前项目的孤立容器
+    ///
+    /// 按 compose 文件中的全部服务判断（包括服务规格预设停用的服务）
+    pub async fn find_orphan_containers(&self) -> Result<Vec<OrphanContainer>> {
+        let services: HashSet<String> = self
+            .load_compose_config()?
+            .services
+            .0
+            .keys()
+            .cloned()
+            .collect();
+        let filter = format!(
+            "label={COMPOSE_PROJECT_LABEL}={}",
+            self.get_compose_project_name()
+        );
+        let format = listing_format();
+        let output = self
+            .run_docker_command(&["ps", "-a", "--filter", &filter, "--format", &format])
+            .await?;
+        Ok(parse_orphan_listing(
+            &docker_stdout(output, "列出项目容器")?,
+            &services,
+        ))
+    }
+
+    /// 停止并删除孤立容器（保留数据卷）
+    pub async fn remove_orphan_containers(&self, orphans: &[OrphanContainer]) -> Result<()> {
+        if orphans.is_empty() {
+            return Ok(());
+        }
+        let mut args = vec!["rm", "-f"];
+        args.extend(orphans.iter().map(|orphan| orphan.name.as_str()));
+        docker_stdout(self.run_docker_command(&args).await?, "删除孤立容器")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_orphan_listing() {
+        let services: HashSet<String> =
+            ["mysql", "backend"].iter().map(|s| s.to_string()).collect();
+        let output = "docker-mysql-1\tmysql\tFalse\tmysql:8.0\trunning\n\
+                      docker-legacy-worker-1\tlegacy-worker\tFalse\tworker:1.0\trunning\n\
+                      docker-old-job-1\told-job\t\tjob:1.0\texited\n\
+                      docker-backend-run-1a2b\tlegacy-worker\tTrue\tworker:1.0\texited\n\
+                      standalone\t\t\tnginx\trunning\n";
+        let orphans = parse_orphan_listing(output, &services);
+        assert_eq!(
+            orphans,
+            vec![
+                OrphanContainer {
+                    name: "docker-legacy-worker-1".to_string(),
+                    service: "legacy-worker".to_string(),
+                    image: "worker:1.0".to_string(),
+                    state: "running".to_string(),
+                },
+                OrphanContainer {
+                    name: "docker-old-job-1".to_string(),
+                    service: "old-job".to_string(),
+                    image: "job:1.0".to_string(),
+                    state: "exited".to_string(),
+                },
+            ]
+        );
+        assert!(orphans[0].is_running());
+        assert!(!orphans[1].is_running());
+    }
+}
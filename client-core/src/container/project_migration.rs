@@ -0,0 +1,284 @@
+//! Compose 项目重命名
+//!
+//! Compose 按 `com.docker.compose.project` 标签识别容器、网络和数据卷，直接修改项目名后
+//! 旧项目的容器和命名数据卷不再被管理。迁移时先停止并删除旧项目的容器（保留数据卷），
+//! 把 `<旧项目>_` 前缀的命名数据卷复制为 `<新项目>_` 前缀，再以新项目名重新创建容器。
+//! 外部数据卷和自定义 name 的数据卷不随项目名变化，继续原样使用。
+
+use super::types::DockerManager;
+use crate::DuckError;
+use crate::constants::docker::{
+    COMPOSE_PROJECT_LABEL, COMPOSE_VOLUME_LABEL, VOLUME_MIGRATION_IMAGE,
+};
+use anyhow::Result;
+use serde::Serialize;
+use std::process::Output;
+use tracing::{error, info, warn};
+
+/// 迁移到新项目名的数据卷
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VolumeMove {
+    pub from: String,
+    pub to: String,
+}
+
+/// 项目迁移结果
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ProjectMigrationReport {
+    pub from: String,
+    pub to: String,
+    /// 旧项目中被停止并在新项目下重建的容器
+    pub containers: Vec<String>,
+    /// 复制到新项目名下的数据卷，旧数据卷已删除
+    pub moved_volumes: Vec<VolumeMove>,
+    /// 名称不含项目前缀、由新项目继续直接使用的数据卷
+    pub shared_volumes: Vec<String>,
+}
+
+/// 检查 compose 项目名：只能包含小写字母、数字、`-` 和 `_`，并以字母或数字开头
+pub fn validate_project_name(name: &str) -> Result<(), DuckError> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(DuckError::Docker(format!(
+            "无效的项目名称: {name}（只能包含小写字母、数字、- 和 _，并以字母或数字开头）"
+        )));
+    }
+    Ok(())
+}
+
+/// 数据卷在新项目下的名称，名称不含 `<旧项目>_` 前缀时返回 `None`
+pub fn migrated_volume_name(volume: &str, from: &str, to: &str) -> Option<String> {
+    volume
+        .strip_prefix(from)
+        .and_then(|rest| rest.strip_prefix('_'))
+        .filter(|key| !key.is_empty())
+        .map(|key| format!("{to}_{key}"))
+}
+
+impl DockerManager {
+    /// 使用相同 compose 文件、指定项目名的管理器
+    pub fn for_project(&self, project_name: &str) -> DockerManager {
+        DockerManager {
+            project_name: Some(project_name.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// 把 compose 项目 `from` 的容器和命名数据卷迁移到项目 `to`
+    ///
+    /// 数据卷全部复制成功后才删除旧数据卷；任一数据卷复制失败时删除已创建的新数据卷，
+    /// 以旧项目名重新启动服务并返回错误
+    pub async fn migrate_project(&self, from: &str, to: &str) -> Result<ProjectMigrationReport> {
+        validate_project_name(from)?;
+        validate_project_name(to)?;
+        if from == to {
+            return Err(DuckError::Docker(format!("新旧项目名称相同: {from}")).into());
+        }
+        self.check_prerequisites().await?;
+
+        let containers = self
+            .list_project_resources(&["ps", "-a", "--format", "{{.Names}}"], from)
+            .await?;
+        let volumes = self
+            .list_project_resources(&["volume", "ls", "--format", "{{.Name}}"], from)
+            .await?;
+        if containers.is_empty() && volumes.is_empty() {
+            return Err(DuckError::Docker(format!("未找到项目 {from} 的容器或数据卷")).into());
+        }
+        let existing = self
+            .list_project_resources(&["ps", "-a", "--format", "{{.Names}}"], to)
+            .await?;
+        if !existing.is_empty() {
+            return Err(DuckError::Docker(format!(
+                "项目 {to} 已有容器: {}，请先停止并删除后再迁移",
+                existing.join(", ")
+            ))
+            .into());
+        }
+
+        let old_project = self.for_project(from);
+        if !containers.is_empty() {
+            info!("⏹️ 停止项目 {} 的 {} 个容器...", from, containers.len());
+            old_project.stop_services().await?;
+        }
+
+        let mut report = ProjectMigrationReport {
+            from: from.to_string(),
+            to: to.to_string(),
+            containers,
+            ..Default::default()
+        };
+        for volume in volumes {
+            match migrated_volume_name(&volume, from, to) {
+                Some(target) => {
+                    info!("📦 复制数据卷 {} -> {}", volume, target);
+                    if let Err(e) = self.copy_volume(&volume, &target, to).await {
+                        error!("❌ 复制数据卷 {} 失败: {}", volume, e);
+                        self.abort_migration(&old_project, &report).await;
+                        return Err(e);
+                    }
+                    report.moved_volumes.push(VolumeMove {
+                        from: volume,
+                        to: target,
+                    });
+                }
+                None => {
+                    info!("🔗 数据卷 {} 不含项目前缀，新项目继续使用", volume);
+                    report.shared_volumes.push(volume);
+                }
+            }
+        }
+
+        for moved in &report.moved_volumes {
+            if let Err(e) = self.remove_volume(&moved.from).await {
+                warn!("⚠️ 删除旧数据卷 {} 失败: {}", moved.from, e);
+            }
+        }
+
+        info!("🚀 以项目名 {} 重新创建容器...", to);
+        self.for_project(to).start_services().await?;
+        Ok(report)
+    }
+
+    /// 迁移失败时删除已创建的新数据卷，并以旧项目名恢复服务
+    async fn abort_migration(&self, old_project: &DockerManager, report: &ProjectMigrationReport) {
+        for moved in &report.moved_volumes {
+            if let Err(e) = self.remove_volume(&moved.to).await {
+                warn!("⚠️ 删除未完成迁移的数据卷 {} 失败: {}", moved.to, e);
+            }
+        }
+        if !report.containers.is_empty() {
+            warn!("↩️ 以项目名 {} 恢复服务...", report.from);
+            if let Err(e) = old_project.start_services().await {
+                error!("❌ 恢复项目 {} 的服务失败: {}", report.from, e);
+            }
+        }
+    }
+
+    /// 按项目标签列出容器或数据卷名称
+    async fn list_project_resources(&self, args: &[&str], project: &str) -> Result<Vec<String>> {
+        let filter = format!("label={COMPOSE_PROJECT_LABEL}={project}");
+        let mut args = args.to_vec();
+        args.extend(["--filter", &filter]);
+        let stdout = docker_stdout(self.run_docker_command(&args).await?, "列出项目资源")?;
+        Ok(stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// 创建带新项目标签的数据卷，并用临时容器复制旧数据卷的内容，复制失败时删除新数据卷
+    async fn copy_volume(&self, source: &str, target: &str, project: &str) -> Result<()> {
+        if self
+            .run_docker_command(&["volume", "inspect", target])
+            .await?
+            .status
+            .success()
+        {
+            return Err(DuckError::Docker(format!("数据卷 {target} 已存在")).into());
+        }
+
+        let key = target
+            .strip_prefix(project)
+            .and_then(|rest| rest.strip_prefix('_'))
+            .unwrap_or(target);
+        let project_label = format!("{COMPOSE_PROJECT_LABEL}={project}");
+        let volume_label = format!("{COMPOSE_VOLUME_LABEL}={key}");
+        docker_stdout(
+            self.run_docker_command(&[
+                "volume",
+                "create",
+                "--label",
+                &project_label,
+                "--label",
+                &volume_label,
+                target,
+            ])
+            .await?,
+            "创建数据卷",
+        )?;
+
+        let source_mount = format!("{source}:/from:ro");
+        let target_mount = format!("{target}:/to");
+        let copied = self
+            .run_docker_command(&[
+                "run",
+                "--rm",
+                "-v",
+                &source_mount,
+                "-v",
+                &target_mount,
+                VOLUME_MIGRATION_IMAGE,
+                "sh",
+                "-c",
+                "cp -a /from/. /to/",
+            ])
+            .await
+            .and_then(|output| docker_stdout(output, "复制数据卷内容"));
+        if let Err(e) = copied {
+            if let Err(remove_err) = self.remove_volume(target).await {
+                warn!("⚠️ 删除未完成复制的数据卷 {} 失败: {}", target, remove_err);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn remove_volume(&self, volume: &str) -> Result<()> {
+        docker_stdout(
+            self.run_docker_command(&["volume", "rm", volume]).await?,
+            "删除数据卷",
+        )?;
+        Ok(())
+    }
+}
+
+/// 命令成功时返回标准输出，否则返回包含标准错误的错误
+pub(super) fn docker_stdout(output: Output, action: &str) -> Result<String> {
+    if !output.status.success() {
+        return Err(DuckError::Docker(format!(
+            "{action}失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_project_name() {
+        assert!(validate_project_name("nuwax").is_ok());
+        assert!(validate_project_name("nuwax-prod_2").is_ok());
+        assert!(validate_project_name("").is_err());
+        assert!(validate_project_name("Nuwax").is_err());
+        assert!(validate_project_name("-nuwax").is_err());
+        assert!(validate_project_name("nuwax prod").is_err());
+    }
+
+    #[test]
+    fn test_migrated_volume_name() {
+        assert_eq!(
+            migrated_volume_name("docker_mysql_data", "docker", "nuwax"),
+            Some("nuwax_mysql_data".to_string())
+        );
+        // 自定义 name 或其他项目的数据卷不迁移
+        assert_eq!(migrated_volume_name("mysql_data", "docker", "nuwax"), None);
+        assert_eq!(
+            migrated_volume_name("dockerx_data", "docker", "nuwax"),
+            None
+        );
+        assert_eq!(migrated_volume_name("docker_", "docker", "nuwax"), None);
+    }
+}
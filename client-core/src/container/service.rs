@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use super::types::{DockerManager, ServiceInfo, ServiceStatus};
+use super::types::{ContainerExitState, DockerManager, ServiceInfo, ServiceStatus};
 use crate::constants::timeout;
 use anyhow::Result;
 use ducker::docker::{container::DockerContainer, util::new_local_docker_connection};
@@ -66,6 +66,33 @@ impl DockerManager {
         Ok(())
     }
 
+    /// 拆除部署：停止并删除项目的全部容器（包括孤立容器）和网络，
+    /// `remove_volumes` 时同时删除 compose 文件中声明的命名数据卷
+    pub async fn teardown(&self, remove_volumes: bool) -> Result<()> {
+        self.check_prerequisites().await?;
+
+        let mut args = vec!["down", "--remove-orphans"];
+        if remove_volumes {
+            args.push("--volumes");
+        }
+        let output = self.run_compose_command(&args).await?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let exit_code = output.status.code().unwrap_or(-1);
+
+            let error_msg = format!(
+                "拆除服务失败 (退出码: {exit_code}):\n标准错误: {stderr}\n标准输出: {stdout}"
+            );
+
+            error!("{}", error_msg);
+            return Err(anyhow::anyhow!(error_msg));
+        }
+
+        Ok(())
+    }
+
     /// 重启所有服务
     pub async fn restart_services(&self) -> Result<()> {
         self.stop_services().await?;
@@ -110,6 +137,169 @@ impl DockerManager {
         Ok(())
     }
 
+    /// 停止单个服务，容器在宽限期内未退出时由 docker 发送 SIGKILL
+    pub async fn stop_service_with_timeout(
+        &self,
+        service_name: &str,
+        grace_period_secs: u64,
+    ) -> Result<()> {
+        let grace_period = grace_period_secs.to_string();
+        let output = self
+            .run_compose_command(&["stop", "-t", &grace_period, service_name])
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let exit_code = output.status.code().unwrap_or(-1);
+
+            let error_msg = format!(
+                "停止服务 {service_name} 失败 (退出码: {exit_code}):\n标准错误: {stderr}\n标准输出: {stdout}"
+            );
+
+            error!("{}", error_msg);
+            return Err(anyhow::anyhow!(error_msg));
+        }
+
+        Ok(())
+    }
+
+    /// 获取服务所有容器（包括已停止的）的状态和退出码
+    pub async fn get_service_exit_states(
+        &self,
+        service_name: &str,
+    ) -> Result<Vec<ContainerExitState>> {
+        let output = self
+            .run_compose_command(&["ps", "-a", "-q", service_name])
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "查询服务 {service_name} 的容器失败: {stderr}"
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let container_ids: Vec<&str> = stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        if container_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut args = vec![
+            "inspect",
+            "--format",
+            "{{.Name}} {{.State.Status}} {{.State.ExitCode}}",
+        ];
+        args.extend(container_ids);
+        let output = self.run_docker_command(&args).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "查询服务 {service_name} 的容器状态失败: {stderr}"
+            ));
+        }
+
+        Ok(parse_container_exit_states(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+
+    /// 获取服务运行中的容器 ID，服务没有运行中的容器时返回 `None`
+    pub async fn get_service_container_id(&self, service_name: &str) -> Result<Option<String>> {
+        let output = self
+            .run_compose_command(&["ps", "-q", service_name])
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "查询服务 {service_name} 的容器失败: {stderr}"
+            ));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string))
+    }
+
+    /// 获取服务运行中容器的健康检查状态（starting / healthy / unhealthy）
+    ///
+    /// 服务没有运行中的容器或未配置 healthcheck 时返回 `None`
+    pub async fn get_service_health(&self, service_name: &str) -> Result<Option<String>> {
+        let Some(container_id) = self.get_service_container_id(service_name).await? else {
+            return Ok(None);
+        };
+
+        let output = self
+            .run_docker_command(&[
+                "inspect",
+                "--format",
+                "{{if .State.Health}}{{.State.Health.Status}}{{end}}",
+                &container_id,
+            ])
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "查询服务 {service_name} 的健康状态失败: {stderr}"
+            ));
+        }
+
+        let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok((!status.is_empty()).then_some(status))
+    }
+
+    /// 获取服务容器被 Docker 自动重启的次数，服务没有容器时返回 `None`
+    pub async fn get_service_restart_count(&self, service_name: &str) -> Result<Option<u64>> {
+        let Some(container_id) = self.get_service_container_id(service_name).await? else {
+            return Ok(None);
+        };
+
+        let output = self
+            .run_docker_command(&["inspect", "--format", "{{.RestartCount}}", &container_id])
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "查询服务 {service_name} 的重启次数失败: {stderr}"
+            ));
+        }
+
+        let count = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        count
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("无法解析服务 {service_name} 的重启次数: {count}"))
+    }
+
+    /// 重建单个服务的容器，不影响其依赖的服务，用于应用端口等配置变更
+    pub async fn recreate_service(&self, service_name: &str) -> Result<()> {
+        self.check_prerequisites().await?;
+
+        let output = self
+            .run_compose_command(&["up", "-d", "--no-deps", "--force-recreate", service_name])
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let exit_code = output.status.code().unwrap_or(-1);
+
+            let error_msg = format!(
+                "重建服务 {service_name} 失败 (退出码: {exit_code}):\n标准错误: {stderr}\n标准输出: {stdout}"
+            );
+
+            error!("{}", error_msg);
+            return Err(anyhow::anyhow!(error_msg));
+        }
+
+        Ok(())
+    }
+
     /// 获取服务状态 - 使用 ducker 库实现，只返回docker-compose中定义的服务
     pub async fn get_services_status(&self) -> Result<Vec<ServiceInfo>> {
 
@@ -508,3 +698,39 @@ impl DockerManager {
         Ok(())
     }
 }
+
+/// 解析 `docker inspect --format "{{.Name}} {{.State.Status}} {{.State.ExitCode}}"` 的输出
+fn parse_container_exit_states(output: &str) -> Vec<ContainerExitState> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?.trim_start_matches('/').to_string();
+            let status = fields.next()?.to_string();
+            let exit_code = fields.next()?.parse().ok()?;
+            Some(ContainerExitState {
+                name,
+                status,
+                exit_code,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_container_exit_states() {
+        let states = parse_container_exit_states(
+            "/docker-mysql-1 exited 0\n/docker-backend-1 exited 137\n/docker-redis-1 running 0\n\n",
+        );
+        assert_eq!(states.len(), 3);
+        assert_eq!(states[0].name, "docker-mysql-1");
+        assert!(states[0].exited_cleanly());
+        assert!(!states[1].exited_cleanly());
+        assert!(states[2].is_running());
+        assert!(!states[2].exited_cleanly());
+    }
+}
@@ -34,6 +34,26 @@ pub struct ServiceInfo {
     pub ports: Vec<String>,
 }
 
+/// 容器的运行状态和退出码（来自 docker inspect）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerExitState {
+    pub name: String,
+    pub status: String,
+    pub exit_code: i32,
+}
+
+impl ContainerExitState {
+    /// 容器是否仍在运行
+    pub fn is_running(&self) -> bool {
+        matches!(self.status.as_str(), "running" | "restarting" | "paused")
+    }
+
+    /// 容器是否已正常退出（退出码为 0）
+    pub fn exited_cleanly(&self) -> bool {
+        !self.is_running() && self.exit_code == 0
+    }
+}
+
 /// 服务配置（从docker-compose.yml解析）
 #[derive(Debug, Clone)]
 pub struct ServiceConfig {
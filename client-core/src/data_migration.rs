@@ -0,0 +1,566 @@
+//! # 数据目录迁移
+//!
+//! 部分升级需要在 SQL 之外调整数据目录（例如重新组织 `upload/` 的目录结构、迁移 MinIO 的存储桶目录）。
+//! 服务包在 `migrations/` 目录下提供迁移描述文件（`*.toml`），按文件名顺序执行：
+//!
+//! ```toml
+//! id = "0001-upload-layout"
+//! description = "上传文件按年份分目录"
+//! # 从低于该版本的版本升级到不低于该版本时执行
+//! version = "0.0.14"
+//! # 可选：源版本低于该版本时不执行
+//! min_from_version = "0.0.10"
+//!
+//! [[steps]]
+//! action = "move"
+//! from = "data/upload/files"
+//! to = "data/upload/2024"
+//! ```
+//!
+//! 路径均相对 docker 工作目录，不能指向工作目录之外。每完成一步都写入迁移日志，
+//! 中途退出后再次执行会跳过已完成的迁移和步骤；各步骤按可重复执行的方式实现
+//! （源路径不存在而目标已存在的移动视为已完成，删除不存在的路径直接跳过）。
+
+use crate::constants::upgrade::{MIGRATIONS_DIR_NAME, get_data_migration_journal_path};
+use crate::events::{EventSender, OperationKind};
+use crate::safe_path::{resolve_entry_path, sanitize_relative_path};
+use crate::version::{Version, version_from_str};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+
+/// 迁移步骤
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum MigrationAction {
+    /// 移动（重命名）文件或目录，目标的上级目录不存在时自动创建
+    Move { from: String, to: String },
+    /// 复制文件或目录，目标已存在的文件会被覆盖
+    Copy { from: String, to: String },
+    /// 删除文件或目录
+    Delete { path: String },
+    /// 创建目录
+    CreateDir { path: String },
+}
+
+impl Display for MigrationAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Move { from, to } => write!(f, "移动 {from} -> {to}"),
+            Self::Copy { from, to } => write!(f, "复制 {from} -> {to}"),
+            Self::Delete { path } => write!(f, "删除 {path}"),
+            Self::CreateDir { path } => write!(f, "创建目录 {path}"),
+        }
+    }
+}
+
+/// 迁移描述文件
+#[derive(Debug, Clone, Deserialize)]
+pub struct MigrationDescriptor {
+    /// 迁移标识，迁移日志按标识记录执行情况
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    /// 引入迁移的版本：从低于该版本的版本升级到不低于该版本时执行
+    #[serde(deserialize_with = "version_from_str")]
+    pub version: Version,
+    /// 源版本低于该版本时不执行
+    #[serde(default, deserialize_with = "optional_version_from_str")]
+    pub min_from_version: Option<Version>,
+    pub steps: Vec<MigrationAction>,
+}
+
+impl MigrationDescriptor {
+    /// 从 `from` 升级到 `to` 时是否需要执行
+    pub fn applies_to(&self, from: &Version, to: &Version) -> bool {
+        *from < self.version
+            && self.version <= *to
+            && self
+                .min_from_version
+                .as_ref()
+                .is_none_or(|min_from| from >= min_from)
+    }
+}
+
+fn optional_version_from_str<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Version>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| Version::from_str(&s).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// 读取服务包中的迁移描述文件，按文件名排序；目录不存在时返回空列表
+pub fn load_migrations(docker_dir: &Path) -> Result<Vec<MigrationDescriptor>> {
+    let dir = docker_dir.join(MIGRATIONS_DIR_NAME);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+
+    let mut migrations: Vec<MigrationDescriptor> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("无法读取迁移描述文件 {}", path.display()))?;
+        let migration: MigrationDescriptor = toml::from_str(&content)
+            .with_context(|| format!("迁移描述文件格式无效 {}", path.display()))?;
+        if migrations.iter().any(|m| m.id == migration.id) {
+            return Err(anyhow::anyhow!(
+                "迁移标识重复: {} ({})",
+                migration.id,
+                path.display()
+            ));
+        }
+        migrations.push(migration);
+    }
+    Ok(migrations)
+}
+
+/// 迁移执行状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationStatus {
+    InProgress,
+    Completed,
+}
+
+/// 迁移日志中的一条记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigrationJournalEntry {
+    pub id: String,
+    pub status: MigrationStatus,
+    /// 已完成的步骤数
+    pub completed_steps: usize,
+    /// 执行迁移时的目标版本
+    pub target_version: String,
+    pub started_at: DateTime<Utc>,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// 最近一次失败的原因
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+/// 迁移日志：记录每个迁移执行到哪一步
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MigrationJournal {
+    #[serde(default)]
+    pub entries: Vec<MigrationJournalEntry>,
+}
+
+impl MigrationJournal {
+    pub fn entry(&self, id: &str) -> Option<&MigrationJournalEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn is_completed(&self, id: &str) -> bool {
+        self.entry(id)
+            .is_some_and(|entry| entry.status == MigrationStatus::Completed)
+    }
+
+    fn entry_mut(&mut self, id: &str, target_version: &str) -> &mut MigrationJournalEntry {
+        if let Some(index) = self.entries.iter().position(|entry| entry.id == id) {
+            return &mut self.entries[index];
+        }
+        self.entries.push(MigrationJournalEntry {
+            id: id.to_string(),
+            status: MigrationStatus::InProgress,
+            completed_steps: 0,
+            target_version: target_version.to_string(),
+            started_at: Utc::now(),
+            completed_at: None,
+            last_error: None,
+        });
+        self.entries.last_mut().expect("刚插入的记录")
+    }
+}
+
+/// 迁移日志文件存储
+#[derive(Debug, Clone)]
+pub struct MigrationJournalStore {
+    path: PathBuf,
+}
+
+impl Default for MigrationJournalStore {
+    fn default() -> Self {
+        Self::new(get_data_migration_journal_path())
+    }
+}
+
+impl MigrationJournalStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 读取迁移日志，文件不存在时返回空日志
+    pub fn load(&self) -> Result<MigrationJournal> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("迁移日志格式无效 {}", self.path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MigrationJournal::default()),
+            Err(e) => Err(e).with_context(|| format!("无法读取迁移日志 {}", self.path.display())),
+        }
+    }
+
+    /// 保存迁移日志（先写临时文件再重命名）
+    pub fn save(&self, journal: &MigrationJournal) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_string_pretty(journal)?)?;
+        std::fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// 迁移执行结果
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    /// 本次执行完成的迁移
+    pub applied: Vec<String>,
+    /// 之前已执行过而跳过的迁移
+    pub already_applied: Vec<String>,
+}
+
+/// 数据目录迁移执行器
+pub struct DataMigrator {
+    docker_dir: PathBuf,
+    store: MigrationJournalStore,
+    events: EventSender,
+}
+
+impl DataMigrator {
+    pub fn new(docker_dir: impl Into<PathBuf>, store: MigrationJournalStore) -> Self {
+        Self {
+            docker_dir: docker_dir.into(),
+            store,
+            events: EventSender::default(),
+        }
+    }
+
+    /// 设置进度事件发送端
+    pub fn with_events(mut self, events: EventSender) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// 从 `from` 升级到 `to` 时需要执行的迁移（已完成的除外）
+    pub fn pending(&self, from: &Version, to: &Version) -> Result<Vec<MigrationDescriptor>> {
+        let journal = self.store.load()?;
+        Ok(load_migrations(&self.docker_dir)?
+            .into_iter()
+            .filter(|migration| migration.applies_to(from, to))
+            .filter(|migration| !journal.is_completed(&migration.id))
+            .collect())
+    }
+
+    /// 按顺序执行待执行的迁移，每完成一步写入迁移日志，失败时保留进度以便续传
+    pub fn run(&self, from: &Version, to: &Version) -> Result<MigrationReport> {
+        let mut report = MigrationReport::default();
+        let mut journal = self.store.load()?;
+        let migrations: Vec<MigrationDescriptor> = load_migrations(&self.docker_dir)?
+            .into_iter()
+            .filter(|migration| migration.applies_to(from, to))
+            .collect();
+        let total_steps: usize = migrations
+            .iter()
+            .filter(|migration| !journal.is_completed(&migration.id))
+            .map(|migration| migration.steps.len())
+            .sum();
+        let mut progress = self
+            .events
+            .progress_reporter(OperationKind::Upgrade, total_steps as u64);
+        let mut finished_steps = 0u64;
+        let target_version = to.to_string();
+
+        for migration in &migrations {
+            if journal.is_completed(&migration.id) {
+                debug!("迁移 {} 已执行过，跳过", migration.id);
+                report.already_applied.push(migration.id.clone());
+                continue;
+            }
+
+            let phase = format!("数据迁移 {}", migration.id);
+            self.events.phase_started(OperationKind::Upgrade, &phase);
+            let resume_from = journal
+                .entry_mut(&migration.id, &target_version)
+                .completed_steps;
+            if resume_from > 0 {
+                info!(
+                    "⏩ 继续执行数据迁移 {}（已完成 {}/{} 步）",
+                    migration.id,
+                    resume_from,
+                    migration.steps.len()
+                );
+            } else {
+                info!(
+                    "🔀 执行数据迁移 {}: {}",
+                    migration.id, migration.description
+                );
+            }
+            finished_steps += resume_from.min(migration.steps.len()) as u64;
+            progress.update(finished_steps);
+
+            for (index, step) in migration.steps.iter().enumerate().skip(resume_from) {
+                info!("   [{}/{}] {}", index + 1, migration.steps.len(), step);
+                if let Err(e) = self.apply_step(step) {
+                    let entry = journal.entry_mut(&migration.id, &target_version);
+                    entry.last_error = Some(format!("第 {} 步失败: {e:#}", index + 1));
+                    self.store.save(&journal)?;
+                    return Err(e.context(format!(
+                        "数据迁移 {} 第 {} 步（{}）失败",
+                        migration.id,
+                        index + 1,
+                        step
+                    )));
+                }
+                let entry = journal.entry_mut(&migration.id, &target_version);
+                entry.completed_steps = index + 1;
+                entry.last_error = None;
+                self.store.save(&journal)?;
+                finished_steps += 1;
+                progress.update(finished_steps);
+            }
+
+            let entry = journal.entry_mut(&migration.id, &target_version);
+            entry.status = MigrationStatus::Completed;
+            entry.completed_at = Some(Utc::now());
+            self.store.save(&journal)?;
+            self.events.phase_completed(OperationKind::Upgrade, &phase);
+            info!("✅ 数据迁移 {} 完成", migration.id);
+            report.applied.push(migration.id.clone());
+        }
+
+        Ok(report)
+    }
+
+    fn resolve(&self, path: &str) -> Result<PathBuf> {
+        if sanitize_relative_path(path)?.as_os_str().is_empty() {
+            return Err(anyhow::anyhow!(
+                "迁移路径不能是 docker 工作目录本身: {path:?}"
+            ));
+        }
+        Ok(resolve_entry_path(&self.docker_dir, path)?)
+    }
+
+    fn apply_step(&self, step: &MigrationAction) -> Result<()> {
+        match step {
+            MigrationAction::Move { from, to } => {
+                let (from, to) = (self.resolve(from)?, self.resolve(to)?);
+                if !from.exists() {
+                    if to.exists() {
+                        debug!("源路径已不存在且目标已存在，视为已移动: {}", to.display());
+                        return Ok(());
+                    }
+                    warn!("⚠️ 迁移源路径不存在，跳过: {}", from.display());
+                    return Ok(());
+                }
+                if to.exists() {
+                    return Err(anyhow::anyhow!("目标路径已存在: {}", to.display()));
+                }
+                create_parent(&to)?;
+                if std::fs::rename(&from, &to).is_err() {
+                    // 跨文件系统时无法重命名，先完整复制再删除源路径
+                    copy_path(&from, &to)?;
+                    remove_path(&from)?;
+                }
+            }
+            MigrationAction::Copy { from, to } => {
+                let (from, to) = (self.resolve(from)?, self.resolve(to)?);
+                if !from.exists() {
+                    warn!("⚠️ 迁移源路径不存在，跳过: {}", from.display());
+                    return Ok(());
+                }
+                create_parent(&to)?;
+                copy_path(&from, &to)?;
+            }
+            MigrationAction::Delete { path } => {
+                let path = self.resolve(path)?;
+                if path.exists() {
+                    remove_path(&path)?;
+                }
+            }
+            MigrationAction::CreateDir { path } => {
+                std::fs::create_dir_all(self.resolve(path)?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(())
+}
+
+fn copy_path(from: &Path, to: &Path) -> Result<()> {
+    if from.is_dir() {
+        for entry in walkdir::WalkDir::new(from) {
+            let entry = entry?;
+            let target = to.join(entry.path().strip_prefix(from)?);
+            if entry.file_type().is_dir() {
+                std::fs::create_dir_all(&target)?;
+            } else {
+                create_parent(&target)?;
+                std::fs::copy(entry.path(), &target)?;
+            }
+        }
+    } else {
+        std::fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+fn remove_path(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn version(s: &str) -> Version {
+        Version::from_str(s).unwrap()
+    }
+
+    fn write_migration(docker_dir: &Path, file_name: &str, content: &str) {
+        let dir = docker_dir.join(MIGRATIONS_DIR_NAME);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(file_name), content).unwrap();
+    }
+
+    #[test]
+    fn test_applies_to_version_range() {
+        let migration: MigrationDescriptor = toml::from_str(
+            r#"
+            id = "0001"
+            version = "0.0.14"
+            min_from_version = "0.0.10"
+            steps = []
+            "#,
+        )
+        .unwrap();
+
+        assert!(migration.applies_to(&version("0.0.13.2"), &version("0.0.14")));
+        assert!(migration.applies_to(&version("0.0.12"), &version("0.0.15")));
+        assert!(!migration.applies_to(&version("0.0.14"), &version("0.0.15")));
+        assert!(!migration.applies_to(&version("0.0.12"), &version("0.0.13.9")));
+        assert!(!migration.applies_to(&version("0.0.9"), &version("0.0.14")));
+    }
+
+    #[test]
+    fn test_run_journals_and_resumes() {
+        let temp_dir = TempDir::new().unwrap();
+        let docker_dir = temp_dir.path().join("docker");
+        std::fs::create_dir_all(docker_dir.join("data/upload/files")).unwrap();
+        std::fs::write(docker_dir.join("data/upload/files/a.txt"), "a").unwrap();
+        write_migration(
+            &docker_dir,
+            "0002-cleanup.toml",
+            r#"
+            id = "0002-cleanup"
+            version = "0.0.14"
+            [[steps]]
+            action = "delete"
+            path = "data/upload/tmp"
+            "#,
+        );
+        write_migration(
+            &docker_dir,
+            "0001-upload-layout.toml",
+            r#"
+            id = "0001-upload-layout"
+            version = "0.0.14"
+            [[steps]]
+            action = "create_dir"
+            path = "data/upload/2024"
+            [[steps]]
+            action = "move"
+            from = "data/upload/files/a.txt"
+            to = "data/upload/2024/a.txt"
+            "#,
+        );
+
+        // 模拟上次执行到第一步后中断
+        let store = MigrationJournalStore::new(temp_dir.path().join("journal.json"));
+        let mut journal = MigrationJournal::default();
+        journal
+            .entry_mut("0001-upload-layout", "0.0.14")
+            .completed_steps = 1;
+        store.save(&journal).unwrap();
+
+        let migrator = DataMigrator::new(&docker_dir, store.clone());
+        let pending = migrator
+            .pending(&version("0.0.13"), &version("0.0.14"))
+            .unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].id, "0001-upload-layout");
+
+        let report = migrator
+            .run(&version("0.0.13"), &version("0.0.14"))
+            .unwrap();
+        assert_eq!(report.applied, vec!["0001-upload-layout", "0002-cleanup"]);
+        assert!(docker_dir.join("data/upload/2024/a.txt").exists());
+        assert!(!docker_dir.join("data/upload/files/a.txt").exists());
+
+        let report = migrator
+            .run(&version("0.0.13"), &version("0.0.14"))
+            .unwrap();
+        assert!(report.applied.is_empty());
+        assert_eq!(report.already_applied.len(), 2);
+        assert!(store.load().unwrap().is_completed("0002-cleanup"));
+    }
+
+    #[test]
+    fn test_paths_outside_docker_dir_are_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let docker_dir = temp_dir.path().join("docker");
+        write_migration(
+            &docker_dir,
+            "0001.toml",
+            r#"
+            id = "0001"
+            version = "0.0.14"
+            [[steps]]
+            action = "delete"
+            path = "../config.toml"
+            "#,
+        );
+        let store = MigrationJournalStore::new(temp_dir.path().join("journal.json"));
+        let migrator = DataMigrator::new(&docker_dir, store.clone());
+
+        assert!(
+            migrator
+                .run(&version("0.0.13"), &version("0.0.14"))
+                .is_err()
+        );
+        let journal = store.load().unwrap();
+        assert!(journal.entry("0001").unwrap().last_error.is_some());
+        assert!(!journal.is_completed("0001"));
+    }
+}
@@ -0,0 +1,391 @@
+//! # 数据目录迁移
+//!
+//! docker/data 所在磁盘写满时，`docker-service relocate-data` 把数据目录整体迁移到更大的磁盘：
+//!
+//! 1. 复制数据：优先使用 rsync（保留属主、权限和硬链接），没有 rsync 时逐个复制文件
+//! 2. 校验：逐项比对目录结构、文件大小和符号链接目标
+//! 3. 切换：原 `data` 目录改名保留，原位置改为指向新目录的符号链接，备份、磁盘占用统计等
+//!    按工作目录访问数据的功能不受影响
+//! 4. 改写 docker-compose.yml 和 .env 中 `./data` 开头的挂载路径为新目录的绝对路径，
+//!    容器直接挂载新目录，不经过符号链接
+//!
+//! 服务包中的 compose 文件总是使用 `./data`，新位置记录在 `docker.data_dir`，部署时重新改写。
+
+use crate::events::{EventSender, OperationKind};
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+/// compose 和 .env 中数据目录的相对路径写法
+pub const RELATIVE_DATA_PATH: &str = "./data";
+
+/// 数据目录的文件数和总大小
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DataDirSummary {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// 统计数据目录中的文件数和总大小（不跟随符号链接）
+pub fn scan_data_dir(dir: &Path) -> Result<DataDirSummary> {
+    let mut summary = DataDirSummary::default();
+    for entry in WalkDir::new(dir) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            summary.files += 1;
+            summary.bytes += entry.metadata()?.len();
+        }
+    }
+    Ok(summary)
+}
+
+/// 检查迁移目标：不能与数据目录互相包含，已存在时必须是空目录
+pub fn validate_relocation_target(source: &Path, target: &Path) -> Result<()> {
+    if !target.is_absolute() {
+        return Err(anyhow!("目标目录必须是绝对路径: {}", target.display()));
+    }
+    let source = fs::canonicalize(source)?;
+    let resolved_target = resolve_existing_prefix(target)?;
+    if resolved_target.starts_with(&source) || source.starts_with(&resolved_target) {
+        return Err(anyhow!(
+            "目标目录 {} 与数据目录 {} 互相包含",
+            target.display(),
+            source.display()
+        ));
+    }
+    if target.exists() {
+        if !target.is_dir() {
+            return Err(anyhow!("目标路径已存在且不是目录: {}", target.display()));
+        }
+        if fs::read_dir(target)?.next().is_some() {
+            return Err(anyhow!("目标目录不为空: {}", target.display()));
+        }
+    }
+    Ok(())
+}
+
+/// 解析路径中已存在的部分（解析符号链接），再拼接尚不存在的部分
+fn resolve_existing_prefix(path: &Path) -> Result<PathBuf> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        rest.push(
+            existing
+                .file_name()
+                .ok_or_else(|| anyhow!("无效的目标目录: {}", path.display()))?,
+        );
+        existing = existing
+            .parent()
+            .ok_or_else(|| anyhow!("无效的目标目录: {}", path.display()))?;
+    }
+    let mut resolved = fs::canonicalize(existing)?;
+    resolved.extend(rest.into_iter().rev());
+    Ok(resolved)
+}
+
+/// 把数据目录的内容复制到 `target`
+///
+/// 系统中有 rsync 时使用 `rsync -aH`（终端中显示 rsync 的总体进度），否则逐个复制文件，
+/// 通过 `events` 上报已复制的字节数
+pub fn copy_data_dir(
+    source: &Path,
+    target: &Path,
+    total: &DataDirSummary,
+    events: &EventSender,
+) -> Result<()> {
+    fs::create_dir_all(target)?;
+    match which::which("rsync") {
+        Ok(rsync) => {
+            info!("📦 使用 rsync 复制数据: {}", rsync.display());
+            // 源目录末尾的 / 表示复制目录内容而不是目录本身
+            let status = Command::new(rsync)
+                .arg("-aH")
+                .arg("--info=progress2")
+                .arg(format!("{}/", source.display()))
+                .arg(target)
+                .status()?;
+            if !status.success() {
+                return Err(anyhow!("rsync 复制数据失败: {status}"));
+            }
+            Ok(())
+        }
+        Err(_) => {
+            info!("📦 未找到 rsync，逐个复制文件");
+            copy_tree(source, target, total, events)
+        }
+    }
+}
+
+fn copy_tree(
+    source: &Path,
+    target: &Path,
+    total: &DataDirSummary,
+    events: &EventSender,
+) -> Result<()> {
+    events.phase_started(OperationKind::Relocate, "copy");
+    let mut progress = events.progress_reporter(OperationKind::Relocate, total.bytes);
+    let mut copied = 0u64;
+    let mut ownership_failures = 0u64;
+
+    for entry in WalkDir::new(source).min_depth(1) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(source)?;
+        let destination = target.join(relative);
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            copy_symlink(entry.path(), &destination)?;
+        } else if file_type.is_dir() {
+            fs::create_dir_all(&destination)?;
+            fs::set_permissions(&destination, entry.metadata()?.permissions())?;
+        } else {
+            // fs::copy 同时复制权限位
+            copied += fs::copy(entry.path(), &destination)?;
+            progress.update(copied);
+        }
+        if !copy_ownership(entry.path(), &destination) {
+            ownership_failures += 1;
+        }
+    }
+    if ownership_failures > 0 {
+        warn!(
+            "⚠️ {} 个文件未能保留原属主，请以 root 运行或手动修正属主，否则容器可能无法写入",
+            ownership_failures
+        );
+    }
+    events.phase_completed(OperationKind::Relocate, "copy");
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, destination: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, destination)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(source: &Path, _destination: &Path) -> Result<()> {
+    Err(anyhow!("当前平台不支持复制符号链接: {}", source.display()))
+}
+
+/// 复制属主和属组，失败（如非 root 运行）时返回 false
+#[cfg(unix)]
+fn copy_ownership(source: &Path, destination: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match fs::symlink_metadata(source) {
+        Ok(metadata) => {
+            std::os::unix::fs::lchown(destination, Some(metadata.uid()), Some(metadata.gid()))
+                .is_ok()
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_source: &Path, _destination: &Path) -> bool {
+    true
+}
+
+/// 校验复制结果：数据目录中的每一项在目标目录中都存在，类型、文件大小和符号链接目标一致
+pub fn verify_data_copy(source: &Path, target: &Path) -> Result<DataDirSummary> {
+    let mut summary = DataDirSummary::default();
+    for entry in WalkDir::new(source).min_depth(1) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(source)?;
+        let copied = target.join(relative);
+        let copied_metadata = fs::symlink_metadata(&copied)
+            .map_err(|_| anyhow!("目标目录缺少: {}", relative.display()))?;
+        let file_type = entry.file_type();
+
+        let mismatch = if file_type.is_symlink() {
+            !copied_metadata.file_type().is_symlink()
+                || fs::read_link(entry.path())? != fs::read_link(&copied)?
+        } else if file_type.is_dir() {
+            !copied_metadata.is_dir()
+        } else {
+            let size = entry.metadata()?.len();
+            summary.files += 1;
+            summary.bytes += size;
+            !copied_metadata.is_file() || copied_metadata.len() != size
+        };
+        if mismatch {
+            return Err(anyhow!("复制结果与原数据不一致: {}", relative.display()));
+        }
+    }
+    Ok(summary)
+}
+
+/// 把工作目录中的数据目录切换到 `target`
+///
+/// `data` 是实际目录时改名为 `data.relocated-<时间>` 保留并返回新名称；已经是符号链接
+/// （之前迁移过）时直接删除链接。随后在原位置创建指向 `target` 的符号链接
+pub fn switch_data_dir(data_dir: &Path, target: &Path) -> Result<Option<PathBuf>> {
+    let kept = if fs::symlink_metadata(data_dir)?.file_type().is_symlink() {
+        // Windows 上的目录符号链接需要按目录删除
+        fs::remove_file(data_dir).or_else(|_| fs::remove_dir(data_dir))?;
+        None
+    } else {
+        let file_name = data_dir
+            .file_name()
+            .ok_or_else(|| anyhow!("无效的数据目录: {}", data_dir.display()))?
+            .to_string_lossy()
+            .to_string();
+        let kept = data_dir.with_file_name(format!(
+            "{file_name}.relocated-{}",
+            chrono::Local::now().format("%Y%m%d%H%M%S")
+        ));
+        fs::rename(data_dir, &kept)?;
+        Some(kept)
+    };
+    link_data_dir(target, data_dir)?;
+    Ok(kept)
+}
+
+#[cfg(unix)]
+fn link_data_dir(target: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn link_data_dir(target: &Path, link: &Path) -> Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+        .map_err(|e| anyhow!("创建目录符号链接失败（需要管理员权限或开发者模式）: {e}"))
+}
+
+/// 把文本中以 `prefixes` 之一开头的数据目录路径替换为 `data_dir`，返回新内容和替换次数
+///
+/// 只替换完整的路径片段：前面是行首、空白、引号、`=`、`:` 或 `-`（如 `${DATA:-./data}`），
+/// 后面是 `/`、`:`、`}`、引号、空白或行尾；注释行不修改
+pub fn rewrite_data_paths(content: &str, prefixes: &[&str], data_dir: &str) -> (String, usize) {
+    let mut replaced = 0;
+    let lines: Vec<String> = content
+        .split('\n')
+        .map(|line| {
+            if line.trim_start().starts_with('#') {
+                return line.to_string();
+            }
+            let mut line = line.to_string();
+            for prefix in prefixes.iter().filter(|prefix| **prefix != data_dir) {
+                let (rewritten, count) = replace_path_prefix(&line, prefix, data_dir);
+                line = rewritten;
+                replaced += count;
+            }
+            line
+        })
+        .collect();
+    (lines.join("\n"), replaced)
+}
+
+fn replace_path_prefix(line: &str, prefix: &str, replacement: &str) -> (String, usize) {
+    let mut result = String::with_capacity(line.len());
+    let mut copied_to = 0;
+    let mut count = 0;
+    for (index, _) in line.match_indices(prefix) {
+        let before = line[..index].chars().last();
+        let after = line[index + prefix.len()..].chars().next();
+        let starts_segment =
+            before.is_none_or(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '=' | ':' | '-'));
+        let ends_segment =
+            after.is_none_or(|c| c.is_whitespace() || matches!(c, '/' | ':' | '"' | '\'' | '}'));
+        if starts_segment && ends_segment {
+            result.push_str(&line[copied_to..index]);
+            result.push_str(replacement);
+            copied_to = index + prefix.len();
+            count += 1;
+        }
+    }
+    result.push_str(&line[copied_to..]);
+    (result, count)
+}
+
+/// 改写 compose 和 .env 文件中的数据目录路径，返回改写的路径数
+///
+/// `previous` 为之前迁移到的目录，再次迁移时一并替换；文件不存在时跳过
+pub fn apply_data_dir(data_dir: &Path, previous: Option<&Path>, files: &[&Path]) -> Result<usize> {
+    let data_dir = data_dir.to_string_lossy().replace('\\', "/");
+    let previous = previous.map(|path| path.to_string_lossy().replace('\\', "/"));
+    let mut prefixes = vec![RELATIVE_DATA_PATH];
+    if let Some(previous) = &previous {
+        prefixes.push(previous);
+    }
+
+    let mut total = 0;
+    for file in files {
+        if !file.exists() {
+            continue;
+        }
+        let content = fs::read_to_string(file)?;
+        let (rewritten, count) = rewrite_data_paths(&content, &prefixes, &data_dir);
+        if count > 0 {
+            fs::write(file, rewritten)?;
+            info!(
+                "📝 {} 中 {} 处数据目录路径改写为 {}",
+                file.display(),
+                count,
+                data_dir
+            );
+            total += count;
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rewrite_data_paths() {
+        let compose = "services:\n  mysql:\n    volumes:\n      - ./data/mysql:/var/lib/mysql\n      - \"./data/redis:/data\"\n      # - ./data/old:/old\n      - ./database:/db\n      - type: bind\n        source: ./data\n";
+        let (rewritten, count) = rewrite_data_paths(compose, &["./data"], "/mnt/big/nuwax-data");
+        assert_eq!(count, 3);
+        assert!(rewritten.contains("- /mnt/big/nuwax-data/mysql:/var/lib/mysql"));
+        assert!(rewritten.contains("\"/mnt/big/nuwax-data/redis:/data\""));
+        assert!(rewritten.contains("# - ./data/old:/old"));
+        assert!(rewritten.contains("- ./database:/db"));
+        assert!(rewritten.contains("source: /mnt/big/nuwax-data\n"));
+
+        let env = "MYSQL_DATA=./data/mysql\nMINIO_DATA=${MINIO_DATA:-/mnt/old/data}/minio\n";
+        let (rewritten, count) = rewrite_data_paths(env, &["./data", "/mnt/old/data"], "/mnt/new");
+        assert_eq!(count, 2);
+        assert_eq!(
+            rewritten,
+            "MYSQL_DATA=/mnt/new/mysql\nMINIO_DATA=${MINIO_DATA:-/mnt/new}/minio\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_verify_and_switch() {
+        let temp = TempDir::new().unwrap();
+        let data_dir = temp.path().join("docker/data");
+        fs::create_dir_all(data_dir.join("mysql")).unwrap();
+        fs::write(data_dir.join("mysql/ibdata1"), "mysql data").unwrap();
+        std::os::unix::fs::symlink("mysql/ibdata1", data_dir.join("link")).unwrap();
+
+        let target = temp.path().join("bigdisk/nuwax-data");
+        validate_relocation_target(&data_dir, &target).unwrap();
+        assert!(validate_relocation_target(&data_dir, &data_dir.join("nested")).is_err());
+
+        let total = scan_data_dir(&data_dir).unwrap();
+        copy_tree(&data_dir, &target, &total, &EventSender::default()).unwrap();
+        assert_eq!(verify_data_copy(&data_dir, &target).unwrap(), total);
+
+        fs::write(target.join("mysql/ibdata1"), "truncated").unwrap();
+        assert!(verify_data_copy(&data_dir, &target).is_err());
+        fs::write(target.join("mysql/ibdata1"), "mysql data").unwrap();
+
+        let kept = switch_data_dir(&data_dir, &target).unwrap().unwrap();
+        assert!(kept.join("mysql/ibdata1").exists());
+        assert_eq!(fs::read_link(&data_dir).unwrap(), target);
+        assert_eq!(
+            fs::read_to_string(data_dir.join("mysql/ibdata1")).unwrap(),
+            "mysql data"
+        );
+    }
+}
@@ -28,13 +28,101 @@ pub struct BackupRecord {
     pub backup_type: BackupType,
     pub status: BackupStatus,
     pub created_at: DateTime<Utc>,
+    /// 备份所属的项目命名空间，早期备份为空
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// 创建后的校验结果，未校验时为空
+    #[serde(default)]
+    pub verified: Option<bool>,
+}
+
+impl BackupRecord {
+    /// 是否属于指定命名空间，未记录命名空间的早期备份视为属于当前项目
+    pub fn belongs_to_namespace(&self, namespace: &str) -> bool {
+        self.namespace.as_deref().is_none_or(|own| own == namespace)
+    }
+
+    /// 从数据库中的记录转换，未知类型按手动备份处理
+    fn from_db(backup: crate::db::BackupRecord) -> Self {
+        let status = match backup.status.as_str() {
+            "completed" => BackupStatus::Completed,
+            _ => BackupStatus::Failed,
+        };
+        BackupRecord {
+            id: backup.id,
+            file_path: backup.file_path,
+            service_version: backup.service_version,
+            backup_type: BackupType::from_db_str(&backup.backup_type),
+            status,
+            created_at: backup.created_at,
+            namespace: backup.namespace,
+            verified: backup.verified,
+        }
+    }
 }
 
 /// 备份类型
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BackupType {
+    /// 用户手动执行 `nuwax-cli backup` 创建
     Manual,
+    /// 自动升级部署前创建
     PreUpgrade,
+    /// 定时任务（`auto-backup run`）创建
+    Scheduled,
+    /// 用户为特定时间点保留的快照（`nuwax-cli backup --snapshot`）
+    Snapshot,
+}
+
+impl BackupType {
+    /// 所有备份类型
+    pub const ALL: [BackupType; 4] = [
+        BackupType::Manual,
+        BackupType::PreUpgrade,
+        BackupType::Scheduled,
+        BackupType::Snapshot,
+    ];
+
+    /// 数据库及备份文件名中使用的标识
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupType::Manual => "manual",
+            BackupType::PreUpgrade => "pre-upgrade",
+            BackupType::Scheduled => "scheduled",
+            BackupType::Snapshot => "snapshot",
+        }
+    }
+
+    /// 中文显示名称
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            BackupType::Manual => "手动",
+            BackupType::PreUpgrade => "升级前",
+            BackupType::Scheduled => "定时",
+            BackupType::Snapshot => "快照",
+        }
+    }
+
+    /// 从数据库中的标识解析，未知类型按手动备份处理
+    fn from_db_str(value: &str) -> Self {
+        value.parse().unwrap_or(BackupType::Manual)
+    }
+}
+
+impl std::str::FromStr for BackupType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "manual" => Ok(BackupType::Manual),
+            "pre-upgrade" | "preupgrade" => Ok(BackupType::PreUpgrade),
+            "scheduled" => Ok(BackupType::Scheduled),
+            "snapshot" => Ok(BackupType::Snapshot),
+            _ => Err(format!(
+                "无效的备份类型: {s}（支持 manual、pre-upgrade、scheduled、snapshot）"
+            )),
+        }
+    }
 }
 
 /// 备份状态
@@ -44,6 +132,164 @@ pub enum BackupStatus {
     Failed,
 }
 
+/// 备份列表的排序字段
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackupSort {
+    /// 创建时间
+    #[default]
+    CreatedAt,
+    /// 服务版本，按版本号各段的数值比较
+    Version,
+    /// 备份类型
+    Type,
+}
+
+impl std::str::FromStr for BackupSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "created" | "created-at" | "time" => Ok(BackupSort::CreatedAt),
+            "version" => Ok(BackupSort::Version),
+            "type" => Ok(BackupSort::Type),
+            _ => Err(format!(
+                "无效的排序字段: {s}（支持 created、version、type）"
+            )),
+        }
+    }
+}
+
+/// 备份记录查询条件，筛选、排序和分页都在数据库查询中完成
+#[derive(Debug, Clone, Default)]
+pub struct BackupQuery {
+    pub backup_type: Option<BackupType>,
+    /// 服务版本，同时匹配该版本的修订版本（0.0.13 匹配 0.0.13.2）
+    pub service_version: Option<String>,
+    /// 命名空间，未记录命名空间的早期备份视为属于当前项目
+    pub namespace: Option<String>,
+    /// 创建时间下限（含）
+    pub since: Option<DateTime<Utc>>,
+    /// 创建时间上限（含）
+    pub until: Option<DateTime<Utc>>,
+    pub sort: BackupSort,
+    /// 升序排列，默认最新、版本最高的在前
+    pub ascending: bool,
+    /// 最多返回的记录数，为空时不限制
+    pub limit: Option<u64>,
+    /// 跳过的记录数
+    pub offset: u64,
+}
+
+/// 备份记录分页查询结果
+#[derive(Debug, Clone)]
+pub struct BackupPage {
+    pub backups: Vec<BackupRecord>,
+    /// 满足条件的记录总数，不受分页影响
+    pub total: u64,
+}
+
+/// 升级历史记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeRecord {
+    /// 升级唯一标识（UUID）
+    pub upgrade_id: String,
+    pub from_version: String,
+    pub to_version: String,
+    pub upgrade_type: UpgradeKind,
+    pub status: UpgradeStatus,
+    /// 升级前备份的 ID
+    pub backup_id: Option<i64>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub error_message: Option<String>,
+}
+
+impl UpgradeRecord {
+    /// 升级耗时
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        self.completed_at
+            .map(|completed_at| completed_at - self.started_at)
+    }
+}
+
+/// 升级方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpgradeKind {
+    /// 全量升级
+    Full,
+    /// 增量补丁升级（包括补丁链）
+    Patch,
+}
+
+impl UpgradeKind {
+    /// 数据库中使用的标识
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            UpgradeKind::Full => "FULL",
+            UpgradeKind::Patch => "INCREMENTAL",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            UpgradeKind::Full => "全量",
+            UpgradeKind::Patch => "增量补丁",
+        }
+    }
+
+    /// 从数据库标识解析，未知值按全量处理
+    pub fn from_db_str(s: &str) -> Self {
+        match s {
+            "INCREMENTAL" | "HOTFIX" => UpgradeKind::Patch,
+            _ => UpgradeKind::Full,
+        }
+    }
+}
+
+/// 升级结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpgradeStatus {
+    Success,
+    Failed,
+    /// 升级失败后已恢复到升级前的数据
+    RolledBack,
+    /// 升级未结束（进程中断等）
+    Running,
+}
+
+impl UpgradeStatus {
+    /// 数据库中使用的标识
+    pub fn as_db_str(&self) -> &'static str {
+        match self {
+            UpgradeStatus::Success => "SUCCESS",
+            UpgradeStatus::Failed => "FAILED",
+            UpgradeStatus::RolledBack => "ROLLED_BACK",
+            UpgradeStatus::Running => "RUNNING",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            UpgradeStatus::Success => "成功",
+            UpgradeStatus::Failed => "失败",
+            UpgradeStatus::RolledBack => "已回滚",
+            UpgradeStatus::Running => "未完成",
+        }
+    }
+
+    /// 从数据库标识解析，PENDING/RUNNING 等未结束状态视为未完成
+    pub fn from_db_str(s: &str) -> Self {
+        match s {
+            "SUCCESS" => UpgradeStatus::Success,
+            "FAILED" => UpgradeStatus::Failed,
+            "ROLLED_BACK" => UpgradeStatus::RolledBack,
+            _ => UpgradeStatus::Running,
+        }
+    }
+}
+
 /// 计划任务
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScheduledTask {
@@ -192,79 +438,74 @@ impl Database {
         service_version: String,
         backup_type: BackupType,
         status: BackupStatus,
+        namespace: Option<String>,
     ) -> Result<i64> {
-        let backup_type_str = match backup_type {
-            BackupType::Manual => "manual",
-            BackupType::PreUpgrade => "pre-upgrade",
-        };
-
         let status_str = match status {
             BackupStatus::Completed => "completed",
             BackupStatus::Failed => "failed",
         };
 
         self.manager
-            .create_backup_record(file_path, service_version, backup_type_str, status_str)
+            .create_backup_record(
+                file_path,
+                service_version,
+                backup_type.as_str(),
+                status_str,
+                namespace,
+            )
+            .await
+    }
+
+    /// 导入已有备份文件的记录，保留原创建时间
+    pub async fn import_backup_record(
+        &self,
+        file_path: String,
+        service_version: String,
+        backup_type: BackupType,
+        created_at: DateTime<Utc>,
+        backup_hash: Option<String>,
+        namespace: Option<String>,
+    ) -> Result<i64> {
+        self.manager
+            .import_backup_record(
+                file_path,
+                service_version,
+                backup_type.as_str(),
+                created_at,
+                backup_hash,
+                namespace,
+            )
             .await
     }
 
     /// 获取所有备份记录
     pub async fn get_all_backups(&self) -> Result<Vec<BackupRecord>> {
         let duckdb_backups = self.manager.get_all_backups().await?;
+        Ok(duckdb_backups
+            .into_iter()
+            .map(BackupRecord::from_db)
+            .collect())
+    }
 
-        let mut backups = Vec::new();
-        for backup in duckdb_backups {
-            let backup_type = match backup.backup_type.as_str() {
-                "manual" => BackupType::Manual,
-                "pre-upgrade" => BackupType::PreUpgrade,
-                _ => BackupType::Manual,
-            };
-
-            let status = match backup.status.as_str() {
-                "completed" => BackupStatus::Completed,
-                "failed" => BackupStatus::Failed,
-                _ => BackupStatus::Failed,
-            };
-
-            backups.push(BackupRecord {
-                id: backup.id,
-                file_path: backup.file_path,
-                service_version: backup.service_version,
-                backup_type,
-                status,
-                created_at: backup.created_at,
-            });
-        }
-
-        Ok(backups)
+    /// 按条件查询备份记录
+    pub async fn query_backups(&self, query: &BackupQuery) -> Result<BackupPage> {
+        let (duckdb_backups, total) = self.manager.query_backups(query.clone()).await?;
+        Ok(BackupPage {
+            backups: duckdb_backups
+                .into_iter()
+                .map(BackupRecord::from_db)
+                .collect(),
+            total,
+        })
     }
 
     /// 根据 ID 获取备份记录
     pub async fn get_backup_by_id(&self, id: i64) -> Result<Option<BackupRecord>> {
-        if let Some(backup) = self.manager.get_backup_by_id(id).await? {
-            let backup_type = match backup.backup_type.as_str() {
-                "manual" => BackupType::Manual,
-                "pre-upgrade" => BackupType::PreUpgrade,
-                _ => BackupType::Manual,
-            };
-
-            let status = match backup.status.as_str() {
-                "completed" => BackupStatus::Completed,
-                "failed" => BackupStatus::Failed,
-                _ => BackupStatus::Failed,
-            };
-
-            Ok(Some(BackupRecord {
-                id: backup.id,
-                file_path: backup.file_path,
-                service_version: backup.service_version,
-                backup_type,
-                status,
-                created_at: backup.created_at,
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(self
+            .manager
+            .get_backup_by_id(id)
+            .await?
+            .map(BackupRecord::from_db))
     }
 
     /// 创建计划任务
@@ -341,6 +582,43 @@ impl Database {
             .await
     }
 
+    /// 记录一次升级
+    pub async fn record_upgrade(&self, record: &UpgradeRecord) -> Result<()> {
+        self.manager
+            .record_upgrade_history(crate::db::UpgradeHistoryRecord {
+                upgrade_id: record.upgrade_id.clone(),
+                from_version: record.from_version.clone(),
+                to_version: record.to_version.clone(),
+                upgrade_type: record.upgrade_type.as_db_str().to_string(),
+                status: record.status.as_db_str().to_string(),
+                backup_id: record.backup_id,
+                started_at: record.started_at,
+                completed_at: record.completed_at,
+                error_message: record.error_message.clone(),
+            })
+            .await
+    }
+
+    /// 获取升级历史（按开始时间倒序），`limit` 为空时返回全部
+    pub async fn get_upgrade_history(&self, limit: Option<i32>) -> Result<Vec<UpgradeRecord>> {
+        let records = self.manager.get_upgrade_history(limit).await?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| UpgradeRecord {
+                upgrade_id: record.upgrade_id,
+                from_version: record.from_version,
+                to_version: record.to_version,
+                upgrade_type: UpgradeKind::from_db_str(&record.upgrade_type),
+                status: UpgradeStatus::from_db_str(&record.status),
+                backup_id: record.backup_id,
+                started_at: record.started_at,
+                completed_at: record.completed_at,
+                error_message: record.error_message,
+            })
+            .collect())
+    }
+
     /// 删除备份记录
     pub async fn delete_backup_record(&self, backup_id: i64) -> Result<()> {
         self.manager.delete_backup_record(backup_id).await
@@ -353,6 +631,11 @@ impl Database {
             .await
     }
 
+    /// 记录备份创建后的校验结果
+    pub async fn set_backup_verified(&self, backup_id: i64, verified: bool) -> Result<()> {
+        self.manager.set_backup_verified(backup_id, verified).await
+    }
+
     /// 批量更新备份文件路径（用于存储目录迁移）
     pub async fn update_all_backup_paths(&self, old_prefix: &str, new_prefix: &str) -> Result<()> {
         let backups = self.get_all_backups().await?;
@@ -367,3 +650,130 @@ impl Database {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_upgrade_history_roundtrip() {
+        let db = Database::connect_memory().await.unwrap();
+        db.init_database().await.unwrap();
+
+        let backup_id = db
+            .create_backup_record(
+                "backup.tar.gz".to_string(),
+                "1.0.0".to_string(),
+                BackupType::PreUpgrade,
+                BackupStatus::Completed,
+                Some("prod".to_string()),
+            )
+            .await
+            .unwrap();
+        let backup = db.get_backup_by_id(backup_id).await.unwrap().unwrap();
+        assert_eq!(backup.namespace.as_deref(), Some("prod"));
+        assert!(backup.belongs_to_namespace("prod"));
+        assert!(!backup.belongs_to_namespace("staging"));
+
+        let started_at = Utc::now() - chrono::Duration::minutes(5);
+        for (index, status) in [UpgradeStatus::RolledBack, UpgradeStatus::Success]
+            .into_iter()
+            .enumerate()
+        {
+            db.record_upgrade(&UpgradeRecord {
+                upgrade_id: Uuid::new_v4().to_string(),
+                from_version: "1.0.0".to_string(),
+                to_version: "1.1.0".to_string(),
+                upgrade_type: UpgradeKind::Patch,
+                status,
+                backup_id: Some(backup_id),
+                started_at: started_at + chrono::Duration::minutes(index as i64 * 2),
+                completed_at: Some(started_at + chrono::Duration::minutes(index as i64 * 2 + 1)),
+                error_message: None,
+            })
+            .await
+            .unwrap();
+        }
+
+        let history = db.get_upgrade_history(None).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].status, UpgradeStatus::Success);
+        assert_eq!(history[1].upgrade_type, UpgradeKind::Patch);
+        assert_eq!(history[0].duration(), Some(chrono::Duration::minutes(1)));
+
+        // 删除被引用的备份后，历史记录保留但不再关联备份
+        db.delete_backup_record(backup_id).await.unwrap();
+        let history = db.get_upgrade_history(Some(1)).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].backup_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_query_backups() {
+        let db = Database::connect_memory().await.unwrap();
+        db.init_database().await.unwrap();
+
+        let now = Utc::now();
+        for (index, (version, backup_type, namespace)) in [
+            ("0.0.13", BackupType::Manual, None),
+            ("0.0.13.2", BackupType::PreUpgrade, Some("prod")),
+            ("0.0.9", BackupType::Scheduled, Some("prod")),
+            ("0.0.14", BackupType::PreUpgrade, Some("staging")),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            db.import_backup_record(
+                format!("backup-{index}.tar.gz"),
+                version.to_string(),
+                backup_type,
+                now - chrono::Duration::days(3 - index as i64),
+                None,
+                namespace.map(str::to_string),
+            )
+            .await
+            .unwrap();
+        }
+        let versions = |page: &BackupPage| -> Vec<String> {
+            page.backups
+                .iter()
+                .map(|backup| backup.service_version.clone())
+                .collect()
+        };
+
+        // 未记录命名空间的早期备份属于任一命名空间，版本同时匹配修订版本
+        let page = db
+            .query_backups(&BackupQuery {
+                namespace: Some("prod".to_string()),
+                service_version: Some("0.0.13".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(versions(&page), ["0.0.13.2", "0.0.13"]);
+
+        let page = db
+            .query_backups(&BackupQuery {
+                backup_type: Some(BackupType::PreUpgrade),
+                since: Some(now - chrono::Duration::hours(36)),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(versions(&page), ["0.0.14"]);
+
+        // 版本号按数值排序，分页不影响总数
+        let page = db
+            .query_backups(&BackupQuery {
+                sort: BackupSort::Version,
+                ascending: true,
+                limit: Some(2),
+                offset: 1,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.total, 4);
+        assert_eq!(versions(&page), ["0.0.13", "0.0.13.2"]);
+    }
+}
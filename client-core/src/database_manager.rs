@@ -1,22 +1,126 @@
+use crate::constants::config::get_database_busy_timeout;
 use anyhow::Result;
 use duckdb::{Connection, Result as DuckResult};
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Weak};
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 use tracing::{debug, error, warn};
 
+/// 进程内已打开的数据库实例，按数据库文件的绝对路径索引
+///
+/// DuckDB 以读写方式打开文件时持有进程级文件锁，同一进程内重复打开同一文件会得到互不知晓的多个实例。
+/// 进程内所有连接都从同一实例派生，最后一个连接释放后实例关闭，文件锁随之释放，其他进程才能打开数据库。
+static OPEN_DATABASES: LazyLock<Mutex<HashMap<PathBuf, Weak<std::sync::Mutex<Connection>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 从共享数据库实例派生的连接
+///
+/// DuckDB 派生的连接不拥有数据库实例，持有实例的引用保证连接释放前实例不会关闭
+pub struct SharedConnection {
+    // 字段按声明顺序释放：先释放连接，再释放实例引用
+    connection: Connection,
+    _database: Option<Arc<std::sync::Mutex<Connection>>>,
+}
+
+impl SharedConnection {
+    /// 从实例派生一个新连接
+    pub(crate) fn derive(database: Arc<std::sync::Mutex<Connection>>) -> Result<Self> {
+        let connection = database
+            .lock()
+            .map_err(|_| anyhow::anyhow!("数据库实例锁已损坏"))?
+            .try_clone()?;
+        Ok(Self {
+            connection,
+            _database: Some(database),
+        })
+    }
+}
+
+impl Deref for SharedConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+/// 打开数据库文件，返回从进程内共享实例派生的连接
+///
+/// 数据库被其他进程占用时按指数退避重试，超过 `busy_timeout` 后返回错误
+pub async fn open_shared_connection(
+    path: &Path,
+    busy_timeout: Duration,
+) -> Result<SharedConnection> {
+    let key = database_key(path);
+    let mut databases = OPEN_DATABASES.lock().await;
+    if let Some(database) = databases.get(&key).and_then(Weak::upgrade) {
+        return SharedConnection::derive(database);
+    }
+
+    let started = Instant::now();
+    let mut delay = Duration::from_millis(50);
+    let connection = loop {
+        match Connection::open(path) {
+            Ok(connection) => break connection,
+            Err(e) if is_lock_conflict(&e.to_string()) && started.elapsed() < busy_timeout => {
+                debug!(
+                    "数据库被其他进程占用，{}ms 后重试: {}",
+                    delay.as_millis(),
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(Duration::from_secs(1));
+            }
+            Err(e) if is_lock_conflict(&e.to_string()) => {
+                return Err(anyhow::anyhow!(
+                    "数据库被其他进程占用，等待 {} 秒后仍无法打开（可能有其他 nuwax-cli 或客户端正在执行长时间操作）: {}",
+                    busy_timeout.as_secs(),
+                    e
+                ));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    let database = Arc::new(std::sync::Mutex::new(connection));
+    databases.retain(|_, database| database.strong_count() > 0);
+    databases.insert(key, Arc::downgrade(&database));
+    SharedConnection::derive(database)
+}
+
+/// 数据库实例的索引键：父目录规范化后的绝对路径，文件尚未创建时同样稳定
+fn database_key(path: &Path) -> PathBuf {
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// 是否为其他进程持有数据库文件锁导致的错误
+fn is_lock_conflict(error_msg: &str) -> bool {
+    error_msg.contains("Could not set lock on file") || error_msg.contains("Conflicting lock")
+}
+
 /// DuckDB 数据库管理器 - 针对并发特性优化
 ///
 /// 设计原则：
-/// - 文件数据库：每个操作创建新连接，天然支持并发读
+/// - 文件数据库：进程内所有连接从同一实例派生，操作结束后释放，让其他进程（如同时运行的图形界面和命令行）可以打开
 /// - 内存数据库：使用单一连接+Mutex，确保数据一致性
 /// - 写操作：串行执行，避免write-write conflict
-/// - 重试机制：检测冲突并实现指数退避重试
+/// - 重试机制：检测冲突并实现指数退避重试，数据库被其他进程占用时最多等待 busy timeout
 #[derive(Clone)]
 pub struct DatabaseManager {
     /// 数据库配置
     config: Arc<DatabaseConfig>,
+    /// 数据库被其他进程占用时的等待时间
+    busy_timeout: Duration,
 }
 
 #[derive(Debug)]
@@ -38,7 +142,8 @@ impl DatabaseManager {
         }
 
         // 测试连接是否可以创建
-        let _test_conn = Connection::open(&db_path)?;
+        let busy_timeout = get_database_busy_timeout();
+        let _test_conn = open_shared_connection(&db_path, busy_timeout).await?;
         debug!("数据库文件连接测试成功: {:?}", db_path);
 
         let manager = Self {
@@ -46,6 +151,7 @@ impl DatabaseManager {
                 db_path: Some(db_path),
                 memory_connection: None,
             }),
+            busy_timeout,
         };
 
         // 移除自动初始化 - 只有在明确调用 init_database 时才初始化
@@ -54,6 +160,12 @@ impl DatabaseManager {
         Ok(manager)
     }
 
+    /// 设置数据库被其他进程占用时的等待时间
+    pub fn with_busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout = busy_timeout;
+        self
+    }
+
     /// 创建内存数据库管理器（主要用于测试）
     pub async fn new_memory() -> Result<Self> {
         // 对于内存数据库，我们需要保持一个共享连接
@@ -65,6 +177,7 @@ impl DatabaseManager {
                 db_path: None,
                 memory_connection: Some(connection),
             }),
+            busy_timeout: get_database_busy_timeout(),
         };
 
         // 移除自动初始化 - 只有在明确调用 init_database 时才初始化
@@ -82,14 +195,17 @@ impl DatabaseManager {
     }
 
     /// 创建数据库连接
-    async fn create_connection(&self) -> Result<Connection> {
+    async fn create_connection(&self) -> Result<SharedConnection> {
         if let Some(ref path) = self.config.db_path {
-            // 文件数据库：创建新连接
-            Ok(Connection::open(path)?)
+            // 文件数据库：从进程内共享实例派生连接
+            open_shared_connection(path, self.busy_timeout).await
         } else if let Some(ref memory_conn) = self.config.memory_connection {
-            // 内存数据库：克隆共享连接
+            // 内存数据库：克隆共享连接（实例由管理器持有）
             let conn = memory_conn.lock().await;
-            Ok(conn.try_clone()?)
+            Ok(SharedConnection {
+                connection: conn.try_clone()?,
+                _database: None,
+            })
         } else {
             Err(anyhow::anyhow!("数据库配置无效"))
         }
@@ -218,6 +334,7 @@ impl DatabaseManager {
             || error_msg.contains("database is busy")
             || error_msg.contains("SQLITE_BUSY")
             || error_msg.contains("SQLITE_LOCKED")
+            || is_lock_conflict(error_msg)
     }
 
     /// 初始化数据库表结构
@@ -636,6 +753,76 @@ mod tests {
         assert_eq!(count.unwrap(), 5);
     }
 
+    #[tokio::test]
+    async fn test_managers_share_database_file() {
+        // 同一进程内的 DatabaseManager 与 Actor 同时访问同一数据库文件
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("shared.db");
+        let actor_manager = crate::db::DuckDbManager::new(&db_path).await.unwrap();
+        actor_manager.init_database().await.unwrap();
+        let manager = DatabaseManager::new(&db_path)
+            .await
+            .unwrap()
+            .with_busy_timeout(Duration::from_secs(5));
+
+        let mut handles = Vec::new();
+        for i in 0..5 {
+            let actor_manager = actor_manager.clone();
+            handles.push(tokio::spawn(async move {
+                actor_manager
+                    .set_config(&format!("key_{i}"), &format!("value_{i}"))
+                    .await
+            }));
+            let manager = manager.clone();
+            handles.push(tokio::spawn(async move {
+                manager
+                    .read_with_retry(|conn| {
+                        conn.query_row("SELECT COUNT(*) FROM app_config", [], |row| {
+                            row.get::<_, i64>(0)
+                        })
+                    })
+                    .await
+                    .map(|_| ())
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+
+        let value = manager
+            .read_with_retry(|conn| {
+                conn.query_row(
+                    "SELECT config_value::VARCHAR FROM app_config WHERE config_key = 'key_4'",
+                    [],
+                    |row| row.get::<_, String>(0),
+                )
+            })
+            .await
+            .unwrap();
+        assert!(value.contains("value_4"));
+
+        // 空闲时实例关闭，文件锁释放（Actor 回复后才释放连接，稍等片刻）
+        let mut released = false;
+        for _ in 0..50 {
+            let databases = OPEN_DATABASES.lock().await;
+            if databases[&database_key(&db_path)].strong_count() == 0 {
+                released = true;
+                break;
+            }
+            drop(databases);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(released);
+    }
+
+    #[test]
+    fn test_lock_conflict_is_retryable() {
+        let error = "IO Error: Could not set lock on file \"/data/duck_client.db\": Conflicting lock is held in /usr/bin/nuwax-cli (PID 1234)";
+        assert!(is_lock_conflict(error));
+        assert!(DatabaseManager::is_retryable_error(error));
+        assert!(!is_lock_conflict("Catalog Error: Table does not exist"));
+    }
+
     #[tokio::test]
     async fn test_debug_sql_initialization() {
         // 创建一个不初始化的数据库管理器
@@ -645,6 +832,7 @@ mod tests {
                 db_path: None,
                 memory_connection: Some(connection),
             }),
+            busy_timeout: get_database_busy_timeout(),
         };
 
         println!("=== 初始化前 ===");
@@ -689,6 +877,7 @@ mod tests {
                 db_path: None,
                 memory_connection: Some(connection),
             }),
+            busy_timeout: get_database_busy_timeout(),
         };
 
         let test_sql = r#"
@@ -724,6 +913,7 @@ mod tests {
                 db_path: None,
                 memory_connection: Some(connection),
             }),
+            busy_timeout: get_database_busy_timeout(),
         };
 
         let schema_sql = include_str!("../migrations/init_duckdb.sql");
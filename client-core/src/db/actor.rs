@@ -1,38 +1,72 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use duckdb::{Connection, params};
+use duckdb::{Connection, ToSql, params};
 use serde_json;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::mpsc;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use super::messages::{AppStateRecord, DbMessage, DownloadTaskRecord, UserActionRecord};
-use super::models::{BackupRecord, ScheduledTask};
-
-/// DuckDB Actor - 确保单线程访问DuckDB
-pub struct DuckDbActor {
-    connection: Connection,
+use super::models::{BackupRecord, ScheduledTask, UpgradeHistoryRecord};
+use crate::database::{BackupQuery, BackupSort};
+use crate::database_manager::{SharedConnection, open_shared_connection};
+
+/// Actor 获取数据库连接的来源
+pub enum ConnectionSource {
+    /// 数据库文件，每批消息处理前打开，处理完后释放，让其他进程可以访问
+    File {
+        path: PathBuf,
+        busy_timeout: Duration,
+    },
+    /// 内存数据库，实例在 Actor 存活期间一直保留
+    Memory(Arc<Mutex<Connection>>),
 }
 
-impl DuckDbActor {
-    /// 创建新的DuckDB Actor
-    pub fn new(db_path: PathBuf) -> Result<Self> {
-        let connection = Connection::open(db_path)?;
-        Ok(Self { connection })
+impl ConnectionSource {
+    /// 内存数据库来源
+    pub fn memory() -> Result<Self> {
+        Ok(Self::Memory(Arc::new(Mutex::new(
+            Connection::open_in_memory()?,
+        ))))
     }
 
-    /// 创建内存DuckDB Actor
-    pub fn new_memory() -> Result<Self> {
-        let connection = Connection::open_in_memory()?;
-        Ok(Self { connection })
+    /// 获取一个连接
+    pub async fn acquire(&self) -> Result<SharedConnection> {
+        match self {
+            Self::File { path, busy_timeout } => open_shared_connection(path, *busy_timeout).await,
+            Self::Memory(database) => SharedConnection::derive(database.clone()),
+        }
     }
+}
+
+/// DuckDB Actor - 确保单线程访问DuckDB
+pub struct DuckDbActor {
+    connection: SharedConnection,
+}
 
+impl DuckDbActor {
     /// 运行Actor消息循环
-    pub async fn run(mut self, mut receiver: mpsc::Receiver<DbMessage>) {
+    ///
+    /// 连续到达的消息共用一个连接，队列清空后释放连接
+    pub async fn run(source: ConnectionSource, mut receiver: mpsc::Receiver<DbMessage>) {
         debug!("DuckDB Actor 已启动");
 
         while let Some(message) = receiver.recv().await {
-            self.handle_message(message).await;
+            let connection = match source.acquire().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!("获取数据库连接失败: {:#}", e);
+                    message.reject(&e);
+                    continue;
+                }
+            };
+            let mut actor = Self { connection };
+            actor.handle_message(message).await;
+            while let Ok(message) = receiver.try_recv() {
+                actor.handle_message(message).await;
+            }
         }
 
         debug!("DuckDB Actor 已关闭");
@@ -62,16 +96,45 @@ impl DuckDbActor {
                 service_version,
                 backup_type,
                 status,
+                namespace,
                 respond_to,
             } => {
-                let result =
-                    self.create_backup_record(&file_path, &service_version, &backup_type, &status);
+                let result = self.create_backup_record(
+                    &file_path,
+                    &service_version,
+                    &backup_type,
+                    &status,
+                    namespace.as_deref(),
+                );
+                let _ = respond_to.send(result);
+            }
+            DbMessage::ImportBackupRecord {
+                file_path,
+                service_version,
+                backup_type,
+                created_at,
+                backup_hash,
+                namespace,
+                respond_to,
+            } => {
+                let result = self.import_backup_record(
+                    &file_path,
+                    &service_version,
+                    &backup_type,
+                    created_at,
+                    backup_hash.as_deref(),
+                    namespace.as_deref(),
+                );
                 let _ = respond_to.send(result);
             }
             DbMessage::GetAllBackups { respond_to } => {
                 let result = self.get_all_backups();
                 let _ = respond_to.send(result);
             }
+            DbMessage::QueryBackups { query, respond_to } => {
+                let result = self.query_backups(&query);
+                let _ = respond_to.send(result);
+            }
             DbMessage::GetBackupById { id, respond_to } => {
                 let result = self.get_backup_by_id(id);
                 let _ = respond_to.send(result);
@@ -91,6 +154,14 @@ impl DuckDbActor {
                 let result = self.update_backup_file_path(backup_id, &new_path);
                 let _ = respond_to.send(result);
             }
+            DbMessage::SetBackupVerified {
+                backup_id,
+                verified,
+                respond_to,
+            } => {
+                let result = self.set_backup_verified(backup_id, verified);
+                let _ = respond_to.send(result);
+            }
             DbMessage::CreateScheduledTask {
                 task_type,
                 target_version,
@@ -229,6 +300,16 @@ impl DuckDbActor {
                 let result = self.get_user_actions(limit);
                 let _ = respond_to.send(result);
             }
+
+            // ========== 升级历史 ==========
+            DbMessage::RecordUpgradeHistory { record, respond_to } => {
+                let result = self.record_upgrade_history(&record);
+                let _ = respond_to.send(result);
+            }
+            DbMessage::GetUpgradeHistory { limit, respond_to } => {
+                let result = self.get_upgrade_history(limit);
+                let _ = respond_to.send(result);
+            }
         }
     }
 
@@ -305,15 +386,16 @@ impl DuckDbActor {
         service_version: &str,
         backup_type: &str,
         _status: &str,
+        namespace: Option<&str>,
     ) -> Result<i64> {
         // 生成唯一的备份名称
         let backup_name = format!("backup_{}", chrono::Utc::now().format("%Y%m%d_%H%M%S"));
 
         // 插入记录，让数据库自动生成ID
         self.connection.execute(
-            "INSERT INTO backup_records (backup_name, backup_type, source_version, backup_path) 
-             VALUES (?, ?, ?, ?)",
-            params![backup_name, backup_type, service_version, file_path],
+            "INSERT INTO backup_records (backup_name, backup_type, source_version, backup_path, namespace) 
+             VALUES (?, ?, ?, ?, ?)",
+            params![backup_name, backup_type, service_version, file_path, namespace],
         )?;
 
         // 获取最后插入的ID
@@ -324,10 +406,36 @@ impl DuckDbActor {
         Ok(id)
     }
 
+    /// 导入已有备份文件的记录
+    fn import_backup_record(
+        &mut self,
+        file_path: &str,
+        service_version: &str,
+        backup_type: &str,
+        created_at: DateTime<Utc>,
+        backup_hash: Option<&str>,
+        namespace: Option<&str>,
+    ) -> Result<i64> {
+        // 同一秒内可能导入多个文件，备份名称使用 UUID 保证唯一
+        let backup_name = format!("imported_{}", uuid::Uuid::new_v4().simple());
+
+        self.connection.execute(
+            "INSERT INTO backup_records (backup_name, backup_type, source_version, backup_path, backup_hash, created_at, namespace) 
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![backup_name, backup_type, service_version, file_path, backup_hash, created_at, namespace],
+        )?;
+
+        let id: i64 =
+            self.connection
+                .query_row("SELECT currval('backup_records_seq')", [], |row| row.get(0))?;
+
+        Ok(id)
+    }
+
     /// 获取所有备份记录
     fn get_all_backups(&mut self) -> Result<Vec<BackupRecord>> {
         let mut stmt = self.connection.prepare(
-            "SELECT id, backup_path, source_version, backup_type, created_at 
+            "SELECT id, backup_path, source_version, backup_type, created_at, namespace, verified 
              FROM backup_records ORDER BY created_at DESC",
         )?;
 
@@ -339,6 +447,8 @@ impl DuckDbActor {
                 backup_type: row.get(3)?,
                 status: "completed".to_string(), // 新表架构没有status字段，默认为completed
                 created_at: row.get(4)?,
+                namespace: row.get(5)?,
+                verified: row.get(6)?,
             })
         })?;
 
@@ -350,10 +460,93 @@ impl DuckDbActor {
         Ok(backups)
     }
 
+    /// 按条件查询备份记录，返回当前页的记录和满足条件的总数
+    fn query_backups(&mut self, query: &BackupQuery) -> Result<(Vec<BackupRecord>, u64)> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+        if let Some(backup_type) = query.backup_type {
+            // 兼容早期记录中 pre_upgrade、PreUpgrade 等写法
+            conditions.push(
+                "replace(replace(lower(trim(backup_type)), '_', '-'), 'preupgrade', 'pre-upgrade') = ?",
+            );
+            values.push(Box::new(backup_type.as_str()));
+        }
+        if let Some(version) = &query.service_version {
+            conditions.push("(source_version = ? OR starts_with(source_version, ?))");
+            values.push(Box::new(version.clone()));
+            values.push(Box::new(format!("{version}.")));
+        }
+        if let Some(namespace) = &query.namespace {
+            conditions.push("(namespace IS NULL OR namespace = ?)");
+            values.push(Box::new(namespace.clone()));
+        }
+        if let Some(since) = query.since {
+            conditions.push("created_at >= ?");
+            values.push(Box::new(since));
+        }
+        if let Some(until) = query.until {
+            conditions.push("created_at <= ?");
+            values.push(Box::new(until));
+        }
+        let values: Vec<&dyn ToSql> = values.iter().map(|value| value.as_ref()).collect();
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+
+        let total: i64 = self.connection.query_row(
+            &format!("SELECT COUNT(*) FROM backup_records{where_clause}"),
+            values.as_slice(),
+            |row| row.get(0),
+        )?;
+
+        let direction = if query.ascending { "ASC" } else { "DESC" };
+        let order_by = match query.sort {
+            BackupSort::CreatedAt => format!("created_at {direction}, id {direction}"),
+            // 版本号按各段数值比较，无法解析的版本排在最后
+            BackupSort::Version => format!(
+                "TRY_CAST(string_split(source_version, '.') AS BIGINT[]) {direction} NULLS LAST, created_at DESC"
+            ),
+            BackupSort::Type => format!("backup_type {direction}, created_at DESC"),
+        };
+        let mut sql = format!(
+            "SELECT id, backup_path, source_version, backup_type, created_at, namespace, verified 
+             FROM backup_records{where_clause} ORDER BY {order_by}"
+        );
+        if let Some(limit) = query.limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+        if query.offset > 0 {
+            sql.push_str(&format!(" OFFSET {}", query.offset));
+        }
+
+        let mut stmt = self.connection.prepare(&sql)?;
+        let backup_iter = stmt.query_map(values.as_slice(), |row| {
+            Ok(BackupRecord {
+                id: row.get(0)?,
+                file_path: row.get(1)?,
+                service_version: row.get(2)?,
+                backup_type: row.get(3)?,
+                status: "completed".to_string(),
+                created_at: row.get(4)?,
+                namespace: row.get(5)?,
+                verified: row.get(6)?,
+            })
+        })?;
+
+        let mut backups = Vec::new();
+        for backup in backup_iter {
+            backups.push(backup?);
+        }
+
+        Ok((backups, total as u64))
+    }
+
     /// 根据ID获取备份记录
     fn get_backup_by_id(&mut self, id: i64) -> Result<Option<BackupRecord>> {
         let mut stmt = self.connection.prepare(
-            "SELECT id, backup_path, source_version, backup_type, created_at 
+            "SELECT id, backup_path, source_version, backup_type, created_at, namespace, verified 
              FROM backup_records WHERE id = ?",
         )?;
 
@@ -367,6 +560,8 @@ impl DuckDbActor {
                 backup_type: row.get(3)?,
                 status: "completed".to_string(),
                 created_at: row.get(4)?,
+                namespace: row.get(5)?,
+                verified: row.get(6)?,
             }))
         } else {
             Ok(None)
@@ -375,6 +570,11 @@ impl DuckDbActor {
 
     /// 删除备份记录
     fn delete_backup_record(&mut self, backup_id: i64) -> Result<()> {
+        // 升级历史通过外键引用备份，先解除引用
+        self.connection.execute(
+            "UPDATE upgrade_history SET backup_id = NULL WHERE backup_id = ?",
+            params![backup_id],
+        )?;
         self.connection.execute(
             "DELETE FROM backup_records WHERE id = ?",
             params![backup_id],
@@ -391,6 +591,15 @@ impl DuckDbActor {
         Ok(())
     }
 
+    /// 记录备份创建后的校验结果
+    fn set_backup_verified(&mut self, backup_id: i64, verified: bool) -> Result<()> {
+        self.connection.execute(
+            "UPDATE backup_records SET verified = ? WHERE id = ?",
+            params![verified, backup_id],
+        )?;
+        Ok(())
+    }
+
     /// 创建计划任务
     fn create_scheduled_task(
         &mut self,
@@ -738,4 +947,59 @@ impl DuckDbActor {
         }
         Ok(actions)
     }
+
+    // ========== 升级历史方法 ==========
+
+    /// 记录一次升级
+    fn record_upgrade_history(&mut self, record: &UpgradeHistoryRecord) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO upgrade_history (upgrade_id, from_version, to_version, upgrade_type, status, started_at, completed_at, backup_id, error_message)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                record.upgrade_id,
+                record.from_version,
+                record.to_version,
+                record.upgrade_type,
+                record.status,
+                record.started_at,
+                record.completed_at,
+                record.backup_id,
+                record.error_message
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// 获取升级历史
+    fn get_upgrade_history(&mut self, limit: Option<i32>) -> Result<Vec<UpgradeHistoryRecord>> {
+        let mut sql =
+            "SELECT upgrade_id, from_version, to_version, upgrade_type, status, backup_id,
+             COALESCE(started_at, created_at), completed_at, error_message
+             FROM upgrade_history ORDER BY COALESCE(started_at, created_at) DESC"
+                .to_string();
+        if let Some(limit) = limit {
+            sql.push_str(&format!(" LIMIT {limit}"));
+        }
+
+        let mut stmt = self.connection.prepare(&sql)?;
+        let record_iter = stmt.query_map([], |row| {
+            Ok(UpgradeHistoryRecord {
+                upgrade_id: row.get(0)?,
+                from_version: row.get(1)?,
+                to_version: row.get(2)?,
+                upgrade_type: row.get(3)?,
+                status: row.get(4)?,
+                backup_id: row.get(5)?,
+                started_at: row.get(6)?,
+                completed_at: row.get(7)?,
+                error_message: row.get(8)?,
+            })
+        })?;
+
+        let mut records = Vec::new();
+        for record in record_iter {
+            records.push(record?);
+        }
+        Ok(records)
+    }
 }
@@ -6,9 +6,11 @@ use tokio::sync::{mpsc, oneshot};
 use tracing::debug;
 use uuid::Uuid;
 
-use super::actor::DuckDbActor;
+use super::actor::{ConnectionSource, DuckDbActor};
 use super::messages::{AppStateRecord, DbMessage, DownloadTaskRecord, UserActionRecord};
-use super::models::{BackupRecord, ScheduledTask};
+use super::models::{BackupRecord, ScheduledTask, UpgradeHistoryRecord};
+use crate::constants::config::get_database_busy_timeout;
+use crate::database::BackupQuery;
 
 /// DuckDB数据库管理器
 #[derive(Debug, Clone)]
@@ -26,11 +28,17 @@ impl DuckDbManager {
             tokio::fs::create_dir_all(parent).await?;
         }
 
+        // 启动前先打开一次，确认数据库可用
+        let source = ConnectionSource::File {
+            path: db_path,
+            busy_timeout: get_database_busy_timeout(),
+        };
+        drop(source.acquire().await?);
+
         let (sender, receiver) = mpsc::channel(100);
 
         // 启动DuckDB Actor
-        let actor = DuckDbActor::new(db_path)?;
-        tokio::spawn(actor.run(receiver));
+        tokio::spawn(DuckDbActor::run(source, receiver));
 
         let manager = Self { sender };
 
@@ -45,8 +53,7 @@ impl DuckDbManager {
         let (sender, receiver) = mpsc::channel(100);
 
         // 启动DuckDB Actor（内存模式）
-        let actor = DuckDbActor::new_memory()?;
-        tokio::spawn(actor.run(receiver));
+        tokio::spawn(DuckDbActor::run(ConnectionSource::memory()?, receiver));
 
         let manager = Self { sender };
 
@@ -364,6 +371,39 @@ impl DuckDbManager {
             .map_err(|_| DuckError::Custom("等待数据库响应超时".to_string()))?
     }
 
+    // ========== 升级历史 ==========
+
+    /// 记录一次升级
+    pub async fn record_upgrade_history(&self, record: UpgradeHistoryRecord) -> Result<()> {
+        let (respond_to, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DbMessage::RecordUpgradeHistory { record, respond_to })
+            .await
+            .map_err(|_| DuckError::Custom("数据库Actor已关闭".to_string()))?;
+
+        receiver
+            .await
+            .map_err(|_| DuckError::Custom("等待数据库响应超时".to_string()))?
+    }
+
+    /// 获取升级历史（按开始时间倒序）
+    pub async fn get_upgrade_history(
+        &self,
+        limit: Option<i32>,
+    ) -> Result<Vec<UpgradeHistoryRecord>> {
+        let (respond_to, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DbMessage::GetUpgradeHistory { limit, respond_to })
+            .await
+            .map_err(|_| DuckError::Custom("数据库Actor已关闭".to_string()))?;
+
+        receiver
+            .await
+            .map_err(|_| DuckError::Custom("等待数据库响应超时".to_string()))?
+    }
+
     // ========== 现有的备份和任务管理 ==========
 
     /// 创建备份记录
@@ -373,6 +413,7 @@ impl DuckDbManager {
         service_version: String,
         backup_type: &str,
         status: &str,
+        namespace: Option<String>,
     ) -> Result<i64> {
         let (respond_to, receiver) = oneshot::channel();
 
@@ -382,6 +423,37 @@ impl DuckDbManager {
                 service_version,
                 backup_type: backup_type.to_string(),
                 status: status.to_string(),
+                namespace,
+                respond_to,
+            })
+            .await
+            .map_err(|_| DuckError::Custom("数据库Actor已关闭".to_string()))?;
+
+        receiver
+            .await
+            .map_err(|_| DuckError::Custom("等待数据库响应超时".to_string()))?
+    }
+
+    /// 导入已有备份文件的记录（保留原创建时间）
+    pub async fn import_backup_record(
+        &self,
+        file_path: String,
+        service_version: String,
+        backup_type: &str,
+        created_at: DateTime<Utc>,
+        backup_hash: Option<String>,
+        namespace: Option<String>,
+    ) -> Result<i64> {
+        let (respond_to, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DbMessage::ImportBackupRecord {
+                file_path,
+                service_version,
+                backup_type: backup_type.to_string(),
+                created_at,
+                backup_hash,
+                namespace,
                 respond_to,
             })
             .await
@@ -406,6 +478,20 @@ impl DuckDbManager {
             .map_err(|_| DuckError::Custom("等待数据库响应超时".to_string()))?
     }
 
+    /// 按条件查询备份记录，返回当前页的记录和满足条件的总数
+    pub async fn query_backups(&self, query: BackupQuery) -> Result<(Vec<BackupRecord>, u64)> {
+        let (respond_to, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DbMessage::QueryBackups { query, respond_to })
+            .await
+            .map_err(|_| DuckError::Custom("数据库Actor已关闭".to_string()))?;
+
+        receiver
+            .await
+            .map_err(|_| DuckError::Custom("等待数据库响应超时".to_string()))?
+    }
+
     /// 根据ID获取备份记录
     pub async fn get_backup_by_id(&self, id: i64) -> Result<Option<BackupRecord>> {
         let (respond_to, receiver) = oneshot::channel();
@@ -455,6 +541,24 @@ impl DuckDbManager {
             .map_err(|_| DuckError::Custom("等待数据库响应超时".to_string()))?
     }
 
+    /// 记录备份创建后的校验结果
+    pub async fn set_backup_verified(&self, backup_id: i64, verified: bool) -> Result<()> {
+        let (respond_to, receiver) = oneshot::channel();
+
+        self.sender
+            .send(DbMessage::SetBackupVerified {
+                backup_id,
+                verified,
+                respond_to,
+            })
+            .await
+            .map_err(|_| DuckError::Custom("数据库Actor已关闭".to_string()))?;
+
+        receiver
+            .await
+            .map_err(|_| DuckError::Custom("等待数据库响应超时".to_string()))?
+    }
+
     /// 创建计划任务
     pub async fn create_scheduled_task(
         &self,
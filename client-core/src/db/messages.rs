@@ -3,7 +3,8 @@ use tokio::sync::oneshot;
 
 use anyhow::Result;
 
-use super::models::{BackupRecord, ScheduledTask};
+use super::models::{BackupRecord, ScheduledTask, UpgradeHistoryRecord};
+use crate::database::BackupQuery;
 
 /// DuckDB数据库操作消息
 #[derive(Debug)]
@@ -101,12 +102,28 @@ pub enum DbMessage {
         service_version: String,
         backup_type: String,
         status: String,
+        namespace: Option<String>,
+        respond_to: oneshot::Sender<Result<i64>>,
+    },
+    /// 导入已有备份文件的记录（保留原创建时间）
+    ImportBackupRecord {
+        file_path: String,
+        service_version: String,
+        backup_type: String,
+        created_at: DateTime<Utc>,
+        backup_hash: Option<String>,
+        namespace: Option<String>,
         respond_to: oneshot::Sender<Result<i64>>,
     },
     /// 获取所有备份记录
     GetAllBackups {
         respond_to: oneshot::Sender<Result<Vec<BackupRecord>>>,
     },
+    /// 按条件查询备份记录，返回当前页的记录和满足条件的总数
+    QueryBackups {
+        query: BackupQuery,
+        respond_to: oneshot::Sender<Result<(Vec<BackupRecord>, u64)>>,
+    },
     /// 根据ID获取备份记录
     GetBackupById {
         id: i64,
@@ -123,6 +140,25 @@ pub enum DbMessage {
         new_path: String,
         respond_to: oneshot::Sender<Result<()>>,
     },
+    /// 记录备份创建后的校验结果
+    SetBackupVerified {
+        backup_id: i64,
+        verified: bool,
+        respond_to: oneshot::Sender<Result<()>>,
+    },
+
+    // ========== 升级历史 ==========
+    /// 记录一次升级
+    RecordUpgradeHistory {
+        record: UpgradeHistoryRecord,
+        respond_to: oneshot::Sender<Result<()>>,
+    },
+    /// 获取升级历史（按开始时间倒序）
+    GetUpgradeHistory {
+        limit: Option<i32>,
+        respond_to: oneshot::Sender<Result<Vec<UpgradeHistoryRecord>>>,
+    },
+
     /// 创建计划任务
     CreateScheduledTask {
         task_type: String,
@@ -148,6 +184,46 @@ pub enum DbMessage {
     },
 }
 
+impl DbMessage {
+    /// 无法获取数据库连接时，以同一错误回复消息
+    pub fn reject(self, error: &anyhow::Error) {
+        macro_rules! reply {
+            ($respond_to:expr) => {{
+                let _ = $respond_to.send(Err(anyhow::anyhow!("{error:#}")));
+            }};
+        }
+        match self {
+            DbMessage::InitTables { respond_to, .. } => reply!(respond_to),
+            DbMessage::GetConfig { respond_to, .. } => reply!(respond_to),
+            DbMessage::SetConfig { respond_to, .. } => reply!(respond_to),
+            DbMessage::CreateDownloadTask { respond_to, .. } => reply!(respond_to),
+            DbMessage::UpdateDownloadTaskStatus { respond_to, .. } => reply!(respond_to),
+            DbMessage::CompleteDownloadTask { respond_to, .. } => reply!(respond_to),
+            DbMessage::GetDownloadTask { respond_to, .. } => reply!(respond_to),
+            DbMessage::GetActiveDownloadTasks { respond_to, .. } => reply!(respond_to),
+            DbMessage::UpdateAppState { respond_to, .. } => reply!(respond_to),
+            DbMessage::GetAppState { respond_to, .. } => reply!(respond_to),
+            DbMessage::RecordUserAction { respond_to, .. } => reply!(respond_to),
+            DbMessage::CompleteUserAction { respond_to, .. } => reply!(respond_to),
+            DbMessage::GetUserActions { respond_to, .. } => reply!(respond_to),
+            DbMessage::CreateBackupRecord { respond_to, .. } => reply!(respond_to),
+            DbMessage::ImportBackupRecord { respond_to, .. } => reply!(respond_to),
+            DbMessage::GetAllBackups { respond_to, .. } => reply!(respond_to),
+            DbMessage::QueryBackups { respond_to, .. } => reply!(respond_to),
+            DbMessage::GetBackupById { respond_to, .. } => reply!(respond_to),
+            DbMessage::DeleteBackupRecord { respond_to, .. } => reply!(respond_to),
+            DbMessage::UpdateBackupFilePath { respond_to, .. } => reply!(respond_to),
+            DbMessage::SetBackupVerified { respond_to, .. } => reply!(respond_to),
+            DbMessage::RecordUpgradeHistory { respond_to, .. } => reply!(respond_to),
+            DbMessage::GetUpgradeHistory { respond_to, .. } => reply!(respond_to),
+            DbMessage::CreateScheduledTask { respond_to, .. } => reply!(respond_to),
+            DbMessage::GetPendingTasks { respond_to, .. } => reply!(respond_to),
+            DbMessage::UpdateTaskStatus { respond_to, .. } => reply!(respond_to),
+            DbMessage::CancelPendingTasks { respond_to, .. } => reply!(respond_to),
+        }
+    }
+}
+
 /// 下载任务记录
 #[derive(Debug, Clone)]
 pub struct DownloadTaskRecord {
@@ -10,6 +10,22 @@ pub struct BackupRecord {
     pub backup_type: String,
     pub status: String,
     pub created_at: DateTime<Utc>,
+    pub namespace: Option<String>,
+    pub verified: Option<bool>,
+}
+
+/// 升级历史记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeHistoryRecord {
+    pub upgrade_id: String,
+    pub from_version: String,
+    pub to_version: String,
+    pub upgrade_type: String,
+    pub status: String,
+    pub backup_id: Option<i64>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub error_message: Option<String>,
 }
 
 /// 计划任务
@@ -0,0 +1,270 @@
+//! # 部署阶段检查点模块
+//!
+//! 自动升级部署由多个耗时阶段组成（下载 → 备份 → 解压 → 部署 → 启动）。
+//! 每完成一个阶段就把进度写入检查点文件，进程中途退出后可以通过
+//! `--resume` 从最后完成的阶段继续，而不必重复下载和备份。
+//!
+//! 检查点与目标版本绑定：目标版本变化后旧检查点自动失效。
+
+use crate::constants::upgrade::get_deploy_checkpoint_path;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// 自动升级部署阶段（按执行顺序排列）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeployPhase {
+    /// 服务包下载完成
+    Download,
+    /// 停止服务并完成数据备份
+    Backup,
+    /// 服务包解压完成
+    Extract,
+    /// 服务部署完成
+    Deploy,
+    /// 服务启动完成
+    Start,
+}
+
+impl DeployPhase {
+    /// 所有阶段，按执行顺序排列
+    pub const ALL: [DeployPhase; 5] = [
+        DeployPhase::Download,
+        DeployPhase::Backup,
+        DeployPhase::Extract,
+        DeployPhase::Deploy,
+        DeployPhase::Start,
+    ];
+
+    /// 检查点文件和命令行中使用的标识
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Download => "download",
+            Self::Backup => "backup",
+            Self::Extract => "extract",
+            Self::Deploy => "deploy",
+            Self::Start => "start",
+        }
+    }
+}
+
+impl Display for DeployPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Download => write!(f, "下载"),
+            Self::Backup => write!(f, "备份"),
+            Self::Extract => write!(f, "解压"),
+            Self::Deploy => write!(f, "部署"),
+            Self::Start => write!(f, "启动"),
+        }
+    }
+}
+
+/// 部署检查点内容
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeployCheckpoint {
+    /// 本次部署的目标版本
+    pub target_version: String,
+    /// 已完成的阶段
+    pub completed_phases: Vec<DeployPhase>,
+    /// 是否为首次部署（解压后 docker 目录已存在，需在开始时记录）
+    pub is_first_deployment: bool,
+    /// 升级前创建的备份 ID
+    pub backup_id: Option<i64>,
+    /// 升级前的服务版本，续传时用于判断需要执行的数据目录迁移
+    #[serde(default)]
+    pub from_version: Option<String>,
+    /// 补丁只影响部分服务时需要重启的服务，为空时重启全部服务
+    #[serde(default)]
+    pub restart_services: Option<Vec<String>>,
+    /// 最后更新时间
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DeployCheckpoint {
+    /// 为目标版本创建新的检查点
+    pub fn new(target_version: impl Into<String>, is_first_deployment: bool) -> Self {
+        Self {
+            target_version: target_version.into(),
+            completed_phases: Vec::new(),
+            is_first_deployment,
+            backup_id: None,
+            from_version: None,
+            restart_services: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// 阶段是否已完成
+    pub fn is_completed(&self, phase: DeployPhase) -> bool {
+        self.completed_phases.contains(&phase)
+    }
+
+    /// 最后完成的阶段
+    pub fn last_completed_phase(&self) -> Option<DeployPhase> {
+        self.completed_phases.iter().max().copied()
+    }
+
+    /// 指定阶段之前第一个未完成的阶段，单独执行某个阶段前用于检查前置阶段
+    pub fn first_incomplete_before(&self, phase: DeployPhase) -> Option<DeployPhase> {
+        DeployPhase::ALL
+            .into_iter()
+            .take_while(|earlier| *earlier < phase)
+            .find(|earlier| !self.is_completed(*earlier))
+    }
+
+    /// 标记阶段完成
+    pub fn mark_completed(&mut self, phase: DeployPhase) {
+        if !self.is_completed(phase) {
+            self.completed_phases.push(phase);
+            self.completed_phases.sort();
+        }
+        self.updated_at = Utc::now();
+    }
+}
+
+/// 检查点文件存储
+#[derive(Debug, Clone)]
+pub struct DeployCheckpointStore {
+    path: PathBuf,
+}
+
+impl Default for DeployCheckpointStore {
+    fn default() -> Self {
+        Self::new(get_deploy_checkpoint_path())
+    }
+}
+
+impl DeployCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 读取检查点；文件不存在或内容损坏时返回 None
+    pub fn load(&self) -> Option<DeployCheckpoint> {
+        let content = std::fs::read_to_string(&self.path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(e) => {
+                warn!(
+                    "⚠️ 部署检查点文件损坏，已忽略: {} ({})",
+                    self.path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// 读取指定目标版本的检查点，版本不一致时返回 None
+    pub fn load_for_version(&self, target_version: &str) -> Option<DeployCheckpoint> {
+        self.load()
+            .filter(|checkpoint| checkpoint.target_version == target_version)
+    }
+
+    /// 保存检查点（先写临时文件再重命名，避免中途退出留下半个文件）
+    pub fn save(&self, checkpoint: &DeployCheckpoint) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_string_pretty(checkpoint)?)?;
+        std::fs::rename(&temp_path, &self.path)?;
+        debug!(
+            "部署检查点已更新: {} -> {:?}",
+            checkpoint.target_version, checkpoint.completed_phases
+        );
+        Ok(())
+    }
+
+    /// 删除检查点
+    pub fn clear(&self) -> Result<()> {
+        if self.path.exists() {
+            std::fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_mark_completed_keeps_phase_order() {
+        let mut checkpoint = DeployCheckpoint::new("1.2.0", false);
+        assert_eq!(checkpoint.last_completed_phase(), None);
+
+        checkpoint.mark_completed(DeployPhase::Backup);
+        checkpoint.mark_completed(DeployPhase::Download);
+        checkpoint.mark_completed(DeployPhase::Backup);
+
+        assert_eq!(
+            checkpoint.completed_phases,
+            vec![DeployPhase::Download, DeployPhase::Backup]
+        );
+        assert_eq!(checkpoint.last_completed_phase(), Some(DeployPhase::Backup));
+        assert!(!checkpoint.is_completed(DeployPhase::Extract));
+    }
+
+    #[test]
+    fn test_first_incomplete_before() {
+        let mut checkpoint = DeployCheckpoint::new("1.2.0", false);
+        assert_eq!(
+            checkpoint.first_incomplete_before(DeployPhase::Download),
+            None
+        );
+        assert_eq!(
+            checkpoint.first_incomplete_before(DeployPhase::Extract),
+            Some(DeployPhase::Download)
+        );
+
+        checkpoint.mark_completed(DeployPhase::Download);
+        checkpoint.mark_completed(DeployPhase::Backup);
+        assert_eq!(
+            checkpoint.first_incomplete_before(DeployPhase::Extract),
+            None
+        );
+        assert_eq!(
+            checkpoint.first_incomplete_before(DeployPhase::Start),
+            Some(DeployPhase::Extract)
+        );
+    }
+
+    #[test]
+    fn test_store_roundtrip_and_version_binding() {
+        let temp_dir = TempDir::new().unwrap();
+        let store =
+            DeployCheckpointStore::new(temp_dir.path().join("data").join("checkpoint.json"));
+        assert!(store.load().is_none());
+
+        let mut checkpoint = DeployCheckpoint::new("1.2.0", true);
+        checkpoint.backup_id = Some(7);
+        checkpoint.mark_completed(DeployPhase::Download);
+        store.save(&checkpoint).unwrap();
+
+        assert_eq!(store.load_for_version("1.2.0"), Some(checkpoint));
+        assert!(store.load_for_version("1.3.0").is_none());
+
+        store.clear().unwrap();
+        assert!(store.load().is_none());
+    }
+
+    #[test]
+    fn test_corrupted_checkpoint_is_ignored() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("checkpoint.json");
+        std::fs::write(&path, "{not json").unwrap();
+
+        assert!(DeployCheckpointStore::new(path).load().is_none());
+    }
+}
@@ -0,0 +1,365 @@
+//! # 下载缓存与升级历史
+//!
+//! 下载缓存按版本保存服务包：全量包位于 `<download_dir>/<基础版本>/full/`，
+//! 补丁包位于 `<download_dir>/<基础版本>/<完整版本>/`。[`scan_download_cache`] 识别这些目录，
+//! [`link_upgrades`] 按升级历史找出使用过每个服务包的升级：
+//!
+//! - 全量包：目标版本的基础版本与服务包相同的全量升级（回滚也记为全量升级）
+//! - 补丁包：基础版本相同，且补丁版本位于升级前后版本之间的补丁升级（含补丁链的中间补丁）
+//!
+//! 保留某个版本需要它的全量包以及同一基础版本中不超过该版本的补丁包，
+//! [`removable_packages`] 据此找出可以删除的服务包。要保留的版本可以写为 `current`（当前部署版本）、
+//! `previous`（升级到当前版本之前的版本，回滚需要）或具体版本号，由 [`resolve_keep_versions`] 解析。
+
+use crate::database::{UpgradeKind, UpgradeRecord, UpgradeStatus};
+use crate::upgrade_strategy::DownloadType;
+use crate::version::Version;
+use anyhow::Result;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use walkdir::WalkDir;
+
+/// 缓存中的服务包
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedPackage {
+    /// 服务包对应的版本，全量包为基础版本
+    pub version: Version,
+    /// 全量包或补丁包
+    pub download_type: DownloadType,
+    /// 服务包所在目录
+    pub dir: PathBuf,
+    /// 目录中所有文件的总大小（字节）
+    pub size_bytes: u64,
+}
+
+impl CachedPackage {
+    /// 服务包版本的显示形式，全量包显示基础版本
+    pub fn version_label(&self) -> String {
+        match self.download_type {
+            DownloadType::Full => self.version.base_version_string(),
+            DownloadType::Patch => self.version.to_string(),
+        }
+    }
+
+    /// 该服务包是否被指定的升级使用过
+    pub fn used_by(&self, record: &UpgradeRecord) -> bool {
+        let (Ok(from), Ok(to)) = (
+            record.from_version.parse::<Version>(),
+            record.to_version.parse::<Version>(),
+        ) else {
+            return false;
+        };
+        match (&self.download_type, record.upgrade_type) {
+            (DownloadType::Full, UpgradeKind::Full) => {
+                to.base_version() == self.version.base_version()
+            }
+            (DownloadType::Patch, UpgradeKind::Patch) => {
+                to.base_version() == self.version.base_version()
+                    && from.base_version() == self.version.base_version()
+                    && from < self.version
+                    && self.version <= to
+            }
+            _ => false,
+        }
+    }
+
+    /// 恢复指定版本是否需要该服务包
+    pub fn needed_by(&self, version: &Version) -> bool {
+        match self.download_type {
+            DownloadType::Full => self.version.base_version() == version.base_version(),
+            DownloadType::Patch => {
+                self.version.base_version() == version.base_version() && self.version <= *version
+            }
+        }
+    }
+}
+
+/// 服务包及使用过它的升级
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub package: CachedPackage,
+    /// 使用过该服务包的升级，顺序与传入的升级历史一致
+    pub upgrades: Vec<UpgradeRecord>,
+}
+
+/// 要保留的版本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeepVersion {
+    /// 当前部署的版本
+    Current,
+    /// 升级到当前版本之前的版本
+    Previous,
+    /// 指定版本
+    Version(Version),
+}
+
+impl FromStr for KeepVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "current" => Ok(Self::Current),
+            "previous" => Ok(Self::Previous),
+            other => other.parse().map(Self::Version).map_err(|_| {
+                anyhow::anyhow!(
+                    "无效的保留版本: {}（可用: current, previous 或具体版本号）",
+                    s.trim()
+                )
+            }),
+        }
+    }
+}
+
+impl fmt::Display for KeepVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Current => write!(f, "current"),
+            Self::Previous => write!(f, "previous"),
+            Self::Version(version) => write!(f, "{version}"),
+        }
+    }
+}
+
+/// 扫描下载缓存目录中的服务包，无法识别为版本的目录会被忽略
+pub fn scan_download_cache(download_dir: &Path) -> Result<Vec<CachedPackage>> {
+    let mut packages = Vec::new();
+    if !download_dir.exists() {
+        return Ok(packages);
+    }
+
+    for base_entry in fs::read_dir(download_dir)? {
+        let base_dir = base_entry?.path();
+        let Some(base_version) = dir_version(&base_dir) else {
+            continue;
+        };
+        for entry in fs::read_dir(&base_dir)? {
+            let dir = entry?.path();
+            let Some(name) = dir
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            let package = if name == DownloadType::Full.to_string() {
+                Some((base_version.base_version(), DownloadType::Full))
+            } else {
+                dir_version(&dir)
+                    .filter(|version| version.base_version() == base_version.base_version())
+                    .map(|version| (version, DownloadType::Patch))
+            };
+            if let Some((version, download_type)) = package {
+                packages.push(CachedPackage {
+                    version,
+                    download_type,
+                    size_bytes: directory_size(&dir),
+                    dir,
+                });
+            }
+        }
+    }
+
+    packages.sort_by(|a, b| {
+        b.version.cmp(&a.version).then_with(|| {
+            (a.download_type == DownloadType::Patch).cmp(&(b.download_type == DownloadType::Patch))
+        })
+    });
+    Ok(packages)
+}
+
+/// 为每个服务包找出使用过它的升级
+pub fn link_upgrades(packages: Vec<CachedPackage>, history: &[UpgradeRecord]) -> Vec<CacheEntry> {
+    packages
+        .into_iter()
+        .map(|package| {
+            let upgrades = history
+                .iter()
+                .filter(|record| package.used_by(record))
+                .cloned()
+                .collect();
+            CacheEntry { package, upgrades }
+        })
+        .collect()
+}
+
+/// 把保留规则解析为具体版本
+///
+/// `previous` 取最近一次成功升级到当前版本时的升级前版本，没有这样的记录时取当前版本之前
+/// 最近一次成功升级的目标版本；都找不到时忽略。`history` 需按开始时间倒序排列
+pub fn resolve_keep_versions(
+    keep: &[KeepVersion],
+    current: &Version,
+    history: &[UpgradeRecord],
+) -> Vec<Version> {
+    let mut versions = Vec::new();
+    for rule in keep {
+        let version = match rule {
+            KeepVersion::Current => Some(current.clone()),
+            KeepVersion::Previous => previous_version(current, history),
+            KeepVersion::Version(version) => Some(version.clone()),
+        };
+        if let Some(version) = version.filter(|version| !versions.contains(version)) {
+            versions.push(version);
+        }
+    }
+    versions
+}
+
+/// 不被任何保留版本需要的服务包
+pub fn removable_packages<'a>(
+    entries: &'a [CacheEntry],
+    keep: &[Version],
+) -> Vec<&'a CachedPackage> {
+    entries
+        .iter()
+        .map(|entry| &entry.package)
+        .filter(|package| !keep.iter().any(|version| package.needed_by(version)))
+        .collect()
+}
+
+/// 删除服务包目录，基础版本目录变为空时一并删除
+pub fn remove_package(package: &CachedPackage) -> Result<()> {
+    fs::remove_dir_all(&package.dir)?;
+    if let Some(base_dir) = package.dir.parent() {
+        let is_empty = fs::read_dir(base_dir)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false);
+        if is_empty {
+            fs::remove_dir(base_dir)?;
+        }
+    }
+    Ok(())
+}
+
+fn previous_version(current: &Version, history: &[UpgradeRecord]) -> Option<Version> {
+    let successful = || {
+        history
+            .iter()
+            .filter(|record| record.status == UpgradeStatus::Success)
+    };
+    successful()
+        .find(|record| record.to_version.parse::<Version>().ok().as_ref() == Some(current))
+        .and_then(|record| record.from_version.parse().ok())
+        .or_else(|| {
+            successful()
+                .filter_map(|record| record.to_version.parse::<Version>().ok())
+                .find(|version| version != current)
+        })
+}
+
+fn dir_version(dir: &Path) -> Option<Version> {
+    if !dir.is_dir() {
+        return None;
+    }
+    dir.file_name()?.to_str()?.parse().ok()
+}
+
+fn directory_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    fn record(from: &str, to: &str, upgrade_type: UpgradeKind) -> UpgradeRecord {
+        UpgradeRecord {
+            upgrade_id: format!("{from}->{to}"),
+            from_version: from.to_string(),
+            to_version: to.to_string(),
+            upgrade_type,
+            status: UpgradeStatus::Success,
+            backup_id: None,
+            started_at: Utc::now(),
+            completed_at: None,
+            error_message: None,
+        }
+    }
+
+    fn write_package(root: &Path, base: &str, dir: &str) {
+        let dir = root.join(base).join(dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("docker.zip"), b"package").unwrap();
+    }
+
+    #[test]
+    fn test_link_upgrades_and_removable_packages() {
+        let temp = TempDir::new().unwrap();
+        write_package(temp.path(), "0.0.12", "full");
+        write_package(temp.path(), "0.0.13", "full");
+        write_package(temp.path(), "0.0.13", "0.0.13.1");
+        write_package(temp.path(), "0.0.13", "0.0.13.2");
+        write_package(temp.path(), "0.0.14", "full");
+        fs::create_dir_all(temp.path().join("tmp")).unwrap();
+
+        let packages = scan_download_cache(temp.path()).unwrap();
+        let labels: Vec<_> = packages.iter().map(|p| p.version_label()).collect();
+        assert_eq!(
+            labels,
+            ["0.0.14", "0.0.13.2", "0.0.13.1", "0.0.13", "0.0.12"]
+        );
+        assert_eq!(packages[0].size_bytes, 7);
+
+        // 倒序的升级历史：0.0.12 -> 0.0.13 -> 0.0.13.2（补丁链）
+        let history = vec![
+            record("0.0.13.0", "0.0.13.2", UpgradeKind::Patch),
+            record("0.0.12.0", "0.0.13.0", UpgradeKind::Full),
+        ];
+        let entries = link_upgrades(packages, &history);
+        let used: Vec<_> = entries.iter().map(|e| e.upgrades.len()).collect();
+        assert_eq!(used, [0, 1, 1, 1, 0]);
+
+        let current: Version = "0.0.13.2".parse().unwrap();
+        let keep = resolve_keep_versions(
+            &[KeepVersion::Current, KeepVersion::Previous],
+            &current,
+            &history,
+        );
+        assert_eq!(keep, [current.clone(), "0.0.13.0".parse().unwrap()]);
+
+        let removable: Vec<_> = removable_packages(&entries, &keep)
+            .iter()
+            .map(|p| p.version_label())
+            .collect();
+        assert_eq!(removable, ["0.0.14", "0.0.12"]);
+
+        remove_package(&entries[4].package).unwrap();
+        assert!(!temp.path().join("0.0.12").exists());
+        remove_package(&entries[2].package).unwrap();
+        assert!(temp.path().join("0.0.13").join("full").exists());
+    }
+
+    #[test]
+    fn test_keep_version_parse() {
+        assert_eq!(
+            "current".parse::<KeepVersion>().unwrap(),
+            KeepVersion::Current
+        );
+        assert_eq!(
+            " Previous".parse::<KeepVersion>().unwrap(),
+            KeepVersion::Previous
+        );
+        assert_eq!(
+            "0.0.13".parse::<KeepVersion>().unwrap(),
+            KeepVersion::Version("0.0.13.0".parse().unwrap())
+        );
+        assert!("latest".parse::<KeepVersion>().is_err());
+
+        // 没有升级到当前版本的记录时，previous 取之前最近一次成功升级的目标版本
+        let history = vec![record("0.0.11.0", "0.0.12.0", UpgradeKind::Full)];
+        let current: Version = "0.0.13.0".parse().unwrap();
+        assert_eq!(
+            resolve_keep_versions(&[KeepVersion::Previous], &current, &history),
+            ["0.0.12.0".parse::<Version>().unwrap()]
+        );
+    }
+}
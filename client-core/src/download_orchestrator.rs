@@ -0,0 +1,471 @@
+//! # 多文件并发下载编排
+//!
+//! 升级时可能需要同时下载服务包、镜像包和补丁包，本模块负责并发下载多个文件：
+//! - 限制同时进行的下载数量
+//! - 汇总所有文件的整体进度，通过单一回调上报
+//! - 所有下载共享同一个带宽预算（令牌桶限速）
+//! - 失败策略：快速失败（取消其余下载）或继续下载其余文件
+//!
+//! 每个文件仍由 [`FileDownloader`] 下载，保留断点续传和哈希校验能力；
+//! 快速失败时被取消的下载会保留元数据，下次可继续续传。
+
+use crate::downloader::{DownloadProgress, DownloaderConfig, FileDownloader};
+use anyhow::Result;
+use reqwest::Client;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+/// 默认最大并发下载数
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+/// 共享带宽限速器（令牌桶）
+///
+/// 桶容量为一秒的流量，允许短暂突发；令牌不足时按欠缺量计算等待时间
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    bytes_per_second: u64,
+    state: tokio::sync::Mutex<LimiterState>,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    available: f64,
+    last_refill: Instant,
+}
+
+impl BandwidthLimiter {
+    /// 创建限速器，`bytes_per_second` 为 0 时视为 1
+    pub fn new(bytes_per_second: u64) -> Self {
+        let bytes_per_second = bytes_per_second.max(1);
+        Self {
+            bytes_per_second,
+            state: tokio::sync::Mutex::new(LimiterState {
+                available: bytes_per_second as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn bytes_per_second(&self) -> u64 {
+        self.bytes_per_second
+    }
+
+    /// 申请发送 `bytes` 字节的额度，超出预算时等待
+    pub async fn acquire(&self, bytes: usize) {
+        let wait = {
+            let mut state = self.state.lock().await;
+            let rate = self.bytes_per_second as f64;
+            let now = Instant::now();
+            let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+            state.available = (state.available + elapsed * rate).min(rate);
+            state.last_refill = now;
+            state.available -= bytes as f64;
+            if state.available < 0.0 {
+                Duration::from_secs_f64(-state.available / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// 下载失败时的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailurePolicy {
+    /// 任一文件失败即取消其余下载
+    #[default]
+    FailFast,
+    /// 继续下载其余文件，最后汇总结果
+    ContinueOnError,
+}
+
+/// 编排器配置
+#[derive(Debug, Clone)]
+pub struct OrchestratorConfig {
+    /// 最大并发下载数
+    pub max_concurrent: usize,
+    /// 所有下载共享的带宽上限（字节/秒），None 表示不限速
+    pub bandwidth_limit: Option<u64>,
+    pub failure_policy: FailurePolicy,
+}
+
+impl Default for OrchestratorConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent: DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+            bandwidth_limit: None,
+            failure_policy: FailurePolicy::default(),
+        }
+    }
+}
+
+/// 待下载的文件
+#[derive(Debug, Clone)]
+pub struct DownloadArtifact {
+    /// 文件标识（如 "service"、"images"、"patch"），需唯一
+    pub name: String,
+    pub url: String,
+    pub path: PathBuf,
+    pub expected_hash: Option<String>,
+    pub version: Option<String>,
+}
+
+impl DownloadArtifact {
+    pub fn new(name: impl Into<String>, url: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            path: path.into(),
+            expected_hash: None,
+            version: None,
+        }
+    }
+
+    pub fn with_hash(mut self, expected_hash: impl Into<String>) -> Self {
+        self.expected_hash = Some(expected_hash.into());
+        self
+    }
+
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+}
+
+/// 整体下载进度
+#[derive(Debug, Clone)]
+pub struct AggregateProgress {
+    pub total_artifacts: usize,
+    pub completed_artifacts: usize,
+    pub downloaded_bytes: u64,
+    /// 已知大小的文件总字节数（服务器未返回大小的文件不计入）
+    pub total_bytes: u64,
+    pub percentage: f64,
+    /// 触发本次上报的文件进度
+    pub current: DownloadProgress,
+}
+
+/// 单个文件的下载结果
+#[derive(Debug)]
+pub struct ArtifactOutcome {
+    pub name: String,
+    pub path: PathBuf,
+    pub result: Result<()>,
+}
+
+impl ArtifactOutcome {
+    pub fn is_success(&self) -> bool {
+        self.result.is_ok()
+    }
+}
+
+/// 汇总各文件进度
+#[derive(Debug, Default)]
+struct ProgressAggregator {
+    total_artifacts: usize,
+    completed: usize,
+    /// 文件名 -> (已下载字节, 总字节)
+    artifacts: HashMap<String, (u64, u64)>,
+}
+
+impl ProgressAggregator {
+    fn new(total_artifacts: usize) -> Self {
+        Self {
+            total_artifacts,
+            ..Default::default()
+        }
+    }
+
+    fn update(&mut self, name: &str, progress: DownloadProgress) -> AggregateProgress {
+        self.artifacts.insert(
+            name.to_string(),
+            (progress.downloaded_bytes, progress.total_bytes),
+        );
+        self.snapshot(progress)
+    }
+
+    fn mark_completed(&mut self) {
+        self.completed += 1;
+    }
+
+    fn snapshot(&self, current: DownloadProgress) -> AggregateProgress {
+        let (downloaded_bytes, total_bytes) = self
+            .artifacts
+            .values()
+            .filter(|(_, total)| *total > 0)
+            .fold((0, 0), |(downloaded, total), (d, t)| {
+                (downloaded + (*d).min(*t), total + t)
+            });
+        let percentage = if total_bytes > 0 {
+            downloaded_bytes as f64 / total_bytes as f64 * 100.0
+        } else {
+            0.0
+        };
+        AggregateProgress {
+            total_artifacts: self.total_artifacts,
+            completed_artifacts: self.completed,
+            downloaded_bytes,
+            total_bytes,
+            percentage,
+            current,
+        }
+    }
+}
+
+/// 多文件并发下载编排器
+pub struct DownloadOrchestrator {
+    config: OrchestratorConfig,
+    downloader: Arc<FileDownloader>,
+}
+
+impl DownloadOrchestrator {
+    pub fn new(config: OrchestratorConfig, downloader_config: DownloaderConfig) -> Self {
+        Self::build(config, downloader_config, None)
+    }
+
+    /// 使用自定义 HTTP 客户端（如带认证的客户端）
+    pub fn new_with_custom_client(
+        config: OrchestratorConfig,
+        downloader_config: DownloaderConfig,
+        client: Client,
+    ) -> Self {
+        Self::build(config, downloader_config, Some(client))
+    }
+
+    fn build(
+        config: OrchestratorConfig,
+        mut downloader_config: DownloaderConfig,
+        client: Option<Client>,
+    ) -> Self {
+        if let Some(limit) = config.bandwidth_limit {
+            downloader_config.bandwidth_limiter = Some(Arc::new(BandwidthLimiter::new(limit)));
+        }
+        let downloader = match client {
+            Some(client) => FileDownloader::new_with_custom_client(downloader_config, client),
+            None => FileDownloader::new(downloader_config),
+        };
+        Self {
+            config,
+            downloader: Arc::new(downloader),
+        }
+    }
+
+    /// 并发下载所有文件
+    ///
+    /// 快速失败策略下返回第一个失败的错误；继续策略下返回每个文件的结果（顺序与输入一致）
+    pub async fn download_all<F>(
+        &self,
+        artifacts: Vec<DownloadArtifact>,
+        progress_callback: Option<F>,
+    ) -> Result<Vec<ArtifactOutcome>>
+    where
+        F: Fn(AggregateProgress) + Send + Sync + 'static,
+    {
+        let total = artifacts.len();
+        let max_concurrent = self.config.max_concurrent.max(1);
+        info!(
+            "📦 开始并发下载 {} 个文件 (并发数: {}, 限速: {})",
+            total,
+            max_concurrent,
+            self.config
+                .bandwidth_limit
+                .map(|limit| format!("{:.1} MB/s", limit as f64 / 1024.0 / 1024.0))
+                .unwrap_or_else(|| "不限".to_string())
+        );
+
+        let semaphore = Arc::new(Semaphore::new(max_concurrent));
+        let aggregator = Arc::new(Mutex::new(ProgressAggregator::new(total)));
+        let callback = progress_callback.map(Arc::new);
+        let mut tasks = JoinSet::new();
+
+        for (index, artifact) in artifacts.iter().cloned().enumerate() {
+            let semaphore = semaphore.clone();
+            let downloader = self.downloader.clone();
+            let aggregator = aggregator.clone();
+            let callback = callback.clone();
+
+            tasks.spawn(async move {
+                let _permit = match semaphore.acquire_owned().await {
+                    Ok(permit) => permit,
+                    Err(e) => return (index, Err(anyhow::anyhow!("获取下载并发许可失败: {e}"))),
+                };
+
+                let name = artifact.name.clone();
+                let per_file_callback = callback.map(|callback| {
+                    let aggregator = aggregator.clone();
+                    move |progress: DownloadProgress| {
+                        let snapshot = aggregator
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .update(&name, progress);
+                        callback(snapshot);
+                    }
+                });
+
+                if let Some(parent) = artifact.path.parent() {
+                    if let Err(e) = tokio::fs::create_dir_all(parent).await {
+                        return (index, Err(anyhow::anyhow!("创建下载目录失败: {e}")));
+                    }
+                }
+
+                let result = downloader
+                    .download_file_with_options(
+                        &artifact.url,
+                        &artifact.path,
+                        per_file_callback,
+                        artifact.expected_hash.as_deref(),
+                        artifact.version.as_deref(),
+                    )
+                    .await;
+                if result.is_ok() {
+                    aggregator
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .mark_completed();
+                }
+                (index, result)
+            });
+        }
+
+        let mut results: Vec<Option<Result<()>>> = (0..total).map(|_| None).collect();
+        while let Some(joined) = tasks.join_next().await {
+            let (index, result) = match joined {
+                Ok(output) => output,
+                Err(e) if e.is_cancelled() => continue,
+                Err(e) => return Err(anyhow::anyhow!("下载任务异常退出: {e}")),
+            };
+
+            if let Err(e) = &result {
+                warn!("❌ 文件下载失败 [{}]: {}", artifacts[index].name, e);
+                if self.config.failure_policy == FailurePolicy::FailFast {
+                    warn!("🛑 快速失败：取消其余下载任务");
+                    tasks.abort_all();
+                    return Err(anyhow::anyhow!(
+                        "文件 {} 下载失败: {}",
+                        artifacts[index].name,
+                        e
+                    ));
+                }
+            } else {
+                info!("✅ 文件下载完成 [{}]", artifacts[index].name);
+            }
+            results[index] = Some(result);
+        }
+
+        let outcomes: Vec<ArtifactOutcome> = artifacts
+            .into_iter()
+            .zip(results)
+            .map(|(artifact, result)| ArtifactOutcome {
+                name: artifact.name,
+                path: artifact.path,
+                result: result.unwrap_or_else(|| Err(anyhow::anyhow!("下载任务未完成"))),
+            })
+            .collect();
+
+        let succeeded = outcomes.iter().filter(|o| o.is_success()).count();
+        info!("📊 并发下载结束: 成功 {}/{}", succeeded, total);
+        Ok(outcomes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloader::DownloadStatus;
+    use tempfile::TempDir;
+
+    fn progress(downloaded_bytes: u64, total_bytes: u64) -> DownloadProgress {
+        DownloadProgress {
+            task_id: "test".to_string(),
+            file_name: "test.bin".to_string(),
+            downloaded_bytes,
+            total_bytes,
+            download_speed: 0.0,
+            eta_seconds: 0,
+            percentage: 0.0,
+            status: DownloadStatus::Downloading,
+        }
+    }
+
+    #[test]
+    fn test_progress_aggregation() {
+        let mut aggregator = ProgressAggregator::new(3);
+        aggregator.update("service", progress(50, 100));
+        aggregator.update("unknown-size", progress(999, 0));
+        let snapshot = aggregator.update("images", progress(100, 300));
+
+        assert_eq!(snapshot.total_artifacts, 3);
+        assert_eq!(snapshot.downloaded_bytes, 150);
+        assert_eq!(snapshot.total_bytes, 400);
+        assert!((snapshot.percentage - 37.5).abs() < f64::EPSILON);
+
+        aggregator.mark_completed();
+        let snapshot = aggregator.update("service", progress(100, 100));
+        assert_eq!(snapshot.completed_artifacts, 1);
+        assert_eq!(snapshot.downloaded_bytes, 200);
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_limiter_throttles_after_burst() {
+        let limiter = BandwidthLimiter::new(10_000);
+        let started = Instant::now();
+        limiter.acquire(10_000).await;
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        limiter.acquire(2_000).await;
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    fn unreachable_artifacts(temp_dir: &TempDir) -> Vec<DownloadArtifact> {
+        // 端口 1 无服务监听，连接会立即被拒绝
+        ["service", "patch"]
+            .iter()
+            .map(|name| {
+                DownloadArtifact::new(
+                    *name,
+                    format!("http://127.0.0.1:1/{name}.zip"),
+                    temp_dir.path().join(format!("{name}.zip")),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast_returns_error() {
+        let temp_dir = TempDir::new().unwrap();
+        let orchestrator =
+            DownloadOrchestrator::new(OrchestratorConfig::default(), DownloaderConfig::default());
+
+        let result = orchestrator
+            .download_all::<fn(AggregateProgress)>(unreachable_artifacts(&temp_dir), None)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_continue_on_error_reports_each_artifact() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = OrchestratorConfig {
+            failure_policy: FailurePolicy::ContinueOnError,
+            ..Default::default()
+        };
+        let orchestrator = DownloadOrchestrator::new(config, DownloaderConfig::default());
+
+        let outcomes = orchestrator
+            .download_all::<fn(AggregateProgress)>(unreachable_artifacts(&temp_dir), None)
+            .await
+            .unwrap();
+        let names: Vec<&str> = outcomes.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, vec!["service", "patch"]);
+        assert!(outcomes.iter().all(|o| !o.is_success()));
+    }
+}
@@ -31,20 +31,28 @@
 //! - 自动检测已下载部分
 //! - 智能文件完整性验证
 //! - 支持大文件下载恢复
+//!
+//! 多个文件的并发下载见 [`crate::download_orchestrator`]
 
+use crate::constants::telemetry::METRICS_TARGET;
+use crate::download_orchestrator::BandwidthLimiter;
 use crate::error::DuckError;
+use crate::events::{EventSender, OperationKind};
 use anyhow::Result;
 use chrono;
 use futures::stream::StreamExt;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::fs::{File, OpenOptions};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::AsyncWriteExt;
 use tracing::{info, warn};
 
+/// 下载失败后重试前的等待时间（秒）
+const DOWNLOAD_RETRY_DELAY_SECONDS: u64 = 2;
+
 /// 下载进度状态枚举
 #[derive(Debug, Clone)]
 pub enum DownloadStatus {
@@ -132,6 +140,10 @@ pub struct DownloaderConfig {
     pub progress_interval_seconds: u64, // 进度显示时间间隔（秒）⭐
     pub progress_bytes_interval: u64,   // 进度显示字节间隔 ⭐
     pub enable_metadata: bool,          // 启用元数据管理 ⭐
+    /// 共享带宽限速器，多个下载可共用同一预算
+    pub bandwidth_limiter: Option<Arc<BandwidthLimiter>>,
+    /// 操作事件发送端（阶段、进度、重试）
+    pub events: EventSender,
 }
 
 impl Default for DownloaderConfig {
@@ -146,6 +158,8 @@ impl Default for DownloaderConfig {
             progress_interval_seconds: 10,              // 每10秒显示一次进度 ⭐
             progress_bytes_interval: 100 * 1024 * 1024, // 每100MB显示一次进度 ⭐
             enable_metadata: true,                      // 默认启用元数据管理 ⭐
+            bandwidth_limiter: None,
+            events: EventSender::default(),
         }
     }
 }
@@ -428,6 +442,15 @@ impl FileDownloader {
             .map_err(|e| DuckError::custom(format!("解析元数据失败: {e}")))?;
 
         info!("📋 已加载下载元数据: {}", metadata_path.display());
+        let last_update = chrono::DateTime::parse_from_rfc3339(&metadata.last_update).ok();
+        if last_update.is_some_and(|last_update| {
+            crate::clock::is_in_future(last_update.with_timezone(&chrono::Utc), chrono::Utc::now())
+        }) {
+            warn!(
+                "⚠️ 下载元数据的更新时间 {} 晚于当前系统时间，系统时间可能被调整过",
+                metadata.last_update
+            );
+        }
         Ok(Some(metadata))
     }
 
@@ -615,29 +638,73 @@ impl FileDownloader {
         // 保存初始元数据
         self.save_metadata(download_path, &metadata).await?;
 
-        // 执行下载
-        let result = match downloader_type {
-            DownloaderType::Http => {
-                self.download_via_http_with_resume(
-                    url,
-                    download_path,
-                    progress_callback,
-                    existing_size,
-                    total_size,
-                    &mut metadata,
-                )
-                .await
-            }
-            DownloaderType::HttpExtendedTimeout => {
-                self.download_via_http_extended_timeout_with_resume(
-                    url,
-                    download_path,
-                    progress_callback,
-                    existing_size,
-                    total_size,
-                    &mut metadata,
-                )
-                .await
+        let events = &self.config.events;
+        let phase = format!(
+            "下载 {}",
+            download_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+        );
+        events.phase_started(OperationKind::Download, phase.as_str());
+
+        // 执行下载，失败时按 retry_count 重试，支持续传时从已下载的位置继续
+        let download_started = std::time::Instant::now();
+        let max_attempts = self.config.retry_count + 1;
+        let mut attempt = 1;
+        let mut resume_size = existing_size;
+        let result = loop {
+            let result = match downloader_type {
+                DownloaderType::Http => {
+                    self.download_via_http_with_resume(
+                        url,
+                        download_path,
+                        progress_callback.as_ref(),
+                        resume_size,
+                        total_size,
+                        &mut metadata,
+                    )
+                    .await
+                }
+                DownloaderType::HttpExtendedTimeout => {
+                    self.download_via_http_extended_timeout_with_resume(
+                        url,
+                        download_path,
+                        progress_callback.as_ref(),
+                        resume_size,
+                        total_size,
+                        &mut metadata,
+                    )
+                    .await
+                }
+            };
+
+            match result {
+                Err(e) if attempt < max_attempts => {
+                    attempt += 1;
+                    warn!(
+                        "⚠️ 下载失败: {}，{} 秒后进行第 {}/{} 次尝试",
+                        e, DOWNLOAD_RETRY_DELAY_SECONDS, attempt, max_attempts
+                    );
+                    events.retry(
+                        OperationKind::Download,
+                        attempt,
+                        max_attempts,
+                        e.to_string(),
+                    );
+                    tokio::time::sleep(Duration::from_secs(DOWNLOAD_RETRY_DELAY_SECONDS)).await;
+
+                    resume_size = if supports_range && self.config.enable_resume {
+                        self.check_resume_feasibility(download_path, total_size, expected_hash)
+                            .await?
+                    } else {
+                        None
+                    };
+                    if let Some(resume_size) = resume_size {
+                        metadata.update_progress(resume_size);
+                    }
+                }
+                result => break result,
             }
         };
 
@@ -648,6 +715,19 @@ impl FileDownloader {
                 info!("🎉 下载完成，清理元数据");
                 let _ = self.cleanup_metadata(download_path).await;
 
+                // 记录下载指标（续传时只统计本次下载的字节数）
+                let downloaded_bytes = tokio::fs::metadata(download_path)
+                    .await
+                    .map(|m| m.len().saturating_sub(existing_size.unwrap_or(0)))
+                    .unwrap_or(0);
+                let elapsed = download_started.elapsed().as_secs_f64();
+                tracing::trace!(
+                    target: METRICS_TARGET,
+                    monotonic_counter.download_bytes_total = downloaded_bytes,
+                    histogram.download_throughput_bytes_per_second =
+                        downloaded_bytes as f64 / elapsed.max(0.001),
+                );
+
                 // 最终hash验证（如果提供）
                 if let Some(hash) = expected_hash {
                     info!("🔍 最终hash验证...");
@@ -664,13 +744,20 @@ impl FileDownloader {
                         }
                         Err(e) => {
                             warn!("⚠️ 计算最终hash失败: {}", e);
+                            events
+                                .warning(OperationKind::Download, format!("计算最终hash失败: {e}"));
                         }
                     }
                 }
+                events.phase_completed(OperationKind::Download, phase);
                 Ok(())
             }
             Err(e) => {
                 // 下载失败，保留元数据用于下次续传
+                tracing::trace!(
+                    target: METRICS_TARGET,
+                    monotonic_counter.download_failures_total = 1_u64,
+                );
                 warn!("❌ 下载失败: {}", e);
                 info!("💾 保留元数据用于下次续传");
                 Err(e)
@@ -683,7 +770,7 @@ impl FileDownloader {
         &self,
         url: &str,
         download_path: &Path,
-        progress_callback: Option<F>,
+        progress_callback: Option<&F>,
         existing_size: Option<u64>,
         total_size: u64,
         metadata: &mut DownloadMetadata,
@@ -709,7 +796,7 @@ impl FileDownloader {
         &self,
         url: &str,
         download_path: &Path,
-        progress_callback: Option<F>,
+        progress_callback: Option<&F>,
         existing_size: Option<u64>,
         total_size: u64,
         metadata: &mut DownloadMetadata,
@@ -744,7 +831,7 @@ impl FileDownloader {
         &self,
         url: &str,
         download_path: &Path,
-        progress_callback: Option<F>,
+        progress_callback: Option<&F>,
         existing_size: Option<u64>,
         total_size: u64,
         task_id: &str,
@@ -782,6 +869,10 @@ impl FileDownloader {
             // 检查是否是服务器不支持Range的错误
             if response.status().as_u16() == 200 || response.status().as_u16() == 416 {
                 warn!("🔄 服务器可能不支持Range请求，自动回退到完整下载");
+                self.config.events.warning(
+                    OperationKind::Download,
+                    "服务器不支持断点续传，重新完整下载",
+                );
 
                 // 删除已有文件，重新开始下载
                 if download_path.exists() {
@@ -884,7 +975,7 @@ impl FileDownloader {
         response: reqwest::Response,
         file: &mut File,
         download_path: &Path,
-        progress_callback: Option<F>,
+        progress_callback: Option<&F>,
         task_id: &str,
         start_byte: u64,
         total_size: u64,
@@ -900,6 +991,11 @@ impl FileDownloader {
         let mut last_progress_bytes = downloaded;
         let progress_interval =
             std::time::Duration::from_secs(self.config.progress_interval_seconds);
+        let mut reporter = self
+            .config
+            .events
+            .progress_reporter(OperationKind::Download, total_size);
+        reporter.update(downloaded);
 
         // 首次进度回调
         if let Some(callback) = progress_callback.as_ref() {
@@ -931,11 +1027,16 @@ impl FileDownloader {
         while let Some(chunk) = stream.next().await {
             let chunk = chunk.map_err(|e| DuckError::custom(format!("下载数据失败: {e}")))?;
 
+            if let Some(limiter) = &self.config.bandwidth_limiter {
+                limiter.acquire(chunk.len()).await;
+            }
+
             file.write_all(&chunk)
                 .await
                 .map_err(|e| DuckError::custom(format!("写入文件失败: {e}")))?;
 
             downloaded += chunk.len() as u64;
+            reporter.update(downloaded);
 
             // 调用进度回调
             if let Some(callback) = progress_callback.as_ref() {
@@ -972,33 +1073,36 @@ impl FileDownloader {
                     (total_size > 0 && downloaded >= total_size); // 下载完成时显示
 
                 if should_show_progress {
-                    if total_size > 0 {
-                        let percentage = (downloaded as f64 / total_size as f64 * 100.0) as u32;
-                        let status_icon =
-                            if is_resume && downloaded <= start_byte + 50 * 1024 * 1024 {
-                                "🔄" // 断点续传图标
+                    // 订阅了操作事件时进度由事件渲染方展示，不再重复输出日志
+                    if !self.config.events.is_enabled() {
+                        if total_size > 0 {
+                            let percentage = (downloaded as f64 / total_size as f64 * 100.0) as u32;
+                            let status_icon =
+                                if is_resume && downloaded <= start_byte + 50 * 1024 * 1024 {
+                                    "🔄" // 断点续传图标
+                                } else {
+                                    "📥" // 普通下载图标
+                                };
+
+                            // 计算下载速度（仅用于显示）
+                            let speed_mbps = if time_since_last.as_secs() > 0 {
+                                (bytes_since_last as f64 / 1024.0 / 1024.0)
+                                    / time_since_last.as_secs() as f64
                             } else {
-                                "📥" // 普通下载图标
+                                0.0
                             };
 
-                        // 计算下载速度（仅用于显示）
-                        let speed_mbps = if time_since_last.as_secs() > 0 {
-                            (bytes_since_last as f64 / 1024.0 / 1024.0)
-                                / time_since_last.as_secs() as f64
+                            info!(
+                                "{} 下载进度: {}% ({:.1}/{:.1} MB) 速度: {:.1} MB/s",
+                                status_icon,
+                                percentage,
+                                downloaded as f64 / 1024.0 / 1024.0,
+                                total_size as f64 / 1024.0 / 1024.0,
+                                speed_mbps
+                            );
                         } else {
-                            0.0
-                        };
-
-                        info!(
-                            "{} 下载进度: {}% ({:.1}/{:.1} MB) 速度: {:.1} MB/s",
-                            status_icon,
-                            percentage,
-                            downloaded as f64 / 1024.0 / 1024.0,
-                            total_size as f64 / 1024.0 / 1024.0,
-                            speed_mbps
-                        );
-                    } else {
-                        info!("📥 已下载: {:.1} MB", downloaded as f64 / 1024.0 / 1024.0);
+                            info!("📥 已下载: {:.1} MB", downloaded as f64 / 1024.0 / 1024.0);
+                        }
                     }
 
                     last_progress_time = now;
@@ -1050,34 +1154,9 @@ impl FileDownloader {
         Ok(())
     }
 
-    /// 计算文件的SHA256哈希值
+    /// 计算文件的SHA256哈希值（大文件并行读取与计算，结果按文件指纹缓存）
     pub async fn calculate_file_hash(file_path: &Path) -> Result<String> {
-        if !file_path.exists() {
-            return Err(anyhow::anyhow!("文件不存在: {}", file_path.display()));
-        }
-
-        let mut file = File::open(file_path)
-            .await
-            .map_err(|e| anyhow::anyhow!("无法打开文件 {}: {}", file_path.display(), e))?;
-
-        let mut hasher = Sha256::new();
-        let mut buffer = vec![0u8; 8192]; // 8KB buffer
-
-        loop {
-            let bytes_read = file
-                .read(&mut buffer)
-                .await
-                .map_err(|e| anyhow::anyhow!("读取文件失败 {}: {}", file_path.display(), e))?;
-
-            if bytes_read == 0 {
-                break;
-            }
-
-            hasher.update(&buffer[..bytes_read]);
-        }
-
-        let hash = hasher.finalize();
-        Ok(format!("{hash:x}"))
+        crate::file_hash::sha256_file(file_path).await
     }
 
     /// 验证文件完整性
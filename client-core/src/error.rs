@@ -70,6 +70,14 @@ pub enum DuckError {
 
     #[error("应用服务升级解析失败: {0}")]
     ServiceUpgradeParse(String),
+
+    #[error("不安全的归档路径: {0}")]
+    UnsafePath(String),
+
+    #[error("服务器繁忙，请在 {} 之后重试", retry_at.format("%H:%M"))]
+    RateLimited {
+        retry_at: chrono::DateTime<chrono::Local>,
+    },
 }
 
 // 为DuckDB错误实现From trait
@@ -0,0 +1,376 @@
+//! # 操作事件
+//!
+//! 下载、补丁、备份、解压等耗时操作通过 [`OperationEvent`] 统一上报阶段切换、进度、
+//! 警告和重试，前端（命令行、桌面端）从通道接收事件后自行渲染。
+//!
+//! 事件通过无界通道发送，同步代码（如 `spawn_blocking` 中的压缩和解压）也可以直接上报；
+//! 未订阅事件时使用 [`EventSender::default`]，上报为空操作。
+//!
+//! ```no_run
+//! use client_core::events::{EventSender, OperationKind};
+//!
+//! # async fn demo() {
+//! let (events, mut receiver) = EventSender::channel();
+//! tokio::spawn(async move {
+//!     while let Some(event) = receiver.recv().await {
+//!         println!("{}", serde_json::to_string(&event).unwrap());
+//!     }
+//! });
+//! events.phase_started(OperationKind::Backup, "压缩归档");
+//! # }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+/// 产生事件的操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Download,
+    Patch,
+    Backup,
+    Extract,
+    /// 升级部署流水线，阶段名为 [`PipelinePhase`] 的标识
+    Upgrade,
+    /// 数据目录迁移到其他磁盘
+    Relocate,
+}
+
+impl OperationKind {
+    /// 获取操作的显示名称
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            OperationKind::Download => "下载",
+            OperationKind::Patch => "补丁",
+            OperationKind::Backup => "备份",
+            OperationKind::Extract => "解压",
+            OperationKind::Upgrade => "升级",
+            OperationKind::Relocate => "数据迁移",
+        }
+    }
+}
+
+/// 升级部署流水线的阶段，按权重汇总为总体进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelinePhase {
+    Download,
+    Backup,
+    Extract,
+    Deploy,
+    Verify,
+}
+
+impl PipelinePhase {
+    /// 所有阶段，按执行顺序排列
+    pub const ALL: [PipelinePhase; 5] = [
+        PipelinePhase::Download,
+        PipelinePhase::Backup,
+        PipelinePhase::Extract,
+        PipelinePhase::Deploy,
+        PipelinePhase::Verify,
+    ];
+
+    /// 阶段在总体进度中所占的百分比，合计为 100
+    pub fn weight(&self) -> u32 {
+        match self {
+            PipelinePhase::Download => 40,
+            PipelinePhase::Backup => 20,
+            PipelinePhase::Extract => 20,
+            PipelinePhase::Deploy => 10,
+            PipelinePhase::Verify => 10,
+        }
+    }
+
+    /// 事件中使用的标识
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PipelinePhase::Download => "download",
+            PipelinePhase::Backup => "backup",
+            PipelinePhase::Extract => "extract",
+            PipelinePhase::Deploy => "deploy",
+            PipelinePhase::Verify => "verify",
+        }
+    }
+
+    /// 中文显示名称
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            PipelinePhase::Download => "下载",
+            PipelinePhase::Backup => "备份",
+            PipelinePhase::Extract => "解压",
+            PipelinePhase::Deploy => "部署",
+            PipelinePhase::Verify => "验证",
+        }
+    }
+
+    /// 进度计入该阶段的操作，补丁在解压阶段应用
+    pub fn for_operation(operation: OperationKind) -> Option<Self> {
+        match operation {
+            OperationKind::Download => Some(PipelinePhase::Download),
+            OperationKind::Backup => Some(PipelinePhase::Backup),
+            OperationKind::Extract | OperationKind::Patch => Some(PipelinePhase::Extract),
+            OperationKind::Upgrade | OperationKind::Relocate => None,
+        }
+    }
+}
+
+impl std::str::FromStr for PipelinePhase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PipelinePhase::ALL
+            .into_iter()
+            .find(|phase| phase.as_str() == s)
+            .ok_or_else(|| format!("未知的升级阶段: {s}"))
+    }
+}
+
+/// 操作事件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OperationEvent {
+    /// 进入新阶段
+    PhaseStarted {
+        operation: OperationKind,
+        phase: String,
+    },
+    /// 阶段完成
+    PhaseCompleted {
+        operation: OperationKind,
+        phase: String,
+    },
+    /// 进度更新，`total` 为 0 表示总量未知
+    Progress {
+        operation: OperationKind,
+        current: u64,
+        total: u64,
+    },
+    /// 不影响操作继续执行的警告
+    Warning {
+        operation: OperationKind,
+        message: String,
+    },
+    /// 操作失败后重试，`attempt` 为即将开始的第几次尝试
+    Retry {
+        operation: OperationKind,
+        attempt: u32,
+        max_attempts: u32,
+        reason: String,
+    },
+    /// 升级流水线的总体进度，`percent` 按阶段权重汇总
+    PipelineProgress {
+        phase: PipelinePhase,
+        phase_percent: f64,
+        percent: f64,
+    },
+}
+
+impl OperationEvent {
+    /// 事件所属的操作
+    pub fn operation(&self) -> OperationKind {
+        match self {
+            OperationEvent::PhaseStarted { operation, .. }
+            | OperationEvent::PhaseCompleted { operation, .. }
+            | OperationEvent::Progress { operation, .. }
+            | OperationEvent::Warning { operation, .. }
+            | OperationEvent::Retry { operation, .. } => *operation,
+            OperationEvent::PipelineProgress { .. } => OperationKind::Upgrade,
+        }
+    }
+
+    /// 进度百分比，非进度事件或总量未知时为 None
+    pub fn percentage(&self) -> Option<f64> {
+        match self {
+            OperationEvent::Progress { current, total, .. } if *total > 0 => {
+                Some((*current as f64 / *total as f64 * 100.0).min(100.0))
+            }
+            OperationEvent::PipelineProgress { percent, .. } => Some(*percent),
+            _ => None,
+        }
+    }
+}
+
+/// 事件接收端
+pub type EventReceiver = mpsc::UnboundedReceiver<OperationEvent>;
+
+/// 事件发送端，克隆后共享同一个通道
+#[derive(Debug, Clone, Default)]
+pub struct EventSender {
+    sender: Option<mpsc::UnboundedSender<OperationEvent>>,
+}
+
+impl EventSender {
+    /// 创建事件通道
+    pub fn channel() -> (Self, EventReceiver) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (
+            Self {
+                sender: Some(sender),
+            },
+            receiver,
+        )
+    }
+
+    /// 是否有订阅者
+    pub fn is_enabled(&self) -> bool {
+        self.sender
+            .as_ref()
+            .is_some_and(|sender| !sender.is_closed())
+    }
+
+    /// 发送事件，接收端已关闭时忽略
+    pub fn emit(&self, event: OperationEvent) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(event);
+        }
+    }
+
+    /// 上报进入新阶段
+    pub fn phase_started(&self, operation: OperationKind, phase: impl Into<String>) {
+        self.emit(OperationEvent::PhaseStarted {
+            operation,
+            phase: phase.into(),
+        });
+    }
+
+    /// 上报阶段完成
+    pub fn phase_completed(&self, operation: OperationKind, phase: impl Into<String>) {
+        self.emit(OperationEvent::PhaseCompleted {
+            operation,
+            phase: phase.into(),
+        });
+    }
+
+    /// 上报进度
+    pub fn progress(&self, operation: OperationKind, current: u64, total: u64) {
+        self.emit(OperationEvent::Progress {
+            operation,
+            current,
+            total,
+        });
+    }
+
+    /// 上报警告
+    pub fn warning(&self, operation: OperationKind, message: impl Into<String>) {
+        self.emit(OperationEvent::Warning {
+            operation,
+            message: message.into(),
+        });
+    }
+
+    /// 上报重试
+    pub fn retry(
+        &self,
+        operation: OperationKind,
+        attempt: u32,
+        max_attempts: u32,
+        reason: impl Into<String>,
+    ) {
+        self.emit(OperationEvent::Retry {
+            operation,
+            attempt,
+            max_attempts,
+            reason: reason.into(),
+        });
+    }
+
+    /// 上报进入升级流水线的阶段
+    pub fn pipeline_phase_started(&self, phase: PipelinePhase) {
+        self.phase_started(OperationKind::Upgrade, phase.as_str());
+    }
+
+    /// 上报升级流水线的阶段完成（跳过的阶段同样视为完成）
+    pub fn pipeline_phase_completed(&self, phase: PipelinePhase) {
+        self.phase_completed(OperationKind::Upgrade, phase.as_str());
+    }
+
+    /// 创建按百分比节流的进度上报器
+    pub fn progress_reporter(&self, operation: OperationKind, total: u64) -> ProgressReporter {
+        ProgressReporter {
+            events: self.clone(),
+            operation,
+            total,
+            last_percent: None,
+        }
+    }
+}
+
+/// 进度上报器：百分比变化（或总量未知时每 1MB）才发送事件，避免逐块上报淹没通道
+#[derive(Debug)]
+pub struct ProgressReporter {
+    events: EventSender,
+    operation: OperationKind,
+    total: u64,
+    last_percent: Option<u64>,
+}
+
+impl ProgressReporter {
+    /// 上报当前进度
+    pub fn update(&mut self, current: u64) {
+        if self.events.sender.is_none() {
+            return;
+        }
+        let bucket = if self.total > 0 {
+            current.min(self.total) * 100 / self.total
+        } else {
+            current / (1024 * 1024)
+        };
+        if self.last_percent != Some(bucket) {
+            self.last_percent = Some(bucket);
+            self.events.progress(self.operation, current, self.total);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json_format() {
+        let event = OperationEvent::Retry {
+            operation: OperationKind::Download,
+            attempt: 2,
+            max_attempts: 4,
+            reason: "连接超时".to_string(),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "retry");
+        assert_eq!(json["operation"], "download");
+        assert_eq!(json["attempt"], 2);
+
+        let parsed: OperationEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, event);
+    }
+
+    #[test]
+    fn test_disabled_sender_is_noop() {
+        let events = EventSender::default();
+        assert!(!events.is_enabled());
+        events.phase_started(OperationKind::Backup, "压缩归档");
+        events
+            .progress_reporter(OperationKind::Backup, 10)
+            .update(5);
+    }
+
+    #[test]
+    fn test_progress_reporter_throttles_by_percent() {
+        let (events, mut receiver) = EventSender::channel();
+        let mut reporter = events.progress_reporter(OperationKind::Extract, 1000);
+        for current in 0..=1000 {
+            reporter.update(current);
+        }
+        drop(reporter);
+        drop(events);
+
+        let mut percentages = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            percentages.push(event.percentage().unwrap());
+        }
+        assert_eq!(percentages.len(), 101);
+        assert_eq!(percentages.first(), Some(&0.0));
+        assert_eq!(percentages.last(), Some(&100.0));
+    }
+}
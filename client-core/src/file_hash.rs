@@ -0,0 +1,281 @@
+//! # 文件哈希模块
+//!
+//! 为服务包等大文件提供 SHA-256 计算：
+//! - 使用大块缓冲区（4MB）减少系统调用次数
+//! - 大文件采用多缓冲流水线：读取线程与哈希线程并行工作，磁盘 IO 与计算相互重叠
+//! - 结果按 (路径, 大小, 修改时间) 缓存到数据库，文件未变化时直接复用
+//!
+//! 计算结果始终是整个文件的标准 SHA-256，与服务端下发的哈希可直接比较。
+
+use crate::database::Database;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, mpsc};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, warn};
+
+/// 单次读取的缓冲区大小
+pub const HASH_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+/// 超过该大小的文件使用读取/哈希流水线
+const PIPELINE_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// 流水线中同时在途的缓冲区数量
+const PIPELINE_BUFFERS: usize = 4;
+
+/// 哈希缓存在 app_config 表中的键前缀
+const CACHE_KEY_PREFIX: &str = "file_hash_cache:";
+
+/// 全局哈希缓存使用的数据库（由应用启动时注册）
+static HASH_CACHE_DB: OnceLock<Database> = OnceLock::new();
+
+/// 注册用于缓存文件哈希的数据库，重复注册会被忽略
+pub fn register_hash_cache(database: Database) {
+    if HASH_CACHE_DB.set(database).is_err() {
+        debug!("文件哈希缓存数据库已注册，忽略重复注册");
+    }
+}
+
+/// 文件指纹：路径、大小和修改时间都未变化时认为内容未变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFingerprint {
+    pub path: PathBuf,
+    pub size: u64,
+    pub mtime_nanos: u128,
+}
+
+impl FileFingerprint {
+    /// 读取文件元数据生成指纹
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .map_err(|e| anyhow::anyhow!("无法读取文件信息 {}: {}", path.display(), e))?;
+        let mtime_nanos = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        Ok(Self {
+            path,
+            size: metadata.len(),
+            mtime_nanos,
+        })
+    }
+
+    fn cache_key(&self) -> String {
+        format!("{CACHE_KEY_PREFIX}{}", self.path.display())
+    }
+
+    /// 缓存值格式：`大小|修改时间|哈希`
+    fn cache_value(&self, hash: &str) -> String {
+        format!("{}|{}|{}", self.size, self.mtime_nanos, hash)
+    }
+
+    /// 缓存值与当前指纹一致时返回其中的哈希
+    fn match_cached_value(&self, value: &str) -> Option<String> {
+        let mut parts = value.splitn(3, '|');
+        let size = parts.next()?.parse::<u64>().ok()?;
+        let mtime_nanos = parts.next()?.parse::<u128>().ok()?;
+        let hash = parts.next()?;
+        (size == self.size && mtime_nanos == self.mtime_nanos && !hash.is_empty())
+            .then(|| hash.to_string())
+    }
+}
+
+/// 计算文件的 SHA-256，优先使用已注册数据库中的缓存
+pub async fn sha256_file(path: &Path) -> Result<String> {
+    sha256_file_cached(path, HASH_CACHE_DB.get()).await
+}
+
+/// 计算文件的 SHA-256，并使用指定数据库缓存结果
+pub async fn sha256_file_cached(path: &Path, database: Option<&Database>) -> Result<String> {
+    if !path.exists() {
+        return Err(anyhow::anyhow!("文件不存在: {}", path.display()));
+    }
+
+    let fingerprint = FileFingerprint::from_path(path)?;
+    if let Some(database) = database {
+        match database.get_config(&fingerprint.cache_key()).await {
+            Ok(Some(value)) => {
+                if let Some(hash) = fingerprint.match_cached_value(&value) {
+                    debug!("命中文件哈希缓存: {}", path.display());
+                    return Ok(hash);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => debug!("读取文件哈希缓存失败: {}", e),
+        }
+    }
+
+    let hash = sha256_file_uncached(path).await?;
+
+    if let Some(database) = database {
+        // 计算期间文件被修改时不写缓存
+        if FileFingerprint::from_path(path).ok().as_ref() == Some(&fingerprint) {
+            if let Err(e) = database
+                .set_config(&fingerprint.cache_key(), &fingerprint.cache_value(&hash))
+                .await
+            {
+                warn!("⚠️ 保存文件哈希缓存失败: {}", e);
+            }
+        }
+    }
+
+    Ok(hash)
+}
+
+/// 不使用缓存直接计算文件的 SHA-256
+pub async fn sha256_file_uncached(path: &Path) -> Result<String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || hash_file_blocking(&path))
+        .await
+        .map_err(|e| anyhow::anyhow!("哈希计算任务异常退出: {}", e))?
+}
+
+fn hash_file_blocking(path: &Path) -> Result<String> {
+    let file =
+        File::open(path).map_err(|e| anyhow::anyhow!("无法打开文件 {}: {}", path.display(), e))?;
+    let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+
+    let digest = if size > PIPELINE_THRESHOLD {
+        hash_pipelined(file, path)?
+    } else {
+        hash_sequential(file, path)?
+    };
+    Ok(format!("{digest:x}"))
+}
+
+type Sha256Output = sha2::digest::Output<Sha256>;
+
+fn hash_sequential(mut file: File, path: &Path) -> Result<Sha256Output> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+    loop {
+        let bytes_read = file
+            .read(&mut buffer)
+            .map_err(|e| anyhow::anyhow!("读取文件失败 {}: {}", path.display(), e))?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// 读取线程填充缓冲区，当前线程负责哈希，用完的缓冲区回收复用
+fn hash_pipelined(mut file: File, path: &Path) -> Result<Sha256Output> {
+    let (filled_tx, filled_rx) = mpsc::sync_channel::<(Vec<u8>, usize)>(PIPELINE_BUFFERS);
+    let (empty_tx, empty_rx) = mpsc::channel::<Vec<u8>>();
+    for _ in 0..PIPELINE_BUFFERS {
+        let _ = empty_tx.send(vec![0u8; HASH_BUFFER_SIZE]);
+    }
+
+    let reader = std::thread::spawn(move || -> std::io::Result<()> {
+        // 哈希端提前退出时 recv/send 失败，读取线程随之结束
+        while let Ok(mut buffer) = empty_rx.recv() {
+            let bytes_read = read_full(&mut file, &mut buffer)?;
+            if bytes_read == 0 || filled_tx.send((buffer, bytes_read)).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    });
+
+    let mut hasher = Sha256::new();
+    for (buffer, len) in filled_rx {
+        hasher.update(&buffer[..len]);
+        let _ = empty_tx.send(buffer);
+    }
+    drop(empty_tx);
+
+    reader
+        .join()
+        .map_err(|_| anyhow::anyhow!("读取线程异常退出: {}", path.display()))?
+        .map_err(|e| anyhow::anyhow!("读取文件失败 {}: {}", path.display(), e))?;
+    Ok(hasher.finalize())
+}
+
+/// 尽量填满缓冲区，返回 0 表示文件结束
+fn read_full(file: &mut File, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // echo -n "hello world" | sha256sum
+    const HELLO_WORLD_SHA256: &str =
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    #[tokio::test]
+    async fn test_sha256_small_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("hello.txt");
+        std::fs::write(&path, "hello world").unwrap();
+
+        let hash = sha256_file_uncached(&path).await.unwrap();
+        assert_eq!(hash, HELLO_WORLD_SHA256);
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_hash_matches_sequential() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("large.bin");
+        let data: Vec<u8> = (0..(PIPELINE_THRESHOLD as usize + 12345))
+            .map(|i| (i % 251) as u8)
+            .collect();
+        std::fs::write(&path, &data).unwrap();
+
+        let expected = format!("{:x}", Sha256::digest(&data));
+        assert_eq!(sha256_file_uncached(&path).await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_hash_cache_invalidated_on_change() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cached.txt");
+        std::fs::write(&path, "hello world").unwrap();
+
+        let database = Database::connect_memory().await.unwrap();
+        database.init_database().await.unwrap();
+
+        let hash = sha256_file_cached(&path, Some(&database)).await.unwrap();
+        assert_eq!(hash, HELLO_WORLD_SHA256);
+        let fingerprint = FileFingerprint::from_path(&path).unwrap();
+        let cached = database
+            .get_config(&fingerprint.cache_key())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            fingerprint.match_cached_value(&cached).as_deref(),
+            Some(HELLO_WORLD_SHA256)
+        );
+
+        std::fs::write(&path, "hello world, changed").unwrap();
+        let changed = FileFingerprint::from_path(&path).unwrap();
+        assert!(changed.match_cached_value(&cached).is_none());
+    }
+
+    #[test]
+    fn test_missing_file_is_error() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime.block_on(sha256_file(Path::new("/non/existent/file.bin")));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,635 @@
+//! # 批量主机操作
+//!
+//! 基于 [`crate::remote`] 的远程执行能力，按清单文件（inventory）对多台主机批量执行
+//! 检查更新、升级、备份等操作：
+//! - 清单为 YAML 格式，`defaults` 中的连接参数可被每台主机覆盖
+//! - 限制同时操作的主机数量
+//! - 每个操作失败后按重试策略重试，某个操作最终失败时跳过该主机的后续操作
+//! - 汇总每台主机的结果，可输出 JSON 或 HTML 报告
+
+use crate::remote::{RemoteHost, SshTarget, build_remote_command};
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+/// 默认同时操作的主机数
+pub const DEFAULT_FLEET_PARALLELISM: usize = 4;
+
+/// 报告中每个操作保留的输出行数（只保留末尾部分）
+const MAX_OUTPUT_LINES: usize = 50;
+
+/// 批量操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FleetOperation {
+    /// 检查服务是否有可用升级
+    CheckUpdate,
+    /// 下载最新的服务包
+    Upgrade,
+    /// 执行自动升级部署
+    Deploy,
+    /// 创建手动备份
+    Backup,
+}
+
+impl FleetOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::CheckUpdate => "check-update",
+            Self::Upgrade => "upgrade",
+            Self::Deploy => "deploy",
+            Self::Backup => "backup",
+        }
+    }
+
+    /// 远程 nuwax-cli 的参数
+    pub fn cli_args(&self) -> &'static [&'static str] {
+        match self {
+            Self::CheckUpdate => &["upgrade", "--check"],
+            Self::Upgrade => &["upgrade"],
+            Self::Deploy => &["auto-upgrade-deploy", "run"],
+            Self::Backup => &["backup"],
+        }
+    }
+}
+
+impl fmt::Display for FleetOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FleetOperation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "check-update" => Ok(Self::CheckUpdate),
+            "upgrade" => Ok(Self::Upgrade),
+            "deploy" => Ok(Self::Deploy),
+            "backup" => Ok(Self::Backup),
+            _ => Err(anyhow!(
+                "未知的批量操作: {s}（可选: check-update、upgrade、deploy、backup）"
+            )),
+        }
+    }
+}
+
+/// 重试策略
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// 每个操作最多执行的次数（含首次）
+    pub attempts: u32,
+    /// 两次尝试之间的等待时间（秒）
+    pub delay_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 1,
+            delay_secs: 10,
+        }
+    }
+}
+
+/// 主机连接参数，`defaults` 与每台主机共用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostSettings {
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    pub identity_file: Option<PathBuf>,
+    /// 远程主机上包含 config.toml 的工作目录
+    pub remote_dir: Option<String>,
+    /// 远程主机上的 nuwax-cli 可执行文件
+    pub remote_cli: Option<String>,
+}
+
+impl HostSettings {
+    /// 未设置的字段使用 `defaults` 中的值
+    fn merged_with(&self, defaults: &HostSettings) -> HostSettings {
+        HostSettings {
+            user: self.user.clone().or_else(|| defaults.user.clone()),
+            port: self.port.or(defaults.port),
+            identity_file: self
+                .identity_file
+                .clone()
+                .or_else(|| defaults.identity_file.clone()),
+            remote_dir: self
+                .remote_dir
+                .clone()
+                .or_else(|| defaults.remote_dir.clone()),
+            remote_cli: self
+                .remote_cli
+                .clone()
+                .or_else(|| defaults.remote_cli.clone()),
+        }
+    }
+}
+
+/// 清单中的主机
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryHost {
+    /// 主机显示名称（默认使用 `host`）
+    pub name: Option<String>,
+    /// 主机地址：`[user@]host[:port]`
+    pub host: String,
+    #[serde(flatten)]
+    pub settings: HostSettings,
+}
+
+/// 批量操作清单
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Inventory {
+    /// 同时操作的主机数
+    pub parallelism: Option<usize>,
+    pub retry: RetryPolicy,
+    pub defaults: HostSettings,
+    /// 默认执行的操作（可被命令行覆盖）
+    pub operations: Vec<FleetOperation>,
+    pub hosts: Vec<InventoryHost>,
+}
+
+impl Inventory {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("读取清单文件失败: {}", path.display()))?;
+        Self::from_yaml(&content)
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        let inventory: Inventory = serde_yaml::from_str(content).context("解析清单文件失败")?;
+        if inventory.hosts.is_empty() {
+            return Err(anyhow!("清单中没有主机"));
+        }
+        Ok(inventory)
+    }
+
+    /// 解析所有主机的连接参数，主机名称需唯一
+    pub fn resolve_hosts(&self) -> Result<Vec<FleetHost>> {
+        let mut names = HashSet::new();
+        self.hosts
+            .iter()
+            .map(|entry| {
+                let mut target: SshTarget = entry.host.parse()?;
+                let settings = entry.settings.merged_with(&self.defaults);
+                if target.user.is_none() {
+                    target.user = settings.user.clone();
+                }
+                if target.port.is_none() {
+                    target.port = settings.port;
+                }
+                let name = entry.name.clone().unwrap_or_else(|| entry.host.clone());
+                if !names.insert(name.clone()) {
+                    return Err(anyhow!("清单中存在重复的主机名称: {name}"));
+                }
+                Ok(FleetHost {
+                    name,
+                    remote: RemoteHost::new(target).with_identity_file(settings.identity_file),
+                    remote_dir: settings.remote_dir,
+                    remote_cli: settings
+                        .remote_cli
+                        .unwrap_or_else(|| "nuwax-cli".to_string()),
+                })
+            })
+            .collect()
+    }
+}
+
+/// 解析后的目标主机
+#[derive(Debug, Clone)]
+pub struct FleetHost {
+    pub name: String,
+    pub remote: RemoteHost,
+    pub remote_dir: Option<String>,
+    pub remote_cli: String,
+}
+
+impl FleetHost {
+    fn remote_command(&self, operation: FleetOperation) -> String {
+        build_remote_command(
+            self.remote_dir.as_deref(),
+            &self.remote_cli,
+            operation.cli_args(),
+        )
+    }
+}
+
+/// 批量执行配置
+#[derive(Debug, Clone)]
+pub struct FleetConfig {
+    pub operations: Vec<FleetOperation>,
+    pub parallelism: usize,
+    pub retry: RetryPolicy,
+}
+
+/// 单个操作的执行结果
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationResult {
+    pub operation: FleetOperation,
+    pub success: bool,
+    /// 实际执行次数
+    pub attempts: u32,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_secs: f64,
+    /// 最后一次尝试的输出（只保留末尾部分）
+    pub output: Vec<String>,
+}
+
+/// 单台主机的执行结果
+#[derive(Debug, Clone, Serialize)]
+pub struct HostReport {
+    pub name: String,
+    pub target: String,
+    pub success: bool,
+    pub operations: Vec<OperationResult>,
+    /// 因前序操作失败而未执行的操作
+    pub skipped: Vec<FleetOperation>,
+}
+
+/// 批量执行汇总报告
+#[derive(Debug, Clone, Serialize)]
+pub struct FleetReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub operations: Vec<FleetOperation>,
+    pub total_hosts: usize,
+    pub succeeded_hosts: usize,
+    pub failed_hosts: usize,
+    pub hosts: Vec<HostReport>,
+}
+
+impl FleetReport {
+    pub fn is_success(&self) -> bool {
+        self.failed_hosts == 0
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>nuwax-cli 批量操作报告</title>\n<style>\n");
+        html.push_str(
+            "body{font-family:sans-serif;margin:2em}table{border-collapse:collapse;width:100%}\
+             th,td{border:1px solid #ccc;padding:6px 10px;text-align:left;vertical-align:top}\
+             .ok{color:#1a7f37}.fail{color:#cf222e}.skip{color:#888}\
+             pre{margin:0;max-height:20em;overflow:auto;font-size:12px}\n",
+        );
+        html.push_str("</style>\n</head>\n<body>\n<h1>nuwax-cli 批量操作报告</h1>\n");
+        html.push_str(&format!(
+            "<p>开始: {} &nbsp; 结束: {} &nbsp; 操作: {}</p>\n",
+            self.started_at.to_rfc3339(),
+            self.finished_at.to_rfc3339(),
+            escape_html(
+                &self
+                    .operations
+                    .iter()
+                    .map(FleetOperation::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        ));
+        html.push_str(&format!(
+            "<p>主机总数: {} &nbsp; <span class=\"ok\">成功: {}</span> &nbsp; \
+             <span class=\"fail\">失败: {}</span></p>\n",
+            self.total_hosts, self.succeeded_hosts, self.failed_hosts
+        ));
+        html.push_str(
+            "<table>\n<tr><th>主机</th><th>地址</th><th>操作</th><th>结果</th>\
+             <th>尝试次数</th><th>耗时</th><th>输出</th></tr>\n",
+        );
+        for host in &self.hosts {
+            let rows = host.operations.len() + host.skipped.len();
+            let mut first = true;
+            let mut host_cells = || {
+                if std::mem::take(&mut first) {
+                    format!(
+                        "<td rowspan=\"{rows}\">{}</td><td rowspan=\"{rows}\">{}</td>",
+                        escape_html(&host.name),
+                        escape_html(&host.target)
+                    )
+                } else {
+                    String::new()
+                }
+            };
+            for result in &host.operations {
+                let (class, status) = if result.success {
+                    ("ok", "成功".to_string())
+                } else {
+                    (
+                        "fail",
+                        format!("失败: {}", result.error.as_deref().unwrap_or_default()),
+                    )
+                };
+                html.push_str(&format!(
+                    "<tr>{}<td>{}</td><td class=\"{class}\">{}</td><td>{}</td><td>{:.1}s</td>\
+                     <td><pre>{}</pre></td></tr>\n",
+                    host_cells(),
+                    result.operation,
+                    escape_html(&status),
+                    result.attempts,
+                    result.duration_secs,
+                    escape_html(&result.output.join("\n"))
+                ));
+            }
+            for operation in &host.skipped {
+                html.push_str(&format!(
+                    "<tr>{}<td>{operation}</td><td class=\"skip\">已跳过</td>\
+                     <td>0</td><td>-</td><td></td></tr>\n",
+                    host_cells()
+                ));
+            }
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// 对所有主机执行批量操作
+///
+/// `on_line` 接收 (主机名称, 输出行)，可用于实时展示各主机的输出
+pub async fn run_fleet<F>(hosts: Vec<FleetHost>, config: FleetConfig, on_line: F) -> FleetReport
+where
+    F: Fn(&str, &str) + Send + Sync + 'static,
+{
+    let started_at = Utc::now();
+    let parallelism = config.parallelism.max(1);
+    info!(
+        "🚀 开始对 {} 台主机执行批量操作: {} (并发数: {})",
+        hosts.len(),
+        config
+            .operations
+            .iter()
+            .map(FleetOperation::as_str)
+            .collect::<Vec<_>>()
+            .join(", "),
+        parallelism
+    );
+
+    let semaphore = Arc::new(Semaphore::new(parallelism));
+    let on_line = Arc::new(on_line);
+    let config = Arc::new(config);
+    let mut tasks = JoinSet::new();
+
+    for (index, host) in hosts.iter().cloned().enumerate() {
+        let semaphore = semaphore.clone();
+        let on_line = on_line.clone();
+        let config = config.clone();
+        tasks.spawn(async move {
+            // 信号量不会被关闭，获取失败时也照常执行
+            let _permit = semaphore.acquire_owned().await.ok();
+            (index, run_host(&host, &config, on_line.as_ref()).await)
+        });
+    }
+
+    let mut reports: Vec<Option<HostReport>> = (0..hosts.len()).map(|_| None).collect();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, report)) => reports[index] = Some(report),
+            Err(e) => warn!("批量操作任务异常退出: {}", e),
+        }
+    }
+
+    let hosts: Vec<HostReport> = reports
+        .into_iter()
+        .zip(&hosts)
+        .map(|(report, host)| {
+            report.unwrap_or_else(|| HostReport {
+                name: host.name.clone(),
+                target: host.remote.target().to_string(),
+                success: false,
+                operations: Vec::new(),
+                skipped: config.operations.clone(),
+            })
+        })
+        .collect();
+    let succeeded_hosts = hosts.iter().filter(|host| host.success).count();
+
+    FleetReport {
+        started_at,
+        finished_at: Utc::now(),
+        operations: config.operations.clone(),
+        total_hosts: hosts.len(),
+        succeeded_hosts,
+        failed_hosts: hosts.len() - succeeded_hosts,
+        hosts,
+    }
+}
+
+/// 在单台主机上依次执行所有操作
+async fn run_host<F>(host: &FleetHost, config: &FleetConfig, on_line: &F) -> HostReport
+where
+    F: Fn(&str, &str),
+{
+    let mut operations = Vec::new();
+    let mut skipped = Vec::new();
+
+    for &operation in &config.operations {
+        if operations
+            .last()
+            .is_some_and(|r: &OperationResult| !r.success)
+        {
+            skipped.push(operation);
+            continue;
+        }
+        let result = run_operation(host, operation, config.retry, on_line).await;
+        if result.success {
+            info!("✅ [{}] {} 完成", host.name, operation);
+        } else {
+            warn!(
+                "❌ [{}] {} 失败: {}",
+                host.name,
+                operation,
+                result.error.as_deref().unwrap_or_default()
+            );
+        }
+        operations.push(result);
+    }
+
+    HostReport {
+        name: host.name.clone(),
+        target: host.remote.target().to_string(),
+        success: skipped.is_empty() && operations.iter().all(|r| r.success),
+        operations,
+        skipped,
+    }
+}
+
+/// 执行单个操作，失败时按重试策略重试
+async fn run_operation<F>(
+    host: &FleetHost,
+    operation: FleetOperation,
+    retry: RetryPolicy,
+    on_line: &F,
+) -> OperationResult
+where
+    F: Fn(&str, &str),
+{
+    let command = host.remote_command(operation);
+    let max_attempts = retry.attempts.max(1);
+    let started = Instant::now();
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        let mut output = VecDeque::with_capacity(MAX_OUTPUT_LINES);
+        let result = host
+            .remote
+            .run(&command, |line| {
+                on_line(&host.name, line);
+                if output.len() == MAX_OUTPUT_LINES {
+                    output.pop_front();
+                }
+                output.push_back(line.to_string());
+            })
+            .await;
+
+        let (exit_code, error) = match result {
+            Ok(0) => (Some(0), None),
+            Ok(255) => (Some(255), Some("SSH 连接失败".to_string())),
+            Ok(code) => (Some(code), Some(format!("退出码 {code}"))),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        if error.is_none() || attempt >= max_attempts {
+            return OperationResult {
+                operation,
+                success: error.is_none(),
+                attempts: attempt,
+                exit_code,
+                error,
+                duration_secs: started.elapsed().as_secs_f64(),
+                output: output.into(),
+            };
+        }
+
+        warn!(
+            "⚠️  [{}] {} 第 {}/{} 次尝试失败: {}，{} 秒后重试",
+            host.name,
+            operation,
+            attempt,
+            max_attempts,
+            error.unwrap_or_default(),
+            retry.delay_secs
+        );
+        tokio::time::sleep(Duration::from_secs(retry.delay_secs)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVENTORY: &str = r#"
+parallelism: 8
+retry:
+  attempts: 3
+defaults:
+  user: ops
+  remote_dir: /opt/nuwax
+operations: [check-update, backup]
+hosts:
+  - name: shop-a
+    host: 10.0.0.5
+  - host: root@10.0.0.6:2222
+    remote_dir: /srv/nuwax
+    remote_cli: /usr/local/bin/nuwax-cli
+"#;
+
+    #[test]
+    fn test_parse_inventory() {
+        let inventory = Inventory::from_yaml(INVENTORY).unwrap();
+        assert_eq!(inventory.parallelism, Some(8));
+        assert_eq!(inventory.retry.attempts, 3);
+        assert_eq!(inventory.retry.delay_secs, 10);
+        assert_eq!(
+            inventory.operations,
+            vec![FleetOperation::CheckUpdate, FleetOperation::Backup]
+        );
+
+        let hosts = inventory.resolve_hosts().unwrap();
+        assert_eq!(hosts[0].name, "shop-a");
+        assert_eq!(hosts[0].remote.target().to_string(), "ops@10.0.0.5");
+        assert_eq!(
+            hosts[0].remote_command(FleetOperation::CheckUpdate),
+            "cd /opt/nuwax && nuwax-cli upgrade --check"
+        );
+
+        assert_eq!(hosts[1].name, "root@10.0.0.6:2222");
+        assert_eq!(hosts[1].remote.target().to_string(), "root@10.0.0.6:2222");
+        assert_eq!(
+            hosts[1].remote_command(FleetOperation::Backup),
+            "cd /srv/nuwax && /usr/local/bin/nuwax-cli backup"
+        );
+    }
+
+    #[test]
+    fn test_inventory_validation() {
+        assert!(Inventory::from_yaml("hosts: []").is_err());
+        assert!(Inventory::from_yaml("hosts:\n  - host: a\noperations: [reboot]").is_err());
+
+        let duplicated = Inventory::from_yaml("hosts:\n  - host: a\n  - host: a").unwrap();
+        assert!(duplicated.resolve_hosts().is_err());
+    }
+
+    #[test]
+    fn test_report_rendering() {
+        let now = Utc::now();
+        let report = FleetReport {
+            started_at: now,
+            finished_at: now,
+            operations: vec![FleetOperation::Backup, FleetOperation::Upgrade],
+            total_hosts: 1,
+            succeeded_hosts: 0,
+            failed_hosts: 1,
+            hosts: vec![HostReport {
+                name: "<shop>".to_string(),
+                target: "ops@shop".to_string(),
+                success: false,
+                operations: vec![OperationResult {
+                    operation: FleetOperation::Backup,
+                    success: false,
+                    attempts: 2,
+                    exit_code: Some(1),
+                    error: Some("退出码 1".to_string()),
+                    duration_secs: 1.5,
+                    output: vec!["disk full".to_string()],
+                }],
+                skipped: vec![FleetOperation::Upgrade],
+            }],
+        };
+        assert!(!report.is_success());
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["hosts"][0]["operations"][0]["operation"], "backup");
+        assert_eq!(json["hosts"][0]["skipped"][0], "upgrade");
+
+        let html = report.to_html();
+        assert!(html.contains("&lt;shop&gt;"));
+        assert!(html.contains("rowspan=\"2\""));
+        assert!(html.contains("已跳过"));
+    }
+}
@@ -0,0 +1,175 @@
+//! 写入前的文件系统预检
+//!
+//! 部分设备上 docker/ 位于只读或几乎写满的 overlay 上。下载、解压和应用补丁之前先检查目标目录：
+//! 挂载标志、实际创建/删除探测文件、可用空间，避免下载数 GB 的升级包后才发现无法写入
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// 探测文件名前缀
+const PROBE_FILE_PREFIX: &str = ".nuwax-write-probe-";
+
+/// 目标目录不可写的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WritabilityIssue {
+    /// 所在文件系统以只读方式挂载
+    ReadOnlyMount,
+    /// 无法创建或删除探测文件
+    ProbeFailed(String),
+    /// 可用空间不足
+    InsufficientSpace { required: u64, available: u64 },
+}
+
+impl fmt::Display for WritabilityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WritabilityIssue::ReadOnlyMount => write!(f, "所在文件系统以只读方式挂载"),
+            WritabilityIssue::ProbeFailed(reason) => write!(f, "无法写入探测文件: {reason}"),
+            WritabilityIssue::InsufficientSpace {
+                required,
+                available,
+            } => write!(
+                f,
+                "可用空间不足，至少需要 {:.1} MB，当前可用 {:.1} MB",
+                *required as f64 / 1024.0 / 1024.0,
+                *available as f64 / 1024.0 / 1024.0
+            ),
+        }
+    }
+}
+
+/// 目标目录不可写
+#[derive(Debug, Clone, Error)]
+#[error("目标目录 {} 不可写: {issue}", path.display())]
+pub struct NotWritableError {
+    pub path: PathBuf,
+    pub issue: WritabilityIssue,
+}
+
+/// 检查 `dir` 是否可写，并在给出 `required_bytes` 时检查可用空间
+///
+/// 目录尚不存在时检查最近的已存在上级目录（之后会在其中创建该目录）
+pub fn check_writable(dir: &Path, required_bytes: Option<u64>) -> Result<(), NotWritableError> {
+    let probe_dir = nearest_existing_dir(dir);
+    let error = |issue| NotWritableError {
+        path: dir.to_path_buf(),
+        issue,
+    };
+
+    if is_read_only_mount(&probe_dir) {
+        return Err(error(WritabilityIssue::ReadOnlyMount));
+    }
+    if let Err(e) = probe_write(&probe_dir) {
+        return Err(error(if e.kind() == io::ErrorKind::ReadOnlyFilesystem {
+            WritabilityIssue::ReadOnlyMount
+        } else {
+            WritabilityIssue::ProbeFailed(e.to_string())
+        }));
+    }
+    match (required_bytes, available_space(&probe_dir)) {
+        (Some(required), Some(available)) if available < required => {
+            Err(error(WritabilityIssue::InsufficientSpace {
+                required,
+                available,
+            }))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 获取路径所在文件系统的可用空间，无法获取时返回 None
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+pub fn available_space(path: &Path) -> Option<u64> {
+    statvfs(path).map(|stat| stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn is_read_only_mount(path: &Path) -> bool {
+    statvfs(path).is_some_and(|stat| stat.f_flag & libc::ST_RDONLY != 0)
+}
+
+#[cfg(not(unix))]
+fn is_read_only_mount(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn statvfs(path: &Path) -> Option<libc::statvfs> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat)
+}
+
+/// 创建并删除一个探测文件，挂载标志之外的只读（如 overlay 上层不可写、权限不足、磁盘已满）也能发现
+fn probe_write(dir: &Path) -> io::Result<()> {
+    let probe_path = dir.join(format!("{PROBE_FILE_PREFIX}{}", std::process::id()));
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe_path)
+        .and_then(|mut file| file.write_all(b"0"));
+    let removed = fs::remove_file(&probe_path);
+    result?;
+    removed
+}
+
+fn nearest_existing_dir(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|ancestor| ancestor.is_dir())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_writable() {
+        let temp_dir = TempDir::new().unwrap();
+        // 尚不存在的目录检查其上级目录
+        let target = temp_dir.path().join("docker").join("app");
+        check_writable(&target, Some(1)).unwrap();
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 0);
+
+        if available_space(temp_dir.path()).is_some() {
+            let err = check_writable(&target, Some(u64::MAX)).unwrap_err();
+            assert!(matches!(
+                err.issue,
+                WritabilityIssue::InsufficientSpace { .. }
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_writable_permission_denied() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // root 不受目录权限限制
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let temp_dir = TempDir::new().unwrap();
+        fs::set_permissions(temp_dir.path(), fs::Permissions::from_mode(0o555)).unwrap();
+        let err = check_writable(temp_dir.path(), None).unwrap_err();
+        assert!(matches!(err.issue, WritabilityIssue::ProbeFailed(_)));
+        fs::set_permissions(temp_dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+//! HTTP 条件请求缓存
+//!
+//! 服务清单和公告会被定时任务频繁轮询。响应正文连同 `ETag` / `Last-Modified` 保存在本地数据库，
+//! 下次请求带上 `If-None-Match` / `If-Modified-Since`，服务器返回 304 时直接使用缓存；
+//! 服务器暂时无法访问时也可以退回到缓存的副本
+
+use crate::database::Database;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+
+/// HTTP 缓存在 app_config 表中的键前缀
+const CACHE_KEY_PREFIX: &str = "http_cache:";
+
+/// 全局 HTTP 缓存使用的数据库（由应用启动时注册）
+static HTTP_CACHE_DB: OnceLock<Database> = OnceLock::new();
+
+/// 注册用于缓存 HTTP 响应的数据库，重复注册会被忽略
+pub fn register_http_cache(database: Database) {
+    if HTTP_CACHE_DB.set(database).is_err() {
+        debug!("HTTP 缓存数据库已注册，忽略重复注册");
+    }
+}
+
+/// 缓存的响应
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub body: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl CachedResponse {
+    /// 是否带有可用于条件请求的校验信息
+    pub fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+fn cache_key(url: &str) -> String {
+    format!("{CACHE_KEY_PREFIX}{url}")
+}
+
+/// 读取 `url` 的缓存响应，未注册数据库或没有缓存时返回 None
+pub async fn load_cached_response(url: &str) -> Option<CachedResponse> {
+    load_cached_response_from(url, HTTP_CACHE_DB.get()?).await
+}
+
+/// 保存 `url` 的响应，未注册数据库时忽略
+pub async fn store_cached_response(url: &str, response: &CachedResponse) {
+    if let Some(database) = HTTP_CACHE_DB.get() {
+        store_cached_response_to(url, response, database).await;
+    }
+}
+
+async fn load_cached_response_from(url: &str, database: &Database) -> Option<CachedResponse> {
+    match database.get_config(&cache_key(url)).await {
+        Ok(Some(value)) => match serde_json::from_str(&value) {
+            Ok(response) => Some(response),
+            Err(e) => {
+                debug!("HTTP 缓存格式无效，忽略: {}", e);
+                None
+            }
+        },
+        Ok(None) => None,
+        Err(e) => {
+            debug!("读取 HTTP 缓存失败: {}", e);
+            None
+        }
+    }
+}
+
+async fn store_cached_response_to(url: &str, response: &CachedResponse, database: &Database) {
+    let value = match serde_json::to_string(response) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("⚠️ 序列化 HTTP 缓存失败: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = database.set_config(&cache_key(url), &value).await {
+        warn!("⚠️ 保存 HTTP 缓存失败: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cached_response_roundtrip() {
+        let database = Database::connect_memory().await.unwrap();
+        database.init_database().await.unwrap();
+
+        let url = "https://example.com/api/v1/manifest";
+        assert!(load_cached_response_from(url, &database).await.is_none());
+
+        let response = CachedResponse {
+            etag: Some("\"abc123\"".to_string()),
+            last_modified: None,
+            body: r#"{"version":"1.0.0"}"#.to_string(),
+            fetched_at: Utc::now(),
+        };
+        store_cached_response_to(url, &response, &database).await;
+
+        let cached = load_cached_response_from(url, &database).await.unwrap();
+        assert_eq!(cached, response);
+        assert!(cached.has_validators());
+        assert!(
+            load_cached_response_from("https://example.com/other", &database)
+                .await
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! # HTTP 调试记录
+//!
+//! 使用 `--debug-http[=FILE]` 时，把每个 API 请求的方法、地址、状态码、耗时和请求/响应正文写入独立的
+//! 调试文件，不需要在客户机器上架设代理就能排查服务清单解析等问题：
+//!
+//! - 地址和正文经 [`LogRedactor`] 脱敏，签名参数、令牌、密码和客户端ID不会写入文件
+//! - 只记录文本类正文（JSON、文本、XML、YAML），超过 [`MAX_BODY_BYTES`] 的部分截断
+//! - 服务包等二进制下载只记录状态和大小，不读取正文
+//!
+//! 未开启时 [`send`] 直接发送请求，不做其他事。
+
+use crate::redact::LogRedactor;
+use anyhow::Result;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, HeaderMap};
+use reqwest::{RequestBuilder, Response, ResponseBuilderExt};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+/// 每个正文最多记录的字节数
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// 超过该大小的响应不读取正文（字节）
+const MAX_BUFFERED_BYTES: u64 = 16 * 1024 * 1024;
+
+/// 开启中的调试记录（由 `--debug-http` 启动）
+static HTTP_TRACE: OnceLock<HttpTrace> = OnceLock::new();
+
+struct HttpTrace {
+    file: Mutex<File>,
+    redactor: LogRedactor,
+}
+
+/// 开启 HTTP 调试记录，记录追加写入 `path`
+pub fn enable_http_trace(path: &Path) -> Result<()> {
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| anyhow::anyhow!("无法打开 HTTP 调试文件 {}: {}", path.display(), e))?;
+    let _ = HTTP_TRACE.set(HttpTrace {
+        file: Mutex::new(file),
+        redactor: LogRedactor::default(),
+    });
+    Ok(())
+}
+
+/// 是否开启了 HTTP 调试记录
+pub fn is_enabled() -> bool {
+    HTTP_TRACE.get().is_some()
+}
+
+/// 发送请求，开启调试记录时记录请求和响应
+///
+/// 文本类响应的正文会先读入内存再重新组装为响应，调用方按原方式读取
+pub async fn send(request: RequestBuilder) -> Result<Response> {
+    let Some(trace) = HTTP_TRACE.get() else {
+        return Ok(request.send().await?);
+    };
+
+    // 复制一份请求用于记录方法、地址和请求体
+    let snapshot = request.try_clone().and_then(|request| request.build().ok());
+    let mut entry = match &snapshot {
+        Some(snapshot) => format!(
+            "{} {}\n",
+            snapshot.method(),
+            trace.redactor.redact(snapshot.url().as_str())
+        ),
+        None => "<请求体为流，无法记录请求>\n".to_string(),
+    };
+    if let Some(body) = snapshot
+        .as_ref()
+        .and_then(|snapshot| snapshot.body())
+        .and_then(|body| body.as_bytes())
+    {
+        entry.push_str(&format!("> {}\n", trace.body_text(body)));
+    }
+
+    let started = Instant::now();
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            entry.push_str(&format!(
+                "< 请求失败 ({} ms): {}\n",
+                started.elapsed().as_millis(),
+                trace.redactor.redact(&e.to_string())
+            ));
+            trace.write(&entry);
+            return Err(e.into());
+        }
+    };
+
+    entry.push_str(&format!(
+        "< {} ({} ms){}\n",
+        response.status(),
+        started.elapsed().as_millis(),
+        describe_content(response.headers())
+    ));
+    if !is_text_body(response.headers()) {
+        trace.write(&entry);
+        return Ok(response);
+    }
+
+    let status = response.status();
+    let version = response.version();
+    let url = response.url().clone();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+    entry.push_str(&format!("< {}\n", trace.body_text(&body)));
+    trace.write(&entry);
+
+    let mut builder = http::Response::builder()
+        .status(status)
+        .version(version)
+        .url(url);
+    if let Some(builder_headers) = builder.headers_mut() {
+        *builder_headers = headers;
+    }
+    Ok(Response::from(builder.body(body)?))
+}
+
+impl HttpTrace {
+    /// 脱敏并截断正文
+    fn body_text(&self, body: &[u8]) -> String {
+        let truncated = body.len() > MAX_BODY_BYTES;
+        let text = String::from_utf8_lossy(&body[..body.len().min(MAX_BODY_BYTES)]);
+        let mut text = self.redactor.redact(&text).into_owned();
+        if truncated {
+            text.push_str(&format!(" ...（已截断，共 {} 字节）", body.len()));
+        }
+        text
+    }
+
+    fn write(&self, entry: &str) {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = writeln!(
+            file,
+            "[{}] {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            entry
+        );
+    }
+}
+
+/// 响应的内容类型和大小
+fn describe_content(headers: &HeaderMap) -> String {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    match (content_type, content_length(headers)) {
+        (Some(content_type), Some(length)) => format!(" {content_type}, {length} 字节"),
+        (Some(content_type), None) => format!(" {content_type}"),
+        (None, Some(length)) => format!(" {length} 字节"),
+        (None, None) => String::new(),
+    }
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+/// 是否为需要记录正文的文本类响应
+fn is_text_body(headers: &HeaderMap) -> bool {
+    let is_text = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|content_type| {
+            let content_type = content_type.to_ascii_lowercase();
+            content_type.starts_with("text/")
+                || ["json", "xml", "yaml"]
+                    .iter()
+                    .any(|kind| content_type.contains(kind))
+        })
+        .unwrap_or(false);
+    is_text && content_length(headers).is_none_or(|length| length <= MAX_BUFFERED_BYTES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_is_text_body() {
+        let mut headers = HeaderMap::new();
+        assert!(!is_text_body(&headers));
+
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/json; charset=utf-8"),
+        );
+        assert!(is_text_body(&headers));
+
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("104857600"));
+        assert!(!is_text_body(&headers));
+
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/zip"));
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("1024"));
+        assert!(!is_text_body(&headers));
+        assert_eq!(describe_content(&headers), " application/zip, 1024 字节");
+    }
+
+    #[test]
+    fn test_body_text_is_redacted_and_truncated() {
+        let trace = HttpTrace {
+            file: Mutex::new(tempfile::tempfile().unwrap()),
+            redactor: LogRedactor::default(),
+        };
+        let text = trace.body_text(br#"{"version": "0.0.13", "token": "abc123"}"#);
+        assert!(text.contains("0.0.13"), "{text}");
+        assert!(!text.contains("abc123"), "{text}");
+
+        let large = vec![b'a'; MAX_BODY_BYTES + 10];
+        let text = trace.body_text(&large);
+        assert!(text.ends_with(&format!("共 {} 字节）", MAX_BODY_BYTES + 10)));
+    }
+}
@@ -0,0 +1,283 @@
+//! # Docker 镜像归档
+//!
+//! 把当前版本 compose 文件引用的全部镜像打包为一个 tar.zst 文件，用于迁移到无法访问镜像仓库的主机。
+//! 归档内第一个条目是 `manifest.json`（服务版本、镜像引用、镜像 ID 和平台），
+//! 第二个条目是 `docker save` 导出的 `images.tar`，导入时只需读取开头即可得到清单。
+
+use crate::architecture::Architecture;
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Read};
+use std::path::{Path, PathBuf};
+
+/// 归档格式版本
+pub const IMAGE_ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// 归档内的清单文件名
+const MANIFEST_ENTRY: &str = "manifest.json";
+
+/// 归档内 `docker save` 输出的文件名
+pub const IMAGES_ENTRY: &str = "images.tar";
+
+/// 备份目录中镜像归档的文件名前缀和后缀
+const ARCHIVE_PREFIX: &str = "images_v";
+const ARCHIVE_SUFFIX: &str = ".tar.zst";
+
+/// zstd 压缩级别，镜像层大多已压缩，使用较低级别节省时间
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// 归档中的一个镜像
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedImage {
+    /// compose 文件中引用的镜像名（含标签）
+    pub reference: String,
+    /// 镜像 ID（`sha256:...`）
+    pub id: String,
+    /// 镜像平台，如 `linux/amd64`
+    pub platform: String,
+}
+
+impl ArchivedImage {
+    /// 镜像的 CPU 架构，无法识别时返回 None
+    pub fn architecture(&self) -> Option<Architecture> {
+        let arch = self.platform.split('/').nth(1)?;
+        Architecture::from_str(arch).ok()
+    }
+}
+
+/// 镜像归档清单
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageArchiveManifest {
+    pub format_version: u32,
+    /// 导出时的服务版本
+    pub service_version: String,
+    pub created_at: DateTime<Utc>,
+    pub images: Vec<ArchivedImage>,
+}
+
+impl ImageArchiveManifest {
+    pub fn new(service_version: impl Into<String>, images: Vec<ArchivedImage>) -> Self {
+        Self {
+            format_version: IMAGE_ARCHIVE_FORMAT_VERSION,
+            service_version: service_version.into(),
+            created_at: Utc::now(),
+            images,
+        }
+    }
+
+    /// 检查归档中的镜像是否都能在指定架构上运行
+    pub fn check_architecture(&self, arch: &Architecture) -> Result<()> {
+        let mismatched: Vec<String> = self
+            .images
+            .iter()
+            .filter(|image| image.architecture().as_ref() != Some(arch))
+            .map(|image| format!("{} ({})", image.reference, image.platform))
+            .collect();
+        if mismatched.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "以下镜像与当前架构 {} 不匹配: {}",
+                arch,
+                mismatched.join(", ")
+            ))
+        }
+    }
+}
+
+/// 备份目录中镜像归档的默认文件名：`images_v{版本}_{%Y-%m-%d_%H-%M-%S}.tar.zst`
+pub fn image_archive_file_name(service_version: &str, created_at: DateTime<Utc>) -> String {
+    format!(
+        "{ARCHIVE_PREFIX}{service_version}_{}{ARCHIVE_SUFFIX}",
+        created_at.format("%Y-%m-%d_%H-%M-%S")
+    )
+}
+
+/// 是否为镜像归档的默认文件名
+pub fn is_image_archive_file_name(file_name: &str) -> bool {
+    file_name.starts_with(ARCHIVE_PREFIX) && file_name.ends_with(ARCHIVE_SUFFIX)
+}
+
+/// 把清单和 `docker save` 的输出写入归档，返回归档大小
+///
+/// 先写入 `.partial` 临时文件，成功后再改名，失败时不会留下不完整的归档
+pub fn write_image_archive(
+    manifest: &ImageArchiveManifest,
+    images_tar: &Path,
+    output: &Path,
+) -> Result<u64> {
+    let partial = partial_path(output);
+    let result = write_archive_entries(manifest, images_tar, &partial);
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    std::fs::rename(&partial, output)
+        .with_context(|| format!("无法写入镜像归档: {}", output.display()))?;
+    Ok(std::fs::metadata(output)?.len())
+}
+
+fn write_archive_entries(
+    manifest: &ImageArchiveManifest,
+    images_tar: &Path,
+    path: &Path,
+) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("无法创建镜像归档: {}", path.display()))?;
+    let encoder = zstd::stream::write::Encoder::new(BufWriter::new(file), COMPRESSION_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    let content = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created_at.timestamp().max(0) as u64);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_ENTRY, content.as_slice())?;
+    builder.append_path_with_name(images_tar, IMAGES_ENTRY)?;
+
+    let encoder = builder.into_inner()?;
+    encoder.finish()?.into_inner()?.sync_all()?;
+    Ok(())
+}
+
+/// 读取归档清单，只解压归档开头的清单条目
+pub fn read_image_archive_manifest(path: &Path) -> Result<ImageArchiveManifest> {
+    let mut archive = open_archive(path)?;
+    let mut entries = archive.entries()?;
+    let entry = entries
+        .next()
+        .ok_or_else(|| anyhow!("镜像归档为空: {}", path.display()))??;
+    if entry.path()?.as_ref() != Path::new(MANIFEST_ENTRY) {
+        return Err(anyhow!(
+            "不是有效的镜像归档（缺少清单）: {}",
+            path.display()
+        ));
+    }
+    parse_manifest(entry)
+}
+
+/// 解压归档中的 `images.tar` 到 `dest_dir`，返回清单和解压后的文件路径
+pub fn unpack_image_archive(
+    path: &Path,
+    dest_dir: &Path,
+) -> Result<(ImageArchiveManifest, PathBuf)> {
+    let mut archive = open_archive(path)?;
+    let mut manifest = None;
+    let mut images_tar = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        match name.as_str() {
+            MANIFEST_ENTRY => manifest = Some(parse_manifest(entry)?),
+            IMAGES_ENTRY => {
+                let target = dest_dir.join(IMAGES_ENTRY);
+                let mut file = File::create(&target)?;
+                std::io::copy(&mut entry, &mut file)?;
+                images_tar = Some(target);
+            }
+            _ => {}
+        }
+    }
+    match (manifest, images_tar) {
+        (Some(manifest), Some(images_tar)) => Ok((manifest, images_tar)),
+        _ => Err(anyhow!("不是有效的镜像归档: {}", path.display())),
+    }
+}
+
+fn open_archive(
+    path: &Path,
+) -> Result<tar::Archive<zstd::stream::read::Decoder<'static, std::io::BufReader<File>>>> {
+    let file = File::open(path).with_context(|| format!("无法打开镜像归档: {}", path.display()))?;
+    Ok(tar::Archive::new(zstd::stream::read::Decoder::new(file)?))
+}
+
+fn parse_manifest(mut reader: impl Read) -> Result<ImageArchiveManifest> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    let manifest: ImageArchiveManifest = serde_json::from_str(&content)?;
+    if manifest.format_version > IMAGE_ARCHIVE_FORMAT_VERSION {
+        return Err(anyhow!(
+            "不支持的镜像归档格式版本: {}（当前支持 {}）",
+            manifest.format_version,
+            IMAGE_ARCHIVE_FORMAT_VERSION
+        ));
+    }
+    Ok(manifest)
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(reference: &str, platform: &str) -> ArchivedImage {
+        ArchivedImage {
+            reference: reference.to_string(),
+            id: "sha256:abc".to_string(),
+            platform: platform.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_write_and_read_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let images_tar = dir.path().join("save.tar");
+        std::fs::write(&images_tar, b"docker save output").unwrap();
+        let manifest = ImageArchiveManifest::new("1.2.0", vec![image("mysql:8.0", "linux/amd64")]);
+        let output = dir.path().join("images.tar.zst");
+
+        let size = write_image_archive(&manifest, &images_tar, &output).unwrap();
+        assert!(size > 0);
+        assert!(!partial_path(&output).exists());
+        assert_eq!(read_image_archive_manifest(&output).unwrap(), manifest);
+
+        let unpack_dir = dir.path().join("unpack");
+        std::fs::create_dir(&unpack_dir).unwrap();
+        let (unpacked, tar_path) = unpack_image_archive(&output, &unpack_dir).unwrap();
+        assert_eq!(unpacked, manifest);
+        assert_eq!(std::fs::read(tar_path).unwrap(), b"docker save output");
+    }
+
+    #[test]
+    fn test_check_architecture() {
+        let manifest = ImageArchiveManifest::new(
+            "1.2.0",
+            vec![
+                image("mysql:8.0", "linux/amd64"),
+                image("redis:7", "linux/arm64"),
+            ],
+        );
+        let err = manifest
+            .check_architecture(&Architecture::X86_64)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("redis:7"));
+        assert!(!err.contains("mysql:8.0"));
+        assert!(
+            ImageArchiveManifest::new("1.2.0", vec![image("redis:7", "linux/arm64")])
+                .check_architecture(&Architecture::Aarch64)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_image_archive_file_name() {
+        let created_at = DateTime::parse_from_rfc3339("2025-01-02T03:04:05Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let name = image_archive_file_name("1.2.0", created_at);
+        assert_eq!(name, "images_v1.2.0_2025-01-02_03-04-05.tar.zst");
+        assert!(is_image_archive_file_name(&name));
+        assert!(!is_image_archive_file_name(
+            "backup_manual_v1.2.0_2025-01-02_03-04-05.tar.gz"
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+//! 旧版 duck-cli 布局的自动迁移
+//!
+//! 旧版本的数据库为 `data/duck_client.db`，默认缓存目录为 `cacheDuckData`，日志环境变量以 `DUCK_` 开头。
+//! 启动时检测到旧文件后迁移到新的布局，更新配置文件中的缓存路径，并在数据目录下留下迁移说明
+
+use crate::config::AppConfig;
+use crate::constants::{config, legacy, logging};
+use anyhow::Result;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// 一次文件或目录迁移
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStep {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// 迁移结果
+#[derive(Debug, Default)]
+pub struct LegacyMigrationReport {
+    /// 已迁移的文件和目录
+    pub moved: Vec<MigrationStep>,
+    /// 缓存路径已更新的配置文件
+    pub config_updated: Option<PathBuf>,
+    /// 未自动处理、需要用户留意的情况
+    pub notes: Vec<String>,
+}
+
+impl LegacyMigrationReport {
+    /// 是否执行了迁移
+    pub fn migrated(&self) -> bool {
+        !self.moved.is_empty() || self.config_updated.is_some()
+    }
+}
+
+/// 检测并迁移 `base_dir` 下的旧版 duck-cli 文件，没有旧文件时不做任何改动
+pub fn migrate_legacy_layout(base_dir: &Path, config_path: &Path) -> Result<LegacyMigrationReport> {
+    let mut report = LegacyMigrationReport::default();
+
+    migrate_database(base_dir, &mut report)?;
+    migrate_cache_dir(base_dir, config_path, &mut report)?;
+
+    for name in logging::LOG_ENV_VARS {
+        let legacy_var = format!("{}{name}", legacy::ENV_PREFIX);
+        if std::env::var_os(&legacy_var).is_some() {
+            report.notes.push(format!(
+                "环境变量 {legacy_var} 已更名为 {}{name}，旧名称仍然有效",
+                logging::ENV_PREFIX
+            ));
+        }
+    }
+
+    if report.migrated() {
+        for step in &report.moved {
+            info!(
+                "📦 已迁移旧版文件: {} -> {}",
+                step.from.display(),
+                step.to.display()
+            );
+        }
+        if let Some(path) = &report.config_updated {
+            info!("📝 已更新配置文件中的缓存路径: {}", path.display());
+        }
+        let note_path = write_migration_note(base_dir, &report)?;
+        info!(
+            "✅ 旧版 duck-cli 布局迁移完成，说明见 {}",
+            note_path.display()
+        );
+    }
+    for note in &report.notes {
+        warn!("⚠️ {}", note);
+    }
+
+    Ok(report)
+}
+
+/// `data/duck_client.db`（及 WAL 文件）重命名为新的数据库文件名
+fn migrate_database(base_dir: &Path, report: &mut LegacyMigrationReport) -> Result<()> {
+    let data_dir = base_dir.join(config::DATA_DIR_NAME);
+    let legacy_db = data_dir.join(legacy::DATABASE_FILE_NAME);
+    if !legacy_db.exists() {
+        return Ok(());
+    }
+
+    let new_db = data_dir.join(config::DATABASE_FILE_NAME);
+    if new_db.exists() {
+        report.notes.push(format!(
+            "同时存在 {} 和 {}，已使用新数据库，确认无用后可删除旧文件",
+            legacy_db.display(),
+            new_db.display()
+        ));
+        return Ok(());
+    }
+
+    for (from, to) in [
+        (legacy_db.clone(), new_db.clone()),
+        (wal_path(&legacy_db), wal_path(&new_db)),
+    ] {
+        if from.exists() {
+            fs::rename(&from, &to)?;
+            report.moved.push(MigrationStep { from, to });
+        }
+    }
+    Ok(())
+}
+
+/// 配置仍使用旧默认缓存目录时，把目录重命名为新的默认目录并更新配置
+fn migrate_cache_dir(
+    base_dir: &Path,
+    config_path: &Path,
+    report: &mut LegacyMigrationReport,
+) -> Result<()> {
+    let config_file = base_dir.join(config_path);
+    if !config_file.exists() {
+        return Ok(());
+    }
+    let mut app_config = AppConfig::load_from_file(&config_file)?;
+    if legacy_cache_relative(&app_config.cache.cache_dir) != Some(PathBuf::new()) {
+        return Ok(());
+    }
+
+    let legacy_dir = base_dir.join(legacy::CACHE_DIR_NAME);
+    let new_dir = base_dir.join(config::CACHE_DIR_NAME);
+    if legacy_dir.exists() {
+        if new_dir.exists() {
+            report.notes.push(format!(
+                "同时存在缓存目录 {} 和 {}，继续使用旧目录，可手动合并后修改配置 [cache]",
+                legacy_dir.display(),
+                new_dir.display()
+            ));
+            return Ok(());
+        }
+        fs::rename(&legacy_dir, &new_dir)?;
+        report.moved.push(MigrationStep {
+            from: legacy_dir,
+            to: new_dir,
+        });
+    }
+
+    let cache_dir = config::get_default_cache_dir();
+    if let Some(relative) = legacy_cache_relative(&app_config.cache.download_dir) {
+        app_config.cache.download_dir = cache_dir.join(relative).to_string_lossy().to_string();
+    }
+    app_config.cache.cache_dir = cache_dir.to_string_lossy().to_string();
+    app_config.save_to_file(&config_file)?;
+    report.config_updated = Some(config_file);
+    Ok(())
+}
+
+/// 路径位于旧默认缓存目录下时返回其相对该目录的部分
+fn legacy_cache_relative(path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    let path = path.strip_prefix(".").unwrap_or(path);
+    path.strip_prefix(legacy::CACHE_DIR_NAME)
+        .ok()
+        .map(Path::to_path_buf)
+}
+
+fn wal_path(database_path: &Path) -> PathBuf {
+    let mut name = database_path.as_os_str().to_os_string();
+    name.push(".wal");
+    PathBuf::from(name)
+}
+
+/// 在数据目录下追加迁移说明，便于日后排查旧路径去向
+fn write_migration_note(base_dir: &Path, report: &LegacyMigrationReport) -> Result<PathBuf> {
+    let data_dir = base_dir.join(config::DATA_DIR_NAME);
+    fs::create_dir_all(&data_dir)?;
+    let note_path = data_dir.join(legacy::MIGRATION_NOTE_FILE_NAME);
+
+    let mut note = format!(
+        "# 从 duck-cli 迁移 ({})\n\n",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    );
+    for step in &report.moved {
+        note.push_str(&format!(
+            "- 已迁移: {} -> {}\n",
+            step.from.display(),
+            step.to.display()
+        ));
+    }
+    if let Some(path) = &report.config_updated {
+        note.push_str(&format!(
+            "- 已更新配置文件中的缓存路径: {}\n",
+            path.display()
+        ));
+    }
+    note.push_str("\n兼容说明:\n");
+    note.push_str(&format!(
+        "- 日志环境变量 {}LOG_* 仍然有效，建议改用 {}LOG_*\n",
+        legacy::ENV_PREFIX,
+        logging::ENV_PREFIX
+    ));
+    note.push_str(&format!(
+        "- 旧版本创建的包含系统状态的备份仍可恢复，其中的 {} 会按新文件名 {} 恢复\n",
+        legacy::DATABASE_FILE_NAME,
+        config::DATABASE_FILE_NAME
+    ));
+    if base_dir.join(legacy::LOG_FILE_NAME).exists() {
+        note.push_str(&format!(
+            "- 旧日志文件 {} 保留在原位置，不再写入\n",
+            legacy::LOG_FILE_NAME
+        ));
+    }
+    note.push('\n');
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&note_path)?;
+    file.write_all(note.as_bytes())?;
+    Ok(note_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_migrate_legacy_layout() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        let data_dir = base.join(config::DATA_DIR_NAME);
+        fs::create_dir_all(&data_dir).unwrap();
+        fs::write(data_dir.join(legacy::DATABASE_FILE_NAME), "db").unwrap();
+        fs::write(data_dir.join("duck_client.db.wal"), "wal").unwrap();
+        fs::create_dir_all(base.join(legacy::CACHE_DIR_NAME).join("download")).unwrap();
+
+        let mut app_config = AppConfig::default();
+        app_config.cache.cache_dir = "./cacheDuckData".to_string();
+        app_config.cache.download_dir = "./cacheDuckData/download".to_string();
+        app_config.save_to_file(base.join("config.toml")).unwrap();
+
+        let report = migrate_legacy_layout(base, Path::new("config.toml")).unwrap();
+        assert_eq!(report.moved.len(), 3);
+        assert!(data_dir.join(config::DATABASE_FILE_NAME).exists());
+        assert!(data_dir.join("nuwax_client.db.wal").exists());
+        assert!(!data_dir.join(legacy::DATABASE_FILE_NAME).exists());
+        assert!(base.join(config::CACHE_DIR_NAME).join("download").exists());
+        assert!(data_dir.join(legacy::MIGRATION_NOTE_FILE_NAME).exists());
+
+        let migrated = AppConfig::load_from_file(base.join("config.toml")).unwrap();
+        assert_eq!(
+            Path::new(&migrated.cache.download_dir),
+            config::get_default_cache_dir().join("download")
+        );
+
+        // 再次运行不做任何改动
+        let report = migrate_legacy_layout(base, Path::new("config.toml")).unwrap();
+        assert!(!report.migrated());
+    }
+}
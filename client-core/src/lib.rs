@@ -5,22 +5,70 @@ pub mod api_types;
 // 重新导出 api_types 中的主要类型以保持向后兼容
 pub use api_types::*;
 pub mod architecture;
+pub mod archive_format;
 pub mod authenticated_client;
 pub mod backup;
+pub mod backup_catalog;
+pub mod backup_dedup;
+pub mod backup_hooks;
+pub mod backup_manifest;
+pub mod backup_permissions;
+pub mod backup_snapshot;
+pub mod backup_storage;
+pub mod changelog;
+pub mod clock;
 pub mod config;
 pub mod config_manager;
+pub mod connectivity;
 pub mod constants;
 pub mod container;
+pub mod data_migration;
+pub mod data_relocation;
 pub mod database;
 pub mod database_manager;
 pub mod db;
+pub mod deploy_checkpoint;
+pub mod download_cache;
+pub mod download_orchestrator;
 pub mod downloader;
 pub mod error;
+pub mod events;
+pub mod file_hash;
+pub mod fleet;
+pub mod fs_probe;
+pub mod http_cache;
+pub mod http_trace;
+pub mod image_archive;
+pub mod legacy_migration;
+pub mod mysql_dump;
 pub mod mysql_executor;
+pub mod mysql_readiness;
+pub mod package_architecture;
+pub mod package_store;
+pub mod package_verification;
 pub mod patch_executor;
+pub mod patch_manifest;
+pub mod paths;
+pub mod pipeline_progress;
+pub mod postgres_executor;
+pub mod rate_limit;
+pub mod redact;
+pub mod remote;
+pub mod restore_preview;
+pub mod safe_path;
+pub mod self_test;
+pub mod service_preset;
 pub mod sql_diff;
+pub mod sql_dry_run;
+pub mod symlink;
+pub mod temp_artifacts;
+pub mod tree_copy;
 pub mod upgrade;
+pub mod upgrade_plan;
+pub mod upgrade_preview;
+pub mod upgrade_session;
 pub mod upgrade_strategy;
+pub mod user_modifications;
 pub mod version;
 
 pub use database_manager::DatabaseManager;
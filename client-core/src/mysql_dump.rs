@@ -0,0 +1,281 @@
+//! # MySQL 逻辑备份
+//!
+//! 在数据库服务容器内执行 `mysqldump` / `mysql`，通过管道读写宿主机上的备份文件，
+//! 宿主机无需安装 MySQL 客户端，也不需要暴露数据库端口。
+//! 导出文件名以 `.gz` 结尾时使用 gzip 压缩；导入时按文件头自动识别是否压缩。
+
+use crate::container::DockerManager;
+use crate::mysql_executor::MySqlConfig;
+use anyhow::{Context, Result, anyhow};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// 管道读写的块大小
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// gzip 文件头
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// 在数据库容器内导出和导入单个数据库
+pub struct MySqlDump {
+    docker_manager: DockerManager,
+    service: String,
+    config: MySqlConfig,
+}
+
+impl MySqlDump {
+    pub fn new(
+        docker_manager: DockerManager,
+        service: impl Into<String>,
+        config: MySqlConfig,
+    ) -> Self {
+        Self {
+            docker_manager,
+            service: service.into(),
+            config,
+        }
+    }
+
+    /// 连接配置
+    pub fn config(&self) -> &MySqlConfig {
+        &self.config
+    }
+
+    /// 导出数据库到文件，`on_progress` 参数为已导出的SQL字节数（压缩前），返回总字节数
+    ///
+    /// 先写入 `.partial` 临时文件，导出成功后再改名，失败时不会留下不完整的备份
+    pub async fn dump_to_file<F>(&self, path: &Path, mut on_progress: F) -> Result<u64>
+    where
+        F: FnMut(u64),
+    {
+        let mut child = self
+            .spawn(
+                "mysqldump",
+                &[
+                    "--single-transaction",
+                    "--quick",
+                    "--routines",
+                    "--triggers",
+                    "--events",
+                    "--hex-blob",
+                    "--no-tablespaces",
+                    "--default-character-set=utf8mb4",
+                ],
+                false,
+            )
+            .await?;
+        let stderr = collect_stderr(&mut child);
+        let mut stdout = child.stdout.take().context("无法读取 mysqldump 输出")?;
+        let partial_path = partial_path(path);
+        let mut writer = DumpWriter::create(&partial_path, is_gzip_path(path))?;
+
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut total = 0u64;
+        let copy_result: Result<()> = async {
+            loop {
+                let n = stdout.read(&mut buf).await?;
+                if n == 0 {
+                    return Ok(());
+                }
+                writer.write_all(&buf[..n])?;
+                total += n as u64;
+                on_progress(total);
+            }
+        }
+        .await;
+        // 写入失败时关闭管道，避免 mysqldump 阻塞在输出上
+        drop(stdout);
+
+        let status = child.wait().await?;
+        let stderr = stderr.await.unwrap_or_default();
+        let result = copy_result.and_then(|()| {
+            if !status.success() {
+                return Err(anyhow!("mysqldump 执行失败: {}", stderr.trim()));
+            }
+            writer.finish()
+        });
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&partial_path);
+            return Err(e);
+        }
+
+        std::fs::rename(&partial_path, path)
+            .with_context(|| format!("无法保存备份文件: {}", path.display()))?;
+        Ok(total)
+    }
+
+    /// 从文件导入数据库，`on_progress` 参数为已导入的SQL字节数（解压后）
+    ///
+    /// 备份中的表会先 `DROP TABLE IF EXISTS` 再重建，备份中没有的表保持不变
+    pub async fn restore_from_file<F>(&self, path: &Path, mut on_progress: F) -> Result<u64>
+    where
+        F: FnMut(u64),
+    {
+        let mut reader = open_dump_reader(path)?;
+        let mut child = self
+            .spawn("mysql", &["--default-character-set=utf8mb4"], true)
+            .await?;
+        let stderr = collect_stderr(&mut child);
+        let mut stdin = child.stdin.take().context("无法写入 mysql 输入")?;
+
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut total = 0u64;
+        let copy_result: Result<()> = async {
+            loop {
+                let n = reader.read(&mut buf)?;
+                if n == 0 {
+                    return Ok(());
+                }
+                stdin.write_all(&buf[..n]).await?;
+                total += n as u64;
+                on_progress(total);
+            }
+        }
+        .await;
+        // 关闭 stdin，mysql 读到 EOF 后退出
+        drop(stdin);
+
+        let status = child.wait().await?;
+        let stderr = stderr.await.unwrap_or_default();
+        if !status.success() {
+            return Err(anyhow!("mysql 导入失败: {}", stderr.trim()));
+        }
+        copy_result?;
+        Ok(total)
+    }
+
+    /// 在数据库服务容器内执行 MySQL 客户端工具，密码通过 `MYSQL_PWD` 传入
+    async fn spawn(&self, program: &str, args: &[&str], stdin: bool) -> Result<Child> {
+        let container = self
+            .docker_manager
+            .get_service_container_id(&self.service)
+            .await?
+            .ok_or_else(|| anyhow!("服务 {} 没有运行中的容器", self.service))?;
+        debug!("在容器 {} 中执行 {}: {:?}", container, program, args);
+
+        Command::new("docker")
+            .args(["exec", "-i", "-e"])
+            .arg(format!("MYSQL_PWD={}", self.config.password.expose()))
+            .args([container.as_str(), program, "-u", self.config.user.as_str()])
+            .args(args)
+            .arg(&self.config.database)
+            .stdin(if stdin { Stdio::piped() } else { Stdio::null() })
+            .stdout(if stdin { Stdio::null() } else { Stdio::piped() })
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("执行 docker exec {program} 失败"))
+    }
+}
+
+/// 文件名是否以 `.gz` 结尾
+pub fn is_gzip_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+}
+
+/// 打开备份文件，按文件头识别 gzip 压缩
+fn open_dump_reader(path: &Path) -> Result<Box<dyn Read>> {
+    let mut file =
+        File::open(path).with_context(|| format!("无法打开备份文件: {}", path.display()))?;
+    let mut header = [0u8; 2];
+    let read = file.read(&mut header)?;
+    file.rewind()?;
+    let file = BufReader::new(file);
+    if header[..read] == GZIP_MAGIC {
+        Ok(Box::new(GzDecoder::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    path.with_file_name(name)
+}
+
+/// 在后台读取子进程的 stderr，避免输出过多时阻塞子进程
+fn collect_stderr(child: &mut Child) -> JoinHandle<String> {
+    let stderr = child.stderr.take();
+    tokio::spawn(async move {
+        let mut bytes = Vec::new();
+        if let Some(mut stderr) = stderr {
+            let _ = stderr.read_to_end(&mut bytes).await;
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+/// 备份文件写入器，gzip 需要在结束时写入文件尾
+enum DumpWriter {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl DumpWriter {
+    fn create(path: &Path, gzip: bool) -> Result<Self> {
+        let file = BufWriter::new(
+            File::create(path).with_context(|| format!("无法创建备份文件: {}", path.display()))?,
+        );
+        Ok(if gzip {
+            DumpWriter::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            DumpWriter::Plain(file)
+        })
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        match self {
+            DumpWriter::Plain(writer) => writer.write_all(buf),
+            DumpWriter::Gzip(writer) => writer.write_all(buf),
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        let mut file = match self {
+            DumpWriter::Plain(writer) => writer,
+            DumpWriter::Gzip(writer) => writer.finish()?,
+        };
+        file.flush()?;
+        file.get_ref().sync_all()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dump_file_compression() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let sql = "CREATE TABLE t (id INT);\nINSERT INTO t VALUES (1);\n";
+
+        for name in ["dump.sql.gz", "dump.sql"] {
+            let path = temp_dir.path().join(name);
+            let mut writer = DumpWriter::create(&path, is_gzip_path(&path)).unwrap();
+            writer.write_all(sql.as_bytes()).unwrap();
+            writer.finish().unwrap();
+
+            let mut content = String::new();
+            open_dump_reader(&path)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            assert_eq!(content, sql);
+        }
+        assert_eq!(
+            partial_path(Path::new("/backups/dump.sql.gz")),
+            Path::new("/backups/dump.sql.gz.partial")
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use crate::container::DockerManager;
+use crate::redact::SecretString;
 use anyhow::{Context, Result, anyhow};
 use docker_compose_types as dct;
 use mysql_async::prelude::*;
-use mysql_async::{Opts, Pool, Row, Transaction, TxOpts};
+use mysql_async::{Opts, Pool, Row, Transaction, TxOpts, Value};
 
 /// MySQL容器异步差异SQL执行器
 /// 专为Duck Client自动升级部署设计
@@ -17,7 +18,7 @@ pub struct MySqlConfig {
     pub host: String,
     pub port: u16,
     pub user: String,
-    pub password: String,
+    pub password: SecretString,
     pub database: String,
 }
 
@@ -89,7 +90,8 @@ impl MySqlConfig {
             password: config_map
                 .get("MYSQL_PASSWORD")
                 .cloned()
-                .unwrap_or_else(|| "root".to_string()),
+                .unwrap_or_else(|| "root".to_string())
+                .into(),
             database: config_map
                 .get("MYSQL_DATABASE")
                 .cloned()
@@ -101,7 +103,11 @@ impl MySqlConfig {
     fn to_url(&self) -> String {
         format!(
             "mysql://{}:{}@{}:{}/{}",
-            self.user, self.password, self.host, self.port, self.database
+            self.user,
+            self.password.expose(),
+            self.host,
+            self.port,
+            self.database
         )
     }
 }
@@ -114,6 +120,11 @@ impl MySqlExecutor {
         Self { pool, config }
     }
 
+    /// 连接配置
+    pub fn config(&self) -> &MySqlConfig {
+        &self.config
+    }
+
     /// 测试连接是否可用
     pub async fn test_connection(&self) -> Result<(), mysql_async::Error> {
         let mut conn = self.pool.get_conn().await?;
@@ -229,6 +240,62 @@ impl MySqlExecutor {
         commands
     }
 
+    /// 按顺序执行已拆分的SQL语句，每条语句执行后调用 `on_statement(序号, 语句, 结果)`
+    ///
+    /// - [`ScriptMode::Autocommit`]：逐条提交，失败时停止，之前的语句已生效
+    /// - [`ScriptMode::Transaction`]：在单个事务中执行，失败时回滚（DDL 会隐式提交，无法回滚）
+    /// - [`ScriptMode::DryRun`]：在事务中执行后回滚，跳过会隐式提交的语句
+    pub async fn execute_script<F>(
+        &self,
+        statements: &[String],
+        mode: ScriptMode,
+        mut on_statement: F,
+    ) -> Result<Vec<StatementOutput>>
+    where
+        F: FnMut(usize, &str, &StatementOutput),
+    {
+        let mut conn = self.pool.get_conn().await?;
+        let mut outputs = Vec::with_capacity(statements.len());
+
+        if mode == ScriptMode::Autocommit {
+            for (idx, sql) in statements.iter().enumerate() {
+                let output = run_statement(&mut conn, sql)
+                    .await
+                    .map_err(|e| statement_error(idx, e, mode))?;
+                on_statement(idx, sql, &output);
+                outputs.push(output);
+            }
+            return Ok(outputs);
+        }
+
+        let mut tx = conn.start_transaction(TxOpts::default()).await?;
+        for (idx, sql) in statements.iter().enumerate() {
+            let output = if mode == ScriptMode::DryRun && causes_implicit_commit(sql) {
+                StatementOutput {
+                    skipped: true,
+                    ..Default::default()
+                }
+            } else {
+                match run_statement(&mut tx, sql).await {
+                    Ok(output) => output,
+                    Err(e) => {
+                        tx.rollback().await?;
+                        return Err(statement_error(idx, e, mode));
+                    }
+                }
+            };
+            on_statement(idx, sql, &output);
+            outputs.push(output);
+        }
+
+        if mode == ScriptMode::DryRun {
+            tx.rollback().await?;
+        } else {
+            tx.commit().await?;
+        }
+        Ok(outputs)
+    }
+
     /// 获取数据库表结构信息
     pub async fn get_table_info(&self, table_name: &str) -> Result<(), mysql_async::Error> {
         let mut conn = self.pool.get_conn().await?;
@@ -240,6 +307,30 @@ impl MySqlExecutor {
         Ok(())
     }
 
+    /// 导出当前数据库的表结构（`SHOW CREATE TABLE`），用于在临时库中重建
+    pub async fn dump_schema(&self) -> Result<String, mysql_async::Error> {
+        let mut conn = self.pool.get_conn().await?;
+        let tables: Vec<String> = conn
+            .query_map(
+                "SHOW FULL TABLES WHERE Table_type = 'BASE TABLE'",
+                |(name, _table_type): (String, String)| name,
+            )
+            .await?;
+
+        let mut schema = String::from("SET FOREIGN_KEY_CHECKS=0;\n\n");
+        for table in tables {
+            let create: Option<(String, String)> = conn
+                .query_first(format!("SHOW CREATE TABLE `{table}`"))
+                .await?;
+            if let Some((_, create_sql)) = create {
+                schema.push_str(&create_sql);
+                schema.push_str(";\n\n");
+            }
+        }
+        schema.push_str("SET FOREIGN_KEY_CHECKS=1;\n");
+        Ok(schema)
+    }
+
     /// 验证执行结果
     pub async fn verify_execution(
         &self,
@@ -265,6 +356,194 @@ impl MySqlExecutor {
     }
 }
 
+/// SQL脚本的执行方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptMode {
+    /// 逐条自动提交
+    Autocommit,
+    /// 单个事务，失败时回滚
+    Transaction,
+    /// 执行后回滚，不修改数据
+    DryRun,
+}
+
+/// 单条语句的执行结果
+#[derive(Debug, Clone, Default)]
+pub struct StatementOutput {
+    /// 结果集列名，非查询语句为空
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub affected_rows: u64,
+    /// 预演时跳过的语句（会隐式提交，无法回滚）
+    pub skipped: bool,
+}
+
+/// 执行单条语句并读取第一个结果集
+async fn run_statement<Q: Queryable>(
+    conn: &mut Q,
+    sql: &str,
+) -> Result<StatementOutput, mysql_async::Error> {
+    let mut result = conn.query_iter(sql).await?;
+    let columns = result
+        .columns()
+        .map(|columns| {
+            columns
+                .iter()
+                .map(|column| column.name_str().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    let rows: Vec<Row> = result.collect().await?;
+    let affected_rows = result.affected_rows();
+    result.drop_result().await?;
+
+    Ok(StatementOutput {
+        columns,
+        rows: rows
+            .into_iter()
+            .map(|row| row.unwrap().into_iter().map(value_to_string).collect())
+            .collect(),
+        affected_rows,
+        skipped: false,
+    })
+}
+
+fn value_to_string(value: Value) -> String {
+    match value {
+        Value::NULL => "NULL".to_string(),
+        Value::Bytes(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        other => other.as_sql(false).trim_matches('\'').to_string(),
+    }
+}
+
+fn statement_error(idx: usize, error: mysql_async::Error, mode: ScriptMode) -> anyhow::Error {
+    match mode {
+        ScriptMode::Autocommit => anyhow!(
+            "第 {} 条语句执行失败: {error}（之前的 {idx} 条语句已提交）",
+            idx + 1
+        ),
+        ScriptMode::Transaction | ScriptMode::DryRun => {
+            anyhow!("第 {} 条语句执行失败，事务已回滚: {error}", idx + 1)
+        }
+    }
+}
+
+/// 会导致 MySQL 隐式提交当前事务的语句（DDL、锁表、事务控制等）
+pub fn causes_implicit_commit(sql: &str) -> bool {
+    const KEYWORDS: [&str; 16] = [
+        "CREATE", "ALTER", "DROP", "TRUNCATE", "RENAME", "GRANT", "REVOKE", "LOCK", "UNLOCK",
+        "BEGIN", "START", "COMMIT", "ROLLBACK", "ANALYZE", "OPTIMIZE", "FLUSH",
+    ];
+    let mut words = sql
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty());
+    let first = words.next().unwrap_or_default().to_ascii_uppercase();
+    // 临时表的创建和删除不会隐式提交
+    let temporary = words
+        .next()
+        .is_some_and(|word| word.eq_ignore_ascii_case("TEMPORARY"));
+    KEYWORDS.contains(&first.as_str()) && !temporary
+}
+
+/// 将SQL脚本拆分为单条语句
+///
+/// 识别引号中的分号、`--`/`#`/`/* */` 注释（保留 `/*! */` 版本注释）和 `DELIMITER` 指令，
+/// 返回的语句不包含结尾的分隔符
+pub fn split_sql_statements(sql: &str) -> Vec<String> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut delimiter: Vec<char> = vec![';'];
+    let mut i = 0;
+
+    let mut flush = |current: &mut String| {
+        let statement = current.trim();
+        if !statement.is_empty() {
+            statements.push(statement.to_string());
+        }
+        current.clear();
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        // 语句开头的 DELIMITER 指令（mysql 客户端语法）
+        if current.trim().is_empty() && (i == 0 || chars[i - 1] == '\n' || c.is_whitespace()) {
+            let rest: String = chars[i..].iter().take_while(|c| **c != '\n').collect();
+            let line = rest.trim_start();
+            let directive = line
+                .get(..10)
+                .filter(|d| d.eq_ignore_ascii_case("DELIMITER "))
+                .map(|d| line[d.len()..].trim())
+                .filter(|d| !d.is_empty());
+            if let Some(directive) = directive {
+                delimiter = directive.chars().collect();
+                current.clear();
+                i += rest.chars().count();
+                continue;
+            }
+        }
+
+        match c {
+            '\'' | '"' | '`' => {
+                current.push(c);
+                i += 1;
+                while i < chars.len() {
+                    let ch = chars[i];
+                    current.push(ch);
+                    i += 1;
+                    if ch == '\\' && c != '`' && i < chars.len() {
+                        current.push(chars[i]);
+                        i += 1;
+                    } else if ch == c {
+                        // 连续两个引号表示转义的引号
+                        if chars.get(i) == Some(&c) {
+                            current.push(c);
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+            }
+            '-' if next == Some('-') && chars.get(i + 2).is_none_or(|c| c.is_whitespace()) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if next == Some('*') => {
+                let start = i;
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i = (i + 2).min(chars.len());
+                if chars.get(start + 2) == Some(&'!') {
+                    current.extend(&chars[start..i]);
+                } else {
+                    current.push(' ');
+                }
+            }
+            _ if chars[i..].starts_with(&delimiter) => {
+                flush(&mut current);
+                i += delimiter.len();
+            }
+            _ => {
+                current.push(c);
+                i += 1;
+            }
+        }
+    }
+    flush(&mut current);
+    statements
+}
+
 /// 健康状态枚举
 #[derive(Debug, Clone)]
 pub enum HealthStatus {
@@ -357,6 +636,35 @@ mod tests {
         assert!(commands[1].contains("ALTER TABLE users ADD COLUMN name"));
     }
 
+    #[test]
+    fn test_split_sql_statements() {
+        let sql = "-- 修复数据\n\
+                   UPDATE users SET name = 'a;b' WHERE id = 1;\n\
+                   # 注释; 不拆分\n\
+                   INSERT INTO logs (msg) VALUES (\"it\\'s;\"), ('x''y;');/* 块注释; */\n\
+                   /*!40101 SET NAMES utf8mb4 */;\n\
+                   DELIMITER $$\n\
+                   CREATE TRIGGER t BEFORE INSERT ON users FOR EACH ROW BEGIN SET NEW.name = 'x'; END$$\n\
+                   DELIMITER ;\n\
+                   SELECT 1";
+
+        let statements = split_sql_statements(sql);
+        assert_eq!(
+            statements,
+            [
+                "UPDATE users SET name = 'a;b' WHERE id = 1",
+                "INSERT INTO logs (msg) VALUES (\"it\\'s;\"), ('x''y;')",
+                "/*!40101 SET NAMES utf8mb4 */",
+                "CREATE TRIGGER t BEFORE INSERT ON users FOR EACH ROW BEGIN SET NEW.name = 'x'; END",
+                "SELECT 1",
+            ]
+        );
+
+        assert!(causes_implicit_commit("ALTER TABLE users ADD age INT"));
+        assert!(!causes_implicit_commit("CREATE TEMPORARY TABLE t (id INT)"));
+        assert!(!causes_implicit_commit("UPDATE users SET name = 'drop'"));
+    }
+
     #[tokio::test]
     async fn test_empty_and_comments() {
         let content = "-- This is a comment\n\nCREATE TABLE test (id INT);\n-- Another comment";
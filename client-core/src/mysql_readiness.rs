@@ -0,0 +1,136 @@
+//! MySQL 就绪等待
+//!
+//! 容器报告已启动时 MySQL 往往还在初始化，立即连接会失败。执行数据库升级前依次检查
+//! 容器健康状态、端口可连接和 `SELECT 1`，未就绪时按指数退避重试，直到超时。
+
+use crate::constants::timeout;
+use crate::container::DockerManager;
+use crate::error::DuckError;
+use crate::mysql_executor::MySqlExecutor;
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tracing::debug;
+
+/// compose 文件中的 MySQL 服务名
+pub const MYSQL_SERVICE: &str = "mysql";
+
+/// 一次未通过的就绪检查
+#[derive(Debug, Clone)]
+pub struct ReadinessProgress {
+    /// 第几次检查，从 1 开始
+    pub attempt: u32,
+    /// 已等待的时间
+    pub elapsed: Duration,
+    /// 下次检查前的等待时间
+    pub next_delay: Duration,
+    /// 未就绪的原因
+    pub reason: String,
+}
+
+/// 第 `attempt` 次（从 0 开始）检查失败后的等待时间
+pub fn ready_delay(attempt: u32) -> Duration {
+    Duration::from_secs(
+        timeout::MYSQL_READY_INITIAL_DELAY
+            .saturating_mul(1 << attempt.min(10))
+            .min(timeout::MYSQL_READY_MAX_DELAY),
+    )
+}
+
+/// 等待 MySQL 可以执行查询，返回等待的时间
+///
+/// 提供 `docker_manager` 时先检查 mysql 容器的健康状态（未配置 healthcheck 时跳过），
+/// 每次检查未通过都会调用 `on_progress`，超过 `max_wait` 仍未就绪时返回最后一次的原因
+pub async fn wait_for_mysql_ready<F>(
+    executor: &MySqlExecutor,
+    docker_manager: Option<&DockerManager>,
+    max_wait: Duration,
+    mut on_progress: F,
+) -> Result<Duration>
+where
+    F: FnMut(&ReadinessProgress),
+{
+    let start = Instant::now();
+    let mut attempt = 0;
+    loop {
+        let reason = match probe(executor, docker_manager).await {
+            Ok(()) => return Ok(start.elapsed()),
+            Err(reason) => reason,
+        };
+
+        let elapsed = start.elapsed();
+        let remaining = max_wait.saturating_sub(elapsed);
+        if remaining.is_zero() {
+            return Err(DuckError::Docker(format!(
+                "等待 MySQL 就绪超时（{} 秒）: {reason}",
+                max_wait.as_secs()
+            ))
+            .into());
+        }
+
+        let next_delay = ready_delay(attempt).min(remaining);
+        attempt += 1;
+        on_progress(&ReadinessProgress {
+            attempt,
+            elapsed,
+            next_delay,
+            reason,
+        });
+        tokio::time::sleep(next_delay).await;
+    }
+}
+
+/// 检查一次，未就绪时返回原因
+async fn probe(
+    executor: &MySqlExecutor,
+    docker_manager: Option<&DockerManager>,
+) -> Result<(), String> {
+    if let Some(docker_manager) = docker_manager {
+        match docker_manager.get_service_health(MYSQL_SERVICE).await {
+            Ok(Some(status)) if status != "healthy" => {
+                return Err(format!("容器健康检查状态为 {status}"));
+            }
+            Ok(_) => {}
+            // 无法查询健康状态时继续用连接检查判断
+            Err(e) => debug!("查询 MySQL 容器健康状态失败: {}", e),
+        }
+    }
+
+    let config = executor.config();
+    let address = (config.host.as_str(), config.port);
+    match tokio::time::timeout(
+        Duration::from_secs(timeout::MYSQL_READY_PROBE_TIMEOUT),
+        TcpStream::connect(address),
+    )
+    .await
+    {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => return Err(format!("端口 {} 无法连接: {e}", config.port)),
+        Err(_) => return Err(format!("连接端口 {} 超时", config.port)),
+    }
+
+    executor
+        .test_connection()
+        .await
+        .map_err(|e| format!("数据库尚未接受查询: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ready_delay_backoff() {
+        assert_eq!(ready_delay(0), Duration::from_secs(1));
+        assert_eq!(ready_delay(1), Duration::from_secs(2));
+        assert_eq!(ready_delay(3), Duration::from_secs(8));
+        assert_eq!(
+            ready_delay(4),
+            Duration::from_secs(timeout::MYSQL_READY_MAX_DELAY)
+        );
+        assert_eq!(
+            ready_delay(u32::MAX),
+            Duration::from_secs(timeout::MYSQL_READY_MAX_DELAY)
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! # 服务包架构检查
+//!
+//! 把 amd64 的服务包拷到 arm64 主机上部署时，要到启动容器才报出 `exec format error`。
+//! 解压前按以下依据判断服务包的架构，与本机架构不一致时直接报错：
+//!
+//! - ZIP 服务包 `images/` 目录中镜像文件名的架构后缀（`-amd64.tar`、`-arm64.tar`），只读取 ZIP 目录，不解压
+//! - 下载地址或服务包文件名中的架构标识（`x86_64`/`amd64`、`aarch64`/`arm64`）
+//!
+//! 镜像文件是实际部署的内容，优先于名称；镜像同时包含多个架构或找不到任何依据时不做判断。
+//! tar 格式的服务包需要完整解压流才能列出条目，只按名称判断。
+
+use crate::architecture::Architecture;
+use crate::archive_format::ArchiveFormat;
+use crate::constants::docker::IMAGES_DIR_NAME;
+use anyhow::Result;
+use std::fmt;
+use std::path::Path;
+
+/// 判断服务包架构的依据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchitectureBasis {
+    /// 服务包内的镜像文件
+    ImageFiles(Vec<String>),
+    /// 下载地址中的文件名
+    DownloadUrl(String),
+    /// 服务包文件名
+    PackageName(String),
+}
+
+impl fmt::Display for ArchitectureBasis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ImageFiles(files) => match files.as_slice() {
+                [file] => write!(f, "镜像文件 {file}"),
+                [file, ..] => write!(f, "镜像文件 {file} 等 {} 个", files.len()),
+                [] => write!(f, "镜像文件"),
+            },
+            Self::DownloadUrl(name) => write!(f, "下载地址 {name}"),
+            Self::PackageName(name) => write!(f, "服务包文件名 {name}"),
+        }
+    }
+}
+
+/// 检测到的服务包架构
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageArchitecture {
+    pub architecture: Architecture,
+    pub basis: ArchitectureBasis,
+}
+
+/// 从文件名中识别架构标识，如 `docker-aarch64.zip`、`mysql-amd64.tar`
+pub fn architecture_from_name(name: &str) -> Option<Architecture> {
+    name.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .find_map(|token| Architecture::from_str(token).ok())
+}
+
+/// 检测服务包的架构，没有可用依据或依据不唯一时返回 None
+///
+/// `download_url` 为服务包的下载地址，本地拷贝的服务包传 None
+pub fn detect_package_architecture(
+    package: &Path,
+    download_url: Option<&str>,
+) -> Result<Option<PackageArchitecture>> {
+    if ArchiveFormat::detect(package)? == ArchiveFormat::Zip {
+        let file = std::fs::File::open(package)?;
+        let archive = zip::ZipArchive::new(file)?;
+        if let Some(detected) = architecture_from_image_entries(archive.file_names()) {
+            return Ok(Some(detected));
+        }
+    }
+
+    let from_url = download_url.and_then(|url| {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let name = path.rsplit('/').next()?;
+        architecture_from_name(name).map(|architecture| PackageArchitecture {
+            architecture,
+            basis: ArchitectureBasis::DownloadUrl(name.to_string()),
+        })
+    });
+    if from_url.is_some() {
+        return Ok(from_url);
+    }
+
+    let package_name = package
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(
+        architecture_from_name(&package_name).map(|architecture| PackageArchitecture {
+            architecture,
+            basis: ArchitectureBasis::PackageName(package_name),
+        }),
+    )
+}
+
+/// 按 `images/` 目录中镜像文件名的架构后缀判断，所有镜像属于同一架构时返回该架构
+fn architecture_from_image_entries<'a>(
+    entries: impl Iterator<Item = &'a str>,
+) -> Option<PackageArchitecture> {
+    let mut architectures: Vec<Architecture> = Vec::new();
+    let mut files = Vec::new();
+    for entry in entries {
+        let mut components = entry.rsplit('/');
+        let (Some(file_name), Some(parent)) = (components.next(), components.next()) else {
+            continue;
+        };
+        if parent != IMAGES_DIR_NAME {
+            continue;
+        }
+        let Some(suffix) = file_name
+            .strip_suffix(".tar")
+            .and_then(|stem| stem.rsplit_once('-'))
+            .map(|(_, suffix)| suffix)
+        else {
+            continue;
+        };
+        if let Ok(architecture) = Architecture::from_str(suffix) {
+            if !architectures.contains(&architecture) {
+                architectures.push(architecture);
+            }
+            files.push(file_name.to_string());
+        }
+    }
+
+    if architectures.len() != 1 {
+        return None;
+    }
+    let architecture = architectures.pop()?;
+    files.sort();
+    Some(PackageArchitecture {
+        architecture,
+        basis: ArchitectureBasis::ImageFiles(files),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_architecture_from_name() {
+        assert_eq!(
+            architecture_from_name("docker-aarch64.zip"),
+            Some(Architecture::Aarch64)
+        );
+        assert_eq!(
+            architecture_from_name("mysql-amd64.tar"),
+            Some(Architecture::X86_64)
+        );
+        assert_eq!(
+            architecture_from_name("docker-x86_64.tar.zst"),
+            Some(Architecture::X86_64)
+        );
+        assert_eq!(architecture_from_name("docker.zip"), None);
+        assert_eq!(architecture_from_name("farm64.zip"), None);
+    }
+
+    #[test]
+    fn test_image_entries_decide_architecture() {
+        let detected = architecture_from_image_entries(
+            [
+                "docker/docker-compose.yml",
+                "docker/images/mysql-arm64.tar",
+                "docker/images/agent-platform-front-arm64.tar",
+                "docker/images/images.json",
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+        assert_eq!(detected.architecture, Architecture::Aarch64);
+        assert_eq!(
+            detected.basis,
+            ArchitectureBasis::ImageFiles(vec![
+                "agent-platform-front-arm64.tar".to_string(),
+                "mysql-arm64.tar".to_string(),
+            ])
+        );
+
+        // 多架构服务包不做判断
+        assert!(
+            architecture_from_image_entries(
+                ["images/mysql-arm64.tar", "images/mysql-amd64.tar"].into_iter()
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_detect_package_architecture_prefers_images() {
+        let temp = TempDir::new().unwrap();
+        let package = temp.path().join("docker-aarch64.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&package).unwrap());
+        zip.start_file(
+            "docker/images/mysql-amd64.tar",
+            zip::write::SimpleFileOptions::default(),
+        )
+        .unwrap();
+        zip.write_all(b"image").unwrap();
+        zip.finish().unwrap();
+
+        let detected = detect_package_architecture(&package, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(detected.architecture, Architecture::X86_64);
+        assert!(matches!(detected.basis, ArchitectureBasis::ImageFiles(_)));
+
+        // 没有镜像文件时按下载地址、再按文件名判断
+        let package = temp.path().join("docker.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&package).unwrap());
+        zip.start_file("docker/.env", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+        let detected = detect_package_architecture(
+            &package,
+            Some("https://example.com/v1/docker-aarch64.zip?token=abc"),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(detected.architecture, Architecture::Aarch64);
+        assert_eq!(
+            detected.basis,
+            ArchitectureBasis::DownloadUrl("docker-aarch64.zip".to_string())
+        );
+        assert!(
+            detect_package_architecture(&package, None)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,622 @@
+//! # 服务包内容寻址存储
+//!
+//! 全量升级解压后，将服务包中的文件按 SHA-256 存入本地存储（哈希 → 对象），
+//! 工作目录中的文件以硬链接指向存储对象，磁盘上只保留一份内容。
+//! 后续全量升级时，路径、大小和 CRC32 都与已存储文件一致的条目直接从存储硬链接，
+//! 无需再次解压写盘。
+//!
+//! 目录结构：
+//! - `objects/<哈希前两位>/<哈希>`：文件内容
+//! - `trees/<版本>.json`：某个版本包含的文件（相对路径 → 哈希）
+//! - `index.json`：对象索引（哈希 → 大小、CRC32、修改时间）
+//!
+//! 工作目录中的文件与存储对象共享 inode，原地修改会同时改变对象内容，
+//! 因此对象记录了入库时的大小和修改时间，不一致时视为已损坏，不再复用并在 GC 时清理。
+
+use crate::version::Version;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+/// 对象目录名
+const OBJECTS_DIR_NAME: &str = "objects";
+
+/// 版本文件树目录名
+const TREES_DIR_NAME: &str = "trees";
+
+/// 对象索引文件名
+const INDEX_FILE_NAME: &str = "index.json";
+
+/// 读取文件时的缓冲区大小
+const READ_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// 全局服务包存储（由应用启动时注册）
+static PACKAGE_STORE: OnceLock<PackageStore> = OnceLock::new();
+
+/// 注册全局服务包存储，重复注册会被忽略
+pub fn register_package_store(store: PackageStore) {
+    if PACKAGE_STORE.set(store).is_err() {
+        debug!("服务包存储已注册，忽略重复注册");
+    }
+}
+
+/// 获取已注册的服务包存储
+pub fn registered_package_store() -> Option<&'static PackageStore> {
+    PACKAGE_STORE.get()
+}
+
+/// 存储对象信息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectInfo {
+    pub size: u64,
+    pub crc32: u32,
+    /// 入库时对象的修改时间，用于发现被原地修改的对象
+    pub mtime_nanos: u128,
+}
+
+/// 某个版本的文件树
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreTree {
+    pub version: String,
+    pub created_at: DateTime<Utc>,
+    /// 相对路径（使用 `/` 分隔）→ 对象哈希
+    pub entries: BTreeMap<String, String>,
+}
+
+/// 入库结果
+#[derive(Debug, Default, Clone)]
+pub struct IngestReport {
+    /// 新写入存储的对象数
+    pub stored: usize,
+    /// 复用已有对象（工作目录文件改为硬链接）的文件数
+    pub reused: usize,
+    /// 无法建立硬链接、保留独立副本的文件数
+    pub unlinked: usize,
+    /// 复用已有对象节省的字节数
+    pub saved_bytes: u64,
+}
+
+/// GC 结果
+#[derive(Debug, Default, Clone)]
+pub struct GcReport {
+    pub removed_trees: Vec<String>,
+    pub removed_objects: usize,
+    pub removed_bytes: u64,
+}
+
+/// 存储使用情况
+#[derive(Debug, Default, Clone)]
+pub struct StoreStatus {
+    /// 已记录的版本（版本、记录时间、文件数），按时间倒序
+    pub trees: Vec<(String, DateTime<Utc>, usize)>,
+    pub objects: usize,
+    pub total_bytes: u64,
+}
+
+/// 按（相对路径、大小、CRC32）查找已存储文件的索引
+#[derive(Debug, Default)]
+pub struct StoreLookup {
+    entries: HashMap<(String, u64, u32), (String, ObjectInfo)>,
+}
+
+impl StoreLookup {
+    /// 查找内容相同的已存储文件，返回对象哈希和对象信息
+    pub fn find(&self, relative_path: &str, size: u64, crc32: u32) -> Option<(&str, &ObjectInfo)> {
+        self.entries
+            .get(&(relative_path.to_string(), size, crc32))
+            .map(|(hash, info)| (hash.as_str(), info))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// 服务包内容寻址存储
+#[derive(Debug)]
+pub struct PackageStore {
+    root: PathBuf,
+    /// 串行化索引和文件树的读写
+    lock: Mutex<()>,
+}
+
+impl PackageStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 对象文件路径
+    pub fn object_path(&self, hash: &str) -> PathBuf {
+        let prefix = hash.get(..2).unwrap_or(hash);
+        self.root.join(OBJECTS_DIR_NAME).join(prefix).join(hash)
+    }
+
+    fn tree_path(&self, version: &str) -> PathBuf {
+        self.root
+            .join(TREES_DIR_NAME)
+            .join(format!("{}.json", version.replace(['/', '\\'], "_")))
+    }
+
+    /// 将目录中的文件存入存储，并记录为指定版本的文件树
+    ///
+    /// `skip` 接收相对路径，返回 true 的文件不入库（如用户数据目录）；符号链接不入库
+    pub fn ingest_dir(
+        &self,
+        dir: &Path,
+        version: &str,
+        skip: impl Fn(&str) -> bool,
+    ) -> Result<IngestReport> {
+        let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+        let mut index = self.load_index()?;
+        let mut report = IngestReport::default();
+        let mut tree = StoreTree {
+            version: version.to_string(),
+            created_at: Utc::now(),
+            entries: BTreeMap::new(),
+        };
+
+        for entry in WalkDir::new(dir).follow_links(false) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let relative = relative_path(dir, path)?;
+            if skip(&relative) {
+                continue;
+            }
+
+            let (hash, crc32, size) = digest_file(path)?;
+            let object = self.object_path(&hash);
+
+            let reusable = index
+                .get(&hash)
+                .is_some_and(|info| object_matches(&object, info));
+            if reusable {
+                match link_into_place(&object, path) {
+                    Ok(true) => {
+                        report.reused += 1;
+                        report.saved_bytes += size;
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        debug!("复用存储对象失败 {}: {}", path.display(), e);
+                        report.unlinked += 1;
+                    }
+                }
+            } else {
+                // 对象不存在或已被修改，用当前文件重新入库
+                if object.exists() {
+                    fs::remove_file(&object)?;
+                }
+                if let Some(parent) = object.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                if let Err(e) = fs::hard_link(path, &object) {
+                    debug!("无法硬链接到存储，改为复制 {}: {}", path.display(), e);
+                    fs::copy(path, &object)?;
+                    report.unlinked += 1;
+                }
+                let mtime_nanos = mtime_nanos(&fs::metadata(&object)?);
+                index.insert(
+                    hash.clone(),
+                    ObjectInfo {
+                        size,
+                        crc32,
+                        mtime_nanos,
+                    },
+                );
+                report.stored += 1;
+            }
+
+            tree.entries.insert(relative, hash);
+        }
+
+        self.save_index(&index)?;
+        write_json(&self.tree_path(version), &tree)?;
+        Ok(report)
+    }
+
+    /// 加载所有版本文件树，生成按（相对路径、大小、CRC32）查找对象的索引
+    pub fn lookup(&self) -> Result<StoreLookup> {
+        let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+        let index = self.load_index()?;
+        let mut lookup = StoreLookup::default();
+        for tree in self.load_trees()? {
+            for (path, hash) in tree.entries {
+                if let Some(info) = index.get(&hash) {
+                    lookup
+                        .entries
+                        .insert((path, info.size, info.crc32), (hash, info.clone()));
+                }
+            }
+        }
+        Ok(lookup)
+    }
+
+    /// 将存储对象硬链接到目标路径
+    ///
+    /// 对象不存在、已被修改或权限与 `mode` 不一致时返回 false，由调用方改为正常解压
+    pub fn link_object(
+        &self,
+        hash: &str,
+        info: &ObjectInfo,
+        target: &Path,
+        mode: Option<u32>,
+    ) -> Result<bool> {
+        let object = self.object_path(hash);
+        if !object_matches(&object, info) || !mode_matches(&object, mode) {
+            return Ok(false);
+        }
+
+        if target.is_dir() {
+            fs::remove_dir_all(target)?;
+        } else if target.symlink_metadata().is_ok() {
+            fs::remove_file(target)?;
+        }
+        match fs::hard_link(&object, target) {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                debug!("硬链接存储对象失败 {}: {}", target.display(), e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// 清理存储：保留最近 `keep` 个版本和 `protected` 中的版本，删除其余版本，
+    /// 再删除不再被任何版本引用或已损坏的对象
+    pub fn gc(&self, keep: usize, protected: &[String]) -> Result<GcReport> {
+        let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+        let mut report = GcReport::default();
+
+        let mut trees = self.load_trees()?;
+        trees.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        let mut referenced = BTreeSet::new();
+        for (position, tree) in trees.into_iter().enumerate() {
+            if position < keep || protected.contains(&tree.version) {
+                referenced.extend(tree.entries.into_values());
+            } else {
+                fs::remove_file(self.tree_path(&tree.version))?;
+                report.removed_trees.push(tree.version);
+            }
+        }
+
+        let mut index = self.load_index()?;
+        index.retain(|hash, info| {
+            let object = self.object_path(hash);
+            if referenced.contains(hash) && object_matches(&object, info) {
+                return true;
+            }
+            if object.exists() {
+                match fs::remove_file(&object) {
+                    Ok(()) => {
+                        report.removed_objects += 1;
+                        report.removed_bytes += info.size;
+                    }
+                    Err(e) => warn!("⚠️ 删除存储对象失败 {}: {}", object.display(), e),
+                }
+            }
+            false
+        });
+
+        // 清理不在索引中的残留对象
+        let objects_dir = self.root.join(OBJECTS_DIR_NAME);
+        if objects_dir.exists() {
+            for entry in WalkDir::new(&objects_dir).min_depth(2) {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy();
+                if entry.file_type().is_file() && !index.contains_key(name.as_ref()) {
+                    report.removed_bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+                    fs::remove_file(entry.path())?;
+                    report.removed_objects += 1;
+                }
+            }
+        }
+
+        self.save_index(&index)?;
+        Ok(report)
+    }
+
+    /// 获取指定版本的文件树
+    ///
+    /// 没有该版本时（如通过补丁升级到达），取同一基础版本中不高于该版本的最近文件树
+    pub fn tree_for_version(&self, version: &Version) -> Result<Option<StoreTree>> {
+        let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+        let tree = self
+            .load_trees()?
+            .into_iter()
+            .filter_map(|tree| {
+                let tree_version = tree.version.parse::<Version>().ok()?;
+                (tree_version.base_version() == version.base_version() && tree_version <= *version)
+                    .then_some((tree_version, tree))
+            })
+            .max_by(|(a, a_tree), (b, b_tree)| {
+                a.cmp(b)
+                    .then_with(|| a_tree.created_at.cmp(&b_tree.created_at))
+            })
+            .map(|(_, tree)| tree);
+        Ok(tree)
+    }
+
+    /// 获取存储使用情况
+    pub fn status(&self) -> Result<StoreStatus> {
+        let _guard = self.lock.lock().unwrap_or_else(|p| p.into_inner());
+        let index = self.load_index()?;
+        let mut trees: Vec<_> = self
+            .load_trees()?
+            .into_iter()
+            .map(|tree| (tree.version, tree.created_at, tree.entries.len()))
+            .collect();
+        trees.sort_by(|a, b| b.1.cmp(&a.1));
+
+        Ok(StoreStatus {
+            trees,
+            objects: index.len(),
+            total_bytes: index.values().map(|info| info.size).sum(),
+        })
+    }
+
+    fn load_index(&self) -> Result<BTreeMap<String, ObjectInfo>> {
+        let path = self.root.join(INDEX_FILE_NAME);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("解析服务包存储索引失败 {}: {}", path.display(), e))
+    }
+
+    fn save_index(&self, index: &BTreeMap<String, ObjectInfo>) -> Result<()> {
+        write_json(&self.root.join(INDEX_FILE_NAME), index)
+    }
+
+    fn load_trees(&self) -> Result<Vec<StoreTree>> {
+        let dir = self.root.join(TREES_DIR_NAME);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut trees = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            match fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| Ok(serde_json::from_str::<StoreTree>(&content)?))
+            {
+                Ok(tree) => trees.push(tree),
+                Err(e) => warn!("⚠️ 忽略无法解析的版本文件树 {}: {}", path.display(), e),
+            }
+        }
+        Ok(trees)
+    }
+}
+
+/// 先写临时文件再重命名，避免中断时留下不完整的 JSON
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(value)?)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+fn relative_path(base: &Path, path: &Path) -> Result<String> {
+    let relative = path
+        .strip_prefix(base)
+        .map_err(|_| anyhow::anyhow!("路径不在目录中: {}", path.display()))?;
+    Ok(relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// 计算文件的 SHA-256 和 CRC32
+/// 计算文件的 SHA-256、CRC32 和大小
+pub(crate) fn digest_file(path: &Path) -> Result<(String, u32, u64)> {
+    let mut file =
+        File::open(path).map_err(|e| anyhow::anyhow!("无法打开文件 {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut crc = flate2::Crc::new();
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    let mut size = 0u64;
+    loop {
+        let bytes_read = file
+            .read(&mut buffer)
+            .map_err(|e| anyhow::anyhow!("读取文件失败 {}: {}", path.display(), e))?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+        crc.update(&buffer[..bytes_read]);
+        size += bytes_read as u64;
+    }
+    Ok((format!("{:x}", hasher.finalize()), crc.sum(), size))
+}
+
+fn mtime_nanos(metadata: &fs::Metadata) -> u128 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or_default()
+}
+
+/// 对象存在且大小、修改时间与入库时一致
+fn object_matches(object: &Path, info: &ObjectInfo) -> bool {
+    fs::metadata(object)
+        .map(|metadata| metadata.len() == info.size && mtime_nanos(&metadata) == info.mtime_nanos)
+        .unwrap_or(false)
+}
+
+#[cfg(unix)]
+fn mode_matches(object: &Path, mode: Option<u32>) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    match (mode, fs::metadata(object)) {
+        (Some(mode), Ok(metadata)) => metadata.permissions().mode() & 0o777 == mode & 0o777,
+        (None, Ok(_)) => true,
+        (_, Err(_)) => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn mode_matches(object: &Path, _mode: Option<u32>) -> bool {
+    object.exists()
+}
+
+/// 用指向存储对象的硬链接替换文件，文件已是该对象的硬链接时返回 false
+///
+/// 先在同目录创建临时链接再重命名覆盖，保证文件始终存在
+fn link_into_place(object: &Path, path: &Path) -> Result<bool> {
+    if is_same_file(object, path) {
+        return Ok(false);
+    }
+    if !same_permissions(object, path) {
+        return Err(anyhow::anyhow!("文件权限与存储对象不一致"));
+    }
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("无效的文件路径: {}", path.display()))?;
+    let temp_path = path.with_file_name(format!(".{}.store-link", file_name.to_string_lossy()));
+    if temp_path.exists() {
+        fs::remove_file(&temp_path)?;
+    }
+    fs::hard_link(object, &temp_path)?;
+    if let Err(e) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_a: &Path, _b: &Path) -> bool {
+    false
+}
+
+fn same_permissions(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.permissions() == b.permissions(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_tree(dir: &Path, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_ingest_reuses_identical_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PackageStore::new(temp_dir.path().join("store"));
+        let v1 = temp_dir.path().join("v1");
+        let v2 = temp_dir.path().join("v2");
+        write_tree(&v1, &[("app.jar", "app-v1"), ("conf/nginx.conf", "nginx")]);
+        write_tree(&v2, &[("app.jar", "app-v2"), ("conf/nginx.conf", "nginx")]);
+
+        let report = store.ingest_dir(&v1, "1.0.0", |_| false).unwrap();
+        assert_eq!(report.stored, 2);
+
+        let report = store.ingest_dir(&v2, "1.0.1", |_| false).unwrap();
+        assert_eq!(report.stored, 1);
+        assert_eq!(report.reused, 1);
+        assert_eq!(report.saved_bytes, 5);
+        assert_eq!(
+            fs::read_to_string(v2.join("conf/nginx.conf")).unwrap(),
+            "nginx"
+        );
+
+        let status = store.status().unwrap();
+        assert_eq!(status.objects, 3);
+        assert_eq!(status.trees.len(), 2);
+    }
+
+    #[test]
+    fn test_lookup_and_link_object() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PackageStore::new(temp_dir.path().join("store"));
+        let work = temp_dir.path().join("work");
+        write_tree(&work, &[("app.jar", "app"), ("upload/a.png", "user")]);
+
+        store
+            .ingest_dir(&work, "1.0.0", |path| path.starts_with("upload/"))
+            .unwrap();
+
+        let lookup = store.lookup().unwrap();
+        let crc = {
+            let mut crc = flate2::Crc::new();
+            crc.update(b"app");
+            crc.sum()
+        };
+        let (hash, info) = lookup.find("app.jar", 3, crc).unwrap();
+        assert!(lookup.find("upload/a.png", 4, 0).is_none());
+        assert!(lookup.find("other.jar", 3, crc).is_none());
+
+        let target = temp_dir.path().join("restored.jar");
+        assert!(store.link_object(hash, info, &target, None).unwrap());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "app");
+    }
+
+    #[test]
+    fn test_gc_removes_unreferenced_and_modified_objects() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = PackageStore::new(temp_dir.path().join("store"));
+        let v1 = temp_dir.path().join("v1");
+        let v2 = temp_dir.path().join("v2");
+        write_tree(&v1, &[("old.jar", "old"), ("shared.txt", "shared")]);
+        store.ingest_dir(&v1, "1.0.0", |_| false).unwrap();
+        write_tree(&v2, &[("new.jar", "new"), ("shared.txt", "shared")]);
+        store.ingest_dir(&v2, "1.0.1", |_| false).unwrap();
+
+        let report = store.gc(1, &[]).unwrap();
+        assert_eq!(report.removed_trees, ["1.0.0"]);
+        assert_eq!(report.removed_objects, 1);
+        assert_eq!(store.status().unwrap().objects, 2);
+
+        // 工作目录中的文件被原地修改后，对应对象视为已损坏
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        fs::write(v2.join("new.jar"), "changed").unwrap();
+        let report = store.gc(1, &[]).unwrap();
+        assert_eq!(report.removed_objects, 1);
+        assert!(store.lookup().unwrap().find("new.jar", 3, 0).is_none());
+    }
+}
@@ -0,0 +1,232 @@
+//! # 服务包校验
+//!
+//! 截断的下载和压缩炸弹原本要到解压时才报错，压缩炸弹还可能先写满磁盘。解压前依次检查：
+//!
+//! - 文件大小与服务清单声明的大小一致
+//! - 文件 SHA-256 与服务清单声明的哈希一致（未声明或为 `external` 时跳过）
+//! - 条目数量和解压后的总大小不超过 `[package_limits]` 的上限：ZIP 只读取目录，
+//!   tar 只读取条目头，超过上限时立即停止，不写入任何文件
+//!
+//! 校验失败的错误中给出服务清单声明的值和实际值，便于判断是下载不完整还是服务包本身有问题。
+
+use crate::archive_format::ArchiveFormat;
+use crate::config::PackageLimitsConfig;
+use crate::file_hash::sha256_file;
+use crate::redact::redact_url;
+use crate::upgrade_strategy::UpgradeStrategy;
+use anyhow::Result;
+use std::path::Path;
+
+/// 服务清单中声明的服务包信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedPackage {
+    /// 下载地址，用于错误信息
+    pub url: String,
+    /// 文件大小（字节）
+    pub size: Option<u64>,
+    /// SHA-256 哈希，可带 `sha256:` 前缀
+    pub hash: Option<String>,
+}
+
+impl ExpectedPackage {
+    /// 升级策略对应的服务包声明，补丁链和无需升级时返回 None
+    pub fn from_strategy(strategy: &UpgradeStrategy) -> Option<Self> {
+        match strategy {
+            UpgradeStrategy::FullUpgrade {
+                url, hash, size, ..
+            } => Some(Self {
+                url: url.clone(),
+                size: *size,
+                hash: Some(hash.clone()),
+            }),
+            UpgradeStrategy::PatchUpgrade { patch_info, .. } => Some(Self {
+                url: patch_info.url.clone(),
+                size: None,
+                hash: patch_info.hash.clone(),
+            }),
+            UpgradeStrategy::PatchChainUpgrade { .. } | UpgradeStrategy::NoUpgrade { .. } => None,
+        }
+    }
+
+    /// 去掉算法前缀后的十六进制哈希，没有可校验的哈希时返回 None
+    fn sha256(&self) -> Option<&str> {
+        let hash = self.hash.as_deref()?.trim();
+        let hash = hash.strip_prefix("sha256:").unwrap_or(hash);
+        let is_hex = hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit());
+        is_hex.then_some(hash)
+    }
+}
+
+/// 服务包的条目统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArchiveStats {
+    /// 条目数量
+    pub entries: usize,
+    /// 解压后的总大小（字节）
+    pub extracted_bytes: u64,
+}
+
+/// 校验已下载服务包的大小和哈希
+pub async fn verify_package_download(package: &Path, expected: &ExpectedPackage) -> Result<()> {
+    let actual_size = std::fs::metadata(package)?.len();
+    let size_mismatch = expected
+        .size
+        .filter(|size| *size > 0 && *size != actual_size);
+    if let Some(size) = size_mismatch {
+        return Err(anyhow::anyhow!(
+            "服务包大小与服务清单不一致: 清单声明 {} 字节，实际 {} 字节{}（{}）。请删除 {} 后重新下载",
+            size,
+            actual_size,
+            if actual_size < size {
+                "，下载可能不完整"
+            } else {
+                ""
+            },
+            redact_url(&expected.url),
+            package.display()
+        ));
+    }
+
+    if let Some(hash) = expected.sha256() {
+        let actual_hash = sha256_file(package).await?;
+        if !actual_hash.eq_ignore_ascii_case(hash) {
+            return Err(anyhow::anyhow!(
+                "服务包哈希与服务清单不一致: 清单声明 sha256:{}，实际 sha256:{}（{}，{} 字节）。请删除 {} 后重新下载",
+                hash.to_lowercase(),
+                actual_hash.to_lowercase(),
+                redact_url(&expected.url),
+                actual_size,
+                package.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 在不解压的情况下检查服务包的条目数量和解压后的总大小
+pub fn check_extraction_limits(
+    package: &Path,
+    limits: &PackageLimitsConfig,
+) -> Result<ArchiveStats> {
+    let max_bytes = limits.max_extracted_size_mb.saturating_mul(1024 * 1024);
+    let mut stats = ArchiveStats::default();
+    let mut add_entry = |size: u64| -> Result<()> {
+        stats.entries += 1;
+        stats.extracted_bytes = stats.extracted_bytes.saturating_add(size);
+        if stats.entries > limits.max_entries {
+            return Err(anyhow::anyhow!(
+                "服务包条目数量超过上限 {}（package_limits.max_entries），拒绝解压: {}",
+                limits.max_entries,
+                package.display()
+            ));
+        }
+        if stats.extracted_bytes > max_bytes {
+            return Err(anyhow::anyhow!(
+                "服务包解压后超过 {} MB（package_limits.max_extracted_size_mb），拒绝解压: {}",
+                limits.max_extracted_size_mb,
+                package.display()
+            ));
+        }
+        Ok(())
+    };
+
+    let format = ArchiveFormat::detect(package)?;
+    if format.is_tar() {
+        let mut archive = format.open_tar(package)?;
+        for entry in archive.entries()? {
+            add_entry(entry?.size())?;
+        }
+    } else {
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(package)?)?;
+        for i in 0..archive.len() {
+            add_entry(archive.by_index_raw(i)?.size())?;
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn write_zip(path: &Path, files: &[(&str, usize)]) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        for (name, size) in files {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(&vec![0u8; *size]).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verify_package_download() {
+        let temp = TempDir::new().unwrap();
+        let package = temp.path().join("docker.zip");
+        std::fs::write(&package, b"package").unwrap();
+        let hash = sha256_file(&package).await.unwrap();
+
+        let mut expected = ExpectedPackage {
+            url: "https://example.com/docker.zip?token=abc".to_string(),
+            size: Some(7),
+            hash: Some(format!("sha256:{}", hash.to_uppercase())),
+        };
+        verify_package_download(&package, &expected).await.unwrap();
+
+        expected.size = Some(100);
+        let error = verify_package_download(&package, &expected)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("清单声明 100 字节，实际 7 字节"), "{error}");
+        assert!(error.contains("下载可能不完整"), "{error}");
+        assert!(!error.contains("token=abc"), "{error}");
+
+        expected.size = None;
+        expected.hash = Some("0".repeat(64));
+        let error = verify_package_download(&package, &expected)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains(&format!("实际 sha256:{hash}")), "{error}");
+
+        // 没有可校验的哈希时跳过
+        expected.hash = Some("external".to_string());
+        verify_package_download(&package, &expected).await.unwrap();
+    }
+
+    #[test]
+    fn test_check_extraction_limits() {
+        let temp = TempDir::new().unwrap();
+        let package = temp.path().join("docker.zip");
+        write_zip(
+            &package,
+            &[("docker/a.bin", 1024 * 1024), ("docker/b.bin", 1024 * 1024)],
+        );
+
+        let stats = check_extraction_limits(&package, &PackageLimitsConfig::default()).unwrap();
+        assert_eq!(
+            stats,
+            ArchiveStats {
+                entries: 2,
+                extracted_bytes: 2 * 1024 * 1024,
+            }
+        );
+
+        let limits = PackageLimitsConfig {
+            max_extracted_size_mb: 1,
+            ..Default::default()
+        };
+        let error = check_extraction_limits(&package, &limits).unwrap_err();
+        assert!(error.to_string().contains("超过 1 MB"), "{error}");
+
+        let limits = PackageLimitsConfig {
+            max_entries: 1,
+            ..Default::default()
+        };
+        let error = check_extraction_limits(&package, &limits).unwrap_err();
+        assert!(error.to_string().contains("条目数量超过上限 1"), "{error}");
+    }
+}
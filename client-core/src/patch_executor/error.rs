@@ -58,6 +58,10 @@ pub enum PatchExecutorError {
     #[error("补丁源目录未设置")]
     PatchSourceNotSet,
 
+    /// 目标目录不可写（只读挂载、无法写入或空间不足）
+    #[error("{0}")]
+    NotWritable(#[from] crate::fs_probe::NotWritableError),
+
     /// 临时文件操作错误
     #[error("临时文件操作错误: {0}")]
     TempFileError(#[from] tempfile::PersistError),
@@ -170,6 +174,7 @@ impl PatchExecutorError {
             Self::UnsupportedOperation { .. } => false,
             Self::BackupNotEnabled => false,
             Self::PatchSourceNotSet => false,
+            Self::NotWritable(_) => false,
             _ => true,
         }
     }
@@ -183,6 +188,7 @@ impl PatchExecutorError {
             Self::DownloadFailed { .. } => false,
             Self::BackupNotEnabled => false,
             Self::PatchSourceNotSet => false,
+            Self::NotWritable(_) => false,
             _ => true,
         }
     }
@@ -4,6 +4,8 @@
 //! 负责安全的文件替换、删除和回滚操作
 
 use super::error::{PatchExecutorError, Result};
+use crate::safe_path::{long_path, sanitize_relative_path};
+use crate::upgrade_session;
 use fs_extra::dir;
 use remove_dir_all::remove_dir_all;
 use std::path::{Path, PathBuf};
@@ -34,7 +36,7 @@ impl FileOperationExecutor {
         debug!("创建文件操作执行器，工作目录: {:?}", work_dir);
 
         Ok(Self {
-            work_dir,
+            work_dir: long_path(&work_dir),
             backup_dir: None,
             patch_source: None,
         })
@@ -55,7 +57,7 @@ impl FileOperationExecutor {
             )));
         }
 
-        self.patch_source = Some(patch_source.to_owned());
+        self.patch_source = Some(long_path(patch_source));
         debug!("设置补丁源目录: {:?}", patch_source);
         Ok(())
     }
@@ -63,6 +65,7 @@ impl FileOperationExecutor {
     /// 执行文件替换操作
     pub async fn replace_files(&self, files: &[String]) -> Result<()> {
         info!("🔄 开始替换 {} 个文件", files.len());
+        upgrade_session::record_file_operation("replace_files", files);
 
         for file_path in files {
             self.replace_single_file(file_path).await?;
@@ -75,6 +78,7 @@ impl FileOperationExecutor {
     /// 执行目录替换操作
     pub async fn replace_directories(&self, directories: &[String]) -> Result<()> {
         info!("🔄 开始替换 {} 个目录", directories.len());
+        upgrade_session::record_file_operation("replace_directories", directories);
 
         for dir_path in directories {
             self.replace_single_directory(dir_path).await?;
@@ -87,6 +91,7 @@ impl FileOperationExecutor {
     /// 执行删除操作
     pub async fn delete_items(&self, items: &[String]) -> Result<()> {
         info!("🗑️ 开始删除 {} 个项目", items.len());
+        upgrade_session::record_file_operation("delete", items);
 
         for item_path in items {
             self.delete_single_item(item_path).await?;
@@ -98,7 +103,7 @@ impl FileOperationExecutor {
 
     /// 替换单个文件
     async fn replace_single_file(&self, file_path: &str) -> Result<()> {
-        let target_path = self.work_dir.join(file_path);
+        let target_path = join_relative(&self.work_dir, file_path)?;
 
         // 获取补丁源路径
         let source_path = self.get_patch_source_path(file_path)?;
@@ -106,7 +111,7 @@ impl FileOperationExecutor {
         // 创建备份
         if let Some(backup_dir) = &self.backup_dir {
             if target_path.exists() {
-                let backup_path = backup_dir.path().join(file_path);
+                let backup_path = join_relative(backup_dir.path(), file_path)?;
                 if let Some(parent) = backup_path.parent() {
                     fs::create_dir_all(parent).await?;
                 }
@@ -124,7 +129,7 @@ impl FileOperationExecutor {
 
     /// 替换单个目录
     async fn replace_single_directory(&self, dir_path: &str) -> Result<()> {
-        let target_path = self.work_dir.join(dir_path);
+        let target_path = join_relative(&self.work_dir, dir_path)?;
 
         // 获取补丁源路径
         let source_path = self.get_patch_source_path(dir_path)?;
@@ -132,7 +137,7 @@ impl FileOperationExecutor {
         // 创建备份
         if let Some(backup_dir) = &self.backup_dir {
             if target_path.exists() {
-                let backup_path = backup_dir.path().join(dir_path);
+                let backup_path = join_relative(backup_dir.path(), dir_path)?;
                 self.backup_directory(&target_path, &backup_path).await?;
                 debug!("已备份目录: {} -> {:?}", dir_path, backup_path);
             }
@@ -152,7 +157,7 @@ impl FileOperationExecutor {
 
     /// 删除单个项目
     async fn delete_single_item(&self, item_path: &str) -> Result<()> {
-        let target_path = self.work_dir.join(item_path);
+        let target_path = join_relative(&self.work_dir, item_path)?;
 
         if !target_path.exists() {
             warn!("⚠️ 删除目标不存在，跳过: {}", item_path);
@@ -161,7 +166,7 @@ impl FileOperationExecutor {
 
         // 创建备份
         if let Some(backup_dir) = &self.backup_dir {
-            let backup_path = backup_dir.path().join(item_path);
+            let backup_path = join_relative(backup_dir.path(), item_path)?;
             if target_path.is_dir() {
                 self.backup_directory(&target_path, &backup_path).await?;
             } else {
@@ -191,7 +196,7 @@ impl FileOperationExecutor {
             .as_ref()
             .ok_or(PatchExecutorError::PatchSourceNotSet)?;
 
-        let source_path = patch_source.join(relative_path);
+        let source_path = join_relative(patch_source, relative_path)?;
 
         if !source_path.exists() {
             return Err(PatchExecutorError::path_error(format!(
@@ -288,7 +293,7 @@ impl FileOperationExecutor {
             warn!("🔙 开始回滚文件操作...");
 
             // 遍历备份目录，恢复所有文件
-            let backup_path = backup_dir.path().to_owned();
+            let backup_path = long_path(backup_dir.path());
             let work_dir = self.work_dir.clone();
 
             tokio::task::spawn_blocking(move || {
@@ -352,6 +357,13 @@ impl FileOperationExecutor {
     }
 }
 
+/// 将补丁清单中的相对路径（`/` 分隔）拼接到 `base` 下，返回不受 Windows `MAX_PATH` 限制的路径
+fn join_relative(base: &Path, relative_path: &str) -> Result<PathBuf> {
+    let relative = sanitize_relative_path(relative_path)
+        .map_err(|e| PatchExecutorError::path_error(e.to_string()))?;
+    Ok(long_path(base).join(relative))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -391,7 +403,10 @@ mod tests {
         let mut executor = FileOperationExecutor::new(temp_dir.path().to_owned()).unwrap();
         let result = executor.set_patch_source(patch_source_dir.path());
         assert!(result.is_ok());
-        assert_eq!(executor.patch_source(), Some(patch_source_dir.path()));
+        assert_eq!(
+            executor.patch_source(),
+            Some(long_path(patch_source_dir.path()).as_path())
+        );
     }
 
     #[tokio::test]
@@ -520,4 +535,41 @@ mod tests {
         let restored_content = fs::read_to_string(&test_file).await.unwrap();
         assert_eq!(restored_content, "delete me");
     }
+
+    #[tokio::test]
+    async fn test_replace_deep_nested_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let patch_source_dir = TempDir::new().unwrap();
+
+        let mut executor = FileOperationExecutor::new(temp_dir.path().to_owned()).unwrap();
+        executor.enable_backup().unwrap();
+        executor.set_patch_source(patch_source_dir.path()).unwrap();
+
+        // 补丁清单中的路径使用 `/` 分隔，拼接后超过 Windows 的 MAX_PATH
+        let segment = "nested".repeat(8);
+        let relative = format!("{}/app.conf", vec![segment.as_str(); 6].join("/"));
+        let relative_path = sanitize_relative_path(&relative).unwrap();
+
+        let original_file = long_path(temp_dir.path()).join(&relative_path);
+        assert!(original_file.as_os_str().len() > 260);
+        fs::create_dir_all(original_file.parent().unwrap())
+            .await
+            .unwrap();
+        fs::write(&original_file, "original").await.unwrap();
+
+        let patch_file = long_path(patch_source_dir.path()).join(&relative_path);
+        fs::create_dir_all(patch_file.parent().unwrap())
+            .await
+            .unwrap();
+        fs::write(&patch_file, "patched").await.unwrap();
+
+        executor.replace_files(&[relative]).await.unwrap();
+        assert_eq!(fs::read_to_string(&original_file).await.unwrap(), "patched");
+
+        executor.rollback().await.unwrap();
+        assert_eq!(
+            fs::read_to_string(&original_file).await.unwrap(),
+            "original"
+        );
+    }
 }
@@ -16,6 +16,8 @@ pub use file_operations::FileOperationExecutor;
 pub use patch_processor::PatchProcessor;
 
 use crate::api_types::{PatchOperations, PatchPackageInfo};
+use crate::events::{EventSender, OperationKind};
+use crate::fs_probe::check_writable;
 use std::path::{Path, PathBuf};
 use tracing::{debug, error, info, warn};
 
@@ -31,6 +33,8 @@ pub struct PatchExecutor {
     patch_processor: PatchProcessor,
     /// 是否启用了备份
     backup_enabled: bool,
+    /// 操作事件发送端
+    events: EventSender,
 }
 
 impl PatchExecutor {
@@ -44,9 +48,16 @@ impl PatchExecutor {
             file_executor,
             patch_processor,
             backup_enabled: false,
+            events: EventSender::default(),
         })
     }
 
+    /// 设置操作事件发送端，补丁应用过程中上报阶段和进度
+    pub fn with_events(mut self, events: EventSender) -> Self {
+        self.events = events;
+        self
+    }
+
     /// 启用备份模式（支持回滚）
     pub fn enable_backup(&mut self) -> Result<(), PatchExecutorError> {
         self.file_executor.enable_backup()?;
@@ -93,6 +104,8 @@ impl PatchExecutor {
                 // 根据错误类型决定是否回滚
                 if e.requires_rollback() && self.backup_enabled {
                     warn!("🔄 开始自动回滚...");
+                    self.events
+                        .warning(OperationKind::Patch, format!("补丁应用失败，自动回滚: {e}"));
                     if let Err(rollback_err) = self.rollback().await {
                         error!("❌ 回滚失败: {}", rollback_err);
                         return Err(PatchExecutorError::rollback_failed(format!(
@@ -121,6 +134,9 @@ impl PatchExecutor {
                 self.work_dir
             )));
         }
+        // 在下载补丁包之前发现只读挂载或已写满的文件系统
+        check_writable(&self.work_dir, None)?;
+        check_writable(self.patch_processor.temp_dir(), None)?;
 
         // 验证操作不为空
         let total_operations = operations.total_operations();
@@ -143,33 +159,45 @@ impl PatchExecutor {
     where
         F: Fn(f64) + Send + Sync,
     {
+        let events = self.events.clone();
+
         // 1. 下载并验证补丁包
         info!("📥 下载补丁包...");
+        events.phase_started(OperationKind::Patch, "下载补丁包");
         let patch_path = self.patch_processor.download_patch(patch_info).await?;
+        events.phase_completed(OperationKind::Patch, "下载补丁包");
         progress_callback(0.25);
 
         // 2. 验证补丁完整性和签名
         info!("🔍 验证补丁完整性...");
+        events.phase_started(OperationKind::Patch, "验证补丁完整性");
         self.patch_processor
             .verify_patch_integrity(&patch_path, patch_info)
             .await?;
+        events.phase_completed(OperationKind::Patch, "验证补丁完整性");
         progress_callback(0.35);
 
         // 3. 解压补丁包
         info!("📦 解压补丁包...");
+        events.phase_started(OperationKind::Patch, "解压补丁包");
         let extracted_path = self.patch_processor.extract_patch(&patch_path).await?;
+        events.phase_completed(OperationKind::Patch, "解压补丁包");
         progress_callback(0.45);
 
         // 4. 验证解压后的文件结构
         info!("🔍 验证补丁文件结构...");
+        events.phase_started(OperationKind::Patch, "验证补丁文件结构");
         self.validate_patch_structure(&extracted_path, operations)
             .await?;
+        events.phase_completed(OperationKind::Patch, "验证补丁文件结构");
         progress_callback(0.5);
 
         // 5. 应用补丁操作
         info!("🔧 应用补丁操作...");
+        events.phase_started(OperationKind::Patch, "应用补丁操作");
         self.apply_patch_operations(&extracted_path, operations, progress_callback)
             .await?;
+        events.phase_completed(OperationKind::Patch, "应用补丁操作");
 
         Ok(())
     }
@@ -240,6 +268,11 @@ impl PatchExecutor {
                     + (completed_operations as f64 / total_operations as f64)
                         * operations_progress_range;
                 progress_callback(progress);
+                self.events.progress(
+                    OperationKind::Patch,
+                    completed_operations as u64,
+                    total_operations as u64,
+                );
             }
 
             // 执行目录替换
@@ -253,6 +286,11 @@ impl PatchExecutor {
                     + (completed_operations as f64 / total_operations as f64)
                         * operations_progress_range;
                 progress_callback(progress);
+                self.events.progress(
+                    OperationKind::Patch,
+                    completed_operations as u64,
+                    total_operations as u64,
+                );
             }
         }
 
@@ -267,6 +305,11 @@ impl PatchExecutor {
                     + (completed_operations as f64 / total_operations as f64)
                         * operations_progress_range;
                 progress_callback(progress);
+                self.events.progress(
+                    OperationKind::Patch,
+                    completed_operations as u64,
+                    total_operations as u64,
+                );
             }
             // 如果有目录需要删除
             if !delete.directories.is_empty() {
@@ -277,6 +320,11 @@ impl PatchExecutor {
                     + (completed_operations as f64 / total_operations as f64)
                         * operations_progress_range;
                 progress_callback(progress);
+                self.events.progress(
+                    OperationKind::Patch,
+                    completed_operations as u64,
+                    total_operations as u64,
+                );
             }
         }
 
@@ -5,6 +5,7 @@
 
 use super::error::{PatchExecutorError, Result};
 use crate::api_types::PatchPackageInfo;
+use crate::safe_path::{long_path, resolve_entry_path, validate_symlink_target};
 use base64;
 use flate2::read::GzDecoder;
 use reqwest::Client;
@@ -202,6 +203,8 @@ impl PatchProcessor {
 
     /// 解压tar.gz文件
     fn extract_tar_gz(archive_path: &Path, extract_to: &Path) -> Result<()> {
+        // 与 resolve_entry_path 返回的路径保持相同形式，符号链接校验才能比较前缀
+        let extract_to = &long_path(extract_to);
         let file = std::fs::File::open(archive_path)?;
         let decoder = GzDecoder::new(file);
         let mut archive = Archive::new(decoder);
@@ -219,19 +222,32 @@ impl PatchProcessor {
             // 将路径转换为PathBuf以避免借用问题
             let path_buf = path.to_path_buf();
 
-            // 安全检查：防止路径遍历攻击
-            if path_buf.is_absolute()
-                || path_buf
-                    .components()
-                    .any(|c| c == std::path::Component::ParentDir)
+            // 安全检查：防止路径遍历攻击（..、绝对路径、经由符号链接逃逸）
+            let extract_path = resolve_entry_path(extract_to, &path_buf.to_string_lossy())
+                .map_err(|e| PatchExecutorError::extraction_failed(e.to_string()))?;
+
+            // 符号链接条目的目标也不能指向解压目录之外
+            if entry.header().entry_type().is_symlink()
+                || entry.header().entry_type().is_hard_link()
             {
-                return Err(PatchExecutorError::extraction_failed(format!(
-                    "不安全的文件路径: {path_buf:?}"
-                )));
+                let link_name = entry.link_name().map_err(|e| {
+                    PatchExecutorError::extraction_failed(format!("读取链接目标失败: {e}"))
+                })?;
+                let Some(link_name) = link_name else {
+                    return Err(PatchExecutorError::extraction_failed(format!(
+                        "符号链接缺少目标: {path_buf:?}"
+                    )));
+                };
+                if entry.header().entry_type().is_hard_link() {
+                    // 硬链接目标以归档根目录为基准
+                    resolve_entry_path(extract_to, &link_name.to_string_lossy())
+                        .map_err(|e| PatchExecutorError::extraction_failed(e.to_string()))?;
+                } else {
+                    validate_symlink_target(extract_to, &extract_path, &link_name)
+                        .map_err(|e| PatchExecutorError::extraction_failed(e.to_string()))?;
+                }
             }
 
-            let extract_path = extract_to.join(&path_buf);
-
             // 确保父目录存在
             if let Some(parent) = extract_path.parent() {
                 std::fs::create_dir_all(parent)?;
@@ -281,7 +297,8 @@ impl PatchProcessor {
         let extract_dir = self.temp_dir.path().join("extracted");
 
         for required_file in required_files {
-            let file_path = extract_dir.join(required_file);
+            let file_path = resolve_entry_path(&extract_dir, required_file)
+                .map_err(|e| PatchExecutorError::verification_failed(e.to_string()))?;
             if !file_path.exists() {
                 return Err(PatchExecutorError::verification_failed(format!(
                     "必需的文件不存在: {required_file}"
@@ -385,6 +402,54 @@ mod tests {
         assert!(extracted_file.exists());
     }
 
+    #[tokio::test]
+    async fn test_tar_gz_extraction_rejects_path_traversal() {
+        let processor = PatchProcessor::new().unwrap();
+        let extract_dir = processor.temp_dir().join("extract_malicious");
+        fs::create_dir_all(&extract_dir).await.unwrap();
+
+        for (name, malicious_path) in [
+            ("parent.tar.gz", "../evil.sh"),
+            ("nested.tar.gz", "docker/../../evil.sh"),
+            ("absolute.tar.gz", "/tmp/evil.sh"),
+        ] {
+            let tar_path = processor.temp_dir().join(name);
+            create_raw_path_tar_gz(&tar_path, malicious_path).unwrap();
+
+            let result = PatchProcessor::extract_tar_gz(&tar_path, &extract_dir);
+            assert!(result.is_err(), "应拒绝恶意路径: {malicious_path}");
+        }
+        assert!(!processor.temp_dir().join("evil.sh").exists());
+    }
+
+    #[tokio::test]
+    async fn test_tar_gz_extraction_rejects_escaping_symlink() {
+        let processor = PatchProcessor::new().unwrap();
+        let extract_dir = processor.temp_dir().join("extract_symlink");
+        fs::create_dir_all(&extract_dir).await.unwrap();
+
+        let tar_path = processor.temp_dir().join("symlink.tar.gz");
+        {
+            use flate2::Compression;
+            use flate2::write::GzEncoder;
+
+            let enc = GzEncoder::new(
+                std::fs::File::create(&tar_path).unwrap(),
+                Compression::default(),
+            );
+            let mut tar = tar::Builder::new(enc);
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            tar.append_link(&mut header, "config", "../../etc").unwrap();
+            tar.finish().unwrap();
+        }
+
+        let result = PatchProcessor::extract_tar_gz(&tar_path, &extract_dir);
+        assert!(result.is_err());
+        assert!(std::fs::symlink_metadata(extract_dir.join("config")).is_err());
+    }
+
     #[tokio::test]
     async fn test_list_extracted_files() {
         let processor = PatchProcessor::new().unwrap();
@@ -451,4 +516,26 @@ mod tests {
 
         Ok(())
     }
+
+    // 辅助函数：直接写入原始路径字段，绕过 tar::Builder 的路径校验，模拟恶意补丁包
+    fn create_raw_path_tar_gz(output_path: &Path, raw_path: &str) -> std::io::Result<()> {
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+
+        let tar_gz = std::fs::File::create(output_path)?;
+        let enc = GzEncoder::new(tar_gz, Compression::default());
+        let mut tar = tar::Builder::new(enc);
+
+        let mut header = tar::Header::new_old();
+        let name_field = &mut header.as_old_mut().name;
+        name_field[..raw_path.len()].copy_from_slice(raw_path.as_bytes());
+        header.set_size(4);
+        header.set_mode(0o644);
+        header.set_cksum();
+
+        tar.append(&header, "evil".as_bytes())?;
+        tar.finish()?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,430 @@
+//! 补丁操作清单（operations）的严格校验
+//!
+//! 补丁包的 replace/delete 操作以前只在应用时才暴露问题。这里按
+//! `schemas/patch-operations.schema.json` 描述的结构在解析阶段检查未知字段、类型错误、
+//! 重复条目、replace 与 delete 路径重叠以及逃逸出工作目录的路径，每个问题都带有
+//! JSON Pointer 位置，供清单校验和 `nuwax-cli patch lint` 使用。
+
+use crate::api_types::PatchOperations;
+use crate::safe_path::sanitize_relative_path;
+use serde_json::Value;
+use std::fmt;
+
+/// 补丁操作清单的 JSON Schema
+pub const PATCH_OPERATIONS_SCHEMA: &str = include_str!("../schemas/patch-operations.schema.json");
+
+const OPERATION_KEYS: [&str; 2] = ["replace", "delete"];
+const PATH_LIST_KEYS: [&str; 2] = ["files", "directories"];
+const ARCHITECTURES: [&str; 2] = ["x86_64", "aarch64"];
+
+/// 清单中的一个问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestIssue {
+    /// 问题所在位置（JSON Pointer，空字符串表示根节点）
+    pub pointer: String,
+    pub message: String,
+}
+
+impl ManifestIssue {
+    fn new(pointer: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            pointer: pointer.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ManifestIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}: {}", self.pointer, self.message)
+    }
+}
+
+/// 清单检查结果
+#[derive(Debug, Default)]
+pub struct ManifestLintReport {
+    /// 检查过的 operations 对象位置
+    pub operations: Vec<String>,
+    pub issues: Vec<ManifestIssue>,
+}
+
+impl ManifestLintReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// 检查补丁清单文件内容
+///
+/// 支持三种格式：operations 对象本身、包含 `operations` 的补丁包信息，
+/// 以及包含 `patch` / `patch_chain` 的服务清单
+pub fn lint_patch_manifest(content: &str) -> ManifestLintReport {
+    let mut report = ManifestLintReport::default();
+    let root: Value = match serde_json::from_str(content) {
+        Ok(root) => root,
+        Err(e) => {
+            report.issues.push(ManifestIssue::new(
+                "",
+                format!(
+                    "JSON 语法错误（第 {} 行第 {} 列）: {}",
+                    e.line(),
+                    e.column(),
+                    e
+                ),
+            ));
+            return report;
+        }
+    };
+
+    let located = locate_operations(&root);
+    if located.is_empty() && !has_operations_url(&root) {
+        report.issues.push(ManifestIssue::new(
+            "",
+            "服务清单中没有补丁操作（patch / patch_chain 中未定义 operations 或 operations_url）",
+        ));
+    }
+    for (pointer, operations) in located {
+        report.issues.extend(lint_operations(operations, &pointer));
+        report.operations.push(pointer);
+    }
+    report
+}
+
+/// 找出清单中所有需要检查的 operations 对象
+fn locate_operations(root: &Value) -> Vec<(String, &Value)> {
+    let mut located = Vec::new();
+    let Some(object) = root.as_object() else {
+        located.push((String::new(), root));
+        return located;
+    };
+
+    if let Some(operations) = object.get("operations") {
+        located.push(("/operations".to_string(), operations));
+    } else if object.contains_key("patch") || object.contains_key("patch_chain") {
+        if let Some(patch) = object.get("patch") {
+            locate_platform_operations(patch, "/patch", &mut located);
+        }
+        if let Some(chain) = object.get("patch_chain").and_then(Value::as_array) {
+            for (index, link) in chain.iter().enumerate() {
+                locate_platform_operations(link, &format!("/patch_chain/{index}"), &mut located);
+            }
+        }
+    } else {
+        located.push((String::new(), root));
+    }
+    located
+}
+
+/// 清单中是否有补丁通过 `operations_url` 引用单独获取的操作清单
+fn has_operations_url(root: &Value) -> bool {
+    let chain = root.get("patch_chain").and_then(Value::as_array);
+    root.get("patch")
+        .into_iter()
+        .chain(chain.into_iter().flatten())
+        .flat_map(|patch| ARCHITECTURES.iter().filter_map(move |arch| patch.get(arch)))
+        .any(|info| info.get("operations_url").is_some())
+}
+
+fn locate_platform_operations<'a>(
+    value: &'a Value,
+    pointer: &str,
+    located: &mut Vec<(String, &'a Value)>,
+) {
+    for arch in ARCHITECTURES {
+        if let Some(operations) = value.get(arch).and_then(|info| info.get("operations")) {
+            located.push((format!("{pointer}/{arch}/operations"), operations));
+        }
+    }
+}
+
+/// 检查单个 operations 对象，`pointer` 为该对象在清单中的位置
+pub fn lint_operations(value: &Value, pointer: &str) -> Vec<ManifestIssue> {
+    let mut issues = Vec::new();
+    let Some(object) = value.as_object() else {
+        issues.push(ManifestIssue::new(
+            pointer,
+            format!("应为对象，实际为 {}", type_name(value)),
+        ));
+        return issues;
+    };
+
+    for (key, operation) in object {
+        let operation_pointer = format!("{pointer}/{}", escape_pointer(key));
+        if !OPERATION_KEYS.contains(&key.as_str()) {
+            issues.push(ManifestIssue::new(
+                operation_pointer,
+                format!("未知字段 {key}（支持 replace、delete）"),
+            ));
+            continue;
+        }
+        if operation.is_null() {
+            continue;
+        }
+        let Some(lists) = operation.as_object() else {
+            issues.push(ManifestIssue::new(
+                operation_pointer,
+                format!("应为对象，实际为 {}", type_name(operation)),
+            ));
+            continue;
+        };
+
+        for (list_key, list) in lists {
+            let list_pointer = format!("{operation_pointer}/{}", escape_pointer(list_key));
+            if !PATH_LIST_KEYS.contains(&list_key.as_str()) {
+                issues.push(ManifestIssue::new(
+                    list_pointer,
+                    format!("未知字段 {list_key}（支持 files、directories）"),
+                ));
+                continue;
+            }
+            let Some(items) = list.as_array() else {
+                issues.push(ManifestIssue::new(
+                    list_pointer,
+                    format!("应为字符串数组，实际为 {}", type_name(list)),
+                ));
+                continue;
+            };
+            for (index, item) in items.iter().enumerate() {
+                if !item.is_string() {
+                    issues.push(ManifestIssue::new(
+                        format!("{list_pointer}/{index}"),
+                        format!("应为字符串，实际为 {}", type_name(item)),
+                    ));
+                }
+            }
+        }
+        for required in PATH_LIST_KEYS {
+            if !lists.contains_key(required) {
+                issues.push(ManifestIssue::new(
+                    operation_pointer.clone(),
+                    format!("缺少必填字段 {required}"),
+                ));
+            }
+        }
+    }
+    if !issues.is_empty() {
+        return issues;
+    }
+
+    match serde_json::from_value::<PatchOperations>(value.clone()) {
+        Ok(operations) => {
+            if operations.total_operations() == 0 {
+                issues.push(ManifestIssue::new(
+                    pointer,
+                    "补丁操作为空（replace 和 delete 中没有任何路径）",
+                ));
+            }
+            issues.extend(check_operations(&operations).into_iter().map(|issue| {
+                ManifestIssue::new(format!("{pointer}{}", issue.pointer), issue.message)
+            }));
+        }
+        Err(e) => issues.push(ManifestIssue::new(pointer, e.to_string())),
+    }
+    issues
+}
+
+/// 已解析的操作路径
+struct OperationPath<'a> {
+    pointer: String,
+    operation: &'static str,
+    raw: &'a str,
+    components: Vec<String>,
+}
+
+/// 检查已解析的补丁操作：空路径、逃逸出工作目录的路径、重复条目和路径重叠
+///
+/// 返回的问题位置相对于 operations 对象
+pub fn check_operations(operations: &PatchOperations) -> Vec<ManifestIssue> {
+    let mut issues = Vec::new();
+    let mut paths: Vec<OperationPath> = Vec::new();
+
+    for (operation, lists) in [
+        ("replace", &operations.replace),
+        ("delete", &operations.delete),
+    ] {
+        let Some(lists) = lists else {
+            continue;
+        };
+        for (kind, entries) in [("files", &lists.files), ("directories", &lists.directories)] {
+            for (index, raw) in entries.iter().enumerate() {
+                let pointer = format!("/{operation}/{kind}/{index}");
+                if raw.trim().is_empty() {
+                    issues.push(ManifestIssue::new(pointer, "路径不能为空"));
+                    continue;
+                }
+                match sanitize_relative_path(raw) {
+                    Ok(relative) if relative.as_os_str().is_empty() => {
+                        issues.push(ManifestIssue::new(
+                            pointer,
+                            format!("路径指向工作目录本身: {raw}"),
+                        ));
+                    }
+                    Ok(relative) => paths.push(OperationPath {
+                        pointer,
+                        operation,
+                        raw,
+                        components: relative
+                            .components()
+                            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                            .collect(),
+                    }),
+                    Err(e) => {
+                        issues.push(ManifestIssue::new(
+                            pointer,
+                            format!("路径逃逸出工作目录: {e}"),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    for (index, path) in paths.iter().enumerate() {
+        let conflict = paths[..index].iter().find(|other| {
+            path.components.starts_with(&other.components)
+                || other.components.starts_with(&path.components)
+        });
+        let Some(other) = conflict else {
+            continue;
+        };
+
+        let message = if path.components != other.components {
+            format!(
+                "路径重叠: {} 与 {} ({}) 存在包含关系",
+                path.raw, other.pointer, other.raw
+            )
+        } else if path.operation == other.operation {
+            format!("重复条目: 与 {} ({}) 相同", other.pointer, other.raw)
+        } else {
+            format!(
+                "replace 与 delete 路径重叠: 与 {} ({}) 相同",
+                other.pointer, other.raw
+            )
+        };
+        issues.push(ManifestIssue::new(path.pointer.clone(), message));
+    }
+
+    issues
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "布尔值",
+        Value::Number(_) => "数字",
+        Value::String(_) => "字符串",
+        Value::Array(_) => "数组",
+        Value::Object(_) => "对象",
+    }
+}
+
+/// 按 RFC 6901 转义 JSON Pointer 中的键
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pointers(report: &ManifestLintReport) -> Vec<&str> {
+        report
+            .issues
+            .iter()
+            .map(|issue| issue.pointer.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_valid_operations() {
+        let report = lint_patch_manifest(
+            r#"{
+                "replace": {"files": ["app/app.jar"], "directories": ["plugins/"]},
+                "delete": {"files": [], "directories": ["temp/cache"]}
+            }"#,
+        );
+        assert!(report.is_valid(), "{:?}", report.issues);
+        assert_eq!(report.operations, [""]);
+        assert!(serde_json::from_str::<Value>(PATCH_OPERATIONS_SCHEMA).is_ok());
+    }
+
+    #[test]
+    fn test_lint_reports_structure_errors() {
+        let report = lint_patch_manifest(
+            r#"{
+                "replace": {"files": ["a.txt", 3], "directory": []},
+                "remove": {"files": [], "directories": []}
+            }"#,
+        );
+        let mut found = pointers(&report);
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                "/remove",
+                "/replace",
+                "/replace/directory",
+                "/replace/files/1"
+            ]
+        );
+        assert!(
+            report
+                .issues
+                .iter()
+                .any(|issue| issue.pointer == "/replace" && issue.message.contains("directories"))
+        );
+
+        let report = lint_patch_manifest("{\n  \"replace\": {,\n}");
+        assert!(report.issues[0].message.contains("第 2 行"));
+    }
+
+    #[test]
+    fn test_lint_reports_path_errors() {
+        let report = lint_patch_manifest(
+            r#"{
+                "operations": {
+                    "replace": {
+                        "files": ["app/app.jar", "./app/app.jar", "config/app.yml", "../etc/passwd"],
+                        "directories": ["plugins"]
+                    },
+                    "delete": {"files": ["plugins/old.jar", "config/app.yml"], "directories": ["/var/lib"]}
+                }
+            }"#,
+        );
+        assert_eq!(
+            pointers(&report),
+            [
+                "/operations/replace/files/3",
+                "/operations/delete/directories/0",
+                "/operations/replace/files/1",
+                "/operations/delete/files/0",
+                "/operations/delete/files/1",
+            ]
+        );
+        assert!(report.issues[2].message.starts_with("重复条目"));
+        assert!(report.issues[3].message.starts_with("路径重叠"));
+        assert!(report.issues[4].message.starts_with("replace 与 delete"));
+    }
+
+    #[test]
+    fn test_lint_service_manifest() {
+        let report = lint_patch_manifest(
+            r#"{
+                "version": "1.0.2",
+                "patch": {"x86_64": {"url": "/p.tar.gz", "operations": {"replace": {"files": ["a"], "directories": []}}}},
+                "patch_chain": [
+                    {"from_version": "1.0.0", "to_version": "1.0.1",
+                     "aarch64": {"url": "/c.tar.gz", "operations": {"delete": {"files": [], "directories": []}}}}
+                ]
+            }"#,
+        );
+        assert_eq!(
+            report.operations,
+            [
+                "/patch/x86_64/operations",
+                "/patch_chain/0/aarch64/operations"
+            ]
+        );
+        assert_eq!(pointers(&report), ["/patch_chain/0/aarch64/operations"]);
+    }
+}
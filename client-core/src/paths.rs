@@ -0,0 +1,316 @@
+//! # 路径解析
+//!
+//! 统一解析客户端使用的目录和文件，每个路径都记录来源，`nuwax-cli paths` 据此显示实际布局。
+//! 优先级为：命令行参数 > 环境变量 > config.toml > 默认值。
+//!
+//! Docker 工作目录、备份目录、缓存目录和 `temp_sql` 属于部署本身，默认位于当前目录下，
+//! 与已有部署的布局一致；日志目录默认使用系统的用户目录：Linux 为
+//! `$XDG_STATE_HOME/nuwax-cli/logs`（未设置时为 `~/.local/state/nuwax-cli/logs`），
+//! macOS 为 `~/Library/Logs/nuwax-cli`，Windows 为 `%APPDATA%\nuwax-cli\logs`。
+
+use crate::config::AppConfig;
+use crate::constants::{backup, config, docker, legacy, logging, upgrade};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// 配置文件环境变量
+pub const CONFIG_FILE_ENV: &str = "NUWAX_CONFIG";
+
+/// Docker 工作目录环境变量
+pub const DOCKER_DIR_ENV: &str = "NUWAX_DOCKER_DIR";
+
+/// 备份目录环境变量，覆盖 `backup.storage_dir`
+pub const BACKUP_DIR_ENV: &str = "NUWAX_BACKUP_DIR";
+
+/// 缓存目录环境变量，覆盖 `cache.cache_dir`，下载目录随之改为其中的 `download`
+pub const CACHE_DIR_ENV: &str = "NUWAX_CACHE_DIR";
+
+/// 升级 SQL 工作目录环境变量
+pub const TEMP_SQL_DIR_ENV: &str = "NUWAX_TEMP_SQL_DIR";
+
+/// 日志目录环境变量
+pub const LOG_DIR_ENV: &str = "NUWAX_LOG_DIR";
+
+/// 未指定 `--config` 时使用的配置文件
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// 用户目录下的应用目录名
+const APP_DIR_NAME: &str = "nuwax-cli";
+
+/// 路径的来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSource {
+    CommandLine,
+    /// 环境变量名
+    Env(String),
+    Config,
+    Default,
+}
+
+impl fmt::Display for PathSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSource::CommandLine => write!(f, "命令行参数"),
+            PathSource::Env(name) => write!(f, "环境变量 {name}"),
+            PathSource::Config => write!(f, "config.toml"),
+            PathSource::Default => write!(f, "默认值"),
+        }
+    }
+}
+
+/// 解析后的路径及其来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedPath {
+    pub path: PathBuf,
+    pub source: PathSource,
+}
+
+impl ResolvedPath {
+    fn new(path: impl Into<PathBuf>, source: PathSource) -> Self {
+        Self {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+/// 命令行参数中指定的路径
+#[derive(Debug, Clone, Default)]
+pub struct PathArgs {
+    /// `--work-dir`
+    pub work_dir: Option<PathBuf>,
+    /// `--log-file`
+    pub log_file: Option<PathBuf>,
+}
+
+/// 客户端使用的全部路径
+#[derive(Debug, Clone)]
+pub struct Paths {
+    pub config_file: ResolvedPath,
+    pub docker_dir: ResolvedPath,
+    pub backup_dir: ResolvedPath,
+    pub cache_dir: ResolvedPath,
+    pub download_dir: ResolvedPath,
+    pub temp_sql_dir: ResolvedPath,
+    pub log_dir: ResolvedPath,
+    /// 日志文件，未指定时日志只输出到终端
+    pub log_file: Option<ResolvedPath>,
+}
+
+impl Paths {
+    /// 按命令行参数、环境变量和配置文件（不存在时为 None）解析全部路径
+    ///
+    /// 配置文件路径需要在读取配置前确定，由 [`resolve_config_file`] 单独解析
+    pub fn resolve(
+        config_file: ResolvedPath,
+        args: &PathArgs,
+        app_config: Option<&AppConfig>,
+    ) -> Self {
+        Self::resolve_with_env(config_file, args, app_config, |name| {
+            std::env::var(name).ok()
+        })
+    }
+
+    fn resolve_with_env(
+        config_file: ResolvedPath,
+        args: &PathArgs,
+        app_config: Option<&AppConfig>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let from_env = |name: &str| from_env(&env, name);
+        let from_args = |path: &Option<PathBuf>| from_args(path.as_deref());
+        let from_config = |path: Option<&str>| {
+            path.filter(|path| !path.is_empty())
+                .map(|path| ResolvedPath::new(path, PathSource::Config))
+        };
+
+        let docker_dir = from_args(&args.work_dir)
+            .or_else(|| from_env(DOCKER_DIR_ENV))
+            .or_else(|| from_config(app_config.and_then(|c| c.docker.work_dir.as_deref())))
+            .unwrap_or_else(|| {
+                ResolvedPath::new(
+                    Path::new(".").join(docker::DOCKER_DIR_NAME),
+                    PathSource::Default,
+                )
+            });
+        let backup_dir = from_env(BACKUP_DIR_ENV)
+            .or_else(|| from_config(app_config.map(|c| c.backup.storage_dir.as_str())))
+            .unwrap_or_else(|| {
+                ResolvedPath::new(backup::get_default_storage_dir(), PathSource::Default)
+            });
+        let cache_override = from_env(CACHE_DIR_ENV);
+        let download_dir = match &cache_override {
+            Some(cache) => ResolvedPath::new(
+                cache.path.join(config::DOWNLOAD_DIR_NAME),
+                cache.source.clone(),
+            ),
+            None => from_config(app_config.map(|c| c.cache.download_dir.as_str())).unwrap_or_else(
+                || ResolvedPath::new(config::get_default_download_dir(), PathSource::Default),
+            ),
+        };
+        let cache_dir = cache_override
+            .or_else(|| from_config(app_config.map(|c| c.cache.cache_dir.as_str())))
+            .unwrap_or_else(|| {
+                ResolvedPath::new(config::get_default_cache_dir(), PathSource::Default)
+            });
+        let temp_sql_dir = from_env(TEMP_SQL_DIR_ENV)
+            .unwrap_or_else(|| ResolvedPath::new(upgrade::TEMP_SQL_DIR_NAME, PathSource::Default));
+        let log_dir = from_env(LOG_DIR_ENV)
+            .unwrap_or_else(|| ResolvedPath::new(default_log_dir(), PathSource::Default));
+        let log_file = from_args(&args.log_file)
+            .or_else(|| from_env(&format!("{}LOG_FILE", logging::ENV_PREFIX)))
+            .or_else(|| from_env(&format!("{}LOG_FILE", legacy::ENV_PREFIX)));
+
+        Self {
+            config_file,
+            docker_dir,
+            backup_dir,
+            cache_dir,
+            download_dir,
+            temp_sql_dir,
+            log_dir,
+            log_file,
+        }
+    }
+
+    /// `--debug-http` 未指定文件时的 HTTP 调试记录文件
+    pub fn http_debug_log_path(&self) -> PathBuf {
+        self.log_dir.path.join(logging::HTTP_DEBUG_LOG_FILE_NAME)
+    }
+
+    /// 按显示顺序列出名称和路径
+    pub fn entries(&self) -> Vec<(&'static str, Option<&ResolvedPath>)> {
+        vec![
+            ("配置文件", Some(&self.config_file)),
+            ("Docker 工作目录", Some(&self.docker_dir)),
+            ("备份目录", Some(&self.backup_dir)),
+            ("缓存目录", Some(&self.cache_dir)),
+            ("下载目录", Some(&self.download_dir)),
+            ("升级 SQL 目录", Some(&self.temp_sql_dir)),
+            ("日志目录", Some(&self.log_dir)),
+            ("日志文件", self.log_file.as_ref()),
+        ]
+    }
+}
+
+/// 解析配置文件路径：`--config` 优先，其次为 `NUWAX_CONFIG`，默认为当前目录下的 config.toml
+pub fn resolve_config_file(arg: Option<&Path>) -> ResolvedPath {
+    resolve_config_file_with_env(arg, |name| std::env::var(name).ok())
+}
+
+fn resolve_config_file_with_env(
+    arg: Option<&Path>,
+    env: impl Fn(&str) -> Option<String>,
+) -> ResolvedPath {
+    from_args(arg)
+        .or_else(|| from_env(&env, CONFIG_FILE_ENV))
+        .unwrap_or_else(|| ResolvedPath::new(DEFAULT_CONFIG_FILE, PathSource::Default))
+}
+
+fn from_args(path: Option<&Path>) -> Option<ResolvedPath> {
+    path.map(|path| ResolvedPath::new(path, PathSource::CommandLine))
+}
+
+/// 空值视为未设置
+fn from_env(env: &impl Fn(&str) -> Option<String>, name: &str) -> Option<ResolvedPath> {
+    env(name)
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| ResolvedPath::new(value, PathSource::Env(name.to_string())))
+}
+
+/// 读取路径环境变量，未设置或为空时返回 None
+pub fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// 升级 SQL 工作目录：`NUWAX_TEMP_SQL_DIR`，未设置时为当前目录下的 `temp_sql`
+pub fn temp_sql_dir() -> PathBuf {
+    env_path(TEMP_SQL_DIR_ENV).unwrap_or_else(|| PathBuf::from(upgrade::TEMP_SQL_DIR_NAME))
+}
+
+/// 当前系统的默认日志目录，无法确定用户目录时使用 `./data/logs`
+pub fn default_log_dir() -> PathBuf {
+    #[cfg(target_os = "macos")]
+    let dir = dirs::home_dir().map(|home| home.join("Library").join("Logs").join(APP_DIR_NAME));
+    #[cfg(windows)]
+    let dir = dirs::data_dir().map(|dir| dir.join(APP_DIR_NAME).join(logging::LOG_DIR_NAME));
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let dir = dirs::state_dir().map(|dir| dir.join(APP_DIR_NAME).join(logging::LOG_DIR_NAME));
+    dir.unwrap_or_else(logging::get_log_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_resolve_precedence() {
+        let mut app_config = AppConfig::default();
+        app_config.docker.work_dir = Some("/srv/nuwax/docker".to_string());
+        app_config.backup.storage_dir = "/srv/nuwax/backups".to_string();
+        let env: HashMap<&str, &str> = [
+            (DOCKER_DIR_ENV, "/env/docker"),
+            (CACHE_DIR_ENV, "/env/cache"),
+            (TEMP_SQL_DIR_ENV, ""),
+            ("NUWAX_LOG_FILE", "/var/log/nuwax.log"),
+        ]
+        .into_iter()
+        .collect();
+        let env = |name: &str| env.get(name).map(|value| value.to_string());
+
+        let config_file = resolve_config_file_with_env(None, env);
+        assert_eq!(config_file.source, PathSource::Default);
+
+        // 环境变量优先于配置文件，空值视为未设置
+        let paths =
+            Paths::resolve_with_env(config_file, &PathArgs::default(), Some(&app_config), env);
+        assert_eq!(paths.docker_dir.path, PathBuf::from("/env/docker"));
+        assert_eq!(
+            paths.docker_dir.source,
+            PathSource::Env(DOCKER_DIR_ENV.to_string())
+        );
+        assert_eq!(paths.backup_dir.path, PathBuf::from("/srv/nuwax/backups"));
+        assert_eq!(paths.backup_dir.source, PathSource::Config);
+        assert_eq!(
+            paths.download_dir.path,
+            PathBuf::from("/env/cache/download")
+        );
+        assert_eq!(paths.temp_sql_dir.source, PathSource::Default);
+        assert_eq!(
+            paths.log_file.unwrap().path,
+            PathBuf::from("/var/log/nuwax.log")
+        );
+
+        // 命令行参数优先于环境变量
+        let config_env =
+            |name: &str| (name == CONFIG_FILE_ENV).then(|| "/env/config.toml".to_string());
+        assert_eq!(
+            resolve_config_file_with_env(None, config_env).path,
+            PathBuf::from("/env/config.toml")
+        );
+        let config_file =
+            resolve_config_file_with_env(Some(Path::new("/etc/nuwax/config.toml")), config_env);
+        assert_eq!(config_file.path, PathBuf::from("/etc/nuwax/config.toml"));
+        let args = PathArgs {
+            work_dir: Some(PathBuf::from("/cli/docker")),
+            log_file: Some(PathBuf::from("cli.log")),
+        };
+        let paths = Paths::resolve_with_env(config_file, &args, Some(&app_config), env);
+        assert_eq!(paths.docker_dir.path, PathBuf::from("/cli/docker"));
+        assert_eq!(paths.docker_dir.source, PathSource::CommandLine);
+        assert_eq!(paths.config_file.source, PathSource::CommandLine);
+        assert_eq!(paths.log_file.unwrap().path, PathBuf::from("cli.log"));
+
+        // 没有配置文件和环境变量时使用默认值
+        let config_file = resolve_config_file_with_env(None, |_| None);
+        let paths = Paths::resolve_with_env(config_file, &PathArgs::default(), None, |_| None);
+        assert_eq!(paths.docker_dir.path, Path::new(".").join("docker"));
+        assert_eq!(paths.backup_dir.path, backup::get_default_storage_dir());
+        assert_eq!(paths.cache_dir.path, config::get_default_cache_dir());
+        assert_eq!(paths.temp_sql_dir.path, PathBuf::from("temp_sql"));
+        assert!(paths.log_file.is_none());
+    }
+}
@@ -0,0 +1,189 @@
+//! # 升级流水线总体进度
+//!
+//! 升级部署由下载、备份、解压、部署、验证几个阶段组成，各阶段按 [`PipelinePhase::weight`]
+//! 计入总体进度。流水线用 `upgrade` 操作的阶段事件标记阶段开始和完成，
+//! 阶段内的进度来自下载器、备份、解压等组件已有的进度事件。
+//!
+//! [`PipelineProgress`] 不发送事件，只根据观察到的事件计算总体进度，
+//! 总体百分比（取整后）变化时返回一个 [`OperationEvent::PipelineProgress`]。
+
+use crate::events::{OperationEvent, OperationKind, PipelinePhase};
+use std::collections::HashSet;
+
+/// 根据操作事件汇总升级流水线的总体进度
+#[derive(Debug, Default)]
+pub struct PipelineProgress {
+    completed: HashSet<PipelinePhase>,
+    current: Option<PipelinePhase>,
+    /// 当前阶段已完成的比例（0.0 ~ 1.0）
+    phase_fraction: f64,
+    /// 最近一次进度事件所属的阶段
+    last_phase: Option<PipelinePhase>,
+    last_percent: Option<u32>,
+}
+
+impl PipelineProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 总体进度百分比
+    pub fn percent(&self) -> f64 {
+        let completed: u32 = self.completed.iter().map(PipelinePhase::weight).sum();
+        let current = match self.current {
+            Some(phase) if !self.completed.contains(&phase) => {
+                phase.weight() as f64 * self.phase_fraction
+            }
+            _ => 0.0,
+        };
+        (completed as f64 + current).min(100.0)
+    }
+
+    /// 处理一个事件，总体百分比变化时返回总体进度事件
+    pub fn observe(&mut self, event: &OperationEvent) -> Option<OperationEvent> {
+        let phase = match event {
+            OperationEvent::PhaseStarted {
+                operation: OperationKind::Upgrade,
+                phase,
+            } => {
+                let phase = phase.parse::<PipelinePhase>().ok()?;
+                self.current = Some(phase);
+                self.phase_fraction = 0.0;
+                phase
+            }
+            OperationEvent::PhaseCompleted {
+                operation: OperationKind::Upgrade,
+                phase,
+            } => {
+                let phase = phase.parse::<PipelinePhase>().ok()?;
+                self.completed.insert(phase);
+                if self.current == Some(phase) {
+                    self.phase_fraction = 1.0;
+                }
+                phase
+            }
+            OperationEvent::Progress { operation, .. } => {
+                let phase = PipelinePhase::for_operation(*operation)?;
+                if self.current != Some(phase) || self.completed.contains(&phase) {
+                    return None;
+                }
+                let fraction = event.percentage()? / 100.0;
+                // 同一阶段可能有多个文件，进度只增不减
+                self.phase_fraction = self.phase_fraction.max(fraction);
+                phase
+            }
+            _ => return None,
+        };
+
+        let percent = self.percent();
+        let rounded = percent.floor() as u32;
+        if self.last_percent == Some(rounded) && self.last_phase == Some(phase) {
+            return None;
+        }
+        self.last_percent = Some(rounded);
+        self.last_phase = Some(phase);
+        let phase_percent = if self.completed.contains(&phase) {
+            100.0
+        } else {
+            self.phase_fraction * 100.0
+        };
+        Some(OperationEvent::PipelineProgress {
+            phase,
+            phase_percent,
+            percent,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started(phase: PipelinePhase) -> OperationEvent {
+        OperationEvent::PhaseStarted {
+            operation: OperationKind::Upgrade,
+            phase: phase.as_str().to_string(),
+        }
+    }
+
+    fn completed(phase: PipelinePhase) -> OperationEvent {
+        OperationEvent::PhaseCompleted {
+            operation: OperationKind::Upgrade,
+            phase: phase.as_str().to_string(),
+        }
+    }
+
+    fn progress(operation: OperationKind, current: u64, total: u64) -> OperationEvent {
+        OperationEvent::Progress {
+            operation,
+            current,
+            total,
+        }
+    }
+
+    #[test]
+    fn test_weights_sum_to_100() {
+        let total: u32 = PipelinePhase::ALL.iter().map(PipelinePhase::weight).sum();
+        assert_eq!(total, 100);
+    }
+
+    #[test]
+    fn test_weighted_percent() {
+        let mut pipeline = PipelineProgress::new();
+        pipeline.observe(&started(PipelinePhase::Download));
+        let event = pipeline
+            .observe(&progress(OperationKind::Download, 50, 100))
+            .unwrap();
+        assert_eq!(event.percentage(), Some(20.0));
+
+        pipeline.observe(&completed(PipelinePhase::Download));
+        pipeline.observe(&started(PipelinePhase::Backup));
+        pipeline.observe(&progress(OperationKind::Backup, 1, 4));
+        assert_eq!(pipeline.percent(), 45.0);
+
+        // 不属于当前阶段的进度不计入
+        assert!(
+            pipeline
+                .observe(&progress(OperationKind::Extract, 9, 10))
+                .is_none()
+        );
+        // 进度不会回退
+        pipeline.observe(&progress(OperationKind::Backup, 0, 4));
+        assert_eq!(pipeline.percent(), 45.0);
+
+        for phase in PipelinePhase::ALL {
+            pipeline.observe(&completed(phase));
+        }
+        assert_eq!(pipeline.percent(), 100.0);
+    }
+
+    #[test]
+    fn test_emits_only_on_percent_change() {
+        let mut pipeline = PipelineProgress::new();
+        pipeline.observe(&started(PipelinePhase::Extract));
+        assert!(
+            pipeline
+                .observe(&progress(OperationKind::Extract, 1, 1000))
+                .is_none()
+        );
+        let event = pipeline
+            .observe(&progress(OperationKind::Patch, 250, 1000))
+            .unwrap();
+        assert_eq!(
+            event,
+            OperationEvent::PipelineProgress {
+                phase: PipelinePhase::Extract,
+                phase_percent: 25.0,
+                percent: 5.0,
+            }
+        );
+        assert!(
+            pipeline
+                .observe(&OperationEvent::Warning {
+                    operation: OperationKind::Extract,
+                    message: "locked".to_string(),
+                })
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! # PostgreSQL 差异SQL执行器
+//!
+//! 通过 compose 中数据库服务容器内的 `psql` 执行差异SQL，宿主机无需暴露端口或安装驱动。
+//! PostgreSQL 的 DDL 支持事务，整个差异SQL在单个事务中执行，任一语句失败时全部回滚。
+
+use crate::container::DockerManager;
+use crate::mysql_readiness::{ReadinessProgress, ready_delay};
+use crate::redact::SecretString;
+use crate::sql_diff::SqlDialect;
+use anyhow::{Context, Result, anyhow};
+use docker_compose_types as dct;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::debug;
+
+/// compose 文件中的默认 MySQL 服务名
+const DEFAULT_MYSQL_SERVICE: &str = "mysql";
+
+/// 按配置或 compose 文件中的数据库服务确定方言和服务名
+///
+/// 指定了 `configured` 时查找该方言的服务；未指定时自动判断，找不到数据库服务时按 MySQL 处理
+pub fn detect_database(
+    docker_manager: &DockerManager,
+    configured: Option<SqlDialect>,
+) -> Result<(SqlDialect, String)> {
+    let compose_config = docker_manager
+        .load_compose_config()
+        .context("无法加载 Docker Compose 配置")?;
+    let services = compose_config.services.0.iter().map(|(name, service)| {
+        let image = service.as_ref().and_then(|s| s.image.as_deref());
+        (name.as_str(), image)
+    });
+
+    match configured {
+        Some(dialect) => services
+            .filter(|(name, image)| SqlDialect::for_service(name, *image) == Some(dialect))
+            .map(|(name, _)| (dialect, name.to_string()))
+            .next()
+            .or_else(|| {
+                (dialect == SqlDialect::Mysql).then(|| (dialect, DEFAULT_MYSQL_SERVICE.to_string()))
+            })
+            .ok_or_else(|| anyhow!("在 docker-compose.yml 中未找到 {dialect} 数据库服务")),
+        None => Ok(SqlDialect::detect(services)
+            .unwrap_or_else(|| (SqlDialect::Mysql, DEFAULT_MYSQL_SERVICE.to_string()))),
+    }
+}
+
+/// PostgreSQL 连接配置
+#[derive(Debug, Clone)]
+pub struct PostgresConfig {
+    /// compose 中的数据库服务名
+    pub service: String,
+    pub user: String,
+    pub password: SecretString,
+    pub database: String,
+}
+
+impl PostgresConfig {
+    /// 从 docker-compose.yml 中数据库服务的 `POSTGRES_*` 环境变量读取配置
+    pub fn for_container(docker_manager: &DockerManager, service: &str) -> Result<Self> {
+        let compose_config = docker_manager
+            .load_compose_config()
+            .context("无法加载 Docker Compose 配置")?;
+        let postgres_service = compose_config
+            .services
+            .0
+            .get(service)
+            .and_then(|s| s.as_ref())
+            .ok_or_else(|| anyhow!("在 docker-compose.yml 中未找到 '{service}' 服务"))?;
+
+        let mut config_map = std::collections::HashMap::new();
+        if let dct::Environment::List(env_list) = &postgres_service.environment {
+            for item in env_list {
+                if let Some((key, value)) = item.split_once('=') {
+                    config_map.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+
+        let user = config_map
+            .get("POSTGRES_USER")
+            .cloned()
+            .unwrap_or_else(|| "postgres".to_string());
+        Ok(PostgresConfig {
+            service: service.to_string(),
+            password: config_map
+                .get("POSTGRES_PASSWORD")
+                .cloned()
+                .unwrap_or_default()
+                .into(),
+            // 未设置 POSTGRES_DB 时，官方镜像创建与用户同名的数据库
+            database: config_map
+                .get("POSTGRES_DB")
+                .cloned()
+                .unwrap_or_else(|| user.clone()),
+            user,
+        })
+    }
+}
+
+/// PostgreSQL 差异SQL执行器
+pub struct PostgresExecutor {
+    docker_manager: DockerManager,
+    config: PostgresConfig,
+}
+
+impl PostgresExecutor {
+    pub fn new(docker_manager: DockerManager, config: PostgresConfig) -> Self {
+        Self {
+            docker_manager,
+            config,
+        }
+    }
+
+    /// 连接配置
+    pub fn config(&self) -> &PostgresConfig {
+        &self.config
+    }
+
+    /// 测试数据库连接
+    pub async fn test_connection(&self) -> Result<()> {
+        self.psql(&["-c", "SELECT 1"], None).await?;
+        Ok(())
+    }
+
+    /// 等待数据库可以执行查询，返回等待的时间，退避策略与 MySQL 就绪等待相同
+    pub async fn wait_until_ready<F>(
+        &self,
+        max_wait: Duration,
+        mut on_progress: F,
+    ) -> Result<Duration>
+    where
+        F: FnMut(&ReadinessProgress),
+    {
+        let start = Instant::now();
+        let mut attempt = 0;
+        loop {
+            let reason = match self.test_connection().await {
+                Ok(()) => return Ok(start.elapsed()),
+                Err(e) => e.to_string(),
+            };
+
+            let elapsed = start.elapsed();
+            let remaining = max_wait.saturating_sub(elapsed);
+            if remaining.is_zero() {
+                return Err(anyhow!(
+                    "等待 PostgreSQL 就绪超时（{} 秒）: {reason}",
+                    max_wait.as_secs()
+                ));
+            }
+
+            let next_delay = ready_delay(attempt).min(remaining);
+            attempt += 1;
+            on_progress(&ReadinessProgress {
+                attempt,
+                elapsed,
+                next_delay,
+                reason,
+            });
+            tokio::time::sleep(next_delay).await;
+        }
+    }
+
+    /// 在单个事务中执行差异SQL，返回 psql 的输出
+    pub async fn execute_diff_sql(&self, sql: &str) -> Result<Vec<String>> {
+        let output = self
+            .psql(
+                &["-v", "ON_ERROR_STOP=1", "--single-transaction", "-f", "-"],
+                Some(sql),
+            )
+            .await
+            .map_err(|e| anyhow!("执行差异SQL失败，事务已回滚: {e}"))?;
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// 数据库服务当前运行的容器
+    async fn container_id(&self) -> Result<String> {
+        self.docker_manager
+            .get_service_container_id(&self.config.service)
+            .await?
+            .ok_or_else(|| anyhow!("服务 {} 没有运行中的容器", self.config.service))
+    }
+
+    /// 在容器内执行 psql，`stdin` 为要执行的SQL
+    async fn psql(&self, args: &[&str], stdin: Option<&str>) -> Result<String> {
+        let container = self.container_id().await?;
+        debug!("在容器 {} 中执行 psql: {:?}", container, args);
+
+        let mut child = Command::new("docker")
+            .args(["exec", "-i", "-e"])
+            .arg(format!("PGPASSWORD={}", self.config.password.expose()))
+            .args([container.as_str(), "psql", "-X", "-q"])
+            .args([
+                "-U",
+                self.config.user.as_str(),
+                "-d",
+                self.config.database.as_str(),
+            ])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("执行 docker exec 失败")?;
+
+        // 取出 stdin 后随即关闭，psql 读到 EOF 后退出
+        if let (Some(mut child_stdin), Some(sql)) = (child.stdin.take(), stdin) {
+            child_stdin.write_all(sql.as_bytes()).await?;
+        }
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_database_from_compose() {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let compose_path = std::path::Path::new(&manifest_dir).join("fixtures/docker-compose.yml");
+        let env_path = std::path::Path::new(&manifest_dir).join("fixtures/.env");
+        let docker_manager = DockerManager::new(compose_path, env_path).unwrap();
+
+        let (dialect, service) = detect_database(&docker_manager, None).unwrap();
+        assert_eq!(dialect, SqlDialect::Mysql);
+        assert_eq!(service, "mysql");
+
+        // 配置为 PostgreSQL 但 compose 中没有对应服务
+        assert!(detect_database(&docker_manager, Some(SqlDialect::Postgres)).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+//! 服务器限流（429 / Retry-After）处理
+//!
+//! 更新服务器会限制请求过于频繁的客户端，返回 `429 Too Many Requests`（或带 `Retry-After` 的 503）。
+//! 等待时间不长时自动退避重试；等待时间过长或重试次数用尽时返回 [`DuckError::RateLimited`]，
+//! 由调用方安排稍后重试，而不是直接失败
+
+use crate::constants::api::http;
+use crate::error::DuckError;
+use chrono::{DateTime, Local, Utc};
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::time::Duration;
+
+/// 响应是否表示被限流
+pub fn is_rate_limited(status: StatusCode, headers: &HeaderMap) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::SERVICE_UNAVAILABLE && headers.contains_key(RETRY_AFTER))
+}
+
+/// 解析 `Retry-After` 头：秒数或 HTTP 日期，日期已过去时返回 0
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let retry_at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (retry_at.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// 没有 `Retry-After` 时的指数退避时间
+pub fn backoff_delay(attempt: u32) -> Duration {
+    Duration::from_secs(http::RATE_LIMIT_BACKOFF_SECS.saturating_mul(1 << attempt.min(10)))
+}
+
+/// 第 `attempt` 次（从 0 开始）被限流后应等待的时间
+pub fn retry_delay(headers: &HeaderMap, attempt: u32, now: DateTime<Utc>) -> Duration {
+    headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_retry_after(value, now))
+        .unwrap_or_else(|| backoff_delay(attempt))
+}
+
+/// 是否在当前请求内等待后重试，否则返回限流错误交给调用方安排
+pub fn should_wait(delay: Duration, attempt: u32) -> bool {
+    attempt < http::RATE_LIMIT_MAX_RETRIES
+        && delay <= Duration::from_secs(http::RATE_LIMIT_MAX_WAIT_SECS)
+}
+
+/// 构造限流错误，`delay` 之后可以重试
+pub fn rate_limited_error(delay: Duration) -> DuckError {
+    let retry_at = Local::now()
+        + chrono::Duration::from_std(delay).unwrap_or_else(|_| chrono::Duration::zero());
+    DuckError::RateLimited { retry_at }
+}
+
+/// 错误为限流错误时返回可以重试的时间
+pub fn retry_at(error: &anyhow::Error) -> Option<DateTime<Local>> {
+    match error.downcast_ref::<DuckError>() {
+        Some(DuckError::RateLimited { retry_at }) => Some(*retry_at),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:30:00 GMT", now),
+            Some(Duration::from_secs(120))
+        );
+        // 已过去的时间立即重试
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_retry_delay_and_rate_limited() {
+        let mut headers = HeaderMap::new();
+        assert!(is_rate_limited(StatusCode::TOO_MANY_REQUESTS, &headers));
+        assert!(!is_rate_limited(StatusCode::SERVICE_UNAVAILABLE, &headers));
+        assert_eq!(retry_delay(&headers, 1, Utc::now()), backoff_delay(1));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("30"));
+        assert!(is_rate_limited(StatusCode::SERVICE_UNAVAILABLE, &headers));
+        assert_eq!(
+            retry_delay(&headers, 0, Utc::now()),
+            Duration::from_secs(30)
+        );
+
+        assert!(should_wait(Duration::from_secs(30), 0));
+        assert!(!should_wait(Duration::from_secs(3600), 0));
+        assert!(!should_wait(
+            Duration::from_secs(1),
+            http::RATE_LIMIT_MAX_RETRIES
+        ));
+    }
+}
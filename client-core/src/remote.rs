@@ -0,0 +1,338 @@
+//! # 远程主机管理
+//!
+//! 通过系统自带的 `ssh` / `scp` 客户端在远程主机上执行 nuwax-cli 命令和传输文件，
+//! 供运维人员在一台机器上集中管理多台部署：
+//! - 只使用非交互模式（`BatchMode=yes`），认证依赖 SSH 密钥或 ssh-agent
+//! - 远程命令的每个参数都经过 shell 转义
+//! - 远程输出按行回调，调用方可加上主机前缀后输出
+
+use anyhow::{Context, Result, anyhow};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+/// SSH 连接超时时间（秒）
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// SSH 目标：`[user@]host[:port]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshTarget {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl SshTarget {
+    /// ssh/scp 使用的目标地址（不含端口）
+    pub fn destination(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{user}@{}", self.host),
+            None => self.host.clone(),
+        }
+    }
+}
+
+impl FromStr for SshTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (user, rest) = match s.rsplit_once('@') {
+            Some((user, rest)) if !user.is_empty() => (Some(user.to_string()), rest),
+            Some(_) => return Err(anyhow!("SSH 目标缺少用户名: {s}")),
+            None => (None, s),
+        };
+
+        // IPv6 地址需写成 [::1]:22 的形式
+        let (host, port) = if let Some(stripped) = rest.strip_prefix('[') {
+            let (host, tail) = stripped
+                .split_once(']')
+                .ok_or_else(|| anyhow!("无效的 SSH 目标: {s}"))?;
+            let port = match tail.strip_prefix(':') {
+                Some(port) => Some(port),
+                None if tail.is_empty() => None,
+                None => return Err(anyhow!("无效的 SSH 目标: {s}")),
+            };
+            (host, port)
+        } else {
+            match rest.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (rest, None),
+            }
+        };
+
+        if host.is_empty() {
+            return Err(anyhow!("SSH 目标缺少主机名: {s}"));
+        }
+        let port = port
+            .map(|p| {
+                p.parse::<u16>()
+                    .map_err(|_| anyhow!("无效的 SSH 端口: {p}"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            user,
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl fmt::Display for SshTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.destination())?;
+        if let Some(port) = self.port {
+            write!(f, ":{port}")?;
+        }
+        Ok(())
+    }
+}
+
+/// 远程主机
+#[derive(Debug, Clone)]
+pub struct RemoteHost {
+    target: SshTarget,
+    identity_file: Option<PathBuf>,
+    connect_timeout: u64,
+}
+
+impl RemoteHost {
+    pub fn new(target: SshTarget) -> Self {
+        Self {
+            target,
+            identity_file: None,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT_SECS,
+        }
+    }
+
+    pub fn with_identity_file(mut self, identity_file: Option<PathBuf>) -> Self {
+        self.identity_file = identity_file;
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, seconds: u64) -> Self {
+        self.connect_timeout = seconds;
+        self
+    }
+
+    pub fn target(&self) -> &SshTarget {
+        &self.target
+    }
+
+    /// ssh 与 scp 共用的连接参数（端口参数名不同，由调用方追加）
+    fn common_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-o".to_string(),
+            "BatchMode=yes".to_string(),
+            "-o".to_string(),
+            format!("ConnectTimeout={}", self.connect_timeout),
+        ];
+        if let Some(identity_file) = &self.identity_file {
+            args.push("-i".to_string());
+            args.push(identity_file.display().to_string());
+        }
+        args
+    }
+
+    /// 执行远程命令的 ssh 参数
+    fn ssh_args(&self, remote_command: &str) -> Vec<String> {
+        let mut args = self.common_args();
+        if let Some(port) = self.target.port {
+            args.push("-p".to_string());
+            args.push(port.to_string());
+        }
+        args.push(self.target.destination());
+        args.push("--".to_string());
+        args.push(remote_command.to_string());
+        args
+    }
+
+    /// 传输文件的 scp 参数
+    fn scp_args(&self, from: String, to: String) -> Vec<String> {
+        let mut args = self.common_args();
+        if let Some(port) = self.target.port {
+            args.push("-P".to_string());
+            args.push(port.to_string());
+        }
+        args.push(from);
+        args.push(to);
+        args
+    }
+
+    fn remote_spec(&self, remote_path: &str) -> String {
+        format!("{}:{}", self.target.destination(), remote_path)
+    }
+
+    /// 在远程主机上执行命令，标准输出和标准错误按行回调，返回退出码
+    pub async fn run<F>(&self, remote_command: &str, mut on_line: F) -> Result<i32>
+    where
+        F: FnMut(&str),
+    {
+        let mut child = Command::new("ssh")
+            .args(self.ssh_args(remote_command))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("执行 ssh 失败，请确认已安装 OpenSSH 客户端")?;
+
+        let mut stdout =
+            BufReader::new(child.stdout.take().expect("stdout 已设置为 piped")).lines();
+        let mut stderr =
+            BufReader::new(child.stderr.take().expect("stderr 已设置为 piped")).lines();
+        let (mut stdout_done, mut stderr_done) = (false, false);
+        while !stdout_done || !stderr_done {
+            tokio::select! {
+                line = stdout.next_line(), if !stdout_done => match line? {
+                    Some(line) => on_line(&line),
+                    None => stdout_done = true,
+                },
+                line = stderr.next_line(), if !stderr_done => match line? {
+                    Some(line) => on_line(&line),
+                    None => stderr_done = true,
+                },
+            }
+        }
+
+        let status = child.wait().await?;
+        // ssh 自身出错（连接失败、认证失败）时退出码为 255
+        Ok(status.code().unwrap_or(-1))
+    }
+
+    /// 上传本地文件到远程路径
+    pub async fn upload(&self, local_path: &Path, remote_path: &str) -> Result<()> {
+        let args = self.scp_args(
+            local_path.display().to_string(),
+            self.remote_spec(remote_path),
+        );
+        run_scp(args).await
+    }
+
+    /// 下载远程文件到本地路径
+    pub async fn download(&self, remote_path: &str, local_path: &Path) -> Result<()> {
+        let args = self.scp_args(
+            self.remote_spec(remote_path),
+            local_path.display().to_string(),
+        );
+        run_scp(args).await
+    }
+}
+
+async fn run_scp(args: Vec<String>) -> Result<()> {
+    let output = Command::new("scp")
+        .args(&args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("执行 scp 失败，请确认已安装 OpenSSH 客户端")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "文件传输失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// 将参数转义为 POSIX shell 中的单个单词
+pub fn shell_quote(arg: &str) -> String {
+    let is_safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@,+%".contains(c));
+    if is_safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// 拼接远程命令：可选地先切换到 `work_dir`，再执行转义后的程序和参数
+pub fn build_remote_command(work_dir: Option<&str>, program: &str, args: &[&str]) -> String {
+    let mut command = String::new();
+    if let Some(dir) = work_dir {
+        command.push_str(&format!("cd {} && ", shell_quote(dir)));
+    }
+    command.push_str(&shell_quote(program));
+    for arg in args {
+        command.push(' ');
+        command.push_str(&shell_quote(arg));
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ssh_target() {
+        let target: SshTarget = "deploy@10.0.0.5:2222".parse().unwrap();
+        assert_eq!(target.user.as_deref(), Some("deploy"));
+        assert_eq!(target.host, "10.0.0.5");
+        assert_eq!(target.port, Some(2222));
+        assert_eq!(target.to_string(), "deploy@10.0.0.5:2222");
+
+        let target: SshTarget = "host-a".parse().unwrap();
+        assert_eq!(target.destination(), "host-a");
+        assert_eq!(target.port, None);
+
+        let target: SshTarget = "root@[::1]:22".parse().unwrap();
+        assert_eq!(target.host, "::1");
+        assert_eq!(target.port, Some(22));
+
+        assert!("@host".parse::<SshTarget>().is_err());
+        assert!("user@".parse::<SshTarget>().is_err());
+        assert!("user@host:ssh".parse::<SshTarget>().is_err());
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("docker-service"), "docker-service");
+        assert_eq!(shell_quote("/opt/nuwax"), "/opt/nuwax");
+        assert_eq!(shell_quote("my dir"), "'my dir'");
+        assert_eq!(shell_quote("a'b"), r"'a'\''b'");
+        assert_eq!(shell_quote("$(rm -rf /)"), "'$(rm -rf /)'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_build_remote_command_and_ssh_args() {
+        let command = build_remote_command(
+            Some("/opt/nuwax client"),
+            "nuwax-cli",
+            &["docker-service", "status"],
+        );
+        assert_eq!(
+            command,
+            "cd '/opt/nuwax client' && nuwax-cli docker-service status"
+        );
+
+        let host = RemoteHost::new("ops@host-a:2200".parse().unwrap())
+            .with_identity_file(Some(PathBuf::from("/keys/id_ed25519")));
+        let args = host.ssh_args(&command);
+        assert_eq!(
+            args,
+            vec![
+                "-o",
+                "BatchMode=yes",
+                "-o",
+                "ConnectTimeout=10",
+                "-i",
+                "/keys/id_ed25519",
+                "-p",
+                "2200",
+                "ops@host-a",
+                "--",
+                command.as_str(),
+            ]
+        );
+        assert_eq!(
+            host.scp_args("a.zip".to_string(), host.remote_spec("/tmp/a.zip"))[6..],
+            ["-P", "2200", "a.zip", "ops@host-a:/tmp/a.zip"]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! # 备份恢复预览
+//!
+//! `rollback --preview` 在确认恢复前列出恢复会带来的变化，不改动工作目录：
+//! - 将被备份中的版本覆盖的文件（含大小变化）
+//! - 当前存在但备份中没有的文件：恢复前会清空 data/、app/ 目录，这些文件会被删除
+//! - 保持不变的受保护路径：本次恢复排除的目录，以及备份中没有的用户数据目录和 `.env`
+//!
+//! 与实际恢复的规则一致：`_system/` 下的 CLI 自身状态只有 `--restore-system` 时才恢复，不计入预览。
+
+use crate::constants::backup::{RESTORE_CLEARED_DIR_NAMES, SYSTEM_BACKUP_DIR_NAME};
+use crate::upgrade_preview::is_protected_path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// 单个文件的变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoreFileChange {
+    /// 相对于工作目录的路径（使用 `/` 分隔）
+    pub path: String,
+    /// 当前文件大小，文件不存在时为 None
+    pub current_size: Option<u64>,
+    /// 备份中的文件大小，备份中没有时为 None
+    pub backup_size: Option<u64>,
+}
+
+impl RestoreFileChange {
+    /// 恢复后的大小变化（字节）
+    pub fn size_delta(&self) -> i64 {
+        self.backup_size.unwrap_or(0) as i64 - self.current_size.unwrap_or(0) as i64
+    }
+}
+
+/// 恢复预览结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestorePreview {
+    /// 将被覆盖的文件
+    pub overwritten: Vec<RestoreFileChange>,
+    /// 当前不存在、恢复后新增的文件
+    pub added: Vec<RestoreFileChange>,
+    /// 当前存在但备份中没有、恢复时被删除的文件
+    pub removed: Vec<RestoreFileChange>,
+    /// 恢复不会改动的受保护路径（顶层文件或目录）
+    pub kept: Vec<String>,
+}
+
+impl RestorePreview {
+    /// 根据备份中的文件（归档路径, 大小）与工作目录生成预览
+    ///
+    /// `dirs_to_exclude` 与恢复时排除的顶层目录一致（如不回滚数据时的 `data`）
+    pub fn build<I>(backup_files: I, work_dir: &Path, dirs_to_exclude: &[&str]) -> Self
+    where
+        I: IntoIterator<Item = (String, u64)>,
+    {
+        let is_excluded =
+            |first: &str| first == SYSTEM_BACKUP_DIR_NAME || dirs_to_exclude.contains(&first);
+
+        let backup_files: BTreeMap<String, u64> = backup_files
+            .into_iter()
+            .map(|(path, size)| (normalize(&path), size))
+            .filter(|(path, _)| !path.is_empty() && !is_excluded(top_level(path)))
+            .collect();
+        let backup_top_level: BTreeSet<&str> =
+            backup_files.keys().map(|path| top_level(path)).collect();
+
+        let mut preview = Self::default();
+        for (path, size) in &backup_files {
+            let current_size = file_size(&work_dir.join(path));
+            let change = RestoreFileChange {
+                path: path.clone(),
+                current_size,
+                backup_size: Some(*size),
+            };
+            match current_size {
+                Some(_) => preview.overwritten.push(change),
+                None => preview.added.push(change),
+            }
+        }
+
+        // 恢复前清空的目录中，备份里没有的文件会被删除
+        for dir in RESTORE_CLEARED_DIR_NAMES
+            .iter()
+            .filter(|dir| !dirs_to_exclude.contains(*dir))
+        {
+            for entry in WalkDir::new(work_dir.join(dir))
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| !entry.file_type().is_dir())
+            {
+                let Ok(relative) = entry.path().strip_prefix(work_dir) else {
+                    continue;
+                };
+                let path = normalize(&relative.to_string_lossy());
+                if !backup_files.contains_key(&path) {
+                    preview.removed.push(RestoreFileChange {
+                        path,
+                        current_size: entry.metadata().ok().map(|metadata| metadata.len()),
+                        backup_size: None,
+                    });
+                }
+            }
+        }
+        preview.removed.sort_by(|a, b| a.path.cmp(&b.path));
+
+        if let Ok(entries) = std::fs::read_dir(work_dir) {
+            let mut kept: Vec<String> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| {
+                    dirs_to_exclude.contains(&name.as_str())
+                        || (is_protected_path(name)
+                            && !RESTORE_CLEARED_DIR_NAMES.contains(&name.as_str())
+                            && !backup_top_level.contains(name.as_str()))
+                })
+                .collect();
+            kept.sort();
+            preview.kept = kept;
+        }
+
+        preview
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overwritten.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// 恢复后工作目录的总大小变化（字节）
+    pub fn total_size_delta(&self) -> i64 {
+        self.overwritten
+            .iter()
+            .chain(&self.added)
+            .chain(&self.removed)
+            .map(RestoreFileChange::size_delta)
+            .sum()
+    }
+}
+
+/// 统一为不带 `./` 和结尾斜杠、使用 `/` 分隔的相对路径
+fn normalize(path: &str) -> String {
+    path.replace('\\', "/")
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+fn top_level(path: &str) -> &str {
+    path.split('/').next().unwrap_or_default()
+}
+
+/// 文件或符号链接的大小，不存在或为目录时返回 None
+fn file_size(path: &Path) -> Option<u64> {
+    path.symlink_metadata()
+        .ok()
+        .filter(|metadata| !metadata.is_dir())
+        .map(|metadata| metadata.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_restore_preview() {
+        let temp = TempDir::new().unwrap();
+        let work = temp.path();
+        for (path, content) in [
+            ("app/main.js", "console.log(2);"),
+            ("app/new-feature.js", "feature"),
+            ("data/mysql/ibdata1", "current data"),
+            ("upload/logo.png", "png"),
+            (".env", "MYSQL_PASSWORD=secret"),
+            ("docker-compose.yml", "services: {}"),
+        ] {
+            let path = work.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let backup_files = [
+            ("app/main.js", 10),
+            ("app/legacy.js", 5),
+            ("./data/mysql/ibdata1", 100),
+            ("docker-compose.yml", 12),
+            ("_system/config.toml", 50),
+        ]
+        .map(|(path, size)| (path.to_string(), size));
+
+        // 不回滚数据时 data 目录保持不变
+        let preview = RestorePreview::build(backup_files.clone(), work, &["data"]);
+        let overwritten: Vec<(&str, i64)> = preview
+            .overwritten
+            .iter()
+            .map(|change| (change.path.as_str(), change.size_delta()))
+            .collect();
+        assert_eq!(
+            overwritten,
+            [("app/main.js", -5), ("docker-compose.yml", 0)]
+        );
+        let added: Vec<&str> = preview.added.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(added, ["app/legacy.js"]);
+        let removed: Vec<&str> = preview.removed.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(removed, ["app/new-feature.js"]);
+        assert_eq!(preview.kept, [".env", "data", "upload"]);
+        assert_eq!(preview.total_size_delta(), -7);
+
+        // 回滚数据时 data 目录中的文件也会被覆盖
+        let preview = RestorePreview::build(backup_files, work, &[]);
+        assert!(
+            preview
+                .overwritten
+                .iter()
+                .any(|change| change.path == "data/mysql/ibdata1" && change.size_delta() == 88)
+        );
+        assert_eq!(preview.kept, [".env", "upload"]);
+    }
+}
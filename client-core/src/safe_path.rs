@@ -0,0 +1,316 @@
+//! # 安全路径解析模块
+//!
+//! 为所有解压路径（服务包、补丁包、补丁清单）提供统一的路径校验，防止 zip-slip
+//! 与路径遍历攻击：
+//! - 拒绝包含 `..` 的条目
+//! - 拒绝绝对路径、盘符前缀（`C:`）和 UNC 路径
+//! - 拒绝经由已存在的符号链接逃逸出目标目录的路径
+//! - 拒绝指向目标目录之外的符号链接条目
+//!
+//! Windows 上解析出的路径带 `\\?\` 前缀，深层目录不受 260 字符的 `MAX_PATH` 限制。
+
+use crate::error::DuckError;
+use std::path::{Component, Path, PathBuf};
+
+/// 将归档条目名（或补丁清单中的相对路径）解析为目标目录下的安全路径
+///
+/// 条目名中的 `\` 会被视为分隔符，`.` 组件会被忽略；空条目名解析为 `base` 本身。
+/// 返回的路径经过 [`long_path`] 处理。
+pub fn resolve_entry_path(base: &Path, entry_name: &str) -> Result<PathBuf, DuckError> {
+    let relative = sanitize_relative_path(entry_name)?;
+    let base = long_path(base);
+    let resolved = base.join(&relative);
+    ensure_no_symlink_escape(&base, &resolved)?;
+    Ok(resolved)
+}
+
+/// 转换为不受 Windows `MAX_PATH` 限制的路径
+///
+/// Windows 上先转为绝对路径，再为盘符路径加上 `\\?\` 前缀、为 UNC 路径加上
+/// `\\?\UNC\` 前缀，`/`、`.` 和 `..` 在转换时解析掉（带前缀的路径不再做这些处理）。
+/// 已带前缀的路径以及其他平台上的路径原样返回。
+pub fn long_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        windows_long_path(path)
+    }
+    #[cfg(not(windows))]
+    {
+        path.to_path_buf()
+    }
+}
+
+#[cfg(windows)]
+fn windows_long_path(path: &Path) -> PathBuf {
+    use std::path::Prefix;
+
+    let Ok(absolute) = std::path::absolute(path) else {
+        return path.to_path_buf();
+    };
+    let mut components = absolute.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return absolute;
+    };
+    let mut long = match prefix.kind() {
+        Prefix::Disk(drive) => PathBuf::from(format!(r"\\?\{}:\", drive as char)),
+        Prefix::UNC(server, share) => PathBuf::from(format!(
+            r"\\?\UNC\{}\{}\",
+            server.to_string_lossy(),
+            share.to_string_lossy()
+        )),
+        // 已经是 `\\?\` 或设备路径
+        _ => return absolute,
+    };
+    for component in components {
+        match component {
+            Component::Normal(part) => long.push(part),
+            Component::ParentDir => {
+                long.pop();
+            }
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+    long
+}
+
+/// 对条目名做纯词法的安全检查，返回规范化后的相对路径
+pub fn sanitize_relative_path(entry_name: &str) -> Result<PathBuf, DuckError> {
+    let normalized = entry_name.replace('\\', "/");
+
+    if normalized.starts_with('/') || has_drive_prefix(&normalized) {
+        return Err(DuckError::UnsafePath(format!("绝对路径: {entry_name}")));
+    }
+
+    let mut relative = PathBuf::new();
+    for component in Path::new(&normalized).components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                return Err(DuckError::UnsafePath(format!(
+                    "路径包含 '..': {entry_name}"
+                )));
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(DuckError::UnsafePath(format!("绝对路径: {entry_name}")));
+            }
+        }
+    }
+
+    Ok(relative)
+}
+
+/// 校验符号链接条目的目标不会指向目标目录之外
+///
+/// `link_path` 为链接本身在磁盘上的位置（必须已位于 `base` 之下），
+/// `link_target` 为归档中记录的链接目标。
+pub fn validate_symlink_target(
+    base: &Path,
+    link_path: &Path,
+    link_target: &Path,
+) -> Result<(), DuckError> {
+    let target_str = link_target.to_string_lossy().replace('\\', "/");
+    if target_str.starts_with('/') || has_drive_prefix(&target_str) {
+        return Err(DuckError::UnsafePath(format!(
+            "符号链接指向绝对路径: {} -> {}",
+            link_path.display(),
+            link_target.display()
+        )));
+    }
+
+    let link_parent = link_path
+        .parent()
+        .and_then(|p| p.strip_prefix(base).ok())
+        .ok_or_else(|| {
+            DuckError::UnsafePath(format!("符号链接不在目标目录内: {}", link_path.display()))
+        })?;
+
+    // 以 base 为根做词法解析，深度小于 0 即表示逃逸
+    let mut depth: usize = link_parent
+        .components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .count();
+    for component in Path::new(&target_str).components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir => {
+                depth = depth.checked_sub(1).ok_or_else(|| {
+                    DuckError::UnsafePath(format!(
+                        "符号链接指向目标目录之外: {} -> {}",
+                        link_path.display(),
+                        link_target.display()
+                    ))
+                })?;
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(DuckError::UnsafePath(format!(
+                    "符号链接指向绝对路径: {} -> {}",
+                    link_path.display(),
+                    link_target.display()
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 检查 `base` 与 `path` 之间已存在的祖先目录中，是否有符号链接指向 `base` 之外
+fn ensure_no_symlink_escape(base: &Path, path: &Path) -> Result<(), DuckError> {
+    let Ok(relative) = path.strip_prefix(base) else {
+        return Err(DuckError::UnsafePath(format!(
+            "路径不在目标目录内: {}",
+            path.display()
+        )));
+    };
+
+    // 目标目录尚不存在时，其下不可能已有符号链接
+    let Ok(canonical_base) = base.canonicalize() else {
+        return Ok(());
+    };
+
+    let mut current = base.to_path_buf();
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        current.push(component);
+        // 最后一个组件是将被覆盖写入的条目本身，由调用方先删除再写入
+        if components.peek().is_none() {
+            break;
+        }
+        let Ok(metadata) = std::fs::symlink_metadata(&current) else {
+            break;
+        };
+        if metadata.file_type().is_symlink() {
+            let escapes = current
+                .canonicalize()
+                .map(|resolved| !resolved.starts_with(&canonical_base))
+                .unwrap_or(true);
+            if escapes {
+                return Err(DuckError::UnsafePath(format!(
+                    "路径经由符号链接逃逸出目标目录: {}",
+                    current.display()
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 是否为 Windows 盘符前缀（如 `C:`、`c:/`）
+fn has_drive_prefix(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_normal_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = long_path(temp_dir.path());
+        let base = base.as_path();
+
+        assert_eq!(
+            resolve_entry_path(base, "docker/docker-compose.yml").unwrap(),
+            base.join("docker").join("docker-compose.yml")
+        );
+        assert_eq!(
+            resolve_entry_path(base, "./config/app.toml").unwrap(),
+            base.join("config").join("app.toml")
+        );
+        assert_eq!(
+            resolve_entry_path(base, "config\\nginx\\default.conf").unwrap(),
+            base.join("config").join("nginx").join("default.conf")
+        );
+        assert_eq!(resolve_entry_path(base, "").unwrap(), base.to_path_buf());
+    }
+
+    #[test]
+    fn test_long_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let long = long_path(temp_dir.path());
+        assert_eq!(long_path(&long), long);
+
+        #[cfg(windows)]
+        {
+            assert!(long.to_string_lossy().starts_with(r"\\?\"));
+            assert_eq!(
+                long_path(Path::new("C:/work/./docker/../nuwax")),
+                PathBuf::from(r"\\?\C:\work\nuwax")
+            );
+            assert_eq!(
+                long_path(Path::new(r"\\server\share\docker")),
+                PathBuf::from(r"\\?\UNC\server\share\docker")
+            );
+        }
+        #[cfg(not(windows))]
+        assert_eq!(long, temp_dir.path());
+    }
+
+    #[test]
+    fn test_resolve_deep_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let segment = "a".repeat(50);
+        let entry = format!("{}/file.txt", vec![segment.as_str(); 8].join("/"));
+
+        // 超过 Windows MAX_PATH 的路径也能正常写入和读取
+        let path = resolve_entry_path(temp_dir.path(), &entry).unwrap();
+        assert!(path.as_os_str().len() > 260);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "ok").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ok");
+    }
+
+    #[test]
+    fn test_reject_parent_dir_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+
+        assert!(resolve_entry_path(base, "../evil.sh").is_err());
+        assert!(resolve_entry_path(base, "docker/../../evil.sh").is_err());
+        assert!(resolve_entry_path(base, "docker\\..\\..\\evil.sh").is_err());
+    }
+
+    #[test]
+    fn test_reject_absolute_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+
+        assert!(resolve_entry_path(base, "/etc/passwd").is_err());
+        assert!(resolve_entry_path(base, "\\Windows\\System32\\evil.dll").is_err());
+        assert!(resolve_entry_path(base, "C:/Windows/evil.dll").is_err());
+        assert!(resolve_entry_path(base, "c:evil.dll").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reject_write_through_escaping_symlink() {
+        let temp_dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let base = temp_dir.path();
+
+        std::os::unix::fs::symlink(outside.path(), base.join("link")).unwrap();
+        assert!(resolve_entry_path(base, "link/evil.sh").is_err());
+
+        // 指向目录内部的符号链接是允许的
+        std::fs::create_dir(base.join("real")).unwrap();
+        std::os::unix::fs::symlink(base.join("real"), base.join("inner")).unwrap();
+        assert!(resolve_entry_path(base, "inner/ok.txt").is_ok());
+    }
+
+    #[test]
+    fn test_validate_symlink_target() {
+        let base = Path::new("/srv/docker");
+        let link = base.join("config").join("current");
+
+        assert!(validate_symlink_target(base, &link, Path::new("v1")).is_ok());
+        assert!(validate_symlink_target(base, &link, Path::new("../shared/v1")).is_ok());
+        assert!(validate_symlink_target(base, &link, Path::new("../../etc")).is_err());
+        assert!(validate_symlink_target(base, &link, Path::new("/etc/passwd")).is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! # 安装自检
+//!
+//! `nuwax-cli self-test` 在临时目录中把各核心子系统完整走一遍，用于验证安装包，
+//! 以及在少见平台上构建的二进制是否可用：
+//!
+//! - 备份：归档示例文件后解压到另一个目录，比对文件内容
+//! - 增量补丁：生成补丁包并校验哈希、解压，替换和删除文件后回滚
+//! - SQL 差异：对内置的新旧初始化脚本生成差异 SQL
+//! - 本地数据库：新建数据库、初始化表结构并读写配置项
+//! - Docker：探测 Docker 服务是否可用
+//!
+//! 自检不读取 `config.toml`，也不改动工作目录、本地数据库和正在运行的服务。
+
+use crate::api_types::{PatchOperations, PatchPackageInfo, ReplaceOperations};
+use crate::backup::{collect_backup_entries, extract_backup_archive, write_backup_archive};
+use crate::backup_permissions::OwnershipMapping;
+use crate::container::DockerManager;
+use crate::database::Database;
+use crate::events::EventSender;
+use crate::file_hash::sha256_file_uncached;
+use crate::patch_executor::{FileOperationExecutor, PatchProcessor};
+use crate::sql_diff::generate_schema_diff;
+use anyhow::Result;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::future::Future;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// 差异检查使用的新旧初始化脚本
+const OLD_SQL_FIXTURE: &str = include_str!("../fixtures/init_mysql_old.sql");
+const NEW_SQL_FIXTURE: &str = include_str!("../fixtures/init_mysql_new.sql");
+
+/// 备份检查的示例文件（相对路径, 内容）
+const BACKUP_SAMPLE_FILES: &[(&str, &str)] = &[
+    ("app/config.yml", "version: 1\n"),
+    ("app/static/index.html", "<html></html>\n"),
+    ("data/mysql/ibdata1", "self-test data\n"),
+];
+
+/// 单项检查结果
+#[derive(Debug)]
+pub struct SelfTestResult {
+    pub name: &'static str,
+    /// 通过时为检查说明，失败时为错误信息
+    pub outcome: Result<String, String>,
+    pub elapsed: Duration,
+}
+
+impl SelfTestResult {
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// 依次运行各项检查，单项失败不影响后续检查
+///
+/// `skip_docker` 用于没有 Docker 的打包机器，跳过 Docker 检查
+pub async fn run_self_test(skip_docker: bool) -> Result<Vec<SelfTestResult>> {
+    let temp = TempDir::new()?;
+    let dir = temp.path();
+
+    let mut results = vec![
+        run_check("备份与恢复", check_backup_roundtrip(dir)).await,
+        run_check("增量补丁", check_patch_apply(dir)).await,
+        run_check("SQL差异", check_sql_diff()).await,
+        run_check("本地数据库", check_database(dir)).await,
+    ];
+    if !skip_docker {
+        results.push(run_check("Docker连接", check_docker(dir)).await);
+    }
+    Ok(results)
+}
+
+async fn run_check(
+    name: &'static str,
+    check: impl Future<Output = Result<String>>,
+) -> SelfTestResult {
+    let started = Instant::now();
+    let outcome = check.await.map_err(|e| format!("{e:#}"));
+    SelfTestResult {
+        name,
+        outcome,
+        elapsed: started.elapsed(),
+    }
+}
+
+fn write_files(base: &Path, files: &[(&str, &str)]) -> Result<()> {
+    for (path, content) in files {
+        let path = base.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+/// 比对目录中的文件内容，不一致时返回错误
+fn expect_files(base: &Path, files: &[(&str, &str)]) -> Result<()> {
+    for (path, expected) in files {
+        let actual = std::fs::read_to_string(base.join(path))
+            .map_err(|e| anyhow::anyhow!("读取 {} 失败: {}", path, e))?;
+        if actual != *expected {
+            return Err(anyhow::anyhow!("{} 的内容与预期不一致", path));
+        }
+    }
+    Ok(())
+}
+
+async fn check_backup_roundtrip(dir: &Path) -> Result<String> {
+    let source = dir.join("backup-source");
+    let restored = dir.join("backup-restored");
+    let archive = dir.join("self-test-backup.tar.gz");
+    write_files(&source, BACKUP_SAMPLE_FILES)?;
+
+    let entries = collect_backup_entries(&[source.join("app"), source.join("data")], &[])?;
+    write_backup_archive(
+        &entries,
+        &archive,
+        6,
+        None,
+        "nuwax-cli self-test",
+        &EventSender::default(),
+    )?;
+    extract_backup_archive(&archive, &restored, &[], &OwnershipMapping::default())?;
+    expect_files(&restored, BACKUP_SAMPLE_FILES)?;
+
+    Ok(format!(
+        "归档并恢复 {} 个文件，归档大小 {} 字节",
+        BACKUP_SAMPLE_FILES.len(),
+        std::fs::metadata(&archive)?.len()
+    ))
+}
+
+async fn check_patch_apply(dir: &Path) -> Result<String> {
+    let work = dir.join("patch-work");
+    let original = [
+        ("app/config.yml", "version: 1\n"),
+        ("app/legacy.js", "legacy\n"),
+    ];
+    let patched = [
+        ("app/config.yml", "version: 2\n"),
+        ("app/feature.js", "feature\n"),
+    ];
+    write_files(&work, &original)?;
+
+    // 生成补丁包
+    let patch_source = dir.join("patch-source");
+    write_files(&patch_source, &patched)?;
+    let patch_path = dir.join("self-test-patch.tar.gz");
+    let mut builder = tar::Builder::new(GzEncoder::new(
+        std::fs::File::create(&patch_path)?,
+        Compression::default(),
+    ));
+    for (path, _) in &patched {
+        builder.append_path_with_name(patch_source.join(path), path)?;
+    }
+    builder.into_inner()?.finish()?;
+
+    let patch_info = PatchPackageInfo {
+        url: patch_path.display().to_string(),
+        hash: Some(format!(
+            "sha256:{}",
+            sha256_file_uncached(&patch_path).await?
+        )),
+        signature: None,
+        operations: PatchOperations {
+            replace: Some(ReplaceOperations {
+                files: patched.iter().map(|(path, _)| path.to_string()).collect(),
+                directories: Vec::new(),
+            }),
+            delete: Some(ReplaceOperations {
+                files: vec!["app/legacy.js".to_string()],
+                directories: Vec::new(),
+            }),
+        },
+        operations_url: None,
+        notes: None,
+    };
+    let processor = PatchProcessor::new()?;
+    processor
+        .verify_patch_integrity(&patch_path, &patch_info)
+        .await?;
+    let extracted = processor.extract_patch(&patch_path).await?;
+
+    let mut executor = FileOperationExecutor::new(work.clone())?;
+    executor.enable_backup()?;
+    executor.set_patch_source(&extracted)?;
+    let changed = patch_info.get_changed_files();
+    if let Some(replace) = &patch_info.operations.replace {
+        executor.replace_files(&replace.files).await?;
+    }
+    if let Some(delete) = &patch_info.operations.delete {
+        executor.delete_items(&delete.files).await?;
+    }
+    expect_files(&work, &patched)?;
+    if work.join("app/legacy.js").exists() {
+        return Err(anyhow::anyhow!("补丁删除的文件 app/legacy.js 仍然存在"));
+    }
+
+    // 回滚后恢复原有文件
+    executor.rollback().await?;
+    expect_files(&work, &original)?;
+
+    Ok(format!("应用并回滚了 {} 项文件变更", changed.len()))
+}
+
+async fn check_sql_diff() -> Result<String> {
+    let (diff_sql, _) = generate_schema_diff(
+        Some(OLD_SQL_FIXTURE),
+        NEW_SQL_FIXTURE,
+        Some("self-test-old"),
+        "self-test-new",
+    )?;
+    for expected in [
+        "CREATE TABLE `custom_page_config`",
+        "ALTER TABLE `agent_config`",
+    ] {
+        if !diff_sql.contains(expected) {
+            return Err(anyhow::anyhow!("差异SQL缺少预期的语句: {}", expected));
+        }
+    }
+    let statements = diff_sql
+        .lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            line.starts_with("CREATE TABLE") || line.starts_with("ALTER TABLE")
+        })
+        .count();
+    Ok(format!("生成 {statements} 条建表/改表语句"))
+}
+
+async fn check_database(dir: &Path) -> Result<String> {
+    let db_path = dir.join("self-test.db");
+    let database = Database::connect(&db_path).await?;
+    database.init_database().await?;
+    database.set_config("self_test", "ok").await?;
+    match database.get_config("self_test").await?.as_deref() {
+        Some("ok") => Ok(format!("读写正常 ({})", db_path.display())),
+        other => Err(anyhow::anyhow!("读回的配置项与写入的不一致: {:?}", other)),
+    }
+}
+
+async fn check_docker(dir: &Path) -> Result<String> {
+    let compose_file = dir.join("docker-compose.yml");
+    let env_file = dir.join(".env");
+    write_files(
+        dir,
+        &[
+            (
+                "docker-compose.yml",
+                "services:\n  self-test:\n    image: busybox\n",
+            ),
+            (".env", ""),
+        ],
+    )?;
+    let version = DockerManager::new(&compose_file, &env_file)?
+        .probe_docker_daemon()
+        .await?;
+    Ok(format!("Docker 服务版本 {version}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_self_test_without_docker() {
+        let results = run_self_test(true).await.unwrap();
+        assert_eq!(results.len(), 4);
+        for result in &results {
+            assert!(result.passed(), "{}: {:?}", result.name, result.outcome);
+        }
+    }
+}
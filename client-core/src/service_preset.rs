@@ -0,0 +1,525 @@
+//! # 服务规格预设
+//!
+//! 按主机规格（small / medium / large）调整 compose 服务的资源限制，并在小规格主机上停用
+//! 可选服务（如视频分析 worker）。预设生成独立的 compose 覆盖文件
+//! （与 docker-compose.yml 同目录的 [`PRESET_OVERRIDE_FILE_NAME`]），compose 命令通过额外的
+//! `-f` 加载，不修改服务包中的 docker-compose.yml；升级替换服务包后部署时重新生成。
+//!
+//! 停用的服务在覆盖文件中加入不会被激活的 profile，`docker compose up` 不再启动它们。
+
+use crate::constants::docker::PRESET_OVERRIDE_FILE_NAME;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 覆盖文件中停用服务使用的 profile，部署时不会激活
+pub const DISABLED_PROFILE: &str = "nuwax-preset-disabled";
+
+/// 服务规格预设
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServicePreset {
+    Small,
+    Medium,
+    Large,
+}
+
+impl ServicePreset {
+    /// 全部预设
+    pub const ALL: [ServicePreset; 3] = [
+        ServicePreset::Small,
+        ServicePreset::Medium,
+        ServicePreset::Large,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ServicePreset::Small => "small",
+            ServicePreset::Medium => "medium",
+            ServicePreset::Large => "large",
+        }
+    }
+
+    /// 适用的主机规格
+    pub fn description(self) -> &'static str {
+        match self {
+            ServicePreset::Small => "4 核 8GB 及以下的主机，限制各服务资源并停用视频分析 worker",
+            ServicePreset::Medium => "8 核 16GB 左右的主机，限制各服务资源",
+            ServicePreset::Large => "资源充足的主机，使用服务包默认配置",
+        }
+    }
+
+    /// 预设内容
+    pub fn profile(self) -> PresetProfile {
+        match self {
+            ServicePreset::Small => PresetProfile {
+                default_limits: ResourceLimits::new("1.0", "1g"),
+                services: BTreeMap::from([
+                    ("backend", ResourceLimits::new("2.0", "2g")),
+                    ("milvus", ResourceLimits::new("1.0", "2g")),
+                ]),
+                disabled: vec!["video-analysis-worker"],
+            },
+            ServicePreset::Medium => PresetProfile {
+                default_limits: ResourceLimits::new("2.0", "2g"),
+                services: BTreeMap::from([
+                    ("backend", ResourceLimits::new("4.0", "4g")),
+                    ("milvus", ResourceLimits::new("2.0", "4g")),
+                    ("video-analysis-worker", ResourceLimits::new("2.0", "4g")),
+                ]),
+                disabled: Vec::new(),
+            },
+            ServicePreset::Large => PresetProfile::default(),
+        }
+    }
+}
+
+impl std::str::FromStr for ServicePreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "small" => Ok(ServicePreset::Small),
+            "medium" => Ok(ServicePreset::Medium),
+            "large" => Ok(ServicePreset::Large),
+            _ => Err(format!(
+                "无效的服务规格预设: {s}（支持 small、medium、large）"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ServicePreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 资源限制，取值与 compose `deploy.resources.limits` 相同（如 cpus = "1.0"、memory = "1g"）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    pub cpus: Option<String>,
+    pub memory: Option<String>,
+}
+
+impl ResourceLimits {
+    fn new(cpus: &str, memory: &str) -> Self {
+        Self {
+            cpus: Some(cpus.to_string()),
+            memory: Some(memory.to_string()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpus.is_none() && self.memory.is_none()
+    }
+
+    /// 以 `other` 中配置的值覆盖当前值，与 compose 合并覆盖文件的方式相同
+    pub fn merged(&self, other: &ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            cpus: other.cpus.clone().or_else(|| self.cpus.clone()),
+            memory: other.memory.clone().or_else(|| self.memory.clone()),
+        }
+    }
+}
+
+impl fmt::Display for ResourceLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("未限制");
+        }
+        let cpus = self.cpus.as_deref().unwrap_or("-");
+        let memory = self.memory.as_deref().unwrap_or("-");
+        write!(f, "cpus={cpus} memory={memory}")
+    }
+}
+
+/// 预设内容
+#[derive(Debug, Clone, Default)]
+pub struct PresetProfile {
+    /// 未单独配置、compose 中也没有资源限制的常驻服务使用的限制
+    pub default_limits: ResourceLimits,
+    /// 按服务配置的资源限制，覆盖 compose 中已有的限制
+    pub services: BTreeMap<&'static str, ResourceLimits>,
+    /// 停用的可选服务
+    pub disabled: Vec<&'static str>,
+}
+
+/// 服务资源限制的变化
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceLimitChange {
+    pub service: String,
+    /// docker-compose.yml 中的资源限制
+    pub current: ResourceLimits,
+    /// 写入覆盖文件的资源限制
+    pub limits: ResourceLimits,
+}
+
+impl ServiceLimitChange {
+    /// 合并覆盖文件后生效的资源限制
+    pub fn effective(&self) -> ResourceLimits {
+        self.current.merged(&self.limits)
+    }
+}
+
+/// 预设应用到当前 compose 文件的结果
+#[derive(Debug, Clone)]
+pub struct PresetPlan {
+    pub preset: ServicePreset,
+    /// 资源限制有变化的服务
+    pub limits: Vec<ServiceLimitChange>,
+    /// 停用的服务
+    pub disabled: Vec<String>,
+    /// 被其他服务依赖而保留的可选服务及依赖它的服务
+    pub required: Vec<(String, Vec<String>)>,
+    /// 预设中配置但 compose 文件中不存在的服务
+    pub missing: Vec<String>,
+}
+
+impl PresetPlan {
+    /// 预设与服务包默认配置没有差异，不需要覆盖文件
+    pub fn is_empty(&self) -> bool {
+        self.limits.is_empty() && self.disabled.is_empty()
+    }
+
+    /// 生成 compose 覆盖文件内容
+    pub fn render_override(&self) -> Result<String> {
+        let mut services = BTreeMap::new();
+        for change in &self.limits {
+            let mut limits = Mapping::new();
+            if let Some(cpus) = &change.limits.cpus {
+                limits.insert("cpus".into(), cpus.as_str().into());
+            }
+            if let Some(memory) = &change.limits.memory {
+                limits.insert("memory".into(), memory.as_str().into());
+            }
+            let mut resources = Mapping::new();
+            resources.insert("limits".into(), Value::Mapping(limits));
+            let mut deploy = Mapping::new();
+            deploy.insert("resources".into(), Value::Mapping(resources));
+            let mut service = Mapping::new();
+            service.insert("deploy".into(), Value::Mapping(deploy));
+            services.insert(change.service.clone(), Value::Mapping(service));
+        }
+        for name in &self.disabled {
+            let mut service = Mapping::new();
+            service.insert(
+                "profiles".into(),
+                Value::Sequence(vec![DISABLED_PROFILE.into()]),
+            );
+            services.insert(name.clone(), Value::Mapping(service));
+        }
+
+        let mut root = Mapping::new();
+        root.insert(
+            "services".into(),
+            Value::Mapping(
+                services
+                    .into_iter()
+                    .map(|(name, service)| (Value::String(name), service))
+                    .collect(),
+            ),
+        );
+        Ok(format!(
+            "# 由 nuwax-cli 根据服务规格预设 {} 生成，请勿手动修改\n\
+             # 修改预设: nuwax-cli docker-service deploy --preset <small|medium|large>\n{}",
+            self.preset,
+            serde_yaml::to_string(&Value::Mapping(root))?
+        ))
+    }
+}
+
+/// 按 compose 文件内容计算预设的资源限制和停用服务
+///
+/// 默认限制只用于常驻服务（`restart: "no"` 的一次性任务不限制）；被其他服务通过
+/// `depends_on` 依赖的可选服务不停用，否则 compose 无法启动依赖它的服务
+pub fn plan_preset(preset: ServicePreset, compose: &Value) -> PresetPlan {
+    let profile = preset.profile();
+    let services = compose
+        .get("services")
+        .and_then(Value::as_mapping)
+        .cloned()
+        .unwrap_or_default();
+    let names: Vec<String> = services
+        .keys()
+        .filter_map(|key| key.as_str().map(str::to_string))
+        .collect();
+
+    let mut plan = PresetPlan {
+        preset,
+        limits: Vec::new(),
+        disabled: Vec::new(),
+        required: Vec::new(),
+        missing: Vec::new(),
+    };
+
+    for name in &profile.disabled {
+        if !names.iter().any(|n| n == name) {
+            plan.missing.push(name.to_string());
+            continue;
+        }
+        let dependents: Vec<String> = services
+            .iter()
+            .filter(|(_, service)| depends_on(service).iter().any(|dep| dep == name))
+            .filter_map(|(key, _)| key.as_str().map(str::to_string))
+            .filter(|dependent| !profile.disabled.iter().any(|d| d == dependent))
+            .collect();
+        if dependents.is_empty() {
+            plan.disabled.push(name.to_string());
+        } else {
+            plan.required.push((name.to_string(), dependents));
+        }
+    }
+
+    for name in profile.services.keys() {
+        if !names.iter().any(|n| n == name) && !plan.missing.iter().any(|m| m == name) {
+            plan.missing.push(name.to_string());
+        }
+    }
+
+    for (key, service) in &services {
+        let Some(name) = key.as_str() else {
+            continue;
+        };
+        if plan.disabled.iter().any(|d| d == name) {
+            continue;
+        }
+        let current = service_limits(service);
+        let limits = match profile.services.get(name) {
+            Some(limits) => limits.clone(),
+            None if current.is_empty() && !is_oneshot(service) => profile.default_limits.clone(),
+            None => continue,
+        };
+        if limits.is_empty() || current.merged(&limits) == current {
+            continue;
+        }
+        plan.limits.push(ServiceLimitChange {
+            service: name.to_string(),
+            current,
+            limits,
+        });
+    }
+
+    plan
+}
+
+/// 读取 compose 文件并计算预设
+pub fn plan_preset_for_file(preset: ServicePreset, compose_path: &Path) -> Result<PresetPlan> {
+    let content = fs::read_to_string(compose_path)?;
+    let compose: Value = serde_yaml::from_str(&content)?;
+    Ok(plan_preset(preset, &compose))
+}
+
+/// compose 文件对应的预设覆盖文件路径
+pub fn override_path(compose_path: &Path) -> PathBuf {
+    compose_path.with_file_name(PRESET_OVERRIDE_FILE_NAME)
+}
+
+/// 写入预设覆盖文件，预设没有差异时删除已有的覆盖文件
+///
+/// 返回写入的覆盖文件路径
+pub fn write_override(compose_path: &Path, plan: &PresetPlan) -> Result<Option<PathBuf>> {
+    if plan.is_empty() {
+        remove_override(compose_path)?;
+        return Ok(None);
+    }
+    let path = override_path(compose_path);
+    fs::write(&path, plan.render_override()?)?;
+    Ok(Some(path))
+}
+
+/// 删除预设覆盖文件，返回文件是否存在
+pub fn remove_override(compose_path: &Path) -> Result<bool> {
+    let path = override_path(compose_path);
+    if !path.exists() {
+        return Ok(false);
+    }
+    fs::remove_file(&path)?;
+    Ok(true)
+}
+
+/// 覆盖文件中停用的服务，文件不存在或无法解析时为空
+pub fn disabled_services(override_path: &Path) -> HashSet<String> {
+    let Some(overlay) = fs::read_to_string(override_path)
+        .ok()
+        .and_then(|content| serde_yaml::from_str::<Value>(&content).ok())
+    else {
+        return HashSet::new();
+    };
+    overlay
+        .get("services")
+        .and_then(Value::as_mapping)
+        .map(|services| {
+            services
+                .iter()
+                .filter(|(_, service)| {
+                    service
+                        .get("profiles")
+                        .and_then(Value::as_sequence)
+                        .is_some_and(|profiles| {
+                            profiles
+                                .iter()
+                                .any(|p| p.as_str() == Some(DISABLED_PROFILE))
+                        })
+                })
+                .filter_map(|(key, _)| key.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// compose 服务中 `deploy.resources.limits` 的 cpus 和 memory
+fn service_limits(service: &Value) -> ResourceLimits {
+    let limits = service
+        .get("deploy")
+        .and_then(|deploy| deploy.get("resources"))
+        .and_then(|resources| resources.get("limits"));
+    let field = |name: &str| {
+        limits
+            .and_then(|limits| limits.get(name))
+            .and_then(scalar_to_string)
+    };
+    ResourceLimits {
+        cpus: field("cpus"),
+        memory: field("memory"),
+    }
+}
+
+/// `restart: "no"` 的服务为一次性任务
+fn is_oneshot(service: &Value) -> bool {
+    service
+        .get("restart")
+        .and_then(scalar_to_string)
+        .is_some_and(|restart| restart == "no" || restart == "false")
+}
+
+/// 服务 `depends_on` 中的服务名（支持列表和映射两种写法）
+fn depends_on(service: &Value) -> Vec<String> {
+    match service.get("depends_on") {
+        Some(Value::Sequence(items)) => items
+            .iter()
+            .filter_map(|item| item.as_str().map(str::to_string))
+            .collect(),
+        Some(Value::Mapping(items)) => items
+            .keys()
+            .filter_map(|key| key.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSE: &str = r#"
+services:
+  backend:
+    image: nuwax/backend
+    depends_on:
+      - mysql
+  mysql:
+    image: mysql:8.0
+    deploy:
+      resources:
+        limits:
+          memory: 512m
+  redis:
+    image: redis:7.0
+  init-db:
+    image: nuwax/init
+    restart: "no"
+  video-analysis-worker:
+    image: nuwax/video-worker
+"#;
+
+    fn compose() -> Value {
+        serde_yaml::from_str(COMPOSE).unwrap()
+    }
+
+    fn change<'a>(plan: &'a PresetPlan, service: &str) -> Option<&'a ServiceLimitChange> {
+        plan.limits.iter().find(|c| c.service == service)
+    }
+
+    #[test]
+    fn test_small_preset_plan() {
+        let plan = plan_preset(ServicePreset::Small, &compose());
+
+        assert_eq!(plan.disabled, vec!["video-analysis-worker".to_string()]);
+        assert_eq!(plan.missing, vec!["milvus".to_string()]);
+        assert_eq!(
+            change(&plan, "backend").unwrap().limits,
+            ResourceLimits::new("2.0", "2g")
+        );
+        assert_eq!(
+            change(&plan, "redis").unwrap().limits,
+            ResourceLimits::new("1.0", "1g")
+        );
+        // compose 中已有限制的服务和一次性任务不使用默认限制
+        assert!(change(&plan, "mysql").is_none());
+        assert!(change(&plan, "init-db").is_none());
+        assert!(change(&plan, "video-analysis-worker").is_none());
+
+        assert!(ServicePreset::Large.profile().services.is_empty());
+        assert!(plan_preset(ServicePreset::Large, &compose()).is_empty());
+    }
+
+    #[test]
+    fn test_required_service_is_not_disabled() {
+        let mut compose = compose();
+        compose["services"]["backend"]["depends_on"] = serde_yaml::from_str(
+            "{mysql: {condition: service_started}, video-analysis-worker: {}}",
+        )
+        .unwrap();
+
+        let plan = plan_preset(ServicePreset::Small, &compose);
+
+        assert!(plan.disabled.is_empty());
+        assert_eq!(
+            plan.required,
+            vec![(
+                "video-analysis-worker".to_string(),
+                vec!["backend".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_override_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let compose_path = dir.path().join("docker-compose.yml");
+        fs::write(&compose_path, COMPOSE).unwrap();
+
+        let plan = plan_preset_for_file(ServicePreset::Small, &compose_path).unwrap();
+        let path = write_override(&compose_path, &plan).unwrap().unwrap();
+        assert_eq!(path, dir.path().join(PRESET_OVERRIDE_FILE_NAME));
+
+        let overlay: Value = serde_yaml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            service_limits(&overlay["services"]["backend"]),
+            ResourceLimits::new("2.0", "2g")
+        );
+        assert_eq!(
+            disabled_services(&path),
+            HashSet::from(["video-analysis-worker".to_string()])
+        );
+
+        let large = plan_preset_for_file(ServicePreset::Large, &compose_path).unwrap();
+        assert!(write_override(&compose_path, &large).unwrap().is_none());
+        assert!(!path.exists());
+        assert!(disabled_services(&path).is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+//! SQL 方言
+//!
+//! 服务包的数据库可能是 MySQL 或 PostgreSQL，两者的标识符引用、列修改和索引语法不同。
+//! 解析和生成差异SQL时按 [`SqlDialect`] 选择对应语法，未配置时根据 docker-compose.yml
+//! 中的数据库服务判断。
+
+use serde::{Deserialize, Serialize};
+use sqlparser::dialect::{Dialect, MySqlDialect, PostgreSqlDialect};
+use std::fmt;
+
+/// 数据库方言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SqlDialect {
+    #[default]
+    Mysql,
+    #[serde(alias = "postgresql")]
+    Postgres,
+}
+
+impl SqlDialect {
+    /// sqlparser 解析使用的方言
+    pub(crate) fn parser_dialect(self) -> Box<dyn Dialect> {
+        match self {
+            SqlDialect::Mysql => Box::new(MySqlDialect {}),
+            SqlDialect::Postgres => Box::new(PostgreSqlDialect {}),
+        }
+    }
+
+    /// 引用标识符：MySQL 使用反引号，PostgreSQL 使用双引号（`schema.table` 分段引用）
+    pub fn quote_ident(self, name: &str) -> String {
+        match self {
+            SqlDialect::Mysql => format!("`{name}`"),
+            SqlDialect::Postgres => name
+                .split('.')
+                .map(|part| format!("\"{}\"", part.trim_matches('"')))
+                .collect::<Vec<_>>()
+                .join("."),
+        }
+    }
+
+    /// 引用并以逗号连接列名
+    pub fn quote_columns(self, columns: &[String]) -> String {
+        columns
+            .iter()
+            .map(|c| self.quote_ident(c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// 服务包 config 目录中的数据库初始化脚本
+    pub fn init_script_name(self) -> &'static str {
+        match self {
+            SqlDialect::Mysql => "init_mysql.sql",
+            SqlDialect::Postgres => "init_postgres.sql",
+        }
+    }
+
+    /// 按 compose 服务名和镜像判断方言，返回方言和数据库服务名
+    ///
+    /// 同时存在两种数据库时优先 MySQL，保持原有服务包的行为
+    pub fn detect<'a, I>(services: I) -> Option<(SqlDialect, String)>
+    where
+        I: IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    {
+        let mut postgres = None;
+        for (name, image) in services {
+            match Self::for_service(name, image) {
+                Some(SqlDialect::Mysql) => return Some((SqlDialect::Mysql, name.to_string())),
+                Some(SqlDialect::Postgres) if postgres.is_none() => {
+                    postgres = Some((SqlDialect::Postgres, name.to_string()));
+                }
+                _ => {}
+            }
+        }
+        postgres
+    }
+
+    /// 按服务名或镜像名判断服务是否为数据库及其方言
+    pub fn for_service(name: &str, image: Option<&str>) -> Option<SqlDialect> {
+        let image_name = image
+            .map(|image| image.rsplit('/').next().unwrap_or(image))
+            .map(|image| image.split([':', '@']).next().unwrap_or(image))
+            .unwrap_or_default();
+        match (name, image_name) {
+            ("mysql" | "mariadb", _) | (_, "mysql" | "mariadb") => Some(SqlDialect::Mysql),
+            ("postgres" | "postgresql", _) | (_, "postgres") => Some(SqlDialect::Postgres),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for SqlDialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mysql" => Ok(SqlDialect::Mysql),
+            "postgres" | "postgresql" => Ok(SqlDialect::Postgres),
+            _ => Err(format!("无效的数据库方言: {s}（支持 mysql、postgres）")),
+        }
+    }
+}
+
+impl fmt::Display for SqlDialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlDialect::Mysql => write!(f, "MySQL"),
+            SqlDialect::Postgres => write!(f, "PostgreSQL"),
+        }
+    }
+}
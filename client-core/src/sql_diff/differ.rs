@@ -1,13 +1,18 @@
-use super::generator::{generate_column_sql, generate_create_table_sql};
+use super::dialect::SqlDialect;
+use super::generator::{
+    generate_add_index_sql, generate_column_sql, generate_create_table_sql,
+    generate_drop_index_sql, generate_modify_column_sql,
+};
 use super::types::{TableColumn, TableDefinition, TableIndex};
 use crate::error::DuckError;
 use std::collections::HashMap;
 use tracing::info;
 
-/// 生成MySQL差异SQL
-pub fn generate_mysql_diff(
+/// 按方言生成差异SQL
+pub fn generate_diff(
     from_tables: &HashMap<String, TableDefinition>,
     to_tables: &HashMap<String, TableDefinition>,
+    dialect: SqlDialect,
 ) -> Result<String, DuckError> {
     let mut diff_sql = Vec::new();
 
@@ -24,7 +29,7 @@ pub fn generate_mysql_diff(
         if !from_tables.contains_key(table_name) {
             info!("发现新增表: {}", table_name);
             diff_sql.push(format!("-- 新增表: {table_name}"));
-            diff_sql.push(generate_create_table_sql(table_def));
+            diff_sql.push(generate_create_table_sql(table_def, dialect));
             diff_sql.push("".to_string());
         }
     }
@@ -34,7 +39,10 @@ pub fn generate_mysql_diff(
         if !to_tables.contains_key(table_name) {
             info!("发现删除表: {}", table_name);
             diff_sql.push(format!("-- 删除表: {table_name}"));
-            diff_sql.push(format!("DROP TABLE IF EXISTS `{table_name}`;"));
+            diff_sql.push(format!(
+                "DROP TABLE IF EXISTS {};",
+                dialect.quote_ident(table_name)
+            ));
             diff_sql.push("".to_string());
         }
     }
@@ -42,7 +50,7 @@ pub fn generate_mysql_diff(
     // 3. 检查修改的表
     for (table_name, new_table_def) in to_tables {
         if let Some(old_table_def) = from_tables.get(table_name) {
-            let table_diffs = generate_table_diff(old_table_def, new_table_def);
+            let table_diffs = generate_table_diff(old_table_def, new_table_def, dialect);
             if !table_diffs.is_empty() {
                 info!("发现表结构变化: {}", table_name);
                 diff_sql.push(format!("-- 修改表: {table_name}"));
@@ -72,22 +80,27 @@ pub fn generate_mysql_diff(
 pub fn generate_table_diff(
     old_table: &TableDefinition,
     new_table: &TableDefinition,
+    dialect: SqlDialect,
 ) -> Vec<String> {
     let mut diffs = Vec::new();
 
     // 比较列差异
-    let column_diffs = generate_column_diffs(old_table, new_table);
+    let column_diffs = generate_column_diffs(old_table, new_table, dialect);
     diffs.extend(column_diffs);
 
     // 比较索引差异
-    let index_diffs = generate_index_diffs(old_table, new_table);
+    let index_diffs = generate_index_diffs(old_table, new_table, dialect);
     diffs.extend(index_diffs);
 
     diffs
 }
 
 /// 生成列差异SQL
-fn generate_column_diffs(old_table: &TableDefinition, new_table: &TableDefinition) -> Vec<String> {
+fn generate_column_diffs(
+    old_table: &TableDefinition,
+    new_table: &TableDefinition,
+    dialect: SqlDialect,
+) -> Vec<String> {
     let mut diffs = Vec::new();
     let table_name = &new_table.name;
 
@@ -107,9 +120,9 @@ fn generate_column_diffs(old_table: &TableDefinition, new_table: &TableDefinitio
     for (col_name, col_def) in &new_columns {
         if !old_columns.contains_key(col_name) {
             diffs.push(format!(
-                "ALTER TABLE `{}` ADD COLUMN {};",
-                table_name,
-                generate_column_sql(col_def)
+                "ALTER TABLE {} ADD COLUMN {};",
+                dialect.quote_ident(table_name),
+                generate_column_sql(col_def, dialect)
             ));
         }
     }
@@ -118,7 +131,9 @@ fn generate_column_diffs(old_table: &TableDefinition, new_table: &TableDefinitio
     for col_name in old_columns.keys() {
         if !new_columns.contains_key(col_name) {
             diffs.push(format!(
-                "ALTER TABLE `{table_name}` DROP COLUMN `{col_name}`;"
+                "ALTER TABLE {} DROP COLUMN {};",
+                dialect.quote_ident(table_name),
+                dialect.quote_ident(col_name)
             ));
         }
     }
@@ -127,10 +142,8 @@ fn generate_column_diffs(old_table: &TableDefinition, new_table: &TableDefinitio
     for (col_name, new_col) in &new_columns {
         if let Some(old_col) = old_columns.get(col_name) {
             if old_col != new_col {
-                diffs.push(format!(
-                    "ALTER TABLE `{}` MODIFY COLUMN {};",
-                    table_name,
-                    generate_column_sql(new_col)
+                diffs.extend(generate_modify_column_sql(
+                    table_name, old_col, new_col, dialect,
                 ));
             }
         }
@@ -140,7 +153,11 @@ fn generate_column_diffs(old_table: &TableDefinition, new_table: &TableDefinitio
 }
 
 /// 生成索引差异SQL
-fn generate_index_diffs(old_table: &TableDefinition, new_table: &TableDefinition) -> Vec<String> {
+fn generate_index_diffs(
+    old_table: &TableDefinition,
+    new_table: &TableDefinition,
+    dialect: SqlDialect,
+) -> Vec<String> {
     let mut diffs = Vec::new();
     let table_name = &new_table.name;
 
@@ -159,53 +176,14 @@ fn generate_index_diffs(old_table: &TableDefinition, new_table: &TableDefinition
     // 检查新增的索引
     for (idx_name, idx_def) in &new_indexes {
         if !old_indexes.contains_key(idx_name) {
-            if idx_def.is_primary {
-                diffs.push(format!(
-                    "ALTER TABLE `{}` ADD PRIMARY KEY ({});",
-                    table_name,
-                    idx_def
-                        .columns
-                        .iter()
-                        .map(|c| format!("`{c}`"))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                ));
-            } else if idx_def.is_unique {
-                diffs.push(format!(
-                    "ALTER TABLE `{}` ADD UNIQUE KEY `{}` ({});",
-                    table_name,
-                    idx_name,
-                    idx_def
-                        .columns
-                        .iter()
-                        .map(|c| format!("`{c}`"))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                ));
-            } else {
-                diffs.push(format!(
-                    "ALTER TABLE `{}` ADD KEY `{}` ({});",
-                    table_name,
-                    idx_name,
-                    idx_def
-                        .columns
-                        .iter()
-                        .map(|c| format!("`{c}`"))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                ));
-            }
+            diffs.push(generate_add_index_sql(table_name, idx_def, dialect));
         }
     }
 
     // 检查删除的索引
     for (idx_name, idx_def) in &old_indexes {
         if !new_indexes.contains_key(idx_name) {
-            if idx_def.is_primary {
-                diffs.push(format!("ALTER TABLE `{table_name}` DROP PRIMARY KEY;"));
-            } else {
-                diffs.push(format!("ALTER TABLE `{table_name}` DROP KEY `{idx_name}`;"));
-            }
+            diffs.push(generate_drop_index_sql(table_name, idx_def, dialect));
         }
     }
 
@@ -213,50 +191,8 @@ fn generate_index_diffs(old_table: &TableDefinition, new_table: &TableDefinition
     for (idx_name, new_idx) in &new_indexes {
         if let Some(old_idx) = old_indexes.get(idx_name) {
             if old_idx != new_idx {
-                // 先删除旧索引
-                if old_idx.is_primary {
-                    diffs.push(format!("ALTER TABLE `{table_name}` DROP PRIMARY KEY;"));
-                } else {
-                    diffs.push(format!("ALTER TABLE `{table_name}` DROP KEY `{idx_name}`;"));
-                }
-
-                // 再添加新索引
-                if new_idx.is_primary {
-                    diffs.push(format!(
-                        "ALTER TABLE `{}` ADD PRIMARY KEY ({});",
-                        table_name,
-                        new_idx
-                            .columns
-                            .iter()
-                            .map(|c| format!("`{c}`"))
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    ));
-                } else if new_idx.is_unique {
-                    diffs.push(format!(
-                        "ALTER TABLE `{}` ADD UNIQUE KEY `{}` ({});",
-                        table_name,
-                        idx_name,
-                        new_idx
-                            .columns
-                            .iter()
-                            .map(|c| format!("`{c}`"))
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    ));
-                } else {
-                    diffs.push(format!(
-                        "ALTER TABLE `{}` ADD KEY `{}` ({});",
-                        table_name,
-                        idx_name,
-                        new_idx
-                            .columns
-                            .iter()
-                            .map(|c| format!("`{c}`"))
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    ));
-                }
+                diffs.push(generate_drop_index_sql(table_name, old_idx, dialect));
+                diffs.push(generate_add_index_sql(table_name, new_idx, dialect));
             }
         }
     }
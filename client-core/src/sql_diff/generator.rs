@@ -1,5 +1,6 @@
-use super::differ::generate_mysql_diff;
-use super::parser::parse_sql_tables;
+use super::dialect::SqlDialect;
+use super::differ::generate_diff;
+use super::parser::parse_sql_tables_with_dialect;
 use super::types::{TableColumn, TableDefinition, TableIndex};
 use crate::error::DuckError;
 use tracing::info;
@@ -11,6 +12,23 @@ pub fn generate_schema_diff(
     to_sql: &str,
     from_version: Option<&str>,
     to_version: &str,
+) -> Result<(String, String), DuckError> {
+    generate_schema_diff_with_dialect(
+        SqlDialect::Mysql,
+        from_sql,
+        to_sql,
+        from_version,
+        to_version,
+    )
+}
+
+/// 按指定方言生成SQL架构差异
+pub fn generate_schema_diff_with_dialect(
+    dialect: SqlDialect,
+    from_sql: Option<&str>,
+    to_sql: &str,
+    from_version: Option<&str>,
+    to_version: &str,
 ) -> Result<(String, String), DuckError> {
     match from_sql {
         None => {
@@ -40,11 +58,11 @@ pub fn generate_schema_diff(
             }
 
             // 解析两个SQL文件的表结构
-            let from_tables = parse_sql_tables(from_content)?;
-            let to_tables = parse_sql_tables(to_sql)?;
+            let from_tables = parse_sql_tables_with_dialect(from_content, dialect)?;
+            let to_tables = parse_sql_tables_with_dialect(to_sql, dialect)?;
 
             // 生成差异SQL
-            let diff_sql = generate_mysql_diff(&from_tables, &to_tables)?;
+            let diff_sql = generate_diff(&from_tables, &to_tables, dialect)?;
 
             let description = if diff_sql.trim().is_empty() {
                 format!(
@@ -72,13 +90,21 @@ pub fn generate_schema_diff(
                 if diff_sql.contains("ALTER TABLE") && diff_sql.contains("DROP COLUMN") {
                     change_types.push("删除列");
                 }
-                if diff_sql.contains("ALTER TABLE") && diff_sql.contains("MODIFY COLUMN") {
+                if diff_sql.contains("ALTER TABLE")
+                    && (diff_sql.contains("MODIFY COLUMN") || diff_sql.contains("ALTER COLUMN"))
+                {
                     change_types.push("修改列");
                 }
-                if diff_sql.contains("ALTER TABLE") && diff_sql.contains("ADD KEY") {
+                if diff_sql.contains("ALTER TABLE") && diff_sql.contains("ADD KEY")
+                    || diff_sql.contains("CREATE INDEX")
+                    || diff_sql.contains("ADD CONSTRAINT")
+                {
                     change_types.push("新增索引");
                 }
-                if diff_sql.contains("ALTER TABLE") && diff_sql.contains("DROP KEY") {
+                if diff_sql.contains("ALTER TABLE") && diff_sql.contains("DROP KEY")
+                    || diff_sql.contains("DROP INDEX")
+                    || diff_sql.contains("DROP CONSTRAINT")
+                {
                     change_types.push("删除索引");
                 }
 
@@ -145,38 +171,51 @@ fn format_default_value_for_sql(default: &str) -> String {
 }
 
 /// 生成CREATE TABLE SQL
-pub fn generate_create_table_sql(table: &TableDefinition) -> String {
-    let mut sql = format!("CREATE TABLE `{}` (", table.name);
+///
+/// PostgreSQL 的普通索引不能写在表定义中，以 `CREATE INDEX` 语句附在表定义之后
+pub fn generate_create_table_sql(table: &TableDefinition, dialect: SqlDialect) -> String {
+    let mut sql = format!("CREATE TABLE {} (", dialect.quote_ident(&table.name));
 
     // 添加列定义
     let mut parts = Vec::new();
     for column in &table.columns {
-        parts.push(format!("  {}", generate_column_sql(column)));
+        parts.push(format!("  {}", generate_column_sql(column, dialect)));
     }
 
     // 添加索引定义
+    let mut separate_indexes = Vec::new();
     for index in &table.indexes {
-        parts.push(format!("  {}", generate_index_sql(index)));
+        if dialect == SqlDialect::Postgres && !index.is_primary && !index.is_unique {
+            separate_indexes.push(index);
+        } else {
+            parts.push(format!("  {}", generate_index_sql(index, dialect)));
+        }
     }
 
     sql.push_str(&parts.join(",\n"));
     sql.push_str("\n)");
 
     // 添加表选项
-    if let Some(engine) = &table.engine {
-        sql.push_str(&format!(" ENGINE={engine}"));
-    }
-    if let Some(charset) = &table.charset {
-        sql.push_str(&format!(" DEFAULT CHARSET={charset}"));
+    if dialect == SqlDialect::Mysql {
+        if let Some(engine) = &table.engine {
+            sql.push_str(&format!(" ENGINE={engine}"));
+        }
+        if let Some(charset) = &table.charset {
+            sql.push_str(&format!(" DEFAULT CHARSET={charset}"));
+        }
     }
 
     sql.push(';');
+    for index in separate_indexes {
+        sql.push('\n');
+        sql.push_str(&generate_add_index_sql(&table.name, index, dialect));
+    }
     sql
 }
 
 /// 生成列定义SQL
-pub fn generate_column_sql(column: &TableColumn) -> String {
-    let mut sql = format!("`{}` {}", column.name, column.data_type);
+pub fn generate_column_sql(column: &TableColumn, dialect: SqlDialect) -> String {
+    let mut sql = format!("{} {}", dialect.quote_ident(&column.name), column.data_type);
 
     if !column.nullable {
         sql.push_str(" NOT NULL");
@@ -185,54 +224,129 @@ pub fn generate_column_sql(column: &TableColumn) -> String {
     if let Some(default) = &column.default_value {
         sql.push_str(&format!(
             " DEFAULT {}",
-            format_default_value_for_sql(default)
+            format_default_value(default, dialect)
         ));
     }
 
-    if column.auto_increment {
-        sql.push_str(" AUTO_INCREMENT");
-    }
+    // PostgreSQL 的自增列由 SERIAL / IDENTITY 类型表示，也不支持列定义中的 COMMENT
+    if dialect == SqlDialect::Mysql {
+        if column.auto_increment {
+            sql.push_str(" AUTO_INCREMENT");
+        }
 
-    if let Some(comment) = &column.comment {
-        sql.push_str(&format!(" COMMENT '{comment}'"));
+        if let Some(comment) = &column.comment {
+            sql.push_str(&format!(" COMMENT '{comment}'"));
+        }
     }
 
     sql
 }
 
+/// 生成修改列的SQL：MySQL 使用 MODIFY COLUMN，PostgreSQL 分别修改类型、可空性和默认值
+pub fn generate_modify_column_sql(
+    table_name: &str,
+    old_column: &TableColumn,
+    new_column: &TableColumn,
+    dialect: SqlDialect,
+) -> Vec<String> {
+    let table = dialect.quote_ident(table_name);
+    if dialect == SqlDialect::Mysql {
+        return vec![format!(
+            "ALTER TABLE {table} MODIFY COLUMN {};",
+            generate_column_sql(new_column, dialect)
+        )];
+    }
+
+    let column = dialect.quote_ident(&new_column.name);
+    let mut actions = Vec::new();
+    if old_column.data_type != new_column.data_type {
+        actions.push(format!(
+            "ALTER COLUMN {column} TYPE {}",
+            new_column.data_type
+        ));
+    }
+    if old_column.nullable != new_column.nullable {
+        let action = if new_column.nullable { "DROP" } else { "SET" };
+        actions.push(format!("ALTER COLUMN {column} {action} NOT NULL"));
+    }
+    if old_column.default_value != new_column.default_value {
+        match &new_column.default_value {
+            Some(default) => actions.push(format!(
+                "ALTER COLUMN {column} SET DEFAULT {}",
+                format_default_value(default, dialect)
+            )),
+            None => actions.push(format!("ALTER COLUMN {column} DROP DEFAULT")),
+        }
+    }
+    if actions.is_empty() {
+        return Vec::new();
+    }
+    vec![format!("ALTER TABLE {table} {};", actions.join(", "))]
+}
+
 /// 生成索引定义SQL
-pub fn generate_index_sql(index: &TableIndex) -> String {
-    if index.is_primary {
-        format!(
-            "PRIMARY KEY ({})",
-            index
-                .columns
-                .iter()
-                .map(|c| format!("`{c}`"))
-                .collect::<Vec<_>>()
-                .join(", ")
-        )
-    } else if index.is_unique {
-        format!(
-            "UNIQUE KEY `{}` ({})",
-            index.name,
-            index
-                .columns
-                .iter()
-                .map(|c| format!("`{c}`"))
-                .collect::<Vec<_>>()
-                .join(", ")
-        )
-    } else {
-        format!(
-            "KEY `{}` ({})",
-            index.name,
-            index
-                .columns
-                .iter()
-                .map(|c| format!("`{c}`"))
-                .collect::<Vec<_>>()
-                .join(", ")
-        )
+pub fn generate_index_sql(index: &TableIndex, dialect: SqlDialect) -> String {
+    let columns = dialect.quote_columns(&index.columns);
+    let name = dialect.quote_ident(&index.name);
+    match dialect {
+        SqlDialect::Mysql if index.is_primary => format!("PRIMARY KEY ({columns})"),
+        SqlDialect::Mysql if index.is_unique => format!("UNIQUE KEY {name} ({columns})"),
+        SqlDialect::Mysql => format!("KEY {name} ({columns})"),
+        SqlDialect::Postgres if index.is_primary => format!("PRIMARY KEY ({columns})"),
+        SqlDialect::Postgres => format!("CONSTRAINT {name} UNIQUE ({columns})"),
+    }
+}
+
+/// 生成新增索引的SQL
+pub fn generate_add_index_sql(table_name: &str, index: &TableIndex, dialect: SqlDialect) -> String {
+    let table = dialect.quote_ident(table_name);
+    let columns = dialect.quote_columns(&index.columns);
+    let name = dialect.quote_ident(&index.name);
+    match dialect {
+        SqlDialect::Mysql if index.is_primary => {
+            format!("ALTER TABLE {table} ADD PRIMARY KEY ({columns});")
+        }
+        SqlDialect::Mysql if index.is_unique => {
+            format!("ALTER TABLE {table} ADD UNIQUE KEY {name} ({columns});")
+        }
+        SqlDialect::Mysql => format!("ALTER TABLE {table} ADD KEY {name} ({columns});"),
+        SqlDialect::Postgres if index.is_primary => {
+            format!("ALTER TABLE {table} ADD PRIMARY KEY ({columns});")
+        }
+        SqlDialect::Postgres if index.is_unique => {
+            format!("ALTER TABLE {table} ADD CONSTRAINT {name} UNIQUE ({columns});")
+        }
+        SqlDialect::Postgres => format!("CREATE INDEX {name} ON {table} ({columns});"),
+    }
+}
+
+/// 生成删除索引的SQL，PostgreSQL 的主键约束按默认名称 `<表名>_pkey` 删除
+pub fn generate_drop_index_sql(
+    table_name: &str,
+    index: &TableIndex,
+    dialect: SqlDialect,
+) -> String {
+    let table = dialect.quote_ident(table_name);
+    let name = dialect.quote_ident(&index.name);
+    match dialect {
+        SqlDialect::Mysql if index.is_primary => format!("ALTER TABLE {table} DROP PRIMARY KEY;"),
+        SqlDialect::Mysql => format!("ALTER TABLE {table} DROP KEY {name};"),
+        SqlDialect::Postgres if index.is_primary => {
+            let base = table_name.rsplit('.').next().unwrap_or(table_name);
+            let pkey = dialect.quote_ident(&format!("{}_pkey", base.trim_matches('"')));
+            format!("ALTER TABLE {table} DROP CONSTRAINT IF EXISTS {pkey};")
+        }
+        SqlDialect::Postgres if index.is_unique => {
+            format!("ALTER TABLE {table} DROP CONSTRAINT IF EXISTS {name};")
+        }
+        SqlDialect::Postgres => format!("DROP INDEX IF EXISTS {name};"),
+    }
+}
+
+/// 格式化默认值：PostgreSQL 的默认值解析时已是合法的表达式（如 `'a'::character varying`），原样输出
+fn format_default_value(default: &str, dialect: SqlDialect) -> String {
+    match dialect {
+        SqlDialect::Mysql => format_default_value_for_sql(default),
+        SqlDialect::Postgres => default.to_string(),
     }
 }
@@ -1,3 +1,4 @@
+mod dialect;
 mod differ;
 mod generator;
 mod parser;
@@ -7,4 +8,5 @@ mod types;
 mod tests;
 
 // 重新导出公共接口
-pub use generator::generate_schema_diff;
+pub use dialect::SqlDialect;
+pub use generator::{generate_schema_diff, generate_schema_diff_with_dialect};
@@ -1,29 +1,37 @@
+use super::dialect::SqlDialect;
 use super::types::{TableColumn, TableDefinition, TableIndex};
 use crate::error::DuckError;
 use regex::Regex;
 use sqlparser::ast::{ColumnDef, DataType, Statement, TableConstraint};
-use sqlparser::dialect::MySqlDialect;
 use sqlparser::parser::Parser;
 use std::collections::HashMap;
 use tracing::{debug, info, warn};
 
 /// 解析SQL文件中的表结构
 pub fn parse_sql_tables(sql_content: &str) -> Result<HashMap<String, TableDefinition>, DuckError> {
+    parse_sql_tables_with_dialect(sql_content, SqlDialect::Mysql)
+}
+
+/// 按指定方言解析SQL文件中的表结构
+pub fn parse_sql_tables_with_dialect(
+    sql_content: &str,
+    dialect: SqlDialect,
+) -> Result<HashMap<String, TableDefinition>, DuckError> {
     let mut tables = HashMap::new();
 
     // 使用正则表达式找到 USE 语句的位置，然后从该位置开始解析后续的 CREATE TABLE 语句
     let create_table_statements = extract_create_table_statements_with_regex(sql_content)?;
 
-    let dialect = MySqlDialect {};
+    let parser_dialect = dialect.parser_dialect();
 
     for create_table_sql in create_table_statements {
         debug!("解析 CREATE TABLE 语句: {}", create_table_sql);
 
-        match Parser::parse_sql(&dialect, &create_table_sql) {
+        match Parser::parse_sql(parser_dialect.as_ref(), &create_table_sql) {
             Ok(statements) => {
                 for statement in statements {
                     if let Statement::CreateTable(create_table) = statement {
-                        let table_name = create_table.name.to_string();
+                        let table_name = normalize_ident(create_table.name.to_string(), dialect);
                         debug!("解析表: {}", table_name);
 
                         let mut table_columns = Vec::new();
@@ -32,11 +40,11 @@ pub fn parse_sql_tables(sql_content: &str) -> Result<HashMap<String, TableDefini
 
                         // 解析列定义
                         for column in &create_table.columns {
-                            let column_def = parse_column_definition(column)?;
+                            let column_def = parse_column_definition(column, dialect)?;
 
                             // 检查是否是列级别的主键
                             if is_column_primary_key(column) {
-                                primary_key_columns.push(column.name.to_string());
+                                primary_key_columns.push(column_def.name.clone());
                             }
 
                             table_columns.push(column_def);
@@ -55,7 +63,13 @@ pub fn parse_sql_tables(sql_content: &str) -> Result<HashMap<String, TableDefini
 
                         // 解析约束（包括索引）
                         for constraint in &create_table.constraints {
-                            if let Some(index) = parse_table_constraint(constraint)? {
+                            if let Some(mut index) = parse_table_constraint(constraint)? {
+                                index.name = normalize_ident(index.name, dialect);
+                                index.columns = index
+                                    .columns
+                                    .into_iter()
+                                    .map(|c| normalize_ident(c, dialect))
+                                    .collect();
                                 table_indexes.push(index);
                             }
                         }
@@ -194,10 +208,25 @@ fn extract_create_table_statements_from_content(content: &str) -> Result<Vec<Str
     Ok(statements)
 }
 
+/// PostgreSQL 的标识符去掉双引号后比较和输出，MySQL 保持原样
+fn normalize_ident(name: String, dialect: SqlDialect) -> String {
+    match dialect {
+        SqlDialect::Mysql => name,
+        SqlDialect::Postgres => name.replace('"', ""),
+    }
+}
+
 /// 解析列定义
-fn parse_column_definition(column: &ColumnDef) -> Result<TableColumn, DuckError> {
-    let column_name = column.name.to_string();
-    let data_type = format_data_type(&column.data_type);
+fn parse_column_definition(
+    column: &ColumnDef,
+    dialect: SqlDialect,
+) -> Result<TableColumn, DuckError> {
+    let column_name = normalize_ident(column.name.to_string(), dialect);
+    // PostgreSQL 的类型（如 SERIAL、TIMESTAMP WITH TIME ZONE）按 sqlparser 的 SQL 形式保留
+    let data_type = match dialect {
+        SqlDialect::Mysql => format_data_type(&column.data_type),
+        SqlDialect::Postgres => column.data_type.to_string(),
+    };
 
     let mut nullable = true;
     let mut default_value = None;
@@ -211,7 +240,11 @@ fn parse_column_definition(column: &ColumnDef) -> Result<TableColumn, DuckError>
                 nullable = false;
             }
             sqlparser::ast::ColumnOption::Default(expr) => {
-                default_value = Some(format_default_value(expr));
+                // PostgreSQL 的默认值（如 now()、'a'::character varying）按原表达式保留
+                default_value = Some(match dialect {
+                    SqlDialect::Mysql => format_default_value(expr),
+                    SqlDialect::Postgres => expr.to_string(),
+                });
             }
             sqlparser::ast::ColumnOption::Comment(c) => {
                 comment = Some(c.clone());
@@ -869,3 +869,78 @@ CREATE TABLE comments (
 
     assert!(diff_sql.contains("posts"));
 }
+
+#[test]
+fn test_postgres_diff() {
+    let from_sql = r#"
+CREATE TABLE users (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(64) NOT NULL,
+    status VARCHAR(16) DEFAULT 'active',
+    CONSTRAINT uk_name UNIQUE (name)
+);
+
+CREATE TABLE "legacy" (
+    id INTEGER PRIMARY KEY
+);
+    "#;
+
+    let to_sql = r#"
+CREATE TABLE users (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(128),
+    status VARCHAR(16) DEFAULT 'active',
+    email TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT now()
+);
+
+CREATE TABLE posts (
+    id BIGSERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL
+);
+    "#;
+
+    let (diff_sql, description) = generate_schema_diff_with_dialect(
+        SqlDialect::Postgres,
+        Some(from_sql),
+        to_sql,
+        Some("1.0.0"),
+        "1.1.0",
+    )
+    .unwrap();
+    println!("Diff SQL: {diff_sql}");
+
+    assert!(diff_sql.contains(r#"ALTER TABLE "users" ADD COLUMN "email" TEXT NOT NULL;"#));
+    assert!(diff_sql.contains(
+        r#"ALTER TABLE "users" ALTER COLUMN "name" TYPE VARCHAR(128), ALTER COLUMN "name" DROP NOT NULL;"#
+    ));
+    assert!(diff_sql.contains(r#"ALTER TABLE "users" DROP CONSTRAINT IF EXISTS "uk_name";"#));
+    assert!(diff_sql.contains(r#"CREATE TABLE "posts""#));
+    assert!(diff_sql.contains(r#"DROP TABLE IF EXISTS "legacy";"#));
+    // 未变化的列和 MySQL 专有语法不应出现
+    assert!(!diff_sql.contains(r#""status""#));
+    assert!(!diff_sql.contains('`'));
+    assert!(!diff_sql.contains("MODIFY COLUMN"));
+    assert!(description.contains("修改列"));
+}
+
+#[test]
+fn test_sql_dialect_detect_and_quote() {
+    assert_eq!(
+        SqlDialect::detect([("backend", Some("app:1.0")), ("mysql", Some("mysql:8.0"))]),
+        Some((SqlDialect::Mysql, "mysql".to_string()))
+    );
+    assert_eq!(
+        SqlDialect::detect([("db", Some("docker.io/library/postgres:16-alpine"))]),
+        Some((SqlDialect::Postgres, "db".to_string()))
+    );
+    assert_eq!(SqlDialect::detect([("redis", Some("redis:7"))]), None);
+
+    assert_eq!("postgresql".parse(), Ok(SqlDialect::Postgres));
+    assert!("sqlite".parse::<SqlDialect>().is_err());
+    assert_eq!(SqlDialect::Mysql.quote_ident("users"), "`users`");
+    assert_eq!(
+        SqlDialect::Postgres.quote_ident("public.users"),
+        r#""public"."users""#
+    );
+}
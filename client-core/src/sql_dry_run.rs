@@ -0,0 +1,280 @@
+//! # SQL 升级预演
+//!
+//! 在真正修改生产数据库前，先在一次性 MySQL 容器中演练差异 SQL：
+//! 1. 使用与 docker-compose.yml 中 `mysql` 服务相同的镜像启动临时容器（仅绑定本机随机端口）
+//! 2. 导入当前数据库的表结构（或旧版本初始化脚本）
+//! 3. 使用与正式升级相同的 [`MySqlExecutor`] 执行差异 SQL
+//! 4. 无论成功与否都删除临时容器
+//!
+//! 预演失败时调用方应中止升级，避免迁移失败导致生产数据库不可用。
+
+use crate::container::DockerManager;
+use crate::mysql_executor::{MySqlConfig, MySqlExecutor};
+use anyhow::{Context, Result, anyhow};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+/// 临时容器名前缀
+pub const DRY_RUN_CONTAINER_PREFIX: &str = "nuwax-sql-dry-run";
+
+/// 等待临时 MySQL 就绪的默认超时时间
+pub const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// 就绪检查间隔
+const READY_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// SQL 升级预演
+#[derive(Debug, Clone)]
+pub struct SqlDryRun {
+    image: String,
+    database: String,
+    startup_timeout: Duration,
+}
+
+impl SqlDryRun {
+    pub fn new(image: impl Into<String>, database: impl Into<String>) -> Self {
+        Self {
+            image: image.into(),
+            database: database.into(),
+            startup_timeout: DEFAULT_STARTUP_TIMEOUT,
+        }
+    }
+
+    /// 从 docker-compose.yml 的 `mysql` 服务读取镜像和数据库名
+    pub async fn from_compose(compose_file: &str, env_file: &str) -> Result<Self> {
+        let docker_manager = DockerManager::new(compose_file, env_file)?;
+        let compose_config = docker_manager
+            .load_compose_config()
+            .context("无法加载 Docker Compose 配置")?;
+        let image = compose_config
+            .services
+            .0
+            .get("mysql")
+            .and_then(|s| s.as_ref())
+            .and_then(|s| s.image.clone())
+            .ok_or_else(|| anyhow!("在 docker-compose.yml 中未找到 'mysql' 服务的镜像"))?;
+
+        let config = MySqlConfig::for_container(Some(compose_file), Some(env_file)).await?;
+        Ok(Self::new(image, config.database))
+    }
+
+    pub fn with_startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+
+    /// 在临时容器中导入 `schema_sql` 后执行 `diff_sql`，返回执行日志
+    pub async fn run(&self, schema_sql: &str, diff_sql: &str) -> Result<Vec<String>> {
+        info!("🧪 启动临时 MySQL 容器进行升级预演 (镜像: {})", self.image);
+        let container = TempMySqlContainer::start(&self.image, &self.database).await?;
+
+        let result = self
+            .run_in_container(&container, schema_sql, diff_sql)
+            .await;
+
+        container.remove().await;
+        result
+    }
+
+    async fn run_in_container(
+        &self,
+        container: &TempMySqlContainer,
+        schema_sql: &str,
+        diff_sql: &str,
+    ) -> Result<Vec<String>> {
+        let executor = MySqlExecutor::new(container.mysql_config(&self.database));
+        container
+            .wait_ready(&executor, self.startup_timeout)
+            .await?;
+
+        info!("📥 导入当前数据库结构到临时容器...");
+        container.import_sql(&self.database, schema_sql).await?;
+
+        info!("🚀 在临时容器中执行差异SQL...");
+        executor
+            .execute_diff_sql(diff_sql)
+            .await
+            .map_err(|e| anyhow!("差异SQL预演失败: {e}"))
+    }
+}
+
+/// 一次性 MySQL 容器
+struct TempMySqlContainer {
+    name: String,
+    port: u16,
+    password: String,
+}
+
+impl TempMySqlContainer {
+    async fn start(image: &str, database: &str) -> Result<Self> {
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        let name = format!("{DRY_RUN_CONTAINER_PREFIX}-{}", &suffix[..12]);
+        let password = suffix;
+
+        let output = Command::new("docker")
+            .args(["run", "-d", "--rm", "--name", name.as_str()])
+            .args(["-e", format!("MYSQL_ROOT_PASSWORD={password}").as_str()])
+            .args(["-e", format!("MYSQL_DATABASE={database}").as_str()])
+            .args(["-p", "127.0.0.1::3306", image])
+            .output()
+            .await
+            .context("执行 docker run 失败")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "启动临时 MySQL 容器失败: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let mut container = Self {
+            name,
+            port: 0,
+            password,
+        };
+        match container.published_port().await {
+            Ok(port) => container.port = port,
+            Err(e) => {
+                container.remove().await;
+                return Err(e);
+            }
+        }
+        debug!(
+            "临时 MySQL 容器 {} 端口: {}",
+            container.name, container.port
+        );
+        Ok(container)
+    }
+
+    async fn published_port(&self) -> Result<u16> {
+        let output = Command::new("docker")
+            .args(["port", self.name.as_str(), "3306/tcp"])
+            .output()
+            .await
+            .context("执行 docker port 失败")?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        parse_published_port(&stdout)
+            .ok_or_else(|| anyhow!("无法获取临时 MySQL 容器端口: {}", stdout.trim()))
+    }
+
+    fn mysql_config(&self, database: &str) -> MySqlConfig {
+        MySqlConfig {
+            host: "127.0.0.1".to_string(),
+            port: self.port,
+            user: "root".to_string(),
+            password: self.password.clone().into(),
+            database: database.to_string(),
+        }
+    }
+
+    /// 初始化期间 MySQL 只监听 socket，能通过 TCP 连接即表示已就绪
+    async fn wait_ready(&self, executor: &MySqlExecutor, timeout: Duration) -> Result<()> {
+        let started = Instant::now();
+        loop {
+            match executor.test_connection().await {
+                Ok(_) => {
+                    info!(
+                        "✅ 临时 MySQL 已就绪 (耗时 {}s)",
+                        started.elapsed().as_secs()
+                    );
+                    return Ok(());
+                }
+                Err(e) if started.elapsed() >= timeout => {
+                    return Err(anyhow!(
+                        "等待临时 MySQL 就绪超时 ({}s): {e}",
+                        timeout.as_secs()
+                    ));
+                }
+                Err(_) => tokio::time::sleep(READY_CHECK_INTERVAL).await,
+            }
+        }
+    }
+
+    /// 通过容器内的 mysql 客户端导入 SQL，支持完整的 dump 语法
+    async fn import_sql(&self, database: &str, sql: &str) -> Result<()> {
+        let mut child = Command::new("docker")
+            .args([
+                "exec",
+                "-i",
+                "-e",
+                format!("MYSQL_PWD={}", self.password).as_str(),
+            ])
+            .args([self.name.as_str(), "mysql", "-uroot", database])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("执行 docker exec 失败")?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(sql.as_bytes()).await?;
+        }
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "导入数据库结构失败: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
+    async fn remove(&self) {
+        match Command::new("docker")
+            .args(["rm", "-f", self.name.as_str()])
+            .output()
+            .await
+        {
+            Ok(output) if output.status.success() => {
+                info!("🧹 已删除临时 MySQL 容器: {}", self.name)
+            }
+            Ok(output) => warn!(
+                "⚠️ 删除临时 MySQL 容器失败 {}: {}",
+                self.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(e) => warn!("⚠️ 删除临时 MySQL 容器失败 {}: {}", self.name, e),
+        }
+    }
+}
+
+/// 解析 `docker port` 输出（如 `127.0.0.1:49153`）中的主机端口
+fn parse_published_port(output: &str) -> Option<u16> {
+    output
+        .lines()
+        .find_map(|line| line.trim().rsplit(':').next()?.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_published_port() {
+        assert_eq!(parse_published_port("127.0.0.1:49153\n"), Some(49153));
+        assert_eq!(
+            parse_published_port("0.0.0.0:32768\n[::]:32768\n"),
+            Some(32768)
+        );
+        assert_eq!(parse_published_port(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_from_compose_reads_mysql_image() {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let compose_path = std::path::Path::new(&manifest_dir).join("fixtures/docker-compose.yml");
+        let env_path = std::path::Path::new(&manifest_dir).join("fixtures/.env");
+
+        let dry_run =
+            SqlDryRun::from_compose(compose_path.to_str().unwrap(), env_path.to_str().unwrap())
+                .await
+                .unwrap();
+        assert_eq!(dry_run.image(), "mysql:8.0");
+    }
+}
@@ -0,0 +1,333 @@
+//! # 符号链接处理
+//!
+//! 备份、恢复和解压服务包时统一的符号链接策略：
+//! - 备份时保存链接本身而不是目标内容；指向工作目录之外的链接直接跳过
+//! - Windows 目录联接（junction）和绝对路径链接在目标位于工作目录内时转换为相对链接
+//! - 解压/恢复时在支持的平台上重建符号链接；Windows 无权限创建链接时改为复制目标内容
+//! - 任何情况下都不会跟随链接读出或写出工作目录
+
+use crate::error::DuckError;
+use crate::safe_path::{long_path, validate_symlink_target};
+use std::path::{Component, Path, PathBuf};
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+/// 链接条目的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkOutcome {
+    /// 已创建符号链接
+    Linked,
+    /// 平台不支持创建链接，已复制目标内容
+    Copied,
+    /// 链接目标尚未解压，待全部条目写入后再处理
+    Deferred,
+}
+
+/// 读取备份时要保存的链接目标（相对路径，使用 `/` 分隔）
+///
+/// 目标不在 `root` 内（或无法解析）时返回 None，调用方应跳过该链接
+pub fn relative_link_target(root: &Path, link_path: &Path) -> Option<PathBuf> {
+    let target = std::fs::read_link(link_path).ok()?;
+
+    if !target.has_root() && !matches!(target.components().next(), Some(Component::Prefix(_))) {
+        validate_symlink_target(root, link_path, &target).ok()?;
+        return Some(to_slash_path(&target));
+    }
+
+    // 绝对路径链接（包括 Windows 目录联接）：目标位于 root 内时换算为相对路径
+    let canonical_root = root.canonicalize().ok()?;
+    let canonical_target = target.canonicalize().ok()?;
+    let target_in_root = canonical_target.strip_prefix(&canonical_root).ok()?;
+    let canonical_parent = link_path.parent()?.canonicalize().ok()?;
+    let parent_in_root = canonical_parent.strip_prefix(&canonical_root).ok()?;
+
+    let mut relative = PathBuf::new();
+    for _ in parent_in_root.components() {
+        relative.push("..");
+    }
+    relative.push(target_in_root);
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    Some(to_slash_path(&relative))
+}
+
+/// 在解压根目录内重建符号链接
+///
+/// Windows 上只能在目标已存在时判断创建文件链接还是目录链接，目标尚未解压的
+/// 链接会被推迟到 [`SymlinkExtractor::finish`] 中处理。
+#[derive(Debug)]
+pub struct SymlinkExtractor {
+    base: PathBuf,
+    deferred: Vec<(PathBuf, PathBuf)>,
+}
+
+impl SymlinkExtractor {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self {
+            // 与 resolve_entry_path 返回的链接路径保持相同的形式
+            base: long_path(&base.into()),
+            deferred: Vec::new(),
+        }
+    }
+
+    /// 在 `link_path` 创建指向 `target` 的链接，已存在的同名条目会被替换
+    pub fn create(&mut self, link_path: &Path, target: &Path) -> Result<LinkOutcome, DuckError> {
+        let link_path = long_path(link_path);
+        validate_symlink_target(&self.base, &link_path, target)?;
+        remove_existing(&link_path)?;
+        if let Some(parent) = link_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let outcome = create_platform_link(&link_path, target)?;
+        if outcome == LinkOutcome::Deferred {
+            self.deferred.push((link_path, target.to_path_buf()));
+        }
+        Ok(outcome)
+    }
+
+    /// 处理推迟的链接，返回仍无法创建（目标不存在）的链接数量
+    pub fn finish(mut self) -> Result<usize, DuckError> {
+        // 链接可能指向另一个推迟的链接，重复处理直到没有进展
+        loop {
+            let pending = std::mem::take(&mut self.deferred);
+            let before = pending.len();
+            for (link_path, target) in pending {
+                if create_platform_link(&link_path, &target)? == LinkOutcome::Deferred {
+                    self.deferred.push((link_path, target));
+                }
+            }
+            if self.deferred.is_empty() || self.deferred.len() == before {
+                break;
+            }
+        }
+
+        for (link_path, target) in &self.deferred {
+            warn!(
+                "⚠️ 链接目标不存在，跳过: {} -> {}",
+                link_path.display(),
+                target.display()
+            );
+        }
+        Ok(self.deferred.len())
+    }
+}
+
+#[cfg(unix)]
+fn create_platform_link(link_path: &Path, target: &Path) -> Result<LinkOutcome, DuckError> {
+    std::os::unix::fs::symlink(target, link_path)?;
+    Ok(LinkOutcome::Linked)
+}
+
+#[cfg(windows)]
+fn create_platform_link(link_path: &Path, target: &Path) -> Result<LinkOutcome, DuckError> {
+    let source = link_source(link_path, target);
+    let Ok(metadata) = std::fs::metadata(&source) else {
+        return Ok(LinkOutcome::Deferred);
+    };
+
+    // 归档中的链接目标使用 `/` 分隔，写入链接前转换为 Windows 分隔符
+    let target: PathBuf = target.components().collect();
+    let result = if metadata.is_dir() {
+        std::os::windows::fs::symlink_dir(&target, link_path)
+    } else {
+        std::os::windows::fs::symlink_file(&target, link_path)
+    };
+    match result {
+        Ok(()) => Ok(LinkOutcome::Linked),
+        Err(e) => {
+            debug!(
+                "创建符号链接失败（可能缺少权限），改为复制目标: {} - {}",
+                link_path.display(),
+                e
+            );
+            copy_link_target(link_path, target)
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn create_platform_link(link_path: &Path, target: &Path) -> Result<LinkOutcome, DuckError> {
+    copy_link_target(link_path, target)
+}
+
+/// 复制链接目标的内容到链接位置，目标不存在时返回 [`LinkOutcome::Deferred`]
+///
+/// 目录中嵌套的符号链接不会被跟随
+pub fn copy_link_target(link_path: &Path, target: &Path) -> Result<LinkOutcome, DuckError> {
+    let source = link_source(link_path, target);
+    let Ok(metadata) = std::fs::symlink_metadata(&source) else {
+        return Ok(LinkOutcome::Deferred);
+    };
+
+    if metadata.is_dir() {
+        for entry in WalkDir::new(&source) {
+            let entry = entry?;
+            let destination = link_path.join(entry.path().strip_prefix(&source)?);
+            let file_type = entry.file_type();
+            if file_type.is_dir() {
+                std::fs::create_dir_all(&destination)?;
+            } else if file_type.is_file() {
+                std::fs::copy(entry.path(), &destination)?;
+            } else {
+                debug!("复制链接目标时跳过嵌套链接: {}", entry.path().display());
+            }
+        }
+    } else if metadata.is_file() {
+        std::fs::copy(&source, link_path)?;
+    } else {
+        return Ok(LinkOutcome::Deferred);
+    }
+    debug!(
+        "已复制链接目标: {} -> {}",
+        source.display(),
+        link_path.display()
+    );
+    Ok(LinkOutcome::Copied)
+}
+
+/// 链接目标在磁盘上的位置（相对链接以链接所在目录为基准）
+///
+/// `..` 按词法解析，带 `\\?\` 前缀的 Windows 路径不会再由系统处理 `..` 和 `/`
+fn link_source(link_path: &Path, target: &Path) -> PathBuf {
+    let mut source = link_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+    for component in target.components() {
+        match component {
+            Component::ParentDir => {
+                source.pop();
+            }
+            Component::CurDir => {}
+            component => source.push(component),
+        }
+    }
+    source
+}
+
+/// 删除已存在的文件、目录或链接（不跟随链接）
+fn remove_existing(path: &Path) -> Result<(), DuckError> {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if metadata.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else if std::fs::remove_file(path).is_err() {
+        // Windows 上目录链接需要用 remove_dir 删除
+        std::fs::remove_dir(path)?;
+    }
+    Ok(())
+}
+
+fn to_slash_path(path: &Path) -> PathBuf {
+    PathBuf::from(path.to_string_lossy().replace('\\', "/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_copy_link_target_defers_missing_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let link = temp_dir.path().join("current");
+
+        assert_eq!(
+            copy_link_target(&link, Path::new("v1")).unwrap(),
+            LinkOutcome::Deferred
+        );
+
+        std::fs::create_dir_all(temp_dir.path().join("v1/conf")).unwrap();
+        std::fs::write(temp_dir.path().join("v1/conf/app.toml"), "port = 80").unwrap();
+        assert_eq!(
+            copy_link_target(&link, Path::new("v1")).unwrap(),
+            LinkOutcome::Copied
+        );
+        assert_eq!(
+            std::fs::read_to_string(link.join("conf/app.toml")).unwrap(),
+            "port = 80"
+        );
+    }
+
+    #[test]
+    fn test_extractor_rejects_escaping_link() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        let mut extractor = SymlinkExtractor::new(base);
+
+        assert!(
+            extractor
+                .create(&base.join("app/config"), Path::new("../../etc"))
+                .is_err()
+        );
+        assert!(
+            std::fs::symlink_metadata(base.join("app/config")).is_err(),
+            "逃逸的链接不应被创建"
+        );
+    }
+
+    #[test]
+    fn test_extractor_links_or_copies_after_target_exists() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        let mut extractor = SymlinkExtractor::new(base);
+
+        // 链接先于目标出现在归档中
+        extractor
+            .create(&base.join("app/current.conf"), Path::new("v2.conf"))
+            .unwrap();
+        std::fs::write(base.join("app/v2.conf"), "v2").unwrap();
+        assert_eq!(extractor.finish().unwrap(), 0);
+
+        assert_eq!(
+            std::fs::read_to_string(base.join("app/current.conf")).unwrap(),
+            "v2"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_relative_link_target() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join("app")).unwrap();
+        std::fs::create_dir_all(root.join("config")).unwrap();
+
+        let relative_link = root.join("app/config");
+        std::os::unix::fs::symlink("../config", &relative_link).unwrap();
+        assert_eq!(
+            relative_link_target(root, &relative_link),
+            Some(PathBuf::from("../config"))
+        );
+
+        // 指向工作目录内的绝对路径链接转换为相对链接
+        let absolute_link = root.join("app/config_abs");
+        std::os::unix::fs::symlink(root.join("config"), &absolute_link).unwrap();
+        assert_eq!(
+            relative_link_target(root, &absolute_link),
+            Some(PathBuf::from("../config"))
+        );
+
+        let escaping_link = root.join("app/etc");
+        std::os::unix::fs::symlink("/etc", &escaping_link).unwrap();
+        assert_eq!(relative_link_target(root, &escaping_link), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_relative_link_target_rejects_outside_absolute() {
+        let temp_dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let link = root.join("outside");
+
+        // 没有创建符号链接的权限时跳过
+        if std::os::windows::fs::symlink_dir(outside.path(), &link).is_err() {
+            return;
+        }
+        assert_eq!(relative_link_target(root, &link), None);
+    }
+}
@@ -0,0 +1,400 @@
+//! # 遗留临时文件清理
+//!
+//! 升级、备份中断后可能留下临时文件：解压临时目录、备份暂存目录、镜像归档临时目录、
+//! 系统临时目录中的数据备份、断点续传元数据（`*.download`）以及未完成的归档（`*.partial`、
+//! `*.repacking`）。[`find_temp_artifacts`] 按命名规则识别这些文件，
+//! [`sweep_temp_artifacts`] 删除超过指定时间未修改的文件。
+//!
+//! `temp_sql` 目录中的数据库初始化脚本供升级回滚使用，只报告不删除。
+
+use crate::clock;
+use crate::constants::{backup, docker, upgrade};
+use crate::paths;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+/// 下载目录中断点续传元数据文件的扩展名
+const DOWNLOAD_METADATA_EXTENSION: &str = "download";
+
+/// 备份存储目录中未完成归档的后缀
+const PARTIAL_SUFFIXES: [&str; 2] = [".partial", ".repacking"];
+
+/// 临时文件类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TempArtifactKind {
+    /// 服务包解压临时目录
+    ExtractDir,
+    /// 备份暂存目录
+    BackupStaging,
+    /// 镜像归档导出、导入的临时目录
+    ImageStaging,
+    /// 全量升级时系统临时目录中的数据备份
+    TempDataBackup,
+    /// 断点续传元数据
+    DownloadMetadata,
+    /// 未完成的备份或镜像归档
+    PartialArchive,
+    /// 升级时保存的数据库初始化脚本和差异SQL
+    SqlWorkDir,
+}
+
+impl TempArtifactKind {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            TempArtifactKind::ExtractDir => "解压临时目录",
+            TempArtifactKind::BackupStaging => "备份暂存目录",
+            TempArtifactKind::ImageStaging => "镜像归档临时目录",
+            TempArtifactKind::TempDataBackup => "临时数据备份",
+            TempArtifactKind::DownloadMetadata => "断点续传元数据",
+            TempArtifactKind::PartialArchive => "未完成的归档",
+            TempArtifactKind::SqlWorkDir => "升级SQL目录",
+        }
+    }
+
+    /// 是否可以自动删除
+    pub fn is_removable(&self) -> bool {
+        *self != TempArtifactKind::SqlWorkDir
+    }
+}
+
+/// 识别出的临时文件或目录
+#[derive(Debug, Clone)]
+pub struct TempArtifact {
+    pub kind: TempArtifactKind,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl TempArtifact {
+    fn new(kind: TempArtifactKind, path: PathBuf) -> Self {
+        let metadata = fs::metadata(&path).ok();
+        let size_bytes = match &metadata {
+            Some(metadata) if metadata.is_dir() => directory_size(&path),
+            Some(metadata) => metadata.len(),
+            None => 0,
+        };
+        Self {
+            kind,
+            modified: metadata.and_then(|metadata| metadata.modified().ok()),
+            path,
+            size_bytes,
+        }
+    }
+
+    /// 距最后修改的时间，无法获取修改时间时视为刚修改
+    pub fn age(&self, now: SystemTime) -> Duration {
+        self.modified
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default()
+    }
+
+    /// 修改时间是否晚于 `now` 超过允许的偏差，说明系统时间被往回调整过
+    pub fn modified_in_future(&self, now: SystemTime) -> bool {
+        self.modified
+            .is_some_and(|modified| clock::is_in_future(modified.into(), now.into()))
+    }
+}
+
+/// 查找临时文件的位置
+#[derive(Debug, Clone)]
+pub struct TempArtifactRoots {
+    /// 服务包解压临时目录
+    pub extract_dir: PathBuf,
+    /// 升级SQL目录
+    pub sql_dir: PathBuf,
+    /// 备份存储目录
+    pub backup_dir: PathBuf,
+    /// 下载缓存目录
+    pub download_dir: PathBuf,
+    /// docker 目录的上级目录，临时数据备份默认位于此处
+    pub work_dir: PathBuf,
+    /// 系统临时目录（旧版本的临时数据备份位于此处）
+    pub system_temp_dir: PathBuf,
+}
+
+impl TempArtifactRoots {
+    /// 使用默认的解压目录、升级SQL目录和系统临时目录
+    pub fn new(backup_dir: PathBuf, download_dir: PathBuf) -> Self {
+        Self {
+            extract_dir: upgrade::get_temp_extract_dir(),
+            sql_dir: paths::temp_sql_dir(),
+            backup_dir,
+            download_dir,
+            work_dir: docker::get_docker_work_dir()
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            system_temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// 按命名规则查找遗留的临时文件
+pub fn find_temp_artifacts(roots: &TempArtifactRoots) -> Vec<TempArtifact> {
+    let mut artifacts = Vec::new();
+
+    for (kind, dir) in [
+        (TempArtifactKind::ExtractDir, &roots.extract_dir),
+        (TempArtifactKind::SqlWorkDir, &roots.sql_dir),
+    ] {
+        if dir.is_dir() {
+            artifacts.push(TempArtifact::new(kind, dir.clone()));
+        }
+    }
+
+    for (dir, kind, prefix) in [
+        (
+            &roots.backup_dir,
+            TempArtifactKind::BackupStaging,
+            backup::STAGING_DIR_PREFIX,
+        ),
+        (
+            &roots.backup_dir,
+            TempArtifactKind::ImageStaging,
+            backup::IMAGE_STAGING_PREFIX,
+        ),
+        (
+            &roots.work_dir,
+            TempArtifactKind::TempDataBackup,
+            upgrade::TEMP_DATA_BACKUP_PREFIX,
+        ),
+        (
+            &roots.system_temp_dir,
+            TempArtifactKind::TempDataBackup,
+            upgrade::TEMP_DATA_BACKUP_PREFIX,
+        ),
+    ] {
+        artifacts.extend(
+            matching_entries(dir, |name| name.starts_with(prefix))
+                .into_iter()
+                .map(|path| TempArtifact::new(kind, path)),
+        );
+    }
+
+    artifacts.extend(
+        matching_entries(&roots.backup_dir, |name| {
+            PARTIAL_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+        })
+        .into_iter()
+        .filter(|path| path.is_file())
+        .map(|path| TempArtifact::new(TempArtifactKind::PartialArchive, path)),
+    );
+
+    // 下载目录结构为 <版本>/<类型>/<文件>，元数据文件与服务包同目录
+    let mut metadata_files: Vec<PathBuf> = WalkDir::new(&roots.download_dir)
+        .max_depth(4)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == DOWNLOAD_METADATA_EXTENSION)
+        })
+        .collect();
+    metadata_files.sort();
+    artifacts.extend(
+        metadata_files
+            .into_iter()
+            .map(|path| TempArtifact::new(TempArtifactKind::DownloadMetadata, path)),
+    );
+
+    artifacts
+}
+
+/// 清理结果
+#[derive(Debug, Default)]
+pub struct TempSweepReport {
+    /// 已删除（`dry_run` 时为将被删除）的文件
+    pub removed: Vec<TempArtifact>,
+    /// 未超过时间阈值或只报告不删除的文件
+    pub kept: Vec<TempArtifact>,
+    /// 删除失败的文件和原因
+    pub failed: Vec<(TempArtifact, String)>,
+}
+
+impl TempSweepReport {
+    /// 已删除文件的总大小
+    pub fn removed_bytes(&self) -> u64 {
+        self.removed
+            .iter()
+            .map(|artifact| artifact.size_bytes)
+            .sum()
+    }
+}
+
+/// 删除超过 `max_age` 未修改的可删除临时文件，`dry_run` 时只报告
+pub fn sweep_temp_artifacts(
+    artifacts: Vec<TempArtifact>,
+    max_age: Duration,
+    now: SystemTime,
+    dry_run: bool,
+) -> TempSweepReport {
+    let mut report = TempSweepReport::default();
+    for artifact in artifacts {
+        if !artifact.kind.is_removable() || artifact.age(now) < max_age {
+            report.kept.push(artifact);
+            continue;
+        }
+        if dry_run {
+            report.removed.push(artifact);
+            continue;
+        }
+        let result = if artifact.path.is_dir() {
+            fs::remove_dir_all(&artifact.path)
+        } else {
+            fs::remove_file(&artifact.path)
+        };
+        match result {
+            Ok(()) => report.removed.push(artifact),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => report.removed.push(artifact),
+            Err(e) => report.failed.push((artifact, e.to_string())),
+        }
+    }
+    report
+}
+
+/// 目录下名称匹配的条目，按路径排序
+fn matching_entries(dir: &Path, matches: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = read_dir
+        .flatten()
+        .filter(|entry| matches(&entry.file_name().to_string_lossy()))
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn directory_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roots(root: &Path) -> TempArtifactRoots {
+        let roots = TempArtifactRoots {
+            extract_dir: root.join("data/temp"),
+            sql_dir: root.join("temp_sql"),
+            backup_dir: root.join("backups"),
+            download_dir: root.join("downloads"),
+            work_dir: root.join("work"),
+            system_temp_dir: root.join("tmp"),
+        };
+        for dir in [
+            &roots.extract_dir,
+            &roots.sql_dir,
+            &roots.backup_dir,
+            &roots.system_temp_dir,
+        ] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::create_dir_all(roots.download_dir.join("1.2.0/full")).unwrap();
+        roots
+    }
+
+    fn kinds(artifacts: &[TempArtifact]) -> Vec<(TempArtifactKind, String)> {
+        artifacts
+            .iter()
+            .map(|artifact| {
+                let name = artifact.path.file_name().unwrap().to_string_lossy();
+                (artifact.kind, name.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_temp_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let roots = roots(dir.path());
+        fs::create_dir(roots.backup_dir.join(".staging_2026-01-01")).unwrap();
+        fs::create_dir(roots.backup_dir.join(".nuwax-images-abc")).unwrap();
+        fs::write(roots.backup_dir.join("images.tar.zst.partial"), "x").unwrap();
+        fs::write(roots.backup_dir.join("backup_manual_v1.tar.gz"), "x").unwrap();
+        fs::create_dir(roots.system_temp_dir.join("duck_data_backup_1700000000")).unwrap();
+        fs::create_dir(roots.system_temp_dir.join("unrelated")).unwrap();
+        fs::write(roots.download_dir.join("1.2.0/full/docker.zip"), "x").unwrap();
+        fs::write(roots.download_dir.join("1.2.0/full/docker.download"), "{}").unwrap();
+
+        let found = kinds(&find_temp_artifacts(&roots));
+        assert_eq!(
+            found,
+            vec![
+                (TempArtifactKind::ExtractDir, "temp".to_string()),
+                (TempArtifactKind::SqlWorkDir, "temp_sql".to_string()),
+                (
+                    TempArtifactKind::BackupStaging,
+                    ".staging_2026-01-01".to_string()
+                ),
+                (
+                    TempArtifactKind::ImageStaging,
+                    ".nuwax-images-abc".to_string()
+                ),
+                (
+                    TempArtifactKind::TempDataBackup,
+                    "duck_data_backup_1700000000".to_string()
+                ),
+                (
+                    TempArtifactKind::PartialArchive,
+                    "images.tar.zst.partial".to_string()
+                ),
+                (
+                    TempArtifactKind::DownloadMetadata,
+                    "docker.download".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sweep_respects_age_and_report_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let roots = roots(dir.path());
+        fs::write(roots.sql_dir.join("init_mysql_old.sql"), "x").unwrap();
+        fs::create_dir(roots.backup_dir.join(".staging_old")).unwrap();
+        fs::write(roots.backup_dir.join(".staging_old/file"), "abc").unwrap();
+
+        let max_age = Duration::from_secs(3600);
+        let now = SystemTime::now();
+
+        // 刚创建的文件未超过阈值
+        let report = sweep_temp_artifacts(find_temp_artifacts(&roots), max_age, now, false);
+        assert!(report.removed.is_empty());
+        assert!(roots.backup_dir.join(".staging_old").exists());
+
+        // 两小时后：只报告 dry_run，不删除
+        let later = now + Duration::from_secs(7200);
+        let report = sweep_temp_artifacts(find_temp_artifacts(&roots), max_age, later, true);
+        assert_eq!(report.removed.len(), 2);
+        assert!(roots.backup_dir.join(".staging_old").exists());
+
+        let report = sweep_temp_artifacts(find_temp_artifacts(&roots), max_age, later, false);
+        assert_eq!(
+            kinds(&report.removed),
+            vec![
+                (TempArtifactKind::ExtractDir, "temp".to_string()),
+                (TempArtifactKind::BackupStaging, ".staging_old".to_string()),
+            ]
+        );
+        assert_eq!(report.removed_bytes(), 3);
+        assert_eq!(
+            kinds(&report.kept),
+            vec![(TempArtifactKind::SqlWorkDir, "temp_sql".to_string())]
+        );
+        assert!(!roots.backup_dir.join(".staging_old").exists());
+        assert!(roots.sql_dir.join("init_mysql_old.sql").exists());
+    }
+}
@@ -0,0 +1,253 @@
+//! # 目录快速复制
+//!
+//! 升级前的临时数据备份需要完整复制数据目录。源和目标位于同一文件系统时，文件优先通过
+//! reflink（Linux 的 `FICLONE`，btrfs、XFS 等支持写时复制的文件系统）共享数据块，
+//! 不支持时使用硬链接；跨文件系统或两者都失败时逐个复制文件。
+//!
+//! 硬链接与原文件是同一个 inode，只适用于原文件之后会被删除或整体替换、不会被原地修改的场景，
+//! 由调用方通过 `allow_hardlink` 决定是否使用。
+
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use tracing::debug;
+use walkdir::WalkDir;
+
+/// 复制结果
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TreeCopyStats {
+    /// 通过 reflink 复制的文件数
+    pub reflinked: u64,
+    /// 通过硬链接复制的文件数
+    pub hardlinked: u64,
+    /// 逐字节复制的文件数
+    pub copied: u64,
+    /// 逐字节复制的字节数
+    pub copied_bytes: u64,
+}
+
+impl TreeCopyStats {
+    /// 复制的文件总数
+    pub fn files(&self) -> u64 {
+        self.reflinked + self.hardlinked + self.copied
+    }
+
+    /// 日志中使用的摘要，如 `reflink 120 个、硬链接 0 个、复制 3 个文件（1.2 MB）`
+    pub fn summary(&self) -> String {
+        format!(
+            "reflink {} 个、硬链接 {} 个、复制 {} 个文件（{:.1} MB）",
+            self.reflinked,
+            self.hardlinked,
+            self.copied,
+            self.copied_bytes as f64 / 1024.0 / 1024.0
+        )
+    }
+}
+
+/// 把 `source` 目录的内容复制到 `target`，保留权限、修改时间、符号链接和（以 root 运行时）属主
+///
+/// `source` 不存在时不做任何操作
+pub fn copy_tree(source: &Path, target: &Path, allow_hardlink: bool) -> io::Result<TreeCopyStats> {
+    let mut stats = TreeCopyStats::default();
+    if !source.exists() {
+        return Ok(stats);
+    }
+    fs::create_dir_all(target)?;
+
+    let same_filesystem = same_filesystem(source, target);
+    let mut try_reflink = same_filesystem;
+    let mut try_hardlink = same_filesystem && allow_hardlink;
+    // 目录权限在其中的文件复制完成后设置，避免只读目录无法写入
+    let mut directories = Vec::new();
+
+    for entry in WalkDir::new(source).min_depth(1) {
+        let entry = entry?;
+        let relative = entry
+            .path()
+            .strip_prefix(source)
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        let destination = target.join(relative);
+        let file_type = entry.file_type();
+
+        if file_type.is_dir() {
+            fs::create_dir_all(&destination)?;
+            copy_owner(entry.path(), &destination);
+            directories.push((destination, entry.metadata()?.permissions()));
+            continue;
+        }
+        if file_type.is_symlink() && copy_symlink(entry.path(), &destination)? {
+            continue;
+        }
+
+        if try_reflink {
+            match reflink(entry.path(), &destination) {
+                Ok(()) => {
+                    copy_file_metadata(entry.path(), &destination)?;
+                    stats.reflinked += 1;
+                    continue;
+                }
+                Err(e) => {
+                    debug!("reflink 不可用，改为硬链接或复制: {}", e);
+                    try_reflink = false;
+                }
+            }
+        }
+        if try_hardlink {
+            match fs::hard_link(entry.path(), &destination) {
+                Ok(()) => {
+                    stats.hardlinked += 1;
+                    continue;
+                }
+                Err(e) => {
+                    debug!("硬链接失败，改为复制: {}", e);
+                    try_hardlink = false;
+                }
+            }
+        }
+        // fs::copy 同时复制权限位
+        stats.copied_bytes += fs::copy(entry.path(), &destination)?;
+        copy_file_metadata(entry.path(), &destination)?;
+        stats.copied += 1;
+    }
+
+    for (directory, permissions) in directories.into_iter().rev() {
+        fs::set_permissions(directory, permissions)?;
+    }
+    Ok(stats)
+}
+
+/// 两个已存在的路径是否位于同一文件系统
+#[cfg(unix)]
+fn same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+/// 非 Unix 平台无法判断，只尝试硬链接，失败时改为复制
+#[cfg(not(unix))]
+fn same_filesystem(_a: &Path, _b: &Path) -> bool {
+    true
+}
+
+/// 通过 `FICLONE` 创建与源文件共享数据块的新文件，失败时删除已创建的空文件
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, target: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    /// `_IOW(0x94, 9, int)`
+    const FICLONE: libc::c_ulong = 0x4004_9409;
+
+    let source_file = File::open(source)?;
+    let target_file = File::options().write(true).create_new(true).open(target)?;
+    let result = unsafe {
+        libc::ioctl(
+            target_file.as_raw_fd(),
+            FICLONE as _,
+            source_file.as_raw_fd(),
+        )
+    };
+    if result == -1 {
+        let error = io::Error::last_os_error();
+        drop(target_file);
+        let _ = fs::remove_file(target);
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_source: &Path, _target: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "当前平台不支持 reflink",
+    ))
+}
+
+/// 把源文件的权限、修改时间和属主复制到新文件
+fn copy_file_metadata(source: &Path, target: &Path) -> io::Result<()> {
+    let metadata = fs::metadata(source)?;
+    fs::set_permissions(target, metadata.permissions())?;
+    if let Ok(modified) = metadata.modified() {
+        File::options()
+            .write(true)
+            .open(target)
+            .and_then(|file| file.set_modified(modified))?;
+    }
+    copy_owner(source, target);
+    Ok(())
+}
+
+/// 复制属主和属组，非 root 运行时失败只记录调试日志
+#[cfg(unix)]
+fn copy_owner(source: &Path, target: &Path) {
+    use std::os::unix::fs::MetadataExt;
+    let owned = fs::symlink_metadata(source).and_then(|metadata| {
+        std::os::unix::fs::lchown(target, Some(metadata.uid()), Some(metadata.gid()))
+    });
+    if let Err(e) = owned {
+        debug!("保留属主失败: {} - {}", target.display(), e);
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_owner(_source: &Path, _target: &Path) {}
+
+/// 重建符号链接，返回 true；不支持符号链接的平台返回 false，按普通文件复制链接指向的内容
+#[cfg(unix)]
+fn copy_symlink(source: &Path, target: &Path) -> io::Result<bool> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, target)?;
+    copy_owner(source, target);
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(_source: &Path, _target: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_tree() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let source = temp_dir.path().join("data");
+        fs::create_dir_all(source.join("mysql")).unwrap();
+        fs::write(source.join("mysql/ibdata1"), "v1").unwrap();
+        fs::write(source.join("redis.conf"), "port 6379").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("mysql/ibdata1", source.join("link")).unwrap();
+
+        for allow_hardlink in [true, false] {
+            let target = temp_dir.path().join(format!("copy_{allow_hardlink}"));
+            let stats = copy_tree(&source, &target, allow_hardlink).unwrap();
+            assert_eq!(stats.files(), 2);
+            if !allow_hardlink {
+                assert_eq!(stats.hardlinked, 0);
+            }
+            assert_eq!(
+                fs::read_to_string(target.join("mysql/ibdata1")).unwrap(),
+                "v1"
+            );
+            assert_eq!(
+                fs::read_to_string(target.join("redis.conf")).unwrap(),
+                "port 6379"
+            );
+            #[cfg(unix)]
+            assert_eq!(
+                fs::read_link(target.join("link")).unwrap(),
+                Path::new("mysql/ibdata1")
+            );
+        }
+
+        // 源目录不存在时不创建目标目录
+        let missing = temp_dir.path().join("missing_copy");
+        let stats = copy_tree(&temp_dir.path().join("missing"), &missing, true).unwrap();
+        assert_eq!(stats, TreeCopyStats::default());
+        assert!(!missing.exists());
+    }
+}
@@ -73,16 +73,17 @@ impl UpgradeManager {
     /// 检查docker应用升级策略
     pub async fn check_for_updates(&self, force_full: bool) -> Result<UpgradeStrategy> {
         info!("检查服务更新...");
-        let current_version = &self.config.get_docker_versions();
+        let current_version = self.config.get_docker_version()?;
         debug!("当前版本: {}", current_version);
         let enhanced_service_manifest = self.api_client.get_enhanced_service_manifest().await?;
 
-        let upgrade_strategy_manager = UpgradeStrategyManager::new(
-            current_version.to_string(),
-            force_full,
-            enhanced_service_manifest,
-        );
-        let upgrade_strategy: UpgradeStrategy = upgrade_strategy_manager.determine_strategy()?;
+        let upgrade_strategy_manager =
+            UpgradeStrategyManager::new(current_version, force_full, enhanced_service_manifest);
+        let mut upgrade_strategy: UpgradeStrategy =
+            upgrade_strategy_manager.determine_strategy()?;
+        self.api_client
+            .fetch_patch_operations(&mut upgrade_strategy)
+            .await?;
 
         Ok(upgrade_strategy)
     }
@@ -0,0 +1,419 @@
+//! # 升级审批计划
+//!
+//! 受监管的环境中升级需要先经过审批：
+//!
+//! 1. `check-update plan` 生成升级计划：目标版本、服务包哈希、变更摘要和当前部署状态，
+//!    计划内容的 SHA-256 摘要写入 `digest`，内容被修改后摘要校验失败
+//! 2. 审批人使用 Ed25519 私钥对摘要签名（`approve`，可以在另一台机器上执行）
+//! 3. `auto-upgrade-deploy run --plan` 只在计划与当前状态一致、且带有受信任公钥的有效签名时执行
+
+use crate::upgrade_strategy::UpgradeStrategy;
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use ring::rand::SystemRandom;
+use ring::signature::{ED25519, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+
+/// 升级计划文件格式版本
+pub const PLAN_FORMAT_VERSION: u32 = 1;
+
+/// 签名内容的前缀，避免审批签名被用于其他用途
+const APPROVAL_CONTEXT: &str = "nuwax-upgrade-plan-approval";
+
+/// 计划中的升级类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanUpgradeType {
+    Full,
+    Patch,
+    PatchChain,
+}
+
+impl fmt::Display for PlanUpgradeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanUpgradeType::Full => write!(f, "全量升级"),
+            PlanUpgradeType::Patch => write!(f, "增量升级"),
+            PlanUpgradeType::PatchChain => write!(f, "补丁链升级"),
+        }
+    }
+}
+
+/// 计划中的服务包
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlanPackage {
+    pub url: String,
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+/// 计划内容，摘要覆盖其中所有字段
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlanContent {
+    pub format_version: u32,
+    pub created_at: DateTime<Utc>,
+    /// 生成计划时部署的版本
+    pub current_version: String,
+    pub target_version: String,
+    pub upgrade_type: PlanUpgradeType,
+    /// 按应用顺序排列的服务包
+    pub packages: Vec<PlanPackage>,
+    /// 生成计划时 docker-compose.yml 的 SHA-256，尚未部署时为空
+    #[serde(default)]
+    pub compose_sha256: Option<String>,
+    #[serde(default)]
+    pub release_notes: String,
+    /// 补丁会变更的文件和目录（相对 docker 工作目录），全量升级替换整个服务包时为空
+    #[serde(default)]
+    pub changed_files: Vec<String>,
+}
+
+impl PlanContent {
+    /// 根据升级策略生成计划内容，无需升级时返回错误
+    pub fn from_strategy(
+        strategy: &UpgradeStrategy,
+        current_version: String,
+        compose_sha256: Option<String>,
+        release_notes: String,
+    ) -> Result<Self> {
+        let (upgrade_type, target_version, packages) = strategy_target(strategy)
+            .ok_or_else(|| anyhow!("当前版本 {current_version} 已是最新版本，无需生成升级计划"))?;
+        Ok(Self {
+            format_version: PLAN_FORMAT_VERSION,
+            created_at: Utc::now(),
+            current_version,
+            target_version,
+            upgrade_type,
+            packages,
+            compose_sha256,
+            release_notes,
+            changed_files: match strategy {
+                UpgradeStrategy::FullUpgrade { .. } => Vec::new(),
+                _ => strategy
+                    .get_changed_files()
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            },
+        })
+    }
+
+    /// 升级策略的类型、目标版本和服务包是否与计划一致
+    pub fn matches_strategy(&self, strategy: &UpgradeStrategy) -> bool {
+        strategy_target(strategy).is_some_and(|(upgrade_type, target_version, packages)| {
+            upgrade_type == self.upgrade_type
+                && target_version == self.target_version
+                && packages == self.packages
+        })
+    }
+}
+
+fn strategy_target(
+    strategy: &UpgradeStrategy,
+) -> Option<(PlanUpgradeType, String, Vec<PlanPackage>)> {
+    match strategy {
+        UpgradeStrategy::FullUpgrade {
+            url,
+            hash,
+            target_version,
+            ..
+        } => Some((
+            PlanUpgradeType::Full,
+            target_version.to_string(),
+            vec![PlanPackage {
+                url: url.clone(),
+                hash: Some(hash.clone()),
+            }],
+        )),
+        UpgradeStrategy::PatchUpgrade {
+            patch_info,
+            target_version,
+            ..
+        } => Some((
+            PlanUpgradeType::Patch,
+            target_version.to_string(),
+            vec![PlanPackage {
+                url: patch_info.url.clone(),
+                hash: patch_info.hash.clone(),
+            }],
+        )),
+        UpgradeStrategy::PatchChainUpgrade {
+            steps,
+            target_version,
+            ..
+        } => Some((
+            PlanUpgradeType::PatchChain,
+            target_version.to_string(),
+            steps
+                .iter()
+                .map(|step| PlanPackage {
+                    url: step.patch_info.url.clone(),
+                    hash: step.patch_info.hash.clone(),
+                })
+                .collect(),
+        )),
+        UpgradeStrategy::NoUpgrade { .. } => None,
+    }
+}
+
+/// 审批签名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanApproval {
+    pub approver: String,
+    pub approved_at: DateTime<Utc>,
+    /// 审批人的 Ed25519 公钥（base64）
+    pub public_key: String,
+    /// 对计划摘要、审批人和审批时间的签名（base64）
+    pub signature: String,
+}
+
+impl PlanApproval {
+    fn message(digest: &str, approver: &str, approved_at: &DateTime<Utc>) -> String {
+        format!(
+            "{APPROVAL_CONTEXT}\n{digest}\n{approver}\n{}",
+            approved_at.to_rfc3339()
+        )
+    }
+
+    /// 校验签名是否由 `public_key` 对应的私钥对该计划摘要签署
+    pub fn verify(&self, digest: &str) -> Result<()> {
+        let public_key = STANDARD
+            .decode(&self.public_key)
+            .context("审批公钥不是有效的 base64")?;
+        let signature = STANDARD
+            .decode(&self.signature)
+            .context("审批签名不是有效的 base64")?;
+        let message = Self::message(digest, &self.approver, &self.approved_at);
+        UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(message.as_bytes(), &signature)
+            .map_err(|_| anyhow!("审批人 {} 的签名无效", self.approver))
+    }
+}
+
+/// 升级计划文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradePlan {
+    pub plan: PlanContent,
+    /// 计划内容的摘要，格式为 `sha256:<hex>`
+    pub digest: String,
+    #[serde(default)]
+    pub approvals: Vec<PlanApproval>,
+}
+
+impl UpgradePlan {
+    pub fn new(plan: PlanContent) -> Result<Self> {
+        let digest = plan_digest(&plan)?;
+        Ok(Self {
+            plan,
+            digest,
+            approvals: Vec::new(),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取升级计划: {}", path.display()))?;
+        let plan: Self = serde_json::from_str(&content)
+            .with_context(|| format!("无法解析升级计划: {}", path.display()))?;
+        if plan.plan.format_version > PLAN_FORMAT_VERSION {
+            return Err(anyhow!(
+                "升级计划格式版本 {} 高于当前支持的版本 {}，请升级 nuwax-cli",
+                plan.plan.format_version,
+                PLAN_FORMAT_VERSION
+            ));
+        }
+        Ok(plan)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("无法写入升级计划: {}", path.display()))
+    }
+
+    /// 校验计划内容未被修改
+    pub fn verify_digest(&self) -> Result<()> {
+        let actual = plan_digest(&self.plan)?;
+        if actual != self.digest {
+            return Err(anyhow!(
+                "升级计划内容与摘要不一致，文件可能被修改 (记录 {}, 实际 {})",
+                self.digest,
+                actual
+            ));
+        }
+        Ok(())
+    }
+
+    /// 审批计划，同一公钥的旧审批会被替换
+    pub fn approve(&mut self, key: &ApprovalKey, approver: &str) -> Result<&PlanApproval> {
+        self.verify_digest()?;
+        let approved_at = Utc::now();
+        let message = PlanApproval::message(&self.digest, approver, &approved_at);
+        let public_key = key.public_key();
+        self.approvals
+            .retain(|approval| approval.public_key != public_key);
+        self.approvals.push(PlanApproval {
+            approver: approver.to_string(),
+            approved_at,
+            public_key,
+            signature: STANDARD.encode(key.key_pair.sign(message.as_bytes())),
+        });
+        Ok(&self.approvals[self.approvals.len() - 1])
+    }
+
+    /// 校验摘要，并返回第一个由受信任公钥签署的有效审批
+    pub fn trusted_approval(&self, trusted_keys: &[String]) -> Result<&PlanApproval> {
+        self.verify_digest()?;
+        if trusted_keys.is_empty() {
+            return Err(anyhow!(
+                "未配置受信任的审批公钥 (upgrade_approval.trusted_keys)"
+            ));
+        }
+        if self.approvals.is_empty() {
+            return Err(anyhow!("升级计划尚未审批"));
+        }
+
+        let mut errors = Vec::new();
+        for approval in &self.approvals {
+            if !trusted_keys
+                .iter()
+                .any(|key| key.trim() == approval.public_key)
+            {
+                errors.push(format!("审批人 {} 的公钥不受信任", approval.approver));
+                continue;
+            }
+            match approval.verify(&self.digest) {
+                Ok(()) => return Ok(approval),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        Err(anyhow!("升级计划没有有效的审批: {}", errors.join("; ")))
+    }
+}
+
+/// 计划内容的摘要
+pub fn plan_digest(plan: &PlanContent) -> Result<String> {
+    let content = serde_json::to_vec(plan)?;
+    Ok(format!("sha256:{:x}", Sha256::digest(&content)))
+}
+
+/// 审批人的 Ed25519 私钥，以 base64 编码的 PKCS#8 格式保存
+pub struct ApprovalKey {
+    key_pair: Ed25519KeyPair,
+}
+
+impl ApprovalKey {
+    /// 生成新的审批密钥，返回密钥和需要保存的 base64 私钥
+    pub fn generate() -> Result<(Self, String)> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| anyhow!("生成审批密钥失败"))?;
+        let encoded = STANDARD.encode(pkcs8.as_ref());
+        Ok((Self::from_base64(&encoded)?, encoded))
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let pkcs8 = STANDARD
+            .decode(encoded.trim())
+            .context("审批私钥不是有效的 base64")?;
+        let key_pair =
+            Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|e| anyhow!("审批私钥格式无效: {e}"))?;
+        Ok(Self { key_pair })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let encoded = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取审批私钥: {}", path.display()))?;
+        Self::from_base64(&encoded)
+    }
+
+    /// base64 编码的公钥，配置到部署机器的 `upgrade_approval.trusted_keys`
+    pub fn public_key(&self) -> String {
+        STANDARD.encode(self.key_pair.public_key().as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upgrade_strategy::DownloadType;
+    use crate::version::Version;
+
+    fn full_upgrade(target: &str, hash: &str) -> UpgradeStrategy {
+        UpgradeStrategy::FullUpgrade {
+            url: "https://example.com/docker.zip".to_string(),
+            hash: hash.to_string(),
+            size: None,
+            signature: String::new(),
+            target_version: target.parse::<Version>().unwrap(),
+            download_type: DownloadType::Full,
+        }
+    }
+
+    fn plan() -> UpgradePlan {
+        let content = PlanContent::from_strategy(
+            &full_upgrade("0.1.5", "abc"),
+            "0.1.4".to_string(),
+            Some("c0ffee".to_string()),
+            "修复若干问题".to_string(),
+        )
+        .unwrap();
+        UpgradePlan::new(content).unwrap()
+    }
+
+    #[test]
+    fn test_plan_matches_strategy() {
+        let plan = plan();
+        assert_eq!(plan.plan.upgrade_type, PlanUpgradeType::Full);
+        assert!(plan.plan.matches_strategy(&full_upgrade("0.1.5", "abc")));
+        assert!(!plan.plan.matches_strategy(&full_upgrade("0.1.5", "def")));
+        assert!(!plan.plan.matches_strategy(&full_upgrade("0.1.6", "abc")));
+        assert!(
+            PlanContent::from_strategy(
+                &UpgradeStrategy::NoUpgrade {
+                    target_version: "0.1.4".parse::<Version>().unwrap(),
+                },
+                "0.1.4".to_string(),
+                None,
+                String::new(),
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_plan_approval() {
+        let (key, encoded) = ApprovalKey::generate().unwrap();
+        let trusted = vec![key.public_key()];
+        let mut plan = plan();
+        assert!(plan.trusted_approval(&trusted).is_err());
+
+        plan.approve(&key, "张三").unwrap();
+        // 保存再读取后签名仍然有效
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("plan.json");
+        plan.save(&path).unwrap();
+        let plan = UpgradePlan::load(&path).unwrap();
+        assert_eq!(plan.trusted_approval(&trusted).unwrap().approver, "张三");
+
+        // 同一密钥重新审批时替换旧的审批
+        let mut reapproved = plan.clone();
+        let key = ApprovalKey::from_base64(&encoded).unwrap();
+        reapproved.approve(&key, "张三").unwrap();
+        assert_eq!(reapproved.approvals.len(), 1);
+
+        // 不受信任的公钥
+        let (other, _) = ApprovalKey::generate().unwrap();
+        assert!(plan.trusted_approval(&[other.public_key()]).is_err());
+
+        // 修改计划内容后摘要和签名都失效
+        let mut tampered = plan.clone();
+        tampered.plan.target_version = "0.1.6".to_string();
+        assert!(tampered.trusted_approval(&trusted).is_err());
+        tampered.digest = plan_digest(&tampered.plan).unwrap();
+        assert!(tampered.trusted_approval(&trusted).is_err());
+    }
+}
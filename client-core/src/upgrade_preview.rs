@@ -0,0 +1,232 @@
+//! # 升级影响预览
+//!
+//! 在应用升级前列出将被替换、新增或删除的文件和目录：
+//! - 增量升级：直接根据补丁包的 `PatchOperations` 生成
+//! - 全量升级：对比服务包中的顶层条目与当前工作目录
+//!
+//! 落在受保护路径（用户数据目录、`.env` 等）下的变更会被单独标记，便于升级前确认。
+
+use crate::api_types::PatchOperations;
+use crate::constants::docker;
+use std::collections::BTreeSet;
+use std::fmt::{self, Display};
+use std::path::Path;
+
+/// 变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    /// 覆盖已存在的文件或目录
+    Replace,
+    /// 新增文件或目录
+    Add,
+    /// 删除文件或目录
+    Delete,
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Replace => write!(f, "替换"),
+            Self::Add => write!(f, "新增"),
+            Self::Delete => write!(f, "删除"),
+        }
+    }
+}
+
+/// 单个文件或目录的变更
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    /// 相对于工作目录的路径
+    pub path: String,
+    pub kind: ChangeKind,
+    /// 是否位于受保护路径下
+    pub protected: bool,
+}
+
+/// 升级变更报告
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpgradeChangeReport {
+    pub changes: Vec<FileChange>,
+}
+
+impl UpgradeChangeReport {
+    /// 根据补丁操作生成报告
+    pub fn from_patch_operations(operations: &PatchOperations, work_dir: &Path) -> Self {
+        let mut report = Self::default();
+
+        if let Some(replace) = &operations.replace {
+            for path in replace.files.iter().chain(replace.directories.iter()) {
+                let path = normalize_path(path);
+                let kind = if work_dir.join(&path).exists() {
+                    ChangeKind::Replace
+                } else {
+                    ChangeKind::Add
+                };
+                report.push(path, kind);
+            }
+        }
+
+        if let Some(delete) = &operations.delete {
+            for path in delete.files.iter().chain(delete.directories.iter()) {
+                report.push(normalize_path(path), ChangeKind::Delete);
+            }
+        }
+
+        report
+    }
+
+    /// 根据全量服务包中的条目生成报告（按顶层文件/目录汇总）
+    ///
+    /// 全量升级会清空工作目录中除保留目录外的所有内容，因此当前存在
+    /// 但服务包中没有的顶层条目都会被删除
+    pub fn from_archive_entries<I, S>(entries: I, work_dir: &Path) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let package_entries: BTreeSet<String> = entries
+            .into_iter()
+            .filter_map(|entry| top_level_name(entry.as_ref()))
+            .collect();
+
+        let mut report = Self::default();
+        for name in &package_entries {
+            let kind = if work_dir.join(name).exists() {
+                ChangeKind::Replace
+            } else {
+                ChangeKind::Add
+            };
+            report.push(name.clone(), kind);
+        }
+
+        if let Ok(current_entries) = std::fs::read_dir(work_dir) {
+            let mut removed: Vec<String> = current_entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| !package_entries.contains(name))
+                .filter(|name| !docker::PRESERVED_DIR_NAMES.contains(&name.as_str()))
+                .collect();
+            removed.sort();
+            for name in removed {
+                report.push(name, ChangeKind::Delete);
+            }
+        }
+
+        report
+    }
+
+    fn push(&mut self, path: String, kind: ChangeKind) {
+        let protected = is_protected_path(&path);
+        self.changes.push(FileChange {
+            path,
+            kind,
+            protected,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// 指定类型的变更数量
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|c| c.kind == kind).count()
+    }
+
+    /// 位于受保护路径下的变更
+    pub fn protected_changes(&self) -> impl Iterator<Item = &FileChange> {
+        self.changes.iter().filter(|c| c.protected)
+    }
+}
+
+/// 路径是否位于受保护路径下（用户数据目录或 `.env`）
+pub fn is_protected_path(path: &str) -> bool {
+    let path = normalize_path(path);
+    let first = path.split('/').next().unwrap_or_default();
+    docker::PRESERVED_DIR_NAMES.contains(&first) || path == docker::ENV_FILE_NAME
+}
+
+/// 统一为不带 `./`、`docker/` 前缀和结尾斜杠的相对路径
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches("./").trim_start_matches('/');
+    let path = path
+        .strip_prefix(&format!("{}/", docker::DOCKER_DIR_NAME))
+        .unwrap_or(path);
+    path.trim_end_matches('/').to_string()
+}
+
+/// 服务包条目对应的顶层文件或目录名
+fn top_level_name(entry: &str) -> Option<String> {
+    let path = normalize_path(entry);
+    if path.is_empty() || path == docker::DOCKER_DIR_NAME {
+        return None;
+    }
+    path.split('/').next().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_types::ReplaceOperations;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_patch_operations_report() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("app")).unwrap();
+
+        let operations = PatchOperations {
+            replace: Some(ReplaceOperations {
+                files: vec!["docker/app/main.js".to_string(), ".env".to_string()],
+                directories: vec!["app/".to_string()],
+            }),
+            delete: Some(ReplaceOperations {
+                files: vec![],
+                directories: vec!["data/mysql".to_string()],
+            }),
+        };
+        let report = UpgradeChangeReport::from_patch_operations(&operations, temp_dir.path());
+
+        assert_eq!(report.count(ChangeKind::Add), 2);
+        assert_eq!(report.count(ChangeKind::Replace), 1);
+        assert_eq!(report.count(ChangeKind::Delete), 1);
+        let protected: Vec<&str> = report
+            .protected_changes()
+            .map(|c| c.path.as_str())
+            .collect();
+        assert_eq!(protected, vec![".env", "data/mysql"]);
+    }
+
+    #[test]
+    fn test_archive_entries_report() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("app")).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("upload")).unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("legacy")).unwrap();
+
+        let entries = [
+            "docker/",
+            "docker/app/index.js",
+            "docker/config/init_mysql.sql",
+            "docker/docker-compose.yml",
+        ];
+        let report = UpgradeChangeReport::from_archive_entries(entries, temp_dir.path());
+
+        let summary: Vec<(&str, ChangeKind)> = report
+            .changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("app", ChangeKind::Replace),
+                ("config", ChangeKind::Add),
+                ("docker-compose.yml", ChangeKind::Add),
+                ("legacy", ChangeKind::Delete),
+            ]
+        );
+        assert_eq!(report.protected_changes().count(), 0);
+    }
+}
@@ -0,0 +1,283 @@
+//! # 升级会话录制
+//!
+//! 使用 `--record <FILE>` 时，把升级过程中的外部交互写入会话文件：
+//!
+//! - API 响应原文和服务清单
+//! - 升级策略的决策输入（当前版本、架构、是否强制全量、工作目录状态）、决策依据和结果
+//! - 补丁执行的文件操作
+//! - 差异SQL及生成它的新旧表结构
+//!
+//! 写入前按敏感键规则脱敏（见 [`crate::redact`]）。开发者用 `replay` 命令按录制的数据重新执行决策逻辑，
+//! 不需要客户的部署环境就能复现升级问题。未开始录制时，各记录函数不做任何事。
+
+use crate::architecture::Architecture;
+use crate::redact::{redact_json, redact_url};
+use crate::sql_diff::SqlDialect;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use tracing::debug;
+
+/// 会话文件格式版本
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+
+/// 录制中的会话（由 `--record` 启动）
+static RECORDING: OnceLock<Mutex<UpgradeSession>> = OnceLock::new();
+
+/// 录制的 API 响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub url: String,
+    /// 响应正文，能解析为 JSON 时保存为 JSON
+    pub body: serde_json::Value,
+}
+
+/// 录制的升级策略决策
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedDecision {
+    pub current_version: String,
+    pub force_full: bool,
+    pub architecture: Architecture,
+    /// 决策时工作目录下是否有 docker 目录和 compose 文件
+    pub compose_present: bool,
+    /// 按判断顺序记录的决策依据
+    pub reasons: Vec<String>,
+    /// 决策结果（见 [`crate::upgrade_strategy::UpgradeStrategy::summary`]），失败时为空
+    #[serde(default)]
+    pub strategy: Option<String>,
+    /// 升级会变更的文件和目录（相对 docker 工作目录）
+    #[serde(default)]
+    pub changed_files: Vec<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// 录制的文件操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFileOperation {
+    /// 操作类型: replace_files、replace_directories 或 delete
+    pub operation: String,
+    pub paths: Vec<String>,
+}
+
+/// 录制的差异SQL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedSqlDiff {
+    pub dialect: SqlDialect,
+    pub from_version: String,
+    pub to_version: String,
+    /// 旧版本表结构，首次部署时为空
+    #[serde(default)]
+    pub old_schema: Option<String>,
+    pub new_schema: String,
+    pub diff_sql: String,
+}
+
+/// 升级会话
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeSession {
+    pub format_version: u32,
+    pub recorded_at: DateTime<Utc>,
+    /// 录制时的客户端版本
+    pub client_version: String,
+    /// 录制的命令行参数
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub api_responses: Vec<RecordedResponse>,
+    /// 最近一次获取的服务清单（已脱敏）
+    #[serde(default)]
+    pub manifest: Option<serde_json::Value>,
+    #[serde(default)]
+    pub decisions: Vec<RecordedDecision>,
+    #[serde(default)]
+    pub file_operations: Vec<RecordedFileOperation>,
+    #[serde(default)]
+    pub sql_diffs: Vec<RecordedSqlDiff>,
+}
+
+impl UpgradeSession {
+    pub fn new(client_version: impl Into<String>, command: Vec<String>) -> Self {
+        Self {
+            format_version: SESSION_FORMAT_VERSION,
+            recorded_at: Utc::now(),
+            client_version: client_version.into(),
+            command,
+            api_responses: Vec::new(),
+            manifest: None,
+            decisions: Vec::new(),
+            file_operations: Vec::new(),
+            sql_diffs: Vec::new(),
+        }
+    }
+
+    /// 读取会话文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("无法读取会话文件 {}", path.display()))?;
+        let session: Self = serde_json::from_str(&content)
+            .with_context(|| format!("会话文件格式无效 {}", path.display()))?;
+        if session.format_version > SESSION_FORMAT_VERSION {
+            return Err(anyhow::anyhow!(
+                "会话文件格式版本 {} 高于当前支持的版本 {}，请升级 nuwax-cli",
+                session.format_version,
+                SESSION_FORMAT_VERSION
+            ));
+        }
+        Ok(session)
+    }
+
+    /// 保存会话文件
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("无法写入会话文件 {}", path.display()))
+    }
+}
+
+/// 开始录制升级会话，重复调用会被忽略
+pub fn start_recording(client_version: &str, command: Vec<String>) {
+    let session = UpgradeSession::new(client_version, redact_arguments(command));
+    if RECORDING.set(Mutex::new(session)).is_err() {
+        debug!("升级会话已在录制中，忽略重复启动");
+    }
+}
+
+/// 是否正在录制升级会话
+pub fn is_recording() -> bool {
+    RECORDING.get().is_some()
+}
+
+/// 把录制的会话写入文件，未开始录制时返回 `Ok(false)`
+pub fn save_recording(path: &Path) -> Result<bool> {
+    let Some(session) = RECORDING.get() else {
+        return Ok(false);
+    };
+    let session = session.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    session.save(path)?;
+    Ok(true)
+}
+
+fn with_recording(record: impl FnOnce(&mut UpgradeSession)) {
+    if let Some(session) = RECORDING.get() {
+        record(&mut session.lock().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+/// 记录 API 响应原文
+pub fn record_api_response(url: &str, body: &str) {
+    with_recording(|session| {
+        session.api_responses.push(RecordedResponse {
+            url: redact_url(url),
+            body: redacted_body(body),
+        });
+    });
+}
+
+/// 记录服务清单原文
+pub fn record_manifest(body: &str) {
+    with_recording(|session| session.manifest = Some(redacted_body(body)));
+}
+
+/// 记录升级策略决策
+pub fn record_decision(decision: RecordedDecision) {
+    with_recording(|session| session.decisions.push(decision));
+}
+
+/// 记录补丁执行的文件操作
+pub fn record_file_operation(operation: &str, paths: &[String]) {
+    if paths.is_empty() {
+        return;
+    }
+    with_recording(|session| {
+        session.file_operations.push(RecordedFileOperation {
+            operation: operation.to_string(),
+            paths: paths.to_vec(),
+        });
+    });
+}
+
+/// 记录差异SQL
+pub fn record_sql_diff(diff: RecordedSqlDiff) {
+    with_recording(|session| session.sql_diffs.push(diff));
+}
+
+/// 响应正文脱敏：JSON 按敏感键脱敏，非 JSON 正文原样保存为字符串
+fn redacted_body(body: &str) -> serde_json::Value {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(mut value) => {
+            redact_json(&mut value);
+            value
+        }
+        Err(_) => serde_json::Value::String(body.to_string()),
+    }
+}
+
+/// 命令行参数脱敏：URL 去掉查询参数的值
+fn redact_arguments(command: Vec<String>) -> Vec<String> {
+    command
+        .into_iter()
+        .map(|argument| redact_url(&argument))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::REDACTED;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_session_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("session.json");
+
+        let mut session = UpgradeSession::new("1.0.0", vec!["nuwax-cli".to_string()]);
+        session.manifest = Some(redacted_body(
+            r#"{"version":"0.0.13.2","access_token":"secret-value"}"#,
+        ));
+        session.decisions.push(RecordedDecision {
+            current_version: "0.0.13.1".to_string(),
+            force_full: false,
+            architecture: Architecture::Aarch64,
+            compose_present: true,
+            reasons: vec!["只有修订号不同".to_string()],
+            strategy: Some("增量升级 -> 0.0.13.2".to_string()),
+            changed_files: vec!["app.jar".to_string()],
+            error: None,
+        });
+        session.save(&path).unwrap();
+
+        let loaded = UpgradeSession::load(&path).unwrap();
+        assert_eq!(loaded.decisions.len(), 1);
+        assert_eq!(loaded.decisions[0].architecture, Architecture::Aarch64);
+        let manifest = loaded.manifest.unwrap();
+        assert_eq!(manifest["version"], "0.0.13.2");
+        assert_eq!(manifest["access_token"], REDACTED);
+    }
+
+    #[test]
+    fn test_load_rejects_newer_format() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("session.json");
+
+        let mut session = UpgradeSession::new("1.0.0", Vec::new());
+        session.format_version = SESSION_FORMAT_VERSION + 1;
+        session.save(&path).unwrap();
+
+        assert!(UpgradeSession::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_non_json_body_is_kept_as_string() {
+        assert_eq!(
+            redacted_body("not json"),
+            serde_json::Value::String("not json".to_string())
+        );
+    }
+}
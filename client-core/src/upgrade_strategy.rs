@@ -1,15 +1,18 @@
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::Display;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::{
-    api_types::{EnhancedServiceManifest, PatchPackageInfo},
+    api_types::{EnhancedServiceManifest, PatchChainLink, PatchPackageInfo},
     architecture::Architecture,
     constants::docker::get_compose_file_path,
     constants::docker::get_docker_work_dir,
-    version::Version,
+    redact::redact_url,
+    upgrade_session,
+    version::{Version, VersionComparison},
 };
 use anyhow::Result;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum DownloadType {
@@ -37,6 +40,8 @@ pub enum UpgradeStrategy {
         url: String,
         /// 文件哈希
         hash: String,
+        /// 服务清单声明的文件大小（字节），未声明时为 None
+        size: Option<u64>,
         /// 签名
         signature: String,
         /// 目标版本
@@ -53,6 +58,15 @@ pub enum UpgradeStrategy {
         /// 下载类型
         download_type: DownloadType,
     },
+    /// 补丁链升级：落后多个版本时按顺序应用相邻版本之间的补丁
+    PatchChainUpgrade {
+        /// 按应用顺序排列的补丁
+        steps: Vec<PatchStep>,
+        /// 目标版本
+        target_version: Version,
+        /// 下载类型
+        download_type: DownloadType,
+    },
     /// 无需升级
     NoUpgrade {
         /// 目标版本
@@ -66,12 +80,119 @@ impl UpgradeStrategy {
         let change_files = match self {
             UpgradeStrategy::FullUpgrade { .. } => vec!["data".to_string(),"upload".to_string()],
             UpgradeStrategy::PatchUpgrade { patch_info, .. } => patch_info.get_changed_files(),
+            UpgradeStrategy::PatchChainUpgrade { steps, .. } => {
+                let mut files: Vec<String> = Vec::new();
+                for file in steps
+                    .iter()
+                    .flat_map(|step| step.patch_info.get_changed_files())
+                {
+                    if !files.contains(&file) {
+                        files.push(file);
+                    }
+                }
+                files
+            }
             UpgradeStrategy::NoUpgrade { .. } => {
                 vec![]
             }
         };
         change_files.into_iter().map(PathBuf::from).collect()
     }
+
+    /// 策略的简要描述（类型、目标版本和脱敏后的下载地址），用于升级会话的录制和回放比对
+    pub fn summary(&self) -> String {
+        match self {
+            UpgradeStrategy::FullUpgrade {
+                url,
+                target_version,
+                ..
+            } => format!("全量升级 -> {target_version} ({})", redact_url(url)),
+            UpgradeStrategy::PatchUpgrade {
+                patch_info,
+                target_version,
+                ..
+            } => format!(
+                "增量升级 -> {target_version} ({})",
+                redact_url(&patch_info.url)
+            ),
+            UpgradeStrategy::PatchChainUpgrade {
+                steps,
+                target_version,
+                ..
+            } => {
+                let links: Vec<String> = steps
+                    .iter()
+                    .map(|step| format!("{} -> {}", step.from_version, step.to_version))
+                    .collect();
+                format!("补丁链升级 -> {target_version} ({})", links.join(", "))
+            }
+            UpgradeStrategy::NoUpgrade { target_version } => {
+                format!("无需升级 ({target_version})")
+            }
+        }
+    }
+
+    /// 按应用顺序展开为单步升级策略：补丁链展开为每一步的增量升级，其余策略原样返回
+    pub fn expand_steps(&self) -> Vec<UpgradeStrategy> {
+        match self {
+            UpgradeStrategy::PatchChainUpgrade { steps, .. } => {
+                steps.iter().map(PatchStep::to_strategy).collect()
+            }
+            _ => vec![self.clone()],
+        }
+    }
+}
+
+/// 补丁链中的一步
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchStep {
+    /// 应用补丁前的版本
+    pub from_version: Version,
+    /// 应用补丁后的版本
+    pub to_version: Version,
+    /// 当前架构的补丁包
+    pub patch_info: PatchPackageInfo,
+}
+
+impl PatchStep {
+    /// 转换为单个补丁的增量升级策略，复用单补丁的下载和解压流程
+    pub fn to_strategy(&self) -> UpgradeStrategy {
+        UpgradeStrategy::PatchUpgrade {
+            patch_info: self.patch_info.clone(),
+            target_version: self.to_version.clone(),
+            download_type: DownloadType::Patch,
+        }
+    }
+
+    /// 校验补丁已正确应用：替换的文件和目录存在，删除的文件和目录已不存在
+    pub fn verify_applied(&self, work_dir: &Path) -> Result<()> {
+        let operations = &self.patch_info.operations;
+        if let Some(replace) = &operations.replace {
+            for item in replace.files.iter().chain(&replace.directories) {
+                if !work_dir.join(item).exists() {
+                    return Err(anyhow::anyhow!(
+                        "补丁 {} -> {} 校验失败: 缺少 {}",
+                        self.from_version,
+                        self.to_version,
+                        item
+                    ));
+                }
+            }
+        }
+        if let Some(delete) = &operations.delete {
+            for item in delete.files.iter().chain(&delete.directories) {
+                if work_dir.join(item).exists() {
+                    return Err(anyhow::anyhow!(
+                        "补丁 {} -> {} 校验失败: {} 未被删除",
+                        self.from_version,
+                        self.to_version,
+                        item
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 /// 决策因素分析
@@ -89,13 +210,34 @@ pub struct DecisionFactors {
     pub time_efficiency: f64,
 }
 
+/// 升级策略的决策说明
+#[derive(Debug, Clone)]
+pub struct StrategyExplanation {
+    /// 当前部署版本
+    pub current_version: Version,
+    /// 服务器最新版本
+    pub server_version: Version,
+    /// 当前架构
+    pub architecture: String,
+    /// 是否强制全量升级
+    pub force_full: bool,
+    /// 版本比较结果
+    pub version_comparison: VersionComparison,
+    /// 清单中参与决策的字段（字段名, 值）
+    pub manifest_fields: Vec<(String, String)>,
+    /// 按判断顺序记录的决策依据
+    pub reasons: Vec<String>,
+    /// 决策结果，失败时为错误信息
+    pub strategy: std::result::Result<UpgradeStrategy, String>,
+}
+
 /// 升级策略管理器
 #[derive(Debug)]
 pub struct UpgradeStrategyManager {
     ///server端docker应用升级版本信息
     manifest: EnhancedServiceManifest,
     ///当前客户端版本
-    current_version: String,
+    current_version: Version,
     ///是否强制全量升级
     force_full: bool,
     ///当前客户端架构
@@ -105,7 +247,7 @@ pub struct UpgradeStrategyManager {
 impl UpgradeStrategyManager {
     /// 创建新的升级策略管理器
     pub fn new(
-        current_version: String,
+        current_version: Version,
         force_full: bool,
         manifest: EnhancedServiceManifest,
     ) -> Self {
@@ -117,18 +259,75 @@ impl UpgradeStrategyManager {
         }
     }
 
+    /// 指定决策使用的架构（默认为当前主机架构），用于回放其他主机录制的升级会话
+    pub fn with_architecture(mut self, architecture: Architecture) -> Self {
+        self.architecture = architecture;
+        self
+    }
+
     /// 确定升级策略（简化版本）
     pub fn determine_strategy(&self) -> Result<UpgradeStrategy> {
+        let compose_present = compose_files_present();
+        let mut reasons = Vec::new();
+        let strategy = self.decide(compose_present, &mut reasons);
+        upgrade_session::record_decision(upgrade_session::RecordedDecision {
+            current_version: self.current_version.to_string(),
+            force_full: self.force_full,
+            architecture: self.architecture.clone(),
+            compose_present,
+            reasons,
+            strategy: strategy.as_ref().map(UpgradeStrategy::summary).ok(),
+            changed_files: strategy
+                .as_ref()
+                .map(|strategy| {
+                    strategy
+                        .get_changed_files()
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            error: strategy.as_ref().err().map(|e| e.to_string()),
+        });
+        strategy
+    }
+
+    /// 确定升级策略并记录决策依据，用于排查选择全量或增量升级的原因
+    pub fn explain(&self) -> StrategyExplanation {
+        self.explain_with(compose_files_present())
+    }
+
+    /// 按指定的工作目录状态确定升级策略并记录决策依据，回放升级会话时使用录制的状态
+    pub fn explain_with(&self, compose_present: bool) -> StrategyExplanation {
+        let mut reasons = Vec::new();
+        let strategy = self
+            .decide(compose_present, &mut reasons)
+            .map_err(|e| e.to_string());
+        let version_comparison = self
+            .current_version
+            .compare_detailed(&self.manifest.version);
+        StrategyExplanation {
+            current_version: self.current_version.clone(),
+            server_version: self.manifest.version.clone(),
+            architecture: self.architecture.as_str().to_string(),
+            force_full: self.force_full,
+            version_comparison,
+            manifest_fields: self.manifest_fields(),
+            reasons,
+            strategy,
+        }
+    }
+
+    /// 决策过程，每个判断结果写入 `reasons`
+    fn decide(&self, compose_present: bool, reasons: &mut Vec<String>) -> Result<UpgradeStrategy> {
         info!("🔍 开始升级策略决策");
         info!("   当前版本: {}", self.current_version);
         info!("   服务器版本: {}", self.manifest.version);
         info!("   目标架构: {}", self.architecture.as_str());
         info!("   强制全量: {}", self.force_full);
 
-        // 1. 解析当前版本
-        let current_ver = self.current_version.parse::<Version>()?;
-
-        // 2. 首先与基础服务器版本比较，确定是否需要升级
+        // 1. 首先与基础服务器版本比较，确定是否需要升级
+        let current_ver = self.current_version.clone();
         let server_ver = self.manifest.version.clone();
         //比较当前版本和服务器版本，判断是全量，还是增量升级，还是不需要升级
         let base_comparison = current_ver.compare_detailed(&server_ver);
@@ -136,46 +335,179 @@ impl UpgradeStrategyManager {
         info!("📊 当前版本详细: {:?}", current_ver);
         info!("📊 服务器版本详细: {:?}", server_ver);
         info!("📊 基础版本比较结果: {:?}", base_comparison);
+        reasons.push(format!(
+            "当前版本 {} 与服务器版本 {} 的比较结果: {:?}",
+            current_ver, server_ver, base_comparison
+        ));
 
-        // 3. 强制全量升级
+        // 2. 强制全量升级
         if self.force_full {
             info!("🔄 强制执行全量升级");
+            reasons.push("指定了强制全量升级（--force / --force-full）".to_string());
             return self.select_full_upgrade_strategy();
         }
         //判断工作目录下,是否有docker目录,如果没有docker目录,则也使用全量升级
-        let work_dir = get_docker_work_dir();
-        let compose_file_path = get_compose_file_path();
-        if !work_dir.exists() || !compose_file_path.exists() {
+        if !compose_present {
             info!("❌ 工作目录下没有docker目录或compose文件，选择全量升级策略");
+            reasons.push(format!(
+                "未找到 docker 目录或 compose 文件 ({})，只能全量升级",
+                get_compose_file_path().display()
+            ));
             return self.select_full_upgrade_strategy();
         }
 
-        // 4. 根据版本比较结果决策
+        // 3. 根据版本比较结果决策
         match base_comparison {
-            crate::version::VersionComparison::Equal | crate::version::VersionComparison::Newer => {
+            crate::version::VersionComparison::Equal => {
                 info!("✅ 当前版本已是最新，无需升级");
+                reasons.push("当前版本与服务器版本相同，无需升级".to_string());
                 Ok(UpgradeStrategy::NoUpgrade {
                     target_version: self.manifest.version.clone(),
                 })
             }
+            crate::version::VersionComparison::Newer => {
+                warn!(
+                    "⚠️ 服务器版本 {} 低于当前版本 {}，不会降级",
+                    server_ver, current_ver
+                );
+                reasons.push(format!(
+                    "服务器版本 {} 低于当前版本 {}，升级不会降级版本",
+                    server_ver, current_ver
+                ));
+                Ok(UpgradeStrategy::NoUpgrade {
+                    target_version: self.manifest.version.clone(),
+                })
+            }
+            _ if self.chain_starts_from(&current_ver) => {
+                // 服务器提供了从当前版本开始的补丁链
+                info!("🔗 选择补丁链升级策略");
+                reasons.push(format!(
+                    "补丁链中有从 {} 开始的环节，按补丁链升级",
+                    current_ver
+                ));
+                self.select_patch_chain_strategy(&current_ver)
+            }
             crate::version::VersionComparison::PatchUpgradeable => {
                 // 可以进行增量升级
                 if !self.has_patch_for_architecture() {
                     info!("📦 当前架构无增量升级包，选择全量升级策略");
+                    reasons.push(format!(
+                        "只有修订号不同，但清单中没有 {} 架构的补丁包，选择全量升级",
+                        self.architecture.as_str()
+                    ));
                     self.select_full_upgrade_strategy()
                 } else {
                     info!("⚡ 选择增量升级策略");
+                    reasons.push(format!(
+                        "只有修订号不同，且清单中有 {} 架构的补丁包，选择增量升级",
+                        self.architecture.as_str()
+                    ));
                     self.select_patch_upgrade_strategy()
                 }
             }
             crate::version::VersionComparison::FullUpgradeRequired => {
                 // 需要全量升级
                 info!("📦 选择全量升级策略");
+                reasons.push(format!(
+                    "基础版本不同（{} -> {}），补丁只适用于同一基础版本，选择全量升级",
+                    current_ver.base_version_string(),
+                    server_ver.base_version_string()
+                ));
                 self.select_full_upgrade_strategy()
             }
         }
     }
 
+    /// 清单中参与决策的字段
+    fn manifest_fields(&self) -> Vec<(String, String)> {
+        let present = |value: bool| if value { "有" } else { "无" }.to_string();
+        let manifest = &self.manifest;
+        let mut fields = vec![
+            ("version".to_string(), manifest.version.to_string()),
+            ("release_date".to_string(), manifest.release_date.clone()),
+            (
+                "packages.full".to_string(),
+                manifest
+                    .packages
+                    .as_ref()
+                    .map(|packages| packages.full.url.clone())
+                    .unwrap_or_else(|| present(false)),
+            ),
+        ];
+        for (arch, platform) in [
+            (
+                "x86_64",
+                manifest
+                    .platforms
+                    .as_ref()
+                    .and_then(|platforms| platforms.x86_64.as_ref()),
+            ),
+            (
+                "aarch64",
+                manifest
+                    .platforms
+                    .as_ref()
+                    .and_then(|platforms| platforms.aarch64.as_ref()),
+            ),
+        ] {
+            fields.push((
+                format!("platforms.{arch}"),
+                platform
+                    .map(|platform| platform.url.clone())
+                    .unwrap_or_else(|| present(false)),
+            ));
+        }
+        for (arch, patch) in [
+            (
+                "x86_64",
+                manifest
+                    .patch
+                    .as_ref()
+                    .and_then(|patch| patch.x86_64.as_ref()),
+            ),
+            (
+                "aarch64",
+                manifest
+                    .patch
+                    .as_ref()
+                    .and_then(|patch| patch.aarch64.as_ref()),
+            ),
+        ] {
+            fields.push((
+                format!("patch.{arch}"),
+                patch
+                    .map(|patch| patch.url.clone())
+                    .unwrap_or_else(|| present(false)),
+            ));
+        }
+        let links: Vec<String> = manifest
+            .patch_chain
+            .iter()
+            .map(|link| {
+                let available = self.chain_patch_for_architecture(link).is_some();
+                format!(
+                    "{} -> {}{}",
+                    link.from_version,
+                    link.to_version,
+                    if available {
+                        ""
+                    } else {
+                        "（当前架构无补丁）"
+                    }
+                )
+            })
+            .collect();
+        fields.push((
+            "patch_chain".to_string(),
+            if links.is_empty() {
+                present(false)
+            } else {
+                links.join(", ")
+            },
+        ));
+        fields
+    }
+
     /// 选择全量升级策略
     pub fn select_full_upgrade_strategy(&self) -> Result<UpgradeStrategy> {
         debug!("🔍 选择全量升级策略");
@@ -187,7 +519,12 @@ impl UpgradeStrategyManager {
             debug!("📦 使用架构特定的全量包: {}", &platform_info.url);
             Ok(UpgradeStrategy::FullUpgrade {
                 url: platform_info.url.clone(),
-                hash: "external".to_string(), // 平台包通常没有预设哈希
+                // 平台包通常没有预设哈希
+                hash: platform_info
+                    .hash
+                    .clone()
+                    .unwrap_or_else(|| "external".to_string()),
+                size: platform_info.size,
                 signature: platform_info.signature.clone(),
                 target_version: self.manifest.version.clone(),
                 download_type: DownloadType::Full,
@@ -199,6 +536,7 @@ impl UpgradeStrategyManager {
                 Ok(UpgradeStrategy::FullUpgrade {
                     url: full_info.url.clone(),
                     hash: full_info.hash.clone(),
+                    size: Some(full_info.size),
                     signature: full_info.signature.clone(),
                     target_version: self.manifest.version.clone(),
                     download_type: DownloadType::Full,
@@ -224,6 +562,109 @@ impl UpgradeStrategyManager {
         })
     }
 
+    /// 选择补丁链升级策略，补丁链只有一步时按普通增量升级处理
+    pub fn select_patch_chain_strategy(
+        &self,
+        current_version: &Version,
+    ) -> Result<UpgradeStrategy> {
+        let mut steps = self
+            .resolve_patch_chain(current_version)
+            .map_err(|e| anyhow::anyhow!("{}，可使用 --force-full 跳过补丁直接全量升级", e))?;
+
+        for step in &steps {
+            debug!(
+                "📦 补丁 {} -> {}: {}",
+                step.from_version, step.to_version, step.patch_info.url
+            );
+        }
+        if steps.len() == 1 {
+            return Ok(steps.remove(0).to_strategy());
+        }
+        Ok(UpgradeStrategy::PatchChainUpgrade {
+            steps,
+            target_version: self.manifest.version.clone(),
+            download_type: DownloadType::Patch,
+        })
+    }
+
+    /// 解析从当前版本到服务器版本的补丁链
+    ///
+    /// 按版本广度优先搜索，得到步数最少的补丁序列；只使用当前架构有补丁包的环节
+    pub fn resolve_patch_chain(&self, current_version: &Version) -> Result<Vec<PatchStep>> {
+        let target = &self.manifest.version;
+        // 到达版本 -> (上一版本, 补丁包)
+        let mut previous: BTreeMap<Version, (Version, &PatchPackageInfo)> = BTreeMap::new();
+        let mut queue = VecDeque::from([current_version.clone()]);
+
+        while let Some(version) = queue.pop_front() {
+            if &version == target {
+                break;
+            }
+            for link in &self.manifest.patch_chain {
+                if link.from_version != version
+                    || link.to_version <= version
+                    || &link.to_version > target
+                    || previous.contains_key(&link.to_version)
+                {
+                    continue;
+                }
+                if let Some(patch_info) = self.chain_patch_for_architecture(link) {
+                    previous.insert(link.to_version.clone(), (version.clone(), patch_info));
+                    queue.push_back(link.to_version.clone());
+                }
+            }
+        }
+
+        if !previous.contains_key(target) {
+            let reached = previous
+                .keys()
+                .max()
+                .cloned()
+                .unwrap_or_else(|| current_version.clone());
+            return Err(anyhow::anyhow!(
+                "补丁链不完整: 只能从 {} 升级到 {}，缺少到 {} 的 {} 架构补丁",
+                current_version,
+                reached,
+                target,
+                self.architecture.as_str()
+            ));
+        }
+
+        let mut steps = Vec::new();
+        let mut version = target.clone();
+        while &version != current_version {
+            let (from_version, patch_info) = &previous[&version];
+            steps.push(PatchStep {
+                from_version: from_version.clone(),
+                to_version: version.clone(),
+                patch_info: (*patch_info).clone(),
+            });
+            version = from_version.clone();
+        }
+        steps.reverse();
+        Ok(steps)
+    }
+
+    /// 补丁链中是否有从指定版本开始的环节
+    fn chain_starts_from(&self, version: &Version) -> bool {
+        self.manifest
+            .patch_chain
+            .iter()
+            .any(|link| &link.from_version == version)
+    }
+
+    /// 获取补丁链环节中当前架构的补丁包
+    fn chain_patch_for_architecture<'a>(
+        &self,
+        link: &'a PatchChainLink,
+    ) -> Option<&'a PatchPackageInfo> {
+        match self.architecture {
+            Architecture::X86_64 => link.x86_64.as_ref(),
+            Architecture::Aarch64 => link.aarch64.as_ref(),
+            Architecture::Unsupported(_) => None,
+        }
+    }
+
     /// 获取指定架构的平台包信息
     fn get_platform_package<'a>(&self) -> Result<crate::api_types::PlatformPackageInfo> {
         if let Some(platforms) = self.manifest.platforms.as_ref() {
@@ -278,6 +719,11 @@ impl UpgradeStrategyManager {
     }
 }
 
+/// 工作目录下是否有 docker 目录和 compose 文件
+fn compose_files_present() -> bool {
+    get_docker_work_dir().exists() && get_compose_file_path().exists()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -304,10 +750,14 @@ mod tests {
                 x86_64: Some(PlatformPackageInfo {
                     signature: "x86_64_signature".to_string(),
                     url: "https://example.com/x86_64/docker.zip".to_string(),
+                    size: None,
+                    hash: None,
                 }),
                 aarch64: Some(PlatformPackageInfo {
                     signature: "aarch64_signature".to_string(),
                     url: "https://example.com/aarch64/docker.zip".to_string(),
+                    size: None,
+                    hash: None,
                 }),
             }),
             patch: Some(PatchInfo {
@@ -328,6 +778,7 @@ mod tests {
                             directories: vec!["old-files/front/".to_string()],
                         }),
                     },
+                    operations_url: None,
                     notes: None,
                 }),
                 aarch64: Some(PatchPackageInfo {
@@ -347,9 +798,38 @@ mod tests {
                             directories: vec!["old-files/front/".to_string()],
                         }),
                     },
+                    operations_url: None,
                     notes: None,
                 }),
             }),
+            patch_chain: Vec::new(),
+        }
+    }
+
+    // 创建补丁链中的一环，两个架构使用相同的补丁内容
+    fn chain_link(from: &str, to: &str) -> PatchChainLink {
+        let patch = PatchPackageInfo {
+            url: format!("https://example.com/patches/{from}-{to}.tar.gz"),
+            hash: None,
+            signature: None,
+            operations: PatchOperations {
+                replace: Some(ReplaceOperations {
+                    files: vec![format!("app-{to}.jar")],
+                    directories: vec![],
+                }),
+                delete: Some(ReplaceOperations {
+                    files: vec![format!("app-{from}.jar")],
+                    directories: vec![],
+                }),
+            },
+            operations_url: None,
+            notes: None,
+        };
+        PatchChainLink {
+            from_version: from.parse().unwrap(),
+            to_version: to.parse().unwrap(),
+            x86_64: Some(patch.clone()),
+            aarch64: Some(patch),
         }
     }
 
@@ -381,7 +861,7 @@ mod tests {
         let _temp_dir = setup_test_environment();
 
         let manager =
-            UpgradeStrategyManager::new("0.0.13.2".to_string(), false, create_test_manifest());
+            UpgradeStrategyManager::new("0.0.13.2".parse().unwrap(), false, create_test_manifest());
 
         // 当前版本与服务器版本相同
         let strategy = manager.determine_strategy().unwrap();
@@ -395,7 +875,7 @@ mod tests {
         let _temp_dir = setup_test_environment();
 
         let manager =
-            UpgradeStrategyManager::new("0.0.13.4".to_string(), false, create_test_manifest());
+            UpgradeStrategyManager::new("0.0.13.4".parse().unwrap(), false, create_test_manifest());
 
         // 当前版本比服务器版本新
         let strategy = manager.determine_strategy().unwrap();
@@ -409,7 +889,7 @@ mod tests {
         let _temp_dir = setup_test_environment();
 
         let manager =
-            UpgradeStrategyManager::new("0.0.12".to_string(), false, create_test_manifest());
+            UpgradeStrategyManager::new("0.0.12".parse().unwrap(), false, create_test_manifest());
 
         // 不同基础版本，需要全量升级
         let strategy = manager.determine_strategy().unwrap();
@@ -433,7 +913,7 @@ mod tests {
         let _temp_dir = setup_test_environment();
 
         let manager =
-            UpgradeStrategyManager::new("0.0.13".to_string(), false, create_test_manifest());
+            UpgradeStrategyManager::new("0.0.13".parse().unwrap(), false, create_test_manifest());
 
         // 相同基础版本，可以增量升级
         let strategy = manager.determine_strategy().unwrap();
@@ -452,11 +932,168 @@ mod tests {
         let _temp_dir = setup_test_environment();
 
         let manager =
-            UpgradeStrategyManager::new("0.0.13.2".to_string(), true, create_test_manifest());
+            UpgradeStrategyManager::new("0.0.13.2".parse().unwrap(), true, create_test_manifest());
 
         // 强制全量升级，即使可以增量升级
         let strategy = manager.determine_strategy().unwrap();
 
         assert!(matches!(strategy, UpgradeStrategy::FullUpgrade { .. }));
     }
+
+    #[test]
+    fn test_explain_records_reasons() {
+        let _temp_dir = setup_test_environment();
+
+        let manager =
+            UpgradeStrategyManager::new("0.0.12".parse().unwrap(), true, create_test_manifest());
+        let explanation = manager.explain();
+
+        assert_eq!(
+            explanation.version_comparison,
+            VersionComparison::FullUpgradeRequired
+        );
+        assert_eq!(explanation.reasons.len(), 2);
+        assert!(explanation.reasons[1].contains("强制全量升级"));
+        assert!(matches!(
+            explanation.strategy,
+            Ok(UpgradeStrategy::FullUpgrade { .. })
+        ));
+        assert!(
+            explanation
+                .manifest_fields
+                .contains(&("patch_chain".to_string(), "无".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_server_version_lower_than_current() {
+        let _temp_dir = setup_test_environment();
+
+        // 按数值比较：0.0.13.10 高于服务器的 0.0.13.2，不会降级
+        let manager = UpgradeStrategyManager::new(
+            "0.0.13.10".parse().unwrap(),
+            false,
+            create_test_manifest(),
+        );
+        let explanation = manager.explain();
+
+        assert_eq!(explanation.version_comparison, VersionComparison::Newer);
+        assert!(explanation.reasons[1].contains("不会降级"));
+        assert!(matches!(
+            explanation.strategy,
+            Ok(UpgradeStrategy::NoUpgrade { .. })
+        ));
+    }
+
+    #[test]
+    fn test_explain_with_recorded_environment() {
+        // 回放时不依赖本机的工作目录和架构
+        let manager =
+            UpgradeStrategyManager::new("0.0.13.1".parse().unwrap(), false, create_test_manifest())
+                .with_architecture(Architecture::Aarch64);
+
+        let explanation = manager.explain_with(true);
+        let strategy = explanation.strategy.unwrap();
+        assert_eq!(
+            strategy.summary(),
+            "增量升级 -> 0.0.13.2 (https://example.com/patches/aarch64-patch.tar.gz)"
+        );
+
+        let explanation = manager.explain_with(false);
+        assert!(explanation.reasons[1].contains("compose 文件"));
+        assert_eq!(
+            explanation.strategy.unwrap().summary(),
+            "全量升级 -> 0.0.13.2 (https://example.com/aarch64/docker.zip)"
+        );
+    }
+
+    #[test]
+    fn test_patch_chain_upgrade() {
+        let _temp_dir = setup_test_environment();
+
+        let mut manifest = create_test_manifest();
+        manifest.patch_chain = vec![
+            chain_link("0.0.13.1", "0.0.13.2"),
+            chain_link("0.0.13", "0.0.13.1"),
+        ];
+        let manager = UpgradeStrategyManager::new("0.0.13".parse().unwrap(), false, manifest);
+
+        let strategy = manager.determine_strategy().unwrap();
+
+        match &strategy {
+            UpgradeStrategy::PatchChainUpgrade { steps, .. } => {
+                let versions: Vec<String> = steps
+                    .iter()
+                    .map(|step| format!("{}->{}", step.from_version, step.to_version))
+                    .collect();
+                assert_eq!(versions, ["0.0.13.0->0.0.13.1", "0.0.13.1->0.0.13.2"]);
+            }
+            _ => panic!("应该选择补丁链升级策略"),
+        }
+        assert_eq!(strategy.expand_steps().len(), 2);
+        assert_eq!(
+            strategy.get_changed_files(),
+            ["app-0.0.13.1.jar", "app-0.0.13.jar", "app-0.0.13.2.jar"]
+                .map(PathBuf::from)
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn test_patch_chain_prefers_fewest_steps() {
+        let mut manifest = create_test_manifest();
+        manifest.patch_chain = vec![
+            chain_link("0.0.13", "0.0.13.1"),
+            chain_link("0.0.13.1", "0.0.13.2"),
+            chain_link("0.0.13", "0.0.13.2"),
+        ];
+        let manager = UpgradeStrategyManager::new("0.0.13".parse().unwrap(), false, manifest);
+
+        let steps = manager
+            .resolve_patch_chain(&"0.0.13".parse().unwrap())
+            .unwrap();
+
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].to_version, "0.0.13.2".parse::<Version>().unwrap());
+    }
+
+    #[test]
+    fn test_broken_patch_chain() {
+        let _temp_dir = setup_test_environment();
+
+        let mut manifest = create_test_manifest();
+        manifest.patch_chain = vec![chain_link("0.0.13", "0.0.13.1")];
+        let manager = UpgradeStrategyManager::new("0.0.13".parse().unwrap(), false, manifest);
+
+        // 补丁链断开时报错，并提示使用 --force-full
+        let error = manager.determine_strategy().unwrap_err().to_string();
+        assert!(error.contains("--force-full"), "{error}");
+
+        let mut manifest = create_test_manifest();
+        manifest.patch_chain = vec![chain_link("0.0.13", "0.0.13.1")];
+        let manager = UpgradeStrategyManager::new("0.0.13".parse().unwrap(), true, manifest);
+
+        let strategy = manager.determine_strategy().unwrap();
+        assert!(matches!(strategy, UpgradeStrategy::FullUpgrade { .. }));
+    }
+
+    #[test]
+    fn test_patch_step_verify_applied() {
+        let temp_dir = TempDir::new().unwrap();
+        let link = chain_link("0.0.13", "0.0.13.1");
+        let step = PatchStep {
+            from_version: link.from_version.clone(),
+            to_version: link.to_version.clone(),
+            patch_info: link.x86_64.clone().unwrap(),
+        };
+
+        fs::write(temp_dir.path().join("app-0.0.13.jar"), "old").unwrap();
+        assert!(step.verify_applied(temp_dir.path()).is_err());
+
+        fs::write(temp_dir.path().join("app-0.0.13.1.jar"), "new").unwrap();
+        assert!(step.verify_applied(temp_dir.path()).is_err());
+
+        fs::remove_file(temp_dir.path().join("app-0.0.13.jar")).unwrap();
+        assert!(step.verify_applied(temp_dir.path()).is_ok());
+    }
 }
@@ -0,0 +1,184 @@
+//! # 用户修改过的服务文件
+//!
+//! 全量升级会清空 docker 目录后重新解压，用户直接改过的服务文件（如 nginx 配置、compose 文件）
+//! 会被新版本覆盖。服务包存储为每个全量解压的版本记录了文件清单（相对路径 → SHA-256），
+//! 升级前把工作目录中的文件与当前版本的清单比对，哈希不一致的就是用户修改过的文件，
+//! 先打包保存再继续升级。
+//!
+//! 受保护目录（upload/、data/ 等）和 `.env` 不在清单中，由各自的保护机制处理；清单中没有的、
+//! 用户新增的文件不在检测范围内。当前版本没有清单时（如通过补丁升级到达）使用同一基础版本
+//! 最近的清单，补丁更新过的文件也会被列出。
+
+use crate::package_store::{PackageStore, digest_file};
+use crate::safe_path::resolve_entry_path;
+use crate::version::Version;
+use anyhow::Result;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// 与清单不一致的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModifiedFile {
+    /// 相对工作目录的路径（使用 `/` 分隔）
+    pub relative: String,
+    /// 工作目录下的实际路径
+    pub path: PathBuf,
+    /// 当前文件大小（字节）
+    pub size: u64,
+}
+
+/// 工作目录与文件清单的比对结果
+#[derive(Debug, Clone, Default)]
+pub struct ModificationReport {
+    /// 比对使用的清单版本
+    pub manifest_version: String,
+    /// 内容与清单不一致的文件
+    pub modified: Vec<ModifiedFile>,
+    /// 清单中有、工作目录中已删除的文件数
+    pub missing: usize,
+}
+
+impl ModificationReport {
+    /// 修改过的文件总大小
+    pub fn modified_bytes(&self) -> u64 {
+        self.modified.iter().map(|file| file.size).sum()
+    }
+}
+
+/// 找出工作目录中相对 `version` 的文件清单被修改过的文件，没有可用清单时返回 None
+///
+/// `skip` 接收相对路径，返回 true 的文件不比对（如受保护目录）
+pub fn find_user_modifications(
+    store: &PackageStore,
+    work_dir: &Path,
+    version: &Version,
+    skip: impl Fn(&str) -> bool,
+) -> Result<Option<ModificationReport>> {
+    let Some(tree) = store.tree_for_version(version)? else {
+        return Ok(None);
+    };
+
+    let mut report = ModificationReport {
+        manifest_version: tree.version,
+        ..Default::default()
+    };
+    for (relative, hash) in &tree.entries {
+        if skip(relative) {
+            continue;
+        }
+        let path = resolve_entry_path(work_dir, relative)?;
+        // 符号链接不入库，被替换为符号链接的文件按修改处理
+        let Ok(metadata) = path.symlink_metadata() else {
+            report.missing += 1;
+            continue;
+        };
+        if !metadata.is_file() {
+            report.modified.push(ModifiedFile {
+                relative: relative.clone(),
+                path,
+                size: 0,
+            });
+            continue;
+        }
+        let (actual_hash, _, size) = digest_file(&path)?;
+        if actual_hash != *hash {
+            report.modified.push(ModifiedFile {
+                relative: relative.clone(),
+                path,
+                size,
+            });
+        }
+    }
+    Ok(Some(report))
+}
+
+/// 把修改过的文件按相对路径打包为 tar.gz
+pub fn archive_user_modifications(report: &ModificationReport, archive_path: &Path) -> Result<()> {
+    if let Some(parent) = archive_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = File::create(archive_path)?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    archive.follow_symlinks(false);
+    for file in &report.modified {
+        archive
+            .append_path_with_name(&file.path, &file.relative)
+            .map_err(|e| anyhow::anyhow!("打包用户修改的文件失败 {}: {}", file.relative, e))?;
+    }
+    archive.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_and_archive_user_modifications() {
+        let temp = TempDir::new().unwrap();
+        let store = PackageStore::new(temp.path().join("store"));
+        let work = temp.path().join("docker");
+        for (path, content) in [
+            ("docker-compose.yml", "services: {}"),
+            ("config/nginx.conf", "listen 80;"),
+            ("config/removed.conf", "old"),
+            ("upload/logo.png", "png"),
+        ] {
+            let path = work.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        store
+            .ingest_dir(&work, "0.0.13.0", |path| path.starts_with("upload/"))
+            .unwrap();
+        let current: Version = "0.0.13.0".parse().unwrap();
+        let report = find_user_modifications(&store, &work, &current, |_| false)
+            .unwrap()
+            .unwrap();
+        assert!(report.modified.is_empty());
+
+        // 写入新文件替换原文件，与编辑器保存的方式一致
+        let nginx = work.join("config/nginx.conf");
+        fs::remove_file(&nginx).unwrap();
+        fs::write(&nginx, "listen 8080;").unwrap();
+        fs::remove_file(work.join("config/removed.conf")).unwrap();
+        fs::write(work.join("upload/logo.png"), "new png").unwrap();
+
+        // 补丁升级后的版本使用同一基础版本的清单
+        let patched: Version = "0.0.13.2".parse().unwrap();
+        let report = find_user_modifications(&store, &work, &patched, |_| false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.manifest_version, "0.0.13.0");
+        let modified: Vec<_> = report
+            .modified
+            .iter()
+            .map(|f| f.relative.as_str())
+            .collect();
+        assert_eq!(modified, ["config/nginx.conf"]);
+        assert_eq!(report.missing, 1);
+        assert_eq!(report.modified_bytes(), 12);
+
+        let archive_path = temp.path().join("user-modifications/0.0.13.2.tar.gz");
+        archive_user_modifications(&report, &archive_path).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(
+            File::open(&archive_path).unwrap(),
+        ));
+        let names: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["config/nginx.conf"]);
+
+        let other: Version = "0.0.14.0".parse().unwrap();
+        assert!(
+            find_user_modifications(&store, &work, &other, |_| false)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -56,11 +56,13 @@ impl FromStr for Version {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
         if s.is_empty() {
             return Err(anyhow::anyhow!("版本号不能为空"));
         }
-        let version =
-            Version::parse_version(s).map_err(|e| anyhow::anyhow!("版本号解析失败: {}", e))?;
+        let version = Version::parse_version(s).map_err(|_| {
+            anyhow::anyhow!("无效的版本号 '{s}'，应为 x.y.z 或 x.y.z.build 格式（可带 v 前缀）")
+        })?;
         Ok(version)
     }
 }
@@ -301,6 +303,16 @@ mod tests {
         // 测试相等
         let v4 = Version::from_str("0.0.13.5").unwrap();
         assert_eq!(v1, v4);
+
+        // 按数值而不是字符串比较，三段式与 build 为 0 的四段式相同
+        assert!(Version::from_str("0.9.10").unwrap() > Version::from_str("0.9.9").unwrap());
+        assert_eq!(
+            Version::from_str("v0.9.9").unwrap(),
+            Version::from_str("0.9.9.0").unwrap()
+        );
+
+        let err = Version::from_str("0.9.x").unwrap_err().to_string();
+        assert!(err.contains("0.9.x"), "{err}");
     }
 
     #[test]
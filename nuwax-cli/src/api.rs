@@ -0,0 +1,380 @@
+//! # 库调用接口
+//!
+//! 面向桌面端等图形界面的稳定接口：参数使用普通结构体，结果以类型化的数据
+//! 和事件返回，不向终端打印内容，也不需要调用方组装 [`CliApp`] 的内部组件。
+//!
+//! ```no_run
+//! # async fn demo() -> anyhow::Result<()> {
+//! use nuwax_cli::CliApp;
+//! use nuwax_cli::api::{self, CheckUpdateParams};
+//!
+//! let app = CliApp::new_with_auto_config().await?;
+//! let info = api::check_update(&app, CheckUpdateParams::default()).await?;
+//! println!("{} -> {}", info.current_version, info.target_version);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::app::CliApp;
+use crate::commands::{backup, update};
+use crate::docker_service::DockerService;
+use crate::project_info::version_info;
+use crate::ui_support::{DownloadProgress, DownloadStatus};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use client_core::database::{BackupQuery, BackupStatus, BackupType};
+use client_core::downloader;
+use client_core::upgrade_strategy::UpgradeStrategy;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub use crate::docker_service::{ContainerStatus, HealthReport, HealthScope, ServiceStatus};
+
+/// 检查更新参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CheckUpdateParams {
+    /// 强制使用全量升级包
+    pub force_full: bool,
+}
+
+/// 升级类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateKind {
+    Full,
+    Patch,
+    None,
+}
+
+/// 更新检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub target_version: String,
+    pub kind: UpdateKind,
+    /// 升级包下载地址，无需升级时为 None
+    pub download_url: Option<String>,
+}
+
+impl UpdateInfo {
+    fn from_strategy(current_version: String, strategy: &UpgradeStrategy) -> Self {
+        let (kind, target_version, download_url) = match strategy {
+            UpgradeStrategy::FullUpgrade {
+                url,
+                target_version,
+                ..
+            } => (UpdateKind::Full, target_version, Some(url.clone())),
+            UpgradeStrategy::PatchUpgrade {
+                patch_info,
+                target_version,
+                ..
+            } => (
+                UpdateKind::Patch,
+                target_version,
+                Some(patch_info.url.clone()),
+            ),
+            UpgradeStrategy::PatchChainUpgrade {
+                steps,
+                target_version,
+                ..
+            } => (
+                UpdateKind::Patch,
+                target_version,
+                steps.first().map(|step| step.patch_info.url.clone()),
+            ),
+            UpgradeStrategy::NoUpgrade { target_version } => {
+                (UpdateKind::None, target_version, None)
+            }
+        };
+        Self {
+            current_version,
+            target_version: target_version.to_string(),
+            kind,
+            download_url,
+        }
+    }
+
+    pub fn has_update(&self) -> bool {
+        self.kind != UpdateKind::None
+    }
+}
+
+/// 升级参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpgradeParams {
+    /// 强制使用全量升级包
+    pub force_full: bool,
+}
+
+/// 升级过程事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UpgradeEvent {
+    /// 已获取更新信息
+    Checked { info: UpdateInfo },
+    /// 开始下载升级包
+    DownloadStarted { url: String, path: PathBuf },
+    /// 下载进度
+    Downloading { progress: DownloadProgress },
+    /// 升级包已就绪
+    Completed { path: PathBuf },
+}
+
+/// 升级结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeResult {
+    pub info: UpdateInfo,
+    /// 已下载的升级包路径，无需升级时为 None
+    pub package_path: Option<PathBuf>,
+}
+
+/// 列出备份参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ListBackupsParams {
+    /// 只列出指定类型的备份
+    pub backup_type: Option<BackupType>,
+    /// 列出所有命名空间的备份，默认只列出当前项目命名空间的备份
+    #[serde(default)]
+    pub all_namespaces: bool,
+}
+
+/// 备份摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupSummary {
+    pub id: i64,
+    pub backup_type: BackupType,
+    pub status: BackupStatus,
+    pub service_version: String,
+    pub file_path: PathBuf,
+    /// 备份文件是否仍然存在
+    pub file_exists: bool,
+    pub created_at: DateTime<Utc>,
+    /// 备份所属的项目命名空间，早期备份为空
+    pub namespace: Option<String>,
+}
+
+/// 回滚参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollbackParams {
+    pub backup_id: i64,
+    /// 同时回滚 data 目录（MySQL、Redis 等数据）
+    pub rollback_data: bool,
+    /// 同时恢复配置文件和本地数据库
+    pub restore_system: bool,
+    /// 回滚完成后启动服务
+    pub auto_start_service: bool,
+    /// 允许恢复其他命名空间（项目）的备份
+    #[serde(default)]
+    pub allow_other_namespace: bool,
+}
+
+impl RollbackParams {
+    pub fn new(backup_id: i64) -> Self {
+        Self {
+            backup_id,
+            rollback_data: false,
+            restore_system: false,
+            auto_start_service: true,
+            allow_other_namespace: false,
+        }
+    }
+}
+
+/// 客户端和部署状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusInfo {
+    pub client_version: String,
+    pub docker_service_version: String,
+    pub client_uuid: String,
+    /// docker-compose.yml 是否存在，不存在时尚未部署服务
+    pub compose_file_exists: bool,
+}
+
+/// 健康检查参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HealthCheckParams {
+    /// 只检查这些服务，为空表示检查全部服务
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// 不检查这些服务
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// 健康检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSummary {
+    pub status: ServiceStatus,
+    pub report: HealthReport,
+}
+
+/// 获取客户端版本和已部署的 Docker 服务版本
+pub async fn status(app: &CliApp) -> Result<StatusInfo> {
+    Ok(StatusInfo {
+        client_version: version_info::CLI_VERSION.to_string(),
+        docker_service_version: app.config.get_docker_versions(),
+        client_uuid: app.database.get_or_create_client_uuid().await?.to_string(),
+        compose_file_exists: Path::new(&app.config.docker.compose_file).exists(),
+    })
+}
+
+/// 检查 Docker 服务是否有可用更新
+pub async fn check_update(app: &CliApp, params: CheckUpdateParams) -> Result<UpdateInfo> {
+    let strategy = app
+        .upgrade_manager
+        .check_for_updates(params.force_full)
+        .await?;
+    Ok(UpdateInfo::from_strategy(
+        app.config.get_docker_versions(),
+        &strategy,
+    ))
+}
+
+/// 检查更新并下载升级包，过程中通过 `on_event` 上报事件
+///
+/// 与 `nuwax-cli upgrade` 一致，只负责准备升级包，部署由调用方另行触发
+pub async fn upgrade_with_progress<F>(
+    app: &CliApp,
+    params: UpgradeParams,
+    on_event: F,
+) -> Result<UpgradeResult>
+where
+    F: Fn(UpgradeEvent) + Send + Sync + 'static,
+{
+    let on_event = Arc::new(on_event);
+    let strategy = app
+        .upgrade_manager
+        .check_for_updates(params.force_full)
+        .await?;
+    let info = UpdateInfo::from_strategy(app.config.get_docker_versions(), &strategy);
+    on_event(UpgradeEvent::Checked { info: info.clone() });
+
+    // 补丁链会依次下载每个补丁，返回最后一个补丁的路径
+    let mut package_path = None;
+    for target in update::resolve_download_targets(app, &strategy)? {
+        on_event(UpgradeEvent::DownloadStarted {
+            url: target.url.clone(),
+            path: target.path.clone(),
+        });
+        let progress_event = on_event.clone();
+        app.api_client
+            .download_service_update_optimized_with_progress(
+                &target.path,
+                Some(&target.version),
+                &target.url,
+                Some(move |progress: downloader::DownloadProgress| {
+                    progress_event(UpgradeEvent::Downloading {
+                        progress: to_ui_progress(progress),
+                    });
+                }),
+            )
+            .await?;
+        on_event(UpgradeEvent::Completed {
+            path: target.path.clone(),
+        });
+        package_path = Some(target.path);
+    }
+
+    Ok(UpgradeResult { info, package_path })
+}
+
+/// 列出备份记录（按创建时间倒序）
+pub async fn list_backups(app: &CliApp, params: ListBackupsParams) -> Result<Vec<BackupSummary>> {
+    let query = BackupQuery {
+        backup_type: params.backup_type,
+        namespace: (!params.all_namespaces).then(|| backup::backup_namespace(app)),
+        ..Default::default()
+    };
+    let page = app.backup_manager.query_backups(&query, false).await?;
+
+    Ok(page
+        .backups
+        .into_iter()
+        .map(|backup| BackupSummary {
+            id: backup.id,
+            backup_type: backup.backup_type,
+            status: backup.status,
+            service_version: backup.service_version,
+            file_exists: Path::new(&backup.file_path).exists(),
+            file_path: PathBuf::from(backup.file_path),
+            created_at: backup.created_at,
+            namespace: backup.namespace,
+        })
+        .collect())
+}
+
+/// 从指定备份回滚，不进行交互确认
+pub async fn rollback(app: &CliApp, params: RollbackParams) -> Result<()> {
+    backup::run_rollback(
+        app,
+        Some(params.backup_id),
+        true,
+        false,
+        params.auto_start_service,
+        params.rollback_data,
+        params.restore_system,
+        params.allow_other_namespace,
+    )
+    .await
+}
+
+/// 检查 Docker 服务健康状态，整体状态只按检查范围内的服务计算
+pub async fn health_check(app: &CliApp, params: HealthCheckParams) -> Result<HealthSummary> {
+    let manager = DockerService::new(app.config.clone(), app.docker_manager.clone())?;
+    let scope = HealthScope::new(params.services, params.exclude);
+    let report = manager.health_check_scoped(&scope).await?;
+    Ok(HealthSummary {
+        status: report.finalize(),
+        report,
+    })
+}
+
+fn to_ui_progress(progress: downloader::DownloadProgress) -> DownloadProgress {
+    let status = match progress.status {
+        downloader::DownloadStatus::Starting => DownloadStatus::Starting,
+        downloader::DownloadStatus::Downloading | downloader::DownloadStatus::Resuming => {
+            DownloadStatus::Downloading
+        }
+        downloader::DownloadStatus::Paused => DownloadStatus::Paused,
+        downloader::DownloadStatus::Completed => DownloadStatus::Completed,
+        downloader::DownloadStatus::Failed(e) => DownloadStatus::Failed(e),
+    };
+    DownloadProgress {
+        task_id: progress.task_id,
+        file_name: progress.file_name,
+        downloaded_bytes: progress.downloaded_bytes,
+        total_bytes: progress.total_bytes,
+        download_speed: progress.download_speed,
+        eta_seconds: progress.eta_seconds,
+        percentage: progress.percentage,
+        status,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_info_without_upgrade() {
+        let strategy = UpgradeStrategy::NoUpgrade {
+            target_version: "0.0.1".parse().unwrap(),
+        };
+        let info = UpdateInfo::from_strategy("0.0.1".to_string(), &strategy);
+
+        assert_eq!(info.kind, UpdateKind::None);
+        assert!(!info.has_update());
+        assert!(info.download_url.is_none());
+    }
+
+    #[test]
+    fn test_upgrade_event_serialization() {
+        let event = UpgradeEvent::Completed {
+            path: PathBuf::from("/tmp/docker.zip"),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "completed");
+        assert_eq!(json["path"], "/tmp/docker.zip");
+    }
+}
@@ -1,26 +1,41 @@
 use anyhow::Result;
+use client_core::database::BackupType;
 use client_core::{
-    api::ApiClient, authenticated_client::AuthenticatedClient, backup::BackupManager,
-    config::AppConfig, constants::config, container::DockerManager, database::Database,
+    api::ApiClient,
+    authenticated_client::AuthenticatedClient,
+    backup::{BackupManager, BackupSizeGuard},
+    backup_permissions::OwnershipMapping,
+    backup_storage::backup_storage_from_config,
+    config::AppConfig,
+    constants::config,
+    container::DockerManager,
+    database::Database,
+    events::EventSender,
+    package_store::PackageStore,
+    paths::{self, PathSource, ResolvedPath},
     upgrade::UpgradeManager,
 };
 use log::info;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use crate::cli::Commands;
+use crate::cli::{CheckUpdateCommand, Commands, UpgradeCommand};
 use crate::commands;
 use tracing::debug;
 
 #[derive(Clone)]
 pub struct CliApp {
     pub config: Arc<AppConfig>,
+    /// 配置文件路径，修改配置后保存到这里
+    pub config_path: PathBuf,
     pub database: Arc<Database>,
     pub api_client: Arc<ApiClient>,
     pub authenticated_client: Arc<AuthenticatedClient>,
     pub docker_manager: Arc<DockerManager>,
     pub backup_manager: Arc<BackupManager>,
     pub upgrade_manager: Arc<UpgradeManager>,
+    /// 操作事件发送端，通过 `--events` 订阅时由前端渲染
+    pub events: EventSender,
 }
 
 impl CliApp {
@@ -28,24 +43,33 @@ impl CliApp {
     pub async fn new_with_auto_config() -> Result<Self> {
         let config = Arc::new(AppConfig::find_and_load_config()?);
 
-        Self::new_with_config(config).await
+        Self::new_with_config(config, PathBuf::from(paths::DEFAULT_CONFIG_FILE)).await
     }
 
-    /// 使用指定配置文件路径初始化CLI应用
-    pub async fn new_with_config_path<P: AsRef<Path>>(config_path: P) -> Result<Self> {
-        let config_path = config_path.as_ref();
+    /// 使用解析后的配置文件路径初始化CLI应用
+    ///
+    /// 默认的 config.toml 不存在时回退到智能查找；通过 `--config` 或 `NUWAX_CONFIG`
+    /// 指定的配置文件必须存在
+    pub async fn new_with_config_path(config_file: &ResolvedPath) -> Result<Self> {
+        let config_path = &config_file.path;
         let config = if config_path.exists() {
             Arc::new(AppConfig::load_from_file(config_path)?)
-        } else {
-            // 如果指定的配置文件不存在，尝试智能查找
+        } else if config_file.source == PathSource::Default {
+            // 如果默认配置文件不存在，尝试智能查找
             Arc::new(AppConfig::find_and_load_config()?)
+        } else {
+            return Err(anyhow::anyhow!(
+                "配置文件不存在: {}（来自{}）",
+                config_path.display(),
+                config_file.source
+            ));
         };
 
-        Self::new_with_config(config).await
+        Self::new_with_config(config, config_path.clone()).await
     }
 
     /// 使用配置初始化CLI应用
-    async fn new_with_config(config: Arc<AppConfig>) -> Result<Self> {
+    async fn new_with_config(config: Arc<AppConfig>, config_path: PathBuf) -> Result<Self> {
         // 确保缓存目录存在
         config.ensure_cache_dirs()?;
 
@@ -61,6 +85,17 @@ impl CliApp {
             ));
         }
 
+        // 文件哈希结果按文件指纹缓存到数据库，避免重复计算大文件
+        client_core::file_hash::register_hash_cache(database.as_ref().clone());
+
+        // 服务清单和公告的响应按 ETag/Last-Modified 缓存到数据库，用于条件请求和离线回退
+        client_core::http_cache::register_http_cache(database.as_ref().clone());
+
+        // 全量升级解压的文件存入内容寻址存储，跨版本共享相同文件
+        client_core::package_store::register_package_store(PackageStore::new(
+            config.get_package_store_dir(),
+        ));
+
         // 创建认证客户端（自动处理注册和认证）
         let server_base_url = client_core::constants::api::DEFAULT_BASE_URL.to_string();
         let authenticated_client =
@@ -74,31 +109,43 @@ impl CliApp {
         ));
 
         // 创建其他管理器
-        let docker_manager = Arc::new(DockerManager::new(
+        let docker_manager = Arc::new(DockerManager::with_project(
             PathBuf::from(&config.docker.compose_file),
             PathBuf::from(&config.docker.env_file),
+            config.docker.project_name.clone(),
         )?);
 
-        let backup_manager = Arc::new(BackupManager::new(
-            PathBuf::from(&config.backup.storage_dir),
-            database.clone(),
-            docker_manager.clone(),
-        )?);
+        let backup_manager = Arc::new(
+            BackupManager::new(
+                config.get_backup_dir(),
+                database.clone(),
+                docker_manager.clone(),
+            )?
+            .with_storage(backup_storage_from_config(config.backup.storage.as_ref())?)
+            .with_hooks(config.backup.hooks.clone())
+            .with_snapshot(config.backup.snapshot.clone())
+            .with_size_guard(BackupSizeGuard::from_config(&config.backup))
+            .with_restore_ownership(OwnershipMapping::from_config(
+                &config.backup.restore_ownership,
+            )?),
+        );
         let upgrade_manager = Arc::new(UpgradeManager::new(
             config.clone(),
-            PathBuf::from("config.toml"), // 使用默认配置路径
+            config_path.clone(),
             api_client.clone(),
             database.clone(),
         ));
 
         Ok(Self {
             config,
+            config_path,
             database,
             api_client,
             authenticated_client,
             docker_manager,
             backup_manager,
             upgrade_manager,
+            events: EventSender::default(),
         })
     }
 
@@ -108,38 +155,140 @@ impl CliApp {
             Commands::Status => commands::run_status(self).await,
             Commands::ApiInfo => commands::run_api_info(self).await,
             Commands::Init { .. } => unreachable!(), // 已经在 main.rs 中处理
+            Commands::Paths => unreachable!(),       // 已经在 main.rs 中处理
+            Commands::CheckUpdate(CheckUpdateCommand::Plan {
+                out,
+                force_full,
+                force,
+            }) => commands::upgrade_plan::create_upgrade_plan(self, &out, force_full, force).await,
+            Commands::CheckUpdate(CheckUpdateCommand::Check { explain: true, .. }) => {
+                commands::update::explain_upgrade_strategy(self).await
+            }
+            Commands::CheckUpdate(CheckUpdateCommand::Check {
+                changelog: true, ..
+            }) => commands::changelog::show_changelog(self).await,
             Commands::CheckUpdate(check_update_cmd) => {
                 commands::handle_check_update_command(check_update_cmd)
                     .await
-                    .map_err(|e| anyhow::anyhow!(format!("检查更新失败: {e}")))
+                    .map_err(|e| {
+                        // 退出码表示检查结果，原样返回
+                        if e.is::<commands::CommandExitCode>() {
+                            e
+                        } else {
+                            anyhow::anyhow!(format!("检查更新失败: {e}"))
+                        }
+                    })
+            }
+            Commands::Upgrade {
+                command:
+                    Some(UpgradeCommand::Rollback {
+                        upgrade_id,
+                        db_dump,
+                        restore_data,
+                    }),
+                ..
+            } => {
+                commands::upgrade_rollback::run_upgrade_rollback(
+                    self,
+                    upgrade_id,
+                    db_dump,
+                    restore_data,
+                )
+                .await
+            }
+            Commands::Upgrade {
+                command:
+                    Some(UpgradeCommand::Phase {
+                        phase,
+                        force_full,
+                        sql_dry_run,
+                        on_conflict,
+                    }),
+                ..
+            } => {
+                commands::upgrade_phase::run_upgrade_phase(
+                    self,
+                    phase,
+                    force_full,
+                    sql_dry_run,
+                    on_conflict,
+                )
+                .await
             }
-            Commands::Upgrade { args } => {
+            Commands::Upgrade {
+                command: None,
+                args,
+            } => {
                 commands::run_upgrade(self, args)
                     .await
                     .map_err(|e| client_core::error::DuckError::custom(format!("升级失败: {e}")))?;
                 Ok(())
             }
-            Commands::Backup => commands::run_backup(self).await,
-            Commands::ListBackups => commands::run_list_backups(self).await,
+            Commands::Backup {
+                command: Some(backup_cmd),
+                ..
+            } => commands::backup::run_backup_catalog_command(self, backup_cmd).await,
+            Commands::Backup {
+                command: None,
+                include_system,
+                snapshot,
+                dedup,
+                force,
+            } => {
+                let backup_type = if snapshot {
+                    BackupType::Snapshot
+                } else {
+                    BackupType::Manual
+                };
+                commands::run_backup(self, backup_type, include_system, dedup, force).await
+            }
+            Commands::ListBackups { args } => commands::run_list_backups(self, args).await,
             Commands::Rollback {
                 backup_id,
                 force,
                 list_json,
                 rollback_data,
+                restore_system,
+                allow_other_namespace,
+                preview,
+            } => {
+                if preview {
+                    commands::backup::run_rollback_preview(
+                        self,
+                        backup_id,
+                        rollback_data,
+                        allow_other_namespace,
+                    )
+                    .await
+                } else {
+                    commands::backup::run_rollback(
+                        self,
+                        backup_id,
+                        force,
+                        list_json,
+                        true,
+                        rollback_data,
+                        restore_system,
+                        allow_other_namespace,
+                    )
+                    .await
+                }
+            }
+            Commands::RollbackDataOnly {
+                backup_id,
+                force,
+                allow_other_namespace,
             } => {
-                commands::backup::run_rollback(
+                commands::backup::run_rollback_data_only(
                     self,
                     backup_id,
                     force,
-                    list_json,
                     true,
-                    rollback_data,
+                    None,
+                    allow_other_namespace,
                 )
                 .await
             }
-            Commands::RollbackDataOnly { backup_id, force } => {
-                commands::backup::run_rollback_data_only(self, backup_id, force, true, None).await
-            }
             Commands::DockerService(docker_cmd) => {
                 commands::run_docker_service_command(self, docker_cmd).await
             }
@@ -147,6 +296,18 @@ impl CliApp {
             Commands::AutoBackup(auto_backup_cmd) => {
                 commands::handle_auto_backup(self, &auto_backup_cmd).await
             }
+            Commands::Watchdog(watchdog_cmd) => {
+                commands::handle_watchdog_command(self, watchdog_cmd).await
+            }
+            Commands::Metrics(metrics_cmd) => {
+                commands::handle_metrics_command(self, metrics_cmd).await
+            }
+            Commands::Serve { listen, token_file } => {
+                commands::run_serve(self, listen, &token_file).await
+            }
+            Commands::Ports(ports_cmd) => commands::handle_ports_command(self, ports_cmd).await,
+            Commands::Env(env_cmd) => commands::handle_env_command(self, env_cmd).await,
+            Commands::Db(db_cmd) => commands::handle_db_command(self, db_cmd).await,
             Commands::AutoUpgradeDeploy(auto_upgrade_deploy_cmd) => {
                 commands::handle_auto_upgrade_deploy_command(self, auto_upgrade_deploy_cmd).await
             }
@@ -157,7 +318,31 @@ impl CliApp {
                 old_version,
                 new_version,
                 output,
-            } => commands::run_diff_sql(old_sql, new_sql, old_version, new_version, output).await,
+                dialect,
+            } => {
+                commands::run_diff_sql(old_sql, new_sql, old_version, new_version, output, dialect)
+                    .await
+            }
+            Commands::DiskUsage {
+                json,
+                warn_above,
+                min_free,
+            } => commands::run_disk_usage(self, json, warn_above, min_free).await,
+            Commands::History(history_cmd) => {
+                commands::handle_history_command(self, history_cmd).await
+            }
+            Commands::SupportBundle { output } => commands::run_support_bundle(self, output).await,
+            Commands::Remote { args, command } => commands::run_remote_command(args, command).await,
+            Commands::Fleet(fleet_cmd) => commands::run_fleet_command(fleet_cmd).await,
+            Commands::Patch(patch_cmd) => commands::run_patch_command(patch_cmd).await,
+            Commands::Replay { session } => commands::run_replay(&session).await,
+            Commands::SelfTest { skip_docker } => commands::run_self_test(skip_docker).await,
+            Commands::Approve {
+                command,
+                plan,
+                key,
+                approver,
+            } => commands::run_approve_command(command, plan, key, approver).await,
         }
     }
 }
@@ -1,5 +1,18 @@
+use crate::commands::disk_usage::DEFAULT_MIN_FREE_GB;
+use crate::commands::upgrade_phase::UpgradePhaseStep;
 use crate::project_info::{metadata, version_info};
+use crate::utils::event_output::EventFormat;
+use crate::utils::log_rotation::LogRotation;
+use crate::utils::patch_conflicts::ConflictPolicy;
 use clap::{Args, Parser, Subcommand};
+use client_core::backup_catalog::CatalogFormat;
+use client_core::database::{BackupSort, BackupType};
+use client_core::download_cache::KeepVersion;
+use client_core::fleet::FleetOperation;
+use client_core::remote::SshTarget;
+use client_core::service_preset::ServicePreset;
+use client_core::sql_diff::SqlDialect;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
 /// 升级相关参数
@@ -9,11 +22,139 @@ pub struct UpgradeArgs {
     #[arg(long)]
     pub force: bool,
 
+    /// 跳过增量补丁（包括补丁链），直接使用全量升级包，用于补丁链不完整时
+    #[arg(long)]
+    pub force_full: bool,
+
     /// 只检查是否有可用的升级版本，不执行下载
     #[arg(long)]
     pub check: bool,
 }
 
+/// 升级相关子命令
+#[derive(Subcommand, Debug)]
+pub enum UpgradeCommand {
+    /// 回滚最近一次全量升级：恢复升级前的服务文件、应用目录和版本号，回退数据库并重启服务
+    Rollback {
+        /// 要回滚的升级记录 ID（默认为最近一次成功的升级，见 history upgrades）
+        #[arg(long)]
+        upgrade_id: Option<String>,
+        /// 从 db dump 导出的文件恢复数据库，代替反向执行差异SQL
+        #[arg(long, value_name = "FILE", conflicts_with = "restore_data")]
+        db_dump: Option<PathBuf>,
+        /// 从升级前备份恢复整个 data 目录（升级后写入的数据会丢失）
+        #[arg(long)]
+        restore_data: bool,
+    },
+    /// 单独执行一个升级阶段（download、backup、extract、start、sql），与 auto-upgrade-deploy 共用部署检查点
+    Phase {
+        /// 要执行的阶段: download、backup、extract、start 或 sql
+        #[arg(value_name = "PHASE")]
+        phase: UpgradePhaseStep,
+        /// 跳过增量补丁（包括补丁链），直接使用全量升级包
+        #[arg(long)]
+        force_full: bool,
+        /// 先在临时 MySQL 容器中预演差异SQL，预演成功后才升级正式数据库（sql 阶段）
+        #[arg(long)]
+        sql_dry_run: bool,
+        /// 补丁与受保护目录冲突时的处理策略：keep、replace、backup-and-replace（extract 阶段）
+        #[arg(long, value_name = "POLICY")]
+        on_conflict: Option<ConflictPolicy>,
+    },
+}
+
+/// 升级审批相关命令
+#[derive(Subcommand, Debug)]
+pub enum ApproveCommand {
+    /// 生成审批密钥，输出需要配置到部署机器的公钥
+    Keygen {
+        /// 私钥保存路径
+        #[arg(long)]
+        out: PathBuf,
+        /// 覆盖已存在的私钥文件
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// 备份目录（catalog）相关命令
+#[derive(Subcommand, Debug)]
+pub enum BackupCommand {
+    /// 导出备份目录（ID、类型、版本、大小、哈希、文件位置）
+    ExportCatalog {
+        /// 导出格式: json 或 csv
+        #[arg(long, default_value = "json", help = "导出格式: json 或 csv")]
+        format: CatalogFormat,
+        /// 输出文件路径（默认输出到标准输出）
+        #[arg(short, long, help = "输出文件路径（默认输出到标准输出）")]
+        output: Option<PathBuf>,
+    },
+    /// 从导出的备份目录重新登记备份记录
+    ImportCatalog {
+        /// 备份目录文件路径
+        file: PathBuf,
+        /// 文件格式（默认按扩展名推断，.csv 为 CSV，其余为 JSON）
+        #[arg(long, help = "文件格式: json 或 csv（默认按扩展名推断）")]
+        format: Option<CatalogFormat>,
+    },
+    /// 登记目录中已有但不在备份列表中的备份归档
+    Adopt {
+        /// 包含备份归档的目录
+        dir: PathBuf,
+    },
+    /// 校验备份完整性（去重备份同时检查引用的分块）
+    Verify {
+        /// 备份 ID（默认校验所有已完成的备份）
+        backup_id: Option<i64>,
+    },
+    /// 把去重备份还原为普通归档
+    Repack {
+        /// 备份 ID
+        backup_id: i64,
+        /// 导出到指定路径（默认原地替换备份文件）
+        #[arg(short, long, help = "导出到指定路径（默认原地替换备份文件）")]
+        output: Option<PathBuf>,
+    },
+}
+
+/// 备份列表的筛选、排序和分页参数
+#[derive(Args, Debug, Default)]
+pub struct BackupListArgs {
+    /// 只显示指定类型的备份（manual、pre-upgrade、scheduled、snapshot）
+    #[arg(long = "type", value_name = "TYPE")]
+    pub backup_type: Option<BackupType>,
+    /// 只显示指定命名空间的备份（默认为当前项目的命名空间）
+    #[arg(long, value_name = "NAMESPACE", conflicts_with = "all_namespaces")]
+    pub namespace: Option<String>,
+    /// 显示共享备份目录中所有命名空间的备份
+    #[arg(long)]
+    pub all_namespaces: bool,
+    /// 只显示指定服务版本的备份（同时包含该版本的修订版本）
+    #[arg(long, value_name = "VERSION")]
+    pub version: Option<String>,
+    /// 只显示该时间之后创建的备份（如 2025-06-01、"2025-06-01 08:00"、7d、12h）
+    #[arg(long, value_name = "TIME")]
+    pub since: Option<String>,
+    /// 只显示该时间之前创建的备份（只写日期时包含当天）
+    #[arg(long, value_name = "TIME")]
+    pub until: Option<String>,
+    /// 只显示备份文件可用的备份（隐藏文件缺失的记录）
+    #[arg(long)]
+    pub only_valid: bool,
+    /// 排序字段: created、version、type
+    #[arg(long, value_name = "FIELD", default_value = "created")]
+    pub sort: BackupSort,
+    /// 升序排列（默认最新、版本最高的在前）
+    #[arg(long)]
+    pub asc: bool,
+    /// 每页显示的备份数
+    #[arg(long, value_name = "N")]
+    pub limit: Option<u64>,
+    /// 显示第几页（从 1 开始，需要同时指定 --limit）
+    #[arg(long, value_name = "N", requires = "limit")]
+    pub page: Option<u64>,
+}
+
 /// 自动备份相关命令
 #[derive(Subcommand, Debug)]
 pub enum AutoBackupCommand {
@@ -23,6 +164,117 @@ pub enum AutoBackupCommand {
     Status,
 }
 
+/// 历史记录相关命令
+#[derive(Subcommand, Debug)]
+pub enum HistoryCommand {
+    /// 显示服务升级时间线（版本、升级方式、耗时、备份、结果）
+    Upgrades {
+        /// 最多显示的记录数
+        #[arg(short = 'n', long, value_name = "N")]
+        limit: Option<usize>,
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+        /// 与服务器升级历史对账，并补报服务器缺少的本地记录
+        #[arg(long)]
+        sync: bool,
+    },
+}
+
+/// 服务看门狗相关命令
+#[derive(Subcommand, Debug)]
+pub enum WatchdogCommand {
+    /// 运行看门狗，按健康检查结果自动重启不健康的常驻服务
+    Run {
+        /// 只检查一轮后退出（便于由 cron 等外部调度器定期调用）
+        #[arg(long, help = "只检查一轮后退出（便于由 cron 等外部调度器定期调用）")]
+        once: bool,
+    },
+    /// 显示看门狗配置
+    Status,
+}
+
+/// 监控指标相关命令
+#[derive(Subcommand, Debug)]
+pub enum MetricsCommand {
+    /// 启动 HTTP 服务，以 Prometheus 文本格式暴露服务健康状态（GET /metrics）
+    Serve {
+        /// 监听地址
+        #[arg(long, default_value = "127.0.0.1:9400")]
+        listen: SocketAddr,
+        /// 健康检查间隔（秒）
+        #[arg(long, default_value_t = 30)]
+        interval: u64,
+    },
+}
+
+/// 服务端口覆盖相关命令
+#[derive(Subcommand, Debug)]
+pub enum PortsCommand {
+    /// 设置服务的主机端口（保存到 config.toml，部署和升级后自动重新应用）
+    Set {
+        /// docker-compose 服务名，服务有多个端口映射时使用 "服务名:容器端口"
+        service: String,
+        /// 主机端口
+        port: u16,
+    },
+    /// 删除服务的端口覆盖
+    Unset {
+        /// 服务名（与设置时相同）
+        service: String,
+    },
+    /// 显示端口覆盖配置和当前端口映射
+    List,
+}
+
+/// 数据库相关命令
+#[derive(Subcommand, Debug)]
+pub enum DbCommand {
+    /// 在已部署的 MySQL 中执行SQL文件（连接方式与升级时执行差异SQL相同）
+    Exec {
+        /// 要执行的SQL文件
+        #[arg(long)]
+        file: PathBuf,
+        /// 预演：在事务中执行后回滚，跳过 DDL 等会隐式提交的语句
+        #[arg(long, conflicts_with = "transaction")]
+        dry_run: bool,
+        /// 在单个事务中执行，任一语句失败时回滚
+        #[arg(long)]
+        transaction: bool,
+        /// 将每条语句的执行结果和查询输出保存到文件
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// 导出数据库（在容器内执行 mysqldump，文件名以 .gz 结尾时压缩）
+    Dump {
+        /// 导出文件路径，如 dump.sql.gz
+        #[arg(long)]
+        out: PathBuf,
+        /// 覆盖已存在的文件
+        #[arg(long)]
+        force: bool,
+    },
+    /// 从导出文件恢复数据库（自动识别 gzip 压缩）
+    Restore {
+        /// 导出文件路径
+        file: PathBuf,
+    },
+}
+
+/// .env 环境变量相关命令
+#[derive(Subcommand, Debug)]
+pub enum EnvCommand {
+    /// 对比当前 .env 与新版本服务包的 .env 模板（新增、删除、默认值变化的变量）
+    ShowDiff {
+        /// 模板来源：服务包或 .env 模板文件（默认使用已下载的最新全量服务包）
+        #[arg(long)]
+        template: Option<PathBuf>,
+        /// 逐项确认并写入选中的变更（非交互环境只添加新增的变量）
+        #[arg(long)]
+        apply: bool,
+    },
+}
+
 /// 自动升级部署相关命令
 #[derive(Subcommand, Debug)]
 pub enum AutoUpgradeDeployCommand {
@@ -44,9 +296,66 @@ pub enum AutoUpgradeDeployCommand {
         #[arg(
             short = 'p',
             long,
-            help = "指定docker-compose的项目名称（默认: 从compose文件读取或使用'docker'）"
+            help = "指定docker-compose的项目名称（默认: 配置 docker.project_name，其次从compose文件读取或使用'docker'）"
         )]
         project: Option<String>,
+        /// 从上次中断的阶段继续部署
+        #[arg(
+            long,
+            conflicts_with = "restart",
+            help = "从上次中断的阶段继续部署（跳过已完成的下载、备份等阶段）"
+        )]
+        resume: bool,
+        /// 忽略已有的部署检查点，强制重新执行完整流程
+        #[arg(long, help = "忽略已有的部署检查点，强制重新执行完整部署流程")]
+        restart: bool,
+        /// 应用升级前列出将被替换或删除的文件，并等待确认
+        #[arg(
+            long,
+            help = "应用升级前列出将被替换、新增或删除的文件和目录，并在确认后继续"
+        )]
+        show_changes: bool,
+        /// 先在临时 MySQL 容器中预演数据库升级，成功后才修改正式数据库
+        #[arg(
+            long,
+            help = "先在临时 MySQL 容器中预演差异SQL，预演成功后才升级正式数据库"
+        )]
+        sql_dry_run: bool,
+        /// 重新生成 .env 中为模板默认值或此前自动生成的密钥
+        #[arg(
+            long,
+            help = "重新生成 .env 中为模板默认值或此前自动生成的密钥（已有数据时需同步修改服务密码）"
+        )]
+        regenerate_secrets: bool,
+        /// 跳过增量补丁（包括补丁链），直接使用全量升级包
+        #[arg(long)]
+        force_full: bool,
+        /// 暂存升级前备份：服务停止期间只复制文件，压缩与解压新版本同时进行
+        #[arg(
+            long,
+            help = "暂存升级前备份，压缩与解压新版本同时进行以缩短停机时间（未配置 backup.staging 时使用 copy 方式）"
+        )]
+        staged_backup: bool,
+        /// 补丁要修改受保护目录（upload/、data/ 等）中已存在的内容时的处理策略
+        #[arg(
+            long,
+            value_name = "POLICY",
+            help = "补丁与受保护目录冲突时的处理策略：keep（保留现有内容）、replace（直接替换）、backup-and-replace（备份后替换），未指定时在终端中询问"
+        )]
+        on_conflict: Option<ConflictPolicy>,
+        /// 被其他程序占用的文件登记为系统重启时替换（仅 Windows，需要管理员权限）
+        #[arg(
+            long,
+            help = "解压时被其他程序占用的文件登记为系统重启时替换（仅 Windows，需要管理员权限）"
+        )]
+        replace_locked_on_reboot: bool,
+        /// 按经过审批的升级计划执行（计划须与当前状态一致）
+        #[arg(
+            long,
+            value_name = "PLAN",
+            help = "按经过审批的升级计划执行，目标版本、服务包或当前部署状态与计划不一致时拒绝升级"
+        )]
+        plan: Option<PathBuf>,
     },
     /// 显示当前自动升级配置
     Status,
@@ -56,7 +365,40 @@ pub enum AutoUpgradeDeployCommand {
 #[derive(Subcommand, Debug)]
 pub enum CheckUpdateCommand {
     /// 检查最新版本信息
-    Check,
+    #[command(
+        after_help = "退出码: 0 = 已是最新版本，10 = 有新版本可用，11 = 有修订版本（补丁）可用，其他非零值 = 检查失败"
+    )]
+    Check {
+        /// 不输出任何内容，只通过退出码返回检查结果（适合 cron 和监控脚本）
+        #[arg(short, long, help = "不输出任何内容，只通过退出码返回检查结果")]
+        quiet: bool,
+        /// 说明Docker服务升级选择全量、增量或无需升级的原因
+        #[arg(
+            long,
+            conflicts_with = "quiet",
+            help = "说明Docker服务升级策略的选择原因"
+        )]
+        explain: bool,
+        /// 显示当前版本到最新版本之间各版本的更新说明
+        #[arg(
+            long,
+            conflicts_with_all = ["quiet", "explain"],
+            help = "显示当前版本到最新版本之间各版本的更新说明"
+        )]
+        changelog: bool,
+    },
+    /// 生成Docker服务升级计划，审批后通过 auto-upgrade-deploy run --plan 执行
+    Plan {
+        /// 升级计划文件路径
+        #[arg(long)]
+        out: PathBuf,
+        /// 跳过增量补丁，按全量升级生成计划（执行时同样需要 --force-full）
+        #[arg(long)]
+        force_full: bool,
+        /// 覆盖已存在的计划文件
+        #[arg(long)]
+        force: bool,
+    },
     /// 安装指定版本或最新版本
     Install {
         /// 指定版本号（如不指定则安装最新版本）
@@ -76,7 +418,23 @@ pub enum DockerServiceCommand {
         #[arg(
             short = 'p',
             long,
-            help = "指定docker-compose的项目名称（默认: 从compose文件读取或使用'docker'）"
+            help = "指定docker-compose的项目名称（默认: 配置 docker.project_name，其次从compose文件读取或使用'docker'）"
+        )]
+        project: Option<String>,
+    },
+    /// 部署Docker服务：加载镜像、重新应用端口覆盖和服务规格预设后启动服务
+    Deploy {
+        /// 服务规格预设，保存到 config.toml 的 docker.preset
+        #[arg(
+            long,
+            help = "服务规格预设: small、medium、large（保存到配置，升级时沿用）"
+        )]
+        preset: Option<ServicePreset>,
+        /// 指定docker-compose的项目名称
+        #[arg(
+            short = 'p',
+            long,
+            help = "指定docker-compose的项目名称（默认: 配置 docker.project_name，其次从compose文件读取或使用'docker'）"
         )]
         project: Option<String>,
     },
@@ -86,7 +444,7 @@ pub enum DockerServiceCommand {
         #[arg(
             short = 'p',
             long,
-            help = "指定docker-compose的项目名称（默认: 从compose文件读取或使用'docker'）"
+            help = "指定docker-compose的项目名称（默认: 配置 docker.project_name，其次从compose文件读取或使用'docker'）"
         )]
         project: Option<String>,
     },
@@ -96,7 +454,7 @@ pub enum DockerServiceCommand {
         #[arg(
             short = 'p',
             long,
-            help = "指定docker-compose的项目名称（默认: 从compose文件读取或使用'docker'）"
+            help = "指定docker-compose的项目名称（默认: 配置 docker.project_name，其次从compose文件读取或使用'docker'）"
         )]
         project: Option<String>,
     },
@@ -106,9 +464,15 @@ pub enum DockerServiceCommand {
         #[arg(
             short = 'p',
             long,
-            help = "指定docker-compose的项目名称（默认: 从compose文件读取或使用'docker'）"
+            help = "指定docker-compose的项目名称（默认: 配置 docker.project_name，其次从compose文件读取或使用'docker'）"
         )]
         project: Option<String>,
+        /// 只检查这些服务（逗号分隔），指定检查范围时服务不健康会以非零状态退出
+        #[arg(long, value_name = "SERVICES", value_delimiter = ',')]
+        services: Vec<String>,
+        /// 不检查这些服务（逗号分隔）
+        #[arg(long, value_name = "SERVICES", value_delimiter = ',')]
+        exclude: Vec<String>,
     },
     /// 重启指定容器
     RestartContainer {
@@ -125,6 +489,109 @@ pub enum DockerServiceCommand {
     ListImages,
     /// 检查并创建docker-compose.yml中的挂载目录
     CheckMountDirs,
+    /// 在compose服务的容器中执行命令
+    Exec {
+        /// compose文件中定义的服务名
+        service: String,
+        /// 要执行的命令及参数（写在 -- 之后）
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+        /// 不分配TTY
+        #[arg(short = 'T', long, help = "不分配TTY，便于在脚本中捕获命令输出")]
+        no_tty: bool,
+        /// 以指定用户执行
+        #[arg(short, long, help = "以指定用户执行命令（格式: user 或 uid[:gid]）")]
+        user: Option<String>,
+        /// 指定docker-compose的项目名称
+        #[arg(
+            short = 'p',
+            long,
+            help = "指定docker-compose的项目名称（默认: 配置 docker.project_name，其次从compose文件读取或使用'docker'）"
+        )]
+        project: Option<String>,
+    },
+    /// 按权限策略修复工作目录中的文件权限和属主
+    FixPerms {
+        /// 只报告与策略不一致的路径，不做修改
+        #[arg(long, help = "只报告与权限策略不一致的路径，不做修改")]
+        check: bool,
+    },
+    /// 修改服务的主机端口，只重建该服务的容器
+    SetPort {
+        /// compose服务名，可用 服务名:容器端口 指定端口映射
+        service: String,
+        /// 新的主机端口
+        port: u16,
+        /// 指定docker-compose的项目名称
+        #[arg(
+            short = 'p',
+            long,
+            help = "指定docker-compose的项目名称（默认: 配置 docker.project_name，其次从compose文件读取或使用'docker'）"
+        )]
+        project: Option<String>,
+    },
+    /// 迁移compose项目名称：停止旧项目的容器，迁移命名数据卷，以新项目名重建容器并更新 config.toml
+    MigrateProject {
+        /// 原项目名称
+        #[arg(long)]
+        from: String,
+        /// 新项目名称
+        #[arg(long)]
+        to: String,
+    },
+    /// 把数据目录迁移到其他磁盘：停止服务，复制并校验数据，改写挂载路径后重启服务
+    RelocateData {
+        /// 新的数据目录，不存在时自动创建，已存在时必须为空
+        #[arg(long, value_name = "DIR")]
+        to: PathBuf,
+        /// 服务重启后删除原数据（默认改名保留）
+        #[arg(long)]
+        remove_source: bool,
+    },
+    /// 停止并删除服务已从compose文件中删除的容器（保留数据卷）
+    CleanupOrphans {
+        /// 只列出孤立容器，不删除
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 拆除部署：创建最终备份后停止服务，删除项目的全部容器和网络
+    Down {
+        /// 同时删除命名数据卷和数据目录（需要输入项目名再次确认）
+        #[arg(long)]
+        volumes: bool,
+        /// 不创建最终备份
+        #[arg(long)]
+        no_backup: bool,
+    },
+    /// 导出当前compose文件引用的全部镜像，用于迁移到无法访问镜像仓库的主机
+    ExportImages {
+        /// 归档文件路径，如 images.tar.zst（默认保存到备份目录，并列入备份目录导出）
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// 覆盖已存在的文件
+        #[arg(long)]
+        force: bool,
+    },
+    /// 导入 export-images 导出的镜像归档
+    ImportImages {
+        /// 镜像归档路径
+        file: PathBuf,
+    },
+    /// 服务规格预设（small / medium / large）
+    #[command(subcommand)]
+    Preset(PresetCommand),
+}
+
+/// 服务规格预设相关命令
+#[derive(Subcommand, Debug)]
+pub enum PresetCommand {
+    /// 显示预设相对服务包默认配置的资源限制和停用服务差异
+    Show {
+        /// 预设名称（默认: 配置 docker.preset）
+        preset: Option<ServicePreset>,
+    },
+    /// 删除预设配置和覆盖文件，恢复服务包默认配置
+    Clear,
 }
 
 /// 缓存管理相关命令
@@ -140,6 +607,156 @@ pub enum CacheCommand {
         #[arg(long, default_value = "3", help = "保留的版本数量")]
         keep: u32,
     },
+    /// 只保留指定版本需要的服务包，删除下载缓存中的其他服务包
+    Keep {
+        /// 保留的版本，逗号分隔：current（当前版本）、previous（升级前的版本）或具体版本号
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "current,previous",
+            help = "保留的版本（current、previous 或具体版本号，逗号分隔）"
+        )]
+        versions: Vec<KeepVersion>,
+        /// 只列出将要删除的服务包，不实际删除
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 清理服务包内容寻址存储中不再使用的文件
+    Gc {
+        /// 保留最近记录的版本数量（当前部署版本始终保留）
+        #[arg(long, default_value = "2", help = "保留最近记录的版本数量")]
+        keep: usize,
+        /// 清理超过该时间（小时）未修改的遗留临时文件
+        #[arg(long, default_value = "24", help = "临时文件清理阈值（小时）")]
+        temp_max_age_hours: u64,
+    },
+}
+
+/// 远程主机连接参数
+#[derive(Args, Debug)]
+pub struct RemoteArgs {
+    /// SSH 目标主机
+    #[arg(
+        long,
+        value_name = "USER@HOST[:PORT]",
+        help = "远程主机，格式: [user@]host[:port]"
+    )]
+    pub ssh: SshTarget,
+    /// SSH 私钥文件
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        help = "SSH 私钥文件（默认使用 ssh-agent 或 ~/.ssh 中的密钥）"
+    )]
+    pub identity: Option<PathBuf>,
+    /// 远程主机上的 nuwax-cli 工作目录
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "远程主机上包含 config.toml 的 nuwax-cli 工作目录（默认: 登录用户的主目录）"
+    )]
+    pub remote_dir: Option<String>,
+    /// 远程主机上的 nuwax-cli 可执行文件
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "nuwax-cli",
+        help = "远程主机上的 nuwax-cli 可执行文件路径"
+    )]
+    pub remote_cli: String,
+}
+
+/// 远程主机管理命令
+#[derive(Subcommand, Debug)]
+pub enum RemoteCommand {
+    /// 显示远程主机的服务状态和版本信息
+    Status,
+    /// 检查远程主机上 Docker 服务的健康状态
+    Health {
+        /// 指定docker-compose的项目名称
+        #[arg(
+            short = 'p',
+            long,
+            help = "指定docker-compose的项目名称（默认: 配置 docker.project_name，其次从compose文件读取或使用'docker'）"
+        )]
+        project: Option<String>,
+    },
+    /// 在远程主机上下载Docker服务文件
+    Upgrade {
+        #[command(flatten)]
+        args: UpgradeArgs,
+    },
+    /// 在远程主机上执行自动升级部署
+    Deploy {
+        /// 传递给 auto-upgrade-deploy run 的参数
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// 在远程主机上执行任意 nuwax-cli 子命令
+    Exec {
+        /// nuwax-cli 子命令及参数
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        args: Vec<String>,
+    },
+    /// 上传本地文件到远程主机
+    Push {
+        /// 本地文件路径
+        local: PathBuf,
+        /// 远程路径（相对路径基于 --remote-dir）
+        remote: String,
+    },
+    /// 从远程主机下载文件
+    Pull {
+        /// 远程路径（相对路径基于 --remote-dir）
+        remote: String,
+        /// 本地文件路径
+        local: PathBuf,
+    },
+}
+
+/// 批量主机操作命令
+#[derive(Subcommand, Debug)]
+pub enum FleetCommand {
+    /// 按清单文件对所有主机执行批量操作
+    Apply {
+        /// 清单文件路径（YAML）
+        inventory: PathBuf,
+        /// 要执行的操作，可重复指定，按顺序执行（默认使用清单中的 operations）
+        #[arg(
+            long = "op",
+            value_name = "OPERATION",
+            help = "要执行的操作: check-update、upgrade、deploy、backup，可重复指定（默认: 清单中的 operations）"
+        )]
+        operations: Vec<FleetOperation>,
+        /// 同时操作的主机数
+        #[arg(long, help = "同时操作的主机数（默认: 清单中的 parallelism 或 4）")]
+        parallel: Option<usize>,
+        /// 每个操作最多执行的次数
+        #[arg(
+            long,
+            help = "每个操作最多执行的次数，含首次（默认: 清单中的 retry.attempts）"
+        )]
+        attempts: Option<u32>,
+        /// JSON 报告输出路径
+        #[arg(long, value_name = "FILE", help = "输出 JSON 格式的汇总报告")]
+        report_json: Option<PathBuf>,
+        /// HTML 报告输出路径
+        #[arg(long, value_name = "FILE", help = "输出 HTML 格式的汇总报告")]
+        report_html: Option<PathBuf>,
+    },
+}
+
+/// 补丁清单相关命令
+#[derive(Subcommand, Debug)]
+pub enum PatchCommand {
+    /// 检查补丁操作清单（未知字段、重复条目、路径重叠和越界路径）
+    Lint {
+        /// 清单文件路径（operations 对象、补丁包信息或完整的服务清单 JSON）
+        manifest: PathBuf,
+    },
+    /// 输出补丁操作清单的 JSON Schema
+    Schema,
 }
 
 /// Nuwax Cli ent CLI - Docker 服务管理和升级工具
@@ -150,14 +767,58 @@ pub enum CacheCommand {
 #[command(long_about = metadata::display::DESCRIPTION_LONG)]
 #[command(author = metadata::PROJECT_AUTHORS)]
 pub struct Cli {
-    /// 配置文件路径
-    #[arg(short, long, default_value = "config.toml")]
-    pub config: PathBuf,
+    /// 配置文件路径（优先于 NUWAX_CONFIG 环境变量，默认为当前目录下的 config.toml）
+    ///
+    /// 配置文件不在当前目录时先切换到其所在目录，部署中的相对路径都以该目录为准
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
 
     /// 详细输出
     #[arg(short, long)]
     pub verbose: bool,
 
+    /// Docker服务工作目录（默认为当前目录下的 docker，优先于 NUWAX_DOCKER_DIR 环境变量和配置文件中的 docker.work_dir）
+    #[arg(long, global = true, value_name = "DIR")]
+    pub work_dir: Option<PathBuf>,
+
+    /// 日志文件路径（优先于 NUWAX_LOG_FILE 环境变量）
+    #[arg(long, global = true)]
+    pub log_file: Option<PathBuf>,
+
+    /// 日志轮转策略：never、hourly、daily 或文件大小（如 50MB）
+    #[arg(long, global = true)]
+    pub log_rotation: Option<LogRotation>,
+
+    /// 保留的日志文件数量
+    #[arg(long, global = true)]
+    pub log_max_files: Option<usize>,
+
+    /// 额外输出下载和升级的独立日志文件
+    #[arg(long, global = true)]
+    pub log_split: bool,
+
+    /// 输出下载、补丁、备份、解压的操作事件：human（日志）或 json（每行一个事件，写入标准输出）
+    ///
+    /// 未指定时，终端上以进度条显示这些操作的进度
+    #[arg(long, global = true, value_name = "FORMAT")]
+    pub events: Option<EventFormat>,
+
+    /// 对所有确认提示自动回答“是”
+    #[arg(short = 'y', long, global = true)]
+    pub yes: bool,
+
+    /// 禁止交互输入：需要确认或选择时直接报错退出（用于 CI、图形界面等环境）
+    #[arg(long, global = true, conflicts_with = "yes")]
+    pub no_input: bool,
+
+    /// 录制升级会话（API 响应、服务清单、升级策略、文件操作、差异SQL）到脱敏后的会话文件，用于 replay 复现问题
+    #[arg(long, global = true, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// 记录每个 API 请求的方法、地址、状态码、耗时和脱敏后的正文，用于排查接口问题（默认写入日志目录下的 http-debug.log，见 paths 命令）
+    #[arg(long, global = true, value_name = "FILE", num_args = 0..=1, require_equals = true)]
+    pub debug_http: Option<Option<PathBuf>>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -177,15 +838,51 @@ pub enum Commands {
     CheckUpdate(CheckUpdateCommand),
     /// 显示当前API配置信息
     ApiInfo,
-    /// 下载Docker服务文件
+    /// 下载Docker服务文件，或回滚升级
+    #[command(args_conflicts_with_subcommands = true)]
     Upgrade {
+        #[command(subcommand)]
+        command: Option<UpgradeCommand>,
         #[command(flatten)]
         args: UpgradeArgs,
     },
-    /// 手动创建备份
-    Backup,
+    /// 手动创建备份，或管理备份目录
+    #[command(args_conflicts_with_subcommands = true)]
+    Backup {
+        #[command(subcommand)]
+        command: Option<BackupCommand>,
+        /// 同时备份 CLI 自身状态（config.toml 和本地数据库）
+        #[arg(long, help = "同时备份 CLI 自身状态（config.toml 和本地数据库）")]
+        include_system: bool,
+        /// 创建快照备份（归类为快照，按快照保留策略清理）
+        #[arg(long, help = "创建快照备份（按快照保留策略清理，默认永久保留）")]
+        snapshot: bool,
+        /// 使用分块去重存储（配置 backup.dedup 开启时始终使用）
+        #[arg(long, help = "使用分块去重存储，相同内容在多个备份间只保存一份")]
+        dedup: bool,
+        /// 持续服务仍在运行时也继续备份
+        #[arg(long, help = "持续服务仍在运行时也继续备份（数据可能不一致）")]
+        force: bool,
+    },
+    /// 审批升级计划（使用审批私钥签名），或生成审批密钥
+    #[command(args_conflicts_with_subcommands = true)]
+    Approve {
+        #[command(subcommand)]
+        command: Option<ApproveCommand>,
+        /// check-update plan 生成的升级计划文件
+        plan: Option<PathBuf>,
+        /// 审批私钥（approve keygen 生成）
+        #[arg(long)]
+        key: Option<PathBuf>,
+        /// 审批人名称，记录在签名中
+        #[arg(long)]
+        approver: Option<String>,
+    },
     /// 列出所有备份
-    ListBackups,
+    ListBackups {
+        #[command(flatten)]
+        args: BackupListArgs,
+    },
     /// 从备份恢复
     Rollback {
         /// 备份 ID（可选，不提供时将显示交互式选择界面）
@@ -199,6 +896,22 @@ pub enum Commands {
         /// 是否回滚数据,默认不会滚数据文件
         #[arg(long, default_value = "false", help = "是否回滚数据文件，默认不回滚")]
         rollback_data: bool,
+        /// 同时恢复 CLI 自身状态（config.toml 和本地数据库）
+        #[arg(
+            long,
+            help = "同时恢复 CLI 自身状态（config.toml 和本地数据库），需备份时使用 --include-system"
+        )]
+        restore_system: bool,
+        /// 允许恢复其他命名空间（项目）的备份
+        #[arg(long, help = "允许恢复其他命名空间（项目）的备份")]
+        allow_other_namespace: bool,
+        /// 只预览恢复会覆盖、删除的文件和保持不变的受保护路径，不执行恢复
+        #[arg(
+            long,
+            conflicts_with_all = ["force", "list_json", "restore_system"],
+            help = "只预览恢复会覆盖、删除的文件和保持不变的受保护路径，不执行恢复"
+        )]
+        preview: bool,
     },
     /// 只从备份恢复 data 目录（保留 app 目录和配置文件）
     RollbackDataOnly {
@@ -207,6 +920,9 @@ pub enum Commands {
         /// 强制覆盖
         #[arg(long)]
         force: bool,
+        /// 允许恢复其他命名空间（项目）的备份
+        #[arg(long, help = "允许恢复其他命名空间（项目）的备份")]
+        allow_other_namespace: bool,
     },
     /// Docker服务相关命令
     #[command(subcommand)]
@@ -223,6 +939,36 @@ pub enum Commands {
     #[command(subcommand)]
     AutoBackup(AutoBackupCommand),
 
+    /// 服务看门狗（按健康检查结果自动重启常驻服务）
+    #[command(subcommand)]
+    Watchdog(WatchdogCommand),
+
+    /// Prometheus 监控指标（服务状态、重启次数、备份时间、部署版本）
+    #[command(subcommand)]
+    Metrics(MetricsCommand),
+
+    /// 启动本机 HTTP API 服务，供桌面端和自动化脚本调用（状态、备份、升级、健康检查）
+    Serve {
+        /// 监听地址（只允许本机地址）
+        #[arg(long, default_value = "127.0.0.1:9410")]
+        listen: SocketAddr,
+        /// 访问令牌文件，不存在时自动生成
+        #[arg(long, default_value = "serve.token")]
+        token_file: PathBuf,
+    },
+
+    /// 服务端口覆盖（持久化自定义主机端口）
+    #[command(subcommand)]
+    Ports(PortsCommand),
+
+    /// .env 环境变量管理
+    #[command(subcommand)]
+    Env(EnvCommand),
+
+    /// 数据库运维（执行SQL文件、逻辑备份与恢复）
+    #[command(subcommand)]
+    Db(DbCommand),
+
     /// 自动升级部署
     #[command(subcommand)]
     AutoUpgradeDeploy(AutoUpgradeDeployCommand),
@@ -248,5 +994,68 @@ pub enum Commands {
         /// 输出文件名（可选，默认为upgrade_diff.sql）
         #[arg(long, default_value = "upgrade_diff.sql", help = "差异SQL输出文件名")]
         output: String,
+        /// 数据库方言
+        #[arg(long, default_value = "mysql", help = "数据库方言：mysql、postgres")]
+        dialect: SqlDialect,
+    },
+
+    /// 统计磁盘占用（数据目录、备份、下载缓存、临时文件、Docker 镜像和数据卷）
+    DiskUsage {
+        /// 以 JSON 格式输出
+        #[arg(long)]
+        json: bool,
+        /// 单项占用超过该值（GB）时发出警告
+        #[arg(long, value_name = "GB")]
+        warn_above: Option<f64>,
+        /// 可用空间低于该值（GB）时发出警告
+        #[arg(long, value_name = "GB", default_value_t = DEFAULT_MIN_FREE_GB)]
+        min_free: f64,
+    },
+
+    /// 查看历史记录
+    #[command(subcommand)]
+    History(HistoryCommand),
+
+    /// 收集诊断信息并打包，用于提交技术支持工单
+    SupportBundle {
+        /// 输出文件路径（可选，默认为当前目录下带时间戳的 tar.gz 文件）
+        #[arg(
+            short,
+            long,
+            help = "诊断包输出路径（默认: nuwax-support-bundle-<时间>.tar.gz）"
+        )]
+        output: Option<PathBuf>,
+    },
+
+    /// 通过 SSH 管理其他主机上的部署
+    Remote {
+        #[command(flatten)]
+        args: RemoteArgs,
+        #[command(subcommand)]
+        command: RemoteCommand,
+    },
+
+    /// 按清单文件对多台主机批量执行操作
+    #[command(subcommand)]
+    Fleet(FleetCommand),
+
+    /// 补丁清单工具（供服务包打包方使用）
+    #[command(subcommand)]
+    Patch(PatchCommand),
+
+    /// 回放 --record 录制的升级会话，按录制数据重新执行升级决策（开发者排查问题用）
+    Replay {
+        /// 会话文件路径
+        session: PathBuf,
+    },
+
+    /// 在临时目录中自检备份、补丁、SQL差异、本地数据库和Docker连接（验证安装包用）
+    SelfTest {
+        /// 跳过 Docker 连接检查（没有 Docker 的打包机器）
+        #[arg(long, help = "跳过 Docker 连接检查")]
+        skip_docker: bool,
     },
+
+    /// 显示配置文件、Docker 工作目录、备份、缓存、日志等路径及其来源（命令行参数、环境变量、配置文件或默认值）
+    Paths,
 }
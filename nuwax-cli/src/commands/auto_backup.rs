@@ -1,12 +1,15 @@
 use std::path::Path;
 
 use crate::app::CliApp;
-use crate::cli::AutoBackupCommand;
+use crate::cli::{AutoBackupCommand, BackupListArgs};
 use crate::commands::{backup, docker_service};
+use crate::docker_service::failure_report::report_startup_failure;
 use crate::docker_service::health_check::HealthChecker;
+use crate::docker_service::ServiceGate;
 use crate::docker_utils;
 use anyhow::Result;
-use client_core::constants::{cron, timeout};
+use client_core::constants::{cron, timeout, upgrade};
+use client_core::database::BackupType;
 use client_core::upgrade_strategy::UpgradeStrategy;
 use serde::{Deserialize, Serialize};
 
@@ -82,7 +85,7 @@ pub async fn run_auto_backup(app: &mut CliApp) -> Result<()> {
     // 3. 执行备份
     info!("开始执行备份操作");
     let mut backup_error_message: String = String::new();
-    match backup::run_backup(app).await {
+    match backup::run_backup(app, BackupType::Scheduled, false, false, false).await {
         Ok(_) => {
             backup_success = true;
             info!("备份执行成功");
@@ -128,6 +131,12 @@ pub async fn run_auto_backup(app: &mut CliApp) -> Result<()> {
                 }
                 Ok(false) => {
                     debug!("最终检查：服务未正常启动");
+                    report_startup_failure(
+                        &app.docker_manager,
+                        "自动备份后启动服务",
+                        &upgrade::get_startup_failure_report_path(),
+                    )
+                    .await;
                 }
                 Err(e) => {
                     error!(error = %e, "最终检查失败");
@@ -151,6 +160,7 @@ pub async fn run_auto_backup(app: &mut CliApp) -> Result<()> {
     Ok(())
 }
 /// 执行自动备份流程：停止服务 -> 备份 -> 重启服务
+#[tracing::instrument(level = "trace", name = "phase.backup", skip_all)]
 pub async fn run_auto_backup_with_upgrade_strategy(
     app: &mut CliApp,
     upgrade_strategy: UpgradeStrategy,
@@ -165,9 +175,11 @@ pub async fn run_auto_backup_with_upgrade_strategy(
 
     // 1. 检查Docker服务状态
     debug!("检查Docker服务状态");
-    let running_flag =
-        backup::check_docker_service_running(app.config.clone(), app.docker_manager.clone())
-            .await?;
+    let gate_report = ServiceGate::new(app.config.clone(), app.docker_manager.clone())
+        .inspect()
+        .await?;
+    gate_report.log_summary(backup::COLD_BACKUP);
+    let running_flag = !gate_report.is_clear();
 
     if running_flag {
         // 2. 停止Docker服务
@@ -239,6 +251,12 @@ pub async fn run_auto_backup_with_upgrade_strategy(
                 }
                 Ok(false) => {
                     debug!("最终检查：服务未正常启动");
+                    report_startup_failure(
+                        &app.docker_manager,
+                        "自动备份后启动服务",
+                        &upgrade::get_startup_failure_report_path(),
+                    )
+                    .await;
                 }
                 Err(e) => {
                     error!(error = %e, "最终检查失败");
@@ -322,7 +340,7 @@ pub async fn show_status(app: &mut CliApp) -> Result<()> {
     info!("============");
 
     // 显示备份历史记录（包含完整的操作列表）
-    backup::run_list_backups(app).await?;
+    backup::run_list_backups(app, BackupListArgs::default()).await?;
 
     // 添加手动备份特定的操作提示
     info!("");
@@ -1,20 +1,53 @@
 use crate::app::CliApp;
-use crate::cli::AutoUpgradeDeployCommand;
-use crate::commands::{auto_backup, backup, docker_service, update};
+use crate::cli::{AutoUpgradeDeployCommand, BackupListArgs};
+use crate::commands::{
+    auto_backup, backup, cache, changelog, docker_service, env, history, update, upgrade_plan,
+};
+use crate::docker_service::compose_parser::DockerComposeParser;
+use crate::docker_service::compose_validation;
+use crate::docker_service::failure_report::report_startup_failure;
 use crate::docker_service::health_check::HealthChecker;
+use crate::docker_service::permission_policy::apply_permission_policy;
+use crate::docker_service::service_impact::{ServiceImpact, analyze_service_impact};
+use crate::utils::env_manager::{
+    GENERATED_SECRETS_FILE_NAME, SecretBootstrapMode, bootstrap_secrets,
+};
+use crate::utils::event_output::spawn_pipeline_forwarder;
+use crate::utils::locked_files::LockedFileReport;
+use crate::utils::patch_conflicts::{self, ConflictPolicy, ConflictResolution};
+use crate::utils::prompt;
 use crate::{DockerService, docker_utils};
 use anyhow::Result;
-use client_core::constants::timeout;
+use client_core::clock;
+use client_core::config::BackupStagingMode;
+use client_core::constants::{
+    clock::MAX_SCHEDULE_DELAY_DAYS, docker, telemetry::METRICS_TARGET, timeout, upgrade,
+};
 use client_core::container::DockerManager;
+use client_core::data_migration::{DataMigrator, MigrationJournalStore};
+use client_core::database::{UpgradeKind, UpgradeRecord, UpgradeStatus};
+use client_core::deploy_checkpoint::{DeployCheckpoint, DeployCheckpointStore, DeployPhase};
+use client_core::events::{EventSender, OperationKind, PipelinePhase};
+use client_core::fs_probe::check_writable;
 use client_core::mysql_executor::{MySqlConfig, MySqlExecutor};
-use client_core::sql_diff::generate_schema_diff;
+use client_core::mysql_readiness::{MYSQL_SERVICE, ReadinessProgress, wait_for_mysql_ready};
+use client_core::paths;
+use client_core::postgres_executor::{PostgresConfig, PostgresExecutor, detect_database};
+use client_core::sql_diff::{SqlDialect, generate_schema_diff_with_dialect};
+use client_core::sql_dry_run::SqlDryRun;
+use client_core::tree_copy::copy_tree;
+use client_core::upgrade_plan::UpgradePlan;
+use client_core::upgrade_preview::{ChangeKind, UpgradeChangeReport};
+use client_core::upgrade_session;
 use client_core::upgrade_strategy::UpgradeStrategy;
+use client_core::user_modifications::{archive_user_modifications, find_user_modifications};
+use client_core::version::Version;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 
 /// 获取docker-compose文件路径
 fn get_compose_file_path(config_file: &Option<PathBuf>) -> PathBuf {
@@ -34,9 +67,63 @@ pub async fn handle_auto_upgrade_deploy_command(
             port,
             config,
             project,
+            resume,
+            restart,
+            show_changes,
+            sql_dry_run,
+            regenerate_secrets,
+            force_full,
+            staged_backup,
+            on_conflict,
+            replace_locked_on_reboot,
+            plan,
         } => {
             info!("🚀 开始自动升级部署流程...");
-            run_auto_upgrade_deploy(app, port, config, project).await
+            // 在下载和停止服务之前校验审批和当前部署状态
+            let plan = match plan {
+                Some(path) => Some(upgrade_plan::verify_plan_for_deploy(app, &path).await?),
+                None => None,
+            };
+            if restart {
+                info!("🧹 清除部署检查点，强制重新执行完整部署流程");
+                DeployCheckpointStore::default().clear()?;
+            }
+            let preview = if show_changes {
+                ChangePreview::Confirm
+            } else {
+                ChangePreview::Skip
+            };
+            let options = DeployOptions {
+                resume,
+                preview,
+                sql_dry_run,
+                regenerate_secrets,
+                force_full,
+                staged_backup,
+                on_conflict,
+                replace_locked_on_reboot,
+                only_phase: None,
+            };
+            let mut reschedules = 0;
+            loop {
+                let result = run_auto_upgrade_deploy(
+                    app,
+                    port,
+                    config.clone(),
+                    project.clone(),
+                    options,
+                    plan.as_ref(),
+                )
+                .await;
+                let retry_at = result
+                    .as_ref()
+                    .err()
+                    .and_then(|e| reschedule_after_rate_limit(e, &mut reschedules));
+                match retry_at {
+                    Some(retry_at) => sleep_until(retry_at).await,
+                    None => return result,
+                }
+            }
         }
         AutoUpgradeDeployCommand::Status => {
             info!("显示自动升级部署状态");
@@ -45,15 +132,195 @@ pub async fn handle_auto_upgrade_deploy_command(
     }
 }
 
+/// 升级前的变更预览方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangePreview {
+    /// 不展示变更
+    Skip,
+    /// 展示变更并等待确认（指定 --yes 时自动确认）
+    Confirm,
+}
+
+/// 自动升级部署的执行选项
+#[derive(Debug, Clone, Copy)]
+pub struct DeployOptions {
+    /// 从部署检查点续传
+    pub resume: bool,
+    /// 升级前的变更预览方式
+    pub preview: ChangePreview,
+    /// 先预演数据库升级
+    pub sql_dry_run: bool,
+    /// 重新生成 .env 中自动生成的密钥
+    pub regenerate_secrets: bool,
+    /// 跳过增量补丁，直接全量升级
+    pub force_full: bool,
+    /// 暂存升级前备份，配置未开启暂存时使用复制方式
+    pub staged_backup: bool,
+    /// 补丁与受保护目录冲突时的处理策略，未指定时在终端中询问
+    pub on_conflict: Option<ConflictPolicy>,
+    /// 被占用而未能替换的文件登记为系统重启时替换
+    pub replace_locked_on_reboot: bool,
+    /// 只执行指定阶段（`upgrade phase`），完成后立即返回；指定启动阶段时同时执行部署
+    pub only_phase: Option<DeployPhase>,
+}
+
+impl Default for DeployOptions {
+    fn default() -> Self {
+        Self {
+            resume: false,
+            preview: ChangePreview::Skip,
+            sql_dry_run: false,
+            regenerate_secrets: false,
+            force_full: false,
+            staged_backup: false,
+            on_conflict: None,
+            replace_locked_on_reboot: false,
+            only_phase: None,
+        }
+    }
+}
+
+/// 本次升级写入升级历史的信息，由各阶段逐步填充
+#[derive(Debug, Default)]
+struct UpgradeAttempt {
+    from_version: String,
+    to_version: String,
+    /// 确定升级策略并确认执行后才有值，为空表示没有实际升级
+    upgrade_type: Option<UpgradeKind>,
+    backup_id: Option<i64>,
+    /// 失败后是否已恢复升级前的数据
+    rolled_back: bool,
+}
+
 /// 执行自动升级部署流程
+///
+/// 每完成一个阶段都会写入部署检查点，`options.resume` 为 true 时从同一目标版本
+/// 最后完成的阶段之后继续执行。每次实际执行的升级都会写入升级历史
+///
+/// 指定 `plan` 时，升级服务器返回的升级内容必须与审批的计划一致
+#[tracing::instrument(level = "trace", name = "auto_upgrade_deploy", skip_all)]
 pub async fn run_auto_upgrade_deploy(
     app: &mut CliApp,
     frontend_port: Option<u16>,
     config_file: Option<PathBuf>,
     project_name: Option<String>,
+    options: DeployOptions,
+    plan: Option<&UpgradePlan>,
 ) -> Result<()> {
+    let started = Instant::now();
+    let started_at = chrono::Utc::now();
+    let mut attempt = UpgradeAttempt::default();
+
+    // 各组件的事件经转发任务汇总为总体进度，流程结束后恢复原发送端并等待剩余事件输出
+    let outer_events = app.events.clone();
+    let (pipeline_events, receiver) = EventSender::channel();
+    app.events = pipeline_events;
+    let forwarder = spawn_pipeline_forwarder(receiver, outer_events.clone());
+    let result = run_auto_upgrade_deploy_phases(
+        app,
+        frontend_port,
+        config_file,
+        project_name,
+        options,
+        plan,
+        &mut attempt,
+    )
+    .await;
+    app.events = outer_events;
+    let _ = forwarder.await;
+
+    // 记录升级耗时和成功/失败次数
+    tracing::trace!(
+        target: METRICS_TARGET,
+        histogram.upgrade_duration_seconds = started.elapsed().as_secs_f64(),
+    );
+    if result.is_ok() {
+        tracing::trace!(target: METRICS_TARGET, monotonic_counter.upgrade_success_total = 1_u64);
+    } else {
+        tracing::trace!(target: METRICS_TARGET, monotonic_counter.upgrade_failures_total = 1_u64);
+    }
+
+    let upgraded_to = attempt
+        .upgrade_type
+        .map(|_| attempt.to_version.clone())
+        .filter(|_| result.is_ok() && app.config.cache.auto_clean_after_upgrade);
+    record_upgrade_history(app, attempt, started_at, &result).await;
+    // 升级历史写入后再清理，本次升级的升级前版本可以作为 previous 保留
+    if let Some(version) = upgraded_to {
+        cache::clean_downloads_after_upgrade(app, &version).await;
+    }
+    result
+}
+
+/// 把本次升级写入本地升级历史并上报服务器，失败只记录警告
+async fn record_upgrade_history(
+    app: &CliApp,
+    attempt: UpgradeAttempt,
+    started_at: chrono::DateTime<chrono::Utc>,
+    result: &Result<()>,
+) {
+    // 取消或无需升级时不记录
+    let Some(upgrade_type) = attempt.upgrade_type else {
+        return;
+    };
+    let status = match result {
+        Ok(()) => UpgradeStatus::Success,
+        Err(_) if attempt.rolled_back => UpgradeStatus::RolledBack,
+        Err(_) => UpgradeStatus::Failed,
+    };
+    let record = UpgradeRecord {
+        upgrade_id: uuid::Uuid::new_v4().to_string(),
+        from_version: attempt.from_version,
+        to_version: attempt.to_version,
+        upgrade_type,
+        status,
+        backup_id: attempt.backup_id,
+        started_at,
+        completed_at: Some(chrono::Utc::now()),
+        error_message: result.as_ref().err().map(|e| e.to_string()),
+    };
+
+    if let Err(e) = app.database.record_upgrade(&record).await {
+        warn!("⚠️ 记录升级历史失败: {}", e);
+    }
+    history::report_upgrade(app, &record).await;
+}
+
+async fn run_auto_upgrade_deploy_phases(
+    app: &mut CliApp,
+    frontend_port: Option<u16>,
+    config_file: Option<PathBuf>,
+    project_name: Option<String>,
+    options: DeployOptions,
+    plan: Option<&UpgradePlan>,
+    attempt: &mut UpgradeAttempt,
+) -> Result<()> {
+    let DeployOptions {
+        resume,
+        preview,
+        sql_dry_run,
+        regenerate_secrets,
+        force_full,
+        staged_backup,
+        on_conflict,
+        replace_locked_on_reboot,
+        only_phase,
+    } = options;
     info!("🚀 开始自动升级部署流程...");
 
+    if plan.is_none() && app.config.upgrade_approval.required {
+        return Err(anyhow::anyhow!(
+            "已开启升级审批 (upgrade_approval.required)，请使用 --plan 指定经过审批的升级计划"
+        ));
+    }
+
+    let backup_staging = match app.config.backup.staging {
+        // 单独执行备份阶段时不暂存，阶段结束时备份即已完成
+        _ if only_phase.is_some() => BackupStagingMode::Off,
+        BackupStagingMode::Off if staged_backup => BackupStagingMode::Copy,
+        mode => mode,
+    };
+
     // 如果指定了端口，显示端口信息
     if let Some(port) = frontend_port {
         info!("🔌 自定义frontend端口: {}", port);
@@ -67,36 +334,183 @@ pub async fn run_auto_upgrade_deploy(
     // 1. 获取最新版本信息并下载
     info!("📥 正在下载最新的Docker服务版本...");
 
-    // 获取最新版本信息
-    let latest_version = match app.api_client.get_enhanced_service_manifest().await {
+    // 获取最新版本信息，版本比较按数值进行
+    let current_version = app.config.get_docker_version()?;
+    let (target_version, release_notes) = match app.api_client.get_enhanced_service_manifest().await
+    {
         Ok(enhanced_service_manifest) => {
-            let lastest_version = enhanced_service_manifest.version.to_string();
-
             info!(
                 "📋 版本信息: {} -> {}",
-                app.config.get_docker_versions(),
-                lastest_version
+                current_version, enhanced_service_manifest.version
             );
-            lastest_version
+            (
+                enhanced_service_manifest.version,
+                Some(enhanced_service_manifest.release_notes),
+            )
         }
         Err(e) => {
             warn!("⚠️ 获取版本信息失败，使用配置版本: {}", e);
-            app.config.get_docker_versions()
+            (current_version.clone(), None)
+        }
+    };
+    let latest_version = target_version.to_string();
+
+    // 加载部署检查点（检查点与目标版本绑定）
+    let checkpoint_store = DeployCheckpointStore::default();
+    let existing_checkpoint = checkpoint_store.load_for_version(&latest_version);
+    let resumed_checkpoint = match existing_checkpoint {
+        Some(checkpoint) if resume => {
+            info!(
+                "⏩ 从上次中断处继续部署 (目标版本: {}, 最后完成阶段: {})",
+                checkpoint.target_version,
+                checkpoint
+                    .last_completed_phase()
+                    .map(|phase| phase.to_string())
+                    .unwrap_or_else(|| "无".to_string())
+            );
+            Some(checkpoint)
+        }
+        Some(checkpoint) => {
+            if let Some(phase) = checkpoint.last_completed_phase() {
+                warn!(
+                    "⚠️ 检测到版本 {} 未完成的部署（已完成阶段: {}），可使用 --resume 继续，本次将重新执行完整流程",
+                    checkpoint.target_version, phase
+                );
+            }
+            None
+        }
+        None => {
+            if resume {
+                info!(
+                    "ℹ️ 未找到目标版本 {} 的部署检查点，将执行完整部署流程",
+                    latest_version
+                );
+            }
+            None
         }
     };
 
-    // 下载服务包，但先不解压
+    // 2. 🔍 检查部署类型：第一次部署 vs 升级部署（续传时沿用检查点中的记录）
+    let mut checkpoint = match resumed_checkpoint {
+        Some(checkpoint) => checkpoint,
+        None => {
+            let mut checkpoint =
+                DeployCheckpoint::new(latest_version.clone(), is_first_deployment().await);
+            checkpoint.from_version = Some(app.config.get_docker_versions());
+            checkpoint
+        }
+    };
+    let is_first_deployment = checkpoint.is_first_deployment;
+
+    // 服务器版本低于已部署版本时不自动降级
+    if !is_first_deployment && target_version < current_version {
+        return Err(anyhow::anyhow!(
+            "服务器版本 {} 低于当前部署版本 {}，自动升级部署不会降级；如需回退请使用 'nuwax-cli upgrade rollback'",
+            target_version,
+            current_version
+        ));
+    }
+
+    // 单独执行某个阶段时，之前的阶段必须已经完成
+    if let Some(only) = only_phase {
+        let required = if only == DeployPhase::Start {
+            DeployPhase::Deploy
+        } else {
+            only
+        };
+        if let Some(missing) = checkpoint.first_incomplete_before(required) {
+            return Err(anyhow::anyhow!(
+                "目标版本 {} 的{}阶段尚未完成，请先运行 'nuwax-cli upgrade phase {}'",
+                latest_version,
+                missing,
+                missing.as_str()
+            ));
+        }
+    }
+
+    // 交互式升级前展示各版本的更新说明，续传、单阶段执行和按计划执行时不再询问
+    if plan.is_none()
+        && only_phase.is_none()
+        && !is_first_deployment
+        && checkpoint.last_completed_phase().is_none()
+        && target_version != current_version
+        && !changelog::confirm_release_notes(app, &target_version, release_notes).await?
+    {
+        info!("👋 已取消升级部署");
+        return Ok(());
+    }
+
+    // 下载服务包，但先不解压；下载阶段已完成时只获取升级策略
+    app.events.pipeline_phase_started(PipelinePhase::Download);
+    let download_completed = !should_run_phase(&checkpoint, only_phase, DeployPhase::Download);
+    if download_completed {
+        info!("⏭️ 下载阶段已完成，跳过下载");
+    }
+    // 按审批的计划升级时，先确认升级内容与计划一致再下载
+    if let Some(plan) = plan {
+        let check_args = crate::cli::UpgradeArgs {
+            force: false,
+            force_full,
+            check: true,
+        };
+        let upgrade_strategy = update::run_upgrade(app, check_args).await?;
+        upgrade_plan::ensure_strategy_matches_plan(plan, &upgrade_strategy)?;
+    }
     let upgrade_args = crate::cli::UpgradeArgs {
         force: false,
-        check: false,
+        force_full,
+        check: download_completed,
     };
     let upgrade_strategy = update::run_upgrade(app, upgrade_args).await?;
+    save_deploy_checkpoint(&checkpoint_store, &mut checkpoint, DeployPhase::Download);
+    app.events.pipeline_phase_completed(PipelinePhase::Download);
+    if only_phase == Some(DeployPhase::Download) {
+        info!("✅ 下载阶段完成，下一阶段: 'nuwax-cli upgrade phase backup'");
+        return Ok(());
+    }
 
-    // 2. 🔍 检查部署类型：第一次部署 vs 升级部署
-    let is_first_deployment = is_first_deployment().await;
-    let latest_backup_id: Option<i64>; // 在外层作用域声明
+    // 在停止服务和备份前展示升级影响，用户取消时不做任何改动
+    if preview == ChangePreview::Confirm {
+        if !should_run_phase(&checkpoint, only_phase, DeployPhase::Extract) {
+            info!("⏭️ 服务包已解压，跳过变更预览");
+        } else if !confirm_upgrade_changes(app, &upgrade_strategy)? {
+            info!("👋 已取消升级部署");
+            return Ok(());
+        }
+    }
 
-    if is_first_deployment {
+    // 补丁要修改受保护目录中已存在的内容时，在停止服务前确定处理策略
+    let conflicts = if !should_run_phase(&checkpoint, only_phase, DeployPhase::Extract) {
+        ConflictResolution::none()
+    } else {
+        patch_conflicts::resolve_conflicts(
+            &upgrade_strategy,
+            &docker::get_docker_work_dir(),
+            on_conflict,
+        )?
+    };
+
+    attempt.from_version = app.config.get_docker_versions();
+    attempt.to_version = latest_version.clone();
+    attempt.upgrade_type = match &upgrade_strategy {
+        UpgradeStrategy::FullUpgrade { .. } => Some(UpgradeKind::Full),
+        UpgradeStrategy::PatchUpgrade { .. } | UpgradeStrategy::PatchChainUpgrade { .. } => {
+            Some(UpgradeKind::Patch)
+        }
+        UpgradeStrategy::NoUpgrade { .. } => None,
+    };
+
+    let mut latest_backup_id: Option<i64>; // 在外层作用域声明
+    // 暂存模式下后台压缩中的备份，解压完成后等待其结束
+    let mut pending_backup: Option<backup::PendingBackup> = None;
+    // 只重启部分服务时保存的被替换文件，解压失败时用于恢复
+    let mut patched_files_backup: Option<PathBuf> = None;
+
+    app.events.pipeline_phase_started(PipelinePhase::Backup);
+    if !should_run_phase(&checkpoint, only_phase, DeployPhase::Backup) {
+        info!("⏭️ 备份阶段已完成，跳过停止服务和数据备份");
+        latest_backup_id = checkpoint.backup_id;
+    } else if is_first_deployment {
         info!("🆕 检测到第一次部署，但检查是否有历史备份可恢复...");
 
         // 🔧 即使是首次部署，也检查是否有备份数据可以恢复
@@ -146,11 +560,27 @@ pub async fn run_auto_upgrade_deploy(
         };
         let health_report = docker_service.health_check().await?;
 
-        if health_report.get_running_count() > 0 {
+        // 补丁只影响部分服务时只停止这些服务，其余服务保持运行
+        let partial_services = if health_report.get_running_count() > 0
+            && only_phase.is_none()
+            && frontend_port.is_none()
+        {
+            partial_restart_services(&get_compose_file_path(&config_file), &upgrade_strategy)
+        } else {
+            None
+        };
+
+        if let Some(services) = &partial_services {
+            info!("🛑 停止受补丁影响的服务: {}", services.join(", "));
+            docker_service.stop_selected_services(services).await?;
+        } else if health_report.get_running_count() > 0 {
             info!(
                 "Docker服务正在运行,运行容器数量:{},准备停止服务...",
                 health_report.get_running_count()
             );
+            // 按依赖顺序停止服务，数据库等服务正常退出后再备份
+            docker_service.stop_services().await?;
+
             // 等待服务完全停止
             info!("⏳ 等待Docker服务完全停止...");
             let compose_path = get_compose_file_path(&config_file);
@@ -170,7 +600,26 @@ pub async fn run_auto_upgrade_deploy(
 
         // 4. 💾 执行数据备份（在服务停止后）
         let need_backup = check_docker_files_exist().await?;
-        latest_backup_id = if need_backup {
+        latest_backup_id = if partial_services.is_some() {
+            // 数据库等服务仍在运行，无法创建冷备份，只保存补丁将替换的文件
+            patched_files_backup = Some(backup_patched_files(
+                app,
+                &upgrade_strategy,
+                &latest_version,
+            )?);
+            None
+        } else if need_backup && backup_staging.is_enabled() {
+            info!("💾 正在暂存数据备份 ({})...", backup_staging.as_str());
+            pending_backup = Some(
+                backup::stage_backup_with_upgrade_strategy(
+                    app,
+                    upgrade_strategy.clone(),
+                    backup_staging,
+                )
+                .await?,
+            );
+            None
+        } else if need_backup {
             info!("💾 正在创建数据备份...");
             // 🔧 复用backup.rs的成熟备份逻辑
             auto_backup::run_auto_backup_with_upgrade_strategy(app, upgrade_strategy.clone())
@@ -197,142 +646,292 @@ pub async fn run_auto_upgrade_deploy(
         };
 
         // 5. 📄 备份当前版本的SQL文件（用于后续差异比较）
-        backup_sql_file_before_upgrade().await?;
+        let (sql_dialect, _) = resolve_database(app, &config_file);
+        backup_sql_file_before_upgrade(sql_dialect).await?;
+        checkpoint.restart_services = partial_services;
+    }
+    checkpoint.backup_id = latest_backup_id;
+    attempt.backup_id = latest_backup_id;
+    // 暂存的备份压缩完成后才算备份阶段完成，中断后续传会重新备份
+    if pending_backup.is_none() {
+        save_deploy_checkpoint(&checkpoint_store, &mut checkpoint, DeployPhase::Backup);
+    }
+    app.events.pipeline_phase_completed(PipelinePhase::Backup);
+    if only_phase == Some(DeployPhase::Backup) {
+        info!("✅ 备份阶段完成，服务保持停止，下一阶段: 'nuwax-cli upgrade phase extract'");
+        return Ok(());
     }
 
-    // 5. 📦 解压新的Docker服务包（在服务停止和备份完成后）
-    info!("📦 正在解压Docker服务包...");
-
-    // 🛡️ 数据保护：只在升级部署时备份现有的数据目录
-    let temp_data_backup = if is_first_deployment {
-        None
+    app.events.pipeline_phase_started(PipelinePhase::Extract);
+    if !should_run_phase(&checkpoint, only_phase, DeployPhase::Extract) {
+        info!("⏭️ 解压阶段已完成，跳过解压");
     } else {
-        backup_data_before_cleanup().await?
-    };
+        // 5. 📦 解压新的Docker服务包（在服务停止和备份完成后）
+        info!("📦 正在解压Docker服务包...");
 
-    // 清理现有的docker目录以避免路径冲突
-    let docker_dir = std::path::Path::new("docker");
-    if docker_dir.exists() {
-        // 增量升级/全量升级
-        match upgrade_strategy.clone() {
-            UpgradeStrategy::PatchUpgrade { patch_info, .. } => {
-                // 增量升级逻辑
-                let changed_files = patch_info.get_changed_files();
-                //基于 docker_dir 目录下, 清理 changed_files 的相对路径的文件/目录
-
-                let remove_file_or_dir = changed_files
-                    .iter()
-                    .map(|path| PathBuf::from(docker_dir).join(path))
-                    .collect::<Vec<_>>();
-
-                let remove_file_or_dir: Vec<&Path> =
-                    remove_file_or_dir.iter().map(|p| p.as_path()).collect();
-                match safe_remove_file_or_dir(&remove_file_or_dir).await {
-                    Ok(_) => info!(
-                        "✅ 清理文件/目录成功: {}",
-                        &remove_file_or_dir
-                            .iter()
-                            .map(|p| p.to_string_lossy())
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    ),
-                    Err(e) => warn!("⚠️ 清理文件/目录失败: {}, 尝试继续解压", e),
+        // 🛡️ 数据保护：只在升级部署时备份现有的数据目录（数据库仍在运行时不复制）
+        let temp_data_backup = if is_first_deployment || checkpoint.restart_services.is_some() {
+            None
+        } else {
+            backup_data_before_cleanup().await?
+        };
+
+        // 按冲突策略先备份将被替换的受保护内容
+        let conflict_backup_dir = app
+            .config
+            .get_backup_dir()
+            .join(upgrade::PROTECTED_CONFLICT_BACKUP_DIR_NAME)
+            .join(format!(
+                "{}_{}",
+                latest_version,
+                chrono::Local::now().format("%Y%m%d_%H%M%S")
+            ));
+        conflicts.backup_if_requested(&conflict_backup_dir)?;
+
+        // 清理现有的docker目录以避免路径冲突
+        let docker_dir = docker::get_docker_work_dir();
+        if docker_dir.exists() {
+            // 增量升级/全量升级
+            match upgrade_strategy.clone() {
+                UpgradeStrategy::PatchUpgrade { .. }
+                | UpgradeStrategy::PatchChainUpgrade { .. } => {
+                    // 增量升级逻辑，补丁链清理所有补丁涉及的文件/目录
+                    let changed_files = upgrade_strategy.get_changed_files();
+                    //基于 docker_dir 目录下, 清理 changed_files 的相对路径的文件/目录
+
+                    // 保留现有内容时不清理受保护目录中的路径
+                    let remove_file_or_dir = changed_files
+                        .iter()
+                        .map(|path| docker_dir.join(path))
+                        .filter(|path| {
+                            conflicts.policy.replaces_protected()
+                                || !patch_conflicts::is_protected_path(path)
+                        })
+                        .collect::<Vec<_>>();
+
+                    let remove_file_or_dir: Vec<&Path> =
+                        remove_file_or_dir.iter().map(|p| p.as_path()).collect();
+                    match safe_remove_file_or_dir(&remove_file_or_dir).await {
+                        Ok(_) => info!(
+                            "✅ 清理文件/目录成功: {}",
+                            &remove_file_or_dir
+                                .iter()
+                                .map(|p| p.to_string_lossy())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                        Err(e) => warn!("⚠️ 清理文件/目录失败: {}, 尝试继续解压", e),
+                    }
                 }
-            }
-            UpgradeStrategy::FullUpgrade { .. } => {
-                // 全量升级逻辑
-                info!("🧹 清理现有docker目录以避免文件冲突...");
-                match safe_remove_docker_directory(docker_dir).await {
-                    Ok(_) => info!("✅ docker目录清理完成"),
-                    Err(e) => {
-                        warn!("⚠️ 清理docker目录失败: {}, 尝试继续解压", e);
-                        return Err(anyhow::anyhow!(format!("清理docker目录失败: {e}")));
+                UpgradeStrategy::FullUpgrade { .. } => {
+                    // 全量升级逻辑，清空前保存用户修改过的服务文件
+                    backup_user_modifications(app, &docker_dir, &latest_version)?;
+                    info!("🧹 清理现有docker目录以避免文件冲突...");
+                    match safe_remove_docker_directory(&docker_dir).await {
+                        Ok(_) => info!("✅ docker目录清理完成"),
+                        Err(e) => {
+                            warn!("⚠️ 清理docker目录失败: {}, 尝试继续解压", e);
+                            return Err(anyhow::anyhow!(format!("清理docker目录失败: {e}")));
+                        }
                     }
                 }
-            }
-            UpgradeStrategy::NoUpgrade { .. } => {
-                //do nothing
-                info!("版本一致,无需升级更新")
+                UpgradeStrategy::NoUpgrade { .. } => {
+                    //do nothing
+                    info!("版本一致,无需升级更新")
+                }
             }
         }
-    }
-
-    // 解压新的Docker服务包（使用最新版本）
-    match docker_service::extract_docker_service_with_upgrade_strategy(app, upgrade_strategy).await
-    {
-        Ok(_) => {
-            info!("✅ Docker服务包解压完成");
 
-            // 🔧 自动修复关键脚本文件权限
-            fix_script_permissions().await?;
+        // 解压新的Docker服务包（使用最新版本）
+        match docker_service::extract_docker_service_with_upgrade_strategy(
+            app,
+            upgrade_strategy,
+            conflicts.policy,
+        )
+        .await
+        {
+            Ok(locked) => {
+                info!("✅ Docker服务包解压完成");
+                report_locked_files(app, &locked, replace_locked_on_reboot);
+                conflicts.report_outcome();
+                if !conflicts.policy.replaces_protected() && !conflicts.conflicts.is_empty() {
+                    app.events.warning(
+                        OperationKind::Patch,
+                        format!(
+                            "已跳过 {} 项受保护路径的补丁操作",
+                            conflicts.conflicts.len()
+                        ),
+                    );
+                }
 
-            // 📝 更新配置文件中的Docker服务版本
-            if latest_version != app.config.get_docker_versions() {
-                info!(
-                    "📝 更新Docker服务版本: {} -> {}",
-                    app.config.get_docker_versions(),
-                    latest_version
-                );
+                // ⏳ 等待后台压缩的备份完成，之后才部署和启动服务
+                if let Some(pending) = pending_backup.take() {
+                    let backup_id = wait_pending_backup(app, pending).await.map_err(|e| {
+                        anyhow::anyhow!(
+                            "升级前备份失败，已停止升级（新版本文件已解压，服务未启动）: {e}"
+                        )
+                    })?;
+                    latest_backup_id = Some(backup_id);
+                    checkpoint.backup_id = latest_backup_id;
+                    attempt.backup_id = latest_backup_id;
+                    save_deploy_checkpoint(&checkpoint_store, &mut checkpoint, DeployPhase::Backup);
+                }
 
-                // 持久化到配置文件,这里修改docker应用版本,然后保存更新到toml配置里
-                let mut config = app.config.as_ref().clone();
-                //TODO: 以后需要优化这里的逻辑
-                config.write_docker_versions(latest_version.clone());
+                // 🔧 按权限策略修复文件权限
+                apply_permission_policy(&docker::get_docker_work_dir());
 
-                match config.save_to_file("config.toml") {
-                    Ok(_) => {
-                        info!("✅ 配置文件版本号已更新并保存");
-                    }
-                    Err(e) => {
-                        warn!("⚠️ 保存配置文件失败: {}", e);
-                        warn!("   版本号已在内存中更新，但配置文件未同步");
+                // 🔐 初始化 .env 中仍为模板默认值的密钥
+                // 只有全新部署（没有可恢复的历史数据）才生成新密钥，避免与已初始化的数据库不一致
+                let secret_mode = if regenerate_secrets {
+                    SecretBootstrapMode::Regenerate
+                } else if is_first_deployment && latest_backup_id.is_none() {
+                    SecretBootstrapMode::Generate
+                } else {
+                    SecretBootstrapMode::RestoreOnly
+                };
+                bootstrap_env_secrets(secret_mode);
+
+                // 🔍 启动前校验新的 docker-compose.yml，避免带着错误配置停服后无法启动
+                let compose_path = get_compose_file_path(&config_file);
+                if let Err(e) = compose_validation::ensure_compose_file_valid(&compose_path) {
+                    error!("❌ {}", e);
+                    if !is_first_deployment {
+                        restore_after_failed_extract(
+                            app,
+                            "docker-compose.yml 校验失败",
+                            latest_backup_id,
+                            &temp_data_backup,
+                            patched_files_backup.as_deref(),
+                        )
+                        .await?;
+                        attempt.rolled_back = true;
                     }
+                    return Err(anyhow::anyhow!("{e}，已停止升级，服务未启动"));
                 }
-            } else {
-                info!("📝 版本号无需更新 (已是最新版本: {})", latest_version);
-            }
 
-            // 📊 生成SQL差异文件（仅在升级部署时）
-            if !is_first_deployment {
-                generate_and_save_sql_diff(&app.config.get_docker_versions(), &latest_version)
-                    .await?;
-            }
-        }
-        Err(e) => {
-            error!("❌ Docker服务包解压失败: {}", e);
-            // 解压失败时，恢复备份的数据（仅在升级部署时）
-            if !is_first_deployment {
-                if let Some(backup_id) = latest_backup_id {
+                // 📝 更新配置文件中的Docker服务版本
+                if target_version != current_version {
                     info!(
-                        "🔄 解压失败，从最新完整备份恢复数据 (备份ID: {})",
-                        backup_id
+                        "📝 更新Docker服务版本: {} -> {}",
+                        app.config.get_docker_versions(),
+                        latest_version
                     );
-                    // data 目录也会被恢复
-                    backup::run_rollback(app, Some(backup_id), true, false, false, true).await?;
+
+                    // 持久化到配置文件,这里修改docker应用版本,然后保存更新到toml配置里
+                    let mut config = app.config.as_ref().clone();
+                    //TODO: 以后需要优化这里的逻辑
+                    config.write_docker_versions(&target_version);
+
+                    match config.save_to_file(&app.config_path) {
+                        Ok(_) => {
+                            info!("✅ 配置文件版本号已更新并保存");
+                        }
+                        Err(e) => {
+                            warn!("⚠️ 保存配置文件失败: {}", e);
+                            warn!("   版本号已在内存中更新，但配置文件未同步");
+                        }
+                    }
                 } else {
-                    info!("⚠️ 解压失败，使用临时备份恢复");
-                    restore_data_after_cleanup(&temp_data_backup).await?;
+                    info!("📝 版本号无需更新 (已是最新版本: {})", latest_version);
+                }
+
+                // 📊 生成SQL差异文件（仅在升级部署时）
+                if !is_first_deployment {
+                    let (sql_dialect, _) = resolve_database(app, &config_file);
+                    generate_and_save_sql_diff(
+                        &app.config.get_docker_versions(),
+                        &latest_version,
+                        sql_dialect,
+                    )
+                    .await?;
                 }
             }
-            return Err(e);
+            Err(e) => {
+                error!("❌ Docker服务包解压失败: {}", e);
+                if let Some(pending) = pending_backup.take() {
+                    match wait_pending_backup(app, pending).await {
+                        Ok(backup_id) => {
+                            latest_backup_id = Some(backup_id);
+                            attempt.backup_id = latest_backup_id;
+                        }
+                        Err(e) => warn!("⚠️ 升级前备份失败: {}", e),
+                    }
+                }
+                // 解压失败时，恢复备份的数据（仅在升级部署时）
+                if !is_first_deployment {
+                    restore_after_failed_extract(
+                        app,
+                        "解压失败",
+                        latest_backup_id,
+                        &temp_data_backup,
+                        patched_files_backup.as_deref(),
+                    )
+                    .await?;
+                    attempt.rolled_back = true;
+                }
+                return Err(e);
+            }
         }
+        save_deploy_checkpoint(&checkpoint_store, &mut checkpoint, DeployPhase::Extract);
+    }
+    app.events.pipeline_phase_completed(PipelinePhase::Extract);
+    if only_phase == Some(DeployPhase::Extract) {
+        info!("✅ 解压阶段完成，下一阶段: 'nuwax-cli upgrade phase start'");
+        return Ok(());
     }
 
     // 6. 🔄 自动部署服务
-    info!("🔄 正在部署Docker服务...");
-    docker_service::deploy_docker_services(
-        app,
-        frontend_port,
-        config_file.clone(),
-        project_name.clone(),
-    )
-    .await?;
+    app.events.pipeline_phase_started(PipelinePhase::Deploy);
+    let restart_services = checkpoint.restart_services.clone();
+    if !should_run_phase(&checkpoint, only_phase, DeployPhase::Deploy) {
+        info!("⏭️ 部署阶段已完成，跳过部署");
+    } else if restart_services.is_some() {
+        // 补丁未改动 docker-compose.yml 和 .env，其余服务仍在运行，无需重新部署
+        info!("⏭️ 只重启受补丁影响的服务，跳过部署");
+        save_deploy_checkpoint(&checkpoint_store, &mut checkpoint, DeployPhase::Deploy);
+    } else {
+        // 🔀 部署前执行服务包中的数据目录迁移（仅在升级部署时）
+        if !is_first_deployment {
+            run_data_migrations(app, &checkpoint)?;
+        }
+
+        info!("🔄 正在部署Docker服务...");
+        docker_service::deploy_docker_services(
+            app,
+            frontend_port,
+            config_file.clone(),
+            project_name.clone(),
+        )
+        .await?;
+        save_deploy_checkpoint(&checkpoint_store, &mut checkpoint, DeployPhase::Deploy);
+    }
 
     // 7. ▶️ 启动服务
-    info!("▶️ 正在启动Docker服务...");
-    docker_service::start_docker_services(app, config_file.clone(), project_name.clone()).await?;
+    if !should_run_phase(&checkpoint, only_phase, DeployPhase::Start) {
+        info!("⏭️ 启动阶段已完成，跳过启动");
+    } else if let Some(services) = &restart_services {
+        info!("▶️ 正在重建受补丁影响的服务: {}", services.join(", "));
+        DockerService::new(
+            app.config.clone(),
+            deploy_docker_manager(app, &config_file, &project_name)?,
+        )?
+        .recreate_selected_services(services)
+        .await?;
+        save_deploy_checkpoint(&checkpoint_store, &mut checkpoint, DeployPhase::Start);
+    } else {
+        info!("▶️ 正在启动Docker服务...");
+        docker_service::start_docker_services(app, config_file.clone(), project_name.clone())
+            .await?;
+        save_deploy_checkpoint(&checkpoint_store, &mut checkpoint, DeployPhase::Start);
+    }
+    app.events.pipeline_phase_completed(PipelinePhase::Deploy);
+    if only_phase == Some(DeployPhase::Start) {
+        info!("✅ 启动阶段完成，服务启动后可运行 'nuwax-cli upgrade phase sql' 执行数据库升级");
+        return Ok(());
+    }
 
     // 等待服务启动完成（最多等待90秒，因为部署后启动可能需要更长时间）
+    app.events.pipeline_phase_started(PipelinePhase::Verify);
     info!("⏳ 等待Docker服务完全启动...");
     let compose_path = get_compose_file_path(&config_file);
     if docker_utils::wait_for_compose_services_started(&compose_path, timeout::DEPLOY_START_TIMEOUT)
@@ -342,7 +941,7 @@ pub async fn run_auto_upgrade_deploy(
 
         // 🔄 执行数据库升级（仅在升级部署时）
         if !is_first_deployment {
-            execute_sql_diff_upgrade(&config_file).await?;
+            execute_sql_diff_upgrade(app, &config_file, &project_name, sql_dry_run).await?;
         }
 
         info!("🎉 自动升级部署流程成功完成");
@@ -356,28 +955,358 @@ pub async fn run_auto_upgrade_deploy(
 
                 // 🔄 如果服务正常，尝试执行数据库升级
                 if !is_first_deployment {
-                    execute_sql_diff_upgrade(&config_file).await?;
+                    execute_sql_diff_upgrade(app, &config_file, &project_name, sql_dry_run).await?;
                 }
             }
             Ok(false) => {
                 info!("🔍 最终检查：服务可能未正常启动");
                 info!("📊 详细状态检查:");
                 let _ = docker_service::check_docker_services_status(app).await;
+                match deploy_docker_manager(app, &config_file, &project_name) {
+                    Ok(docker_manager) => {
+                        report_startup_failure(
+                            &docker_manager,
+                            "升级部署",
+                            &upgrade::get_startup_failure_report_path(),
+                        )
+                        .await;
+                    }
+                    Err(e) => warn!("⚠️ 收集启动失败诊断信息失败: {}", e),
+                }
             }
             Err(e) => warn!("🔍 最终检查失败: {}", e),
         }
     }
 
+    app.events.pipeline_phase_completed(PipelinePhase::Verify);
+
+    // 整个流程已结束，清除检查点，下次部署重新开始
+    if let Err(e) = checkpoint_store.clear() {
+        warn!("⚠️ 清除部署检查点失败: {}", e);
+    }
+
+    Ok(())
+}
+
+/// 执行服务包中适用于本次升级的数据目录迁移（此时服务已停止、新服务包已解压）
+fn run_data_migrations(app: &CliApp, checkpoint: &DeployCheckpoint) -> Result<()> {
+    let from_version = checkpoint
+        .from_version
+        .clone()
+        .unwrap_or_else(|| app.config.get_docker_versions());
+    let (Ok(from), Ok(to)) = (
+        from_version.parse::<Version>(),
+        checkpoint.target_version.parse::<Version>(),
+    ) else {
+        warn!(
+            "⚠️ 无法解析版本号 {} -> {}，跳过数据目录迁移",
+            from_version, checkpoint.target_version
+        );
+        return Ok(());
+    };
+
+    let report = DataMigrator::new(docker::get_docker_work_dir(), MigrationJournalStore::default())
+        .with_events(app.events.clone())
+        .run(&from, &to)
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "{e:#}，服务未启动。修复后可运行 'nuwax-cli auto-upgrade-deploy run --resume' 从未完成的步骤继续"
+            )
+        })?;
+    if !report.applied.is_empty() {
+        info!("✅ 已执行 {} 个数据目录迁移", report.applied.len());
+    }
+    Ok(())
+}
+
+/// 阶段是否需要执行：单独执行某个阶段时忽略检查点，只执行该阶段（启动阶段同时执行部署）
+fn should_run_phase(
+    checkpoint: &DeployCheckpoint,
+    only_phase: Option<DeployPhase>,
+    phase: DeployPhase,
+) -> bool {
+    match only_phase {
+        Some(only) => phase == only || (only == DeployPhase::Start && phase == DeployPhase::Deploy),
+        None => !checkpoint.is_completed(phase),
+    }
+}
+
+/// 单独执行一个部署阶段（`upgrade phase`），使用与完整流程相同的检查点，不写入升级历史
+pub(crate) async fn run_deploy_phase(
+    app: &mut CliApp,
+    phase: DeployPhase,
+    options: DeployOptions,
+) -> Result<()> {
+    let options = DeployOptions {
+        resume: true,
+        preview: ChangePreview::Skip,
+        only_phase: Some(phase),
+        ..options
+    };
+    let mut attempt = UpgradeAttempt::default();
+    run_auto_upgrade_deploy_phases(app, None, None, None, options, None, &mut attempt).await
+}
+
+/// 单独执行数据库升级，部署检查点存在时要求启动阶段已完成，升级成功后清除检查点（试运行时保留）
+pub(crate) async fn run_sql_upgrade_phase(app: &CliApp, sql_dry_run: bool) -> Result<()> {
+    let checkpoint_store = DeployCheckpointStore::default();
+    let checkpoint = checkpoint_store.load();
+    if let Some(checkpoint) = &checkpoint {
+        if !checkpoint.is_completed(DeployPhase::Start) {
+            return Err(anyhow::anyhow!(
+                "目标版本 {} 的部署尚未完成启动阶段，请先运行 'nuwax-cli upgrade phase start'",
+                checkpoint.target_version
+            ));
+        }
+        if checkpoint.is_first_deployment {
+            info!("🆕 首次部署无需执行数据库升级");
+            return Ok(());
+        }
+    }
+
+    execute_sql_diff_upgrade(app, &None, &None, sql_dry_run).await?;
+
+    if sql_dry_run || checkpoint.is_none() {
+        return Ok(());
+    }
+    if let Err(e) = checkpoint_store.clear() {
+        warn!("⚠️ 清除部署检查点失败: {}", e);
+    }
+    Ok(())
+}
+
+/// 解压或校验失败后恢复升级前的数据：优先使用最新完整备份，没有备份时使用临时数据备份
+async fn restore_after_failed_extract(
+    app: &CliApp,
+    reason: &str,
+    latest_backup_id: Option<i64>,
+    temp_data_backup: &Option<std::path::PathBuf>,
+    patched_files_backup: Option<&Path>,
+) -> Result<()> {
+    if let Some(backup_dir) = patched_files_backup {
+        info!(
+            "🔄 {}，恢复补丁替换前的文件: {}",
+            reason,
+            backup_dir.display()
+        );
+        copy_tree(backup_dir, &docker::get_docker_work_dir(), false)?;
+    } else if let Some(backup_id) = latest_backup_id {
+        info!("🔄 {}，从最新完整备份恢复数据 (备份ID: {})", reason, backup_id);
+        // data 目录也会被恢复
+        backup::run_rollback(app, Some(backup_id), true, false, false, true, false, false).await?;
+    } else {
+        info!("⚠️ {}，使用临时备份恢复", reason);
+        restore_data_after_cleanup(temp_data_backup).await?;
+    }
+    Ok(())
+}
+
+/// 补丁只影响部分服务时返回需要重启的服务，其余情况返回 None（重启全部服务）
+fn partial_restart_services(
+    compose_path: &Path,
+    upgrade_strategy: &UpgradeStrategy,
+) -> Option<Vec<String>> {
+    if !matches!(
+        upgrade_strategy,
+        UpgradeStrategy::PatchUpgrade { .. } | UpgradeStrategy::PatchChainUpgrade { .. }
+    ) {
+        return None;
+    }
+    let parser = match DockerComposeParser::from_file(&compose_path.to_path_buf()) {
+        Ok(parser) => parser,
+        Err(e) => {
+            warn!("⚠️ 解析 docker-compose 文件失败，将重启全部服务: {}", e);
+            return None;
+        }
+    };
+    match analyze_service_impact(&parser, &upgrade_strategy.get_changed_files()) {
+        ServiceImpact::All { reason } => {
+            info!("🔄 补丁需要重启全部服务: {}", reason);
+            None
+        }
+        ServiceImpact::Partial { restart, untouched } => {
+            info!(
+                "🎯 补丁只影响服务 {}，以下服务保持运行: {}",
+                restart.join(", "),
+                untouched.join(", ")
+            );
+            Some(restart)
+        }
+    }
+}
+
+/// 只重启部分服务时无法创建冷备份，把补丁将替换或删除的现有文件复制到备份存储目录
+fn backup_patched_files(
+    app: &CliApp,
+    upgrade_strategy: &UpgradeStrategy,
+    target_version: &str,
+) -> Result<PathBuf> {
+    let docker_dir = docker::get_docker_work_dir();
+    let backup_dir = app
+        .config
+        .get_backup_dir()
+        .join(upgrade::PATCHED_FILES_BACKUP_DIR_NAME)
+        .join(format!(
+            "{}_to_{}_{}",
+            app.config.get_docker_versions(),
+            target_version,
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        ));
+    for relative in upgrade_strategy.get_changed_files() {
+        let source = docker_dir.join(&relative);
+        let target = backup_dir.join(&relative);
+        let copied = if source.is_dir() {
+            // 备份长期保留在备份存储目录，不能与可能被原地修改的文件共享 inode
+            copy_tree(&source, &target, false).map(|_| ())
+        } else if source.is_file() {
+            target
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::copy(&source, &target).map(|_| ()))
+        } else {
+            Ok(())
+        };
+        copied.map_err(|e| {
+            anyhow::anyhow!(
+                "保存补丁将替换的文件失败 {}: {}，已停止升级",
+                relative.display(),
+                e
+            )
+        })?;
+    }
+    info!("💾 已保存补丁将替换的文件: {}", backup_dir.display());
+    Ok(backup_dir)
+}
+
+/// 报告解压时被其他程序占用、未能替换的文件，按需登记为系统重启时替换
+/// 全量升级清空 docker 目录前，把相对当前版本文件清单修改过的服务文件打包保存并列出
+///
+/// 没有当前版本的文件清单时跳过；保存失败时停止升级，避免修改被静默覆盖
+fn backup_user_modifications(app: &CliApp, docker_dir: &Path, target_version: &str) -> Result<()> {
+    let Some(store) = client_core::package_store::registered_package_store() else {
+        return Ok(());
+    };
+    let current_version = app.config.get_docker_versions();
+    let Ok(current) = current_version.parse::<Version>() else {
+        return Ok(());
+    };
+    let report = find_user_modifications(store, docker_dir, &current, |relative| {
+        relative == ".env" || patch_conflicts::is_protected_path(Path::new(relative))
+    })?;
+    let Some(report) = report else {
+        info!(
+            "ℹ️ 没有版本 {} 的文件清单，跳过用户修改检测",
+            current_version
+        );
+        return Ok(());
+    };
+    if report.modified.is_empty() {
+        debug!(
+            "未发现用户修改过的服务文件 (清单版本 {})",
+            report.manifest_version
+        );
+        return Ok(());
+    }
+
+    warn!(
+        "⚠️ 发现 {} 个修改过的服务文件 (对照版本 {} 的文件清单)，全量升级会覆盖这些修改:",
+        report.modified.len(),
+        report.manifest_version
+    );
+    for file in &report.modified {
+        warn!("   [已修改] {}", file.relative);
+    }
+
+    let archive_path = app
+        .config
+        .get_backup_dir()
+        .join(upgrade::USER_MODIFICATIONS_DIR_NAME)
+        .join(format!(
+            "{}_to_{}_{}.tar.gz",
+            current_version,
+            target_version,
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        ));
+    archive_user_modifications(&report, &archive_path)
+        .map_err(|e| anyhow::anyhow!("保存用户修改的服务文件失败，已停止升级: {e}"))?;
+    info!(
+        "💾 已保存修改过的服务文件 ({:.2} MB): {}",
+        report.modified_bytes() as f64 / 1024.0 / 1024.0,
+        archive_path.display()
+    );
+    info!("💡 升级完成后请对照新版本文件重新应用需要保留的修改");
+    app.events.warning(
+        OperationKind::Upgrade,
+        format!(
+            "{} 个修改过的服务文件已保存到 {}",
+            report.modified.len(),
+            archive_path.display()
+        ),
+    );
     Ok(())
 }
 
+fn report_locked_files(app: &CliApp, locked: &LockedFileReport, replace_on_reboot: bool) {
+    if locked.is_empty() {
+        return;
+    }
+    locked.log_summary();
+    if replace_on_reboot {
+        match locked.schedule_on_reboot() {
+            Ok(count) => info!("🔁 已登记 {} 个文件在系统重启时替换", count),
+            Err(e) => warn!("⚠️ {}（需要管理员权限）", e),
+        }
+    } else {
+        info!("💡 关闭占用这些文件的程序后手动用暂存文件替换，");
+        info!("   或使用 --replace-locked-on-reboot 在系统重启时自动替换");
+    }
+    app.events.warning(
+        OperationKind::Extract,
+        format!("{} 个文件被其他程序占用，未能替换", locked.len()),
+    );
+}
+
+/// 等待后台压缩的升级前备份完成，记录备份时间并返回备份ID
+async fn wait_pending_backup(app: &CliApp, pending: backup::PendingBackup) -> Result<i64> {
+    info!("⏳ 等待升级前备份压缩完成...");
+    let backup_time = chrono::Utc::now();
+    let result = pending.wait().await;
+
+    if let Err(e) = auto_backup::update_last_backup_time(app, backup_time, result.is_ok()).await {
+        warn!(error = %e, "记录备份时间失败");
+    }
+
+    let backup_record = result?;
+    info!("✅ 数据备份完成，备份ID: {}", backup_record.id);
+    Ok(backup_record.id)
+}
+
+/// 标记部署阶段完成并写入检查点
+///
+/// 检查点写入失败不影响部署本身，只会导致无法从该阶段续传
+fn save_deploy_checkpoint(
+    store: &DeployCheckpointStore,
+    checkpoint: &mut DeployCheckpoint,
+    phase: DeployPhase,
+) {
+    if checkpoint.is_completed(phase) {
+        return;
+    }
+    checkpoint.mark_completed(phase);
+    match store.save(checkpoint) {
+        Ok(_) => info!("📍 部署阶段完成: {}", phase),
+        Err(e) => warn!("⚠️ 写入部署检查点失败: {}，中断后将无法从该阶段继续", e),
+    }
+}
+
 /// 预约延迟执行自动升级部署
 pub async fn schedule_delayed_deploy(app: &mut CliApp, time: u32, unit: &str) -> Result<()> {
     // 计算延迟时间（转换为秒）
+    let time_secs = u64::from(time);
     let delay_seconds = match unit.to_lowercase().as_str() {
-        "minutes" | "minute" | "min" => time * 60,
-        "hours" | "hour" | "h" => time * 3600,
-        "days" | "day" | "d" => time * 86400,
+        "minutes" | "minute" | "min" => time_secs * 60,
+        "hours" | "hour" | "h" => time_secs * 3600,
+        "days" | "day" | "d" => time_secs * 86400,
         _ => {
             error!("不支持的时间单位: {}", unit);
             return Err(anyhow::anyhow!(format!(
@@ -386,7 +1315,13 @@ pub async fn schedule_delayed_deploy(app: &mut CliApp, time: u32, unit: &str) ->
         }
     };
 
-    let delay_duration = Duration::from_secs(delay_seconds as u64);
+    if delay_seconds > MAX_SCHEDULE_DELAY_DAYS * 86400 {
+        return Err(anyhow::anyhow!(
+            "延迟时间 {time} {unit} 超过 {MAX_SCHEDULE_DELAY_DAYS} 天上限"
+        ));
+    }
+
+    let delay_duration = Duration::from_secs(delay_seconds);
     let scheduled_at = chrono::Utc::now() + chrono::Duration::seconds(delay_seconds as i64);
 
     // 创建升级任务记录
@@ -436,13 +1371,40 @@ pub async fn schedule_delayed_deploy(app: &mut CliApp, time: u32, unit: &str) ->
     info!("⏳ 等待中...");
 
     // 这里可以优化为后台任务，避免阻塞
+    // 按单调时钟等待，系统时间在等待期间被调整不影响执行时间
+    let wait_started = (chrono::Utc::now(), Instant::now());
     sleep(delay_duration).await;
+    let drift =
+        clock::wall_clock_drift(wait_started.0, chrono::Utc::now(), wait_started.1.elapsed());
+    if let Some(message) = clock::drift_warning(drift) {
+        warn!("⚠️ {}", message);
+    }
 
     info!("🔔 延迟时间到，开始执行自动升级部署");
     info!("延迟时间到，开始执行自动升级部署，任务ID: {}", task.task_id);
 
-    // 执行自动升级部署
-    match run_auto_upgrade_deploy(app, None, None, None).await {
+    // 执行自动升级部署，升级服务器限流时按 Retry-After 重新安排
+    let mut reschedules = 0;
+    let result = loop {
+        let result =
+            run_auto_upgrade_deploy(app, None, None, None, DeployOptions::default(), None).await;
+        let Err(e) = &result else {
+            break result;
+        };
+        let Some(retry_at) = reschedule_after_rate_limit(e, &mut reschedules) else {
+            break result;
+        };
+        let config_manager =
+            client_core::config_manager::ConfigManager::new_with_database(app.database.clone());
+        config_manager
+            .update_upgrade_task_status(&task.task_id, "pending", Some(0), Some(&e.to_string()))
+            .await?;
+        sleep_until(retry_at).await;
+        config_manager
+            .update_upgrade_task_status(&task.task_id, "in_progress", Some(0), None)
+            .await?;
+    };
+    match result {
         Ok(_) => {
             let config_manager =
                 client_core::config_manager::ConfigManager::new_with_database(app.database.clone());
@@ -465,6 +1427,35 @@ pub async fn schedule_delayed_deploy(app: &mut CliApp, time: u32, unit: &str) ->
     Ok(())
 }
 
+/// 升级服务器限流时返回重新执行的时间，不是限流错误或已达到重新安排次数上限时返回 None
+fn reschedule_after_rate_limit(
+    error: &anyhow::Error,
+    reschedules: &mut u32,
+) -> Option<chrono::DateTime<chrono::Local>> {
+    let retry_at = client_core::rate_limit::retry_at(error)?;
+    if *reschedules >= upgrade::RATE_LIMIT_MAX_RESCHEDULES {
+        warn!(
+            "⚠️ 升级服务器持续繁忙，已重新安排 {} 次，停止重试",
+            reschedules
+        );
+        return None;
+    }
+    *reschedules += 1;
+    warn!(
+        "⏰ 服务器繁忙，已安排在 {} 重试 ({}/{})",
+        retry_at.format("%H:%M"),
+        reschedules,
+        upgrade::RATE_LIMIT_MAX_RESCHEDULES
+    );
+    Some(retry_at)
+}
+
+/// 等待到指定的本地时间
+async fn sleep_until(at: chrono::DateTime<chrono::Local>) {
+    let delay = (at - chrono::Local::now()).to_std().unwrap_or_default();
+    sleep(delay).await;
+}
+
 /// 显示自动升级部署状态
 pub async fn show_status(app: &mut CliApp) -> Result<()> {
     let config_manager =
@@ -474,6 +1465,26 @@ pub async fn show_status(app: &mut CliApp) -> Result<()> {
     info!("   功能状态: 已实现");
     info!("   流程说明: 下载最新版本 -> 智能备份 -> 部署服务 -> 启动服务");
 
+    // 显示未完成的部署检查点
+    if let Some(checkpoint) = DeployCheckpointStore::default().load() {
+        info!("📍 未完成的部署:");
+        info!("   目标版本: {}", checkpoint.target_version);
+        info!(
+            "   已完成阶段: {}",
+            checkpoint
+                .completed_phases
+                .iter()
+                .map(|phase| phase.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        );
+        info!(
+            "   更新时间: {}",
+            checkpoint.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        info!("   使用 'auto-upgrade-deploy run --resume' 继续，或 '--restart' 重新开始");
+    }
+
     // 显示待执行的升级任务
     match config_manager.get_pending_upgrade_tasks().await {
         Ok(tasks) => {
@@ -490,6 +1501,14 @@ pub async fn show_status(app: &mut CliApp) -> Result<()> {
                         "     计划执行时间: {}",
                         task.schedule_time.format("%Y-%m-%d %H:%M:%S UTC")
                     );
+                    if task.schedule_time - chrono::Utc::now()
+                        > chrono::Duration::days(MAX_SCHEDULE_DELAY_DAYS as i64)
+                    {
+                        warn!(
+                            "     ⚠️ 计划执行时间超过 {} 天，系统时间可能被调整过",
+                            MAX_SCHEDULE_DELAY_DAYS
+                        );
+                    }
                     if let Some(target_version) = &task.target_version {
                         info!("     目标版本: {}", target_version);
                     }
@@ -514,7 +1533,7 @@ pub async fn show_status(app: &mut CliApp) -> Result<()> {
 
     // 显示最近的备份
     info!("📝 最近的备份:");
-    backup::run_list_backups(app).await?;
+    backup::run_list_backups(app, BackupListArgs::default()).await?;
 
     Ok(())
 }
@@ -533,38 +1552,156 @@ async fn check_docker_service_status(
         return Ok(false);
     }
 
-    // 🔧 修复：根据config_file参数创建使用正确路径的DockerManager
+    let docker_manager = deploy_docker_manager(app, config_file, project_name)?;
+    let health_checker = HealthChecker::new(docker_manager);
+    let report = health_checker.health_check().await?;
+    Ok(report.is_all_healthy())
+}
+
+/// 根据config_file和project_name参数创建使用正确路径的DockerManager
+fn deploy_docker_manager(
+    app: &CliApp,
+    config_file: &Option<PathBuf>,
+    project_name: &Option<String>,
+) -> Result<Arc<DockerManager>> {
     if let Some(config_file_path) = config_file {
-        let custom_docker_manager = Arc::new(DockerManager::with_project(
+        Ok(Arc::new(DockerManager::with_project(
             config_file_path.clone(),
             client_core::constants::docker::get_env_file_path(),
             project_name.clone(),
-        )?);
-        let health_checker = HealthChecker::new(custom_docker_manager);
-        let report = health_checker.health_check().await?;
-        Ok(report.is_all_healthy())
-    } else {
+        )?))
+    } else if let Some(project_name) = project_name {
         // 如果没有指定config文件，但有project name，创建带project name的DockerManager
-        if let Some(project_name) = project_name {
-            let custom_docker_manager = Arc::new(DockerManager::with_project(
-                client_core::constants::docker::get_compose_file_path(),
-                client_core::constants::docker::get_env_file_path(),
-                Some(project_name.clone()),
-            )?);
-            let health_checker = HealthChecker::new(custom_docker_manager);
-            let report = health_checker.health_check().await?;
-            Ok(report.is_all_healthy())
+        Ok(Arc::new(DockerManager::with_project(
+            client_core::constants::docker::get_compose_file_path(),
+            client_core::constants::docker::get_env_file_path(),
+            Some(project_name.clone()),
+        )?))
+    } else {
+        Ok(app.docker_manager.clone())
+    }
+}
+
+/// 展示升级将带来的文件变更并等待用户确认
+///
+/// 返回 false 表示用户取消了升级
+fn confirm_upgrade_changes(app: &CliApp, upgrade_strategy: &UpgradeStrategy) -> Result<bool> {
+    let work_dir = docker::get_docker_work_dir();
+    let report = match upgrade_strategy {
+        UpgradeStrategy::PatchUpgrade { patch_info, .. } => {
+            UpgradeChangeReport::from_patch_operations(&patch_info.operations, &work_dir)
+        }
+        UpgradeStrategy::PatchChainUpgrade { steps, .. } => {
+            let mut report = UpgradeChangeReport::default();
+            for step in steps {
+                info!("🔗 补丁 {} -> {}", step.from_version, step.to_version);
+                report.changes.extend(
+                    UpgradeChangeReport::from_patch_operations(
+                        &step.patch_info.operations,
+                        &work_dir,
+                    )
+                    .changes,
+                );
+            }
+            report
+        }
+        UpgradeStrategy::FullUpgrade { .. } => {
+            let Some(package_path) =
+                docker_service::get_upgrade_package_path(app, upgrade_strategy)
+            else {
+                return Ok(true);
+            };
+            info!("🔍 正在分析服务包内容: {}", package_path.display());
+            let entries = crate::utils::list_package_entries(&package_path)?;
+            env::preview_env_template_changes(app, &package_path);
+            UpgradeChangeReport::from_archive_entries(entries, &work_dir)
+        }
+        UpgradeStrategy::NoUpgrade { .. } => {
+            info!("ℹ️ 当前已是最新版本，没有需要应用的变更");
+            return Ok(true);
+        }
+    };
+
+    info!("📋 升级变更预览（工作目录: {}）", work_dir.display());
+    if report.is_empty() {
+        info!("   没有文件变更");
+    }
+    for change in &report.changes {
+        if change.protected {
+            warn!("   [{}] {}  ⚠️ 受保护路径", change.kind, change.path);
         } else {
-            let health_checker = HealthChecker::new(app.docker_manager.clone());
-            let report = health_checker.health_check().await?;
-            Ok(report.is_all_healthy())
+            info!("   [{}] {}", change.kind, change.path);
+        }
+    }
+    info!(
+        "📊 共 {} 项变更: 替换 {}, 新增 {}, 删除 {}",
+        report.changes.len(),
+        report.count(ChangeKind::Replace),
+        report.count(ChangeKind::Add),
+        report.count(ChangeKind::Delete)
+    );
+    let protected_count = report.protected_changes().count();
+    if protected_count > 0 {
+        warn!(
+            "⚠️ 有 {} 项变更涉及受保护路径（用户数据目录、.env 等），请确认已有可用备份",
+            protected_count
+        );
+    }
+
+    prompt::confirm("确认应用以上变更?")
+}
+
+/// 在临时 MySQL 容器中预演差异SQL，失败时返回错误且不修改正式数据库
+///
+/// 优先使用正式数据库当前的表结构，导出失败时退回旧版本初始化脚本
+async fn dry_run_sql_upgrade(
+    executor: &MySqlExecutor,
+    compose_file: &str,
+    env_file: &str,
+    old_sql_path: &Path,
+    diff_sql: &str,
+) -> Result<()> {
+    info!("🧪 开始数据库升级预演...");
+    let schema_sql = match executor.dump_schema().await {
+        Ok(schema) => {
+            info!("📋 已导出正式数据库当前表结构");
+            schema
+        }
+        Err(e) => {
+            warn!("⚠️ 导出当前表结构失败: {}，改用旧版本初始化脚本", e);
+            fs::read_to_string(old_sql_path).map_err(|read_err| {
+                anyhow::anyhow!(
+                    "无法获取预演所需的数据库结构: {} ({})",
+                    read_err,
+                    old_sql_path.display()
+                )
+            })?
+        }
+    };
+
+    let dry_run = SqlDryRun::from_compose(compose_file, env_file).await?;
+    match dry_run.run(&schema_sql, diff_sql).await {
+        Ok(results) => {
+            for result in results {
+                debug!("  [预演] {}", result);
+            }
+            info!("✅ 数据库升级预演通过，继续升级正式数据库");
+            Ok(())
+        }
+        Err(e) => {
+            error!("❌ 数据库升级预演失败，未修改正式数据库: {}", e);
+            info!(
+                "💡 请检查差异SQL文件: {}",
+                paths::temp_sql_dir().join("upgrade_diff.sql").display()
+            );
+            Err(e)
         }
     }
 }
 
 /// 检查docker目录是否存在且有文件需要备份
 async fn check_docker_files_exist() -> Result<bool> {
-    let docker_dir = Path::new("./docker");
+    let docker_dir = docker::get_docker_work_dir();
 
     if !docker_dir.exists() {
         info!("docker目录不存在，无需备份");
@@ -607,11 +1744,43 @@ fn format_duration(duration: Duration) -> String {
     }
 }
 
+/// 初始化 .env 中的密钥，失败时只记录警告，不中断部署
+fn bootstrap_env_secrets(mode: SecretBootstrapMode) {
+    let env_path = docker::get_env_file_path();
+    if !env_path.exists() {
+        return;
+    }
+    let record_path = docker::get_data_dir_path().join(GENERATED_SECRETS_FILE_NAME);
+    match bootstrap_secrets(&env_path, &record_path, mode) {
+        Ok(report) => {
+            if mode == SecretBootstrapMode::Regenerate && !report.generated.is_empty() {
+                warn!("⚠️ 已重新生成密钥，已有数据的服务（如 MySQL）可能需要同步修改密码");
+            }
+            for key in &report.generated {
+                info!("🔐 已生成密钥: {}", key);
+            }
+            for key in &report.restored {
+                info!("🔐 已恢复先前生成的密钥: {}", key);
+            }
+            for key in &report.skipped {
+                warn!(
+                    "⚠️ 密钥 {} 仍为模板默认值，可使用 --regenerate-secrets 重新生成",
+                    key
+                );
+            }
+            if !report.generated.is_empty() {
+                info!("📝 生成的密钥已记录到: {}", record_path.display());
+            }
+        }
+        Err(e) => warn!("⚠️ 初始化 .env 密钥失败: {}", e),
+    }
+}
+
 /// 检测是否为第一次部署
 async fn is_first_deployment() -> bool {
-    let docker_dir = std::path::Path::new("docker");
-    let docker_compose_file = docker_dir.join("docker-compose.yml");
-    let docker_data_dir = docker_dir.join("data");
+    let docker_dir = docker::get_docker_work_dir();
+    let docker_compose_file = docker::get_compose_file_path();
+    let docker_data_dir = docker::get_data_dir_path();
 
     // 如果docker目录不存在，肯定是第一次部署
     if !docker_dir.exists() {
@@ -635,7 +1804,7 @@ async fn is_first_deployment() -> bool {
 
 /// 在清理docker目录前备份数据目录
 async fn backup_data_before_cleanup() -> Result<Option<std::path::PathBuf>> {
-    let docker_data_dir = Path::new("docker/data");
+    let docker_data_dir = docker::get_data_dir_path();
 
     if !docker_data_dir.exists() {
         info!("📁 无现有数据目录需要备份");
@@ -643,34 +1812,60 @@ async fn backup_data_before_cleanup() -> Result<Option<std::path::PathBuf>> {
     }
 
     // 创建临时备份目录
-    let temp_dir = std::env::temp_dir();
-    let backup_name = format!("duck_data_backup_{}", chrono::Utc::now().timestamp());
-    let temp_backup_path = temp_dir.join(backup_name);
+    let backup_name = format!(
+        "{}{}",
+        upgrade::TEMP_DATA_BACKUP_PREFIX,
+        chrono::Utc::now().timestamp()
+    );
+    let temp_backup_path = temp_data_backup_parent(&docker_data_dir).join(backup_name);
 
     info!(
         "🛡️ 正在备份数据目录到临时位置: {}",
         temp_backup_path.display()
     );
 
-    // 递归复制数据目录到临时位置
-    match copy_dir_recursively(docker_data_dir, &temp_backup_path) {
-        Ok(_) => {
-            info!("✅ 数据目录备份完成");
+    // 清理 docker 目录时原文件被删除而不是原地修改，可以使用硬链接
+    match copy_tree(&docker_data_dir, &temp_backup_path, true) {
+        Ok(stats) => {
+            info!("✅ 数据目录备份完成: {}", stats.summary());
             Ok(Some(temp_backup_path))
         }
         Err(e) => {
             warn!("⚠️ 数据目录备份失败: {}", e);
+            if temp_backup_path.exists() {
+                if let Err(e) = fs::remove_dir_all(&temp_backup_path) {
+                    warn!("⚠️ 清理未完成的临时备份失败: {}", e);
+                }
+            }
             // 备份失败时，返回None表示没有备份
             Ok(None)
         }
     }
 }
 
+/// 临时数据备份的存放目录：与数据目录位于同一文件系统且在 docker 目录之外，
+/// 以便使用 reflink 或硬链接；该目录不可写时使用系统临时目录
+fn temp_data_backup_parent(data_dir: &Path) -> PathBuf {
+    let work_dir = docker::get_docker_work_dir();
+    let work_dir = fs::canonicalize(&work_dir).unwrap_or(work_dir);
+    // 数据目录迁移到其他磁盘后是指向目标目录的符号链接
+    let data_dir = fs::canonicalize(data_dir).unwrap_or_else(|_| data_dir.to_path_buf());
+    let parent = if data_dir.starts_with(&work_dir) {
+        work_dir.parent()
+    } else {
+        data_dir.parent()
+    };
+    match parent {
+        Some(parent) if check_writable(parent, None).is_ok() => parent.to_path_buf(),
+        _ => std::env::temp_dir(),
+    }
+}
+
 /// 解压完成后恢复备份的数据目录
 async fn restore_data_after_cleanup(temp_backup_path: &Option<std::path::PathBuf>) -> Result<()> {
     if let Some(backup_path) = temp_backup_path {
         if backup_path.exists() {
-            let docker_data_dir = Path::new("docker/data");
+            let docker_data_dir = docker::get_data_dir_path();
 
             info!("🔄 正在恢复数据目录从: {}", backup_path.display());
 
@@ -681,13 +1876,13 @@ async fn restore_data_after_cleanup(temp_backup_path: &Option<std::path::PathBuf
 
             // 如果新解压的包中有data目录，先删除它
             if docker_data_dir.exists() {
-                fs::remove_dir_all(docker_data_dir)?;
+                fs::remove_dir_all(&docker_data_dir)?;
             }
 
-            // 从临时备份恢复数据目录
-            match copy_dir_recursively(backup_path, docker_data_dir) {
-                Ok(_) => {
-                    info!("✅ 数据目录恢复完成");
+            // 从临时备份恢复数据目录，临时备份随后删除，可以使用硬链接
+            match copy_tree(backup_path, &docker_data_dir, true) {
+                Ok(stats) => {
+                    info!("✅ 数据目录恢复完成: {}", stats.summary());
 
                     // 设置正确的权限（特别是MySQL目录需要775权限）
                     let mysql_data_dir = docker_data_dir.join("mysql");
@@ -721,38 +1916,39 @@ async fn restore_data_after_cleanup(temp_backup_path: &Option<std::path::PathBuf
     Ok(())
 }
 
-/// 递归复制目录
-fn copy_dir_recursively(src: &Path, dst: &Path) -> std::io::Result<()> {
-    if !src.exists() {
-        return Ok(());
-    }
-
-    fs::create_dir_all(dst)?;
-
-    for entry in fs::read_dir(src)? {
-        let entry = entry?;
-        let src_path = entry.path();
-        let dst_path = dst.join(entry.file_name());
-
-        if src_path.is_dir() {
-            copy_dir_recursively(&src_path, &dst_path)?;
-        } else {
-            fs::copy(&src_path, &dst_path)?;
+/// 数据库方言和服务名：优先使用配置 `docker.sql_dialect`，否则根据 compose 文件中的数据库服务判断
+pub(crate) fn resolve_database(
+    app: &CliApp,
+    config_file: &Option<PathBuf>,
+) -> (SqlDialect, String) {
+    let configured = app.config.docker.sql_dialect;
+    let detected = DockerManager::new(
+        get_compose_file_path(config_file),
+        docker::get_env_file_path(),
+    )
+    .and_then(|docker_manager| detect_database(&docker_manager, configured));
+    match detected {
+        Ok((dialect, service)) => {
+            debug!("数据库: {} (服务 {})", dialect, service);
+            (dialect, service)
+        }
+        Err(e) => {
+            let dialect = configured.unwrap_or_default();
+            warn!("⚠️ 无法确定数据库服务，按 {} 处理: {}", dialect, e);
+            (dialect, MYSQL_SERVICE.to_string())
         }
     }
-
-    Ok(())
 }
 
 /// 备份当前版本的SQL文件（用于后续差异比较）
-async fn backup_sql_file_before_upgrade() -> Result<()> {
-    let current_sql_path = Path::new("docker/config/init_mysql.sql");
-    let temp_sql_dir = Path::new("temp_sql");
+async fn backup_sql_file_before_upgrade(dialect: SqlDialect) -> Result<()> {
+    let current_sql_path = docker::get_config_dir_path().join(dialect.init_script_name());
+    let temp_sql_dir = paths::temp_sql_dir();
     let old_sql_path = temp_sql_dir.join("init_mysql_old.sql");
 
     // 创建临时SQL目录
     if !temp_sql_dir.exists() {
-        fs::create_dir_all(temp_sql_dir)?;
+        fs::create_dir_all(&temp_sql_dir)?;
         info!("📁 创建临时SQL目录: {}", temp_sql_dir.display());
     }
 
@@ -772,7 +1968,7 @@ async fn backup_sql_file_before_upgrade() -> Result<()> {
     // 复制当前SQL文件到临时目录
     // 注意：此函数只在非首次部署时调用，所以SQL文件应该存在
     if current_sql_path.exists() {
-        fs::copy(current_sql_path, &old_sql_path)?;
+        fs::copy(&current_sql_path, &old_sql_path)?;
         info!("📄 已备份当前版本SQL文件: {}", old_sql_path.display());
     } else {
         // 如果文件不存在，说明可能是特殊情况，记录警告但不中断流程
@@ -786,16 +1982,20 @@ async fn backup_sql_file_before_upgrade() -> Result<()> {
 }
 
 /// 生成并保存SQL差异文件
-async fn generate_and_save_sql_diff(from_version: &str, to_version: &str) -> Result<()> {
-    let temp_sql_dir = Path::new("temp_sql");
+async fn generate_and_save_sql_diff(
+    from_version: &str,
+    to_version: &str,
+    dialect: SqlDialect,
+) -> Result<()> {
+    let temp_sql_dir = paths::temp_sql_dir();
     let old_sql_path = temp_sql_dir.join("init_mysql_old.sql");
     let new_sql_path = temp_sql_dir.join("init_mysql_new.sql");
     let diff_sql_path = temp_sql_dir.join("upgrade_diff.sql");
 
     // 复制新版本的SQL文件
-    let current_sql_path = Path::new("docker/config/init_mysql.sql");
+    let current_sql_path = docker::get_config_dir_path().join(dialect.init_script_name());
     if current_sql_path.exists() {
-        fs::copy(current_sql_path, &new_sql_path)?;
+        fs::copy(&current_sql_path, &new_sql_path)?;
         info!("📄 已复制新版本SQL文件: {}", new_sql_path.display());
     } else {
         info!("📄 新版本没有SQL文件，跳过差异生成");
@@ -815,8 +2015,9 @@ async fn generate_and_save_sql_diff(from_version: &str, to_version: &str) -> Res
     let new_sql_content = fs::read_to_string(&new_sql_path)?;
 
     // 生成SQL差异
-    info!("🔄 正在生成SQL差异...");
-    let (diff_sql, description) = generate_schema_diff(
+    info!("🔄 正在生成{}差异SQL...", dialect);
+    let (diff_sql, description) = generate_schema_diff_with_dialect(
+        dialect,
         old_sql_content.as_deref(),
         &new_sql_content,
         Some(from_version),
@@ -825,6 +2026,16 @@ async fn generate_and_save_sql_diff(from_version: &str, to_version: &str) -> Res
     .map_err(|e| client_core::error::DuckError::custom(format!("生成SQL差异失败: {e}")))?;
 
     info!("📊 SQL差异分析结果: {}", description);
+    if upgrade_session::is_recording() {
+        upgrade_session::record_sql_diff(upgrade_session::RecordedSqlDiff {
+            dialect,
+            from_version: from_version.to_string(),
+            to_version: to_version.to_string(),
+            old_schema: old_sql_content.clone(),
+            new_schema: new_sql_content.clone(),
+            diff_sql: diff_sql.clone(),
+        });
+    }
 
     // 检查是否有实际的SQL语句需要执行
     let meaningful_lines: Vec<&str> = diff_sql
@@ -889,7 +2100,7 @@ async fn safe_remove_file_or_dir(paths: &[&Path]) -> Result<()> {
 }
 
 /// 安全地删除目录，处理"Directory not empty"错误（保留upload目录）
-async fn safe_remove_docker_directory(path: &Path) -> Result<()> {
+pub(crate) async fn safe_remove_docker_directory(path: &Path) -> Result<()> {
     if !path.exists() {
         return Ok(());
     }
@@ -986,8 +2197,14 @@ async fn force_cleanup_directory(path: &Path) -> Result<()> {
 }
 
 /// 连接MySQL容器并执行差异SQL
-async fn execute_sql_diff_upgrade(config_file: &Option<PathBuf>) -> Result<()> {
-    let temp_sql_dir = Path::new("temp_sql");
+#[tracing::instrument(level = "trace", name = "phase.sql_upgrade", skip_all)]
+async fn execute_sql_diff_upgrade(
+    app: &CliApp,
+    config_file: &Option<PathBuf>,
+    project_name: &Option<String>,
+    sql_dry_run: bool,
+) -> Result<()> {
+    let temp_sql_dir = paths::temp_sql_dir();
     let diff_sql_path = temp_sql_dir.join("upgrade_diff.sql");
 
     // 检查差异SQL文件是否存在
@@ -998,6 +2215,7 @@ async fn execute_sql_diff_upgrade(config_file: &Option<PathBuf>) -> Result<()> {
 
     // 🔄 重新生成差异SQL以确保准确性
     info!("🔄 检测到差异SQL文件，重新生成以确保准确性...");
+    let (sql_dialect, database_service) = resolve_database(app, config_file);
     
     let old_sql_path = temp_sql_dir.join("init_mysql_old.sql");
     let new_sql_path = temp_sql_dir.join("init_mysql_new.sql");
@@ -1009,7 +2227,8 @@ async fn execute_sql_diff_upgrade(config_file: &Option<PathBuf>) -> Result<()> {
         
         // 重新生成差异SQL
         info!("📊 正在基于源文件重新生成SQL差异...");
-        let (regenerated_diff_sql, description) = generate_schema_diff(
+        let (regenerated_diff_sql, description) = generate_schema_diff_with_dialect(
+            sql_dialect,
             if old_sql_content.trim().is_empty() { None } else { Some(&old_sql_content) },
             &new_sql_content,
             Some("旧版本"),
@@ -1061,6 +2280,23 @@ async fn execute_sql_diff_upgrade(config_file: &Option<PathBuf>) -> Result<()> {
     info!("🔄 开始执行数据库升级...");
     info!("📋 即将执行 {} 行SQL语句", meaningful_lines.len());
 
+    if sql_dialect == SqlDialect::Postgres {
+        if sql_dry_run {
+            warn!(
+                "⚠️ PostgreSQL 暂不支持 --sql-dry-run 预演，差异SQL将在单个事务中执行，失败时自动回滚"
+            );
+        }
+        return execute_postgres_diff_upgrade(
+            app,
+            config_file,
+            project_name,
+            &database_service,
+            &diff_sql,
+            &diff_sql_path,
+        )
+        .await;
+    }
+
     //从App配置中动态获取MySQL端口
     let compose_file = get_compose_file_path(&config_file);
     let env_file = client_core::constants::docker::get_env_file_path();
@@ -1075,10 +2311,38 @@ async fn execute_sql_diff_upgrade(config_file: &Option<PathBuf>) -> Result<()> {
     let executor = MySqlExecutor::new(config);
 
     info!("🔌 正在连接到MySQL数据库...");
-    if let Err(e) = executor.test_connection().await {
-        error!("❌ 数据库连接失败: {}", e);
-        error!("🏃 请确保MySQL容器正在运行并且端口 13306 可访问");
-        return Err(e.into());
+    let docker_manager = deploy_docker_manager(app, config_file, project_name)
+        .map_err(|e| warn!("⚠️ 无法检查MySQL容器健康状态，仅检查数据库连接: {}", e))
+        .ok();
+    let ready_timeout = Duration::from_secs(app.config.docker.mysql_ready_timeout_secs);
+    match wait_for_mysql_ready(
+        &executor,
+        docker_manager.as_deref(),
+        ready_timeout,
+        |progress| log_readiness_progress(SqlDialect::Mysql, progress),
+    )
+    .await
+    {
+        Ok(waited) => info!("✅ MySQL已就绪（等待 {} 秒）", waited.as_secs()),
+        Err(e) => {
+            error!("❌ 数据库连接失败: {}", e);
+            error!(
+                "🏃 请确保MySQL容器正在运行并且端口 {} 可访问，或调大配置 docker.mysql_ready_timeout_secs",
+                executor.config().port
+            );
+            return Err(e);
+        }
+    }
+
+    if sql_dry_run {
+        dry_run_sql_upgrade(
+            &executor,
+            compose_file_str,
+            env_file_str,
+            &old_sql_path,
+            &diff_sql,
+        )
+        .await?;
     }
 
     info!("🚀 开始执行差异SQL...");
@@ -1087,18 +2351,7 @@ async fn execute_sql_diff_upgrade(config_file: &Option<PathBuf>) -> Result<()> {
             for result in results {
                 info!("  {}", result);
             }
-            // Rename diff SQL file after successful upgrade to preserve history
-            if diff_sql_path.is_file() {
-                let parent = diff_sql_path.parent().unwrap_or(Path::new("."));
-                let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-                let new_name = format!("diff_sql_executed_{timestamp}.sql");
-                let new_path = parent.join(new_name);
-
-                match fs::rename(&diff_sql_path, &new_path) {
-                    Ok(_) => info!("✅ Renamed diff SQL file to: {}", new_path.display()),
-                    Err(e) => warn!("⚠️ Failed to rename diff SQL file: {}", e),
-                }
-            }
+            archive_executed_diff_sql(&diff_sql_path);
 
             info!("✅ 数据库升级成功");
         }
@@ -1111,93 +2364,95 @@ async fn execute_sql_diff_upgrade(config_file: &Option<PathBuf>) -> Result<()> {
     Ok(())
 }
 
-/// 自动修复关键脚本文件权限
-async fn fix_script_permissions() -> Result<()> {
-    info!("🔧 正在修复关键脚本文件权限...");
-
-    // 需要修复权限的脚本文件列表
-    let script_files = ["docker/config/docker-entrypoint.sh"];
-
-    let mut fixed_count = 0;
-    let mut total_count = 0;
-
-    for script_path in script_files.iter() {
-        let path = std::path::Path::new(script_path);
-
-        if path.exists() {
-            total_count += 1;
-
-            // 检查当前权限
-            match std::fs::metadata(path) {
-                Ok(metadata) => {
-                    #[cfg(unix)]
-                    {
-                        use std::os::unix::fs::PermissionsExt;
-                        let current_mode = metadata.permissions().mode() & 0o777;
-
-                        // 如果没有执行权限，添加执行权限
-                        if current_mode & 0o111 == 0 {
-                            info!(
-                                "🔒 修复权限: {} (当前: {:o} -> 目标: 755)",
-                                path.display(),
-                                current_mode
-                            );
-
-                            let new_permissions = std::fs::Permissions::from_mode(0o755);
-                            if let Err(e) = std::fs::set_permissions(path, new_permissions) {
-                                warn!("⚠️ 修复权限失败 {}: {}", path.display(), e);
-                            } else {
-                                fixed_count += 1;
-                                info!("✅ 权限修复成功: {}", path.display());
-                            }
-                        } else {
-                            info!("✓ 权限正常: {} ({:o})", path.display(), current_mode);
-                        }
-                    }
+/// 等待PostgreSQL就绪后，在数据库容器内以单个事务执行差异SQL
+async fn execute_postgres_diff_upgrade(
+    app: &CliApp,
+    config_file: &Option<PathBuf>,
+    project_name: &Option<String>,
+    service: &str,
+    diff_sql: &str,
+    diff_sql_path: &Path,
+) -> Result<()> {
+    let docker_manager = deploy_docker_manager(app, config_file, project_name)?;
+    let config = PostgresConfig::for_container(&docker_manager, service)?;
+    let executor = PostgresExecutor::new(docker_manager.as_ref().clone(), config);
+
+    info!("🔌 正在连接到PostgreSQL数据库 (服务 {})...", service);
+    let ready_timeout = Duration::from_secs(app.config.docker.mysql_ready_timeout_secs);
+    match executor
+        .wait_until_ready(ready_timeout, |progress| {
+            log_readiness_progress(SqlDialect::Postgres, progress)
+        })
+        .await
+    {
+        Ok(waited) => info!("✅ PostgreSQL已就绪（等待 {} 秒）", waited.as_secs()),
+        Err(e) => {
+            error!("❌ 数据库连接失败: {}", e);
+            error!(
+                "🏃 请确保 {} 容器正在运行，或调大配置 docker.mysql_ready_timeout_secs",
+                service
+            );
+            return Err(e);
+        }
+    }
 
-                    #[cfg(not(unix))]
-                    {
-                        info!("ℹ️ 非Unix系统，跳过权限修复: {}", path.display());
-                    }
-                }
-                Err(e) => {
-                    warn!("⚠️ 无法读取文件元数据 {}: {}", path.display(), e);
-                }
+    info!("🚀 开始执行差异SQL...");
+    match executor.execute_diff_sql(diff_sql).await {
+        Ok(results) => {
+            for result in results {
+                info!("  {}", result);
             }
-        } else {
-            info!("📄 脚本文件不存在，跳过: {}", script_path);
+            archive_executed_diff_sql(diff_sql_path);
+            info!("✅ 数据库升级成功");
+            Ok(())
+        }
+        Err(e) => {
+            error!("❌ 数据库升级失败: {}", e);
+            Err(e)
         }
     }
+}
 
-    if total_count > 0 {
-        info!(
-            "🔧 权限修复完成: {}/{} 个脚本文件已修复",
-            fixed_count, total_count
-        );
-    } else {
-        info!("📄 未找到需要修复权限的脚本文件");
-    }
+/// 输出数据库就绪等待进度
+pub(crate) fn log_readiness_progress(dialect: SqlDialect, progress: &ReadinessProgress) {
+    info!(
+        "⏳ 等待{}就绪（第 {} 次检查，已等待 {} 秒）: {}，{} 秒后重试",
+        dialect,
+        progress.attempt,
+        progress.elapsed.as_secs(),
+        progress.reason,
+        progress.next_delay.as_secs()
+    );
+}
 
-    Ok(())
+/// 升级成功后重命名差异SQL文件，保留执行历史
+fn archive_executed_diff_sql(diff_sql_path: &Path) {
+    if diff_sql_path.is_file() {
+        let parent = diff_sql_path.parent().unwrap_or(Path::new("."));
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let new_name = format!("diff_sql_executed_{timestamp}.sql");
+        let new_path = parent.join(new_name);
+
+        match fs::rename(diff_sql_path, &new_path) {
+            Ok(_) => info!("✅ Renamed diff SQL file to: {}", new_path.display()),
+            Err(e) => warn!("⚠️ Failed to rename diff SQL file: {}", e),
+        }
+    }
 }
 
 /// 获取最新备份的ID
 async fn get_latest_backup_id(app: &CliApp) -> Result<Option<i64>> {
-    let backup_manager = client_core::backup::BackupManager::new(
-        app.config.get_backup_dir(),
-        app.database.clone(),
-        app.docker_manager.clone(),
-    )?;
-
-    match backup_manager.list_backups().await {
+    match app.backup_manager.list_backups().await {
         Ok(backups) => {
             if backups.is_empty() {
                 info!("📁 未找到备份记录");
                 Ok(None)
             } else {
-                // 获取最新的备份（按创建时间排序，取最新的）
+                // 获取当前项目命名空间中最新的备份（按创建时间排序，取最新的）
+                let namespace = backup::backup_namespace(app);
                 let latest_backup = backups
                     .iter()
+                    .filter(|backup| backup.belongs_to_namespace(&namespace))
                     .max_by(|a, b| a.created_at.cmp(&b.created_at));
 
                 match latest_backup {
@@ -1208,12 +2463,15 @@ async fn get_latest_backup_id(app: &CliApp) -> Result<Option<i64>> {
                             backup.created_at.format("%Y-%m-%d %H:%M:%S")
                         );
 
-                        //检查备份文件是否存在,
-                        let backup_file = Path::new(&backup.file_path);
-                        if !backup_file.exists() {
+                        //检查备份文件是否存在（本地或备份存储中）,
+                        let available = app
+                            .backup_manager
+                            .available_backup_ids(std::slice::from_ref(backup))
+                            .await?;
+                        if available.is_empty() {
                             warn!(
                                 "❌ 数据库中记录的备份文件,再磁盘上不存在: {}",
-                                backup_file.display()
+                                backup.file_path
                             );
                             Ok(None)
                         } else {
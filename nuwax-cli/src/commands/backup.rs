@@ -1,17 +1,26 @@
 use crate::app::CliApp;
-use crate::docker_service::health_check::ContainerInfo;
-use crate::docker_service::{DockerService, HealthReport};
+use crate::cli::{BackupCommand, BackupListArgs};
+use crate::docker_service::ServiceGate;
+use crate::docker_service::permission_policy::apply_permission_policy;
+use crate::project_info::{metadata, version_info};
+use crate::utils::prompt;
 use anyhow::Result;
 use anyhow::anyhow;
-use client_core::backup::{BackupManager, BackupOptions};
-use client_core::config::AppConfig;
-use client_core::constants::docker;
-use client_core::container::DockerManager;
-use client_core::database::BackupType;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use client_core::backup::{BackupManager, BackupOptions, BackupSizeGuard, BackupVerification};
+use client_core::backup_catalog::{
+    BackupCatalog, CatalogFormat, CatalogImportReport, normalize_backup_namespace,
+};
+use client_core::backup_permissions::OwnershipMapping;
+use client_core::config::{BackupRetentionConfig, BackupStagingMode};
+use client_core::constants::{config, docker};
+use client_core::database::{BackupQuery, BackupRecord, BackupStatus, BackupType};
+use client_core::restore_preview::RestoreFileChange;
 use client_core::upgrade_strategy::UpgradeStrategy;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
 
 /// JSON 格式的备份信息（用于 GUI 集成）
@@ -24,6 +33,11 @@ pub struct JsonBackupInfo {
     pub file_path: String,
     pub file_size: Option<u64>,
     pub file_exists: bool,
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// 创建后的校验结果，未校验时为空
+    #[serde(default)]
+    pub verified: Option<bool>,
 }
 
 /// JSON 格式的备份列表响应
@@ -44,131 +58,20 @@ pub(crate) fn validate_docker_compose_file(compose_path: &Path) -> Result<()> {
     Ok(())
 }
 
-/// 展示运行状态的容器信息
-fn display_running_containers(containers: &Vec<&&ContainerInfo>) {
-    for container in containers {
-        info!(
-            "   - {} (状态: {}, restart: {})",
-            container.name,
-            container.status.display_name(),
-            container.get_restart_display()
-        );
-    }
-}
-
-/// 展示容器状态摘要信息
-fn display_container_summary(report: &HealthReport) {
-    let running_containers = report.get_running_containers();
-    let completed_containers = report.get_completed_containers();
-    let failed_containers = report.get_failed_containers();
-
-    let failed_containers: Vec<_> = failed_containers.iter().collect();
-
-    // 分离不同类型的容器
-    let persistent_running: Vec<_> = running_containers
-        .iter()
-        .filter(|c| c.is_persistent_service())
-        .collect();
-    let oneshot_running: Vec<_> = running_containers
-        .iter()
-        .filter(|c| c.is_oneshot())
-        .collect();
-
-    let oneshot_completed: Vec<_> = completed_containers
-        .iter()
-        .filter(|c| c.is_oneshot())
-        .collect();
-    let other_completed: Vec<_> = completed_containers
-        .iter()
-        .filter(|c| !c.is_oneshot())
-        .collect();
-    // 如果有持续运行的服务，显示警告
-    if !persistent_running.is_empty() {
-        warn!("⚠️  持续服务仍在运行中！");
-        error!("❌ 冷备份要求持续运行的服务必须处于停止状态");
-
-        info!("📝 发现 {} 个持续运行的服务:", persistent_running.len());
-        display_running_containers(&persistent_running);
-
-        if !oneshot_running.is_empty() {
-            info!("🔄 忽略 {} 个运行中的一次性任务:", oneshot_running.len());
-            display_running_containers(&oneshot_running);
-        }
-
-        info!("💡 请先停止持续运行的服务后再进行备份");
-    } else {
-        info!("✅ 所有持续服务已停止，可以进行备份");
-    }
-
-    // 显示其他已完成的容器
-    if !oneshot_completed.is_empty() {
-        info!(
-            "🔄 忽略 {} 个已完成的一次性任务容器:",
-            oneshot_completed.len()
-        );
-        display_running_containers(&oneshot_completed);
-    }
-
-    if !other_completed.is_empty() {
-        info!("📝 发现 {} 个其他已完成容器:", other_completed.len());
-        display_running_containers(&other_completed);
-    }
-
-    if !failed_containers.is_empty() {
-        warn!(
-            "⚠️  发现 {} 个失败的容器（不影响备份）:",
-            failed_containers.len()
-        );
-        display_running_containers(&failed_containers);
-    }
-}
-/// 检查Docker服务状态
-pub(crate) async fn check_docker_service_running(
-    app_config: Arc<AppConfig>,
-    docker_manager: Arc<DockerManager>,
-) -> Result<bool> {
-    let docker_service = DockerService::new(app_config.clone(), docker_manager)?;
-    let report = docker_service
-        .health_check()
-        .await
-        .map_err(|e| anyhow!("检查Docker服务状态失败: {}", e))?;
-
-    info!("📊 服务状态: {}", report.get_status_summary());
-    display_container_summary(&report);
-
-    // 检查是否有持续运行的服务
-    let running_containers = report.get_running_containers();
-    let persistent_running: Vec<_> = running_containers
-        .iter()
-        .filter(|c| c.is_persistent_service())
-        .collect();
-
-    let running_flag = !persistent_running.is_empty();
-
-    Ok(running_flag)
-}
-
-/// 检查Docker服务状态
-pub(crate) async fn check_docker_service_status(
-    app_config: Arc<AppConfig>,
-    docker_manager: Arc<DockerManager>,
-) -> Result<()> {
-    info!("🔍 检查Docker服务状态...");
+/// 服务停止检查中的操作名称
+pub(crate) const COLD_BACKUP: &str = "冷备份";
 
-    let runing_flag = check_docker_service_running(app_config, docker_manager).await?;
-
-    if runing_flag {
-        error!("有持续运行的服务，无法进行冷备份");
-        return Err(anyhow!("有持续运行的服务，无法进行冷备份"));
-    }
-
-    Ok(())
+/// 当前项目的备份命名空间：优先使用配置 backup.namespace，否则使用 docker-compose 项目名
+pub(crate) fn backup_namespace(app: &CliApp) -> String {
+    let name = match &app.config.backup.namespace {
+        Some(namespace) => namespace.clone(),
+        None => app.docker_manager.get_compose_project_name(),
+    };
+    normalize_backup_namespace(&name)
 }
 
-/// 创建新的备份
-async fn create_new_backup(app: &CliApp, change_files: Vec<PathBuf>) -> Result<()> {
-    info!("🔄 开始创建备份...");
-
+/// 升级前备份的选项：数据目录、应用目录及本次升级涉及的文件
+fn pre_upgrade_backup_options(app: &CliApp, change_files: Vec<PathBuf>) -> BackupOptions {
     //change_files 需要拼接 ./docker 目录的路径
     let work_dir = docker::get_docker_work_dir();
     let change_file_or_dir = change_files
@@ -179,28 +82,94 @@ async fn create_new_backup(app: &CliApp, change_files: Vec<PathBuf>) -> Result<(
     let mut need_backup_paths = vec![docker::get_data_dir_path(), docker::get_app_dir_path()];
     need_backup_paths.extend(change_file_or_dir);
 
-    let backup_options = BackupOptions {
-        backup_type: BackupType::Manual,
+    BackupOptions {
+        backup_type: BackupType::PreUpgrade,
         service_version: app.config.get_docker_versions(),
+        namespace: Some(backup_namespace(app)),
         work_dir,
         source_paths: need_backup_paths,
+        system_paths: Vec::new(),
         compression_level: 6,
-    };
+        dedup: app.config.backup.dedup,
+        verify: app.config.backup.verify.applies_to(BackupType::PreUpgrade),
+        events: app.events.clone(),
+        created_by: backup_created_by(),
+    }
+}
+
+/// 手动备份的选项：数据目录、应用目录，`include_system` 时包括 CLI 自身状态
+pub(crate) fn manual_backup_options(
+    app: &CliApp,
+    backup_type: BackupType,
+    include_system: bool,
+    dedup: bool,
+) -> BackupOptions {
+    // 执行需要备份的目录: app, data 目录
+    let source_paths = vec![docker::get_data_dir_path(), docker::get_app_dir_path()];
 
-    let backup_manager = BackupManager::new(
-        app.config.get_backup_dir(),
-        app.database.clone(),
-        app.docker_manager.clone(),
-    )?;
+    BackupOptions {
+        backup_type,
+        service_version: app.config.get_docker_versions(),
+        namespace: Some(backup_namespace(app)),
+        work_dir: docker::get_docker_work_dir(),
+        source_paths,
+        system_paths: if include_system {
+            info!("🗄️  包含 CLI 系统状态（配置文件、数据库）");
+            get_system_state_paths(&app.config_path)
+        } else {
+            Vec::new()
+        },
+        compression_level: 6, // 平衡压缩率和速度
+        dedup: dedup || app.config.backup.dedup,
+        verify: app.config.backup.verify.applies_to(backup_type),
+        events: app.events.clone(),
+        created_by: backup_created_by(),
+    }
+}
+
+/// 写入备份格式清单的客户端名称及版本
+fn backup_created_by() -> String {
+    format!("{} {}", metadata::PROJECT_NAME, version_info::CLI_VERSION)
+}
+
+/// 创建新的备份
+async fn create_new_backup(app: &CliApp, change_files: Vec<PathBuf>) -> Result<()> {
+    info!("🔄 开始创建备份...");
+
+    let backup_options = pre_upgrade_backup_options(app, change_files);
+
+    let backup_manager = app.backup_manager.clone();
 
     let backup_record = backup_manager.create_backup(backup_options).await?;
     info!("✅ 备份创建成功: {}", backup_record.file_path);
     info!("📝 备份ID: {}", backup_record.id);
     info!("📏 备份服务版本: {}", backup_record.service_version);
 
+    prune_backups_by_retention(
+        &app.config.backup.retention,
+        &backup_namespace(app),
+        &backup_manager,
+    )
+    .await;
+
     Ok(())
 }
 
+/// 按配置的保留策略清理当前命名空间的旧备份，清理失败不影响本次备份结果
+async fn prune_backups_by_retention(
+    retention: &BackupRetentionConfig,
+    namespace: &str,
+    backup_manager: &BackupManager,
+) {
+    match backup_manager.prune_backups(retention, namespace).await {
+        Ok(pruned) if !pruned.is_empty() => {
+            info!("🧹 已按保留策略清理 {} 个旧备份", pruned.len());
+        }
+        Ok(_) => {}
+        Err(e) => warn!("⚠️ 按保留策略清理旧备份失败: {}", e),
+    }
+}
+
 /// 执行带升级策略的备份
 pub async fn run_backup_with_upgrade_strategy(
     app: &CliApp,
@@ -210,7 +179,9 @@ pub async fn run_backup_with_upgrade_strategy(
     validate_docker_compose_file(Path::new(&app.config.docker.compose_file))?;
 
     // 检查服务状态
-    check_docker_service_status(app.config.clone(), app.docker_manager.clone()).await?;
+    ServiceGate::new(app.config.clone(), app.docker_manager.clone())
+        .ensure_stopped(COLD_BACKUP, false)
+        .await?;
 
     // 创建备份
     let change_files = upgrade_strategy.get_changed_files();
@@ -220,8 +191,87 @@ pub async fn run_backup_with_upgrade_strategy(
     Ok(())
 }
 
+/// 后台压缩中的升级前备份
+#[derive(Debug)]
+pub struct PendingBackup {
+    handle: JoinHandle<Result<BackupRecord>>,
+}
+
+impl PendingBackup {
+    /// 等待后台压缩完成，返回备份记录
+    pub async fn wait(self) -> Result<BackupRecord> {
+        self.handle
+            .await
+            .map_err(|e| anyhow!("备份压缩任务异常退出: {e}"))?
+    }
+}
+
+/// 暂存升级前备份，压缩在后台执行
+///
+/// 服务停止期间只需生成文件清单并复制（或硬链接）文件，返回后即可继续解压新版本；
+/// 通过 [`PendingBackup::wait`] 等待压缩完成并取得备份记录
+pub async fn stage_backup_with_upgrade_strategy(
+    app: &CliApp,
+    upgrade_strategy: UpgradeStrategy,
+    mode: BackupStagingMode,
+) -> Result<PendingBackup> {
+    // 验证Docker环境
+    validate_docker_compose_file(Path::new(&app.config.docker.compose_file))?;
+
+    // 检查服务状态
+    ServiceGate::new(app.config.clone(), app.docker_manager.clone())
+        .ensure_stopped(COLD_BACKUP, false)
+        .await?;
+
+    let backup_options = pre_upgrade_backup_options(app, upgrade_strategy.get_changed_files());
+    let backup_manager = app.backup_manager.clone();
+
+    let staged = backup_manager.stage_backup(backup_options, mode).await?;
+    info!(
+        "✅ 备份文件已暂存（{} 个条目），开始后台压缩",
+        staged.entry_count()
+    );
+
+    let retention = app.config.backup.retention.clone();
+    let namespace = backup_namespace(app);
+    let handle = tokio::spawn(async move {
+        let backup_record = backup_manager.create_backup_from_staging(staged).await?;
+        info!("✅ 备份创建成功: {}", backup_record.file_path);
+        prune_backups_by_retention(&retention, &namespace, &backup_manager).await;
+        Ok(backup_record)
+    });
+
+    Ok(PendingBackup { handle })
+}
+
+/// CLI 自身状态文件：配置文件、数据库及其 WAL 文件
+fn get_system_state_paths(config_path: &Path) -> Vec<PathBuf> {
+    let database_path = config::get_database_path();
+    let mut wal_name = database_path.as_os_str().to_os_string();
+    wal_name.push(".wal");
+
+    vec![
+        config_path.to_path_buf(),
+        database_path,
+        PathBuf::from(wal_name),
+    ]
+}
+
 /// 创建备份
-pub async fn run_backup(app: &CliApp) -> Result<()> {
+///
+/// `backup_type` 决定备份归类及适用的保留策略；
+/// `include_system` 为 true 时同时备份 CLI 自身状态（config.toml、数据库），
+/// 以便主机恢复时一并找回备份历史、计划任务和客户端注册信息；
+/// `dedup` 为 true 或配置开启 `backup.dedup` 时使用分块去重存储；
+/// `force` 为 true 时即使持续服务仍在运行也继续备份
+#[tracing::instrument(level = "trace", name = "phase.backup", skip_all)]
+pub async fn run_backup(
+    app: &CliApp,
+    backup_type: BackupType,
+    include_system: bool,
+    dedup: bool,
+    force: bool,
+) -> Result<()> {
     // 1. 检查Docker环境
     let compose_path = Path::new(&app.config.docker.compose_file);
 
@@ -231,123 +281,22 @@ pub async fn run_backup(app: &CliApp) -> Result<()> {
         return Ok(());
     }
 
-    // 2. 使用 DockerService 的 health_check 进行智能状态检查
+    // 2. 按 restart 策略检查持续服务是否已停止，一次性任务不影响备份
     info!("🔍 检查Docker服务状态...");
 
-    let docker_service = DockerService::new(app.config.clone(), app.docker_manager.clone())?;
-    match docker_service.health_check().await {
+    let gate = ServiceGate::new(app.config.clone(), app.docker_manager.clone());
+    match gate.inspect().await {
         Ok(report) => {
-            info!("📊 服务状态: {}", report.get_status_summary());
-
-            // 智能分析服务状态
-            let running_containers = report.get_running_containers();
-            let completed_containers = report.get_completed_containers();
-            let failed_containers = report.get_failed_containers();
-
-            // 🔧 改进：使用restart字段智能判断一次性任务和持续服务
-            let persistent_running_services: Vec<_> = running_containers
-                .iter()
-                .filter(|c| c.is_persistent_service())
-                .collect();
-
-            if !persistent_running_services.is_empty() {
-                warn!("⚠️  持续服务仍在运行中！");
-                error!("❌ 冷备份要求持续运行的服务必须处于停止状态");
-
-                info!(
-                    "📝 发现 {} 个持续运行的服务:",
-                    persistent_running_services.len()
-                );
-                for container in &persistent_running_services {
-                    info!(
-                        "   - {} (状态: {}, restart: {})",
-                        container.name,
-                        container.status.display_name(),
-                        container.get_restart_display()
-                    );
-                }
-
-                // 显示被忽略的一次性任务
-                let oneshot_running_services: Vec<_> = running_containers
-                    .iter()
-                    .filter(|c| c.is_oneshot())
-                    .collect();
-
-                if !oneshot_running_services.is_empty() {
-                    info!(
-                        "📝 发现 {} 个运行中的一次性任务（已忽略）:",
-                        oneshot_running_services.len()
-                    );
-                    for container in oneshot_running_services {
-                        info!(
-                            "   - {} (一次性任务，restart: {}, 不影响备份)",
-                            container.name,
-                            container.get_restart_display()
-                        );
-                    }
-                }
-
-                info!("💡 请先停止持续运行的服务后再进行备份");
+            report.log_summary(COLD_BACKUP);
+            if !report.permits(COLD_BACKUP, force) {
                 return Ok(());
             }
-
-            // 成功：所有持续服务已停止
-            info!("✅ 所有持续服务已停止，可以进行备份");
-
-            // 显示已完成和被忽略的容器信息
-            if !completed_containers.is_empty() {
-                let oneshot_completed: Vec<_> = completed_containers
-                    .iter()
-                    .filter(|c| c.is_oneshot())
-                    .collect();
-
-                let other_completed: Vec<_> = completed_containers
-                    .iter()
-                    .filter(|c| !c.is_oneshot())
-                    .collect();
-
-                if !oneshot_completed.is_empty() {
-                    info!("🔄 忽略 {} 个一次性任务容器:", oneshot_completed.len());
-                    for container in oneshot_completed {
-                        info!(
-                            "   - {} (状态: {}, restart: {})",
-                            container.name,
-                            container.status.display_name(),
-                            container.get_restart_display()
-                        );
-                    }
-                }
-
-                if !other_completed.is_empty() {
-                    info!("📝 发现 {} 个其他已完成容器:", other_completed.len());
-                    for container in other_completed {
-                        info!(
-                            "   - {} (状态: {}, restart: {})",
-                            container.name,
-                            container.status.display_name(),
-                            container.get_restart_display()
-                        );
-                    }
-                }
-            }
-
-            if !failed_containers.is_empty() {
-                warn!(
-                    "⚠️  发现 {} 个失败的容器（不影响备份）:",
-                    failed_containers.len()
-                );
-                for container in failed_containers {
-                    warn!(
-                        "   - {} (状态: {}, restart: {})",
-                        container.name,
-                        container.status.display_name(),
-                        container.get_restart_display()
-                    );
-                }
-            }
+        }
+        Err(e) if force => {
+            warn!("⚠️  {}，已指定 --force，继续备份", e);
         }
         Err(e) => {
-            error!("❌ 检查Docker服务状态失败: {}", e);
+            error!("❌ {}", e);
             info!("💡 无法确认服务状态，建议手动检查后再进行备份");
             return Ok(());
         }
@@ -355,24 +304,10 @@ pub async fn run_backup(app: &CliApp) -> Result<()> {
 
     // 3. 执行备份
     info!("🔄 开始创建备份...");
-
-    // 执行需要备份的目录: app, data 目录
-    let source_paths = vec![docker::get_data_dir_path(), docker::get_app_dir_path()];
-
-    let backup_options = BackupOptions {
-        backup_type: BackupType::Manual,
-        service_version: app.config.get_docker_versions(),
-        work_dir: PathBuf::from("./docker"),
-        source_paths,
-        compression_level: 6, // 平衡压缩率和速度
-    };
+    let backup_options = manual_backup_options(app, backup_type, include_system, dedup);
 
     // 使用 BackupManager 创建备份
-    let backup_manager = BackupManager::new(
-        app.config.get_backup_dir(),
-        app.database.clone(),
-        app.docker_manager.clone(),
-    )?;
+    let backup_manager = app.backup_manager.clone();
 
     match backup_manager.create_backup(backup_options).await {
         Ok(backup_record) => {
@@ -386,22 +321,145 @@ pub async fn run_backup(app: &CliApp) -> Result<()> {
         }
     }
 
+    prune_backups_by_retention(
+        &app.config.backup.retention,
+        &backup_namespace(app),
+        &backup_manager,
+    )
+    .await;
+
     Ok(())
 }
 
-/// 列出备份
-pub async fn run_list_backups(app: &CliApp) -> Result<()> {
-    let backups = app.backup_manager.list_backups().await?;
+/// 解析 `--since`/`--until` 的时间：RFC 3339、本地时间的 `YYYY-MM-DD[ HH:MM[:SS]]`，
+/// 或相对当前时间的 `<N>d`/`<N>h`（如 7d 表示 7 天前）
+///
+/// `end_of_day` 为 true 时，只写日期表示当天结束，使 `--until 2025-06-01` 包含当天的备份
+fn parse_time_bound(value: &str, end_of_day: bool) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let relative = value
+        .strip_suffix('d')
+        .and_then(|days| days.parse().ok())
+        .map(Duration::days)
+        .or_else(|| {
+            value
+                .strip_suffix('h')
+                .and_then(|hours| hours.parse().ok())
+                .map(Duration::hours)
+        });
+    if let Some(ago) = relative {
+        return Ok(Utc::now() - ago);
+    }
+
+    let local = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+            if end_of_day {
+                date.and_hms_opt(23, 59, 59)
+            } else {
+                date.and_hms_opt(0, 0, 0)
+            }
+        })
+        .ok_or_else(|| {
+            anyhow!("无法识别的时间: {value}（支持 2025-06-01、2025-06-01 08:00、7d、12h）")
+        })?;
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| anyhow!("本地时间不存在: {value}"))
+}
+
+/// 列出备份，按 `args` 中的条件筛选、排序和分页
+///
+/// 默认只显示当前项目命名空间（及早期未记录命名空间）的备份，`--namespace` 指定其他命名空间，
+/// `--all-namespaces` 显示共享备份目录中所有项目的备份
+pub async fn run_list_backups(app: &CliApp, args: BackupListArgs) -> Result<()> {
+    let namespace = match args.namespace {
+        _ if args.all_namespaces => None,
+        Some(namespace) => Some(normalize_backup_namespace(&namespace)),
+        None => Some(backup_namespace(app)),
+    };
+    let page_number = args.page.unwrap_or(1);
+    if page_number == 0 || args.limit == Some(0) {
+        return Err(anyhow!("--page 和 --limit 必须大于 0"));
+    }
+    let query = BackupQuery {
+        backup_type: args.backup_type,
+        service_version: args.version,
+        namespace: namespace.clone(),
+        since: args
+            .since
+            .as_deref()
+            .map(|time| parse_time_bound(time, false))
+            .transpose()?,
+        until: args
+            .until
+            .as_deref()
+            .map(|time| parse_time_bound(time, true))
+            .transpose()?,
+        sort: args.sort,
+        ascending: args.asc,
+        limit: args.limit,
+        offset: args.limit.map_or(0, |limit| (page_number - 1) * limit),
+    };
+    let page = app
+        .backup_manager
+        .query_backups(&query, args.only_valid)
+        .await?;
+    let backups = page.backups;
+
+    if namespace.is_some() {
+        let other_namespaces = BackupQuery {
+            namespace: None,
+            limit: Some(0),
+            offset: 0,
+            ..query.clone()
+        };
+        let all_total = app
+            .backup_manager
+            .query_backups(&other_namespaces, args.only_valid)
+            .await?
+            .total;
+        if all_total > page.total {
+            info!(
+                "💡 另有 {} 个其他命名空间的备份未显示，使用 --all-namespaces 查看",
+                all_total - page.total
+            );
+        }
+    }
 
     if backups.is_empty() {
-        info!("📦 暂无备份记录");
+        if page.total > 0 {
+            info!(
+                "📦 第 {} 页没有备份记录（共 {} 条）",
+                page_number, page.total
+            );
+            return Ok(());
+        }
+        match args.backup_type {
+            Some(t) => info!("📦 暂无{}备份记录", t.display_name()),
+            None => info!("📦 暂无备份记录"),
+        }
         info!("💡 使用以下命令创建备份:");
         info!("   nuwax-cli backup");
         return Ok(());
     }
 
-    info!("📦 备份列表");
+    match &namespace {
+        Some(namespace) => info!("📦 备份列表（命名空间: {}）", namespace),
+        None => info!("📦 备份列表（所有命名空间）"),
+    }
+    if let Some(storage) = app.backup_manager.storage() {
+        info!("☁️ 备份存储: {}", storage.location());
+    }
     info!("============");
+    let available = app.backup_manager.available_backup_ids(&backups).await?;
 
     // 统计信息
     let total_backups = backups.len();
@@ -411,8 +469,8 @@ pub async fn run_list_backups(app: &CliApp) -> Result<()> {
 
     // 详细信息表头
     info!(
-        "{:<4} {:<12} {:<20} {:<10} {:<8} {:<12} {}",
-        "ID", "类型", "创建时间", "版本", "状态", "大小", "文件路径"
+        "{:<4} {:<12} {:<20} {:<10} {:<8} {:<6} {:<12} {}",
+        "ID", "类型", "创建时间", "版本", "状态", "校验", "大小", "文件路径"
     );
     info!("{}", "-".repeat(100));
 
@@ -442,16 +500,16 @@ pub async fn run_list_backups(app: &CliApp) -> Result<()> {
             };
 
             ("✅ 可用", size)
+        } else if available.contains(&backup.id) {
+            valid_backups += 1;
+            ("☁️ 已存储", "---".to_string())
         } else {
             invalid_backups += 1;
             ("❌ 文件缺失", "---".to_string())
         };
 
         // 备份类型显示
-        let backup_type_display = match backup.backup_type {
-            client_core::database::BackupType::Manual => "手动",
-            client_core::database::BackupType::PreUpgrade => "升级前",
-        };
+        let backup_type_display = backup.backup_type.display_name();
 
         // 获取文件名而不是完整路径用于显示
         let filename = backup_path
@@ -459,27 +517,46 @@ pub async fn run_list_backups(app: &CliApp) -> Result<()> {
             .map(|n| n.to_string_lossy().to_string())
             .unwrap_or_else(|| backup.file_path.clone());
 
+        // 创建后校验结果，早期备份和未开启校验的备份没有记录
+        let verified_display = match backup.verified {
+            Some(true) => "✅",
+            Some(false) => "❌",
+            None => "-",
+        };
+
         info!(
-            "{:<4} {:<12} {:<20} {:<10} {:<8} {:<12} {}",
+            "{:<4} {:<12} {:<20} {:<10} {:<8} {:<6} {:<12} {}",
             backup.id,
             backup_type_display,
             backup.created_at.format("%Y-%m-%d %H:%M:%S"),
             backup.service_version,
             status_display,
+            verified_display,
             size_display,
             filename
         );
 
         // 如果文件不存在，显示警告信息
-        if !file_exists {
+        if !available.contains(&backup.id) {
             warn!("     ⚠️  警告: 备份文件不存在，无法用于回滚！");
             warn!("         预期路径: {}", backup.file_path);
         }
+        if backup.verified == Some(false) {
+            warn!("     ⚠️  警告: 备份创建后校验未通过，不建议用于回滚！");
+        }
     }
 
     info!("{}", "-".repeat(100));
+    if let Some(limit) = args.limit {
+        info!(
+            "📄 第 {}/{} 页，共 {} 个备份，使用 --page <N> 查看其他页",
+            page_number,
+            page.total.div_ceil(limit),
+            page.total
+        );
+    }
 
-    // 统计摘要
+    // 统计摘要（只统计当前页）
     info!("📊 备份统计:");
     info!("   总备份数: {}", total_backups);
     info!("   可用备份: {} ✅", valid_backups);
@@ -520,7 +597,139 @@ pub async fn run_list_backups(app: &CliApp) -> Result<()> {
     Ok(())
 }
 
+/// 执行备份目录相关命令（导出、导入、登记已有归档）
+pub async fn run_backup_catalog_command(app: &CliApp, cmd: BackupCommand) -> Result<()> {
+    match cmd {
+        BackupCommand::ExportCatalog { format, output } => {
+            let catalog = app.backup_manager.export_catalog().await?;
+            let content = catalog.render(format)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, content)?;
+                    info!(
+                        "✅ 已导出 {} 条备份记录到: {}",
+                        catalog.backups.len(),
+                        path.display()
+                    );
+                }
+                // 只输出目录内容到标准输出，便于外部系统直接读取
+                None => print!("{content}"),
+            }
+            Ok(())
+        }
+        BackupCommand::ImportCatalog { file, format } => {
+            let format = format.unwrap_or_else(|| CatalogFormat::from_path(&file));
+            let content = std::fs::read_to_string(&file)
+                .map_err(|e| anyhow!("无法读取备份目录文件 {}: {}", file.display(), e))?;
+            let catalog = BackupCatalog::parse(&content, format)?;
+            info!(
+                "📥 从 {} 导入 {} 条备份记录...",
+                file.display(),
+                catalog.backups.len()
+            );
+            let report = app.backup_manager.import_catalog(&catalog).await?;
+            print_catalog_import_report(&report);
+            Ok(())
+        }
+        BackupCommand::Adopt { dir } => {
+            info!("🔍 扫描备份目录: {}", dir.display());
+            let report = app.backup_manager.adopt_directory(&dir).await?;
+            print_catalog_import_report(&report);
+            Ok(())
+        }
+        BackupCommand::Verify { backup_id } => {
+            let backup_ids = match backup_id {
+                Some(id) => vec![id],
+                None => app
+                    .backup_manager
+                    .list_backups()
+                    .await?
+                    .iter()
+                    .filter(|backup| backup.status == BackupStatus::Completed)
+                    .map(|backup| backup.id)
+                    .collect(),
+            };
+
+            let mut failed = 0;
+            for id in &backup_ids {
+                let verification = app.backup_manager.verify_backup(*id).await?;
+                print_backup_verification(&verification);
+                if !verification.is_ok() {
+                    failed += 1;
+                }
+            }
+
+            info!(
+                "📊 校验完成: 通过 {} 个，失败 {} 个",
+                backup_ids.len() - failed,
+                failed
+            );
+            if failed > 0 {
+                return Err(anyhow!("{failed} 个备份未通过完整性校验"));
+            }
+            Ok(())
+        }
+        BackupCommand::Repack { backup_id, output } => {
+            let path = app
+                .backup_manager
+                .repack_backup(backup_id, output.as_deref())
+                .await?;
+            info!("✅ 备份 {} 已还原为普通归档: {}", backup_id, path.display());
+            Ok(())
+        }
+    }
+}
+
+fn print_backup_verification(verification: &BackupVerification) {
+    let kind = if verification.deduplicated {
+        "去重备份"
+    } else {
+        "普通备份"
+    };
+    if verification.is_ok() {
+        info!("✅ 备份 {} ({}) 校验通过", verification.backup_id, kind);
+        return;
+    }
+
+    error!("❌ 备份 {} ({}) 校验失败", verification.backup_id, kind);
+    if let Some(report) = &verification.dedup {
+        for hash in &report.missing {
+            error!("   缺失分块: {}", hash);
+        }
+        for hash in &report.corrupt {
+            error!("   分块损坏: {}", hash);
+        }
+        for path in &report.size_mismatch {
+            error!("   文件大小不一致: {}", path);
+        }
+    }
+    if let Some(err) = &verification.error {
+        error!("   {}", err);
+    }
+}
+
+fn print_catalog_import_report(report: &CatalogImportReport) {
+    for backup in &report.imported {
+        info!(
+            "✅ 已登记备份 ID {}: {} ({}, v{})",
+            backup.id,
+            backup.file_path,
+            backup.backup_type.display_name(),
+            backup.service_version
+        );
+    }
+    for (path, reason) in &report.skipped {
+        warn!("⏭️ 跳过 {}: {}", path, reason);
+    }
+    info!(
+        "📊 登记完成: 新增 {} 个，跳过 {} 个",
+        report.imported.len(),
+        report.skipped.len()
+    );
+}
+
 /// 从备份恢复
+#[allow(clippy::too_many_arguments)]
 pub async fn run_rollback(
     app: &CliApp,
     backup_id: Option<i64>,
@@ -528,6 +737,8 @@ pub async fn run_rollback(
     list_json: bool,
     auto_start_service: bool,
     rollback_data: bool,
+    restore_system: bool,
+    allow_other_namespace: bool,
 ) -> Result<()> {
     // 如果指定了 --list-json，禁用日志输出并输出 JSON 格式的备份列表
     if list_json {
@@ -554,6 +765,7 @@ pub async fn run_rollback(
             }
         }
     };
+    ensure_backup_namespace(app, selected_backup_id, allow_other_namespace).await?;
 
     if !force {
         if rollback_data {
@@ -561,15 +773,14 @@ pub async fn run_rollback(
         } else {
             warn!("⚠️  警告: 此操作会回滚后端和前端应用版本,但不回滚Mysql,Redis等数据!");
         }
+        if restore_system {
+            warn!(
+                "⚠️  警告: 配置文件和本地数据库（备份历史、计划任务、客户端注册信息）也会被恢复!"
+            );
+        }
 
-        use std::io::{self, Write};
-        print!("请确认您要从备份 {selected_backup_id} 恢复数据 (y/N): ");
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-
-        if input.trim().to_lowercase() != "y" {
+        if !prompt::confirm(&format!("请确认您要从备份 {selected_backup_id} 恢复数据"))?
+        {
             warn!("操作已取消");
             return Ok(());
         }
@@ -587,10 +798,143 @@ pub async fn run_rollback(
         run_rollback_with_exculde(app, selected_backup_id, auto_start_service, &["data"]).await?;
     }
 
+    if restore_system {
+        restore_system_state(app, selected_backup_id).await?;
+    }
+
     info!("✅ 数据回滚完成");
     Ok(())
 }
 
+/// 预览列表中每类变化最多列出的文件数
+const PREVIEW_LIST_LIMIT: usize = 50;
+
+/// 预览从备份恢复会带来的变化，不停止服务、不改动工作目录
+pub async fn run_rollback_preview(
+    app: &CliApp,
+    backup_id: Option<i64>,
+    rollback_data: bool,
+    allow_other_namespace: bool,
+) -> Result<()> {
+    let selected_backup_id = if let Some(id) = backup_id {
+        id
+    } else {
+        match interactive_backup_selection(app).await? {
+            Some(id) => id,
+            None => {
+                info!("操作已取消");
+                return Ok(());
+            }
+        }
+    };
+    ensure_backup_namespace(app, selected_backup_id, allow_other_namespace).await?;
+
+    // 与 rollback 的排除规则一致：不回滚数据时保留 data 目录
+    let dirs_to_exclude: &[&str] = if rollback_data { &[] } else { &["data"] };
+    let docker_dir = docker::get_docker_work_dir();
+    let preview = app
+        .backup_manager
+        .preview_restore(selected_backup_id, &docker_dir, dirs_to_exclude)
+        .await?;
+
+    info!(
+        "📋 从备份 {} 恢复的变更预览（工作目录: {}）",
+        selected_backup_id,
+        docker_dir.display()
+    );
+    if preview.is_empty() {
+        info!("   没有文件变更");
+    }
+    print_preview_changes("覆盖", &preview.overwritten);
+    print_preview_changes("新增", &preview.added);
+    print_preview_changes("删除", &preview.removed);
+    if !preview.kept.is_empty() {
+        info!("🔒 保持不变的受保护路径:");
+        for path in &preview.kept {
+            info!("   {}", path);
+        }
+    }
+    info!(
+        "📊 覆盖 {} 个文件, 新增 {} 个, 删除 {} 个, 大小变化 {}",
+        preview.overwritten.len(),
+        preview.added.len(),
+        preview.removed.len(),
+        format_size_delta(preview.total_size_delta())
+    );
+    if !preview.removed.is_empty() {
+        warn!(
+            "⚠️ 有 {} 个当前存在的文件不在备份中，恢复时会被删除",
+            preview.removed.len()
+        );
+    }
+    info!(
+        "💡 确认后执行 'nuwax-cli rollback {}{}' 开始恢复",
+        selected_backup_id,
+        if rollback_data {
+            " --rollback-data"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+fn print_preview_changes(label: &str, changes: &[RestoreFileChange]) {
+    for change in changes.iter().take(PREVIEW_LIST_LIMIT) {
+        info!(
+            "   [{}] {} ({})",
+            label,
+            change.path,
+            format_size_delta(change.size_delta())
+        );
+    }
+    if changes.len() > PREVIEW_LIST_LIMIT {
+        info!(
+            "   [{}] ... 还有 {} 个文件",
+            label,
+            changes.len() - PREVIEW_LIST_LIMIT
+        );
+    }
+}
+
+/// 带符号的大小变化，如 `+1.5MB`、`-320B`
+fn format_size_delta(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    let bytes = delta.unsigned_abs() as f64;
+    if bytes >= 1024.0 * 1024.0 {
+        format!("{sign}{:.1}MB", bytes / (1024.0 * 1024.0))
+    } else if bytes >= 1024.0 {
+        format!("{sign}{:.1}KB", bytes / 1024.0)
+    } else {
+        format!("{sign}{bytes}B")
+    }
+}
+
+/// 从备份恢复 CLI 自身状态（config.toml、数据库）
+async fn restore_system_state(app: &CliApp, backup_id: i64) -> Result<()> {
+    if !app
+        .backup_manager
+        .backup_contains_system_state(backup_id)
+        .await?
+    {
+        warn!("⚠️  备份 {} 不包含 CLI 系统状态，跳过恢复", backup_id);
+        info!("💡 使用 'nuwax-cli backup --include-system' 创建包含系统状态的备份");
+        return Ok(());
+    }
+
+    info!("🗄️  恢复 CLI 系统状态（配置文件、数据库）...");
+    let restored = app
+        .backup_manager
+        .restore_system_state(backup_id, &app.config_path, &config::get_database_path())
+        .await?;
+
+    for path in &restored {
+        info!("   ✅ 已恢复: {}", path.display());
+    }
+    info!("💡 系统状态已恢复，后续命令将使用恢复后的配置和数据库");
+    Ok(())
+}
+
 /// 只回滚 data 目录，保留 app 目录和配置文件
 pub async fn run_rollback_data_only(
     app: &CliApp,
@@ -598,6 +942,7 @@ pub async fn run_rollback_data_only(
     force: bool,
     auto_start_service: bool,
     config_file: Option<&std::path::PathBuf>,
+    allow_other_namespace: bool,
 ) -> Result<()> {
     // 如果没有提供backup_id，启动交互式选择
     let selected_backup_id = if let Some(id) = backup_id {
@@ -611,19 +956,15 @@ pub async fn run_rollback_data_only(
             }
         }
     };
+    ensure_backup_namespace(app, selected_backup_id, allow_other_namespace).await?;
 
     if !force {
         warn!("⚠️  警告: 此操作将覆盖当前 data 目录!");
         warn!("⚠️  注意: 此操作只恢复 data 目录，app 目录和配置文件将保持不变");
 
-        use std::io::{self, Write};
-        print!("请确认您要从备份 {selected_backup_id} 恢复 data 目录 (y/N): ");
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
-
-        if input.trim().to_lowercase() != "y" {
+        if !prompt::confirm(&format!(
+            "请确认您要从备份 {selected_backup_id} 恢复 data 目录"
+        ))? {
             warn!("操作已取消");
             return Ok(());
         }
@@ -639,12 +980,43 @@ pub async fn run_rollback_data_only(
     Ok(())
 }
 
+/// 确认备份属于当前项目的命名空间，避免把共享备份目录中其他项目的备份恢复到当前项目
+pub(crate) async fn ensure_backup_namespace(
+    app: &CliApp,
+    backup_id: i64,
+    allow_other_namespace: bool,
+) -> Result<()> {
+    // 备份不存在时由后续恢复步骤报告
+    let Some(backup) = app.database.get_backup_by_id(backup_id).await? else {
+        return Ok(());
+    };
+    let namespace = backup_namespace(app);
+    if backup.belongs_to_namespace(&namespace) {
+        return Ok(());
+    }
+
+    let other_namespace = backup.namespace.unwrap_or_default();
+    if allow_other_namespace {
+        warn!(
+            "⚠️  备份 {} 属于命名空间 {}，与当前项目 {} 不同，按 --allow-other-namespace 继续恢复",
+            backup_id, other_namespace, namespace
+        );
+        return Ok(());
+    }
+    Err(anyhow!(
+        "备份 {backup_id} 属于命名空间 {other_namespace}，与当前项目 {namespace} 不同，已拒绝恢复（确认无误可使用 --allow-other-namespace）"
+    ))
+}
+
 /// 交互式备份选择
 async fn interactive_backup_selection(app: &CliApp) -> Result<Option<i64>> {
     info!("🗂️  备份选择");
     info!("============");
 
-    let backups = app.backup_manager.list_backups().await?;
+    // 只列出当前项目命名空间的备份
+    let namespace = backup_namespace(app);
+    let mut backups = app.backup_manager.list_backups().await?;
+    backups.retain(|backup| backup.belongs_to_namespace(&namespace));
 
     if backups.is_empty() {
         warn!("❌ 没有可用的备份");
@@ -653,14 +1025,12 @@ async fn interactive_backup_selection(app: &CliApp) -> Result<Option<i64>> {
         return Ok(None);
     }
 
-    // 筛选可用的备份（文件存在且有效）
-    let mut valid_backups = Vec::new();
-    for backup in &backups {
-        let backup_path = std::path::Path::new(&backup.file_path);
-        if backup_path.exists() {
-            valid_backups.push(backup);
-        }
-    }
+    // 筛选可用的备份（本地文件存在或已保存到备份存储）
+    let available = app.backup_manager.available_backup_ids(&backups).await?;
+    let valid_backups: Vec<&BackupRecord> = backups
+        .iter()
+        .filter(|backup| available.contains(&backup.id))
+        .collect();
 
     if valid_backups.is_empty() {
         warn!("❌ 没有可用的备份文件");
@@ -696,10 +1066,7 @@ async fn interactive_backup_selection(app: &CliApp) -> Result<Option<i64>> {
         };
 
         // 备份类型显示
-        let backup_type_display = match backup.backup_type {
-            client_core::database::BackupType::Manual => "手动",
-            client_core::database::BackupType::PreUpgrade => "升级前",
-        };
+        let backup_type_display = backup.backup_type.display_name();
 
         // 获取文件名
         let filename = backup_path
@@ -725,14 +1092,13 @@ async fn interactive_backup_selection(app: &CliApp) -> Result<Option<i64>> {
     info!("   - 输入 'l' 或 'list' 重新显示列表");
 
     // 交互式选择循环
-    use std::io::{self, Write};
     loop {
-        print!("\n请选择要恢复的备份 (1-{}/q/l): ", valid_backups.len());
-        io::stdout().flush()?;
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim();
+        let input = prompt::input(&format!(
+            "请选择要恢复的备份 (1-{}/q/l): ",
+            valid_backups.len()
+        ))
+        .map_err(|e| anyhow!("{e}，请直接指定备份ID"))?;
+        let input = input.as_str();
 
         // 处理退出命令
         if input.is_empty() || input.eq_ignore_ascii_case("q") || input.eq_ignore_ascii_case("quit")
@@ -768,10 +1134,7 @@ async fn interactive_backup_selection(app: &CliApp) -> Result<Option<i64>> {
                     "未知".to_string()
                 };
 
-                let backup_type_display = match backup.backup_type {
-                    client_core::database::BackupType::Manual => "手动",
-                    client_core::database::BackupType::PreUpgrade => "升级前",
-                };
+                let backup_type_display = backup.backup_type.display_name();
 
                 let filename = backup_path
                     .file_name()
@@ -801,13 +1164,7 @@ async fn interactive_backup_selection(app: &CliApp) -> Result<Option<i64>> {
                     // 显示选择确认
                     info!("✅ 您选择了备份:");
                     info!("   备份ID: {}", selected_backup.id);
-                    info!(
-                        "   类型: {}",
-                        match selected_backup.backup_type {
-                            client_core::database::BackupType::Manual => "手动",
-                            client_core::database::BackupType::PreUpgrade => "升级前",
-                        }
-                    );
+                    info!("   类型: {}", selected_backup.backup_type.display_name());
                     info!(
                         "   创建时间: {}",
                         selected_backup.created_at.format("%Y-%m-%d %H:%M:%S")
@@ -827,6 +1184,18 @@ async fn interactive_backup_selection(app: &CliApp) -> Result<Option<i64>> {
     }
 }
 
+/// 备份记录了属主和目录权限时已在恢复时设置，旧版本创建的备份按权限策略设置权限
+async fn restore_permissions(backup_manager: &BackupManager, backup_id: i64, docker_dir: &Path) {
+    match backup_manager.backup_preserves_permissions(backup_id).await {
+        Ok(true) => info!("🔐 已按备份中记录的属主和权限恢复"),
+        Ok(false) => apply_permission_policy(docker_dir),
+        Err(e) => {
+            warn!("⚠️ 读取备份的权限记录失败，按权限策略设置权限: {}", e);
+            apply_permission_policy(docker_dir);
+        }
+    }
+}
+
 /// 只恢复数据的智能回滚
 async fn run_rollback_with_exculde(
     app: &CliApp,
@@ -840,12 +1209,12 @@ async fn run_rollback_with_exculde(
     info!("   不恢复的目录:{:?}", dirs_to_exculde);
 
     // 使用 BackupManager 的智能数据恢复功能
-    let docker_dir = std::path::Path::new("./docker");
+    let docker_dir = docker::get_docker_work_dir();
     match app
         .backup_manager
         .restore_data_from_backup_with_exculde(
             backup_id,
-            docker_dir,
+            &docker_dir,
             auto_start_service,
             dirs_to_exculde,
         )
@@ -854,20 +1223,7 @@ async fn run_rollback_with_exculde(
         Ok(_) => {
             info!("✅ 智能数据恢复完成");
 
-            // 设置正确的权限
-            let mysql_data_dir = docker_dir.join("data/mysql");
-            if mysql_data_dir.exists() {
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::PermissionsExt;
-                    let permissions = std::fs::Permissions::from_mode(0o775);
-                    if let Err(e) = std::fs::set_permissions(&mysql_data_dir, permissions) {
-                        warn!("⚠️ 设置MySQL权限失败: {}", e);
-                    } else {
-                        info!("🔒 已设置MySQL数据目录权限为775");
-                    }
-                }
-            }
+            restore_permissions(&app.backup_manager, backup_id, &docker_dir).await;
 
             info!("💡 数据恢复说明:");
             info!("   ✅ 所有数据库数据已恢复");
@@ -905,7 +1261,7 @@ async fn run_data_directory_only_rollback(
     info!("   🔧 将保留: app/ 目录, docker-compose.yml, .env 等配置文件");
 
     // 使用 BackupManager 的智能数据恢复功能
-    let docker_dir = std::path::Path::new("./docker");
+    let docker_dir = docker::get_docker_work_dir();
 
     // 如果有自定义配置文件，创建新的 DockerManager
     let backup_manager = if let Some(config_path) = config_file {
@@ -917,11 +1273,20 @@ async fn run_data_directory_only_rollback(
             client_core::container::DockerManager::new(config_path.clone(), env_file.clone())
                 .map_err(|e| anyhow::anyhow!("创建自定义DockerManager失败: {}", e))?,
         );
-        Arc::new(client_core::backup::BackupManager::new(
-            app.config.get_backup_dir(),
-            app.database.clone(),
-            custom_docker_manager,
-        )?)
+        Arc::new(
+            client_core::backup::BackupManager::new(
+                app.config.get_backup_dir(),
+                app.database.clone(),
+                custom_docker_manager,
+            )?
+            .with_storage(app.backup_manager.storage().cloned())
+            .with_hooks(app.config.backup.hooks.clone())
+            .with_snapshot(app.config.backup.snapshot.clone())
+            .with_size_guard(BackupSizeGuard::from_config(&app.config.backup))
+            .with_restore_ownership(OwnershipMapping::from_config(
+                &app.config.backup.restore_ownership,
+            )?),
+        )
     } else {
         app.backup_manager.clone()
     };
@@ -929,26 +1294,13 @@ async fn run_data_directory_only_rollback(
     //只恢复 data 目录,其他的数据不恢复
     let dir_to_restore = vec!["data"];
     match backup_manager
-        .restore_data_directory_only(backup_id, docker_dir, auto_start_service, &dir_to_restore)
+        .restore_data_directory_only(backup_id, &docker_dir, auto_start_service, &dir_to_restore)
         .await
     {
         Ok(_) => {
             info!("✅ 智能 data 目录恢复完成");
 
-            // 设置正确的权限
-            let mysql_data_dir = docker_dir.join("data/mysql");
-            if mysql_data_dir.exists() {
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::PermissionsExt;
-                    let permissions = std::fs::Permissions::from_mode(0o775);
-                    if let Err(e) = std::fs::set_permissions(&mysql_data_dir, permissions) {
-                        warn!("⚠️ 设置MySQL权限失败: {}", e);
-                    } else {
-                        info!("🔒 已设置MySQL数据目录权限为775");
-                    }
-                }
-            }
+            restore_permissions(&backup_manager, backup_id, &docker_dir).await;
 
             info!("💡 数据恢复说明:");
             info!("   ✅ 所有数据库数据已恢复");
@@ -1014,35 +1366,30 @@ async fn output_backups_as_json(app: &CliApp) -> Result<()> {
 
 /// 获取 JSON 格式的备份列表
 async fn get_backups_as_json(app: &CliApp) -> Result<JsonBackupListResponse> {
-    let backups = app.backup_manager.list_backups().await?;
+    let namespace = backup_namespace(app);
+    let mut backups = app.backup_manager.list_backups().await?;
+    backups.retain(|backup| backup.belongs_to_namespace(&namespace));
 
     let mut json_backups = Vec::new();
+    let available = app.backup_manager.available_backup_ids(&backups).await?;
 
     for backup in backups {
-        let backup_path = std::path::Path::new(&backup.file_path);
-        let file_exists = backup_path.exists();
+        let file_exists = available.contains(&backup.id);
 
-        // 获取文件大小
-        let file_size = if file_exists {
-            std::fs::metadata(&backup.file_path).ok().map(|m| m.len())
-        } else {
-            None
-        };
+        // 获取文件大小（只保存在备份存储中的备份没有本地文件）
+        let file_size = std::fs::metadata(&backup.file_path).ok().map(|m| m.len());
 
         // 备份类型转换为字符串
-        let backup_type_str = match backup.backup_type {
-            client_core::database::BackupType::Manual => "Manual",
-            client_core::database::BackupType::PreUpgrade => "PreUpgrade",
-        };
-
         json_backups.push(JsonBackupInfo {
             id: backup.id,
-            backup_type: backup_type_str.to_string(),
+            backup_type: format!("{:?}", backup.backup_type),
             created_at: backup.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
             service_version: backup.service_version,
             file_path: backup.file_path,
             file_size,
             file_exists,
+            namespace: backup.namespace,
+            verified: backup.verified,
         });
     }
 
@@ -1,8 +1,22 @@
 use crate::app::CliApp;
 use crate::cli::CacheCommand;
 use anyhow::Result;
+use client_core::config::AppConfig;
+use client_core::constants::upgrade::STALE_TEMP_ARTIFACT_HOURS;
+use client_core::database::UpgradeRecord;
+use client_core::download_cache::{
+    CacheEntry, CachedPackage, KeepVersion, link_upgrades, removable_packages, remove_package,
+    resolve_keep_versions, scan_download_cache,
+};
+use client_core::package_store::PackageStore;
+use client_core::temp_artifacts::{
+    TempArtifactRoots, TempSweepReport, find_temp_artifacts, sweep_temp_artifacts,
+};
+use client_core::upgrade_strategy::DownloadType;
+use client_core::version::Version;
 use std::fs;
 use std::path::Path;
+use std::time::{Duration, SystemTime};
 use tracing::{info, warn};
 use walkdir::WalkDir;
 
@@ -12,6 +26,17 @@ pub async fn handle_cache_command(app: &CliApp, cache_cmd: CacheCommand) -> Resu
         CacheCommand::Clear => clear_cache(app).await,
         CacheCommand::Status => show_cache_status(app).await,
         CacheCommand::CleanDownloads { keep } => clean_downloads(app, keep).await,
+        CacheCommand::Keep { versions, dry_run } => {
+            keep_versions(app, &versions, &app.config.get_docker_version()?, dry_run).await
+        }
+        CacheCommand::Gc {
+            keep,
+            temp_max_age_hours,
+        } => {
+            gc_package_store(app, keep).await?;
+            gc_temp_artifacts(&app.config, temp_max_age_hours);
+            Ok(())
+        }
     }
 }
 
@@ -19,7 +44,7 @@ pub async fn handle_cache_command(app: &CliApp, cache_cmd: CacheCommand) -> Resu
 async fn clear_cache(app: &CliApp) -> Result<()> {
     info!("🧹 开始清理缓存文件...");
 
-    let cache_dir = Path::new(&app.config.cache.cache_dir);
+    let cache_dir = app.config.get_cache_dir();
 
     if !cache_dir.exists() {
         info!("缓存目录不存在: {}", cache_dir.display());
@@ -30,7 +55,7 @@ async fn clear_cache(app: &CliApp) -> Result<()> {
     let mut total_size_freed = 0u64;
 
     // 遍历缓存目录
-    for entry in fs::read_dir(cache_dir)? {
+    for entry in fs::read_dir(&cache_dir)? {
         let entry = entry?;
         let path = entry.path();
 
@@ -82,8 +107,8 @@ async fn show_cache_status(app: &CliApp) -> Result<()> {
     info!("📊 缓存使用情况");
     info!("================");
 
-    let cache_dir = Path::new(&app.config.cache.cache_dir);
-    let download_dir = Path::new(&app.config.cache.download_dir);
+    let cache_dir = app.config.get_cache_dir();
+    let download_dir = app.config.get_download_dir();
 
     if !cache_dir.exists() {
         info!("缓存目录不存在: {}", cache_dir.display());
@@ -93,7 +118,7 @@ async fn show_cache_status(app: &CliApp) -> Result<()> {
     info!("缓存根目录: {}", cache_dir.display());
 
     // 计算总大小
-    match calculate_directory_size(cache_dir) {
+    match calculate_directory_size(&cache_dir) {
         Ok(total_size) => {
             info!("总大小: {:.2} MB", total_size as f64 / 1024.0 / 1024.0);
         }
@@ -102,43 +127,58 @@ async fn show_cache_status(app: &CliApp) -> Result<()> {
         }
     }
 
-    // 显示下载目录详情
+    // 显示下载目录详情及使用过各服务包的升级
     if download_dir.exists() {
         info!("\n📥 下载缓存详情:");
 
-        if let Ok(entries) = fs::read_dir(download_dir) {
-            let mut version_count = 0;
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        version_count += 1;
-                        let version_name = path.file_name().unwrap().to_string_lossy();
-
-                        match calculate_directory_size(&path) {
-                            Ok(size) => {
-                                info!(
-                                    "   版本 {}: {:.2} MB",
-                                    version_name,
-                                    size as f64 / 1024.0 / 1024.0
-                                );
-                            }
-                            Err(_) => {
-                                info!("   版本 {}: (计算大小失败)", version_name);
-                            }
-                        }
-                    }
-                }
+        let (entries, _) = load_cache_entries(app).await?;
+        for entry in &entries {
+            let package = &entry.package;
+            info!(
+                "   {} {}: {:.2} MB",
+                package.version_label(),
+                download_type_name(package),
+                package.size_bytes as f64 / 1024.0 / 1024.0
+            );
+            for record in &entry.upgrades {
+                info!(
+                    "      └ {} {} → {} ({}，{})",
+                    record.started_at.format("%Y-%m-%d %H:%M:%S"),
+                    record.from_version,
+                    record.to_version,
+                    record.upgrade_type.display_name(),
+                    record.status.display_name()
+                );
             }
+        }
 
-            if version_count == 0 {
-                info!("   (无版本缓存)");
-            }
+        if entries.is_empty() {
+            info!("   (无版本缓存)");
         }
     } else {
         info!("\n📥 下载缓存: 不存在");
     }
 
+    // 显示服务包存储详情
+    let store = PackageStore::new(app.config.get_package_store_dir());
+    if store.root().exists() {
+        let status = store.status()?;
+        info!("\n🗄️ 服务包存储: {}", store.root().display());
+        info!(
+            "   对象: {} 个, {:.2} MB",
+            status.objects,
+            status.total_bytes as f64 / 1024.0 / 1024.0
+        );
+        for (version, created_at, files) in &status.trees {
+            info!(
+                "   版本 {}: {} 个文件 (记录于 {})",
+                version,
+                files,
+                created_at.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+    }
+
     Ok(())
 }
 
@@ -146,7 +186,7 @@ async fn show_cache_status(app: &CliApp) -> Result<()> {
 async fn clean_downloads(app: &CliApp, keep: u32) -> Result<()> {
     info!("🧹 清理下载缓存 (保留最新 {} 个版本)...", keep);
 
-    let download_dir = Path::new(&app.config.cache.download_dir);
+    let download_dir = app.config.get_download_dir();
 
     if !download_dir.exists() {
         info!("下载缓存目录不存在: {}", download_dir.display());
@@ -156,7 +196,7 @@ async fn clean_downloads(app: &CliApp, keep: u32) -> Result<()> {
     // 收集所有版本目录
     let mut versions = Vec::new();
 
-    if let Ok(entries) = fs::read_dir(download_dir) {
+    if let Ok(entries) = fs::read_dir(&download_dir) {
         for entry in entries {
             if let Ok(entry) = entry {
                 let path = entry.path();
@@ -214,8 +254,218 @@ async fn clean_downloads(app: &CliApp, keep: u32) -> Result<()> {
     Ok(())
 }
 
+/// 只保留指定版本需要的服务包，删除下载缓存中的其他服务包
+async fn keep_versions(
+    app: &CliApp,
+    keep: &[KeepVersion],
+    current: &Version,
+    dry_run: bool,
+) -> Result<()> {
+    let rules: Vec<String> = keep.iter().map(|rule| rule.to_string()).collect();
+    info!("🧹 清理下载缓存 (保留: {})...", rules.join(", "));
+
+    let (entries, history) = load_cache_entries(app).await?;
+    if keep.contains(&KeepVersion::Previous)
+        && resolve_keep_versions(&[KeepVersion::Previous], current, &history).is_empty()
+    {
+        warn!("⚠️ 升级历史中没有当前版本之前的版本，previous 未匹配任何版本");
+    }
+    let versions = resolve_keep_versions(keep, current, &history);
+    let labels: Vec<String> = versions.iter().map(|v| v.to_string()).collect();
+    info!("保留版本: {}", labels.join(", "));
+
+    remove_unneeded_packages(&entries, &versions, dry_run);
+    Ok(())
+}
+
+/// 升级成功后按 `cache.keep_versions` 清理下载缓存，失败只记录警告
+///
+/// `current_version` 为升级后的版本
+pub async fn clean_downloads_after_upgrade(app: &CliApp, current_version: &str) {
+    let result = async {
+        let keep = app
+            .config
+            .cache
+            .keep_versions
+            .iter()
+            .map(|rule| rule.parse())
+            .collect::<Result<Vec<KeepVersion>>>()?;
+        keep_versions(app, &keep, &current_version.parse()?, false).await
+    }
+    .await;
+    if let Err(e) = result {
+        warn!("⚠️ 升级后清理下载缓存失败: {}", e);
+    }
+}
+
+/// 扫描下载缓存并关联升级历史，读取升级历史失败时不关联
+async fn load_cache_entries(app: &CliApp) -> Result<(Vec<CacheEntry>, Vec<UpgradeRecord>)> {
+    let packages = scan_download_cache(&app.config.get_download_dir())?;
+    let history = match app.database.get_upgrade_history(None).await {
+        Ok(history) => history,
+        Err(e) => {
+            warn!("读取升级历史失败: {}", e);
+            Vec::new()
+        }
+    };
+    Ok((link_upgrades(packages, &history), history))
+}
+
+fn remove_unneeded_packages(entries: &[CacheEntry], keep: &[Version], dry_run: bool) {
+    let removable = removable_packages(entries, keep);
+    let mut deleted_count = 0;
+    let mut freed_space = 0u64;
+    for package in &removable {
+        let label = format!(
+            "{} {}",
+            package.version_label(),
+            download_type_name(package)
+        );
+        if dry_run {
+            info!("将删除: {} ({})", label, package.dir.display());
+            continue;
+        }
+        match remove_package(package) {
+            Ok(()) => {
+                info!("已删除: {}", label);
+                deleted_count += 1;
+                freed_space += package.size_bytes;
+            }
+            Err(e) => warn!("删除服务包失败 {}: {}", label, e),
+        }
+    }
+
+    if dry_run {
+        info!(
+            "预演完成: 将删除 {} 个服务包 ({:.2} MB)",
+            removable.len(),
+            removable.iter().map(|p| p.size_bytes).sum::<u64>() as f64 / 1024.0 / 1024.0
+        );
+        return;
+    }
+    info!("🎉 下载缓存清理完成!");
+    info!("   删除服务包: {} 个", deleted_count);
+    info!(
+        "   释放空间: {:.2} MB",
+        freed_space as f64 / 1024.0 / 1024.0
+    );
+}
+
+fn download_type_name(package: &CachedPackage) -> &'static str {
+    match package.download_type {
+        DownloadType::Full => "全量包",
+        DownloadType::Patch => "补丁包",
+    }
+}
+
+/// 清理服务包存储：保留最近的版本和当前部署版本，删除不再引用的对象
+async fn gc_package_store(app: &CliApp, keep: usize) -> Result<()> {
+    info!("🧹 清理服务包存储 (保留最近 {} 个版本)...", keep);
+
+    let store = PackageStore::new(app.config.get_package_store_dir());
+    if !store.root().exists() {
+        info!("服务包存储不存在: {}", store.root().display());
+        return Ok(());
+    }
+
+    // 存储中的版本使用完整的四段式版本号
+    let current_version = app.config.get_docker_version()?.to_string();
+    let report = store.gc(keep, &[current_version])?;
+
+    for version in &report.removed_trees {
+        info!("已删除版本记录: {}", version);
+    }
+    info!("🎉 服务包存储清理完成!");
+    info!("   删除版本: {} 个", report.removed_trees.len());
+    info!("   删除对象: {} 个", report.removed_objects);
+    info!(
+        "   释放空间: {:.2} MB",
+        report.removed_bytes as f64 / 1024.0 / 1024.0
+    );
+
+    Ok(())
+}
+
+/// 清理遗留的临时文件，并列出未到清理时间或需要手动处理的文件
+fn gc_temp_artifacts(config: &AppConfig, max_age_hours: u64) {
+    info!("🧹 清理超过 {} 小时的遗留临时文件...", max_age_hours);
+
+    let report = sweep_stale_temp_files(config, max_age_hours);
+    for artifact in &report.removed {
+        info!(
+            "已删除{}: {}",
+            artifact.kind.display_name(),
+            artifact.path.display()
+        );
+    }
+    for artifact in &report.kept {
+        if artifact.kind.is_removable() {
+            info!(
+                "保留{} (未超过 {} 小时): {}",
+                artifact.kind.display_name(),
+                max_age_hours,
+                artifact.path.display()
+            );
+        } else {
+            info!(
+                "保留{} (可在确认不再回滚后手动删除, {:.2} MB): {}",
+                artifact.kind.display_name(),
+                artifact.size_bytes as f64 / 1024.0 / 1024.0,
+                artifact.path.display()
+            );
+        }
+    }
+    for (artifact, error) in &report.failed {
+        warn!("删除失败 {}: {}", artifact.path.display(), error);
+    }
+    info!("🎉 临时文件清理完成!");
+    info!("   删除: {} 个", report.removed.len());
+    info!(
+        "   释放空间: {:.2} MB",
+        report.removed_bytes() as f64 / 1024.0 / 1024.0
+    );
+}
+
+/// 启动时清理超过默认时间的遗留临时文件，仅在有删除或失败时输出日志
+pub fn sweep_stale_temp_artifacts(config: &AppConfig) {
+    let report = sweep_stale_temp_files(config, STALE_TEMP_ARTIFACT_HOURS);
+    if !report.removed.is_empty() {
+        info!(
+            "🧹 已清理 {} 个遗留临时文件 ({:.2} MB)",
+            report.removed.len(),
+            report.removed_bytes() as f64 / 1024.0 / 1024.0
+        );
+    }
+    for (artifact, error) in &report.failed {
+        warn!(
+            "清理遗留临时文件失败 {}: {}",
+            artifact.path.display(),
+            error
+        );
+    }
+}
+
+fn sweep_stale_temp_files(config: &AppConfig, max_age_hours: u64) -> TempSweepReport {
+    let roots = TempArtifactRoots::new(config.get_backup_dir(), config.get_download_dir());
+    let now = SystemTime::now();
+    let report = sweep_temp_artifacts(
+        find_temp_artifacts(&roots),
+        Duration::from_secs(max_age_hours * 3600),
+        now,
+        false,
+    );
+    if report
+        .kept
+        .iter()
+        .any(|artifact| artifact.modified_in_future(now))
+    {
+        warn!("⚠️ 部分临时文件的修改时间晚于当前系统时间，系统时间可能被调整过");
+    }
+    report
+}
+
 /// 计算目录大小
-fn calculate_directory_size(dir: &Path) -> Result<u64> {
+pub(crate) fn calculate_directory_size(dir: &Path) -> Result<u64> {
     let mut total_size = 0;
 
     for entry in WalkDir::new(dir) {
@@ -0,0 +1,108 @@
+use crate::app::CliApp;
+use crate::utils::prompt;
+use anyhow::Result;
+use client_core::changelog::{ReleaseNote, release_notes_between};
+use client_core::version::Version;
+use tracing::{info, warn};
+
+/// 更新说明每页显示的行数
+const PAGE_LINES: usize = 20;
+
+/// 获取当前版本到 `target_version` 之间各版本的更新说明
+///
+/// 版本列表接口不可用或没有目标版本的条目时，使用服务清单中目标版本的 `release_notes`
+async fn fetch_release_notes(
+    app: &CliApp,
+    target_version: &Version,
+    manifest_notes: Option<String>,
+) -> Vec<ReleaseNote> {
+    let current_version = app.config.get_docker_version().ok();
+    let mut notes = match app.api_client.get_docker_version_list().await {
+        Ok(list) => release_notes_between(list.versions, current_version.as_ref(), target_version),
+        Err(e) => {
+            warn!("⚠️ 获取版本列表失败: {}，只显示目标版本的更新说明", e);
+            Vec::new()
+        }
+    };
+    let has_target = notes.iter().any(|note| {
+        note.version
+            .parse::<Version>()
+            .is_ok_and(|v| v == *target_version)
+    });
+    if let Some(manifest_notes) = manifest_notes.filter(|_| !has_target) {
+        notes.push(ReleaseNote {
+            version: target_version.to_string(),
+            release_date: String::new(),
+            notes: manifest_notes,
+        });
+    }
+    notes
+}
+
+/// 分页显示更新说明，可以询问用户时每页暂停，输入 q 结束显示
+fn display_release_notes(notes: &[ReleaseNote]) -> Result<()> {
+    let lines: Vec<String> = notes.iter().flat_map(ReleaseNote::lines).collect();
+    let paged = prompt::can_prompt() && !prompt::assume_yes();
+    let pages = lines.len().div_ceil(PAGE_LINES);
+    for (index, page) in lines.chunks(PAGE_LINES).enumerate() {
+        for line in page {
+            info!("{}", line);
+        }
+        if paged && index + 1 < pages {
+            let answer = prompt::input(&format!(
+                "-- 第 {}/{} 页，按回车继续，输入 q 结束 --",
+                index + 1,
+                pages
+            ))?;
+            if answer.eq_ignore_ascii_case("q") {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `check-update check --changelog`：显示当前版本到最新版本之间各版本的更新说明
+pub async fn show_changelog(app: &CliApp) -> Result<()> {
+    let current_version = app.config.get_docker_version()?;
+    let manifest = app.api_client.get_enhanced_service_manifest().await?;
+    let target_version = manifest.version;
+
+    info!("📝 Docker服务更新说明");
+    info!("========================");
+    info!("   当前版本: {}", current_version);
+    info!("   最新版本: {}", target_version);
+
+    let notes = fetch_release_notes(app, &target_version, Some(manifest.release_notes)).await;
+    if notes.is_empty() || current_version >= target_version {
+        info!("✅ 当前已是最新版本，没有新的更新说明");
+        return Ok(());
+    }
+    display_release_notes(&notes)
+}
+
+/// 升级前展示当前版本到目标版本之间的更新说明并请求确认，用户取消时返回 false
+///
+/// 指定 `--yes` 或无法询问用户（定时任务、图形界面）时不展示，直接继续升级
+pub async fn confirm_release_notes(
+    app: &CliApp,
+    target_version: &Version,
+    manifest_notes: Option<String>,
+) -> Result<bool> {
+    if prompt::assume_yes() || !prompt::can_prompt() {
+        return Ok(true);
+    }
+    let notes = fetch_release_notes(app, target_version, manifest_notes).await;
+    if notes.is_empty() {
+        return Ok(true);
+    }
+
+    info!(
+        "📝 {} -> {} 的更新说明（共 {} 个版本）:",
+        app.config.get_docker_versions(),
+        target_version,
+        notes.len()
+    );
+    display_release_notes(&notes)?;
+    prompt::confirm(&format!("继续升级到 {target_version}?"))
+}
@@ -14,12 +14,21 @@ pub const VERSION_API_BASE_URL: &str = "https://api-version.nuwax.com";
 //cli 命令工具请求的地址
 pub const CLI_API_URL_PATH: &str = "/api/v1/cli/versions/latest.json";
 
+/// `check-update check` 退出码：已是最新版本
+pub const EXIT_UP_TO_DATE: i32 = 0;
+/// `check-update check` 退出码：有新的主版本或次版本可用
+pub const EXIT_UPDATE_AVAILABLE: i32 = 10;
+/// `check-update check` 退出码：只有修订号更新（补丁版本）可用
+pub const EXIT_PATCH_AVAILABLE: i32 = 11;
+
 /// 获取完整的 CLI API URL
 pub fn get_cli_api_url() -> String {
     format!("{VERSION_API_BASE_URL}{CLI_API_URL_PATH}")
 }
 
 use crate::cli::CheckUpdateCommand;
+use crate::utils::network_diagnostics::report_connectivity;
+use client_core::connectivity::ProbeKind;
 
 /// GitHub Release API 响应结构
 #[derive(Debug, Deserialize)]
@@ -333,6 +342,28 @@ pub fn compare_versions(current: &str, latest: &str) -> std::cmp::Ordering {
     current_parts.cmp(&latest_parts)
 }
 
+/// 按版本差异计算 `check-update check` 的退出码
+///
+/// 主版本号和次版本号相同、只有修订号更新时视为补丁版本
+pub fn update_exit_code(version_info: &VersionInfo) -> i32 {
+    if !version_info.is_update_available {
+        return EXIT_UP_TO_DATE;
+    }
+
+    let major_minor = |v: &str| -> Vec<u32> {
+        v.trim_start_matches('v')
+            .split('.')
+            .take(2)
+            .map(|s| s.parse::<u32>().unwrap_or(0))
+            .collect()
+    };
+    if major_minor(&version_info.current_version) == major_minor(&version_info.latest_version) {
+        EXIT_PATCH_AVAILABLE
+    } else {
+        EXIT_UPDATE_AVAILABLE
+    }
+}
+
 /// 检查更新
 pub async fn check_for_updates() -> Result<VersionInfo> {
     let current_version = get_current_version();
@@ -719,12 +750,19 @@ fn find_executable_in_dir(dir: &PathBuf) -> Result<PathBuf> {
 /// 处理 check-update 命令
 pub async fn handle_check_update_command(command: CheckUpdateCommand) -> Result<()> {
     match command {
-        CheckUpdateCommand::Check => {
+        CheckUpdateCommand::Check { quiet, .. } => {
             info!("🔍 正在检查 Nuwax Cli  更新...");
 
             match check_for_updates().await {
                 Ok(version_info) => {
-                    display_version_info(&version_info);
+                    if !quiet {
+                        display_version_info(&version_info);
+                    }
+                    // 通过退出码返回检查结果，便于 cron 和监控脚本判断
+                    let code = update_exit_code(&version_info);
+                    if code != EXIT_UP_TO_DATE {
+                        return Err(super::CommandExitCode(code).into());
+                    }
                 }
                 Err(e) => {
                     warn!("❌ 检查更新失败: {}", e);
@@ -734,6 +772,9 @@ pub async fn handle_check_update_command(command: CheckUpdateCommand) -> Result<
                     info!("   - 版本检查服务器暂时不可用");
                     info!("   - GitHub API 暂时不可用");
                     info!("   - 项目尚未发布任何版本");
+                    if !quiet {
+                        report_connectivity(VERSION_API_BASE_URL, ProbeKind::Api).await;
+                    }
                     return Err(e);
                 }
             }
@@ -792,10 +833,14 @@ pub async fn handle_check_update_command(command: CheckUpdateCommand) -> Result<
                     info!("   - 检查网络连接");
                     info!("   - 确保有足够的磁盘空间");
                     info!("   - 以管理员权限运行");
+                    report_connectivity(&download_url, ProbeKind::Download).await;
                     return Err(e);
                 }
             }
         }
+
+        // 需要服务配置，已在 app.rs 中处理
+        CheckUpdateCommand::Plan { .. } => unreachable!(),
     }
 
     Ok(())
@@ -811,3 +856,43 @@ async fn get_version_download_url(version: &str) -> Result<String> {
         .download_url
         .ok_or_else(|| anyhow::anyhow!("未找到版本 {} 适合当前平台的下载链接", version))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version_info(current: &str, latest: &str) -> VersionInfo {
+        VersionInfo {
+            current_version: current.to_string(),
+            latest_version: latest.to_string(),
+            is_update_available: compare_versions(current, latest) == std::cmp::Ordering::Less,
+            release_notes: String::new(),
+            download_url: None,
+            published_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_update_exit_code() {
+        assert_eq!(
+            update_exit_code(&version_info("v1.2.3", "v1.2.3")),
+            EXIT_UP_TO_DATE
+        );
+        assert_eq!(
+            update_exit_code(&version_info("v1.2.3", "1.2.0")),
+            EXIT_UP_TO_DATE
+        );
+        assert_eq!(
+            update_exit_code(&version_info("v1.2.3", "v1.2.4")),
+            EXIT_PATCH_AVAILABLE
+        );
+        assert_eq!(
+            update_exit_code(&version_info("v1.2.3", "v1.3.0")),
+            EXIT_UPDATE_AVAILABLE
+        );
+        assert_eq!(
+            update_exit_code(&version_info("v1.2.3", "v2.0.0")),
+            EXIT_UPDATE_AVAILABLE
+        );
+    }
+}
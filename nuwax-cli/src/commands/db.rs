@@ -0,0 +1,339 @@
+use crate::app::CliApp;
+use crate::cli::DbCommand;
+use crate::utils::prompt;
+use anyhow::{Context, Result};
+use client_core::constants::docker;
+use client_core::container::DockerManager;
+use client_core::mysql_dump::MySqlDump;
+use client_core::mysql_executor::{
+    MySqlConfig, MySqlExecutor, ScriptMode, StatementOutput, causes_implicit_commit,
+    split_sql_statements,
+};
+use client_core::mysql_readiness::MYSQL_SERVICE;
+use client_core::postgres_executor::detect_database;
+use client_core::sql_diff::SqlDialect;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use tracing::{debug, info, warn};
+
+/// 语句预览的最大字符数
+const STATEMENT_PREVIEW_CHARS: usize = 80;
+
+/// 导出和导入时每处理这么多字节输出一次进度
+const PROGRESS_LOG_BYTES: u64 = 50 * 1024 * 1024;
+
+/// 处理数据库命令
+pub async fn handle_db_command(app: &CliApp, cmd: DbCommand) -> Result<()> {
+    match cmd {
+        DbCommand::Exec {
+            file,
+            dry_run,
+            transaction,
+            output,
+        } => {
+            let mode = if dry_run {
+                ScriptMode::DryRun
+            } else if transaction {
+                ScriptMode::Transaction
+            } else {
+                ScriptMode::Autocommit
+            };
+            run_db_exec(app, &file, mode, output.as_deref()).await
+        }
+        DbCommand::Dump { out, force } => run_db_dump(app, &out, force).await,
+        DbCommand::Restore { file } => run_db_restore(app, &file).await,
+    }
+}
+
+/// 确认部署的数据库是 MySQL，返回数据库服务名
+fn resolve_mysql_service(app: &CliApp, docker_manager: &DockerManager) -> Result<String> {
+    match detect_database(docker_manager, app.config.docker.sql_dialect) {
+        Ok((SqlDialect::Postgres, _)) => Err(anyhow::anyhow!(
+            "db 命令目前只支持 MySQL，PostgreSQL 请使用 docker exec 进入容器执行 psql / pg_dump"
+        )),
+        Ok((_, service)) => Ok(service),
+        Err(e) => {
+            debug!("无法确定数据库服务，按 MySQL 处理: {}", e);
+            Ok(MYSQL_SERVICE.to_string())
+        }
+    }
+}
+
+/// 从 docker-compose.yml 读取 MySQL 连接配置，与升级时执行差异SQL相同
+async fn load_mysql_config() -> Result<MySqlConfig> {
+    let compose_file = docker::get_compose_file_path();
+    let env_file = docker::get_env_file_path();
+    MySqlConfig::for_container(compose_file.to_str(), env_file.to_str()).await
+}
+
+/// 在已部署的 MySQL 中执行SQL文件，连接方式与升级时执行差异SQL相同
+async fn run_db_exec(
+    app: &CliApp,
+    file: &Path,
+    mode: ScriptMode,
+    output: Option<&Path>,
+) -> Result<()> {
+    let sql =
+        fs::read_to_string(file).with_context(|| format!("无法读取SQL文件: {}", file.display()))?;
+    let statements = split_sql_statements(&sql);
+    if statements.is_empty() {
+        return Err(anyhow::anyhow!(
+            "SQL文件中没有可执行的语句: {}",
+            file.display()
+        ));
+    }
+
+    resolve_mysql_service(app, &app.docker_manager)?;
+    let config = load_mysql_config().await?;
+    let target = format!(
+        "{}@{}:{}/{}",
+        config.user, config.host, config.port, config.database
+    );
+    let executor = MySqlExecutor::new(config);
+    executor
+        .test_connection()
+        .await
+        .with_context(|| format!("无法连接到 MySQL ({target})，请确认服务已启动"))?;
+
+    info!("🗄️ 目标数据库: {}", target);
+    info!(
+        "📄 SQL文件: {} ({} 条语句)",
+        file.display(),
+        statements.len()
+    );
+    for (idx, statement) in statements.iter().enumerate() {
+        info!("   [{}] {}", idx + 1, statement_preview(statement));
+    }
+    let ddl_count = statements
+        .iter()
+        .filter(|statement| causes_implicit_commit(statement))
+        .count();
+    match mode {
+        ScriptMode::DryRun => {
+            info!("🧪 预演模式：语句在事务中执行后回滚，不修改数据");
+            if ddl_count > 0 {
+                warn!("⚠️ {} 条 DDL 等会隐式提交的语句将被跳过", ddl_count);
+            }
+        }
+        ScriptMode::Transaction if ddl_count > 0 => {
+            warn!(
+                "⚠️ 包含 {} 条 DDL 等会隐式提交的语句，这些语句及之前的修改无法回滚",
+                ddl_count
+            );
+        }
+        ScriptMode::Transaction => info!("🔒 所有语句在单个事务中执行，失败时回滚"),
+        ScriptMode::Autocommit => {
+            info!("⚠️ 语句逐条提交，失败时已执行的语句不会回滚（可使用 --transaction）")
+        }
+    }
+
+    if mode != ScriptMode::DryRun && !prompt::confirm("确认在数据库中执行以上语句?")? {
+        info!("已取消执行");
+        return Ok(());
+    }
+
+    let start = Instant::now();
+    let total = statements.len();
+    let mut report = String::new();
+    let mut affected_rows = 0;
+    let result = executor
+        .execute_script(&statements, mode, |idx, statement, outcome| {
+            affected_rows += outcome.affected_rows;
+            let summary = statement_summary(outcome);
+            info!(
+                "[{}/{}] ✅ {} ({})",
+                idx + 1,
+                total,
+                statement_preview(statement),
+                summary
+            );
+            print_result_set(outcome);
+            append_report(&mut report, idx, statement, outcome, &summary);
+        })
+        .await;
+
+    if let Some(output) = output {
+        fs::write(output, &report)
+            .with_context(|| format!("无法写入输出文件: {}", output.display()))?;
+        info!("📝 执行结果已保存到: {}", output.display());
+    }
+    result?;
+
+    let elapsed = start.elapsed().as_secs_f64();
+    if mode == ScriptMode::DryRun {
+        info!(
+            "✅ 预演完成，共影响 {} 行，所有修改已回滚 (耗时 {:.2} 秒)",
+            affected_rows, elapsed
+        );
+    } else {
+        info!(
+            "✅ 执行完成: {} 条语句，共影响 {} 行 (耗时 {:.2} 秒)",
+            total, affected_rows, elapsed
+        );
+    }
+    Ok(())
+}
+
+/// 导出数据库到文件
+async fn run_db_dump(app: &CliApp, out: &Path, force: bool) -> Result<()> {
+    if out.exists() && !force {
+        return Err(anyhow::anyhow!(
+            "文件已存在: {}，使用 --force 覆盖",
+            out.display()
+        ));
+    }
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let docker_manager = app.docker_manager.as_ref().clone();
+    let service = resolve_mysql_service(app, &docker_manager)?;
+    let config = load_mysql_config().await?;
+    info!(
+        "📤 开始导出数据库 {} (服务 {}) 到 {}",
+        config.database,
+        service,
+        out.display()
+    );
+    let dump = MySqlDump::new(docker_manager, service, config);
+
+    let start = Instant::now();
+    let mut next_log = PROGRESS_LOG_BYTES;
+    let sql_bytes = dump
+        .dump_to_file(out, |bytes| {
+            if bytes >= next_log {
+                info!("   已导出 {:.0} MB", bytes_to_mb(bytes));
+                next_log += PROGRESS_LOG_BYTES;
+            }
+        })
+        .await?;
+
+    info!(
+        "✅ 数据库导出完成: {} (SQL {:.1} MB, 文件 {:.1} MB, 耗时 {:.1} 秒)",
+        out.display(),
+        bytes_to_mb(sql_bytes),
+        bytes_to_mb(fs::metadata(out)?.len()),
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+/// 从导出文件恢复数据库，导入前等待用户确认
+async fn run_db_restore(app: &CliApp, file: &Path) -> Result<()> {
+    restore_database_dump(app, file, true).await
+}
+
+/// 从导出文件恢复数据库，`confirm` 为 false 时调用方已确认过
+pub(crate) async fn restore_database_dump(app: &CliApp, file: &Path, confirm: bool) -> Result<()> {
+    let metadata =
+        fs::metadata(file).with_context(|| format!("备份文件不存在: {}", file.display()))?;
+    let docker_manager = app.docker_manager.as_ref().clone();
+    let service = resolve_mysql_service(app, &docker_manager)?;
+    let config = load_mysql_config().await?;
+
+    let modified: chrono::DateTime<chrono::Local> = metadata.modified()?.into();
+    info!(
+        "📥 备份文件: {} ({:.1} MB, 修改于 {})",
+        file.display(),
+        bytes_to_mb(metadata.len()),
+        modified.format("%Y-%m-%d %H:%M:%S")
+    );
+    info!("🗄️ 目标数据库: {} (服务 {})", config.database, service);
+    warn!("⚠️ 备份中的表会被删除并重建，其中的当前数据将被覆盖");
+    warn!("💡 如需保留当前数据，请先执行 nuwax-cli db dump --out <文件>");
+    let message = format!("确认将备份导入数据库 {}?", config.database);
+    if confirm && !prompt::confirm(&message)? {
+        info!("已取消恢复");
+        return Ok(());
+    }
+
+    let dump = MySqlDump::new(docker_manager, service, config);
+    let start = Instant::now();
+    let mut next_log = PROGRESS_LOG_BYTES;
+    let sql_bytes = dump
+        .restore_from_file(file, |bytes| {
+            if bytes >= next_log {
+                info!("   已导入 {:.0} MB", bytes_to_mb(bytes));
+                next_log += PROGRESS_LOG_BYTES;
+            }
+        })
+        .await?;
+
+    info!(
+        "✅ 数据库恢复完成: 导入 {:.1} MB SQL (耗时 {:.1} 秒)",
+        bytes_to_mb(sql_bytes),
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+fn bytes_to_mb(bytes: u64) -> f64 {
+    bytes as f64 / 1024.0 / 1024.0
+}
+
+/// 单行显示的语句，过长时截断
+fn statement_preview(statement: &str) -> String {
+    let statement = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+    if statement.chars().count() <= STATEMENT_PREVIEW_CHARS {
+        return statement;
+    }
+    let truncated: String = statement.chars().take(STATEMENT_PREVIEW_CHARS).collect();
+    format!("{truncated}...")
+}
+
+fn statement_summary(output: &StatementOutput) -> String {
+    if output.skipped {
+        "预演时跳过".to_string()
+    } else if !output.columns.is_empty() {
+        format!("{} 行结果", output.rows.len())
+    } else {
+        format!("影响 {} 行", output.affected_rows)
+    }
+}
+
+/// 以制表符分隔输出查询结果
+fn print_result_set(output: &StatementOutput) {
+    if output.columns.is_empty() {
+        return;
+    }
+    println!("{}", output.columns.join("\t"));
+    for row in &output.rows {
+        println!("{}", row.join("\t"));
+    }
+}
+
+fn append_report(
+    report: &mut String,
+    idx: usize,
+    statement: &str,
+    output: &StatementOutput,
+    summary: &str,
+) {
+    let _ = writeln!(report, "-- [{}] {}", idx + 1, statement);
+    if !output.columns.is_empty() {
+        let _ = writeln!(report, "{}", output.columns.join("\t"));
+        for row in &output.rows {
+            let _ = writeln!(report, "{}", row.join("\t"));
+        }
+    }
+    let _ = writeln!(report, "-- {summary}\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_preview() {
+        assert_eq!(
+            statement_preview("UPDATE users\n    SET name = 'a'\n WHERE id = 1"),
+            "UPDATE users SET name = 'a' WHERE id = 1"
+        );
+        let long = format!("SELECT '{}'", "数".repeat(100));
+        let preview = statement_preview(&long);
+        assert!(preview.ends_with("..."));
+        assert_eq!(preview.chars().count(), STATEMENT_PREVIEW_CHARS + 3);
+    }
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use client_core::sql_diff::generate_schema_diff;
+use client_core::sql_diff::{SqlDialect, generate_schema_diff_with_dialect};
 use std::fs;
 use std::path::PathBuf;
 use tracing::info;
@@ -11,6 +11,7 @@ pub async fn run_diff_sql(
     old_version: Option<String>,
     new_version: Option<String>,
     output_file: String,
+    dialect: SqlDialect,
 ) -> Result<()> {
     info!("🔄 开始SQL文件差异对比...");
     info!("📄 旧版本SQL: {}", old_sql_path.display());
@@ -47,7 +48,8 @@ pub async fn run_diff_sql(
 
     // 生成差异SQL
     info!("🔍 正在分析SQL差异...");
-    let (diff_sql, description) = generate_schema_diff(
+    let (diff_sql, description) = generate_schema_diff_with_dialect(
+        dialect,
         Some(&old_sql_content),
         &new_sql_content,
         Some(from_version),
@@ -102,10 +104,16 @@ pub async fn run_diff_sql(
     info!("   3. 确认无误后在生产环境执行");
 
     if !meaningful_lines.is_empty() {
-        info!(
-            "   4. 执行示例: mysql -u username -p database_name < {}",
-            output_file
-        );
+        match dialect {
+            SqlDialect::Mysql => info!(
+                "   4. 执行示例: mysql -u username -p database_name < {}",
+                output_file
+            ),
+            SqlDialect::Postgres => info!(
+                "   4. 执行示例: psql -U username -d database_name --single-transaction -f {}",
+                output_file
+            ),
+        }
     }
 
     info!("✅ SQL差异对比完成");
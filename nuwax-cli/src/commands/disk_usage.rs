@@ -0,0 +1,363 @@
+use crate::app::CliApp;
+use crate::commands::cache::calculate_directory_size;
+use anyhow::Result;
+use client_core::constants::docker;
+use client_core::fs_probe::available_space;
+use client_core::temp_artifacts::{TempArtifactRoots, find_temp_artifacts};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// 默认的最小可用空间（GB），低于该值时发出警告
+pub const DEFAULT_MIN_FREE_GB: f64 = 10.0;
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// 磁盘占用类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageCategory {
+    /// docker/data 下的子目录
+    Data,
+    /// 备份存储目录
+    Backups,
+    /// 下载缓存
+    DownloadCache,
+    /// 服务包内容寻址存储
+    PackageStore,
+    /// 可安全清理的临时文件（解压临时目录、暂存目录、临时数据备份、未完成的下载和归档）
+    Trash,
+    /// 项目使用的 Docker 镜像
+    DockerImage,
+    /// 项目的 Docker 数据卷
+    DockerVolume,
+}
+
+impl UsageCategory {
+    fn label(&self) -> &'static str {
+        match self {
+            UsageCategory::Data => "数据目录",
+            UsageCategory::Backups => "备份",
+            UsageCategory::DownloadCache => "下载缓存",
+            UsageCategory::PackageStore => "服务包存储",
+            UsageCategory::Trash => "临时文件",
+            UsageCategory::DockerImage => "Docker 镜像",
+            UsageCategory::DockerVolume => "Docker 数据卷",
+        }
+    }
+}
+
+/// 单项磁盘占用
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageEntry {
+    pub category: UsageCategory,
+    pub name: String,
+    /// 本地路径，Docker 资源为空
+    pub path: Option<PathBuf>,
+    pub size_bytes: u64,
+}
+
+/// 磁盘占用报告
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskUsageReport {
+    pub entries: Vec<UsageEntry>,
+    pub total_bytes: u64,
+    /// 工作目录所在文件系统的可用空间，无法获取时为空
+    pub available_bytes: Option<u64>,
+    /// 获取 Docker 镜像和数据卷占用失败的原因
+    pub docker_error: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// 统计磁盘占用并输出报告
+///
+/// `warn_above_gb` 为单项占用的警告阈值，`min_free_gb` 为可用空间的警告阈值
+pub async fn run_disk_usage(
+    app: &CliApp,
+    json: bool,
+    warn_above_gb: Option<f64>,
+    min_free_gb: f64,
+) -> Result<()> {
+    let mut entries = collect_local_usage(app);
+
+    let mut docker_error = None;
+    match app.docker_manager.project_disk_usage().await {
+        Ok(usage) => {
+            entries.extend(usage.images.into_iter().map(|image| UsageEntry {
+                category: UsageCategory::DockerImage,
+                name: image.name,
+                path: None,
+                size_bytes: image.size_bytes,
+            }));
+            entries.extend(usage.volumes.into_iter().map(|volume| UsageEntry {
+                category: UsageCategory::DockerVolume,
+                name: volume.name,
+                path: None,
+                size_bytes: volume.size_bytes,
+            }));
+        }
+        Err(e) => {
+            warn!("⚠️ 获取 Docker 镜像和数据卷占用失败: {}", e);
+            docker_error = Some(e.to_string());
+        }
+    }
+
+    let available_bytes = available_space(Path::new("."));
+    let warnings = threshold_warnings(
+        &entries,
+        available_bytes,
+        warn_above_gb.map(gb_to_bytes),
+        gb_to_bytes(min_free_gb),
+    );
+    let report = DiskUsageReport {
+        total_bytes: entries.iter().map(|entry| entry.size_bytes).sum(),
+        entries,
+        available_bytes,
+        docker_error,
+        warnings,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+/// 统计本地目录的占用，不存在的目录跳过
+fn collect_local_usage(app: &CliApp) -> Vec<UsageEntry> {
+    let mut entries = Vec::new();
+
+    // docker/data 按子目录统计
+    let data_dir = docker::get_data_dir_path();
+    if let Ok(read_dir) = fs::read_dir(&data_dir) {
+        for entry in read_dir.flatten() {
+            let name = format!(
+                "{}/{}",
+                docker::DATA_DIR_NAME,
+                entry.file_name().to_string_lossy()
+            );
+            entries.push(usage_entry(UsageCategory::Data, name, entry.path()));
+        }
+    }
+
+    let backup_dir = app.config.get_backup_dir();
+    if backup_dir.exists() {
+        entries.push(usage_entry(
+            UsageCategory::Backups,
+            "backups".to_string(),
+            backup_dir.clone(),
+        ));
+    }
+
+    let download_dir = app.config.get_download_dir();
+    if download_dir.exists() {
+        entries.push(usage_entry(
+            UsageCategory::DownloadCache,
+            "downloads".to_string(),
+            download_dir.clone(),
+        ));
+    }
+
+    let store_dir = app.config.get_package_store_dir();
+    if store_dir.exists() {
+        entries.push(usage_entry(
+            UsageCategory::PackageStore,
+            "package-store".to_string(),
+            store_dir,
+        ));
+    }
+
+    for path in trash_paths(&TempArtifactRoots::new(backup_dir, download_dir)) {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        entries.push(usage_entry(UsageCategory::Trash, name, path));
+    }
+
+    entries
+}
+
+/// 可安全清理的临时文件，不包括升级回滚需要的SQL目录
+fn trash_paths(roots: &TempArtifactRoots) -> Vec<PathBuf> {
+    find_temp_artifacts(roots)
+        .into_iter()
+        .filter(|artifact| artifact.kind.is_removable())
+        .map(|artifact| artifact.path)
+        .collect()
+}
+
+fn usage_entry(category: UsageCategory, name: String, path: PathBuf) -> UsageEntry {
+    let size_bytes = if path.is_dir() {
+        calculate_directory_size(&path).unwrap_or(0)
+    } else {
+        path.metadata().map(|metadata| metadata.len()).unwrap_or(0)
+    };
+    UsageEntry {
+        category,
+        name,
+        path: Some(path),
+        size_bytes,
+    }
+}
+
+/// 根据阈值生成警告：单项占用超过 `warn_above` 或可用空间低于 `min_free`
+fn threshold_warnings(
+    entries: &[UsageEntry],
+    available_bytes: Option<u64>,
+    warn_above: Option<u64>,
+    min_free: u64,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if let Some(available) = available_bytes {
+        if available < min_free {
+            warnings.push(format!(
+                "可用空间仅剩 {}，低于 {}，升级可能因空间不足失败",
+                format_size(available),
+                format_size(min_free)
+            ));
+        }
+    }
+
+    if let Some(limit) = warn_above {
+        for entry in entries.iter().filter(|entry| entry.size_bytes > limit) {
+            warnings.push(format!(
+                "{} {} 占用 {}，超过 {}",
+                entry.category.label(),
+                entry.name,
+                format_size(entry.size_bytes),
+                format_size(limit)
+            ));
+        }
+    }
+
+    warnings
+}
+
+fn print_report(report: &DiskUsageReport) {
+    info!("💽 磁盘占用:");
+
+    let mut category = None;
+    for entry in &report.entries {
+        if category != Some(entry.category) {
+            category = Some(entry.category);
+            info!("\n{}:", entry.category.label());
+        }
+        match &entry.path {
+            Some(path) => info!(
+                "   {:>10}  {} ({})",
+                format_size(entry.size_bytes),
+                entry.name,
+                path.display()
+            ),
+            None => info!("   {:>10}  {}", format_size(entry.size_bytes), entry.name),
+        }
+    }
+
+    info!("\n合计: {}", format_size(report.total_bytes));
+    if let Some(available) = report.available_bytes {
+        info!("可用空间: {}", format_size(available));
+    }
+    if let Some(error) = &report.docker_error {
+        info!("Docker 镜像和数据卷未统计: {}", error);
+    }
+    if report
+        .entries
+        .iter()
+        .any(|entry| entry.category == UsageCategory::Trash)
+    {
+        info!("💡 临时文件在确认没有正在进行的升级或备份后可以手动删除");
+    }
+
+    for warning in &report.warnings {
+        warn!("⚠️ {}", warning);
+    }
+}
+
+fn gb_to_bytes(gb: f64) -> u64 {
+    (gb.max(0.0) * GB) as u64
+}
+
+fn format_size(bytes: u64) -> String {
+    let bytes = bytes as f64;
+    if bytes >= GB {
+        format!("{:.2} GB", bytes / GB)
+    } else if bytes >= 1024.0 * 1024.0 {
+        format!("{:.2} MB", bytes / 1024.0 / 1024.0)
+    } else if bytes >= 1024.0 {
+        format!("{:.2} KB", bytes / 1024.0)
+    } else {
+        format!("{bytes} B")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(category: UsageCategory, name: &str, size_bytes: u64) -> UsageEntry {
+        UsageEntry {
+            category,
+            name: name.to_string(),
+            path: None,
+            size_bytes,
+        }
+    }
+
+    #[test]
+    fn test_threshold_warnings() {
+        let entries = vec![
+            entry(UsageCategory::Data, "data/mysql", 30 * 1024 * 1024 * 1024),
+            entry(UsageCategory::Backups, "backups", 1024),
+            entry(UsageCategory::Trash, ".staging_1", 12 * 1024 * 1024 * 1024),
+        ];
+
+        let warnings = threshold_warnings(&entries, Some(u64::MAX), None, gb_to_bytes(10.0));
+        assert!(warnings.is_empty());
+
+        let warnings = threshold_warnings(
+            &entries,
+            Some(gb_to_bytes(2.0)),
+            Some(gb_to_bytes(10.0)),
+            gb_to_bytes(10.0),
+        );
+        assert_eq!(warnings.len(), 3, "{warnings:?}");
+        assert!(warnings[0].contains("可用空间"));
+        assert!(warnings[1].contains("data/mysql"));
+        assert!(warnings[2].contains(".staging_1"));
+    }
+
+    #[test]
+    fn test_trash_paths_match_prefixes() {
+        let backup_dir = tempfile::TempDir::new().unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let roots = TempArtifactRoots {
+            extract_dir: temp_dir.path().join("extract"),
+            sql_dir: temp_dir.path().join("temp_sql"),
+            backup_dir: backup_dir.path().to_path_buf(),
+            download_dir: temp_dir.path().join("downloads"),
+            work_dir: temp_dir.path().join("work"),
+            system_temp_dir: temp_dir.path().to_path_buf(),
+        };
+        fs::create_dir(&roots.sql_dir).unwrap();
+        fs::create_dir(backup_dir.path().join(".staging_2026-01-01")).unwrap();
+        fs::write(backup_dir.path().join("backup_manual_v1.tar.gz"), "").unwrap();
+        fs::create_dir(temp_dir.path().join("duck_data_backup_1700000000")).unwrap();
+        fs::create_dir(temp_dir.path().join("unrelated")).unwrap();
+
+        let names: Vec<String> = trash_paths(&roots)
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .collect();
+        assert!(names.contains(&".staging_2026-01-01".to_string()));
+        assert!(names.contains(&"duck_data_backup_1700000000".to_string()));
+        assert!(!names.contains(&"backup_manual_v1.tar.gz".to_string()));
+        assert!(!names.contains(&"unrelated".to_string()));
+        assert!(!names.contains(&"temp_sql".to_string()));
+    }
+}
@@ -1,11 +1,24 @@
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crate::app::CliApp;
 use crate::cli::DockerServiceCommand;
-use crate::docker_service::{ContainerStatus, DockerService};
+use crate::docker_service::health_check::HealthChecker;
+use crate::docker_service::permission_policy::PermissionPolicy;
+use crate::docker_service::{ContainerStatus, DockerService, HealthScope};
+use crate::utils::locked_files::LockedFileReport;
+use crate::utils::patch_conflicts::ConflictPolicy;
+use crate::utils::prompt;
 use anyhow::Result;
+use client_core::architecture::Architecture;
+use client_core::package_architecture::detect_package_architecture;
+use client_core::package_verification::{
+    ExpectedPackage, check_extraction_limits, verify_package_download,
+};
+use client_core::redact::redact_url;
 use client_core::upgrade_strategy::UpgradeStrategy;
-use tracing::{error, info, warn};
+use tracing::{debug, error, info, warn};
 
 /// 运行 Docker 服务相关命令的统一入口
 pub async fn run_docker_service_command(app: &CliApp, cmd: DockerServiceCommand) -> Result<()> {
@@ -14,6 +27,21 @@ pub async fn run_docker_service_command(app: &CliApp, cmd: DockerServiceCommand)
             info!("▶️  启动 Docker 服务...");
             start_docker_services(app, None, project).await
         }
+        DockerServiceCommand::Deploy {
+            preset: Some(preset),
+            project,
+        } => {
+            // 先保存预设，部署时按配置生成覆盖文件
+            let mut app = app.clone();
+            let config = super::preset::save_service_preset(app, Some(preset))?;
+            app.config = Arc::new(config);
+            info!("📐 服务规格预设已设置为 {}", preset);
+            deploy_docker_services(&app, None, None, project).await
+        }
+        DockerServiceCommand::Deploy {
+            preset: None,
+            project,
+        } => deploy_docker_services(app, None, None, project).await,
         DockerServiceCommand::Stop { project } => {
             info!("⏹️  停止 Docker 服务...");
             stop_docker_services(app, None, project).await
@@ -22,9 +50,14 @@ pub async fn run_docker_service_command(app: &CliApp, cmd: DockerServiceCommand)
             info!("🔄 重启 Docker 服务...");
             restart_docker_services(app, None, project).await
         }
-        DockerServiceCommand::Status { project } => {
+        DockerServiceCommand::Status {
+            project,
+            services,
+            exclude,
+        } => {
             info!("📊 检查 Docker 服务状态...");
-            check_docker_services_status_with_project(app, project).await
+            check_docker_services_status_scoped(app, project, &HealthScope::new(services, exclude))
+                .await
         }
         DockerServiceCommand::RestartContainer { container_name } => {
             info!("🔄 重启容器: {}", container_name);
@@ -65,13 +98,225 @@ pub async fn run_docker_service_command(app: &CliApp, cmd: DockerServiceCommand)
             info!("✅ 挂载目录检查完成");
             Ok(())
         }
+        DockerServiceCommand::Exec {
+            service,
+            command,
+            no_tty,
+            user,
+            project,
+        } => exec_in_service(app, &service, &command, no_tty, user, project).await,
+        DockerServiceCommand::FixPerms { check } => fix_permissions(check),
+        DockerServiceCommand::SetPort {
+            service,
+            port,
+            project,
+        } => super::ports::set_service_port(app, service, port, project).await,
+        DockerServiceCommand::MigrateProject { from, to } => {
+            info!("🚚 迁移 compose 项目: {} -> {}", from, to);
+            migrate_project(app, &from, &to).await
+        }
+        DockerServiceCommand::RelocateData { to, remove_source } => {
+            super::relocate_data::relocate_data(app, to, remove_source).await
+        }
+        DockerServiceCommand::CleanupOrphans { dry_run } => cleanup_orphans(app, dry_run).await,
+        DockerServiceCommand::Down { volumes, no_backup } => {
+            super::teardown::teardown(app, volumes, no_backup).await
+        }
+        DockerServiceCommand::ExportImages { out, force } => {
+            super::images::export_images(app, out, force).await
+        }
+        DockerServiceCommand::ImportImages { file } => {
+            super::images::import_images(app, &file).await
+        }
+        DockerServiceCommand::Preset(preset_cmd) => {
+            super::preset::handle_preset_command(app, preset_cmd).await
+        }
+    }
+}
+
+/// 把容器和命名数据卷迁移到新的 compose 项目名，并保存到 config.toml
+///
+/// 未配置备份命名空间时固定为原项目名，迁移前的备份仍属于当前项目
+async fn migrate_project(app: &CliApp, from: &str, to: &str) -> Result<()> {
+    let report = app.docker_manager.migrate_project(from, to).await?;
+
+    let mut config = app.config.as_ref().clone();
+    config.docker.project_name = Some(to.to_string());
+    if config.backup.namespace.is_none() {
+        config.backup.namespace = Some(from.to_string());
+        info!("🗂️ 备份命名空间保持为 {}", from);
+    }
+    config.save_to_file(&app.config_path)?;
+
+    info!(
+        "✅ 项目已迁移: {} -> {}，重建容器 {} 个",
+        report.from,
+        report.to,
+        report.containers.len()
+    );
+    for moved in &report.moved_volumes {
+        info!("   数据卷 {} -> {}", moved.from, moved.to);
+    }
+    for volume in &report.shared_volumes {
+        info!("   数据卷 {}（不含项目前缀，继续使用）", volume);
     }
+    info!("💡 已更新 config.toml 中的 docker.project_name，后续命令无需再指定 --project");
+    Ok(())
+}
+
+/// 停止并删除服务已从 compose 文件中删除的容器，数据卷保留
+async fn cleanup_orphans(app: &CliApp, dry_run: bool) -> Result<()> {
+    let orphans = app.docker_manager.find_orphan_containers().await?;
+    if orphans.is_empty() {
+        info!("✅ 没有孤立容器");
+        return Ok(());
+    }
+
+    info!("🔍 发现 {} 个孤立容器:", orphans.len());
+    for orphan in &orphans {
+        info!(
+            "  ⚫ {} (服务: {}，{}，镜像: {})",
+            orphan.name, orphan.service, orphan.state, orphan.image
+        );
+    }
+    if dry_run {
+        info!("💡 去掉 --dry-run 删除以上容器");
+        return Ok(());
+    }
+    if !prompt::confirm(&format!("停止并删除以上 {} 个容器?", orphans.len()))? {
+        info!("已取消");
+        return Ok(());
+    }
+
+    app.docker_manager
+        .remove_orphan_containers(&orphans)
+        .await?;
+    info!("✅ 已删除 {} 个孤立容器（数据卷已保留）", orphans.len());
+    Ok(())
+}
+
+/// 命令以非零退出码结束，进程应以相同的退出码退出
+#[derive(Debug)]
+pub struct CommandExitCode(pub i32);
+
+impl std::fmt::Display for CommandExitCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "命令退出码: {}", self.0)
+    }
+}
+
+impl std::error::Error for CommandExitCode {}
+
+/// 在compose服务对应的容器中执行命令，返回命令的退出码
+async fn exec_in_service(
+    app: &CliApp,
+    service: &str,
+    command: &[String],
+    no_tty: bool,
+    user: Option<String>,
+    project_name: Option<String>,
+) -> Result<()> {
+    let docker_manager = if let Some(project_name) = project_name {
+        std::sync::Arc::new(client_core::container::DockerManager::with_project(
+            client_core::constants::docker::get_compose_file_path(),
+            client_core::constants::docker::get_env_file_path(),
+            Some(project_name),
+        )?)
+    } else {
+        app.docker_manager.clone()
+    };
+    let container = HealthChecker::new(docker_manager)
+        .resolve_service_container(service)
+        .await?;
+
+    // 只有标准输入输出都是终端时才分配TTY，否则输出可被管道捕获
+    let tty = !no_tty && std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    let args = build_exec_args(&container, command, tty, user.as_deref());
+    info!("🐚 在容器 {} 中执行: {}", container, command.join(" "));
+
+    let status = tokio::process::Command::new("docker")
+        .args(&args)
+        .status()
+        .await
+        .map_err(|e| anyhow::anyhow!("执行 docker exec 失败: {e}"))?;
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(CommandExitCode(code).into()),
+        None => Err(anyhow::anyhow!("docker exec 被信号终止")),
+    }
+}
+
+/// 构造 docker exec 参数
+fn build_exec_args(
+    container: &str,
+    command: &[String],
+    tty: bool,
+    user: Option<&str>,
+) -> Vec<String> {
+    let mut args = vec!["exec".to_string(), "-i".to_string()];
+    if tty {
+        args.push("-t".to_string());
+    }
+    if let Some(user) = user {
+        args.push("--user".to_string());
+        args.push(user.to_string());
+    }
+    args.push(container.to_string());
+    args.extend(command.iter().cloned());
+    args
+}
+
+/// 按权限策略检查或修复工作目录
+fn fix_permissions(check_only: bool) -> Result<()> {
+    let work_dir = client_core::constants::docker::get_docker_work_dir();
+    let policy = PermissionPolicy::load(&work_dir)?;
+
+    if check_only {
+        info!("🔍 检查文件权限: {}", work_dir.display());
+        let deviations = policy.check(&work_dir)?;
+        if deviations.is_empty() {
+            info!("✅ 所有文件权限均符合策略");
+            return Ok(());
+        }
+        for deviation in &deviations {
+            warn!("   ⚠️ {}", deviation);
+        }
+        return Err(anyhow::anyhow!(
+            "发现 {} 个路径与权限策略不一致，运行 'nuwax-cli docker-service fix-perms' 进行修复",
+            deviations.len()
+        ));
+    }
+
+    info!("🔧 按权限策略修复: {}", work_dir.display());
+    let fixed = policy.apply(&work_dir)?;
+    for deviation in &fixed {
+        info!("   ✅ {}", deviation);
+    }
+    info!("✅ 权限修复完成，共修复 {} 个路径", fixed.len());
+    Ok(())
 }
 
 /// 部署 Docker 服务
+#[tracing::instrument(level = "trace", name = "phase.deploy", skip_all)]
 pub async fn deploy_docker_services(app: &CliApp, frontend_port: Option<u16>, config_file: Option<PathBuf>, project_name: Option<String>) -> Result<()> {
     info!("🚀 开始部署 Docker 服务...");
 
+    // 重新应用 config.toml 中的端口覆盖（服务包中的 .env / docker-compose.yml 会覆盖之前的修改）
+    let compose_path = config_file
+        .clone()
+        .unwrap_or_else(client_core::constants::docker::get_compose_file_path);
+    super::ports::apply_port_overrides(
+        &app.config.ports,
+        &compose_path,
+        &client_core::constants::docker::get_env_file_path(),
+    )?;
+    super::relocate_data::apply_data_dir_override(
+        &app.config,
+        &compose_path,
+        &client_core::constants::docker::get_env_file_path(),
+    )?;
+    super::preset::apply_service_preset(&app.config, &app.docker_manager, &compose_path).await?;
+
     // 如果指定了端口，先设置端口配置
     if let Some(port) = frontend_port {
         info!("🔧 配置frontend端口: {}", port);
@@ -148,6 +393,7 @@ pub async fn deploy_docker_services(app: &CliApp, frontend_port: Option<u16>, co
 }
 
 /// 启动 Docker 服务
+#[tracing::instrument(level = "trace", name = "phase.start", skip_all)]
 pub async fn start_docker_services(app: &CliApp, config_file: Option<PathBuf>, project_name: Option<String>) -> Result<()> {
     info!("▶️ 启动 Docker 服务...");
 
@@ -298,6 +544,15 @@ pub async fn check_docker_services_status(app: &CliApp) -> Result<()> {
 
 /// 检查 Docker 服务状态（支持项目名称）
 pub async fn check_docker_services_status_with_project(app: &CliApp, project_name: Option<String>) -> Result<()> {
+    check_docker_services_status_scoped(app, project_name, &HealthScope::default()).await
+}
+
+/// 按检查范围检查 Docker 服务状态，指定范围时范围内的服务不健康会返回错误
+pub async fn check_docker_services_status_scoped(
+    app: &CliApp,
+    project_name: Option<String>,
+    scope: &HealthScope,
+) -> Result<()> {
     info!("📊 检查 Docker 服务状态...");
 
     // 创建支持项目名称的 DockerService
@@ -314,13 +569,16 @@ pub async fn check_docker_services_status_with_project(app: &CliApp, project_nam
         DockerService::new(app.config.clone(), app.docker_manager.clone())?
     };
 
-    match docker_service_manager.health_check().await {
+    match docker_service_manager.health_check_scoped(scope).await {
         Ok(report) => {
             info!("=== Docker 服务状态报告 ===");
             info!(
                 "检查时间: {}",
                 report.check_time.format("%Y-%m-%d %H:%M:%S UTC")
             );
+            if !scope.is_all() {
+                info!("检查范围: {}", scope.describe());
+            }
             info!("整体状态: {}", report.finalize().display_name());
             info!(
                 "运行统计: {}/{} 个容器正在运行",
@@ -352,6 +610,28 @@ pub async fn check_docker_services_status_with_project(app: &CliApp, project_nam
                 }
             }
 
+            if !report.out_of_scope.is_empty() {
+                info!("不在检查范围内（不计入整体状态）:");
+                for container in &report.out_of_scope {
+                    info!(
+                        "  ⚫ {} ({}，范围外)",
+                        container.name,
+                        container.status.display_name()
+                    );
+                }
+            }
+
+            if !report.orphans.is_empty() {
+                warn!("⚠️ 孤立容器（服务已不在compose文件中，不计入整体状态）:");
+                for orphan in &report.orphans {
+                    warn!(
+                        "  ⚫ {} (服务: {}，{}，镜像: {})",
+                        orphan.name, orphan.service, orphan.state, orphan.image
+                    );
+                }
+                warn!("💡 使用 'nuwax-cli docker-service cleanup-orphans' 删除孤立容器");
+            }
+
             if !report.errors.is_empty() {
                 warn!("⚠️ 错误信息:");
                 for error in &report.errors {
@@ -377,6 +657,13 @@ pub async fn check_docker_services_status_with_project(app: &CliApp, project_nam
                 );
                 info!("  📝 注意: 如果使用了自定义端口参数，请使用相应的端口访问");
             }
+
+            if !scope.is_all() && !report.finalize().is_healthy() {
+                return Err(anyhow::anyhow!(
+                    "检查范围内的服务未全部健康: {}",
+                    report.get_status_summary()
+                ));
+            }
         }
         Err(e) => {
             error!("❌ 获取服务状态失败: {:?}", e);
@@ -402,12 +689,19 @@ pub async fn load_docker_images(app: &CliApp) -> Result<()> {
         Ok(result) => {
             info!("📦 镜像加载完成!");
             info!("  • 成功加载: {} 个镜像", result.success_count());
+            if result.pulled_count() > 0 {
+                info!("  • 其中从镜像仓库拉取: {} 个镜像", result.pulled_count());
+            }
             info!("  • 加载失败: {} 个镜像", result.failure_count());
 
             if !result.loaded_images.is_empty() {
                 info!("✅ 成功加载的镜像:");
                 for image in &result.loaded_images {
-                    info!("  • {}", image);
+                    if result.pulled_images.contains(image) {
+                        info!("  • {} (从镜像仓库拉取)", image);
+                    } else {
+                        info!("  • {}", image);
+                    }
                 }
             }
 
@@ -477,20 +771,158 @@ pub async fn setup_image_tags(app: &CliApp) -> Result<()> {
 }
 
 /// 解压Docker服务包, 并根据升级策略进行处理
+///
+/// 返回被其他程序占用、未能替换的文件
+#[tracing::instrument(level = "trace", name = "phase.extract", skip_all)]
 pub async fn extract_docker_service_with_upgrade_strategy(
     app: &CliApp,
     upgrade_strategy: UpgradeStrategy,
-) -> Result<()> {
+    conflict_policy: ConflictPolicy,
+) -> Result<LockedFileReport> {
+    // 补丁链：按顺序逐个应用补丁，每一步应用后校验，失败时立即中止
+    if let UpgradeStrategy::PatchChainUpgrade { steps, .. } = &upgrade_strategy {
+        let work_dir = client_core::constants::docker::get_docker_work_dir();
+        let mut locked = LockedFileReport::default();
+        for (index, step) in steps.iter().enumerate() {
+            info!(
+                "🔗 应用补丁 {}/{}: {} -> {}",
+                index + 1,
+                steps.len(),
+                step.from_version,
+                step.to_version
+            );
+            let step_locked =
+                extract_upgrade_package(app, &step.to_strategy(), conflict_policy).await?;
+            locked.merge(step_locked);
+            step.verify_applied(&work_dir).map_err(|e| {
+                anyhow::anyhow!("{}，补丁链已中止，可使用 --force-full 改为全量升级", e)
+            })?;
+            info!("✅ 已升级到 {}", step.to_version);
+        }
+        return Ok(locked);
+    }
+
+    extract_upgrade_package(app, &upgrade_strategy, conflict_policy).await
+}
+
+/// 解压单个升级包（全量包或单个补丁）
+async fn extract_upgrade_package(
+    app: &CliApp,
+    upgrade_strategy: &UpgradeStrategy,
+    conflict_policy: ConflictPolicy,
+) -> Result<LockedFileReport> {
     //区分升级策略,来进行解压
-    let upgrade_file_zip: Option<PathBuf> = match &upgrade_strategy {
+    if let UpgradeStrategy::FullUpgrade { .. } = upgrade_strategy {
+        // 强制升级策略，直接解压并覆盖现有文件
+        info!("📦 开始解压Docker服务包...");
+    }
+    let upgrade_file_zip = get_upgrade_package_path(app, upgrade_strategy);
+
+    // 检查文件是否存在
+    if let Some(file_zip) = upgrade_file_zip {
+        if !file_zip.exists() {
+            error!("❌ Docker服务包文件不存在: {}", file_zip.display());
+            return Err(anyhow::anyhow!(format!(
+                "Docker服务包文件不存在: {}",
+                file_zip.display()
+            )));
+        }
+
+        info!("📦 找到Docker服务包: {}", file_zip.display());
+
+        // 打开服务包前先按服务清单校验大小和哈希，再检查解压后的大小，截断的下载和压缩炸弹在这里拦下
+        if let Some(expected) = ExpectedPackage::from_strategy(upgrade_strategy) {
+            verify_package_download(&file_zip, &expected).await?;
+        }
+        let stats = check_extraction_limits(&file_zip, &app.config.package_limits)?;
+        debug!(
+            "服务包校验通过: {} 个条目，解压后 {:.1} MB",
+            stats.entries,
+            stats.extracted_bytes as f64 / 1024.0 / 1024.0
+        );
+
+        // 解压前确认服务包与本机架构一致，避免部署后才出现 exec format error
+        ensure_package_architecture(app, &file_zip, upgrade_strategy).await?;
+
+        // 使用utils中的解压函数
+        let locked = crate::utils::extract_docker_service_with_events(
+            &file_zip,
+            upgrade_strategy,
+            conflict_policy,
+            &app.events,
+        )
+        .await?;
+
+        info!("✅ Docker服务包解压完成");
+        return Ok(locked);
+    }
+    Ok(LockedFileReport::default())
+}
+
+/// 检查服务包架构与本机架构是否一致，不一致时返回包含正确服务包下载地址的错误
+async fn ensure_package_architecture(
+    app: &CliApp,
+    package: &Path,
+    upgrade_strategy: &UpgradeStrategy,
+) -> Result<()> {
+    let host = Architecture::detect();
+    if !host.is_supported() {
+        return Ok(());
+    }
+    let download_url = match upgrade_strategy {
+        UpgradeStrategy::FullUpgrade { url, .. } => Some(url.as_str()),
+        UpgradeStrategy::PatchUpgrade { patch_info, .. } => Some(patch_info.url.as_str()),
+        _ => None,
+    };
+    let Some(detected) = detect_package_architecture(package, download_url)? else {
+        debug!("无法判断服务包架构，跳过架构检查: {}", package.display());
+        return Ok(());
+    };
+    if detected.architecture == host {
+        info!(
+            "✅ 服务包架构与本机一致: {} (依据: {})",
+            host, detected.basis
+        );
+        return Ok(());
+    }
+
+    // 从服务清单取本机架构的服务包地址，无法获取时给出服务包文件名
+    let expected = match app.api_client.get_enhanced_service_manifest().await {
+        Ok(manifest) => manifest
+            .platforms
+            .as_ref()
+            .and_then(|platforms| platforms.for_architecture(&host))
+            .map(|package| redact_url(&package.url)),
+        Err(e) => {
+            debug!("获取服务清单失败: {}", e);
+            None
+        }
+    };
+    let expected = expected.unwrap_or_else(|| host.get_docker_file_name());
+    Err(anyhow::anyhow!(
+        "服务包架构为 {} ({})，与本机架构 {} ({}) 不一致（依据: {}），部署后容器将无法启动。请下载本机架构的服务包: {}",
+        detected.architecture,
+        detected.architecture.display_name(),
+        host,
+        host.display_name(),
+        detected.basis,
+        expected
+    ))
+}
+
+/// 获取升级策略对应的已下载服务包路径，无需升级时返回 None
+///
+/// 补丁链包含多个服务包，需按补丁逐个获取
+pub(crate) fn get_upgrade_package_path(
+    app: &CliApp,
+    upgrade_strategy: &UpgradeStrategy,
+) -> Option<PathBuf> {
+    match upgrade_strategy {
         UpgradeStrategy::FullUpgrade {
             target_version,
             download_type,
             ..
         } => {
-            // 强制升级策略，直接解压并覆盖现有文件
-            info!("📦 开始解压Docker服务包...");
-
             let base_version = target_version.base_version_string();
 
             let zip_path = app.config.get_version_download_file_path(
@@ -512,30 +944,11 @@ pub async fn extract_docker_service_with_upgrade_strategy(
             );
             Some(zip_path)
         }
-        UpgradeStrategy::NoUpgrade { .. } => {
+        UpgradeStrategy::PatchChainUpgrade { .. } | UpgradeStrategy::NoUpgrade { .. } => {
             // 无需升级
             None
         }
-    };
-
-    // 检查文件是否存在
-    if let Some(file_zip) = upgrade_file_zip {
-        if !file_zip.exists() {
-            error!("❌ Docker服务包文件不存在: {}", file_zip.display());
-            return Err(anyhow::anyhow!(format!(
-                "Docker服务包文件不存在: {}",
-                file_zip.display()
-            )));
-        }
-
-        info!("📦 找到Docker服务包: {}", file_zip.display());
-
-        // 使用utils中的解压函数
-        crate::utils::extract_docker_service(&file_zip, &upgrade_strategy).await?;
-
-        info!("✅ Docker服务包解压完成");
     }
-    Ok(())
 }
 
 /// 获取系统架构信息
@@ -633,3 +1046,21 @@ async fn set_frontend_port(port: u16) -> Result<()> {
     info!("✅ 端口配置更新成功!");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_exec_args() {
+        let command: Vec<String> = ["mysql", "-e", "select 1"].map(String::from).to_vec();
+        assert_eq!(
+            build_exec_args("docker-mysql-1", &command, false, None),
+            ["exec", "-i", "docker-mysql-1", "mysql", "-e", "select 1"]
+        );
+        assert_eq!(
+            build_exec_args("mysql-1", &command[..1], true, Some("root")),
+            ["exec", "-i", "-t", "--user", "root", "mysql-1", "mysql"]
+        );
+    }
+}
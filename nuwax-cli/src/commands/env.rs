@@ -0,0 +1,216 @@
+use crate::app::CliApp;
+use crate::cli::EnvCommand;
+use crate::utils::env_diff::{EnvChange, EnvChangeKind, EnvTemplate, diff_env};
+use crate::utils::env_manager::{EnvManager, is_placeholder_value};
+use crate::utils::prompt;
+use anyhow::Result;
+use client_core::constants::docker;
+use client_core::version::Version;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// 处理 .env 环境变量命令
+pub async fn handle_env_command(app: &CliApp, cmd: EnvCommand) -> Result<()> {
+    match cmd {
+        EnvCommand::ShowDiff { template, apply } => show_env_diff(app, template, apply),
+    }
+}
+
+/// 对比当前 .env 与服务包模板，`apply` 时逐项确认并写入
+fn show_env_diff(app: &CliApp, template: Option<PathBuf>, apply: bool) -> Result<()> {
+    let env_path = docker::get_env_file_path();
+    if !env_path.exists() {
+        return Err(anyhow::anyhow!(
+            ".env 文件不存在: {}，请先部署服务",
+            env_path.display()
+        ));
+    }
+
+    let template_path = match template {
+        Some(path) => path,
+        None => latest_downloaded_package(app).ok_or_else(|| {
+            anyhow::anyhow!("未找到已下载的全量服务包，请先下载新版本或使用 --template 指定模板")
+        })?,
+    };
+    info!("📄 模板来源: {}", template_path.display());
+    let template = EnvTemplate::load(&template_path)?;
+    let previous = current_version_template(app, &template_path);
+
+    let changes = diff_env(
+        &load_current_variables(&env_path)?,
+        &template,
+        previous.as_ref(),
+    );
+    if changes.is_empty() {
+        info!("✅ .env 与模板一致，没有需要处理的变量");
+        return Ok(());
+    }
+    print_env_changes(&changes);
+
+    if apply {
+        apply_env_changes(&env_path, &changes)
+    } else {
+        info!("💡 逐项确认并写入变更: nuwax-cli env show-diff --apply");
+        Ok(())
+    }
+}
+
+/// 升级预览时展示服务包 .env 模板带来的变量变化，失败时只记录调试日志
+pub fn preview_env_template_changes(app: &CliApp, package_path: &Path) {
+    let env_path = docker::get_env_file_path();
+    if !env_path.exists() {
+        return;
+    }
+    let changes = EnvTemplate::load_from_package(package_path).and_then(|template| {
+        let previous = current_version_template(app, package_path);
+        Ok(diff_env(
+            &load_current_variables(&env_path)?,
+            &template,
+            previous.as_ref(),
+        ))
+    });
+    let changes = match changes {
+        Ok(changes) => changes,
+        Err(e) => {
+            debug!("跳过 .env 模板对比: {}", e);
+            return;
+        }
+    };
+    if changes.is_empty() {
+        return;
+    }
+
+    info!("🧩 .env 模板变化:");
+    print_env_changes(&changes);
+    info!(
+        "💡 升级前可执行 nuwax-cli env show-diff --template {} --apply 将变量合并到当前 .env",
+        package_path.display()
+    );
+}
+
+/// 读取 .env 中的全部变量（包括空值）
+fn load_current_variables(env_path: &Path) -> Result<HashMap<String, String>> {
+    let mut env_manager = EnvManager::new();
+    env_manager.load(env_path)?;
+    Ok(env_manager
+        .get_all_variables()
+        .iter()
+        .map(|(key, var)| (key.clone(), var.value.clone()))
+        .collect())
+}
+
+/// 已下载的版本号最高的全量服务包
+fn latest_downloaded_package(app: &CliApp) -> Option<PathBuf> {
+    fs::read_dir(app.config.get_download_dir())
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let version = name.parse::<Version>().ok()?;
+            let path = app
+                .config
+                .get_version_download_file_path(&name, "full", None);
+            path.exists().then_some((version, path))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, path)| path)
+}
+
+/// 当前部署版本的服务包模板，用于区分模板默认值的变化和用户的自定义值
+fn current_version_template(app: &CliApp, template_path: &Path) -> Option<EnvTemplate> {
+    let path =
+        app.config
+            .get_version_download_file_path(&app.config.get_docker_versions(), "full", None);
+    if !path.exists() || path == template_path {
+        return None;
+    }
+    EnvTemplate::load_from_package(&path)
+        .inspect_err(|e| debug!("无法读取当前版本的 .env 模板: {}", e))
+        .ok()
+}
+
+fn print_env_changes(changes: &[EnvChange]) {
+    for change in changes {
+        let required = if change.required { " (必填)" } else { "" };
+        let line = format!(
+            "   [{}] {}{}: {} -> {}",
+            change.kind,
+            change.key,
+            required,
+            change.display_value(change.current.as_deref()),
+            change.display_value(change.template.as_deref())
+        );
+        if change.required && change.kind == EnvChangeKind::Added {
+            warn!("{}", line);
+        } else {
+            info!("{}", line);
+        }
+        if let Some(description) = &change.description {
+            info!("         {}", description);
+        }
+    }
+    let count = |kind| changes.iter().filter(|change| change.kind == kind).count();
+    info!(
+        "📊 共 {} 项: 新增 {}, 删除 {}, 修改 {}",
+        changes.len(),
+        count(EnvChangeKind::Added),
+        count(EnvChangeKind::Removed),
+        count(EnvChangeKind::Changed)
+    );
+}
+
+/// 逐项确认并写入 .env，写入前备份原文件
+///
+/// 无法询问时只添加新增的变量，删除和修改需要在终端中确认或指定 --yes
+fn apply_env_changes(env_path: &Path, changes: &[EnvChange]) -> Result<()> {
+    let interactive = prompt::assume_yes() || prompt::can_prompt();
+    if !interactive {
+        warn!("⚠️ 非交互环境，只添加新增的变量");
+    }
+
+    let mut env_manager = EnvManager::new();
+    env_manager.load(env_path)?;
+    let mut applied = Vec::new();
+    for change in changes {
+        let accepted = if interactive {
+            prompt::confirm(&format!(
+                "[{}] {} -> {}，应用此变更?",
+                change.kind,
+                change.key,
+                change.display_value(change.template.as_deref())
+            ))?
+        } else {
+            change.kind == EnvChangeKind::Added
+        };
+        if accepted {
+            change.apply(&mut env_manager);
+            applied.push(change);
+        }
+    }
+
+    if applied.is_empty() {
+        info!("未写入任何变更");
+        return Ok(());
+    }
+    let backup_path = env_path.with_file_name(format!("{}.bak", docker::ENV_FILE_NAME));
+    fs::copy(env_path, &backup_path)?;
+    env_manager.save()?;
+    info!(
+        "✅ 已写入 {} 项变更，原文件已备份到 {}",
+        applied.len(),
+        backup_path.display()
+    );
+
+    for change in applied {
+        let unset = change.template.as_deref().is_none_or(is_placeholder_value);
+        if change.required && change.kind != EnvChangeKind::Removed && unset {
+            warn!(
+                "⚠️ 必填变量 {} 仍为空或占位值，启动服务前请在 .env 中设置",
+                change.key
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use crate::cli::FleetCommand;
+use anyhow::{Result, anyhow};
+use client_core::fleet::{
+    DEFAULT_FLEET_PARALLELISM, FleetConfig, FleetOperation, FleetReport, Inventory, RetryPolicy,
+    run_fleet,
+};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// 处理批量主机操作命令
+pub async fn run_fleet_command(command: FleetCommand) -> Result<()> {
+    match command {
+        FleetCommand::Apply {
+            inventory,
+            operations,
+            parallel,
+            attempts,
+            report_json,
+            report_html,
+        } => {
+            run_fleet_apply(
+                &inventory,
+                operations,
+                parallel,
+                attempts,
+                report_json,
+                report_html,
+            )
+            .await
+        }
+    }
+}
+
+async fn run_fleet_apply(
+    inventory_path: &Path,
+    operations: Vec<FleetOperation>,
+    parallel: Option<usize>,
+    attempts: Option<u32>,
+    report_json: Option<PathBuf>,
+    report_html: Option<PathBuf>,
+) -> Result<()> {
+    let inventory = Inventory::load(inventory_path)?;
+    let hosts = inventory.resolve_hosts()?;
+
+    // 命令行参数优先于清单中的设置
+    let operations = if operations.is_empty() {
+        inventory.operations.clone()
+    } else {
+        operations
+    };
+    if operations.is_empty() {
+        return Err(anyhow!(
+            "未指定要执行的操作，请使用 --op 或在清单中配置 operations"
+        ));
+    }
+    let config = FleetConfig {
+        operations,
+        parallelism: parallel
+            .or(inventory.parallelism)
+            .unwrap_or(DEFAULT_FLEET_PARALLELISM),
+        retry: RetryPolicy {
+            attempts: attempts.unwrap_or(inventory.retry.attempts),
+            ..inventory.retry
+        },
+    };
+
+    let report = run_fleet(hosts, config, |host, line| info!("[{}] {}", host, line)).await;
+    print_summary(&report);
+
+    if let Some(path) = report_json {
+        std::fs::write(&path, report.to_json()?)?;
+        info!("📄 JSON 报告已保存: {}", path.display());
+    }
+    if let Some(path) = report_html {
+        std::fs::write(&path, report.to_html())?;
+        info!("📄 HTML 报告已保存: {}", path.display());
+    }
+
+    if report.is_success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} 台主机中有 {} 台执行失败",
+            report.total_hosts,
+            report.failed_hosts
+        ))
+    }
+}
+
+fn print_summary(report: &FleetReport) {
+    info!("");
+    info!("📊 批量操作结果:");
+    for host in &report.hosts {
+        if host.success {
+            info!("   ✅ {} ({})", host.name, host.target);
+            continue;
+        }
+        warn!("   ❌ {} ({})", host.name, host.target);
+        for result in host.operations.iter().filter(|r| !r.success) {
+            warn!(
+                "      {} 失败（尝试 {} 次）: {}",
+                result.operation,
+                result.attempts,
+                result.error.as_deref().unwrap_or_default()
+            );
+        }
+        if !host.skipped.is_empty() {
+            let skipped: Vec<_> = host.skipped.iter().map(|op| op.as_str()).collect();
+            warn!("      已跳过: {}", skipped.join(", "));
+        }
+    }
+    info!(
+        "   共 {} 台主机，成功 {} 台，失败 {} 台",
+        report.total_hosts, report.succeeded_hosts, report.failed_hosts
+    );
+}
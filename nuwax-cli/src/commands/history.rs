@@ -0,0 +1,331 @@
+//! 历史记录命令：本地升级时间线，以及与服务器升级历史的对账
+
+use crate::app::CliApp;
+use crate::cli::HistoryCommand;
+use anyhow::Result;
+use client_core::api_types::{ServiceUpgradeHistoryEntry, ServiceUpgradeHistoryRequest};
+use client_core::constants::upgrade::UPGRADE_HISTORY_SERVICE_NAME;
+use client_core::database::{UpgradeRecord, UpgradeStatus};
+use serde::Serialize;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// 处理 history 命令
+pub async fn handle_history_command(app: &CliApp, cmd: HistoryCommand) -> Result<()> {
+    match cmd {
+        HistoryCommand::Upgrades { limit, json, sync } => {
+            run_upgrade_history(app, limit, json, sync).await
+        }
+    }
+}
+
+/// 升级时间线（JSON 输出）
+#[derive(Debug, Serialize)]
+struct UpgradeTimeline {
+    upgrades: Vec<TimelineEntry>,
+    /// 只有服务器上有的升级记录，未对账时为空
+    server_only: Option<Vec<ServiceUpgradeHistoryEntry>>,
+}
+
+#[derive(Debug, Serialize)]
+struct TimelineEntry {
+    #[serde(flatten)]
+    record: UpgradeRecord,
+    duration_seconds: Option<i64>,
+    /// 服务器上是否已有该记录，未对账时为空
+    synced: Option<bool>,
+}
+
+/// 显示升级时间线，`sync` 为 true 时先与服务器记录对账
+async fn run_upgrade_history(
+    app: &CliApp,
+    limit: Option<usize>,
+    json: bool,
+    sync: bool,
+) -> Result<()> {
+    // 对账需要全部本地记录，显示时再按 limit 截取
+    let records = app.database.get_upgrade_history(None).await?;
+
+    let mut synced_ids = None;
+    let mut server_only = None;
+    if sync {
+        match sync_with_server(app, &records).await {
+            Ok((ids, only)) => {
+                synced_ids = Some(ids);
+                server_only = Some(only);
+            }
+            Err(e) => warn!("⚠️ 无法获取服务器升级历史，只显示本地记录: {}", e),
+        }
+    }
+
+    let upgrades: Vec<TimelineEntry> = records
+        .into_iter()
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|record| TimelineEntry {
+            duration_seconds: record.duration().map(|duration| duration.num_seconds()),
+            synced: synced_ids
+                .as_ref()
+                .map(|ids| ids.contains(&record.upgrade_id)),
+            record,
+        })
+        .collect();
+
+    if json {
+        let timeline = UpgradeTimeline {
+            upgrades,
+            server_only,
+        };
+        println!("{}", serde_json::to_string_pretty(&timeline)?);
+        return Ok(());
+    }
+
+    print_timeline(&upgrades);
+    if let Some(server_only) = server_only.filter(|entries| !entries.is_empty()) {
+        info!("☁️ 仅服务器有记录的升级 ({} 条):", server_only.len());
+        for entry in &server_only {
+            info!(
+                "   {}  {} -> {}  [{}]",
+                entry.created_at.as_deref().unwrap_or("-"),
+                entry.from_version,
+                entry.to_version,
+                entry.status
+            );
+        }
+    }
+    Ok(())
+}
+
+fn print_timeline(upgrades: &[TimelineEntry]) {
+    if upgrades.is_empty() {
+        info!("📭 暂无升级记录");
+        return;
+    }
+
+    info!("📜 服务升级历史（最近 {} 条）", upgrades.len());
+    for entry in upgrades {
+        let record = &entry.record;
+        let icon = match record.status {
+            UpgradeStatus::Success => "✅",
+            UpgradeStatus::Failed => "❌",
+            UpgradeStatus::RolledBack => "↩️",
+            UpgradeStatus::Running => "⏳",
+        };
+        info!(
+            "{} {}  {} -> {}  [{}] {}",
+            icon,
+            record
+                .started_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            record.from_version,
+            record.to_version,
+            record.upgrade_type.display_name(),
+            record.status.display_name()
+        );
+
+        let sync_status = match entry.synced {
+            Some(true) => "  服务器: 已同步",
+            Some(false) => "  服务器: 未同步",
+            None => "",
+        };
+        info!(
+            "   耗时: {}  备份: {}{}",
+            entry
+                .duration_seconds
+                .map(format_elapsed)
+                .unwrap_or_else(|| "-".to_string()),
+            record
+                .backup_id
+                .map(|id| format!("#{id}"))
+                .unwrap_or_else(|| "无".to_string()),
+            sync_status
+        );
+        if let Some(error) = &record.error_message {
+            info!("   错误: {}", error);
+        }
+    }
+}
+
+/// 格式化升级耗时，如 `3分12秒`
+fn format_elapsed(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    if seconds >= 3600 {
+        format!("{}时{:02}分", seconds / 3600, seconds % 3600 / 60)
+    } else if seconds >= 60 {
+        format!("{}分{:02}秒", seconds / 60, seconds % 60)
+    } else {
+        format!("{seconds}秒")
+    }
+}
+
+/// 与服务器记录对账：补报服务器缺少的本地记录
+///
+/// 返回服务器上已有的本地记录 ID，以及只有服务器上有的记录
+async fn sync_with_server(
+    app: &CliApp,
+    records: &[UpgradeRecord],
+) -> Result<(HashSet<String>, Vec<ServiceUpgradeHistoryEntry>)> {
+    let server = app
+        .api_client
+        .get_service_upgrade_history(UPGRADE_HISTORY_SERVICE_NAME)
+        .await?;
+    let (mut synced, server_only) = reconcile(records, &server);
+
+    let mut reported = 0;
+    for record in records
+        .iter()
+        .filter(|record| record.status != UpgradeStatus::Running)
+        .filter(|record| !synced.contains(&record.upgrade_id))
+    {
+        match app
+            .api_client
+            .report_service_upgrade_history(upgrade_report_request(record))
+            .await
+        {
+            Ok(()) => {
+                synced.insert(record.upgrade_id.clone());
+                reported += 1;
+            }
+            Err(e) => warn!("⚠️ 补报升级记录 {} 失败: {}", record.upgrade_id, e),
+        }
+    }
+    if reported > 0 {
+        info!("☁️ 已补报 {} 条升级记录到服务器", reported);
+    }
+
+    Ok((synced, server_only))
+}
+
+/// 上报一次升级到服务器，失败只记录警告
+pub async fn report_upgrade(app: &CliApp, record: &UpgradeRecord) {
+    if let Err(e) = app
+        .api_client
+        .report_service_upgrade_history(upgrade_report_request(record))
+        .await
+    {
+        warn!(
+            "⚠️ 上报升级历史失败，可稍后使用 history upgrades --sync 补报: {}",
+            e
+        );
+    }
+}
+
+/// 构造升级历史上报请求，升级 ID 等本地信息放在 details 中用于对账
+fn upgrade_report_request(record: &UpgradeRecord) -> ServiceUpgradeHistoryRequest {
+    let details = serde_json::json!({
+        "upgrade_id": record.upgrade_id,
+        "upgrade_type": record.upgrade_type,
+        "backup_id": record.backup_id,
+        "started_at": record.started_at,
+        "duration_seconds": record.duration().map(|duration| duration.num_seconds()),
+        "error": record.error_message,
+    });
+
+    ServiceUpgradeHistoryRequest {
+        service_name: UPGRADE_HISTORY_SERVICE_NAME.to_string(),
+        from_version: record.from_version.clone(),
+        to_version: record.to_version.clone(),
+        status: record.status.as_db_str().to_ascii_lowercase(),
+        details: Some(details.to_string()),
+    }
+}
+
+/// 按升级 ID 匹配服务器记录；不带升级 ID 的早期记录按版本匹配尚未对上的本地记录
+fn reconcile(
+    records: &[UpgradeRecord],
+    server: &[ServiceUpgradeHistoryEntry],
+) -> (HashSet<String>, Vec<ServiceUpgradeHistoryEntry>) {
+    let local_ids: HashSet<&str> = records
+        .iter()
+        .map(|record| record.upgrade_id.as_str())
+        .collect();
+    let mut synced = HashSet::new();
+    let mut server_only = Vec::new();
+
+    for entry in server {
+        let matched = match server_upgrade_id(entry) {
+            Some(id) => local_ids.contains(id.as_str()).then_some(id),
+            None => records
+                .iter()
+                .find(|record| {
+                    !synced.contains(&record.upgrade_id)
+                        && record.from_version == entry.from_version
+                        && record.to_version == entry.to_version
+                })
+                .map(|record| record.upgrade_id.clone()),
+        };
+        match matched {
+            Some(id) => {
+                synced.insert(id);
+            }
+            None => server_only.push(entry.clone()),
+        }
+    }
+
+    (synced, server_only)
+}
+
+fn server_upgrade_id(entry: &ServiceUpgradeHistoryEntry) -> Option<String> {
+    let details: serde_json::Value = serde_json::from_str(entry.details.as_deref()?).ok()?;
+    details.get("upgrade_id")?.as_str().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use client_core::database::UpgradeKind;
+
+    fn record(upgrade_id: &str, from: &str, to: &str) -> UpgradeRecord {
+        UpgradeRecord {
+            upgrade_id: upgrade_id.to_string(),
+            from_version: from.to_string(),
+            to_version: to.to_string(),
+            upgrade_type: UpgradeKind::Patch,
+            status: UpgradeStatus::Success,
+            backup_id: None,
+            started_at: Utc::now(),
+            completed_at: None,
+            error_message: None,
+        }
+    }
+
+    fn server_entry(from: &str, to: &str, details: Option<String>) -> ServiceUpgradeHistoryEntry {
+        ServiceUpgradeHistoryEntry {
+            from_version: from.to_string(),
+            to_version: to.to_string(),
+            status: "success".to_string(),
+            details,
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_reconcile_by_upgrade_id_and_versions() {
+        let records = vec![
+            record("a", "1.0.0", "1.1.0"),
+            record("b", "1.1.0", "1.2.0"),
+            record("c", "1.2.0", "1.3.0"),
+        ];
+        let server = vec![
+            server_entry(
+                "1.0.0",
+                "1.1.0",
+                upgrade_report_request(&records[0]).details,
+            ),
+            // 早期上报没有升级 ID
+            server_entry("1.1.0", "1.2.0", None),
+            server_entry(
+                "0.9.0",
+                "1.0.0",
+                Some(r#"{"upgrade_id":"old"}"#.to_string()),
+            ),
+        ];
+
+        let (synced, server_only) = reconcile(&records, &server);
+        assert_eq!(synced, HashSet::from(["a".to_string(), "b".to_string()]));
+        assert_eq!(server_only.len(), 1);
+        assert_eq!(server_only[0].to_version, "1.0.0");
+        assert_eq!(format_elapsed(192), "3分12秒");
+    }
+}
@@ -0,0 +1,176 @@
+use crate::app::CliApp;
+use anyhow::Result;
+use chrono::Utc;
+use client_core::architecture::Architecture;
+use client_core::constants::backup::IMAGE_STAGING_PREFIX;
+use client_core::image_archive::{
+    ArchivedImage, IMAGES_ENTRY, ImageArchiveManifest, image_archive_file_name,
+    read_image_archive_manifest, unpack_image_archive, write_image_archive,
+};
+use client_core::version::Version;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// 导出当前compose文件引用的全部镜像到 tar.zst 归档
+///
+/// 未指定 `out` 时保存到备份目录，`backup export-catalog` 会把它列为可选内容
+pub async fn export_images(app: &CliApp, out: Option<PathBuf>, force: bool) -> Result<()> {
+    let service_version = app.config.get_docker_versions();
+    let out = out.unwrap_or_else(|| {
+        app.backup_manager
+            .get_storage_dir()
+            .join(image_archive_file_name(&service_version, Utc::now()))
+    });
+    if out.exists() && !force {
+        return Err(anyhow::anyhow!(
+            "文件已存在: {}，使用 --force 覆盖",
+            out.display()
+        ));
+    }
+    let parent = out
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    fs::create_dir_all(&parent)?;
+
+    let references = app.docker_manager.compose_images().await?;
+    if references.is_empty() {
+        return Err(anyhow::anyhow!("compose文件中没有引用任何镜像"));
+    }
+
+    let mut images = Vec::new();
+    let mut missing = Vec::new();
+    for reference in &references {
+        match app.docker_manager.inspect_image_platform(reference).await? {
+            Some((id, platform)) => images.push(ArchivedImage {
+                reference: reference.clone(),
+                id,
+                platform,
+            }),
+            None => missing.push(reference.as_str()),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(anyhow::anyhow!(
+            "以下镜像在本机不存在: {}，请先运行 'nuwax-cli docker-service load-images'",
+            missing.join(", ")
+        ));
+    }
+
+    let manifest = ImageArchiveManifest::new(service_version, images);
+    if let Err(e) = manifest.check_architecture(&Architecture::detect()) {
+        warn!("⚠️ {}", e);
+    }
+    info!(
+        "📤 导出 {} 个镜像 (服务版本 {}) 到 {}",
+        manifest.images.len(),
+        manifest.service_version,
+        out.display()
+    );
+    for image in &manifest.images {
+        info!("   {} ({})", image.reference, image.platform);
+    }
+
+    let start = Instant::now();
+    // docker save 的输出先写入与归档相同的磁盘
+    let staging = tempfile::Builder::new()
+        .prefix(IMAGE_STAGING_PREFIX)
+        .tempdir_in(&parent)?;
+    let images_tar = staging.path().join(IMAGES_ENTRY);
+    app.docker_manager
+        .save_images(&references, &images_tar)
+        .await?;
+
+    let image_count = manifest.images.len();
+    let archive_path = out.clone();
+    let size = tokio::task::spawn_blocking(move || {
+        write_image_archive(&manifest, &images_tar, &archive_path)
+    })
+    .await??;
+
+    info!(
+        "✅ 镜像导出完成: {} ({} 个镜像, {:.1} MB, 耗时 {:.1} 秒)",
+        out.display(),
+        image_count,
+        size as f64 / 1024.0 / 1024.0,
+        start.elapsed().as_secs_f64()
+    );
+    info!("💡 在目标主机上运行 'nuwax-cli docker-service import-images <文件>' 导入");
+    Ok(())
+}
+
+/// 导入镜像归档，导入前检查架构，导入后核对镜像 ID
+pub async fn import_images(app: &CliApp, file: &Path) -> Result<()> {
+    let archive_path = file.to_path_buf();
+    let manifest =
+        tokio::task::spawn_blocking(move || read_image_archive_manifest(&archive_path)).await??;
+    info!(
+        "📦 镜像归档: 服务版本 {}，{} 个镜像，导出于 {}",
+        manifest.service_version,
+        manifest.images.len(),
+        manifest.created_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    manifest.check_architecture(&Architecture::detect())?;
+
+    let current_version = app.config.get_docker_versions();
+    let same_version = match (
+        manifest.service_version.parse::<Version>(),
+        current_version.parse::<Version>(),
+    ) {
+        (Ok(archived), Ok(current)) => archived == current,
+        _ => manifest.service_version == current_version,
+    };
+    if !same_version {
+        warn!(
+            "⚠️ 归档的服务版本 {} 与当前版本 {} 不同",
+            manifest.service_version, current_version
+        );
+    }
+
+    let start = Instant::now();
+    let staging_dir = app.backup_manager.get_storage_dir();
+    fs::create_dir_all(staging_dir)?;
+    let staging = tempfile::Builder::new()
+        .prefix(IMAGE_STAGING_PREFIX)
+        .tempdir_in(staging_dir)?;
+    let archive_path = file.to_path_buf();
+    let staging_path = staging.path().to_path_buf();
+    let (_, images_tar) =
+        tokio::task::spawn_blocking(move || unpack_image_archive(&archive_path, &staging_path))
+            .await??;
+
+    info!("📥 加载镜像...");
+    let loaded = app.docker_manager.load_images_from_tar(&images_tar).await?;
+    for name in &loaded {
+        info!("   已加载: {}", name);
+    }
+
+    let mut mismatched = Vec::new();
+    for image in &manifest.images {
+        match app
+            .docker_manager
+            .inspect_image_platform(&image.reference)
+            .await?
+        {
+            Some((id, _)) if id == image.id => {}
+            Some((id, _)) => mismatched.push(format!("{} (ID {})", image.reference, id)),
+            None => mismatched.push(format!("{} (未加载)", image.reference)),
+        }
+    }
+    if !mismatched.is_empty() {
+        return Err(anyhow::anyhow!(
+            "以下镜像与归档不一致: {}",
+            mismatched.join(", ")
+        ));
+    }
+
+    info!(
+        "✅ 已导入 {} 个镜像，耗时 {:.1} 秒",
+        manifest.images.len(),
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
@@ -0,0 +1,318 @@
+use crate::app::CliApp;
+use crate::cli::MetricsCommand;
+use crate::commands::backup::backup_namespace;
+use crate::docker_service::health_check::HealthChecker;
+use crate::docker_service::watchdog::is_container_healthy;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use client_core::database::BackupStatus;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tracing::{debug, error, info, warn};
+
+/// 读取 HTTP 请求头的超时时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// HTTP 请求头的最大长度
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+/// 单个服务的指标
+#[derive(Debug, Clone)]
+struct ServiceMetrics {
+    service: String,
+    up: bool,
+    /// 容器被 Docker 自动重启的次数，无法获取时为 `None`
+    restarts: Option<u64>,
+}
+
+/// 一次采集的结果，备份时间在响应时换算为距今秒数
+#[derive(Debug, Clone, Default)]
+struct MetricsSnapshot {
+    /// 健康检查是否成功，失败时没有服务指标
+    check_success: bool,
+    services: Vec<ServiceMetrics>,
+    last_backup: Option<DateTime<Utc>>,
+    version: String,
+}
+
+/// 处理监控指标命令
+pub async fn handle_metrics_command(app: &CliApp, cmd: MetricsCommand) -> Result<()> {
+    match cmd {
+        MetricsCommand::Serve { listen, interval } => serve_metrics(app, listen, interval).await,
+    }
+}
+
+/// 定期执行健康检查，并在 `listen` 上以 Prometheus 文本格式提供 `/metrics`
+async fn serve_metrics(app: &CliApp, listen: SocketAddr, interval: u64) -> Result<()> {
+    let listener = TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("无法监听 {listen}"))?;
+    let interval = Duration::from_secs(interval.max(1));
+    let snapshot = Arc::new(RwLock::new(None));
+
+    info!("📈 监控指标服务已启动: http://{}/metrics", listen);
+    info!("   健康检查间隔: {} 秒", interval.as_secs());
+    let server = tokio::spawn(accept_connections(listener, snapshot.clone()));
+
+    loop {
+        let collected = collect_metrics(app).await;
+        *snapshot.write().await = Some(collected);
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => {
+                info!("🛑 收到中断信号，监控指标服务退出");
+                server.abort();
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// 采集一轮指标，健康检查失败时记录日志并标记为失败
+async fn collect_metrics(app: &CliApp) -> MetricsSnapshot {
+    let mut snapshot = MetricsSnapshot {
+        version: app.config.get_docker_versions(),
+        last_backup: last_backup_time(app).await,
+        ..Default::default()
+    };
+
+    let report = match HealthChecker::new(app.docker_manager.clone())
+        .health_check()
+        .await
+    {
+        Ok(report) => report,
+        Err(e) => {
+            error!("❌ 健康检查失败: {}", e);
+            return snapshot;
+        }
+    };
+    snapshot.check_success = true;
+
+    for container in &report.containers {
+        if !container.is_persistent_service() {
+            continue;
+        }
+        let restarts = app
+            .docker_manager
+            .get_service_restart_count(&container.name)
+            .await
+            .inspect_err(|e| debug!("无法获取服务 {} 的重启次数: {}", container.name, e))
+            .ok()
+            .flatten();
+        snapshot.services.push(ServiceMetrics {
+            service: container.name.clone(),
+            up: is_container_healthy(container),
+            restarts,
+        });
+    }
+    debug!("采集到 {} 个服务的指标", snapshot.services.len());
+    snapshot
+}
+
+/// 当前项目最近一次成功备份的时间
+async fn last_backup_time(app: &CliApp) -> Option<DateTime<Utc>> {
+    let namespace = backup_namespace(app);
+    match app.backup_manager.list_backups().await {
+        Ok(backups) => backups
+            .into_iter()
+            .filter(|backup| {
+                backup.status == BackupStatus::Completed && backup.belongs_to_namespace(&namespace)
+            })
+            .map(|backup| backup.created_at)
+            .max(),
+        Err(e) => {
+            warn!("⚠️ 无法读取备份记录: {}", e);
+            None
+        }
+    }
+}
+
+async fn accept_connections(listener: TcpListener, snapshot: Arc<RwLock<Option<MetricsSnapshot>>>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let snapshot = snapshot.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, snapshot).await {
+                        debug!("处理来自 {} 的请求失败: {}", peer, e);
+                    }
+                });
+            }
+            Err(e) => warn!("⚠️ 接受连接失败: {}", e),
+        }
+    }
+}
+
+/// 处理单个 HTTP 请求：`GET /metrics` 返回指标，其他路径返回 404
+async fn handle_connection(
+    mut stream: TcpStream,
+    snapshot: Arc<RwLock<Option<MetricsSnapshot>>>,
+) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    tokio::time::timeout(REQUEST_TIMEOUT, async {
+        while !request.windows(4).any(|window| window == b"\r\n\r\n")
+            && request.len() < MAX_REQUEST_BYTES
+        {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        Ok::<_, std::io::Error>(())
+    })
+    .await
+    .context("读取请求超时")??;
+
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+    let path = path.map(|path| path.split('?').next().unwrap_or(path));
+
+    let (status, body) = match (method, path) {
+        (Some("GET"), Some("/metrics")) => match snapshot.read().await.as_ref() {
+            Some(snapshot) => ("200 OK", render_metrics(snapshot, Utc::now())),
+            None => (
+                "503 Service Unavailable",
+                "metrics not collected yet\n".to_string(),
+            ),
+        },
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// 按 Prometheus 文本格式输出指标
+fn render_metrics(snapshot: &MetricsSnapshot, now: DateTime<Utc>) -> String {
+    let mut out = String::new();
+
+    write_header(
+        &mut out,
+        "nuwax_health_check_success",
+        "Whether the last health check succeeded (1) or failed (0).",
+    );
+    let _ = writeln!(
+        out,
+        "nuwax_health_check_success {}",
+        u8::from(snapshot.check_success)
+    );
+
+    write_header(
+        &mut out,
+        "nuwax_service_up",
+        "Whether the service container is running and not unhealthy.",
+    );
+    for service in &snapshot.services {
+        let _ = writeln!(
+            out,
+            "nuwax_service_up{{service=\"{}\"}} {}",
+            escape_label(&service.service),
+            u8::from(service.up)
+        );
+    }
+
+    write_header(
+        &mut out,
+        "nuwax_container_restarts",
+        "Number of times Docker restarted the service container.",
+    );
+    for service in &snapshot.services {
+        if let Some(restarts) = service.restarts {
+            let _ = writeln!(
+                out,
+                "nuwax_container_restarts{{service=\"{}\"}} {}",
+                escape_label(&service.service),
+                restarts
+            );
+        }
+    }
+
+    if let Some(last_backup) = snapshot.last_backup {
+        write_header(
+            &mut out,
+            "nuwax_last_backup_age_seconds",
+            "Seconds since the last successful backup.",
+        );
+        let age = (now - last_backup).num_seconds().max(0);
+        let _ = writeln!(out, "nuwax_last_backup_age_seconds {age}");
+    }
+
+    write_header(
+        &mut out,
+        "nuwax_deployed_version_info",
+        "Deployed service version.",
+    );
+    let _ = writeln!(
+        out,
+        "nuwax_deployed_version_info{{version=\"{}\"}} 1",
+        escape_label(&snapshot.version)
+    );
+    out
+}
+
+fn write_header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+}
+
+/// 转义标签值中的反斜杠、双引号和换行
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_metrics() {
+        let now = Utc::now();
+        let snapshot = MetricsSnapshot {
+            check_success: true,
+            services: vec![
+                ServiceMetrics {
+                    service: "backend".to_string(),
+                    up: true,
+                    restarts: Some(2),
+                },
+                ServiceMetrics {
+                    service: "mysql".to_string(),
+                    up: false,
+                    restarts: None,
+                },
+            ],
+            last_backup: Some(now - chrono::Duration::hours(1)),
+            version: "0.1.5".to_string(),
+        };
+
+        let output = render_metrics(&snapshot, now);
+        assert!(output.contains("nuwax_health_check_success 1\n"));
+        assert!(output.contains("nuwax_service_up{service=\"backend\"} 1\n"));
+        assert!(output.contains("nuwax_service_up{service=\"mysql\"} 0\n"));
+        assert!(output.contains("nuwax_container_restarts{service=\"backend\"} 2\n"));
+        assert!(!output.contains("nuwax_container_restarts{service=\"mysql\"}"));
+        assert!(output.contains("nuwax_last_backup_age_seconds 3600\n"));
+        assert!(output.contains("nuwax_deployed_version_info{version=\"0.1.5\"} 1\n"));
+
+        let output = render_metrics(&MetricsSnapshot::default(), now);
+        assert!(output.contains("nuwax_health_check_success 0\n"));
+        assert!(!output.contains("nuwax_last_backup_age_seconds"));
+        assert_eq!(escape_label("a\"b\\c"), "a\\\"b\\\\c");
+    }
+}
@@ -2,12 +2,35 @@ pub mod auto_backup;
 pub mod auto_upgrade_deploy;
 pub mod backup;
 pub mod cache;
+pub mod changelog;
 pub mod check_update;
+pub mod db;
 pub mod diff_sql;
+pub mod disk_usage;
 pub mod docker_service;
 pub mod ducker;
+pub mod env;
+pub mod fleet;
+pub mod history;
+pub mod images;
+pub mod metrics;
+pub mod patch;
+pub mod paths;
+pub mod ports;
+pub mod preset;
+pub mod relocate_data;
+pub mod remote;
+pub mod replay;
+pub mod self_test;
+pub mod serve;
 pub mod status;
+pub mod support_bundle;
+pub mod teardown;
 pub mod update;
+pub mod upgrade_phase;
+pub mod upgrade_plan;
+pub mod upgrade_rollback;
+pub mod watchdog;
 
 // Status commands
 pub use status::{run_api_info, run_status, run_status_details, show_client_version};
@@ -18,12 +41,24 @@ pub use backup::{run_backup, run_list_backups};
 // Update commands
 pub use update::run_upgrade;
 
+// Upgrade plan commands
+pub use upgrade_plan::run_approve_command;
+
+// Upgrade rollback commands
+pub use upgrade_rollback::run_upgrade_rollback;
+
+// Upgrade phase commands
+pub use upgrade_phase::run_upgrade_phase;
+
 // Docker service commands
-pub use docker_service::run_docker_service_command;
+pub use docker_service::{CommandExitCode, run_docker_service_command};
 
 // Ducker command
 pub use ducker::run_ducker;
 
+// Env commands
+pub use env::handle_env_command;
+
 // Auto backup commands
 pub use auto_backup::handle_auto_backup;
 
@@ -31,10 +66,52 @@ pub use auto_backup::handle_auto_backup;
 pub use auto_upgrade_deploy::handle_auto_upgrade_deploy_command;
 
 // Cache commands
-pub use cache::handle_cache_command;
+pub use cache::{handle_cache_command, sweep_stale_temp_artifacts};
 
 // Check update commands
 pub use check_update::handle_check_update_command;
 
+// Database commands
+pub use db::handle_db_command;
+
 // Diff SQL commands
 pub use diff_sql::run_diff_sql;
+
+// Disk usage commands
+pub use disk_usage::run_disk_usage;
+
+// Fleet commands
+pub use fleet::run_fleet_command;
+
+// History commands
+pub use history::handle_history_command;
+
+// Patch manifest commands
+pub use patch::run_patch_command;
+
+// Paths commands
+pub use paths::run_paths;
+
+// Metrics commands
+pub use metrics::handle_metrics_command;
+
+// Ports commands
+pub use ports::handle_ports_command;
+
+// Remote commands
+pub use remote::run_remote_command;
+
+// Replay commands
+pub use replay::run_replay;
+
+// Self-test commands
+pub use self_test::run_self_test;
+
+// Serve commands
+pub use serve::run_serve;
+
+// Support bundle commands
+pub use support_bundle::run_support_bundle;
+
+// Watchdog commands
+pub use watchdog::handle_watchdog_command;
@@ -0,0 +1,37 @@
+use crate::cli::PatchCommand;
+use anyhow::{Result, anyhow};
+use client_core::patch_manifest::{PATCH_OPERATIONS_SCHEMA, lint_patch_manifest};
+use std::path::Path;
+use tracing::{error, info};
+
+/// 处理补丁清单相关命令
+pub async fn run_patch_command(command: PatchCommand) -> Result<()> {
+    match command {
+        PatchCommand::Lint { manifest } => lint_manifest_file(&manifest),
+        PatchCommand::Schema => {
+            println!("{PATCH_OPERATIONS_SCHEMA}");
+            Ok(())
+        }
+    }
+}
+
+/// 检查补丁清单文件，存在问题时返回错误
+fn lint_manifest_file(path: &Path) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("无法读取补丁清单 {}: {}", path.display(), e))?;
+    let report = lint_patch_manifest(&content);
+
+    for pointer in &report.operations {
+        info!("🔍 已检查补丁操作: #{}", pointer);
+    }
+    if report.is_valid() {
+        info!("✅ 补丁清单检查通过: {}", path.display());
+        return Ok(());
+    }
+
+    error!("❌ 补丁清单 {} 存在以下问题:", path.display());
+    for issue in &report.issues {
+        error!("   {}", issue);
+    }
+    Err(anyhow!("补丁清单存在 {} 个问题", report.issues.len()))
+}
@@ -0,0 +1,35 @@
+use client_core::paths::{
+    BACKUP_DIR_ENV, CACHE_DIR_ENV, CONFIG_FILE_ENV, DOCKER_DIR_ENV, LOG_DIR_ENV, Paths,
+    TEMP_SQL_DIR_ENV,
+};
+use tracing::info;
+
+/// 显示实际使用的路径及其来源
+pub fn run_paths(paths: &Paths) {
+    info!("📁 路径布局（优先级：命令行参数 > 环境变量 > config.toml > 默认值）");
+    for (name, resolved) in paths.entries() {
+        match resolved {
+            Some(resolved) => info!(
+                "   {}: {} ({}{})",
+                name,
+                resolved.path.display(),
+                resolved.source,
+                if resolved.path.exists() {
+                    ""
+                } else {
+                    "，尚不存在"
+                }
+            ),
+            None => info!("   {}: 未指定，日志只输出到终端", name),
+        }
+    }
+    info!(
+        "💡 可通过环境变量 {}、{}、{}、{}、{}、{}、NUWAX_LOG_FILE 覆盖",
+        CONFIG_FILE_ENV,
+        DOCKER_DIR_ENV,
+        BACKUP_DIR_ENV,
+        CACHE_DIR_ENV,
+        TEMP_SQL_DIR_ENV,
+        LOG_DIR_ENV
+    );
+}
@@ -0,0 +1,262 @@
+use crate::app::CliApp;
+use crate::cli::PortsCommand;
+use crate::docker_service::health_check::HealthChecker;
+use crate::docker_service::port_manager::{PortManager, PortOverride, PortOverrideTarget};
+use crate::docker_service::watchdog::is_container_healthy;
+use crate::utils::env_manager::EnvManager;
+use anyhow::Result;
+use bollard::models::HealthStatusEnum;
+use client_core::constants::{docker, timeout};
+use client_core::container::DockerManager;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// 处理端口覆盖命令
+pub async fn handle_ports_command(app: &CliApp, cmd: PortsCommand) -> Result<()> {
+    match cmd {
+        PortsCommand::Set { service, port } => set_port_override(app, service, port),
+        PortsCommand::Unset { service } => unset_port_override(app, &service),
+        PortsCommand::List => list_port_overrides(app).await,
+    }
+}
+
+/// 将配置中的端口覆盖应用到 .env / docker-compose.yml
+///
+/// 部署和升级会用服务包中的文件覆盖 .env 和 docker-compose.yml，部署时调用此函数重新应用；
+/// 端口冲突或覆盖的服务不存在时返回错误，不修改任何文件
+pub fn apply_port_overrides(
+    ports: &BTreeMap<String, u16>,
+    compose_path: &Path,
+    env_path: &Path,
+) -> Result<()> {
+    if ports.is_empty() {
+        return Ok(());
+    }
+    if !compose_path.exists() {
+        warn!(
+            "⚠️ docker-compose文件不存在，跳过端口覆盖: {}",
+            compose_path.display()
+        );
+        return Ok(());
+    }
+
+    let overrides = PortOverride::parse_all(ports)?;
+    let plan = PortManager::new().plan_port_overrides(compose_path, env_path, &overrides)?;
+
+    if !plan.env_updates.is_empty() {
+        let mut env_manager = EnvManager::new();
+        if !env_path.exists() {
+            fs::write(env_path, "")?;
+        }
+        env_manager.load(env_path)?;
+        for (key, value) in &plan.env_updates {
+            env_manager.set_or_add_variable(key, value);
+        }
+        env_manager.save()?;
+    }
+    if let Some(content) = &plan.compose_content {
+        fs::write(compose_path, content)?;
+    }
+
+    for planned in &plan.overrides {
+        let location = match &planned.target {
+            PortOverrideTarget::EnvVar(var_name) => format!(".env {var_name}"),
+            PortOverrideTarget::Compose => "docker-compose.yml".to_string(),
+        };
+        info!(
+            "🔌 服务 {} 端口 {} -> {}:{} ({})",
+            planned.service_name,
+            planned.previous_host_port,
+            planned.host_port,
+            planned.container_port,
+            location
+        );
+    }
+    Ok(())
+}
+
+/// 设置服务的主机端口并保存到配置文件
+fn set_port_override(app: &CliApp, service: String, port: u16) -> Result<()> {
+    PortOverride::parse(&service, port)?;
+
+    let mut config = app.config.as_ref().clone();
+    config.ports.insert(service.clone(), port);
+
+    // 先应用到当前的部署文件，端口冲突时不保存配置
+    apply_port_overrides(
+        &config.ports,
+        &docker::get_compose_file_path(),
+        &docker::get_env_file_path(),
+    )?;
+    config.save_to_file(&app.config_path)?;
+    info!("✅ 已设置服务 {} 的主机端口为 {}", service, port);
+
+    if !PortManager::new().is_port_available(port) {
+        warn!(
+            "⚠️ 端口 {} 当前已被占用，如不是本服务占用，启动服务前请先释放该端口",
+            port
+        );
+    }
+    info!("💡 重启服务后生效: nuwax-cli docker-service restart");
+    Ok(())
+}
+
+/// 修改服务的主机端口并只重建该服务的容器，无需重新部署
+///
+/// 重建失败时还原 .env / docker-compose.yml 并尝试恢复原容器，不保存配置
+pub async fn set_service_port(
+    app: &CliApp,
+    service: String,
+    port: u16,
+    project: Option<String>,
+) -> Result<()> {
+    let port_override = PortOverride::parse(&service, port)?;
+    let compose_path = docker::get_compose_file_path();
+    let env_path = docker::get_env_file_path();
+    if !compose_path.exists() {
+        return Err(anyhow::anyhow!(
+            "docker-compose文件不存在: {}，请先部署服务",
+            compose_path.display()
+        ));
+    }
+
+    let plan = PortManager::new().plan_port_overrides(
+        &compose_path,
+        &env_path,
+        std::slice::from_ref(&port_override),
+    )?;
+    let service_name = port_override.service_name.clone();
+    let previous_port = plan.overrides[0].previous_host_port;
+    if previous_port == port {
+        info!("✅ 服务 {} 的主机端口已是 {}，无需修改", service_name, port);
+        return Ok(());
+    }
+    if !PortManager::new().is_port_available(port) {
+        return Err(anyhow::anyhow!(
+            "端口 {} 已被占用，请先释放该端口或选择其他端口",
+            port
+        ));
+    }
+
+    let original_compose = fs::read_to_string(&compose_path)?;
+    let original_env = fs::read_to_string(&env_path).ok();
+
+    let mut config = app.config.as_ref().clone();
+    config.ports.insert(service.clone(), port);
+    apply_port_overrides(&config.ports, &compose_path, &env_path)?;
+
+    let docker_manager = match project {
+        Some(project) => Arc::new(DockerManager::with_project(
+            compose_path.clone(),
+            env_path.clone(),
+            Some(project),
+        )?),
+        None => app.docker_manager.clone(),
+    };
+
+    info!("🔄 重建服务 {} 的容器...", service_name);
+    if let Err(e) = docker_manager.recreate_service(&service_name).await {
+        error!("❌ 重建服务 {} 失败，还原端口配置: {}", service_name, e);
+        fs::write(&compose_path, &original_compose)?;
+        match &original_env {
+            Some(content) => fs::write(&env_path, content)?,
+            None => {
+                let _ = fs::remove_file(&env_path);
+            }
+        }
+        if let Err(restore_err) = docker_manager.recreate_service(&service_name).await {
+            warn!("⚠️ 恢复服务 {} 的原容器失败: {}", service_name, restore_err);
+        }
+        return Err(anyhow::anyhow!(
+            "修改服务 {} 端口失败，已还原为原端口 {}: {}",
+            service_name,
+            previous_port,
+            e
+        ));
+    }
+
+    config.save_to_file(&app.config_path)?;
+    info!(
+        "✅ 服务 {} 的主机端口已从 {} 修改为 {}",
+        service_name, previous_port, port
+    );
+
+    wait_for_service_healthy(docker_manager, &service_name).await
+}
+
+/// 等待服务容器运行且健康检查通过
+async fn wait_for_service_healthy(
+    docker_manager: Arc<DockerManager>,
+    service_name: &str,
+) -> Result<()> {
+    let health_checker = HealthChecker::new(docker_manager);
+    let deadline = Instant::now() + Duration::from_secs(timeout::SERVICE_START_TIMEOUT);
+
+    loop {
+        let report = health_checker.health_check().await?;
+        let healthy = report.containers.iter().any(|container| {
+            container.name == service_name
+                && is_container_healthy(container)
+                && container.health != Some(HealthStatusEnum::STARTING)
+        });
+        if healthy {
+            info!("🎉 服务 {} 已就绪", service_name);
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(anyhow::anyhow!(
+                "服务 {} 在 {} 秒内未就绪，请通过 nuwax-cli docker-service status 检查",
+                service_name,
+                timeout::SERVICE_START_TIMEOUT
+            ));
+        }
+        tokio::time::sleep(Duration::from_secs(timeout::SERVICE_CHECK_INTERVAL)).await;
+    }
+}
+
+/// 删除服务的端口覆盖
+fn unset_port_override(app: &CliApp, service: &str) -> Result<()> {
+    let mut config = app.config.as_ref().clone();
+    if config.ports.remove(service).is_none() {
+        warn!("⚠️ 服务 {} 没有配置端口覆盖", service);
+        return Ok(());
+    }
+
+    config.save_to_file(&app.config_path)?;
+    info!("✅ 已删除服务 {} 的端口覆盖", service);
+    info!("💡 已写入 .env / docker-compose.yml 的端口不会自动还原，全量升级后恢复为服务包默认端口");
+    Ok(())
+}
+
+/// 显示端口覆盖配置和当前端口映射
+async fn list_port_overrides(app: &CliApp) -> Result<()> {
+    if app.config.ports.is_empty() {
+        info!("📋 未配置端口覆盖");
+    } else {
+        info!("📋 端口覆盖配置:");
+        for (service, port) in &app.config.ports {
+            info!("   {} = {}", service, port);
+        }
+    }
+
+    let compose_path = docker::get_compose_file_path();
+    if !compose_path.exists() {
+        return Ok(());
+    }
+    let mut port_manager = PortManager::new();
+    port_manager.load_env_file(&docker::get_env_file_path())?;
+    let mappings = port_manager.parse_compose_ports(&compose_path).await?;
+
+    info!("📋 当前端口映射:");
+    for mapping in &mappings {
+        info!(
+            "   {} {}:{}/{}",
+            mapping.service_name, mapping.host_port, mapping.container_port, mapping.protocol
+        );
+    }
+    Ok(())
+}
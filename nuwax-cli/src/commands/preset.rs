@@ -0,0 +1,159 @@
+use crate::app::CliApp;
+use crate::cli::PresetCommand;
+use anyhow::Result;
+use client_core::config::AppConfig;
+use client_core::constants::docker;
+use client_core::container::DockerManager;
+use client_core::service_preset::{self, PresetPlan, ServicePreset};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// 处理服务规格预设命令
+pub async fn handle_preset_command(app: &CliApp, cmd: PresetCommand) -> Result<()> {
+    match cmd {
+        PresetCommand::Show { preset } => show_preset(app, preset),
+        PresetCommand::Clear => clear_preset(app),
+    }
+}
+
+/// 保存服务规格预设到配置文件，返回更新后的配置
+pub fn save_service_preset(app: &CliApp, preset: Option<ServicePreset>) -> Result<AppConfig> {
+    let mut config = app.config.as_ref().clone();
+    config.docker.preset = preset;
+    config.save_to_file(&app.config_path)?;
+    Ok(config)
+}
+
+/// 按配置的服务规格预设生成或删除 compose 覆盖文件
+///
+/// 部署和升级会用服务包中的 docker-compose.yml 覆盖之前的文件，部署时调用此函数按新的
+/// compose 文件重新生成；已在运行的停用服务会被停止，`docker compose up` 不会自动停止它们
+pub async fn apply_service_preset(
+    config: &AppConfig,
+    docker_manager: &DockerManager,
+    compose_path: &Path,
+) -> Result<()> {
+    let Some(preset) = config.docker.preset else {
+        if service_preset::remove_override(compose_path)? {
+            info!("🧹 未配置服务规格预设，已删除预设覆盖文件");
+        }
+        return Ok(());
+    };
+    if !compose_path.exists() {
+        warn!(
+            "⚠️ docker-compose文件不存在，跳过服务规格预设: {}",
+            compose_path.display()
+        );
+        return Ok(());
+    }
+
+    let plan = service_preset::plan_preset_for_file(preset, compose_path)?;
+    match service_preset::write_override(compose_path, &plan)? {
+        Some(path) => info!("📐 应用服务规格预设 {}: {}", preset, path.display()),
+        None => info!("📐 服务规格预设 {}: 使用服务包默认配置", preset),
+    }
+    log_plan(&plan);
+
+    for service in &plan.disabled {
+        let grace_period = config.docker.stop.grace_period_for(service);
+        if let Err(e) = docker_manager
+            .stop_service_with_timeout(service, grace_period)
+            .await
+        {
+            warn!("⚠️ 停止已停用的服务 {} 失败: {}", service, e);
+        }
+    }
+    Ok(())
+}
+
+/// 显示预设与服务包默认配置的差异，未指定预设时使用配置中的预设
+fn show_preset(app: &CliApp, preset: Option<ServicePreset>) -> Result<()> {
+    let configured = app.config.docker.preset;
+    info!("📋 服务规格预设:");
+    for candidate in ServicePreset::ALL {
+        let marker = if Some(candidate) == configured {
+            "*"
+        } else {
+            " "
+        };
+        info!("  {} {:<6} {}", marker, candidate, candidate.description());
+    }
+
+    let Some(preset) = preset.or(configured) else {
+        info!("💡 未配置服务规格预设，使用服务包默认配置");
+        info!("💡 应用预设: nuwax-cli docker-service deploy --preset <small|medium|large>");
+        return Ok(());
+    };
+
+    let compose_path = docker::get_compose_file_path();
+    if !compose_path.exists() {
+        warn!(
+            "⚠️ docker-compose文件不存在，无法计算预设差异: {}",
+            compose_path.display()
+        );
+        return Ok(());
+    }
+    let plan = service_preset::plan_preset_for_file(preset, &compose_path)?;
+    info!("");
+    info!("📐 预设 {} 相对服务包默认配置的差异:", preset);
+    if plan.is_empty() {
+        info!("   无差异");
+    }
+    log_plan(&plan);
+
+    let override_path = service_preset::override_path(&compose_path);
+    if Some(preset) != configured {
+        info!(
+            "💡 当前配置的预设不是 {}，应用: nuwax-cli docker-service deploy --preset {}",
+            preset, preset
+        );
+    } else if !plan.is_empty() && !override_path.exists() {
+        info!("💡 覆盖文件尚未生成，执行 nuwax-cli docker-service deploy 后生效");
+    } else if override_path.exists() {
+        info!("📄 覆盖文件: {}", override_path.display());
+    }
+    Ok(())
+}
+
+/// 删除服务规格预设配置和覆盖文件
+fn clear_preset(app: &CliApp) -> Result<()> {
+    if app.config.docker.preset.is_some() {
+        save_service_preset(app, None)?;
+        info!("✅ 已删除服务规格预设配置");
+    } else {
+        info!("📋 未配置服务规格预设");
+    }
+    if service_preset::remove_override(&docker::get_compose_file_path())? {
+        info!("🧹 已删除预设覆盖文件");
+        info!("💡 重启服务后恢复服务包默认配置: nuwax-cli docker-service restart");
+    }
+    Ok(())
+}
+
+/// 输出资源限制变化、停用的服务以及无法应用的部分
+fn log_plan(plan: &PresetPlan) {
+    for change in &plan.limits {
+        info!(
+            "   {}: {} -> {}",
+            change.service,
+            change.current,
+            change.effective()
+        );
+    }
+    for service in &plan.disabled {
+        info!("   {}: 停用", service);
+    }
+    for (service, dependents) in &plan.required {
+        warn!(
+            "⚠️ 服务 {} 被 {} 依赖，保持启用",
+            service,
+            dependents.join(", ")
+        );
+    }
+    if !plan.missing.is_empty() {
+        info!(
+            "   compose文件中不存在的服务已忽略: {}",
+            plan.missing.join(", ")
+        );
+    }
+}
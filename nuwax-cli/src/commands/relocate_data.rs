@@ -0,0 +1,134 @@
+use crate::app::CliApp;
+use crate::utils::prompt;
+use anyhow::{Result, anyhow};
+use client_core::config::AppConfig;
+use client_core::constants::docker;
+use client_core::data_relocation::{
+    apply_data_dir, copy_data_dir, scan_data_dir, switch_data_dir, validate_relocation_target,
+    verify_data_copy,
+};
+use client_core::fs_probe::check_writable;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+/// 部署时把 config.toml 中记录的数据目录重新应用到 compose 和 .env
+///
+/// 服务包中的 docker-compose.yml / .env 使用 `./data`，升级覆盖后需要重新改写
+pub fn apply_data_dir_override(
+    config: &AppConfig,
+    compose_path: &Path,
+    env_path: &Path,
+) -> Result<()> {
+    if let Some(data_dir) = &config.docker.data_dir {
+        apply_data_dir(Path::new(data_dir), None, &[compose_path, env_path])?;
+    }
+    Ok(())
+}
+
+/// `docker-service relocate-data`：把数据目录迁移到 `to`
+///
+/// 停止服务后复制并校验数据，切换数据目录、改写挂载路径并记录到 config.toml，最后重启服务。
+/// 切换前任一步骤失败时保留原数据目录并重启服务；原数据默认改名保留，`remove_source` 时在
+/// 服务重启成功后删除
+pub async fn relocate_data(app: &CliApp, to: PathBuf, remove_source: bool) -> Result<()> {
+    let data_dir = docker::get_data_dir_path();
+    if !data_dir.is_dir() {
+        return Err(anyhow!("数据目录不存在: {}", data_dir.display()));
+    }
+    let target = if to.is_absolute() {
+        to
+    } else {
+        std::env::current_dir()?.join(to)
+    };
+    // 之前迁移过时 data 是指向上次目标目录的符号链接，从实际目录复制
+    let source = fs::canonicalize(&data_dir)?;
+    let previous = app.config.docker.data_dir.as_deref().map(PathBuf::from);
+    validate_relocation_target(&source, &target)?;
+
+    let total = scan_data_dir(&source)?;
+    info!(
+        "🚚 迁移数据目录: {} -> {}（{} 个文件，{:.1} MB）",
+        source.display(),
+        target.display(),
+        total.files,
+        total.bytes as f64 / 1024.0 / 1024.0
+    );
+    check_writable(&target, Some(total.bytes))?;
+    if remove_source
+        && !prompt::confirm(&format!(
+            "迁移完成并重启服务后将删除原数据 {}，确认继续?",
+            source.display()
+        ))?
+    {
+        info!("已取消迁移");
+        return Ok(());
+    }
+
+    info!("⏹️ 停止服务...");
+    app.docker_manager.stop_services().await?;
+
+    let started = Instant::now();
+    let copied = copy_data_dir(&source, &target, &total, &app.events)
+        .and_then(|_| verify_data_copy(&source, &target));
+    let copied = match copied {
+        Ok(copied) => copied,
+        Err(e) => {
+            error!("❌ 复制数据失败，原数据目录未改动: {}", e);
+            warn!(
+                "⚠️ 目标目录 {} 中可能留有部分数据，确认后可手动删除",
+                target.display()
+            );
+            restart_services(app).await;
+            return Err(e);
+        }
+    };
+    info!(
+        "✅ 已复制并校验 {} 个文件（{:.1} MB），耗时 {} 秒",
+        copied.files,
+        copied.bytes as f64 / 1024.0 / 1024.0,
+        started.elapsed().as_secs()
+    );
+
+    let kept = switch_data_dir(&data_dir, &target)?;
+    let rewritten = apply_data_dir(
+        &target,
+        previous.as_deref(),
+        &[
+            &docker::get_compose_file_path(),
+            &docker::get_env_file_path(),
+        ],
+    )?;
+    info!("📝 改写了 {} 处挂载路径", rewritten);
+
+    let mut config = app.config.as_ref().clone();
+    config.docker.data_dir = Some(target.to_string_lossy().to_string());
+    config.save_to_file(&app.config_path)?;
+    info!("💾 新的数据目录已记录到 config.toml 的 docker.data_dir");
+
+    info!("▶️ 启动服务...");
+    app.docker_manager.start_services().await?;
+
+    // 之前迁移过时原数据就是上次的目标目录，否则为改名保留的 data 目录
+    let old_data = kept.unwrap_or(source);
+    if remove_source {
+        info!("🗑️ 删除原数据: {}", old_data.display());
+        fs::remove_dir_all(&old_data)?;
+    } else {
+        info!(
+            "💡 原数据保留在 {}，确认服务正常后可手动删除",
+            old_data.display()
+        );
+    }
+    info!("✅ 数据目录已迁移到 {}", target.display());
+    Ok(())
+}
+
+/// 迁移失败后按原数据目录重新启动服务
+async fn restart_services(app: &CliApp) {
+    info!("▶️ 使用原数据目录重新启动服务...");
+    if let Err(e) = app.docker_manager.start_services().await {
+        error!("❌ 重新启动服务失败: {}，请检查后手动启动", e);
+    }
+}
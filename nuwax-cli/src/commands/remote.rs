@@ -0,0 +1,146 @@
+use crate::cli::{RemoteArgs, RemoteCommand, UpgradeArgs};
+use anyhow::{Result, anyhow};
+use client_core::remote::{RemoteHost, build_remote_command};
+use tracing::info;
+
+/// ssh 自身出错（连接失败、认证失败等）时的退出码
+const SSH_ERROR_EXIT_CODE: i32 = 255;
+
+/// 通过 SSH 在远程主机上执行管理命令
+pub async fn run_remote_command(args: RemoteArgs, command: RemoteCommand) -> Result<()> {
+    let host = RemoteHost::new(args.ssh.clone()).with_identity_file(args.identity.clone());
+    let label = host.target().to_string();
+
+    match command {
+        RemoteCommand::Push { local, remote } => {
+            if !local.exists() {
+                return Err(anyhow!("本地文件不存在: {}", local.display()));
+            }
+            let remote = resolve_remote_path(args.remote_dir.as_deref(), &remote);
+            info!("📤 [{}] 上传 {} -> {}", label, local.display(), remote);
+            host.upload(&local, &remote).await?;
+            info!("✅ [{}] 上传完成", label);
+            Ok(())
+        }
+        RemoteCommand::Pull { remote, local } => {
+            let remote = resolve_remote_path(args.remote_dir.as_deref(), &remote);
+            info!("📥 [{}] 下载 {} -> {}", label, remote, local.display());
+            host.download(&remote, &local).await?;
+            info!("✅ [{}] 下载完成", label);
+            Ok(())
+        }
+        command => {
+            let cli_args = remote_cli_args(command);
+            let cli_args: Vec<&str> = cli_args.iter().map(String::as_str).collect();
+            let remote_command =
+                build_remote_command(args.remote_dir.as_deref(), &args.remote_cli, &cli_args);
+            info!("🌐 [{}] 执行: {}", label, remote_command);
+
+            let code = host
+                .run(&remote_command, |line| info!("[{}] {}", label, line))
+                .await?;
+            match code {
+                0 => Ok(()),
+                SSH_ERROR_EXIT_CODE => Err(anyhow!(
+                    "无法连接到远程主机 {label}，请检查网络、SSH 密钥和主机地址"
+                )),
+                code => Err(anyhow!("远程命令在 {label} 上执行失败（退出码 {code}）")),
+            }
+        }
+    }
+}
+
+/// 将远程子命令转换为远程 nuwax-cli 的参数
+fn remote_cli_args(command: RemoteCommand) -> Vec<String> {
+    match command {
+        RemoteCommand::Status => vec!["status".into()],
+        RemoteCommand::Health { project } => {
+            let mut cli_args = vec!["docker-service".into(), "status".into()];
+            if let Some(project) = project {
+                cli_args.extend(["--project".into(), project]);
+            }
+            cli_args
+        }
+        RemoteCommand::Upgrade {
+            args:
+                UpgradeArgs {
+                    force,
+                    force_full,
+                    check,
+                },
+        } => {
+            let mut cli_args = vec!["upgrade".to_string()];
+            if force {
+                cli_args.push("--force".into());
+            }
+            if force_full {
+                cli_args.push("--force-full".into());
+            }
+            if check {
+                cli_args.push("--check".into());
+            }
+            cli_args
+        }
+        RemoteCommand::Deploy { args } => {
+            let mut cli_args = vec!["auto-upgrade-deploy".to_string(), "run".to_string()];
+            cli_args.extend(args);
+            cli_args
+        }
+        RemoteCommand::Exec { args } => args,
+        RemoteCommand::Push { .. } | RemoteCommand::Pull { .. } => {
+            unreachable!("文件传输命令不经过远程 nuwax-cli")
+        }
+    }
+}
+
+/// 解析远程路径：相对路径基于远程工作目录（远程主机按 POSIX 路径处理）
+fn resolve_remote_path(remote_dir: Option<&str>, path: &str) -> String {
+    match remote_dir {
+        Some(dir) if !path.starts_with('/') && !path.starts_with('~') => {
+            format!("{}/{}", dir.trim_end_matches('/'), path)
+        }
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_cli_args() {
+        assert_eq!(remote_cli_args(RemoteCommand::Status), ["status"]);
+        assert_eq!(
+            remote_cli_args(RemoteCommand::Health {
+                project: Some("nuwax".into())
+            }),
+            ["docker-service", "status", "--project", "nuwax"]
+        );
+        assert_eq!(
+            remote_cli_args(RemoteCommand::Upgrade {
+                args: UpgradeArgs {
+                    force: false,
+                    force_full: true,
+                    check: true
+                }
+            }),
+            ["upgrade", "--force-full", "--check"]
+        );
+        assert_eq!(
+            remote_cli_args(RemoteCommand::Deploy {
+                args: vec!["--resume".into()]
+            }),
+            ["auto-upgrade-deploy", "run", "--resume"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_remote_path() {
+        assert_eq!(
+            resolve_remote_path(Some("/opt/nuwax"), "docker/.env"),
+            "/opt/nuwax/docker/.env"
+        );
+        assert_eq!(resolve_remote_path(Some("/opt/nuwax"), "/tmp/a"), "/tmp/a");
+        assert_eq!(resolve_remote_path(None, "docker/.env"), "docker/.env");
+    }
+}
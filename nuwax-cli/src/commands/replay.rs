@@ -0,0 +1,167 @@
+use anyhow::Result;
+use client_core::api::ApiClient;
+use client_core::sql_diff::generate_schema_diff_with_dialect;
+use client_core::upgrade_session::{RecordedDecision, RecordedSqlDiff, UpgradeSession};
+use client_core::upgrade_strategy::{UpgradeStrategy, UpgradeStrategyManager};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// 回放 `--record` 录制的升级会话：按录制的服务清单和决策输入重新执行升级策略决策和差异SQL生成，
+/// 并与录制时的结果对比，不访问网络、Docker 或本地数据库
+pub async fn run_replay(session_path: &Path) -> Result<()> {
+    let session = UpgradeSession::load(session_path)?;
+
+    info!("🎞️ 回放升级会话: {}", session_path.display());
+    info!("========================");
+    info!("   录制时间: {}", session.recorded_at);
+    info!("   客户端版本: {}", session.client_version);
+    info!("   命令: {}", session.command.join(" "));
+    info!("   API 响应: {} 个", session.api_responses.len());
+    for response in &session.api_responses {
+        info!("     {}", response.url);
+    }
+
+    let mut mismatches = 0;
+
+    if !session.decisions.is_empty() {
+        let Some(manifest) = &session.manifest else {
+            return Err(anyhow::anyhow!(
+                "会话文件中没有服务清单，无法回放升级策略决策"
+            ));
+        };
+        let manifest_text = manifest.to_string();
+        for (index, decision) in session.decisions.iter().enumerate() {
+            info!("");
+            info!("🧭 决策 #{}", index + 1);
+            if !replay_decision(decision, &manifest_text)? {
+                mismatches += 1;
+            }
+        }
+    }
+
+    if !session.file_operations.is_empty() {
+        info!("");
+        info!("📁 录制的文件操作:");
+        for operation in &session.file_operations {
+            info!("   {} ({} 项)", operation.operation, operation.paths.len());
+            for path in &operation.paths {
+                info!("     {}", path);
+            }
+        }
+    }
+
+    for (index, diff) in session.sql_diffs.iter().enumerate() {
+        info!("");
+        info!(
+            "🗄️ 差异SQL #{}: {} -> {} ({})",
+            index + 1,
+            diff.from_version,
+            diff.to_version,
+            diff.dialect
+        );
+        if !replay_sql_diff(diff)? {
+            mismatches += 1;
+        }
+    }
+
+    info!("");
+    if mismatches > 0 {
+        return Err(anyhow::anyhow!("回放结果有 {mismatches} 项与录制时不一致"));
+    }
+    info!("✅ 回放结果与录制时一致");
+    Ok(())
+}
+
+/// 重新执行一次升级策略决策，返回结果是否与录制时一致
+fn replay_decision(decision: &RecordedDecision, manifest_text: &str) -> Result<bool> {
+    info!("   当前版本: {}", decision.current_version);
+    info!("   架构: {}", decision.architecture);
+    info!("   强制全量: {}", decision.force_full);
+    info!("   compose 文件存在: {}", decision.compose_present);
+
+    let manifest = ApiClient::parse_enhanced_service_manifest(manifest_text)?;
+    let explanation = UpgradeStrategyManager::new(
+        decision.current_version.parse()?,
+        decision.force_full,
+        manifest,
+    )
+    .with_architecture(decision.architecture.clone())
+    .explain_with(decision.compose_present);
+
+    info!("   决策依据:");
+    for (index, reason) in explanation.reasons.iter().enumerate() {
+        info!("     {}. {}", index + 1, reason);
+    }
+    if explanation.reasons != decision.reasons {
+        warn!("   ⚠️ 决策依据与录制时不同，录制时为:");
+        for (index, reason) in decision.reasons.iter().enumerate() {
+            warn!("     {}. {}", index + 1, reason);
+        }
+    }
+
+    let replayed = explanation.strategy.as_ref().map(UpgradeStrategy::summary);
+    let recorded = match (&decision.strategy, &decision.error) {
+        (Some(strategy), _) => Ok(strategy.clone()),
+        (None, Some(error)) => Err(error.clone()),
+        (None, None) => Err("未记录".to_string()),
+    };
+    match (&recorded, &replayed) {
+        (Ok(recorded), Ok(replayed)) if recorded == replayed => {
+            info!("   ✅ 决策结果一致: {}", replayed);
+            Ok(true)
+        }
+        (Err(_), Err(replayed)) => {
+            info!("   ✅ 决策同样失败: {}", replayed);
+            Ok(true)
+        }
+        _ => {
+            warn!("   ❌ 决策结果不一致");
+            warn!("      录制: {}", describe(&recorded));
+            warn!("      回放: {}", describe(&replayed));
+            Ok(false)
+        }
+    }
+}
+
+/// 按录制的新旧表结构重新生成差异SQL，返回可执行语句是否与录制时一致
+fn replay_sql_diff(diff: &RecordedSqlDiff) -> Result<bool> {
+    let (diff_sql, description) = generate_schema_diff_with_dialect(
+        diff.dialect,
+        diff.old_schema.as_deref(),
+        &diff.new_schema,
+        Some(&diff.from_version),
+        &diff.to_version,
+    )
+    .map_err(|e| anyhow::anyhow!("重新生成差异SQL失败: {e}"))?;
+    info!("   {}", description);
+
+    // 注释中含生成时间，只比较可执行语句
+    let replayed = statements(&diff_sql);
+    let recorded = statements(&diff.diff_sql);
+    if replayed == recorded {
+        info!("   ✅ 差异SQL一致（{} 行可执行语句）", replayed.len());
+        return Ok(true);
+    }
+    warn!("   ❌ 差异SQL不一致");
+    for line in recorded.iter().filter(|line| !replayed.contains(line)) {
+        warn!("      - {}", line);
+    }
+    for line in replayed.iter().filter(|line| !recorded.contains(line)) {
+        warn!("      + {}", line);
+    }
+    Ok(false)
+}
+
+fn statements(sql: &str) -> Vec<&str> {
+    sql.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("--"))
+        .collect()
+}
+
+fn describe(result: &std::result::Result<String, String>) -> String {
+    match result {
+        Ok(strategy) => strategy.clone(),
+        Err(error) => format!("失败（{error}）"),
+    }
+}
@@ -0,0 +1,31 @@
+use anyhow::Result;
+use client_core::self_test;
+use tracing::{error, info};
+
+/// 在临时目录中运行各核心子系统的自检，任一检查失败时返回错误
+pub async fn run_self_test(skip_docker: bool) -> Result<()> {
+    info!("🧪 开始自检（使用临时目录，不改动当前部署）...");
+
+    let results = self_test::run_self_test(skip_docker).await?;
+    for result in &results {
+        let elapsed = result.elapsed.as_millis();
+        match &result.outcome {
+            Ok(detail) => info!("   ✅ {} ({} ms): {}", result.name, elapsed, detail),
+            Err(e) => error!("   ❌ {} ({} ms): {}", result.name, elapsed, e),
+        }
+    }
+    if skip_docker {
+        info!("   ⏭️ Docker连接: 已跳过 (--skip-docker)");
+    }
+
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "自检未通过：{} 项检查中有 {} 项失败",
+            results.len(),
+            failed
+        ));
+    }
+    info!("🎉 自检通过：{} 项检查全部成功", results.len());
+    Ok(())
+}
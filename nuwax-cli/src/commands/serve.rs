@@ -0,0 +1,541 @@
+//! # 本机 HTTP API 服务
+//!
+//! 以 REST 接口提供 [`crate::api`] 中的操作，桌面端和自动化脚本无需为每个操作启动一个
+//! CLI 进程。服务只监听本机地址，每个请求都需要携带访问令牌
+//! （`Authorization: Bearer <token>`），令牌保存在 `--token-file` 指定的文件中。
+//!
+//! | 方法 | 路径 | 说明 |
+//! | --- | --- | --- |
+//! | GET | `/api/v1/status` | 客户端和服务版本 |
+//! | GET | `/api/v1/health?services=mysql,backend&exclude=` | 服务健康检查，可限定检查范围 |
+//! | GET | `/api/v1/update?force_full=true` | 检查更新 |
+//! | POST | `/api/v1/upgrade` | 下载升级包，以 SSE 推送进度 |
+//! | GET | `/api/v1/backups?type=manual&all_namespaces=true` | 列出备份 |
+//! | POST | `/api/v1/backups/{id}/rollback` | 从备份回滚 |
+//!
+//! 升级和回滚同一时间只允许执行一个，其余请求返回 409。
+
+use crate::api::{
+    self, CheckUpdateParams, HealthCheckParams, ListBackupsParams, RollbackParams, UpgradeParams,
+};
+use crate::app::CliApp;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, mpsc};
+use tracing::{debug, info, warn};
+
+/// 读取 HTTP 请求的超时时间
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HTTP 请求头的最大长度
+const MAX_HEADER_BYTES: usize = 8 * 1024;
+
+/// HTTP 请求体的最大长度
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// API 路径前缀
+const API_PREFIX: &str = "/api/v1";
+
+/// 服务共享状态
+struct ServerState {
+    app: CliApp,
+    token: String,
+    /// 升级、回滚等修改部署的操作互斥执行
+    busy: Arc<Mutex<()>>,
+}
+
+/// 解析后的 HTTP 请求
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// 查询参数是否为 `true` / `1`，只写参数名时也视为开启
+    fn query_flag(&self, name: &str) -> bool {
+        self.query(name)
+            .is_some_and(|value| matches!(value, "" | "1" | "true"))
+    }
+
+    /// 逗号分隔的查询参数，未提供时为空
+    fn query_list(&self, name: &str) -> Vec<String> {
+        self.query(name)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 解析 JSON 请求体，请求体为空时使用默认值
+    fn json<T: for<'de> Deserialize<'de> + Default>(&self) -> Result<T> {
+        if self.body.iter().all(u8::is_ascii_whitespace) {
+            return Ok(T::default());
+        }
+        serde_json::from_slice(&self.body).context("请求体不是有效的 JSON")
+    }
+}
+
+/// 回滚请求体，字段含义与 [`RollbackParams`] 相同
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct RollbackRequest {
+    rollback_data: bool,
+    restore_system: bool,
+    auto_start_service: bool,
+    allow_other_namespace: bool,
+}
+
+impl Default for RollbackRequest {
+    fn default() -> Self {
+        Self {
+            rollback_data: false,
+            restore_system: false,
+            auto_start_service: true,
+            allow_other_namespace: false,
+        }
+    }
+}
+
+/// 错误响应
+#[derive(Debug, Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// 在 `listen` 上启动 API 服务，直到收到中断信号
+pub async fn run_serve(app: &CliApp, listen: SocketAddr, token_file: &Path) -> Result<()> {
+    if !listen.ip().is_loopback() {
+        bail!("API 服务只允许监听本机地址（127.0.0.1 或 ::1）: {listen}");
+    }
+    let token = load_or_create_token(token_file)?;
+    let listener = TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("无法监听 {listen}"))?;
+    let state = Arc::new(ServerState {
+        app: app.clone(),
+        token,
+        busy: Arc::new(Mutex::new(())),
+    });
+
+    info!("🌐 API 服务已启动: http://{}{}", listen, API_PREFIX);
+    info!("   访问令牌文件: {}", token_file.display());
+
+    tokio::select! {
+        _ = accept_connections(listener, state) => Ok(()),
+        _ = tokio::signal::ctrl_c() => {
+            info!("🛑 收到中断信号，API 服务退出");
+            Ok(())
+        }
+    }
+}
+
+/// 读取令牌文件，文件不存在或为空时生成新令牌并只允许所有者读写
+fn load_or_create_token(path: &Path) -> Result<String> {
+    if let Some(token) = fs::read_to_string(path)
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|token| !token.is_empty())
+    {
+        return Ok(token);
+    }
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    fs::write(path, format!("{token}\n"))
+        .with_context(|| format!("无法写入访问令牌: {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    info!("🔑 已生成访问令牌: {}", path.display());
+    Ok(token)
+}
+
+async fn accept_connections(listener: TcpListener, state: Arc<ServerState>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, state).await {
+                        debug!("处理来自 {} 的请求失败: {}", peer, e);
+                    }
+                });
+            }
+            Err(e) => warn!("⚠️ 接受连接失败: {}", e),
+        }
+    }
+}
+
+/// 处理单个请求：校验令牌后按路径分发
+async fn handle_connection(mut stream: TcpStream, state: Arc<ServerState>) -> Result<()> {
+    let request = match tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => request,
+        Ok(Err(e)) => return write_error(&mut stream, "400 Bad Request", &e).await,
+        Err(_) => bail!("读取请求超时"),
+    };
+
+    if !is_authorized(&request, &state.token) {
+        return write_json(
+            &mut stream,
+            "401 Unauthorized",
+            &ErrorBody {
+                error: "缺少或无效的访问令牌".to_string(),
+            },
+        )
+        .await;
+    }
+    debug!("API 请求: {} {}", request.method, request.path);
+
+    let app = &state.app;
+    let route = request.path.strip_prefix(API_PREFIX).unwrap_or("");
+    match (request.method.as_str(), route) {
+        ("GET", "/status") => respond(&mut stream, api::status(app).await).await,
+        ("GET", "/health") => {
+            let params = HealthCheckParams {
+                services: request.query_list("services"),
+                exclude: request.query_list("exclude"),
+            };
+            respond(&mut stream, api::health_check(app, params).await).await
+        }
+        ("GET", "/update") => {
+            let params = CheckUpdateParams {
+                force_full: request.query_flag("force_full"),
+            };
+            respond(&mut stream, api::check_update(app, params).await).await
+        }
+        ("GET", "/backups") => {
+            let backup_type = match request.query("type").map(str::parse).transpose() {
+                Ok(backup_type) => backup_type,
+                Err(e) => {
+                    return write_error(&mut stream, "400 Bad Request", &anyhow::anyhow!(e)).await;
+                }
+            };
+            let params = ListBackupsParams {
+                backup_type,
+                all_namespaces: request.query_flag("all_namespaces"),
+            };
+            respond(&mut stream, api::list_backups(app, params).await).await
+        }
+        ("POST", "/upgrade") => {
+            let params: UpgradeParams = match request.json() {
+                Ok(params) => params,
+                Err(e) => return write_error(&mut stream, "400 Bad Request", &e).await,
+            };
+            stream_upgrade(stream, state.clone(), params).await
+        }
+        ("POST", route) if rollback_backup_id(route).is_some() => {
+            let body: RollbackRequest = match request.json() {
+                Ok(body) => body,
+                Err(e) => return write_error(&mut stream, "400 Bad Request", &e).await,
+            };
+            let Ok(_guard) = state.busy.clone().try_lock_owned() else {
+                return write_busy(&mut stream).await;
+            };
+            let params = RollbackParams {
+                backup_id: rollback_backup_id(route).unwrap_or_default(),
+                rollback_data: body.rollback_data,
+                restore_system: body.restore_system,
+                auto_start_service: body.auto_start_service,
+                allow_other_namespace: body.allow_other_namespace,
+            };
+            info!("⏪ API 请求回滚到备份 {}", params.backup_id);
+            let result = api::rollback(app, params)
+                .await
+                .map(|()| serde_json::json!({ "ok": true }));
+            respond(&mut stream, result).await
+        }
+        _ => {
+            write_json(
+                &mut stream,
+                "404 Not Found",
+                &ErrorBody {
+                    error: format!("未知接口: {} {}", request.method, request.path),
+                },
+            )
+            .await
+        }
+    }
+}
+
+/// 下载升级包，以 SSE 推送 [`api::UpgradeEvent`]，结束时发送 `result` 或 `error` 事件
+///
+/// 升级在独立任务中执行，客户端断开连接不会中断下载
+async fn stream_upgrade(
+    mut stream: TcpStream,
+    state: Arc<ServerState>,
+    params: UpgradeParams,
+) -> Result<()> {
+    let Ok(guard) = state.busy.clone().try_lock_owned() else {
+        return write_busy(&mut stream).await;
+    };
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let progress_sender = sender.clone();
+    let app = state.app.clone();
+    tokio::spawn(async move {
+        let _guard = guard;
+        info!("⬇️ API 请求下载升级包");
+        let result = api::upgrade_with_progress(&app, params, move |event| {
+            let _ = progress_sender.send(sse_event(event_name(&event), &event));
+        })
+        .await;
+        let message = match result {
+            Ok(result) => sse_event("result", &result),
+            Err(e) => {
+                warn!("⚠️ API 升级失败: {:#}", e);
+                sse_event(
+                    "error",
+                    &ErrorBody {
+                        error: format!("{e:#}"),
+                    },
+                )
+            }
+        };
+        let _ = sender.send(message);
+    });
+
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+    while let Some(message) = receiver.recv().await {
+        if stream.write_all(message.as_bytes()).await.is_err() {
+            debug!("SSE 客户端已断开，升级继续在后台执行");
+            return Ok(());
+        }
+    }
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// 读取请求头和请求体
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if data.len() >= MAX_HEADER_BYTES {
+            bail!("请求头过长");
+        }
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            bail!("连接已关闭");
+        }
+        data.extend_from_slice(&buf[..n]);
+    };
+
+    let mut request = parse_head(&data[..header_end])?;
+    let content_length = match request.header("Content-Length") {
+        Some(value) => value
+            .trim()
+            .parse::<usize>()
+            .context("无效的 Content-Length")?,
+        None => 0,
+    };
+    if content_length > MAX_BODY_BYTES {
+        bail!("请求体过长");
+    }
+    let mut body = data[header_end..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            bail!("请求体不完整");
+        }
+        body.extend_from_slice(&buf[..n]);
+    }
+    body.truncate(content_length);
+    request.body = body;
+    Ok(request)
+}
+
+/// 解析请求行和请求头
+fn parse_head(head: &[u8]) -> Result<Request> {
+    let head = String::from_utf8_lossy(head);
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        bail!("无效的请求行");
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (key.to_string(), value.to_string())
+            })
+            .collect(),
+        headers: lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect(),
+        body: Vec::new(),
+    })
+}
+
+/// 校验 `Authorization: Bearer <token>`
+fn is_authorized(request: &Request, token: &str) -> bool {
+    request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|provided| constant_time_eq(provided.trim().as_bytes(), token.as_bytes()))
+}
+
+/// 比较耗时与内容无关，避免通过响应时间猜测令牌
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// `/backups/{id}/rollback` 中的备份 ID
+fn rollback_backup_id(route: &str) -> Option<i64> {
+    route
+        .strip_prefix("/backups/")?
+        .strip_suffix("/rollback")?
+        .parse()
+        .ok()
+}
+
+fn event_name(event: &api::UpgradeEvent) -> &'static str {
+    match event {
+        api::UpgradeEvent::Checked { .. } => "checked",
+        api::UpgradeEvent::DownloadStarted { .. } => "download_started",
+        api::UpgradeEvent::Downloading { .. } => "downloading",
+        api::UpgradeEvent::Completed { .. } => "completed",
+    }
+}
+
+fn sse_event<T: Serialize>(name: &str, data: &T) -> String {
+    let data = serde_json::to_string(data).unwrap_or_else(|_| "{}".to_string());
+    format!("event: {name}\ndata: {data}\n\n")
+}
+
+/// 成功时返回 JSON 结果，失败时返回 500 和错误信息
+async fn respond<T: Serialize>(stream: &mut TcpStream, result: Result<T>) -> Result<()> {
+    match result {
+        Ok(value) => write_json(stream, "200 OK", &value).await,
+        Err(e) => write_error(stream, "500 Internal Server Error", &e).await,
+    }
+}
+
+async fn write_error(stream: &mut TcpStream, status: &str, error: &anyhow::Error) -> Result<()> {
+    write_json(
+        stream,
+        status,
+        &ErrorBody {
+            error: format!("{error:#}"),
+        },
+    )
+    .await
+}
+
+async fn write_busy(stream: &mut TcpStream) -> Result<()> {
+    write_json(
+        stream,
+        "409 Conflict",
+        &ErrorBody {
+            error: "已有升级或回滚正在执行".to_string(),
+        },
+    )
+    .await
+}
+
+async fn write_json<T: Serialize>(stream: &mut TcpStream, status: &str, value: &T) -> Result<()> {
+    let body = serde_json::to_string(value)?;
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_and_authorization() {
+        let request = parse_head(
+            b"GET /api/v1/backups?type=manual&all_namespaces HTTP/1.1\r\nHost: localhost\r\nauthorization: Bearer secret\r\n\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/api/v1/backups");
+        assert_eq!(request.query("type"), Some("manual"));
+        assert!(request.query_flag("all_namespaces"));
+        assert!(!request.query_flag("force_full"));
+        assert!(is_authorized(&request, "secret"));
+        assert!(!is_authorized(&request, "secret2"));
+        assert!(!is_authorized(&Request::default(), "secret"));
+    }
+
+    #[test]
+    fn test_rollback_route_and_body() {
+        assert_eq!(rollback_backup_id("/backups/42/rollback"), Some(42));
+        assert_eq!(rollback_backup_id("/backups/abc/rollback"), None);
+        assert_eq!(rollback_backup_id("/backups/42"), None);
+
+        let request = Request {
+            body: br#"{"rollback_data": true}"#.to_vec(),
+            ..Default::default()
+        };
+        let body: RollbackRequest = request.json().unwrap();
+        assert!(body.rollback_data);
+        assert!(body.auto_start_service);
+        let empty: RollbackRequest = Request::default().json().unwrap();
+        assert!(!empty.rollback_data);
+    }
+
+    #[test]
+    fn test_token_file_is_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("serve.token");
+
+        let token = load_or_create_token(&path).unwrap();
+        assert_eq!(token.len(), 32);
+        assert_eq!(load_or_create_token(&path).unwrap(), token);
+    }
+}
@@ -1,9 +1,12 @@
 use std::sync::Arc;
 
+use crate::commands::cache::calculate_directory_size;
 use crate::docker_utils;
 use crate::{app::CliApp, docker_service::health_check::HealthChecker};
 use anyhow::Result;
 use client_core::container::{DockerManager, ServiceStatus};
+use client_core::database::TaskType;
+use client_core::deploy_checkpoint::DeployCheckpointStore;
 use tracing::{error, info, warn};
 
 /// 显示客户端版本信息（标题和基本信息）
@@ -24,7 +27,7 @@ pub async fn run_status(app: &CliApp) -> Result<()> {
 pub async fn run_status_details(app: &CliApp) -> Result<()> {
     // 继续显示其他基本信息
     info!("   Docker服务版本: {}", app.config.get_docker_versions());
-    info!("   配置文件: {}", "config.toml");
+    info!("   配置文件: {}", app.config_path.display());
 
     // 显示客户端UUID
     let client_uuid = app.database.get_or_create_client_uuid().await?;
@@ -61,9 +64,26 @@ pub async fn run_status_details(app: &CliApp) -> Result<()> {
         info!("   ❌ 服务包文件: {} (不存在)", download_path.display());
     }
 
+    // 以下信息只读取配置、数据库和本地文件，没有 Docker 时同样可用
+    show_backup_summary(app).await;
+    show_pending_tasks(app).await;
+    show_cache_usage(app);
+
     // Docker服务状态
     info!("🐳 Docker服务状态:");
-    if docker_compose_path.exists() {
+    let docker_available = match app.docker_manager.probe_docker_daemon().await {
+        Ok(version) => {
+            info!("   ✅ Docker服务版本: {}", version);
+            true
+        }
+        Err(e) => {
+            warn!("   ⚫ 不可用，无法获取服务运行状态: {}", e);
+            false
+        }
+    };
+    if !docker_compose_path.exists() {
+        warn!("   ❌ Docker Compose文件不存在，服务未初始化");
+    } else if docker_available {
         info!("   📋 Docker Compose文件已就绪");
 
         // 检查具体的服务状态
@@ -79,13 +99,14 @@ pub async fn run_status_details(app: &CliApp) -> Result<()> {
                 info!("      - 使用 'docker-compose ps' 手动查看状态");
             }
         }
-    } else {
-        warn!("   ❌ Docker Compose文件不存在，服务未初始化");
     }
 
     // 根据状态提供建议
     info!("💡 状态分析和建议:");
 
+    if !docker_available {
+        info!("   🐳 未检测到可用的 Docker，部署和启动服务前需先安装并启动 Docker");
+    }
     if !docker_compose_path.exists() && !download_path.exists() {
         info!("   🆕 您似乎是首次使用");
         info!("   📝 建议执行以下步骤:");
@@ -108,6 +129,82 @@ pub async fn run_status_details(app: &CliApp) -> Result<()> {
     Ok(())
 }
 
+/// 显示备份数量和最近一次备份
+async fn show_backup_summary(app: &CliApp) {
+    info!("📦 备份:");
+    match app.database.get_all_backups().await {
+        Ok(backups) => match backups.first() {
+            Some(latest) => {
+                info!("   备份数量: {}", backups.len());
+                info!(
+                    "   最近备份: #{} {} ({}, 版本 {})",
+                    latest.id,
+                    latest.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    latest.backup_type.display_name(),
+                    latest.service_version
+                );
+            }
+            None => info!("   暂无备份记录"),
+        },
+        Err(e) => warn!("   ⚠️ 读取备份记录失败: {}", e),
+    }
+}
+
+/// 显示待执行的计划任务和未完成的升级部署
+async fn show_pending_tasks(app: &CliApp) {
+    info!("🗓️ 待执行任务:");
+    let mut has_tasks = false;
+    match app.database.get_pending_tasks().await {
+        Ok(tasks) => {
+            for task in &tasks {
+                has_tasks = true;
+                let task_type = match task.task_type {
+                    TaskType::ServiceUpgrade => "服务升级",
+                };
+                info!(
+                    "   #{} {} -> {} (计划于 {})",
+                    task.id,
+                    task_type,
+                    task.target_version,
+                    task.scheduled_at.format("%Y-%m-%d %H:%M:%S")
+                );
+            }
+        }
+        Err(e) => warn!("   ⚠️ 读取计划任务失败: {}", e),
+    }
+    if let Some(checkpoint) = DeployCheckpointStore::default().load() {
+        has_tasks = true;
+        let last_phase = checkpoint
+            .last_completed_phase()
+            .map_or_else(|| "无".to_string(), |phase| phase.to_string());
+        info!(
+            "   未完成的升级部署: 目标版本 {}，已完成阶段: {}（执行 'nuwax-cli auto-upgrade-deploy run' 继续）",
+            checkpoint.target_version, last_phase
+        );
+    }
+    if !has_tasks {
+        info!("   无");
+    }
+}
+
+/// 显示缓存目录占用
+fn show_cache_usage(app: &CliApp) {
+    info!("💾 缓存使用:");
+    let cache_dir = app.config.get_cache_dir();
+    if !cache_dir.exists() {
+        info!("   缓存目录不存在: {}", cache_dir.display());
+        return;
+    }
+    match calculate_directory_size(&cache_dir) {
+        Ok(size) => info!(
+            "   {}: {:.2} MB",
+            cache_dir.display(),
+            size as f64 / 1024.0 / 1024.0
+        ),
+        Err(e) => warn!("   ⚠️ 计算缓存大小失败: {}", e),
+    }
+}
+
 /// 显示API配置信息
 pub async fn run_api_info(app: &CliApp) -> Result<()> {
     let api_config = app.api_client.get_config();
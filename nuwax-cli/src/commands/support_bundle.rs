@@ -0,0 +1,425 @@
+use crate::app::CliApp;
+use crate::commands::cache::calculate_directory_size;
+use crate::docker_service::health_check::HealthChecker;
+use crate::utils::{active_log_file, log_env_var, prompt};
+use anyhow::Result;
+use client_core::constants::{docker, upgrade};
+use client_core::paths;
+use client_core::redact::{REDACTED, is_sensitive_key};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{info, warn};
+
+/// 单个日志文件最多收集的字节数（只保留末尾部分）
+const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+
+/// 诊断包中最多包含的备份记录数
+const MAX_HISTORY_RECORDS: usize = 50;
+
+/// 诊断包条目
+struct BundleItem {
+    /// 在诊断包中的路径
+    name: String,
+    /// 条目说明（在确认列表中展示）
+    description: String,
+    /// 条目内容
+    content: Vec<u8>,
+}
+
+impl BundleItem {
+    fn new(name: impl Into<String>, description: impl Into<String>, content: Vec<u8>) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            content,
+        }
+    }
+}
+
+/// 收集诊断信息并打包为 tar.gz
+pub async fn run_support_bundle(app: &CliApp, output: Option<PathBuf>) -> Result<()> {
+    info!("🩺 正在收集诊断信息...");
+
+    let items = collect_bundle_items(app).await;
+    let output = output.unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "nuwax-support-bundle-{}.tar.gz",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ))
+    });
+
+    info!("📋 诊断包将包含以下内容:");
+    for item in &items {
+        info!(
+            "   - {:<28} {:>10}  {}",
+            item.name,
+            format!("{:.1} KB", item.content.len() as f64 / 1024.0),
+            item.description
+        );
+    }
+    info!("📦 输出文件: {}", output.display());
+    info!("🔒 config.toml 中的密码、令牌等敏感字段已脱敏，.env 文件不会被收集");
+
+    if !prompt::confirm("确认生成诊断包?")? {
+        info!("👋 操作已取消");
+        return Ok(());
+    }
+
+    write_bundle(&output, &items)?;
+
+    let size = std::fs::metadata(&output)?.len();
+    info!(
+        "✅ 诊断包已生成: {} ({:.2} MB)",
+        output.display(),
+        size as f64 / 1024.0 / 1024.0
+    );
+    info!("💡 请将该文件附加到工单中提交给技术支持");
+
+    Ok(())
+}
+
+/// 收集所有诊断条目，单项失败只记录警告，不影响其他条目
+async fn collect_bundle_items(app: &CliApp) -> Vec<BundleItem> {
+    let mut items = vec![BundleItem::new(
+        "versions.txt",
+        "客户端、服务与 Docker 版本信息",
+        collect_versions(app).into_bytes(),
+    )];
+
+    // 配置文件（脱敏）
+    match std::fs::read_to_string(&app.config_path) {
+        Ok(content) => items.push(BundleItem::new(
+            "config.toml",
+            "配置文件（敏感字段已脱敏）",
+            redact_config(&content).into_bytes(),
+        )),
+        Err(e) => warn!("⚠️ 读取配置文件失败: {}", e),
+    }
+
+    // 健康检查报告
+    let health_checker = HealthChecker::new(app.docker_manager.clone());
+    let health_json = match health_checker.health_check().await {
+        Ok(report) => serde_json::to_string_pretty(&report).unwrap_or_default(),
+        Err(e) => {
+            warn!("⚠️ 健康检查失败: {}", e);
+            serde_json::json!({ "error": e.to_string() }).to_string()
+        }
+    };
+    items.push(BundleItem::new(
+        "health_report.json",
+        "服务健康检查报告",
+        health_json.into_bytes(),
+    ));
+
+    // docker-compose 文件
+    let compose_path = Path::new(&app.config.docker.compose_file);
+    match std::fs::read(compose_path) {
+        Ok(content) => items.push(BundleItem::new(
+            "docker-compose.yml",
+            "当前使用的 docker-compose 文件",
+            content,
+        )),
+        Err(e) => warn!("⚠️ 读取 docker-compose 文件失败: {}", e),
+    }
+
+    // 操作历史
+    match collect_history(app).await {
+        Ok(history) => items.push(BundleItem::new(
+            "history.json",
+            "最近的备份记录和计划任务",
+            history.into_bytes(),
+        )),
+        Err(e) => warn!("⚠️ 读取操作历史失败: {}", e),
+    }
+
+    items.push(BundleItem::new(
+        "disk_usage.txt",
+        "磁盘空间与工作目录占用",
+        collect_disk_usage(app).into_bytes(),
+    ));
+
+    // 日志
+    items.extend(collect_logs());
+
+    // 最近一次服务启动失败时收集的容器状态和日志
+    if let Ok(content) = std::fs::read(upgrade::get_startup_failure_report_path()) {
+        items.push(BundleItem::new(
+            "startup_failure_report.json",
+            "最近一次服务启动失败的容器状态与日志",
+            content,
+        ));
+    }
+
+    // 最近一次的 SQL 差异文件
+    let diff_sql_path = paths::temp_sql_dir().join("upgrade_diff.sql");
+    if let Ok(content) = std::fs::read(&diff_sql_path) {
+        items.push(BundleItem::new(
+            "upgrade_diff.sql",
+            "最近一次升级生成的 SQL 差异",
+            content,
+        ));
+    }
+
+    items
+}
+
+/// 收集版本信息
+fn collect_versions(app: &CliApp) -> String {
+    let mut lines = vec![
+        format!("nuwax-cli: v{}", env!("CARGO_PKG_VERSION")),
+        format!("docker_service: {}", app.config.get_docker_versions()),
+        format!("os: {} ({})", std::env::consts::OS, std::env::consts::ARCH),
+        format!(
+            "collected_at: {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S %z")
+        ),
+    ];
+    lines.push(format!(
+        "docker: {}",
+        command_output("docker", &["--version"]).unwrap_or_else(|| "不可用".to_string())
+    ));
+    lines.push(format!(
+        "docker compose: {}",
+        command_output("docker", &["compose", "version"]).unwrap_or_else(|| "不可用".to_string())
+    ));
+    lines.join("\n") + "\n"
+}
+
+/// 收集最近的备份记录和待执行任务
+async fn collect_history(app: &CliApp) -> Result<String> {
+    let mut backups = app.database.get_all_backups().await?;
+    backups.truncate(MAX_HISTORY_RECORDS);
+    let tasks = app.database.get_pending_tasks().await?;
+
+    Ok(serde_json::to_string_pretty(&serde_json::json!({
+        "backups": backups,
+        "pending_tasks": tasks,
+    }))?)
+}
+
+/// 收集磁盘空间和工作目录占用情况
+fn collect_disk_usage(app: &CliApp) -> String {
+    let mut report = String::new();
+
+    if cfg!(unix) {
+        if let Some(df) = command_output("df", &["-h", "."]) {
+            report.push_str(&df);
+            report.push_str("\n\n");
+        }
+    }
+
+    let directories = [
+        ("docker", docker::get_docker_work_dir()),
+        ("backups", app.config.get_backup_dir()),
+        ("downloads", app.config.get_download_dir()),
+        ("cache", app.config.get_cache_dir()),
+    ];
+    for (label, dir) in directories {
+        let size = if dir.exists() {
+            calculate_directory_size(&dir)
+                .map(|size| format!("{:.2} MB", size as f64 / 1024.0 / 1024.0))
+                .unwrap_or_else(|e| format!("统计失败: {e}"))
+        } else {
+            "不存在".to_string()
+        };
+        report.push_str(&format!("{label:<10} {:<40} {size}\n", dir.display()));
+    }
+
+    report
+}
+
+/// 收集日志：优先使用当前日志文件（--log-file 或 NUWAX_LOG_FILE），否则尝试读取 Docker 的 systemd journal
+fn collect_logs() -> Vec<BundleItem> {
+    let log_file = active_log_file()
+        .map(Path::to_path_buf)
+        .or_else(|| log_env_var("LOG_FILE").map(PathBuf::from));
+    if let Some(log_path) = log_file {
+        match read_tail(&log_path, MAX_LOG_BYTES) {
+            Ok(content) => {
+                let file_name = log_path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "nuwax-cli.log".to_string());
+                return vec![BundleItem::new(
+                    format!("logs/{file_name}"),
+                    "客户端日志",
+                    content,
+                )];
+            }
+            Err(e) => warn!("⚠️ 读取日志文件 {} 失败: {}", log_path.display(), e),
+        }
+    }
+
+    if cfg!(target_os = "linux") {
+        if let Some(journal) = command_output(
+            "journalctl",
+            &["--no-pager", "-u", "docker", "--since", "24 hours ago"],
+        ) {
+            return vec![BundleItem::new(
+                "logs/docker_journal.log",
+                "最近 24 小时的 Docker 服务日志 (journalctl)",
+                journal.into_bytes(),
+            )];
+        }
+    }
+
+    warn!("⚠️ 未找到可收集的日志（可设置 NUWAX_LOG_FILE 将日志写入文件）");
+    Vec::new()
+}
+
+/// 读取文件末尾最多 `max_bytes` 字节
+fn read_tail(path: &Path, max_bytes: u64) -> io::Result<Vec<u8>> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    if len > max_bytes {
+        file.seek(SeekFrom::Start(len - max_bytes))?;
+    }
+    let mut content = Vec::new();
+    file.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// 执行外部命令，成功时返回去除首尾空白的标准输出
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// 对配置文件内容脱敏：敏感键对应的值替换为占位符
+///
+/// 无法解析为 TOML 时逐行处理，保证不会把原文原样带出
+fn redact_config(content: &str) -> String {
+    match content.parse::<toml::Table>() {
+        Ok(mut table) => {
+            redact_table(&mut table);
+            toml::to_string_pretty(&table).unwrap_or_default()
+        }
+        Err(_) => content
+            .lines()
+            .map(|line| match line.split_once('=') {
+                Some((key, _)) if is_sensitive_key(key) => format!("{key}= \"{REDACTED}\""),
+                _ => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn redact_table(table: &mut toml::Table) {
+    for (key, value) in table.iter_mut() {
+        if is_sensitive_key(key) && !value.is_table() && !value.is_array() {
+            *value = toml::Value::String(REDACTED.to_string());
+        } else {
+            redact_value(value);
+        }
+    }
+}
+
+fn redact_value(value: &mut toml::Value) {
+    match value {
+        toml::Value::Table(table) => redact_table(table),
+        toml::Value::Array(array) => array.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+/// 将所有条目写入 tar.gz
+fn write_bundle(output: &Path, items: &[BundleItem]) -> Result<()> {
+    if let Some(parent) = output.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+
+    let file = std::fs::File::create(output)?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let mtime = chrono::Utc::now().timestamp() as u64;
+
+    for item in items {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(item.content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+        builder.append_data(&mut header, &item.name, item.content.as_slice())?;
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_config_masks_sensitive_keys() {
+        let content = r#"
+[versions]
+docker_service = "1.0.0"
+
+[mysql]
+user = "root"
+password = "p@ss"
+
+[api]
+access_token = "abc"
+endpoints = [{ url = "https://example.com", api_key = "k" }]
+"#;
+        let redacted = redact_config(content);
+
+        assert!(redacted.contains("docker_service = \"1.0.0\""));
+        assert!(redacted.contains("user = \"root\""));
+        assert!(!redacted.contains("p@ss"));
+        assert!(!redacted.contains("\"abc\""));
+        assert!(!redacted.contains("\"k\""));
+        assert!(redacted.contains(REDACTED));
+    }
+
+    #[test]
+    fn test_redact_config_falls_back_to_line_mode() {
+        let content = "broken = [\npassword = \"p@ss\"";
+        let redacted = redact_config(content);
+
+        assert!(!redacted.contains("p@ss"));
+        assert!(redacted.contains("broken = ["));
+    }
+
+    #[test]
+    fn test_write_bundle_roundtrip() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let output = temp_dir.path().join("bundle.tar.gz");
+        let items = vec![
+            BundleItem::new("versions.txt", "", b"nuwax-cli: v1".to_vec()),
+            BundleItem::new("logs/duck.log", "", b"line".to_vec()),
+        ];
+
+        write_bundle(&output, &items).unwrap();
+
+        let file = std::fs::File::open(&output).unwrap();
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+        let names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["versions.txt", "logs/duck.log"]);
+    }
+
+    #[test]
+    fn test_read_tail_limits_size() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("duck.log");
+        std::fs::write(&path, b"0123456789").unwrap();
+
+        assert_eq!(read_tail(&path, 4).unwrap(), b"6789");
+        assert_eq!(read_tail(&path, 100).unwrap(), b"0123456789");
+    }
+}
@@ -0,0 +1,112 @@
+use super::backup::manual_backup_options;
+use crate::app::CliApp;
+use crate::utils::prompt;
+use anyhow::{Result, anyhow};
+use client_core::constants::docker;
+use client_core::database::BackupType;
+use std::fs;
+use std::path::Path;
+use tracing::{error, info, warn};
+
+/// `docker-service down`：拆除部署
+///
+/// 停止服务后创建包含 CLI 自身状态的最终备份（`no_backup` 时跳过），再删除项目的全部容器和网络。
+/// `volumes` 时同时删除命名数据卷和数据目录，需要输入项目名确认，`--yes` 不能代替。
+/// 备份失败时不删除任何内容，服务保持停止
+pub async fn teardown(app: &CliApp, volumes: bool, no_backup: bool) -> Result<()> {
+    let project = app.docker_manager.get_compose_project_name();
+    let data_dir = docker::get_data_dir_path();
+
+    info!("🧨 将拆除部署（项目 {}）:", project);
+    info!("   ⏹️ 停止服务，删除项目的全部容器和网络");
+    if no_backup {
+        warn!("   ⚠️ 已指定 --no-backup，不创建最终备份");
+    } else {
+        info!("   💾 停止服务后创建最终备份（包括配置文件和数据库）");
+    }
+    if volumes {
+        warn!("   🗑️ 删除命名数据卷和数据目录 {}", data_dir.display());
+    }
+    if !prompt::confirm("确认拆除部署?")? {
+        info!("已取消");
+        return Ok(());
+    }
+    if volumes {
+        let answer = prompt::input(&format!(
+            "数据卷和数据目录删除后无法恢复{}，请输入项目名 {project} 确认: ",
+            if no_backup {
+                ""
+            } else {
+                "（只能从最终备份恢复）"
+            }
+        ))?;
+        if answer != project {
+            info!("输入与项目名不一致，已取消");
+            return Ok(());
+        }
+    }
+
+    info!("⏹️ 停止服务...");
+    app.docker_manager.stop_services().await?;
+
+    if !no_backup {
+        info!("💾 创建最终备份...");
+        let mut options = manual_backup_options(app, BackupType::Manual, true, false);
+        // 删除数据卷后只能从这份备份恢复，始终校验
+        options.verify |= volumes;
+        match app.backup_manager.create_backup(options).await {
+            Ok(record) => {
+                info!("✅ 最终备份已创建: {}", record.file_path);
+                info!("📝 备份ID: {}", record.id);
+            }
+            Err(e) => {
+                error!("❌ 创建最终备份失败，未删除任何内容，服务保持停止: {}", e);
+                return Err(e);
+            }
+        }
+    }
+
+    info!(
+        "🧹 删除项目的容器和网络{}...",
+        if volumes { "和数据卷" } else { "" }
+    );
+    app.docker_manager.teardown(volumes).await?;
+
+    if volumes {
+        remove_data_dir(&data_dir)?;
+        // 迁移后的数据目录已删除，重新部署时使用服务包中的默认位置
+        if app.config.docker.data_dir.is_some() {
+            let mut config = app.config.as_ref().clone();
+            config.docker.data_dir = None;
+            config.save_to_file(&app.config_path)?;
+            info!("💾 已清除 config.toml 中的 docker.data_dir");
+        }
+    }
+
+    info!("✅ 部署已拆除");
+    if !volumes {
+        info!(
+            "💡 数据目录 {} 已保留，可使用 'nuwax-cli docker-service start' 重新启动服务",
+            data_dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// 删除数据目录；迁移到其他磁盘后数据目录是符号链接，同时删除链接指向的目录
+fn remove_data_dir(data_dir: &Path) -> Result<()> {
+    let Ok(metadata) = fs::symlink_metadata(data_dir) else {
+        return Ok(());
+    };
+    if metadata.file_type().is_symlink() {
+        let target = fs::canonicalize(data_dir)
+            .map_err(|e| anyhow!("无法解析数据目录链接 {}: {}", data_dir.display(), e))?;
+        info!("🗑️ 删除数据目录: {}", target.display());
+        fs::remove_dir_all(&target)?;
+        fs::remove_file(data_dir)?;
+    } else {
+        info!("🗑️ 删除数据目录: {}", data_dir.display());
+        fs::remove_dir_all(data_dir)?;
+    }
+    Ok(())
+}
@@ -1,9 +1,20 @@
 use crate::app::CliApp;
 use crate::cli::UpgradeArgs;
+use crate::utils::network_diagnostics::report_connectivity;
 use anyhow::Result;
-use client_core::{architecture::Architecture, upgrade_strategy::UpgradeStrategy};
-use std::{fs, path::PathBuf};
-use tracing::{error, info};
+use client_core::{
+    api::ApiClient,
+    api_types::DownloadHashInfo,
+    architecture::Architecture,
+    archive_format::ArchiveFormat,
+    connectivity::ProbeKind,
+    upgrade_strategy::{UpgradeStrategy, UpgradeStrategyManager},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{error, info, warn};
 
 /// 获取指定版本的全量下载目录路径,并创建目录
 pub fn create_version_download_dir(
@@ -17,36 +28,123 @@ pub fn create_version_download_dir(
     Ok(dir)
 }
 
+/// 升级包的下载地址和本地保存路径
+pub(crate) struct DownloadTarget {
+    pub url: String,
+    /// 写入哈希文件的版本标识（主版本号）
+    pub version: String,
+    pub path: PathBuf,
+}
+
+/// 根据升级策略确定所有需要下载的升级包，补丁链按应用顺序返回每个补丁
+pub(crate) fn resolve_download_targets(
+    app: &CliApp,
+    upgrade_strategy: &UpgradeStrategy,
+) -> Result<Vec<DownloadTarget>> {
+    let mut targets = Vec::new();
+    for step in upgrade_strategy.expand_steps() {
+        if let Some(target) = resolve_download_target(app, &step)? {
+            targets.push(target);
+        }
+    }
+    Ok(targets)
+}
+
+/// 根据升级策略确定下载地址和保存路径（会创建下载目录），无需升级时返回 None
+///
+/// 补丁链需先通过 `resolve_download_targets` 展开为单个补丁
+pub(crate) fn resolve_download_target(
+    app: &CliApp,
+    upgrade_strategy: &UpgradeStrategy,
+) -> Result<Option<DownloadTarget>> {
+    let Some(target) = download_target(app, upgrade_strategy) else {
+        return Ok(None);
+    };
+    // 确保下载目录存在
+    if let Some(dir) = target.path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    Ok(Some(target))
+}
+
+/// 根据升级策略确定下载地址和保存路径，不创建目录
+fn download_target(app: &CliApp, upgrade_strategy: &UpgradeStrategy) -> Option<DownloadTarget> {
+    //获取主版本号，不包含补丁版本号
+    let (url, version_str, download_type) = match upgrade_strategy {
+        UpgradeStrategy::FullUpgrade {
+            url,
+            target_version,
+            download_type,
+            ..
+        } => (
+            url,
+            target_version.base_version_string(),
+            download_type.to_string(),
+        ),
+        UpgradeStrategy::PatchUpgrade {
+            patch_info,
+            target_version,
+            ..
+        } => (
+            &patch_info.url,
+            target_version.base_version_string(),
+            target_version.to_string(),
+        ),
+        UpgradeStrategy::PatchChainUpgrade { .. } | UpgradeStrategy::NoUpgrade { .. } => {
+            return None;
+        }
+    };
+
+    let version_download_dir = app
+        .config
+        .get_download_dir()
+        .join(&version_str)
+        .join(download_type);
+
+    //根据当前架构和下载地址的扩展名获取docker文件名（默认zip）
+    let package_format = ArchiveFormat::from_file_name(url).unwrap_or(ArchiveFormat::Zip);
+    let docker_file_name = Architecture::detect().get_docker_package_name(package_format);
+
+    Some(DownloadTarget {
+        url: url.clone(),
+        version: version_str,
+        path: version_download_dir.join(docker_file_name),
+    })
+}
+
 /// 处理下载服务包并显示相关信息
 async fn handle_service_download(
     app: &mut CliApp,
-    url: &str,
+    target: &DownloadTarget,
     target_version: &client_core::version::Version,
-    download_dir: PathBuf,
-    version_str: &str,
-    download_type: &str,
 ) -> Result<()> {
-    // 确保下载目录存在
-    let version_download_dir =
-        create_version_download_dir(download_dir, version_str, download_type)?;
-
     // 检查文件是否已存在（智能下载会处理这个检查）
-    info!("   文件路径: {}", version_download_dir.to_string_lossy());
+    info!("   文件路径: {}", target.path.display());
 
-    //根据当前架构获取docker文件名
-    let docker_file_name = Architecture::detect().get_docker_file_name();
-
-    let download_path = version_download_dir.join(docker_file_name);
+    // 下载数 GB 的服务包之前，先确认下载目录和 docker 目录都可写
+    let docker_dir = client_core::constants::docker::get_docker_work_dir();
+    let download_dir = target.path.parent().unwrap_or(&target.path);
+    for dir in [download_dir, docker_dir.as_path()] {
+        if let Err(e) = client_core::fs_probe::check_writable(dir, None) {
+            error!("❌ {}", e);
+            return Err(e.into());
+        }
+    }
 
     let download_result = app
         .api_client
-        .download_service_update_optimized(&download_path, Some(version_str), url)
+        .download_service_update_with_events(
+            &target.path,
+            Some(&target.version),
+            &target.url,
+            app.events.clone(),
+        )
         .await;
 
     match download_result {
         Ok(_) => {
             info!("✅ 服务包已准备就绪!");
-            info!("   文件位置: {}", download_path.display());
+            info!("   文件位置: {}", target.path.display());
             info!("   下载版本: {}", target_version.to_string());
             info!("   当前部署版本: {}", app.config.get_docker_versions());
             info!("📝 下一步: 运行 'nuwax-cli docker-service deploy' 来部署服务");
@@ -54,13 +152,14 @@ async fn handle_service_download(
         }
         Err(e) => {
             error!("❌ 操作失败: {}", e);
-            info!("💡 请检查网络连接或稍后重试");
+            report_connectivity(&target.url, ProbeKind::Download).await;
             Err(e)
         }
     }
 }
 
 /// 下载Docker服务升级文件
+#[tracing::instrument(level = "trace", name = "phase.download", skip_all)]
 pub async fn run_upgrade(app: &mut CliApp, args: UpgradeArgs) -> Result<UpgradeStrategy> {
     if args.check {
         info!("🔍 检查Docker服务升级版本");
@@ -79,22 +178,35 @@ pub async fn run_upgrade(app: &mut CliApp, args: UpgradeArgs) -> Result<UpgradeS
         info!("   将下载完整的Docker服务包");
     } else if args.force {
         info!("🔧 强制重新下载模式");
+    } else if args.force_full {
+        info!("🔧 跳过增量补丁，使用全量升级");
     }
 
     // 2. 获取当前版本信息
     let current_version_str = app.config.get_docker_versions();
 
-    let upgrade_strategy = app.upgrade_manager.check_for_updates(args.force).await?;
-
-    let download_dir: PathBuf = app.config.get_download_dir();
+    let upgrade_strategy = match app
+        .upgrade_manager
+        .check_for_updates(args.force || args.force_full)
+        .await
+    {
+        Ok(upgrade_strategy) => upgrade_strategy,
+        Err(e) => {
+            error!("❌ 检查升级版本失败: {}", e);
+            // 被限流说明网络正常，不需要连通性诊断
+            if client_core::rate_limit::retry_at(&e).is_none() {
+                let base_url = app.api_client.get_config().base_url.clone();
+                report_connectivity(&base_url, ProbeKind::Api).await;
+            }
+            return Err(e);
+        }
+    };
 
     match &upgrade_strategy {
         UpgradeStrategy::FullUpgrade {
             url,
-            hash: _,
-            signature: _,
             target_version,
-            download_type,
+            ..
         } => {
             info!("🔄 全量升级");
             info!("   目标版本: {}", target_version);
@@ -108,47 +220,46 @@ pub async fn run_upgrade(app: &mut CliApp, args: UpgradeArgs) -> Result<UpgradeS
                 return Ok(upgrade_strategy);
             }
 
-            //获取主版本号，不包含补丁版本号
-            let version_str = target_version.base_version_string();
-            let download_type_str = download_type.to_string();
-
-            handle_service_download(
-                app,
-                url,
-                target_version,
-                download_dir,
-                &version_str,
-                &download_type_str,
-            )
-            .await?;
+            if let Some(target) = resolve_download_target(app, &upgrade_strategy)? {
+                handle_service_download(app, &target, target_version).await?;
+            }
+        }
+        UpgradeStrategy::PatchUpgrade { target_version, .. } => {
+            info!("🔄 增量升级");
+            info!("   当前版本: {}", current_version_str);
+            info!("   最新版本: {}", target_version);
+
+            if args.check {
+                info!("🔍 检查升级版本执行完毕");
+                return Ok(upgrade_strategy);
+            }
+
+            if let Some(target) = resolve_download_target(app, &upgrade_strategy)? {
+                handle_service_download(app, &target, target_version).await?;
+            }
         }
-        UpgradeStrategy::PatchUpgrade {
-            patch_info,
+        UpgradeStrategy::PatchChainUpgrade {
+            steps,
             target_version,
-            download_type: _,
+            ..
         } => {
-            info!("🔄 增量升级");
+            info!("🔄 补丁链升级（共 {} 个补丁）", steps.len());
             info!("   当前版本: {}", current_version_str);
             info!("   最新版本: {}", target_version);
+            for step in steps {
+                info!("   补丁: {} -> {}", step.from_version, step.to_version);
+            }
 
             if args.check {
                 info!("🔍 检查升级版本执行完毕");
                 return Ok(upgrade_strategy);
             }
 
-            //获取主版本号，不包含补丁版本号
-            let base_version = target_version.base_version_string();
-            let version_str = target_version.to_string();
-
-            handle_service_download(
-                app,
-                &patch_info.url,
-                target_version,
-                download_dir,
-                &base_version,
-                &version_str,
-            )
-            .await?;
+            let targets = resolve_download_targets(app, &upgrade_strategy)?;
+            for (target, step) in targets.iter().zip(steps) {
+                info!("📥 下载补丁 {} -> {}", step.from_version, step.to_version);
+                handle_service_download(app, target, &step.to_version).await?;
+            }
         }
         UpgradeStrategy::NoUpgrade { target_version } => {
             info!("   当前版本: {}", current_version_str);
@@ -159,3 +270,84 @@ pub async fn run_upgrade(app: &mut CliApp, args: UpgradeArgs) -> Result<UpgradeS
 
     Ok(upgrade_strategy)
 }
+
+/// 说明升级策略的选择原因：版本比较、当前架构的补丁包、参与决策的清单字段和本地服务包状态
+pub async fn explain_upgrade_strategy(app: &CliApp) -> Result<()> {
+    let current_version = app.config.get_docker_version()?;
+    let manifest = app.api_client.get_enhanced_service_manifest().await?;
+    let explanation = UpgradeStrategyManager::new(current_version, false, manifest).explain();
+
+    info!("🧭 升级策略决策说明");
+    info!("========================");
+    info!("   当前版本: {}", explanation.current_version);
+    info!("   最新版本: {}", explanation.server_version);
+    info!("   当前架构: {}", explanation.architecture);
+    info!("   版本比较: {:?}", explanation.version_comparison);
+
+    info!("📋 参与决策的清单字段:");
+    for (field, value) in &explanation.manifest_fields {
+        info!("   {}: {}", field, value);
+    }
+
+    info!("🔍 决策依据:");
+    for (index, reason) in explanation.reasons.iter().enumerate() {
+        info!("   {}. {}", index + 1, reason);
+    }
+
+    let strategy = match explanation.strategy {
+        Ok(strategy) => strategy,
+        Err(e) => {
+            warn!("❌ 无法确定升级策略: {}", e);
+            return Ok(());
+        }
+    };
+    match &strategy {
+        UpgradeStrategy::FullUpgrade { .. } => info!("📦 结果: 全量升级"),
+        UpgradeStrategy::PatchUpgrade { .. } => info!("⚡ 结果: 增量升级"),
+        UpgradeStrategy::PatchChainUpgrade { steps, .. } => {
+            info!("🔗 结果: 补丁链升级（共 {} 个补丁）", steps.len())
+        }
+        UpgradeStrategy::NoUpgrade { .. } => {
+            info!("✅ 结果: 无需升级");
+            return Ok(());
+        }
+    }
+
+    info!("💾 本地服务包:");
+    for step in strategy.expand_steps() {
+        if let Some(target) = download_target(app, &step) {
+            info!("   {}", target.path.display());
+            info!("     {}", cached_package_status(&target.path).await);
+        }
+    }
+    Ok(())
+}
+
+/// 本地服务包的状态，判断规则与下载时是否复用已有文件一致
+async fn cached_package_status(path: &Path) -> String {
+    if !path.exists() {
+        if path.with_extension("download").exists() {
+            return "未下载完成，将断点续传".to_string();
+        }
+        return "不存在，将重新下载".to_string();
+    }
+    let hash_file_path = path.with_extension("zip.hash");
+    let Ok(hash_content) = fs::read_to_string(&hash_file_path) else {
+        return format!("缺少哈希文件 {}，将重新下载", hash_file_path.display());
+    };
+    let hash_info = match hash_content.parse::<DownloadHashInfo>() {
+        Ok(hash_info) => hash_info,
+        Err(e) => return format!("哈希文件格式无效 ({e})，将重新下载"),
+    };
+    match ApiClient::calculate_file_hash(path).await {
+        Ok(actual_hash) if actual_hash.eq_ignore_ascii_case(&hash_info.hash) => format!(
+            "哈希校验通过（版本 {}，保存于 {}），将复用本地文件",
+            hash_info.version, hash_info.timestamp
+        ),
+        Ok(actual_hash) => format!(
+            "哈希不匹配（本地 {}，记录 {}），将重新下载",
+            actual_hash, hash_info.hash
+        ),
+        Err(e) => format!("无法计算哈希 ({e})，将重新下载"),
+    }
+}
@@ -0,0 +1,85 @@
+//! 单独执行升级部署的某个阶段，便于在维护窗口内分步升级
+//!
+//! 各阶段与 `auto-upgrade-deploy run` 共用部署检查点，执行前要求之前的阶段都已完成
+
+use crate::app::CliApp;
+use crate::commands::auto_upgrade_deploy::{
+    DeployOptions, run_deploy_phase, run_sql_upgrade_phase,
+};
+use crate::utils::patch_conflicts::ConflictPolicy;
+use anyhow::Result;
+use client_core::deploy_checkpoint::DeployPhase;
+
+/// `upgrade phase` 可单独执行的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradePhaseStep {
+    /// 下载服务包
+    Download,
+    /// 停止服务并备份
+    Backup,
+    /// 解压服务包
+    Extract,
+    /// 执行数据库差异SQL
+    Sql,
+    /// 部署并启动服务
+    Start,
+}
+
+impl std::str::FromStr for UpgradePhaseStep {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "download" => Ok(UpgradePhaseStep::Download),
+            "backup" => Ok(UpgradePhaseStep::Backup),
+            "extract" => Ok(UpgradePhaseStep::Extract),
+            "sql" => Ok(UpgradePhaseStep::Sql),
+            "start" => Ok(UpgradePhaseStep::Start),
+            _ => Err(format!(
+                "无效的升级阶段: {s}（支持 download、backup、extract、sql、start）"
+            )),
+        }
+    }
+}
+
+/// 执行 `upgrade phase <阶段>`
+pub async fn run_upgrade_phase(
+    app: &mut CliApp,
+    step: UpgradePhaseStep,
+    force_full: bool,
+    sql_dry_run: bool,
+    on_conflict: Option<ConflictPolicy>,
+) -> Result<()> {
+    let phase = match step {
+        UpgradePhaseStep::Sql => return run_sql_upgrade_phase(app, sql_dry_run).await,
+        UpgradePhaseStep::Download => DeployPhase::Download,
+        UpgradePhaseStep::Backup => DeployPhase::Backup,
+        UpgradePhaseStep::Extract => DeployPhase::Extract,
+        UpgradePhaseStep::Start => DeployPhase::Start,
+    };
+    let options = DeployOptions {
+        force_full,
+        sql_dry_run,
+        on_conflict,
+        ..DeployOptions::default()
+    };
+    run_deploy_phase(app, phase, options).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_phase_step() {
+        assert_eq!(
+            "download".parse::<UpgradePhaseStep>(),
+            Ok(UpgradePhaseStep::Download)
+        );
+        assert_eq!(
+            " SQL ".parse::<UpgradePhaseStep>(),
+            Ok(UpgradePhaseStep::Sql)
+        );
+        assert!("deploy".parse::<UpgradePhaseStep>().is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use crate::app::CliApp;
+use crate::cli::ApproveCommand;
+use crate::utils::prompt;
+use anyhow::{Context, Result};
+use client_core::file_hash::sha256_file_uncached;
+use client_core::upgrade_plan::{ApprovalKey, PlanContent, UpgradePlan};
+use client_core::upgrade_strategy::{UpgradeStrategy, UpgradeStrategyManager};
+use client_core::version::Version;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// 生成升级计划：目标版本、服务包哈希、变更摘要和当前部署状态
+pub async fn create_upgrade_plan(
+    app: &CliApp,
+    out: &Path,
+    force_full: bool,
+    force: bool,
+) -> Result<()> {
+    if out.exists() && !force {
+        return Err(anyhow::anyhow!(
+            "文件已存在: {}，使用 --force 覆盖",
+            out.display()
+        ));
+    }
+
+    let current_version = app.config.get_docker_version()?;
+    let manifest = app.api_client.get_enhanced_service_manifest().await?;
+    let release_notes = manifest.release_notes.clone();
+    let mut strategy = UpgradeStrategyManager::new(current_version.clone(), force_full, manifest)
+        .determine_strategy()?;
+    app.api_client.fetch_patch_operations(&mut strategy).await?;
+    let content = PlanContent::from_strategy(
+        &strategy,
+        current_version.to_string(),
+        compose_sha256(app).await?,
+        release_notes,
+    )?;
+
+    let plan = UpgradePlan::new(content)?;
+    plan.save(out)?;
+    print_plan(&plan);
+    info!("✅ 升级计划已保存到: {}", out.display());
+    info!(
+        "💡 审批: nuwax-cli approve {} --key <审批私钥>",
+        out.display()
+    );
+    Ok(())
+}
+
+/// 处理审批命令：审批升级计划，或生成审批密钥
+///
+/// 不依赖本地配置和数据库，可以在部署机器以外的机器上执行
+pub async fn run_approve_command(
+    command: Option<ApproveCommand>,
+    plan: Option<PathBuf>,
+    key: Option<PathBuf>,
+    approver: Option<String>,
+) -> Result<()> {
+    match command {
+        Some(ApproveCommand::Keygen { out, force }) => generate_approval_key(&out, force),
+        None => {
+            let plan = plan.ok_or_else(|| anyhow::anyhow!("请指定要审批的升级计划文件"))?;
+            let key = key.ok_or_else(|| anyhow::anyhow!("请使用 --key 指定审批私钥"))?;
+            let approver = approver
+                .or_else(|| std::env::var("USER").ok())
+                .or_else(|| std::env::var("USERNAME").ok())
+                .filter(|name| !name.trim().is_empty())
+                .ok_or_else(|| anyhow::anyhow!("请使用 --approver 指定审批人"))?;
+            approve_plan(&plan, &key, &approver)
+        }
+    }
+}
+
+/// 校验升级计划的审批和当前部署状态，在下载和停止服务之前调用
+pub async fn verify_plan_for_deploy(app: &CliApp, path: &Path) -> Result<UpgradePlan> {
+    let plan = UpgradePlan::load(path)?;
+    let approval = plan.trusted_approval(&app.config.upgrade_approval.trusted_keys)?;
+    info!(
+        "🔏 升级计划已由 {} 于 {} 审批",
+        approval.approver,
+        approval
+            .approved_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+    );
+
+    let current_version = app.config.get_docker_version()?;
+    if current_version != plan.plan.current_version.parse::<Version>()? {
+        return Err(anyhow::anyhow!(
+            "当前部署版本 {} 与升级计划中的版本 {} 不一致，请重新生成并审批升级计划",
+            current_version,
+            plan.plan.current_version
+        ));
+    }
+    if compose_sha256(app).await? != plan.plan.compose_sha256 {
+        return Err(anyhow::anyhow!(
+            "docker-compose.yml 在生成升级计划后已被修改，请重新生成并审批升级计划"
+        ));
+    }
+    Ok(plan)
+}
+
+/// 确认实际的升级策略与计划一致
+pub fn ensure_strategy_matches_plan(plan: &UpgradePlan, strategy: &UpgradeStrategy) -> Result<()> {
+    if !plan.plan.matches_strategy(strategy) {
+        return Err(anyhow::anyhow!(
+            "升级服务器返回的升级内容与计划不一致（计划: {} {}），请重新生成并审批升级计划",
+            plan.plan.upgrade_type,
+            plan.plan.target_version
+        ));
+    }
+    info!("✅ 升级内容与审批的计划一致");
+    Ok(())
+}
+
+async fn compose_sha256(app: &CliApp) -> Result<Option<String>> {
+    let path = Path::new(&app.config.docker.compose_file);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(sha256_file_uncached(path).await?))
+}
+
+fn approve_plan(path: &Path, key_path: &Path, approver: &str) -> Result<()> {
+    let mut plan = UpgradePlan::load(path)?;
+    plan.verify_digest()?;
+    let key = ApprovalKey::load(key_path)?;
+    print_plan(&plan);
+
+    if !prompt::confirm(&format!("确认以 {approver} 的身份审批此升级计划?"))? {
+        info!("已取消审批");
+        return Ok(());
+    }
+
+    let approval = plan.approve(&key, approver)?;
+    info!("✅ {} 已审批升级计划", approval.approver);
+    info!("   公钥: {}", approval.public_key);
+    plan.save(path)?;
+    info!(
+        "💡 执行: nuwax-cli auto-upgrade-deploy run --plan {}",
+        path.display()
+    );
+    Ok(())
+}
+
+fn generate_approval_key(out: &Path, force: bool) -> Result<()> {
+    if out.exists() && !force {
+        return Err(anyhow::anyhow!(
+            "文件已存在: {}，使用 --force 覆盖",
+            out.display()
+        ));
+    }
+    if let Some(parent) = out.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let (key, encoded) = ApprovalKey::generate()?;
+    fs::write(out, format!("{encoded}\n"))
+        .with_context(|| format!("无法写入审批私钥: {}", out.display()))?;
+    // 私钥只允许所有者读写
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(out, fs::Permissions::from_mode(0o600))?;
+    }
+
+    info!("🔑 审批私钥已保存到: {}", out.display());
+    info!("   公钥: {}", key.public_key());
+    info!("💡 在部署机器的 config.toml 中信任此公钥:");
+    info!("   [upgrade_approval]");
+    info!("   required = true");
+    info!("   trusted_keys = [\"{}\"]", key.public_key());
+    warn!("⚠️ 请妥善保管私钥，持有私钥即可审批升级");
+    Ok(())
+}
+
+fn print_plan(plan: &UpgradePlan) {
+    let content = &plan.plan;
+    info!("📋 升级计划:");
+    info!(
+        "   版本: {} -> {} ({})",
+        content.current_version, content.target_version, content.upgrade_type
+    );
+    info!(
+        "   生成时间: {}",
+        content
+            .created_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+    );
+    for package in &content.packages {
+        info!(
+            "   服务包: {} (哈希: {})",
+            package.url,
+            package.hash.as_deref().unwrap_or("-")
+        );
+    }
+    if !content.changed_files.is_empty() {
+        info!("   变更文件: {}", content.changed_files.join(", "));
+    }
+    if !content.release_notes.trim().is_empty() {
+        info!("   更新说明:");
+        for line in content.release_notes.lines() {
+            info!("     {}", line);
+        }
+    }
+    info!("   摘要: {}", plan.digest);
+    for approval in &plan.approvals {
+        info!(
+            "   已审批: {} ({})",
+            approval.approver,
+            approval
+                .approved_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+}
@@ -0,0 +1,531 @@
+//! 升级回滚命令：把最近一次全量升级整体回退到升级前的版本
+
+use crate::app::CliApp;
+use crate::commands::auto_upgrade_deploy::{
+    log_readiness_progress, resolve_database, safe_remove_docker_directory,
+};
+use crate::commands::{backup, db, docker_service, history};
+use crate::docker_utils;
+use crate::utils::patch_conflicts::ConflictPolicy;
+use crate::utils::prompt;
+use anyhow::Result;
+use client_core::constants::{docker, timeout};
+use client_core::database::{UpgradeKind, UpgradeRecord, UpgradeStatus};
+use client_core::mysql_executor::{MySqlConfig, MySqlExecutor};
+use client_core::mysql_readiness::wait_for_mysql_ready;
+use client_core::paths;
+use client_core::postgres_executor::{PostgresConfig, PostgresExecutor};
+use client_core::sql_diff::{SqlDialect, generate_schema_diff_with_dialect};
+use client_core::upgrade_strategy::{DownloadType, UpgradeStrategy};
+use client_core::version::Version;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// 升级时保存在 temp_sql 目录中的旧版本和新版本数据库初始化脚本
+const OLD_SQL_FILE_NAME: &str = "init_mysql_old.sql";
+const NEW_SQL_FILE_NAME: &str = "init_mysql_new.sql";
+
+/// 数据库的回滚方式
+#[derive(Debug, Clone, PartialEq)]
+enum DatabaseRollback {
+    /// 升级没有修改数据库架构
+    Unchanged,
+    /// 反向执行升级时的差异SQL
+    ReverseDiff {
+        dialect: SqlDialect,
+        service: String,
+        sql: String,
+    },
+    /// 从 `db dump` 导出的文件恢复
+    Dump(PathBuf),
+    /// 从升级前备份恢复整个 data 目录
+    RestoreData,
+}
+
+impl DatabaseRollback {
+    fn describe(&self) -> String {
+        match self {
+            DatabaseRollback::Unchanged => "无需处理（升级未修改数据库架构）".to_string(),
+            DatabaseRollback::ReverseDiff { sql, .. } => {
+                format!("反向执行差异SQL（{} 条语句）", meaningful_lines(sql).len())
+            }
+            DatabaseRollback::Dump(file) => format!("从导出文件恢复: {}", file.display()),
+            DatabaseRollback::RestoreData => {
+                "从升级前备份恢复 data 目录（升级后写入的数据会丢失）".to_string()
+            }
+        }
+    }
+}
+
+/// 回滚最近一次全量升级：恢复升级前的文件和版本号、回退数据库并重新启动服务
+///
+/// `upgrade_id` 为空时回滚最近一次成功的升级；`db_dump` 与 `restore_data`
+/// 指定数据库的恢复方式，都未指定时反向执行升级时的差异SQL
+pub async fn run_upgrade_rollback(
+    app: &CliApp,
+    upgrade_id: Option<String>,
+    db_dump: Option<PathBuf>,
+    restore_data: bool,
+) -> Result<()> {
+    let records = app.database.get_upgrade_history(None).await?;
+    let current_version = app.config.get_docker_version()?;
+    let record = select_rollback_target(&records, &current_version, upgrade_id.as_deref())?;
+    let backup_id = record
+        .backup_id
+        .ok_or_else(|| anyhow::anyhow!("升级 {} 没有升级前备份", record.upgrade_id))?;
+
+    // 在停止服务之前确认回滚所需的备份和服务包都可用
+    let backup_record = app
+        .database
+        .get_backup_by_id(backup_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("升级前备份 {} 的记录不存在，无法回滚", backup_id))?;
+    if app
+        .backup_manager
+        .available_backup_ids(std::slice::from_ref(&backup_record))
+        .await?
+        .is_empty()
+    {
+        return Err(anyhow::anyhow!(
+            "升级前备份文件不存在: {}，无法回滚",
+            backup_record.file_path
+        ));
+    }
+    backup::ensure_backup_namespace(app, backup_id, false).await?;
+
+    let from_version: Version = record.from_version.parse()?;
+    let package_path = app.config.get_version_download_file_path(
+        &from_version.base_version_string(),
+        &DownloadType::Full.to_string(),
+        None,
+    );
+    if !package_path.exists() {
+        return Err(anyhow::anyhow!(
+            "版本 {} 的全量服务包不在下载缓存中: {}，无法恢复该版本的服务文件",
+            record.from_version,
+            package_path.display()
+        ));
+    }
+
+    let database = plan_database_rollback(app, record, db_dump, restore_data)?;
+
+    info!("↩️ 升级回滚计划:");
+    info!("   版本: {} -> {}", record.to_version, record.from_version);
+    info!(
+        "   升级时间: {}",
+        record
+            .started_at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+    );
+    info!("   服务文件: {}", package_path.display());
+    info!("   应用目录: 从升级前备份 #{} 恢复", backup_id);
+    info!("   数据库: {}", database.describe());
+    if let DatabaseRollback::ReverseDiff { sql, .. } = &database {
+        for line in meaningful_lines(sql) {
+            info!("     {}", line);
+        }
+    }
+    warn!("⚠️ 回滚期间服务将停止");
+    if !prompt::confirm("确认回滚升级?")? {
+        info!("已取消回滚");
+        return Ok(());
+    }
+
+    let started_at = chrono::Utc::now();
+    let result = rollback_upgrade(app, record, backup_id, from_version, &database).await;
+
+    // 回滚本身也记入升级历史，版本方向与被回滚的升级相反
+    let rollback_record = UpgradeRecord {
+        upgrade_id: uuid::Uuid::new_v4().to_string(),
+        from_version: record.to_version.clone(),
+        to_version: record.from_version.clone(),
+        upgrade_type: UpgradeKind::Full,
+        status: if result.is_ok() {
+            UpgradeStatus::Success
+        } else {
+            UpgradeStatus::Failed
+        },
+        backup_id: Some(backup_id),
+        started_at,
+        completed_at: Some(chrono::Utc::now()),
+        error_message: result.as_ref().err().map(|e| e.to_string()),
+    };
+    if let Err(e) = app.database.record_upgrade(&rollback_record).await {
+        warn!("⚠️ 记录升级历史失败: {}", e);
+    }
+    history::report_upgrade(app, &rollback_record).await;
+
+    if let Err(e) = &result {
+        error!("❌ 升级回滚失败: {}", e);
+        info!(
+            "💡 可使用 nuwax-cli rollback {} --rollback-data 从升级前备份手动恢复",
+            backup_id
+        );
+    }
+    result
+}
+
+/// 按计划执行回滚，服务停止后任一步骤失败即中止
+async fn rollback_upgrade(
+    app: &CliApp,
+    record: &UpgradeRecord,
+    backup_id: i64,
+    from_version: Version,
+    database: &DatabaseRollback,
+) -> Result<()> {
+    info!("⏹️ 正在停止服务...");
+    docker_service::stop_docker_services(app, None, None).await?;
+
+    // 1. 用旧版本的全量服务包替换 docker-compose.yml、配置等服务文件
+    info!("📦 正在恢复版本 {} 的服务文件...", record.from_version);
+    safe_remove_docker_directory(&docker::get_docker_work_dir()).await?;
+    let strategy = UpgradeStrategy::FullUpgrade {
+        url: String::new(),
+        hash: String::new(),
+        size: None,
+        signature: String::new(),
+        target_version: from_version,
+        download_type: DownloadType::Full,
+    };
+    let locked = docker_service::extract_docker_service_with_upgrade_strategy(
+        app,
+        strategy,
+        ConflictPolicy::Replace,
+    )
+    .await?;
+    if !locked.is_empty() {
+        locked.log_summary();
+    }
+
+    // 2. 从升级前备份恢复应用目录，按需连同 data 目录
+    let restore_data = matches!(database, DatabaseRollback::RestoreData);
+    backup::run_rollback(
+        app,
+        Some(backup_id),
+        true,
+        false,
+        false,
+        restore_data,
+        false,
+        false,
+    )
+    .await?;
+
+    // 3. 回退配置文件中的服务版本
+    let mut config = app.config.as_ref().clone();
+    config.write_docker_versions(&record.from_version.parse()?);
+    config.save_to_file(&app.config_path)?;
+    info!(
+        "📝 配置文件版本已回退: {} -> {}",
+        record.to_version, record.from_version
+    );
+
+    // 4. 部署并启动旧版本服务
+    info!("🔄 正在部署Docker服务...");
+    docker_service::deploy_docker_services(app, None, None, None).await?;
+    docker_service::start_docker_services(app, None, None).await?;
+    if !docker_utils::wait_for_compose_services_started(
+        &docker::get_compose_file_path(),
+        timeout::DEPLOY_START_TIMEOUT,
+    )
+    .await?
+    {
+        warn!("⚠️ 等待服务启动超时，请手动检查服务状态");
+    }
+
+    // 5. 回退数据库
+    match database {
+        DatabaseRollback::Unchanged | DatabaseRollback::RestoreData => {}
+        DatabaseRollback::ReverseDiff {
+            dialect,
+            service,
+            sql,
+        } => execute_reverse_diff(app, *dialect, service, sql).await?,
+        DatabaseRollback::Dump(file) => db::restore_database_dump(app, file, false).await?,
+    }
+
+    info!("✅ 已回滚到版本 {}", record.from_version);
+    Ok(())
+}
+
+/// 选择要回滚的升级：只能回滚当前部署版本对应的最近一次成功的全量升级
+fn select_rollback_target<'a>(
+    records: &'a [UpgradeRecord],
+    current_version: &Version,
+    upgrade_id: Option<&str>,
+) -> Result<&'a UpgradeRecord> {
+    // 升级历史按时间倒序排列
+    let latest_success = records
+        .iter()
+        .find(|record| record.status == UpgradeStatus::Success);
+    let record = match upgrade_id {
+        Some(id) => records
+            .iter()
+            .find(|record| record.upgrade_id == id)
+            .ok_or_else(|| anyhow::anyhow!("升级记录不存在: {id}"))?,
+        None => latest_success.ok_or_else(|| anyhow::anyhow!("没有可回滚的升级记录"))?,
+    };
+
+    if record.status != UpgradeStatus::Success {
+        return Err(anyhow::anyhow!(
+            "升级 {} 的结果为{}，只能回滚成功的升级",
+            record.upgrade_id,
+            record.status.display_name()
+        ));
+    }
+    if record.upgrade_type != UpgradeKind::Full {
+        return Err(anyhow::anyhow!(
+            "升级 {} 是增量补丁升级，只能回滚全量升级；补丁升级请使用 nuwax-cli rollback 从备份恢复",
+            record.upgrade_id
+        ));
+    }
+    if latest_success.map(|latest| latest.upgrade_id.as_str()) != Some(record.upgrade_id.as_str()) {
+        return Err(anyhow::anyhow!(
+            "升级 {} 之后还有其他成功的升级，只能回滚最近一次升级",
+            record.upgrade_id
+        ));
+    }
+    let from: Version = record.from_version.parse()?;
+    let to: Version = record.to_version.parse()?;
+    if to <= from {
+        return Err(anyhow::anyhow!(
+            "最近一次升级 {} -> {} 已是版本回退，不能再次回滚",
+            record.from_version,
+            record.to_version
+        ));
+    }
+    if to != *current_version {
+        return Err(anyhow::anyhow!(
+            "当前部署版本 {} 与升级后的版本 {} 不一致，无法回滚",
+            current_version,
+            record.to_version
+        ));
+    }
+    if record.backup_id.is_none() {
+        return Err(anyhow::anyhow!(
+            "升级 {} 没有升级前备份，无法回滚",
+            record.upgrade_id
+        ));
+    }
+    Ok(record)
+}
+
+/// 确定数据库的回滚方式
+///
+/// 未指定 `db_dump` 或 `restore_data` 时，用升级时保存的新旧初始化脚本生成反向差异SQL；
+/// 升级删除或修改过表、列时反向执行无法找回原有数据，需要改用导出文件或备份恢复
+fn plan_database_rollback(
+    app: &CliApp,
+    record: &UpgradeRecord,
+    db_dump: Option<PathBuf>,
+    restore_data: bool,
+) -> Result<DatabaseRollback> {
+    if let Some(file) = db_dump {
+        if !file.is_file() {
+            return Err(anyhow::anyhow!("数据库导出文件不存在: {}", file.display()));
+        }
+        return Ok(DatabaseRollback::Dump(file));
+    }
+    if restore_data {
+        return Ok(DatabaseRollback::RestoreData);
+    }
+
+    let temp_sql_dir = paths::temp_sql_dir();
+    let old_sql_path = temp_sql_dir.join(OLD_SQL_FILE_NAME);
+    let new_sql_path = temp_sql_dir.join(NEW_SQL_FILE_NAME);
+    if !new_sql_path.exists() {
+        info!("📄 升级时未生成SQL差异，数据库无需回退");
+        return Ok(DatabaseRollback::Unchanged);
+    }
+    let old_sql = fs::read_to_string(&old_sql_path).unwrap_or_default();
+    if old_sql.trim().is_empty() {
+        return Err(anyhow::anyhow!(
+            "缺少升级前的数据库初始化脚本 {}，无法生成反向差异SQL，请使用 --db-dump 或 --restore-data",
+            old_sql_path.display()
+        ));
+    }
+    let new_sql = fs::read_to_string(&new_sql_path)?;
+
+    let (dialect, service) = resolve_database(app, &None);
+    let (forward_sql, _) = generate_schema_diff_with_dialect(
+        dialect,
+        Some(&old_sql),
+        &new_sql,
+        Some(&record.from_version),
+        &record.to_version,
+    )
+    .map_err(|e| anyhow::anyhow!("生成升级差异SQL失败: {e}"))?;
+    let lossy = lossy_statements(&forward_sql);
+    if !lossy.is_empty() {
+        for statement in &lossy {
+            warn!("   {}", statement);
+        }
+        return Err(anyhow::anyhow!(
+            "升级删除或修改了 {} 处表结构，反向执行差异SQL无法找回原有数据，请使用 --db-dump 或 --restore-data",
+            lossy.len()
+        ));
+    }
+
+    let (reverse_sql, description) = generate_schema_diff_with_dialect(
+        dialect,
+        Some(&new_sql),
+        &old_sql,
+        Some(&record.to_version),
+        &record.from_version,
+    )
+    .map_err(|e| anyhow::anyhow!("生成反向差异SQL失败: {e}"))?;
+    info!("📊 反向差异SQL: {}", description);
+    if meaningful_lines(&reverse_sql).is_empty() {
+        return Ok(DatabaseRollback::Unchanged);
+    }
+    Ok(DatabaseRollback::ReverseDiff {
+        dialect,
+        service,
+        sql: reverse_sql,
+    })
+}
+
+/// 差异SQL中会丢弃数据的语句：删除表或列、修改列定义
+fn lossy_statements(diff_sql: &str) -> Vec<&str> {
+    meaningful_lines(diff_sql)
+        .into_iter()
+        .filter(|line| {
+            let upper = line.to_uppercase();
+            upper.contains("DROP TABLE")
+                || upper.contains("DROP COLUMN")
+                || upper.contains("MODIFY COLUMN")
+                || upper.contains("CHANGE COLUMN")
+                || (upper.contains("ALTER COLUMN") && upper.contains(" TYPE "))
+        })
+        .collect()
+}
+
+/// 差异SQL中可执行的行
+fn meaningful_lines(sql: &str) -> Vec<&str> {
+    sql.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("--") && !line.starts_with("/*"))
+        .collect()
+}
+
+/// 等待数据库就绪后执行反向差异SQL，执行前保存到 temp_sql 目录
+async fn execute_reverse_diff(
+    app: &CliApp,
+    dialect: SqlDialect,
+    service: &str,
+    sql: &str,
+) -> Result<()> {
+    let sql_path = paths::temp_sql_dir().join(format!(
+        "diff_sql_rollback_{}.sql",
+        chrono::Local::now().format("%Y%m%d_%H%M%S")
+    ));
+    fs::write(&sql_path, sql)?;
+    info!("📄 已保存反向差异SQL: {}", sql_path.display());
+
+    let ready_timeout = Duration::from_secs(app.config.docker.mysql_ready_timeout_secs);
+    let results = match dialect {
+        SqlDialect::Mysql => {
+            let compose_file = docker::get_compose_file_path();
+            let env_file = docker::get_env_file_path();
+            let config =
+                MySqlConfig::for_container(compose_file.to_str(), env_file.to_str()).await?;
+            let executor = MySqlExecutor::new(config);
+            wait_for_mysql_ready(
+                &executor,
+                Some(app.docker_manager.as_ref()),
+                ready_timeout,
+                |progress| log_readiness_progress(SqlDialect::Mysql, progress),
+            )
+            .await?;
+            info!("🚀 开始执行反向差异SQL...");
+            executor.execute_diff_sql_with_retry(sql, 3).await?
+        }
+        SqlDialect::Postgres => {
+            let config = PostgresConfig::for_container(&app.docker_manager, service)?;
+            let executor = PostgresExecutor::new(app.docker_manager.as_ref().clone(), config);
+            executor
+                .wait_until_ready(ready_timeout, |progress| {
+                    log_readiness_progress(SqlDialect::Postgres, progress)
+                })
+                .await?;
+            info!("🚀 开始执行反向差异SQL...");
+            executor.execute_diff_sql(sql).await?
+        }
+    };
+    for result in results {
+        info!("  {}", result);
+    }
+    info!("✅ 数据库已回退");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, from: &str, to: &str, status: UpgradeStatus) -> UpgradeRecord {
+        UpgradeRecord {
+            upgrade_id: id.to_string(),
+            from_version: from.to_string(),
+            to_version: to.to_string(),
+            upgrade_type: UpgradeKind::Full,
+            status,
+            backup_id: Some(1),
+            started_at: chrono::Utc::now(),
+            completed_at: None,
+            error_message: None,
+        }
+    }
+
+    fn v(version: &str) -> Version {
+        version.parse().unwrap()
+    }
+
+    #[test]
+    fn test_select_rollback_target() {
+        let records = vec![
+            record("failed", "1.0.2", "1.0.3", UpgradeStatus::Failed),
+            record("latest", "1.0.1", "1.0.2", UpgradeStatus::Success),
+            record("older", "1.0.0", "1.0.1", UpgradeStatus::Success),
+        ];
+
+        let target = select_rollback_target(&records, &v("1.0.2"), None).unwrap();
+        assert_eq!(target.upgrade_id, "latest");
+        // 按版本号比较，配置中的四段式版本号与记录中的三段式版本号相同
+        let target = select_rollback_target(&records, &v("1.0.2.0"), None).unwrap();
+        assert_eq!(target.upgrade_id, "latest");
+
+        // 版本不一致、非最近一次、失败的升级都不能回滚
+        assert!(select_rollback_target(&records, &v("1.0.3"), None).is_err());
+        assert!(select_rollback_target(&records, &v("1.0.2"), Some("older")).is_err());
+        assert!(select_rollback_target(&records, &v("1.0.2"), Some("failed")).is_err());
+
+        let mut patch = records.clone();
+        patch[1].upgrade_type = UpgradeKind::Patch;
+        assert!(select_rollback_target(&patch, &v("1.0.2"), None).is_err());
+
+        let mut no_backup = records.clone();
+        no_backup[1].backup_id = None;
+        assert!(select_rollback_target(&no_backup, &v("1.0.2"), None).is_err());
+
+        // 回滚记录本身不能再被回滚
+        let mut rolled_back = records;
+        rolled_back.insert(
+            0,
+            record("rollback", "1.0.2", "1.0.1", UpgradeStatus::Success),
+        );
+        assert!(select_rollback_target(&rolled_back, &v("1.0.1"), None).is_err());
+    }
+
+    #[test]
+    fn test_lossy_statements() {
+        let additive = "-- 新增表\nCREATE TABLE `t` (`id` int);\n\nALTER TABLE `u` ADD COLUMN `c` int;\nALTER TABLE `u` ADD KEY `k` (`c`);";
+        assert!(lossy_statements(additive).is_empty());
+
+        let destructive = "ALTER TABLE `u` DROP COLUMN `c`;\nALTER TABLE `u` MODIFY COLUMN `n` varchar(10);\nDROP TABLE IF EXISTS `t`;\nALTER TABLE u ALTER COLUMN n TYPE text;\nALTER TABLE u ALTER COLUMN n DROP DEFAULT;";
+        assert_eq!(lossy_statements(destructive).len(), 4);
+    }
+}
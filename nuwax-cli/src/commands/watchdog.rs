@@ -0,0 +1,195 @@
+use crate::app::CliApp;
+use crate::cli::WatchdogCommand;
+use crate::docker_service::health_check::HealthChecker;
+use crate::docker_service::watchdog::{ServiceWatchdog, WatchdogAction, is_container_healthy};
+use anyhow::Result;
+use client_core::config::WatchdogConfig;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// 看门狗通知事件
+#[derive(Debug, Serialize)]
+struct WatchdogEvent<'a> {
+    event: &'a str,
+    service: &'a str,
+    failures: u32,
+    attempt: u32,
+    message: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// 处理看门狗命令
+pub async fn handle_watchdog_command(app: &CliApp, cmd: WatchdogCommand) -> Result<()> {
+    match cmd {
+        WatchdogCommand::Run { once } => run_watchdog(app, once).await,
+        WatchdogCommand::Status => {
+            show_watchdog_config(&app.config.watchdog);
+            Ok(())
+        }
+    }
+}
+
+/// 运行看门狗：定期检查常驻服务健康状态，连续失败达到阈值后自动重启
+///
+/// `once` 为 true 时只检查一轮（便于由 cron 等外部调度器调用）
+pub async fn run_watchdog(app: &CliApp, once: bool) -> Result<()> {
+    let config = app.config.watchdog.clone();
+    let interval = Duration::from_secs(config.check_interval_secs.max(1));
+    let mut watchdog = ServiceWatchdog::new(&config);
+
+    info!("🐕 服务看门狗已启动");
+    show_watchdog_config(&config);
+
+    loop {
+        if let Err(e) = check_and_restart(app, &config, &mut watchdog).await {
+            error!("❌ 看门狗健康检查失败: {}", e);
+        }
+        if once {
+            return Ok(());
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => {
+                info!("🛑 收到中断信号，看门狗退出");
+                return Ok(());
+            }
+        }
+    }
+}
+
+async fn check_and_restart(
+    app: &CliApp,
+    config: &WatchdogConfig,
+    watchdog: &mut ServiceWatchdog,
+) -> Result<()> {
+    let report = HealthChecker::new(app.docker_manager.clone())
+        .health_check()
+        .await?;
+    let now = Instant::now();
+
+    for container in &report.containers {
+        if !container.is_persistent_service() || !config.watches(&container.name) {
+            continue;
+        }
+        let service = container.name.as_str();
+
+        match watchdog.observe(service, is_container_healthy(container), now) {
+            WatchdogAction::Healthy => {}
+            WatchdogAction::Recovered => {
+                info!("✅ 服务 {} 已恢复健康", service);
+                notify(
+                    config,
+                    "recovered",
+                    service,
+                    0,
+                    0,
+                    format!("服务 {service} 已恢复健康"),
+                )
+                .await;
+            }
+            WatchdogAction::Failing { failures } => {
+                warn!(
+                    "⚠️ 服务 {} 不健康（状态: {}，连续 {}/{} 次）",
+                    service,
+                    container.status.display_name(),
+                    failures,
+                    config.failure_threshold
+                );
+            }
+            WatchdogAction::BackingOff {
+                failures,
+                remaining,
+            } => {
+                warn!(
+                    "⏳ 服务 {} 仍不健康（连续 {} 次），{} 秒后再次尝试重启",
+                    service,
+                    failures,
+                    remaining.as_secs()
+                );
+            }
+            WatchdogAction::Restart { failures, attempt } => {
+                warn!(
+                    "🔄 服务 {} 连续 {} 次不健康，第 {} 次自动重启",
+                    service, failures, attempt
+                );
+                let (event, message) = match app.docker_manager.restart_service(service).await {
+                    Ok(()) => {
+                        info!("✅ 服务 {} 已重启", service);
+                        (
+                            "restarted",
+                            format!("服务 {service} 连续 {failures} 次不健康，已自动重启"),
+                        )
+                    }
+                    Err(e) => {
+                        error!("❌ 重启服务 {} 失败: {}", service, e);
+                        (
+                            "restart_failed",
+                            format!("服务 {service} 自动重启失败: {e}"),
+                        )
+                    }
+                };
+                notify(config, event, service, failures, attempt, message).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 发送看门狗 Webhook 通知，未配置时不发送
+async fn notify(
+    config: &WatchdogConfig,
+    event: &str,
+    service: &str,
+    failures: u32,
+    attempt: u32,
+    message: String,
+) {
+    let Some(url) = config
+        .webhook_url
+        .as_deref()
+        .filter(|url| !url.trim().is_empty())
+    else {
+        return;
+    };
+    let payload = WatchdogEvent {
+        event,
+        service,
+        failures,
+        attempt,
+        message,
+        timestamp: chrono::Utc::now(),
+    };
+
+    let result = reqwest::Client::new()
+        .post(url)
+        .timeout(Duration::from_secs(10))
+        .json(&payload)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    if let Err(e) = result {
+        warn!("⚠️ 发送看门狗通知失败: {}", e);
+    }
+}
+
+fn show_watchdog_config(config: &WatchdogConfig) {
+    info!("📋 看门狗配置:");
+    if config.services.is_empty() {
+        info!("   监控服务: 所有常驻服务");
+    } else {
+        info!("   监控服务: {}", config.services.join(", "));
+    }
+    info!("   检查间隔: {} 秒", config.check_interval_secs);
+    info!("   失败阈值: 连续 {} 次", config.failure_threshold);
+    info!(
+        "   重启退避: {} 秒起，最长 {} 秒",
+        config.restart_backoff_secs, config.max_restart_backoff_secs
+    );
+    match &config.webhook_url {
+        Some(url) => info!("   通知地址: {}", url),
+        None => info!("   通知地址: 未配置（只记录日志）"),
+    }
+}
@@ -1,5 +1,5 @@
 use serde_yaml::Value;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::path::PathBuf;
 
 /// 挂载信息
@@ -20,10 +20,84 @@ impl DockerComposeParser {
     /// 从文件解析Docker Compose配置
     pub fn from_file(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
         let content = std::fs::read_to_string(path)?;
-        let compose: Value = serde_yaml::from_str(&content)?;
+        Ok(Self::from_yaml_str(&content)?)
+    }
+
+    /// 从YAML文本解析Docker Compose配置
+    pub fn from_yaml_str(content: &str) -> Result<Self, serde_yaml::Error> {
+        let compose: Value = serde_yaml::from_str(content)?;
         Ok(Self { compose })
     }
 
+    /// 获取原始YAML内容
+    pub fn raw(&self) -> &Value {
+        &self.compose
+    }
+
+    /// 获取所有服务定义（服务名, 配置），`services` 缺失或格式错误时返回空列表
+    pub fn services(&self) -> Vec<(String, &Value)> {
+        self.compose
+            .get("services")
+            .and_then(|services| services.as_mapping())
+            .map(|services| {
+                services
+                    .iter()
+                    .filter_map(|(name, service)| Some((name.as_str()?.to_string(), service)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 获取每个服务通过 `depends_on` 依赖的服务，支持列表和映射两种写法
+    pub fn service_dependencies(&self) -> BTreeMap<String, Vec<String>> {
+        self.services()
+            .into_iter()
+            .map(|(name, service)| {
+                let dependencies = match service.get("depends_on") {
+                    Some(Value::Sequence(items)) => items
+                        .iter()
+                        .filter_map(|item| item.as_str().map(str::to_string))
+                        .collect(),
+                    Some(Value::Mapping(items)) => items
+                        .keys()
+                        .filter_map(|key| key.as_str().map(str::to_string))
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                (name, dependencies)
+            })
+            .collect()
+    }
+
+    /// 获取每个服务的绑定挂载主机路径，支持短写法和 `type: bind` 的长写法
+    pub fn service_bind_mounts(&self) -> BTreeMap<String, Vec<String>> {
+        self.services()
+            .into_iter()
+            .map(|(name, service)| {
+                let mounts = service
+                    .get("volumes")
+                    .and_then(|volumes| volumes.as_sequence())
+                    .map(|volumes| {
+                        volumes
+                            .iter()
+                            .filter_map(|volume| match volume {
+                                Value::String(volume) => self.extract_host_path_from_volume(volume),
+                                Value::Mapping(_) => {
+                                    let is_bind =
+                                        volume.get("type").and_then(Value::as_str) == Some("bind");
+                                    let source = volume.get("source").and_then(Value::as_str)?;
+                                    is_bind.then(|| self.normalize_path(source))
+                                }
+                                _ => None,
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (name, mounts)
+            })
+            .collect()
+    }
+
     /// 提取所有绑定挂载目录
     pub fn extract_mount_directories(&self) -> Vec<String> {
         let mut mount_dirs = HashSet::new();
@@ -53,7 +127,7 @@ impl DockerComposeParser {
     }
 
     /// 从volume字符串中提取主机路径
-    fn extract_host_path_from_volume(&self, volume: &str) -> Option<String> {
+    pub fn extract_host_path_from_volume(&self, volume: &str) -> Option<String> {
         // 检查是否是Windows路径格式 (如 C:\path 或 C:/path)
         let colon_pos = if volume.len() > 1 && volume.chars().nth(1) == Some(':') {
             // 如果是Windows路径，寻找第二个冒号作为分隔符
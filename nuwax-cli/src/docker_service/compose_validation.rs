@@ -0,0 +1,530 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde_yaml::Value;
+use tracing::{info, warn};
+
+use super::architecture::{Architecture, detect_architecture};
+use super::compose_parser::DockerComposeParser;
+use super::error::{DockerServiceError, DockerServiceResult};
+use crate::utils::env_manager::load_env_variables;
+
+/// 镜像标签中可识别的架构后缀
+const ARCH_TAG_SUFFIXES: [(&str, Architecture); 4] = [
+    ("-amd64", Architecture::Amd64),
+    ("-x86_64", Architecture::Amd64),
+    ("-arm64", Architecture::Arm64),
+    ("-aarch64", Architecture::Arm64),
+];
+
+/// docker-compose.yml 校验结果
+#[derive(Debug, Default)]
+pub struct ComposeValidationReport {
+    /// 会导致服务无法启动的问题
+    pub errors: Vec<String>,
+    /// 不阻止启动但需要关注的问题
+    pub warnings: Vec<String>,
+}
+
+impl ComposeValidationReport {
+    /// 是否通过校验
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// 校验解压后的 docker-compose.yml：
+///
+/// - 文件可以解析，且每个服务都定义了 `image` 或 `build`
+/// - 引用的环境变量（没有默认值的）在 `.env` 或当前环境中存在
+/// - `build` 上下文、`env_file` 和文件类型的绑定挂载在磁盘上存在
+/// - 镜像标签非空，且带架构后缀的标签与当前系统架构一致
+///
+/// `.env` 取 compose 文件所在目录下的 `.env`，与 docker compose 的行为一致
+pub fn validate_compose_file(
+    compose_path: &Path,
+    architecture: Architecture,
+) -> ComposeValidationReport {
+    let mut report = ComposeValidationReport::default();
+
+    let content = match std::fs::read_to_string(compose_path) {
+        Ok(content) => content,
+        Err(e) => {
+            report
+                .errors
+                .push(format!("无法读取 {}: {e}", compose_path.display()));
+            return report;
+        }
+    };
+    let parser = match DockerComposeParser::from_yaml_str(&content) {
+        Ok(parser) => parser,
+        Err(e) => {
+            report
+                .errors
+                .push(format!("docker-compose.yml 格式错误: {e}"));
+            return report;
+        }
+    };
+
+    let base_dir = compose_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let env_path = base_dir.join(client_core::constants::docker::ENV_FILE_NAME);
+    let env = if env_path.exists() {
+        match load_env_variables(&env_path) {
+            Ok(env) => env,
+            Err(e) => {
+                report
+                    .errors
+                    .push(format!("无法读取 {}: {e}", env_path.display()));
+                HashMap::new()
+            }
+        }
+    } else {
+        report
+            .warnings
+            .push(format!("未找到 {}，仅使用当前环境变量", env_path.display()));
+        HashMap::new()
+    };
+
+    validate_compose(&parser, &base_dir, &env, architecture, &mut report);
+    report
+}
+
+/// 校验解压后的 docker-compose.yml，存在错误时返回配置错误
+pub fn ensure_compose_file_valid(compose_path: &Path) -> DockerServiceResult<()> {
+    info!("🔍 校验 docker-compose.yml: {}", compose_path.display());
+    let report = validate_compose_file(compose_path, detect_architecture());
+
+    for warning in &report.warnings {
+        warn!("⚠️ {}", warning);
+    }
+    if !report.is_valid() {
+        return Err(DockerServiceError::Configuration(format!(
+            "docker-compose.yml 校验失败:\n  - {}",
+            report.errors.join("\n  - ")
+        )));
+    }
+
+    info!("✅ docker-compose.yml 校验通过");
+    Ok(())
+}
+
+fn validate_compose(
+    parser: &DockerComposeParser,
+    base_dir: &Path,
+    env: &HashMap<String, String>,
+    architecture: Architecture,
+    report: &mut ComposeValidationReport,
+) {
+    let services = parser.services();
+    if services.is_empty() {
+        report
+            .errors
+            .push("docker-compose.yml 中没有定义任何服务".to_string());
+        return;
+    }
+
+    let lookup = |name: &str| -> Option<String> {
+        env.get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok().filter(|value| !value.is_empty()))
+    };
+
+    // 环境变量引用
+    let mut missing = BTreeSet::new();
+    let mut strings = Vec::new();
+    collect_strings(parser.raw(), &mut strings);
+    for value in strings {
+        for reference in find_variable_references(value) {
+            if !reference.has_default && lookup(&reference.name).is_none() {
+                missing.insert(reference.name);
+            }
+        }
+    }
+    for name in missing {
+        report
+            .errors
+            .push(format!("引用的环境变量 {name} 未在 .env 中定义"));
+    }
+
+    for (name, service) in services {
+        if !service.is_mapping() {
+            report.errors.push(format!("服务 {name} 的配置格式错误"));
+            continue;
+        }
+
+        let image = service.get("image").and_then(Value::as_str);
+        let build = service.get("build");
+        if image.is_none() && build.is_none() {
+            report
+                .errors
+                .push(format!("服务 {name} 未定义 image 或 build"));
+        }
+
+        if let Some(image) = image {
+            let image = interpolate(image, &lookup);
+            if let Err(e) = check_image_reference(&image, architecture) {
+                report
+                    .errors
+                    .push(format!("服务 {name} 的镜像 {image}: {e}"));
+            }
+        }
+
+        if let Some(build) = build {
+            check_build(&name, build, base_dir, &lookup, report);
+        }
+
+        for env_file in string_or_sequence(service.get("env_file")) {
+            let path = base_dir.join(interpolate(env_file, &lookup));
+            if !path.exists() {
+                report.errors.push(format!(
+                    "服务 {name} 的 env_file 不存在: {}",
+                    path.display()
+                ));
+            }
+        }
+
+        if let Some(volumes) = service.get("volumes").and_then(Value::as_sequence) {
+            for volume in volumes {
+                let host_path = match volume {
+                    Value::String(volume) => {
+                        parser.extract_host_path_from_volume(&interpolate(volume, &lookup))
+                    }
+                    Value::Mapping(_) => volume
+                        .get("type")
+                        .and_then(Value::as_str)
+                        .filter(|kind| *kind == "bind")
+                        .and(volume.get("source").and_then(Value::as_str))
+                        .map(|source| interpolate(source, &lookup)),
+                    _ => None,
+                };
+                if let Some(host_path) = host_path {
+                    check_bind_mount(&name, &host_path, base_dir, report);
+                }
+            }
+        }
+    }
+}
+
+fn check_build(
+    service: &str,
+    build: &Value,
+    base_dir: &Path,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    report: &mut ComposeValidationReport,
+) {
+    let (context, dockerfile) = match build {
+        Value::String(context) => (context.as_str(), None),
+        Value::Mapping(_) => (
+            build.get("context").and_then(Value::as_str).unwrap_or("."),
+            build.get("dockerfile").and_then(Value::as_str),
+        ),
+        _ => {
+            report
+                .errors
+                .push(format!("服务 {service} 的 build 配置格式错误"));
+            return;
+        }
+    };
+
+    let context = interpolate(context, lookup);
+    // 远程构建上下文（git 仓库、URL）无法在本地检查
+    if context.contains("://") || context.starts_with("git@") {
+        return;
+    }
+    let context_dir = base_dir.join(&context);
+    if !context_dir.is_dir() {
+        report.errors.push(format!(
+            "服务 {service} 的构建目录不存在: {}",
+            context_dir.display()
+        ));
+        return;
+    }
+
+    let dockerfile = context_dir.join(interpolate(dockerfile.unwrap_or("Dockerfile"), lookup));
+    if !dockerfile.is_file() {
+        report.errors.push(format!(
+            "服务 {service} 的 Dockerfile 不存在: {}",
+            dockerfile.display()
+        ));
+    }
+}
+
+/// 目录挂载在部署时会自动创建；文件挂载缺失时 Docker 会在原位置创建同名目录，导致服务启动失败
+fn check_bind_mount(
+    service: &str,
+    host_path: &str,
+    base_dir: &Path,
+    report: &mut ComposeValidationReport,
+) {
+    let path = base_dir.join(host_path);
+    if path.exists() {
+        return;
+    }
+    if path.extension().is_some() {
+        report.errors.push(format!(
+            "服务 {service} 挂载的文件不存在: {}",
+            path.display()
+        ));
+    } else {
+        report.warnings.push(format!(
+            "服务 {service} 挂载的目录不存在，部署时将自动创建: {}",
+            path.display()
+        ));
+    }
+}
+
+/// 检查镜像引用：标签不能为空，带架构后缀的标签必须与当前架构一致
+fn check_image_reference(image: &str, architecture: Architecture) -> Result<(), String> {
+    if image.trim().is_empty() {
+        return Err("镜像名称为空".to_string());
+    }
+
+    // 镜像名中可能包含带端口的仓库地址（registry:5000/app:tag），只取最后一段的标签
+    let name = image.rsplit('/').next().unwrap_or(image);
+    let name = name.split('@').next().unwrap_or(name);
+    let Some((_, tag)) = name.split_once(':') else {
+        return Ok(());
+    };
+    if tag.is_empty() {
+        return Err("镜像标签为空，请检查对应的版本变量".to_string());
+    }
+
+    if let Some((suffix, tag_arch)) = ARCH_TAG_SUFFIXES
+        .iter()
+        .find(|(suffix, _)| tag.ends_with(suffix))
+    {
+        if *tag_arch != architecture {
+            return Err(format!(
+                "标签架构后缀 {suffix} 与当前系统架构 {architecture} 不一致"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 变量引用
+#[derive(Debug, PartialEq)]
+struct VariableReference {
+    name: String,
+    /// 是否提供了默认值（`${VAR:-default}` / `${VAR-default}`）
+    has_default: bool,
+}
+
+/// 查找字符串中的 `$VAR` / `${VAR}` 引用，`$$` 为转义
+fn find_variable_references(value: &str) -> Vec<VariableReference> {
+    let mut references = Vec::new();
+    let bytes = value.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'$' {
+            i += 1;
+            continue;
+        }
+        match bytes.get(i + 1) {
+            Some(b'$') => i += 2,
+            Some(b'{') => {
+                let Some(end) = value[i + 2..].find('}') else {
+                    break;
+                };
+                let expression = &value[i + 2..i + 2 + end];
+                let name_len = variable_name_len(expression);
+                if name_len > 0 {
+                    let modifier = &expression[name_len..];
+                    references.push(VariableReference {
+                        name: expression[..name_len].to_string(),
+                        has_default: modifier.starts_with(":-") || modifier.starts_with('-'),
+                    });
+                }
+                i += end + 3;
+            }
+            Some(_) => {
+                let name_len = variable_name_len(&value[i + 1..]);
+                if name_len > 0 {
+                    references.push(VariableReference {
+                        name: value[i + 1..i + 1 + name_len].to_string(),
+                        has_default: false,
+                    });
+                }
+                i += name_len + 1;
+            }
+            None => break,
+        }
+    }
+    references
+}
+
+/// 按 compose 规则替换变量，未定义且无默认值的变量替换为空字符串
+fn interpolate(value: &str, lookup: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        if let Some(after) = after.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+        } else if let Some(expression) = after.strip_prefix('{') {
+            let Some(end) = expression.find('}') else {
+                result.push_str(&rest[pos..]);
+                return result;
+            };
+            let expression_body = &expression[..end];
+            let name_len = variable_name_len(expression_body);
+            let name = &expression_body[..name_len];
+            let modifier = &expression_body[name_len..];
+            let value = lookup(name);
+            let default = modifier
+                .strip_prefix(":-")
+                .or_else(|| modifier.strip_prefix('-'));
+            result.push_str(&value.or(default.map(str::to_string)).unwrap_or_default());
+            rest = &expression[end + 1..];
+        } else {
+            let name_len = variable_name_len(after);
+            if name_len == 0 {
+                result.push('$');
+            } else {
+                result.push_str(&lookup(&after[..name_len]).unwrap_or_default());
+            }
+            rest = &after[name_len..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn variable_name_len(value: &str) -> usize {
+    value
+        .char_indices()
+        .find(|(index, c)| {
+            !(c.is_ascii_alphanumeric() || *c == '_') || (*index == 0 && c.is_ascii_digit())
+        })
+        .map_or(value.len(), |(index, _)| index)
+}
+
+fn collect_strings<'a>(value: &'a Value, strings: &mut Vec<&'a str>) {
+    match value {
+        Value::String(value) => strings.push(value),
+        Value::Sequence(values) => values
+            .iter()
+            .for_each(|value| collect_strings(value, strings)),
+        Value::Mapping(mapping) => mapping
+            .values()
+            .for_each(|value| collect_strings(value, strings)),
+        Value::Tagged(tagged) => collect_strings(&tagged.value, strings),
+        _ => {}
+    }
+}
+
+fn string_or_sequence(value: Option<&Value>) -> Vec<&str> {
+    match value {
+        Some(Value::String(value)) => vec![value.as_str()],
+        Some(Value::Sequence(values)) => values
+            .iter()
+            .filter_map(|value| {
+                value
+                    .as_str()
+                    .or_else(|| value.get("path").and_then(Value::as_str))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_project(compose: &str, env: &str) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("docker-compose.yml"), compose).unwrap();
+        std::fs::write(dir.path().join(".env"), env).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_find_variable_references() {
+        let references = find_variable_references("${A}/${B:-x}/$C/$$D/${E-y}/${F:?err}");
+        let names: Vec<_> = references
+            .iter()
+            .map(|r| (r.name.as_str(), r.has_default))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("A", false),
+                ("B", true),
+                ("C", false),
+                ("E", true),
+                ("F", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_valid_compose_passes() {
+        let dir = write_project(
+            r#"
+services:
+  backend:
+    image: ${DOCKER_REGISTRY}/backend:${BACKEND_VERSION}
+    env_file: .env
+    volumes:
+      - ./config/app.yml:/app/app.yml
+      - ./data/backend:/data
+  mysql:
+    image: mysql:${MYSQL_TAG:-8.0}
+"#,
+            "DOCKER_REGISTRY=registry.example.com\nBACKEND_VERSION=1.2.0\n",
+        );
+        std::fs::create_dir_all(dir.path().join("config")).unwrap();
+        std::fs::write(dir.path().join("config/app.yml"), "").unwrap();
+
+        let report =
+            validate_compose_file(&dir.path().join("docker-compose.yml"), Architecture::Amd64);
+        assert!(report.is_valid(), "{:?}", report.errors);
+        assert_eq!(report.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_compose_reports_all_problems() {
+        let dir = write_project(
+            r#"
+services:
+  backend:
+    image: registry.example.com/backend:${BACKEND_VERSION}
+    volumes:
+      - ./config/app.yml:/app/app.yml
+  frontend:
+    image: registry.example.com/frontend:1.2.0-arm64
+  worker:
+    build: ./worker
+  broken:
+    restart: always
+"#,
+            "OTHER=1\n",
+        );
+
+        let report =
+            validate_compose_file(&dir.path().join("docker-compose.yml"), Architecture::Amd64);
+        let errors = report.errors.join("\n");
+        assert!(errors.contains("BACKEND_VERSION"));
+        assert!(errors.contains("镜像标签为空"));
+        assert!(errors.contains("app.yml"));
+        assert!(errors.contains("-arm64"));
+        assert!(errors.contains("构建目录不存在"));
+        assert!(errors.contains("服务 broken 未定义 image 或 build"));
+    }
+
+    #[test]
+    fn test_malformed_compose_fails() {
+        let dir = write_project("services:\n  app:\n    image: [unclosed\n", "");
+        let report =
+            validate_compose_file(&dir.path().join("docker-compose.yml"), Architecture::Amd64);
+        assert!(!report.is_valid());
+        assert!(report.errors[0].contains("格式错误"));
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use bollard::Docker;
+use bollard::container::{InspectContainerOptions, ListContainersOptions, LogsOptions};
+use chrono::{DateTime, Utc};
+use client_core::container::DockerManager;
+use serde::{Deserialize, Serialize};
+use tokio_stream::StreamExt;
+use tracing::{error, info, warn};
+
+use super::error::{DockerServiceError, DockerServiceResult};
+
+/// 每个容器默认收集的日志行数
+pub const DEFAULT_LOG_TAIL_LINES: usize = 100;
+
+/// 单个未正常启动容器的诊断信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerDiagnostics {
+    /// compose 服务名
+    pub service: String,
+    /// 容器名称，服务未创建容器时为空
+    pub container: Option<String>,
+    /// 容器状态（running、exited、restarting 等）
+    pub state: String,
+    /// 容器退出码
+    pub exit_code: Option<i64>,
+    /// 容器重启次数
+    pub restart_count: Option<i64>,
+    /// 健康检查状态
+    pub health: Option<String>,
+    /// 容器最近的日志
+    pub logs: Vec<String>,
+}
+
+/// 服务启动失败诊断报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupFailureReport {
+    /// 触发报告的操作（如升级部署、自动备份）
+    pub operation: String,
+    /// 生成时间
+    pub generated_at: DateTime<Utc>,
+    /// 未正常启动的容器
+    pub containers: Vec<ContainerDiagnostics>,
+    /// 收集过程中的错误
+    pub errors: Vec<String>,
+}
+
+impl StartupFailureReport {
+    fn new(operation: &str) -> Self {
+        Self {
+            operation: operation.to_string(),
+            generated_at: Utc::now(),
+            containers: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// 生成可直接输出到终端的文本报告
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "服务启动失败诊断 ({}，{})\n",
+            self.operation,
+            self.generated_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+
+        for container in &self.containers {
+            text.push_str(&format!(
+                "\n▶ 服务 {} (容器: {})\n",
+                container.service,
+                container.container.as_deref().unwrap_or("未创建")
+            ));
+            text.push_str(&format!(
+                "  状态: {}  退出码: {}  重启次数: {}  健康检查: {}\n",
+                container.state,
+                display_option(container.exit_code),
+                display_option(container.restart_count),
+                container.health.as_deref().unwrap_or("-")
+            ));
+            if container.logs.is_empty() {
+                text.push_str("  (无日志)\n");
+            } else {
+                text.push_str(&format!("  最近 {} 行日志:\n", container.logs.len()));
+                for line in &container.logs {
+                    text.push_str(&format!("    {line}\n"));
+                }
+            }
+        }
+
+        for error in &self.errors {
+            text.push_str(&format!("\n⚠️ {error}\n"));
+        }
+        text
+    }
+
+    /// 保存为 JSON 文件，供诊断包收集
+    pub fn save(&self, path: &Path) -> DockerServiceResult<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(|e| {
+            DockerServiceError::Configuration(format!("序列化启动失败诊断报告失败: {e}"))
+        })?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// 收集 compose 项目中未正常启动容器的状态和最近日志
+///
+/// 单个容器收集失败只记入报告的错误列表，不中断其他容器的收集
+pub async fn collect_startup_failure_report(
+    docker_manager: &DockerManager,
+    operation: &str,
+    tail_lines: usize,
+) -> StartupFailureReport {
+    let mut report = StartupFailureReport::new(operation);
+
+    let docker = match Docker::connect_with_socket_defaults() {
+        Ok(docker) => docker,
+        Err(e) => {
+            report.errors.push(format!("连接Docker失败: {e}"));
+            return report;
+        }
+    };
+
+    let project_name = docker_manager.get_compose_project_name();
+    let mut filters = HashMap::new();
+    filters.insert(
+        "label".to_string(),
+        vec![format!("com.docker.compose.project={project_name}")],
+    );
+    let containers = match docker
+        .list_containers(Some(ListContainersOptions::<String> {
+            all: true,
+            filters,
+            ..Default::default()
+        }))
+        .await
+    {
+        Ok(containers) => containers,
+        Err(e) => {
+            report.errors.push(format!("获取容器列表失败: {e}"));
+            return report;
+        }
+    };
+
+    let mut seen_services = HashSet::new();
+    for summary in containers {
+        let Some(id) = summary.id else {
+            continue;
+        };
+        let labels = summary.labels.unwrap_or_default();
+        let service = labels
+            .get("com.docker.compose.service")
+            .cloned()
+            .unwrap_or_else(|| id.clone());
+        seen_services.insert(service.clone());
+
+        let inspect = match docker
+            .inspect_container(&id, None::<InspectContainerOptions>)
+            .await
+        {
+            Ok(inspect) => inspect,
+            Err(e) => {
+                report
+                    .errors
+                    .push(format!("获取服务 {service} 的容器信息失败: {e}"));
+                continue;
+            }
+        };
+
+        let state = inspect.state.unwrap_or_default();
+        let status = state
+            .status
+            .map(|status| status.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        let health = state
+            .health
+            .and_then(|health| health.status)
+            .map(|status| status.to_string())
+            .filter(|status| !status.is_empty() && status != "none");
+        if !needs_diagnosis(&status, state.exit_code, health.as_deref()) {
+            continue;
+        }
+
+        let container_name = inspect
+            .name
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| id.clone());
+        let logs = match fetch_container_logs(&docker, &id, tail_lines).await {
+            Ok(logs) => logs,
+            Err(e) => {
+                report
+                    .errors
+                    .push(format!("获取容器 {container_name} 的日志失败: {e}"));
+                Vec::new()
+            }
+        };
+
+        report.containers.push(ContainerDiagnostics {
+            service,
+            container: Some(container_name),
+            state: status,
+            exit_code: state.exit_code,
+            restart_count: inspect.restart_count,
+            health,
+            logs,
+        });
+    }
+
+    // compose 中定义但没有创建容器的服务
+    match docker_manager.get_compose_service_names().await {
+        Ok(services) => {
+            let mut missing: Vec<_> = services
+                .into_iter()
+                .filter(|service| !seen_services.contains(service))
+                .collect();
+            missing.sort();
+            for service in missing {
+                report.containers.push(ContainerDiagnostics {
+                    service,
+                    container: None,
+                    state: "未创建".to_string(),
+                    exit_code: None,
+                    restart_count: None,
+                    health: None,
+                    logs: Vec::new(),
+                });
+            }
+        }
+        Err(e) => report.errors.push(format!("获取compose服务列表失败: {e}")),
+    }
+
+    report.containers.sort_by(|a, b| a.service.cmp(&b.service));
+    report
+}
+
+/// 收集启动失败诊断报告，输出到错误日志并保存到 `report_path`
+pub async fn report_startup_failure(
+    docker_manager: &DockerManager,
+    operation: &str,
+    report_path: &Path,
+) -> StartupFailureReport {
+    info!("🩺 正在收集未正常启动容器的状态和日志...");
+    let report =
+        collect_startup_failure_report(docker_manager, operation, DEFAULT_LOG_TAIL_LINES).await;
+
+    if report.containers.is_empty() && report.errors.is_empty() {
+        info!("🔍 未发现异常容器，服务可能仍在启动中");
+    } else {
+        error!("{}", report.to_text());
+    }
+
+    match report.save(report_path) {
+        Ok(()) => info!(
+            "📄 诊断报告已保存: {}（nuwax-cli support-bundle 会自动收集）",
+            report_path.display()
+        ),
+        Err(e) => warn!("⚠️ 保存诊断报告失败: {}", e),
+    }
+    report
+}
+
+/// 判断容器是否未正常启动：非运行状态（正常退出的一次性任务除外）或健康检查未通过
+fn needs_diagnosis(status: &str, exit_code: Option<i64>, health: Option<&str>) -> bool {
+    match status {
+        "running" => matches!(health, Some("unhealthy" | "starting")),
+        "exited" => exit_code != Some(0),
+        _ => true,
+    }
+}
+
+/// 读取容器最近 `tail_lines` 行日志（标准输出和标准错误）
+async fn fetch_container_logs(
+    docker: &Docker,
+    container: &str,
+    tail_lines: usize,
+) -> Result<Vec<String>, bollard::errors::Error> {
+    let chunks: Vec<_> = docker
+        .logs(
+            container,
+            Some(LogsOptions::<String> {
+                stdout: true,
+                stderr: true,
+                tail: tail_lines.to_string(),
+                ..Default::default()
+            }),
+        )
+        .collect()
+        .await;
+
+    let mut output = String::new();
+    for chunk in chunks {
+        output.push_str(&chunk?.to_string());
+    }
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+fn display_option(value: Option<i64>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_diagnosis() {
+        assert!(!needs_diagnosis("running", None, None));
+        assert!(!needs_diagnosis("running", None, Some("healthy")));
+        assert!(needs_diagnosis("running", None, Some("unhealthy")));
+        assert!(needs_diagnosis("running", None, Some("starting")));
+        assert!(!needs_diagnosis("exited", Some(0), None));
+        assert!(needs_diagnosis("exited", Some(137), None));
+        assert!(needs_diagnosis("restarting", Some(1), None));
+        assert!(needs_diagnosis("created", None, None));
+    }
+
+    #[test]
+    fn test_report_text_includes_exit_code_and_logs() {
+        let mut report = StartupFailureReport::new("升级部署");
+        report.containers.push(ContainerDiagnostics {
+            service: "backend".to_string(),
+            container: Some("nuwax-backend-1".to_string()),
+            state: "restarting".to_string(),
+            exit_code: Some(1),
+            restart_count: Some(5),
+            health: None,
+            logs: vec!["Error: connect ECONNREFUSED mysql:3306".to_string()],
+        });
+        report.containers.push(ContainerDiagnostics {
+            service: "frontend".to_string(),
+            container: None,
+            state: "未创建".to_string(),
+            exit_code: None,
+            restart_count: None,
+            health: None,
+            logs: Vec::new(),
+        });
+
+        let text = report.to_text();
+        assert!(text.contains("服务 backend (容器: nuwax-backend-1)"));
+        assert!(text.contains("退出码: 1  重启次数: 5"));
+        assert!(text.contains("ECONNREFUSED mysql:3306"));
+        assert!(text.contains("服务 frontend (容器: 未创建)"));
+        assert!(text.contains("(无日志)"));
+    }
+}
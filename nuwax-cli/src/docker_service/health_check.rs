@@ -4,6 +4,7 @@ use bollard::container::{InspectContainerOptions, ListContainersOptions};
 use bollard::models::{Health, HealthStatusEnum};
 use client_core::constants::timeout;
 use client_core::container::DockerManager;
+use client_core::container::orphans::OrphanContainer;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use std::{collections::HashSet, sync::Arc};
@@ -260,6 +261,50 @@ impl ServiceStatus {
     }
 }
 
+/// 健康检查范围：只评估部分服务，例如备份前只要求核心服务健康
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthScope {
+    /// 只检查这些服务，为空表示检查 compose 文件中的全部服务
+    #[serde(default)]
+    pub services: Vec<String>,
+    /// 不检查这些服务
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl HealthScope {
+    pub fn new(services: Vec<String>, exclude: Vec<String>) -> Self {
+        Self { services, exclude }
+    }
+
+    /// 是否检查全部服务
+    pub fn is_all(&self) -> bool {
+        self.services.is_empty() && self.exclude.is_empty()
+    }
+
+    /// 服务是否在检查范围内
+    pub fn includes(&self, service: &str) -> bool {
+        (self.services.is_empty() || self.services.iter().any(|s| s == service))
+            && !self.exclude.iter().any(|s| s == service)
+    }
+
+    /// 范围的显示文本
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.services.is_empty() {
+            parts.push(format!("只检查 {}", self.services.join(", ")));
+        }
+        if !self.exclude.is_empty() {
+            parts.push(format!("排除 {}", self.exclude.join(", ")));
+        }
+        if parts.is_empty() {
+            "全部服务".to_string()
+        } else {
+            parts.join("，")
+        }
+    }
+}
+
 /// 健康检查报告
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthReport {
@@ -275,6 +320,15 @@ pub struct HealthReport {
     pub check_time: chrono::DateTime<chrono::Utc>,
     /// 错误信息
     pub errors: Vec<String>,
+    /// 检查范围
+    #[serde(default)]
+    pub scope: HealthScope,
+    /// 不在检查范围内的服务，不参与整体状态和各项统计
+    #[serde(default)]
+    pub out_of_scope: Vec<ContainerInfo>,
+    /// 服务已从 compose 文件中删除的容器，不参与整体状态和各项统计
+    #[serde(default)]
+    pub orphans: Vec<OrphanContainer>,
 }
 
 impl HealthReport {
@@ -288,6 +342,22 @@ impl HealthReport {
         self.errors.push(error);
     }
 
+    /// 按检查范围拆分报告：范围外的服务移到 `out_of_scope`，范围中不存在的服务记为错误
+    pub fn apply_scope(&mut self, scope: &HealthScope) {
+        self.containers.append(&mut self.out_of_scope);
+        for name in scope.services.iter().chain(&scope.exclude) {
+            if !self.containers.iter().any(|c| &c.name == name) {
+                self.add_error(format!("服务 {name} 不在 compose 文件中"));
+            }
+        }
+        let (in_scope, out_of_scope) = std::mem::take(&mut self.containers)
+            .into_iter()
+            .partition(|c| scope.includes(&c.name));
+        self.containers = in_scope;
+        self.out_of_scope = out_of_scope;
+        self.scope = scope.clone();
+    }
+
     /// 完成报告并计算整体状态
     pub fn finalize(&self) -> ServiceStatus {
         let healthy_count = self.get_healthy_count();
@@ -446,6 +516,9 @@ impl Default for HealthReport {
             total_count: 0,
             check_time: chrono::Utc::now(),
             errors: Vec::new(),
+            scope: HealthScope::default(),
+            out_of_scope: Vec::new(),
+            orphans: Vec::new(),
         }
     }
 }
@@ -606,6 +679,12 @@ impl HealthChecker {
             added_containers.len()
         );
 
+        // 服务已从 compose 文件中删除、但仍带有当前项目标签的容器
+        match self.docker_manager.find_orphan_containers().await {
+            Ok(orphans) => report.orphans = orphans,
+            Err(e) => warn!("⚠️  检测孤立容器失败: {}", e),
+        }
+
         // 为未找到的compose服务创建"已停止"状态的条目
         for service_name in &compose_services {
             if !found_services.contains(service_name) {
@@ -666,6 +745,23 @@ impl HealthChecker {
         Ok(report)
     }
 
+    /// 按范围执行健康检查，范围外的服务只在报告中列出，不影响整体状态
+    pub async fn health_check_scoped(
+        &self,
+        scope: &HealthScope,
+    ) -> DockerServiceResult<HealthReport> {
+        let mut report = self.health_check().await?;
+        if !scope.is_all() {
+            report.apply_scope(scope);
+            info!(
+                "🎯 检查范围: {}（{} 个服务不在范围内）",
+                scope.describe(),
+                report.out_of_scope.len()
+            );
+        }
+        Ok(report)
+    }
+
     /// 智能判断容器状态
     fn determine_container_status(
         &self,
@@ -946,6 +1042,77 @@ impl HealthChecker {
         }
     }
 
+    /// 根据compose服务名查找当前项目中对应的容器名
+    /// 使用与健康检查相同的标签匹配逻辑，存在多个容器时优先返回运行中的容器
+    pub async fn resolve_service_container(
+        &self,
+        service_name: &str,
+    ) -> DockerServiceResult<String> {
+        let compose_services = self
+            .docker_manager
+            .get_compose_service_names()
+            .await
+            .map_err(|e| {
+                DockerServiceError::Configuration(format!("获取compose服务列表失败: {e}"))
+            })?;
+        if !compose_services.contains(service_name) {
+            let mut available: Vec<_> = compose_services.into_iter().collect();
+            available.sort();
+            return Err(DockerServiceError::Configuration(format!(
+                "compose文件中未定义服务 {service_name}，可用服务: {}",
+                available.join(", ")
+            )));
+        }
+
+        let compose_project_name = self.docker_manager.get_compose_project_name();
+        let compose_file_path = self
+            .docker_manager
+            .get_compose_file()
+            .to_string_lossy()
+            .to_string();
+        let all_containers = self
+            .docker_manager
+            .get_all_containers_status()
+            .await
+            .map_err(|e| DockerServiceError::DockerCommand(format!("获取容器状态失败: {e}")))?;
+
+        let mut stopped_match = None;
+        for container in &all_containers {
+            if self
+                .get_container_service_name(&container.name)
+                .await
+                .as_deref()
+                != Some(service_name)
+            {
+                continue;
+            }
+            if !self
+                .is_container_from_compose_project(
+                    &container.name,
+                    &compose_project_name,
+                    &compose_file_path,
+                )
+                .await
+            {
+                continue;
+            }
+            if container.status == client_core::container::ServiceStatus::Running {
+                debug!("服务 {} 对应容器: {}", service_name, container.name);
+                return Ok(container.name.clone());
+            }
+            stopped_match.get_or_insert_with(|| container.name.clone());
+        }
+
+        match stopped_match {
+            Some(name) => Err(DockerServiceError::ServiceManagement(format!(
+                "服务 {service_name} 的容器 {name} 未运行，请先启动服务"
+            ))),
+            None => Err(DockerServiceError::ServiceManagement(format!(
+                "未找到服务 {service_name} 的容器，请先启动服务"
+            ))),
+        }
+    }
+
     /// 获取服务状态摘要
     pub async fn get_status_summary(&self) -> DockerServiceResult<String> {
         let report = self.health_check().await?;
@@ -1005,4 +1172,40 @@ mod tests {
         assert_eq!(report.running_count, 1);
         assert_eq!(report.total_count, 2);
     }
+
+    #[test]
+    fn test_health_report_scope() {
+        let container = |name: &str, status: ContainerStatus| ContainerInfo {
+            name: name.to_string(),
+            status,
+            image: "test:latest".to_string(),
+            ports: vec![],
+            uptime: None,
+            health: Some(HealthStatusEnum::HEALTHY),
+            is_oneshot: false,
+            restart: Some(RestartPolicy::Always),
+        };
+        let mut report = HealthReport::default();
+        report.add_container(container("mysql", ContainerStatus::Running));
+        report.add_container(container("backend", ContainerStatus::Running));
+        report.add_container(container("video-analysis-worker", ContainerStatus::Stopped));
+        assert_eq!(report.finalize(), ServiceStatus::PartiallyRunning);
+
+        report.apply_scope(&HealthScope::new(
+            Vec::new(),
+            vec!["video-analysis-worker".to_string()],
+        ));
+        assert_eq!(report.finalize(), ServiceStatus::AllRunning);
+        assert_eq!(report.out_of_scope.len(), 1);
+        assert!(report.errors.is_empty());
+
+        // 重新应用范围时从完整的服务列表开始，不存在的服务记为错误
+        report.apply_scope(&HealthScope::new(
+            vec!["mysql".to_string(), "redis".to_string()],
+            Vec::new(),
+        ));
+        assert_eq!(report.get_total_count(), 1);
+        assert_eq!(report.out_of_scope.len(), 2);
+        assert_eq!(report.errors, vec!["服务 redis 不在 compose 文件中"]);
+    }
 }
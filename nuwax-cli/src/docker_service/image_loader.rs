@@ -1,10 +1,11 @@
 use crate::docker_service::architecture::{Architecture, detect_architecture};
 use crate::docker_service::error::{DockerServiceError, DockerServiceResult};
-use client_core::constants::docker::DOCKER_SOCKET_PATH;
+use client_core::constants::docker::{DOCKER_SOCKET_PATH, IMAGE_MANIFEST_FILE_NAME};
 use client_core::container::DockerManager;
 // use client_core::{DuckError, Result};
 use ducker::docker::{image::DockerImage, util::new_local_docker_connection};
-use std::path::PathBuf;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tracing::{error, info, warn};
 
@@ -88,12 +89,74 @@ impl ImageInfo {
     }
 }
 
+/// 镜像清单中的单个镜像
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImageManifestEntry {
+    /// 镜像文件名，如 `agent-platform-front-amd64.tar`
+    pub file: String,
+    /// 镜像名称（带架构后缀），如 `nuwax/agent-platform-front:latest-amd64`
+    pub image: String,
+    /// 镜像仓库 digest，如 `sha256:...`
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+/// 随服务包发布的镜像清单（镜像目录下的 `images.json`），用于镜像文件损坏时从仓库拉取
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ImageManifest {
+    #[serde(default)]
+    pub images: Vec<ImageManifestEntry>,
+}
+
+impl ImageManifest {
+    /// 读取镜像目录中的清单，清单不存在时返回 `None`
+    pub fn load(images_dir: &Path) -> DockerServiceResult<Option<Self>> {
+        let path = images_dir.join(IMAGE_MANIFEST_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| DockerServiceError::FileSystem(e.to_string()))?;
+        serde_json::from_str(&content).map(Some).map_err(|e| {
+            DockerServiceError::ImageLoading(format!("无法解析镜像清单 {}: {e}", path.display()))
+        })
+    }
+
+    /// 查找镜像文件对应的清单记录
+    pub fn find(&self, file_name: &str) -> Option<&ImageManifestEntry> {
+        self.images.iter().find(|entry| entry.file == file_name)
+    }
+}
+
+/// 从镜像仓库拉取时使用的镜像地址
+///
+/// 配置了 `registry` 时替换镜像名称中原有的仓库地址，如
+/// `registry.example.com` + `docker.io/nuwax/app:v1` -> `registry.example.com/nuwax/app:v1`
+pub fn registry_reference(image: &str, registry: Option<&str>) -> String {
+    let Some(registry) = registry
+        .map(|registry| registry.trim().trim_end_matches('/'))
+        .filter(|registry| !registry.is_empty())
+    else {
+        return image.to_string();
+    };
+    // 第一段包含 `.` 或 `:`（或为 localhost）时是仓库地址
+    let path = match image.split_once('/') {
+        Some((host, rest)) if host.contains('.') || host.contains(':') || host == "localhost" => {
+            rest
+        }
+        _ => image,
+    };
+    format!("{registry}/{path}")
+}
+
 /// 镜像加载结果
 #[derive(Debug, Clone)]
 pub struct LoadResult {
     pub success_count: usize,
     pub failure_count: usize,
     pub loaded_images: Vec<String>,
+    /// 镜像文件加载失败后从镜像仓库拉取的镜像（文件名），同时计入 `loaded_images`
+    pub pulled_images: Vec<String>,
     pub failed_images: Vec<(String, String)>, // (文件名, 错误信息)
     pub image_mappings: Vec<(String, String)>, // (文件名, 实际镜像名称)
 }
@@ -104,6 +167,7 @@ impl LoadResult {
             success_count: 0,
             failure_count: 0,
             loaded_images: Vec::new(),
+            pulled_images: Vec::new(),
             failed_images: Vec::new(),
             image_mappings: Vec::new(),
         }
@@ -120,6 +184,11 @@ impl LoadResult {
         self.image_mappings.push((file_name, actual_image_name));
     }
 
+    pub fn add_pulled_with_mapping(&mut self, file_name: String, actual_image_name: String) {
+        self.pulled_images.push(file_name.clone());
+        self.add_success_with_mapping(file_name, actual_image_name);
+    }
+
     pub fn add_failure(&mut self, image_name: String, error: String) {
         self.failure_count += 1;
         self.failed_images.push((image_name, error));
@@ -136,6 +205,10 @@ impl LoadResult {
     pub fn failure_count(&self) -> usize {
         self.failure_count
     }
+
+    pub fn pulled_count(&self) -> usize {
+        self.pulled_images.len()
+    }
 }
 
 impl Default for LoadResult {
@@ -199,6 +272,8 @@ pub struct ImageLoader {
     work_dir: PathBuf,
     architecture: Architecture,
     images_dir: PathBuf,
+    /// 镜像文件加载失败时拉取使用的镜像仓库
+    registry: Option<String>,
 }
 
 impl ImageLoader {
@@ -212,9 +287,16 @@ impl ImageLoader {
             work_dir,
             architecture,
             images_dir,
+            registry: None,
         })
     }
 
+    /// 设置镜像文件加载失败时拉取使用的镜像仓库
+    pub fn with_registry(mut self, registry: Option<String>) -> Self {
+        self.registry = registry;
+        self
+    }
+
     /// 扫描并获取当前架构的镜像列表
     pub fn scan_architecture_images(&self) -> DockerServiceResult<Vec<ImageInfo>> {
         if !self.images_dir.exists() {
@@ -273,6 +355,10 @@ impl ImageLoader {
     pub async fn load_all_images(&self) -> DockerServiceResult<LoadResult> {
         let images = self.scan_architecture_images()?;
         let mut result = LoadResult::new();
+        let manifest = ImageManifest::load(&self.images_dir).unwrap_or_else(|e| {
+            warn!("{}，镜像文件加载失败时将无法从镜像仓库拉取", e);
+            None
+        });
 
         info!("开始加载 {} 个镜像文件...", images.len());
 
@@ -301,18 +387,67 @@ impl ImageLoader {
                 }
                 Err(e) => {
                     error!("{} ✗ 镜像加载失败: {} - {}", progress, file_name, e);
-                    result.add_failure(file_name.to_string(), e.to_string());
+                    match self.pull_from_registry(file_name, manifest.as_ref()).await {
+                        Ok(image_name) => {
+                            info!(
+                                "{} ✓ 已从镜像仓库拉取: {} -> {}",
+                                progress, file_name, image_name
+                            );
+                            result.add_pulled_with_mapping(file_name.to_string(), image_name);
+                        }
+                        Err(pull_error) => {
+                            error!(
+                                "{} ✗ 从镜像仓库拉取失败: {} - {}",
+                                progress, file_name, pull_error
+                            );
+                            result.add_failure(
+                                file_name.to_string(),
+                                format!("{e}; 从镜像仓库拉取失败: {pull_error}"),
+                            );
+                        }
+                    }
                 }
             }
         }
 
         info!(
-            "镜像加载完成: 成功 {}, 失败 {}",
-            result.success_count, result.failure_count
+            "镜像加载完成: 成功 {} (其中从仓库拉取 {}), 失败 {}",
+            result.success_count,
+            result.pulled_count(),
+            result.failure_count
         );
         Ok(result)
     }
 
+    /// 按镜像清单从镜像仓库拉取镜像文件对应的镜像，校验 digest 后打上清单中的镜像名称
+    async fn pull_from_registry(
+        &self,
+        file_name: &str,
+        manifest: Option<&ImageManifest>,
+    ) -> DockerServiceResult<String> {
+        let entry = manifest
+            .and_then(|manifest| manifest.find(file_name))
+            .ok_or_else(|| {
+                DockerServiceError::ImageLoading(format!(
+                    "镜像清单 {IMAGE_MANIFEST_FILE_NAME} 中没有 {file_name} 的记录"
+                ))
+            })?;
+        let reference = registry_reference(&entry.image, self.registry.as_deref());
+        if entry.digest.is_none() {
+            warn!("镜像清单未记录 {} 的 digest，跳过校验", entry.image);
+        }
+
+        info!("尝试从镜像仓库拉取: {}", reference);
+        self.docker_manager
+            .pull_image_verified(&reference, entry.digest.as_deref())
+            .await
+            .map_err(|e| DockerServiceError::ImageLoading(e.to_string()))?;
+        if reference != entry.image {
+            self.tag_image(&reference, &entry.image).await?;
+        }
+        Ok(entry.image.clone())
+    }
+
     /// 基于实际加载的镜像设置标签
     pub async fn setup_image_tags_with_mappings(
         &self,
@@ -624,4 +759,42 @@ mod tests {
         assert!(image_info.original_tag.contains("amd64"));
         assert!(!image_info.target_tag.contains("amd64"));
     }
+
+    #[test]
+    fn test_image_manifest() {
+        let temp_dir = tempdir().unwrap();
+        assert!(ImageManifest::load(temp_dir.path()).unwrap().is_none());
+
+        std::fs::write(
+            temp_dir.path().join(IMAGE_MANIFEST_FILE_NAME),
+            r#"{"images":[{"file":"agent-platform-front-amd64.tar","image":"nuwax/agent-platform-front:latest-amd64","digest":"sha256:abc"}]}"#,
+        )
+        .unwrap();
+        let manifest = ImageManifest::load(temp_dir.path()).unwrap().unwrap();
+        let entry = manifest.find("agent-platform-front-amd64.tar").unwrap();
+        assert_eq!(entry.image, "nuwax/agent-platform-front:latest-amd64");
+        assert_eq!(entry.digest.as_deref(), Some("sha256:abc"));
+        assert!(manifest.find("mysql-amd64.tar").is_none());
+    }
+
+    #[test]
+    fn test_registry_reference() {
+        let image = "nuwax/agent-platform-front:latest-amd64";
+        assert_eq!(registry_reference(image, None), image);
+        assert_eq!(
+            registry_reference(image, Some("mirror.example.com/")),
+            "mirror.example.com/nuwax/agent-platform-front:latest-amd64"
+        );
+        assert_eq!(
+            registry_reference(
+                "registry.example.com:5000/nuwax/app:v1",
+                Some("mirror.local")
+            ),
+            "mirror.local/nuwax/app:v1"
+        );
+        assert_eq!(
+            registry_reference("mysql:8.0-amd64", Some("mirror.local")),
+            "mirror.local/mysql:8.0-amd64"
+        );
+    }
 }
@@ -1,10 +1,11 @@
 use crate::docker_service::architecture::{Architecture, detect_architecture};
 use crate::docker_service::directory_permissions::DirectoryPermissionManager;
 use crate::docker_service::error::{DockerServiceError, DockerServiceResult};
-use crate::docker_service::health_check::{HealthChecker, HealthReport};
+use crate::docker_service::health_check::{HealthChecker, HealthReport, HealthScope};
 use crate::docker_service::image_loader::{ImageLoader, LoadResult, TagResult};
 use crate::docker_service::port_manager::PortManager;
 use crate::docker_service::script_permissions::ScriptPermissionManager;
+use crate::docker_service::service_manager::ServiceManager;
 
 use client_core::config::AppConfig;
 use client_core::constants::timeout;
@@ -16,7 +17,6 @@ use tracing::{error, info, warn};
 
 /// Docker 服务管理器
 pub struct DockerServiceManager {
-    #[allow(dead_code)]
     config: Arc<AppConfig>,
     docker_manager: Arc<DockerManager>,
     work_dir: PathBuf,
@@ -39,7 +39,8 @@ impl DockerServiceManager {
 
         // 由于 DockerManager 实现了 Clone，我们可以安全地克隆它
         let image_loader = ImageLoader::new(docker_manager.clone(), work_dir.clone())
-            .expect("Failed to create image loader");
+            .expect("Failed to create image loader")
+            .with_registry(config.docker.image_registry.clone());
         let health_checker = HealthChecker::new(docker_manager.clone());
 
         Self {
@@ -416,6 +417,25 @@ impl DockerServiceManager {
     pub async fn stop_services(&self) -> DockerServiceResult<()> {
         info!("停止 Docker Compose 服务...");
 
+        // 先按依赖顺序逐个停止服务并等待容器退出，避免数据库等服务在落盘前被强制终止
+        let service_manager =
+            ServiceManager::new(self.docker_manager.clone(), self.config.docker.stop.clone());
+        match service_manager.stop_services_gracefully().await {
+            Ok(results) => {
+                let unclean: Vec<&str> = results
+                    .iter()
+                    .filter(|result| !result.is_clean())
+                    .map(|result| result.service.as_str())
+                    .collect();
+                if !unclean.is_empty() {
+                    warn!("⚠️ 以下服务未正常退出: {}", unclean.join(", "));
+                }
+            }
+            Err(e) => {
+                warn!("⚠️ 按顺序停止服务失败，直接执行 docker compose down: {}", e);
+            }
+        }
+
         // 直接使用已配置的 DockerManager，无需切换目录
         let result = self.docker_manager.stop_services().await;
 
@@ -431,6 +451,33 @@ impl DockerServiceManager {
         }
     }
 
+    /// 按依赖顺序只停止指定的服务，其余服务保持运行
+    pub async fn stop_selected_services(&self, services: &[String]) -> DockerServiceResult<()> {
+        let service_manager =
+            ServiceManager::new(self.docker_manager.clone(), self.config.docker.stop.clone());
+        let results = service_manager
+            .stop_selected_services(services)
+            .await
+            .map_err(|e| DockerServiceError::ServiceManagement(e.to_string()))?;
+        if let Some(result) = results.iter().find(|result| result.timed_out) {
+            return Err(DockerServiceError::ServiceManagement(format!(
+                "等待服务 {} 退出超时",
+                result.service
+            )));
+        }
+        Ok(())
+    }
+
+    /// 按依赖顺序重建指定的服务，不影响其他服务
+    pub async fn recreate_selected_services(&self, services: &[String]) -> DockerServiceResult<()> {
+        let service_manager =
+            ServiceManager::new(self.docker_manager.clone(), self.config.docker.stop.clone());
+        service_manager
+            .recreate_selected_services(services)
+            .await
+            .map_err(|e| DockerServiceError::ServiceManagement(e.to_string()))
+    }
+
     /// 重启所有服务
     pub async fn restart_services(&mut self) -> DockerServiceResult<()> {
         info!("重启 Docker Compose 服务...");
@@ -469,6 +516,14 @@ impl DockerServiceManager {
         self.health_checker.health_check().await
     }
 
+    /// 按范围执行健康检查
+    pub async fn health_check_scoped(
+        &self,
+        scope: &HealthScope,
+    ) -> DockerServiceResult<HealthReport> {
+        self.health_checker.health_check_scoped(scope).await
+    }
+
     /// 获取服务状态摘要
     pub async fn get_status_summary(&self) -> DockerServiceResult<String> {
         self.health_checker.get_status_summary().await
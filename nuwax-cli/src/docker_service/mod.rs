@@ -7,16 +7,22 @@ use client_core::container::DockerManager;
 // 子模块声明
 pub mod architecture;
 pub mod compose_parser;
+pub mod compose_validation;
 pub mod config;
 pub mod directory_permissions;
 pub mod environment;
 pub mod error;
+pub mod failure_report;
 pub mod health_check;
 pub mod image_loader;
 pub mod manager;
+pub mod permission_policy;
 pub mod port_manager;
 pub mod script_permissions;
+pub mod service_gate;
+pub mod service_impact;
 pub mod service_manager;
+pub mod watchdog;
 
 // 公共接口导出
 pub use architecture::{Architecture, detect_architecture};
@@ -27,12 +33,13 @@ pub use environment::EnvironmentChecker;
 #[allow(unused_imports)]
 pub use error::{DockerServiceError, DockerServiceResult};
 #[allow(unused_imports)]
-pub use health_check::{ContainerStatus, HealthReport, ServiceStatus};
+pub use health_check::{ContainerStatus, HealthReport, HealthScope, ServiceStatus};
 #[allow(unused_imports)]
 pub use image_loader::{ImageInfo, ImageLoader, ImageType, LoadResult, TagResult};
 pub use manager::DockerServiceManager;
 #[allow(unused_imports)]
 pub use port_manager::{PortConflict, PortConflictReport, PortManager, PortMapping};
+pub use service_gate::{ServiceGate, ServiceGateReport};
 #[allow(unused_imports)]
 pub use service_manager::ServiceManager;
 
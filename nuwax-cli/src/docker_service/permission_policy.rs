@@ -0,0 +1,573 @@
+//! 声明式权限策略
+//!
+//! 服务包可在工作目录中附带 `permissions.yaml`，按路径模式声明期望的权限和属主；
+//! 未提供时使用与原有硬编码规则等价的内置策略。策略在解压服务包和从备份恢复后应用，
+//! 也可通过 `docker-service fix-perms --check` 只报告不一致的路径。
+//!
+//! ```yaml
+//! rules:
+//!   - path: "data/mysql"
+//!     mode: "775"
+//!   - path: "**/*.sh"
+//!     type: file
+//!     mode: "+x"
+//!   - path: "data/redis/**"
+//!     owner: "999:999"
+//! ```
+//!
+//! 同一路径匹配多条规则时按顺序依次应用，后面的规则覆盖前面的设置。
+
+use crate::docker_service::error::{DockerServiceError, DockerServiceResult};
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+use walkdir::WalkDir;
+
+/// 服务包中的权限策略文件名（位于 Docker 工作目录）
+pub const PERMISSION_POLICY_FILE_NAME: &str = "permissions.yaml";
+
+/// 内置默认策略，与原有的硬编码规则一致
+const DEFAULT_POLICY: &str = r#"
+rules:
+  - path: "data/mysql"
+    type: dir
+    mode: "775"
+  - path: "logs/mysql"
+    type: dir
+    mode: "775"
+  - path: "**/*.sh"
+    type: file
+    mode: "+x"
+"#;
+
+/// 权限模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeSpec {
+    /// 设置为指定的权限位（如 775）
+    Exact(u32),
+    /// 在现有权限上增加可执行位（+x）
+    AddExecute,
+}
+
+impl ModeSpec {
+    /// 根据当前权限计算期望权限
+    pub fn apply(&self, current: u32) -> u32 {
+        match self {
+            Self::Exact(mode) => *mode,
+            Self::AddExecute => current | 0o111,
+        }
+    }
+}
+
+impl std::str::FromStr for ModeSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "+x" {
+            return Ok(Self::AddExecute);
+        }
+        match u32::from_str_radix(s.trim_start_matches("0o"), 8) {
+            Ok(mode) if mode <= 0o7777 => Ok(Self::Exact(mode)),
+            _ => Err(format!("无效的权限模式: {s}（应为八进制如 775，或 +x）")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ModeSpec {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 文件属主（数字形式的 uid[:gid]）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OwnerSpec {
+    pub uid: u32,
+    pub gid: Option<u32>,
+}
+
+impl std::str::FromStr for OwnerSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("无效的属主: {s}（应为数字形式的 uid 或 uid:gid）");
+        let (uid, gid) = match s.trim().split_once(':') {
+            Some((uid, gid)) => (uid, Some(gid)),
+            None => (s.trim(), None),
+        };
+        Ok(Self {
+            uid: uid.parse().map_err(|_| invalid())?,
+            gid: gid
+                .map(|gid| gid.parse().map_err(|_| invalid()))
+                .transpose()?,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for OwnerSpec {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for OwnerSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.gid {
+            Some(gid) => write!(f, "{}:{}", self.uid, gid),
+            None => write!(f, "{}", self.uid),
+        }
+    }
+}
+
+/// 规则适用的路径类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    #[default]
+    Any,
+    File,
+    Dir,
+}
+
+/// 单条权限规则
+#[derive(Debug, Clone, Deserialize)]
+pub struct PermissionRule {
+    /// 相对于工作目录的路径模式，支持 `*`、`?` 和 `**`
+    pub path: String,
+    #[serde(default, rename = "type")]
+    pub kind: EntryKind,
+    pub mode: Option<ModeSpec>,
+    pub owner: Option<OwnerSpec>,
+}
+
+impl PermissionRule {
+    fn matches(&self, relative: &[&str], is_dir: bool) -> bool {
+        let kind_matches = match self.kind {
+            EntryKind::Any => true,
+            EntryKind::File => !is_dir,
+            EntryKind::Dir => is_dir,
+        };
+        kind_matches && glob_match(&pattern_segments(&self.path), relative)
+    }
+}
+
+/// 权限策略
+#[derive(Debug, Clone, Deserialize)]
+pub struct PermissionPolicy {
+    pub rules: Vec<PermissionRule>,
+}
+
+impl PermissionPolicy {
+    pub fn from_yaml(content: &str) -> DockerServiceResult<Self> {
+        let policy: Self = serde_yaml::from_str(content)
+            .map_err(|e| DockerServiceError::Configuration(format!("解析权限策略失败: {e}")))?;
+        if let Some(rule) = policy
+            .rules
+            .iter()
+            .find(|rule| rule.mode.is_none() && rule.owner.is_none())
+        {
+            return Err(DockerServiceError::Configuration(format!(
+                "权限规则 {} 未设置 mode 或 owner",
+                rule.path
+            )));
+        }
+        Ok(policy)
+    }
+
+    /// 内置默认策略
+    pub fn builtin() -> Self {
+        Self::from_yaml(DEFAULT_POLICY).expect("内置权限策略格式正确")
+    }
+
+    /// 读取工作目录中的策略文件，不存在时使用内置策略
+    pub fn load(work_dir: &Path) -> DockerServiceResult<Self> {
+        let path = work_dir.join(PERMISSION_POLICY_FILE_NAME);
+        if !path.exists() {
+            debug!("未找到权限策略文件，使用内置策略: {}", path.display());
+            return Ok(Self::builtin());
+        }
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            DockerServiceError::FileSystem(format!("读取权限策略失败 {}: {e}", path.display()))
+        })?;
+        info!("📜 使用权限策略: {}", path.display());
+        Self::from_yaml(&content)
+    }
+
+    /// 计算路径的期望权限和属主，未匹配任何规则时返回 None
+    fn expected(
+        &self,
+        relative: &[&str],
+        is_dir: bool,
+        current_mode: u32,
+    ) -> Option<(Option<u32>, Option<OwnerSpec>)> {
+        let mut matched = false;
+        let mut mode = None;
+        let mut owner = None;
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(relative, is_dir))
+        {
+            matched = true;
+            if let Some(spec) = rule.mode {
+                mode = Some(spec.apply(mode.unwrap_or(current_mode)));
+            }
+            if rule.owner.is_some() {
+                owner = rule.owner;
+            }
+        }
+        matched.then_some((mode, owner))
+    }
+
+    /// 目录下是否可能存在匹配的路径（用于跳过无关的大目录）
+    fn may_match_below(&self, relative: &[&str]) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| prefix_may_match(&pattern_segments(&rule.path), relative))
+    }
+
+    /// 检查工作目录中与策略不一致的路径
+    pub fn check(&self, work_dir: &Path) -> DockerServiceResult<Vec<PermissionDeviation>> {
+        let mut deviations = Vec::new();
+        self.walk(work_dir, |deviation| {
+            deviations.push(deviation);
+            Ok(())
+        })?;
+        Ok(deviations)
+    }
+
+    /// 将策略应用到工作目录，返回修复的路径
+    pub fn apply(&self, work_dir: &Path) -> DockerServiceResult<Vec<PermissionDeviation>> {
+        let mut fixed = Vec::new();
+        self.walk(work_dir, |deviation| {
+            deviation.fix()?;
+            debug!("已修复权限: {}", deviation);
+            fixed.push(deviation);
+            Ok(())
+        })?;
+        Ok(fixed)
+    }
+
+    #[cfg(unix)]
+    fn walk<F>(&self, work_dir: &Path, mut on_deviation: F) -> DockerServiceResult<()>
+    where
+        F: FnMut(PermissionDeviation) -> DockerServiceResult<()>,
+    {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let relative_segments = |path: &Path| -> Vec<String> {
+            path.strip_prefix(work_dir)
+                .unwrap_or(path)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        let walker = WalkDir::new(work_dir)
+            .min_depth(1)
+            .follow_links(false)
+            .into_iter()
+            .filter_entry(|entry| {
+                if !entry.file_type().is_dir() {
+                    return true;
+                }
+                let segments = relative_segments(entry.path());
+                let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+                self.may_match_below(&segments) || self.expected(&segments, true, 0).is_some()
+            });
+
+        for entry in walker {
+            let entry =
+                entry.map_err(|e| DockerServiceError::FileSystem(format!("访问目录失败: {e}")))?;
+            // 符号链接的权限没有意义，修改时还会作用到链接目标
+            if entry.file_type().is_symlink() {
+                continue;
+            }
+            let metadata = entry
+                .metadata()
+                .map_err(|e| DockerServiceError::FileSystem(format!("获取文件元数据失败: {e}")))?;
+            let current_mode = metadata.permissions().mode() & 0o7777;
+            let segments = relative_segments(entry.path());
+            let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+            let Some((mode, owner)) = self.expected(&segments, metadata.is_dir(), current_mode)
+            else {
+                continue;
+            };
+            let mode = mode.filter(|mode| *mode != current_mode);
+            let owner = owner.filter(|owner| {
+                owner.uid != metadata.uid() || owner.gid.is_some_and(|gid| gid != metadata.gid())
+            });
+            if mode.is_none() && owner.is_none() {
+                continue;
+            }
+
+            on_deviation(PermissionDeviation {
+                path: entry.path().to_path_buf(),
+                current_mode,
+                expected_mode: mode,
+                current_owner: OwnerSpec {
+                    uid: metadata.uid(),
+                    gid: Some(metadata.gid()),
+                },
+                expected_owner: owner,
+            })?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn walk<F>(&self, _work_dir: &Path, _on_deviation: F) -> DockerServiceResult<()>
+    where
+        F: FnMut(PermissionDeviation) -> DockerServiceResult<()>,
+    {
+        info!("ℹ️ 非Unix系统，跳过权限策略检查");
+        Ok(())
+    }
+}
+
+/// 与策略不一致的路径
+#[derive(Debug, Clone)]
+pub struct PermissionDeviation {
+    pub path: PathBuf,
+    pub current_mode: u32,
+    /// 期望的权限（权限一致时为 None）
+    pub expected_mode: Option<u32>,
+    pub current_owner: OwnerSpec,
+    /// 期望的属主（属主一致时为 None）
+    pub expected_owner: Option<OwnerSpec>,
+}
+
+impl PermissionDeviation {
+    #[cfg(unix)]
+    fn fix(&self) -> DockerServiceResult<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Some(owner) = self.expected_owner {
+            std::os::unix::fs::chown(&self.path, Some(owner.uid), owner.gid).map_err(|e| {
+                DockerServiceError::Permission(format!(
+                    "修改属主失败 {}: {e}（可能需要 root 权限）",
+                    self.path.display()
+                ))
+            })?;
+        }
+        // chown 会清除 setuid/setgid 位，因此最后设置权限
+        if let Some(mode) = self.expected_mode {
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(mode)).map_err(
+                |e| {
+                    DockerServiceError::Permission(format!(
+                        "设置权限失败 {}: {e}",
+                        self.path.display()
+                    ))
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn fix(&self) -> DockerServiceResult<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Display for PermissionDeviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(mode) = self.expected_mode {
+            write!(f, " 权限 {:o} -> {:o}", self.current_mode, mode)?;
+        }
+        if let Some(owner) = self.expected_owner {
+            write!(f, " 属主 {} -> {}", self.current_owner, owner)?;
+        }
+        Ok(())
+    }
+}
+
+/// 解压或恢复后应用工作目录的权限策略，失败时只记录警告
+pub fn apply_permission_policy(work_dir: &Path) {
+    let result = PermissionPolicy::load(work_dir).and_then(|policy| policy.apply(work_dir));
+    match result {
+        Ok(fixed) if fixed.is_empty() => info!("✓ 文件权限符合策略"),
+        Ok(fixed) => info!("🔒 已按权限策略修复 {} 个路径", fixed.len()),
+        Err(e) => warn!("⚠️ 应用权限策略失败: {}", e),
+    }
+}
+
+fn pattern_segments(pattern: &str) -> Vec<&str> {
+    pattern
+        .trim_start_matches("./")
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// 按路径段匹配，`**` 匹配任意层级（包括零层）
+fn glob_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| glob_match(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path_rest)) => segment_match(segment, name) && glob_match(rest, path_rest),
+            None => false,
+        },
+    }
+}
+
+/// 目录 `path` 之下是否可能有路径匹配 `pattern`
+fn prefix_may_match(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (Some((&"**", _)), _) => true,
+        (Some(_), None) => true,
+        (Some((segment, rest)), Some((name, path_rest))) => {
+            segment_match(segment, name) && prefix_may_match(rest, path_rest)
+        }
+        (None, _) => false,
+    }
+}
+
+/// 单个路径段的通配符匹配（`*` 和 `?`）
+fn segment_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segs(path: &str) -> Vec<&str> {
+        pattern_segments(path)
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(&segs("data/mysql"), &segs("data/mysql")));
+        assert!(!glob_match(
+            &segs("data/mysql"),
+            &segs("data/mysql/ibdata1")
+        ));
+        assert!(glob_match(&segs("**/*.sh"), &segs("entrypoint.sh")));
+        assert!(glob_match(
+            &segs("**/*.sh"),
+            &segs("config/docker-entrypoint.sh")
+        ));
+        assert!(!glob_match(&segs("**/*.sh"), &segs("config/run.shx")));
+        assert!(glob_match(
+            &segs("data/redis/**"),
+            &segs("data/redis/dump.rdb")
+        ));
+        assert!(glob_match(&segs("logs/*/?.log"), &segs("logs/agent/a.log")));
+
+        assert!(prefix_may_match(&segs("data/mysql"), &segs("data")));
+        assert!(!prefix_may_match(&segs("data/mysql"), &segs("upload")));
+        assert!(!prefix_may_match(&segs("data/mysql"), &segs("data/mysql")));
+        assert!(prefix_may_match(&segs("**/*.sh"), &segs("app/bin")));
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy = PermissionPolicy::from_yaml(
+            "rules:\n  - path: data/redis/**\n    owner: \"999:1000\"\n  - path: bin/*\n    type: file\n    mode: \"0755\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            policy.rules[0].owner,
+            Some(OwnerSpec {
+                uid: 999,
+                gid: Some(1000)
+            })
+        );
+        assert_eq!(policy.rules[1].kind, EntryKind::File);
+        assert_eq!(policy.rules[1].mode, Some(ModeSpec::Exact(0o755)));
+
+        assert!(PermissionPolicy::from_yaml("rules:\n  - path: data\n").is_err());
+        assert!(
+            PermissionPolicy::from_yaml("rules:\n  - path: data\n    mode: \"u+rw\"\n").is_err()
+        );
+        assert!(PermissionPolicy::from_yaml("rules:\n  - path: data\n    owner: mysql\n").is_err());
+        assert_eq!(PermissionPolicy::builtin().rules.len(), 3);
+    }
+
+    #[test]
+    fn test_expected_applies_rules_in_order() {
+        let policy = PermissionPolicy::from_yaml(
+            "rules:\n  - path: \"**\"\n    mode: \"640\"\n  - path: \"**/*.sh\"\n    type: file\n    mode: \"+x\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            policy.expected(&["run.sh"], false, 0o600),
+            Some((Some(0o751), None))
+        );
+        assert_eq!(
+            policy.expected(&["run.sh"], true, 0o700),
+            Some((Some(0o640), None))
+        );
+        assert_eq!(
+            PermissionPolicy::builtin().expected(&["upload"], true, 0o755),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_and_apply() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let work_dir = temp.path();
+        std::fs::create_dir_all(work_dir.join("data/mysql")).unwrap();
+        std::fs::create_dir_all(work_dir.join("config")).unwrap();
+        std::fs::write(work_dir.join("config/docker-entrypoint.sh"), "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(
+            work_dir.join("config/docker-entrypoint.sh"),
+            std::fs::Permissions::from_mode(0o644),
+        )
+        .unwrap();
+        std::fs::set_permissions(
+            work_dir.join("data/mysql"),
+            std::fs::Permissions::from_mode(0o700),
+        )
+        .unwrap();
+
+        let policy = PermissionPolicy::builtin();
+        let deviations = policy.check(work_dir).unwrap();
+        assert_eq!(deviations.len(), 2);
+
+        let fixed = policy.apply(work_dir).unwrap();
+        assert_eq!(fixed.len(), 2);
+        assert!(policy.check(work_dir).unwrap().is_empty());
+
+        let mode = |path: &str| {
+            std::fs::metadata(work_dir.join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777
+        };
+        assert_eq!(mode("data/mysql"), 0o775);
+        assert_eq!(mode("config/docker-entrypoint.sh"), 0o755);
+    }
+}
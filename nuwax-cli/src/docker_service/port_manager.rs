@@ -11,7 +11,7 @@ use nom::{
     sequence::{delimited, pair},
 };
 use serde_yaml::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::fs;
 use std::net::{SocketAddr, TcpListener};
@@ -53,6 +53,88 @@ pub struct PortConflict {
     pub mapping: String,
 }
 
+/// 用户配置的主机端口覆盖
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortOverride {
+    /// compose 服务名
+    pub service_name: String,
+    /// 容器端口，未指定时覆盖该服务的第一个端口映射
+    pub container_port: Option<u16>,
+    /// 主机端口
+    pub host_port: u16,
+}
+
+impl PortOverride {
+    /// 由配置项解析端口覆盖，键格式为 `服务名` 或 `服务名:容器端口`
+    pub fn parse(key: &str, host_port: u16) -> DockerServiceResult<Self> {
+        if host_port == 0 {
+            return Err(DockerServiceError::Configuration(format!(
+                "端口覆盖 {key} 的主机端口无效: 0"
+            )));
+        }
+        let (service_name, container_port) = match key.trim().split_once(':') {
+            Some((service_name, container_port)) => {
+                let container_port = container_port.trim().parse::<u16>().map_err(|_| {
+                    DockerServiceError::Configuration(format!(
+                        "端口覆盖 {key} 的容器端口无效: {container_port}"
+                    ))
+                })?;
+                (service_name.trim(), Some(container_port))
+            }
+            None => (key.trim(), None),
+        };
+        if service_name.is_empty() {
+            return Err(DockerServiceError::Configuration(format!(
+                "端口覆盖 {key} 缺少服务名"
+            )));
+        }
+
+        Ok(Self {
+            service_name: service_name.to_string(),
+            container_port,
+            host_port,
+        })
+    }
+
+    /// 解析配置中的所有端口覆盖
+    pub fn parse_all(overrides: &BTreeMap<String, u16>) -> DockerServiceResult<Vec<Self>> {
+        overrides
+            .iter()
+            .map(|(key, port)| Self::parse(key, *port))
+            .collect()
+    }
+}
+
+/// 端口覆盖的写入位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortOverrideTarget {
+    /// 主机端口由 .env 变量定义，写入该变量
+    EnvVar(String),
+    /// 主机端口写死在 docker-compose.yml 中，直接修改端口定义
+    Compose,
+}
+
+/// 单个端口覆盖的应用方式
+#[derive(Debug, Clone)]
+pub struct PlannedPortOverride {
+    pub service_name: String,
+    pub container_port: u16,
+    /// 覆盖前的主机端口
+    pub previous_host_port: u16,
+    pub host_port: u16,
+    pub target: PortOverrideTarget,
+}
+
+/// 端口覆盖计划
+#[derive(Debug, Default)]
+pub struct PortOverridePlan {
+    pub overrides: Vec<PlannedPortOverride>,
+    /// 需要写入 .env 的变量
+    pub env_updates: BTreeMap<String, String>,
+    /// 修改后的 docker-compose.yml 内容，无需修改时为 None
+    pub compose_content: Option<String>,
+}
+
 /// 环境变量解析结果
 #[derive(Debug, Clone)]
 enum VarExpansion {
@@ -125,6 +207,92 @@ fn parse_env_string(input: &str) -> IResult<&str, Vec<VarExpansion>> {
     .parse(input)
 }
 
+/// 拆分端口定义为 (IP 前缀, 主机端口, 容器端口及协议) 三部分
+///
+/// 例如 `127.0.0.1:${PORT:-80}:80/tcp` 拆分为 `127.0.0.1:`、`${PORT:-80}`、`:80/tcp`，
+/// 变量和 IPv6 地址中的冒号不作为分隔符
+fn split_port_spec(spec: &str) -> Option<(&str, &str, &str)> {
+    let mut depth = 0usize;
+    let mut separators = Vec::new();
+    for (index, c) in spec.char_indices() {
+        match c {
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth = depth.saturating_sub(1),
+            ':' if depth == 0 => separators.push(index),
+            _ => {}
+        }
+    }
+
+    let container_start = *separators.last()?;
+    let host_start = separators
+        .iter()
+        .rev()
+        .nth(1)
+        .map_or(0, |separator| separator + 1);
+    Some((
+        &spec[..host_start],
+        &spec[host_start..container_start],
+        &spec[container_start..],
+    ))
+}
+
+/// 主机端口完全由单个变量定义时返回变量名
+fn host_port_variable(host: &str) -> Option<String> {
+    match parse_env_string(host) {
+        Ok(("", expansions)) if expansions.len() == 1 => match &expansions[0] {
+            VarExpansion::Variable(var_name) | VarExpansion::VariableWithDefault(var_name, _) => {
+                Some(var_name.clone())
+            }
+            VarExpansion::Text(_) => None,
+        },
+        _ => None,
+    }
+}
+
+/// 在 docker-compose 内容中替换指定服务的端口定义，保留其余内容和注释
+fn replace_port_entry(
+    content: &str,
+    service_name: &str,
+    port_spec: &str,
+    new_spec: &str,
+) -> Option<String> {
+    let mut lines: Vec<String> = content.split_inclusive('\n').map(str::to_string).collect();
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let is_content = |line: &str| {
+        let trimmed = line.trim();
+        !trimmed.is_empty() && !trimmed.starts_with('#')
+    };
+
+    // 服务定义位于顶层 services 下的第一层缩进
+    let services_line = lines
+        .iter()
+        .position(|line| line.trim_end() == "services:")?;
+    let service_indent = lines[services_line + 1..]
+        .iter()
+        .find(|line| is_content(line))
+        .map(|line| indent(line))?;
+    let header = format!("{service_name}:");
+    let start = (services_line + 1..lines.len())
+        .find(|&index| indent(&lines[index]) == service_indent && lines[index].trim() == header)?;
+    let end = (start + 1..lines.len())
+        .find(|&index| is_content(&lines[index]) && indent(&lines[index]) <= service_indent)
+        .unwrap_or(lines.len());
+
+    for line in &mut lines[start + 1..end] {
+        let Some(item) = line.trim().strip_prefix('-') else {
+            continue;
+        };
+        let value = item.split(" #").next().unwrap_or_default().trim();
+        let value = value.trim_matches(|c| c == '"' || c == '\'');
+        if value == port_spec {
+            *line = line.replacen(port_spec, new_spec, 1);
+            return Some(lines.concat());
+        }
+    }
+
+    None
+}
+
 /// 端口管理器 - 负责检测和管理端口冲突
 #[derive(Debug, Clone)]
 pub struct PortManager {
@@ -361,7 +529,14 @@ impl PortManager {
             ))
         })?;
 
-        let yaml: Value = serde_yaml::from_str(&content).map_err(|e| {
+        let port_mappings = self.parse_compose_content(&content)?;
+        info!("解析完成，找到 {} 个端口映射", port_mappings.len());
+        Ok(port_mappings)
+    }
+
+    /// 从docker-compose文件内容中解析端口映射
+    fn parse_compose_content(&self, content: &str) -> DockerServiceResult<Vec<PortMapping>> {
+        let yaml: Value = serde_yaml::from_str(content).map_err(|e| {
             DockerServiceError::Configuration(format!("解析docker-compose文件失败: {e}"))
         })?;
 
@@ -383,7 +558,6 @@ impl PortManager {
             }
         }
 
-        info!("解析完成，找到 {} 个端口映射", port_mappings.len());
         Ok(port_mappings)
     }
 
@@ -734,6 +908,148 @@ impl PortManager {
             info!("💡 提示: 已跳过相关服务占用的端口");
         }
     }
+
+    /// 根据用户配置的端口覆盖生成 .env / docker-compose.yml 修改计划，并检查主机端口冲突
+    ///
+    /// 主机端口由变量定义时写入 .env，写死在 compose 中时直接修改端口定义；
+    /// 覆盖后任意两个端口映射使用相同的主机端口和协议时返回错误
+    pub fn plan_port_overrides(
+        &mut self,
+        compose_file_path: &Path,
+        env_file_path: &Path,
+        overrides: &[PortOverride],
+    ) -> DockerServiceResult<PortOverridePlan> {
+        let content = fs::read_to_string(compose_file_path).map_err(|e| {
+            DockerServiceError::Configuration(format!(
+                "无法读取docker-compose文件 {}: {}",
+                compose_file_path.display(),
+                e
+            ))
+        })?;
+        self.load_env_file(env_file_path)?;
+        self.plan_port_overrides_for_content(&content, overrides)
+    }
+
+    fn plan_port_overrides_for_content(
+        &mut self,
+        content: &str,
+        overrides: &[PortOverride],
+    ) -> DockerServiceResult<PortOverridePlan> {
+        let yaml: Value = serde_yaml::from_str(content).map_err(|e| {
+            DockerServiceError::Configuration(format!("解析docker-compose文件失败: {e}"))
+        })?;
+        let services = yaml.get("services").and_then(|s| s.as_mapping());
+
+        let mut plan = PortOverridePlan::default();
+        let mut new_content = content.to_string();
+        let mut targeted = HashSet::new();
+
+        for port_override in overrides {
+            let service_name = port_override.service_name.as_str();
+            let ports = services
+                .and_then(|services| services.get(service_name))
+                .ok_or_else(|| {
+                    DockerServiceError::Configuration(format!(
+                        "端口覆盖无效: docker-compose.yml 中不存在服务 {service_name}"
+                    ))
+                })?
+                .get("ports")
+                .and_then(|ports| ports.as_sequence())
+                .ok_or_else(|| {
+                    DockerServiceError::Configuration(format!(
+                        "端口覆盖无效: 服务 {service_name} 没有端口映射"
+                    ))
+                })?;
+
+            let mut selected = None;
+            for port_def in ports {
+                let Value::String(port_spec) = port_def else {
+                    continue;
+                };
+                let Some(mapping) = self.parse_port_definition(port_def, service_name)? else {
+                    continue;
+                };
+                if port_override
+                    .container_port
+                    .is_none_or(|port| port == mapping.container_port)
+                {
+                    selected = Some((port_spec.trim(), mapping));
+                    break;
+                }
+            }
+            let (port_spec, mapping) = selected.ok_or_else(|| {
+                DockerServiceError::Configuration(match port_override.container_port {
+                    Some(port) => {
+                        format!("端口覆盖无效: 服务 {service_name} 没有容器端口 {port} 的映射")
+                    }
+                    None => format!("端口覆盖无效: 服务 {service_name} 没有主机端口映射"),
+                })
+            })?;
+
+            if !targeted.insert((service_name, mapping.container_port)) {
+                return Err(DockerServiceError::Configuration(format!(
+                    "端口覆盖重复: 服务 {service_name} 的容器端口 {} 被配置了多次",
+                    mapping.container_port
+                )));
+            }
+
+            let (prefix, host, rest) = split_port_spec(port_spec).ok_or_else(|| {
+                DockerServiceError::Configuration(format!(
+                    "无法解析端口定义: {port_spec} (服务: {service_name})"
+                ))
+            })?;
+            let target = match host_port_variable(host) {
+                Some(var_name) => {
+                    plan.env_updates
+                        .insert(var_name.clone(), port_override.host_port.to_string());
+                    PortOverrideTarget::EnvVar(var_name)
+                }
+                None => {
+                    let new_spec = format!("{prefix}{}{rest}", port_override.host_port);
+                    new_content =
+                        replace_port_entry(&new_content, service_name, port_spec, &new_spec)
+                            .ok_or_else(|| {
+                                DockerServiceError::Configuration(format!(
+                                    "无法在docker-compose.yml中定位端口定义: {port_spec} (服务: {service_name})"
+                                ))
+                            })?;
+                    PortOverrideTarget::Compose
+                }
+            };
+
+            plan.overrides.push(PlannedPortOverride {
+                service_name: service_name.to_string(),
+                container_port: mapping.container_port,
+                previous_host_port: mapping.host_port,
+                host_port: port_override.host_port,
+                target,
+            });
+        }
+
+        // 使用覆盖后的配置重新解析端口映射，检查主机端口冲突
+        for (key, value) in &plan.env_updates {
+            self.env_vars.insert(key.clone(), value.clone());
+        }
+        // 只报告与覆盖端口相关的冲突，服务包自身的端口定义不在此处校验
+        let override_ports: HashSet<u16> = overrides.iter().map(|o| o.host_port).collect();
+        let mut used_ports: HashMap<(u16, String), String> = HashMap::new();
+        for mapping in self.parse_compose_content(&new_content)? {
+            let key = (mapping.host_port, mapping.protocol.clone());
+            if let Some(other) = used_ports.insert(key, mapping.service_name.clone())
+                && override_ports.contains(&mapping.host_port)
+            {
+                return Err(DockerServiceError::Configuration(format!(
+                    "端口覆盖冲突: 主机端口 {}/{} 同时被服务 {} 和 {} 使用",
+                    mapping.host_port, mapping.protocol, other, mapping.service_name
+                )));
+            }
+        }
+
+        if new_content != content {
+            plan.compose_content = Some(new_content);
+        }
+        Ok(plan)
+    }
 }
 
 impl Default for PortManager {
@@ -881,4 +1197,104 @@ mod tests {
         let result = port_manager.expand_env_vars("${UNDEFINED_VAR}");
         assert_eq!(result, "${UNDEFINED_VAR}");
     }
+
+    const OVERRIDE_COMPOSE: &str = r#"services:
+  frontend:
+    image: nginx
+    ports:
+      - "${FRONTEND_HOST_PORT:-80}:80"
+    depends_on:
+      mysql:
+        condition: service_healthy
+  mysql:
+    image: mysql
+    ports:
+      - "13306:3306" # MySQL
+  minio:
+    image: minio
+    ports:
+      - "9000:9000"
+      - "127.0.0.1:9001:9001"
+"#;
+
+    fn overrides(entries: &[(&str, u16)]) -> Vec<PortOverride> {
+        entries
+            .iter()
+            .map(|(key, port)| PortOverride::parse(key, *port).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_split_port_spec_and_parse_override() {
+        assert_eq!(
+            split_port_spec("127.0.0.1:${PORT:-80}:80/tcp"),
+            Some(("127.0.0.1:", "${PORT:-80}", ":80/tcp"))
+        );
+        assert_eq!(split_port_spec("8080:80"), Some(("", "8080", ":80")));
+        assert_eq!(split_port_spec("80"), None);
+        assert_eq!(host_port_variable("${PORT:-80}"), Some("PORT".to_string()));
+        assert_eq!(host_port_variable("8080"), None);
+
+        let port_override = PortOverride::parse("minio:9001", 19001).unwrap();
+        assert_eq!(port_override.service_name, "minio");
+        assert_eq!(port_override.container_port, Some(9001));
+        assert!(PortOverride::parse("minio:console", 19001).is_err());
+        assert!(PortOverride::parse("mysql", 0).is_err());
+    }
+
+    #[test]
+    fn test_plan_port_overrides() {
+        let mut port_manager = PortManager::new();
+        let plan = port_manager
+            .plan_port_overrides_for_content(
+                OVERRIDE_COMPOSE,
+                &overrides(&[("frontend", 8443), ("mysql", 23306), ("minio:9001", 19001)]),
+            )
+            .unwrap();
+
+        assert_eq!(
+            plan.env_updates
+                .get("FRONTEND_HOST_PORT")
+                .map(String::as_str),
+            Some("8443")
+        );
+        let content = plan.compose_content.unwrap();
+        assert!(content.contains(r#"- "23306:3306" # MySQL"#));
+        assert!(content.contains(r#"- "127.0.0.1:19001:9001""#));
+        assert!(content.contains(r#"- "9000:9000""#));
+        // depends_on 中的同名键不应被当作服务定义
+        assert!(content.contains("      mysql:\n        condition: service_healthy"));
+
+        let mysql = &plan.overrides[1];
+        assert_eq!(mysql.previous_host_port, 13306);
+        assert_eq!(mysql.target, PortOverrideTarget::Compose);
+        assert_eq!(
+            plan.overrides[0].target,
+            PortOverrideTarget::EnvVar("FRONTEND_HOST_PORT".to_string())
+        );
+    }
+
+    #[test]
+    fn test_plan_port_overrides_rejects_invalid() {
+        let mut port_manager = PortManager::new();
+        let plan = |entries: &[(&str, u16)]| {
+            PortManager::new()
+                .plan_port_overrides_for_content(OVERRIDE_COMPOSE, &overrides(entries))
+        };
+
+        let err = plan(&[("mysql", 9000)]).unwrap_err().to_string();
+        assert!(err.contains("端口覆盖冲突"), "{err}");
+        assert!(plan(&[("redis", 16379)]).is_err());
+        assert!(plan(&[("minio:1234", 11234)]).is_err());
+        assert!(plan(&[("minio", 19000), ("minio:9000", 29000)]).is_err());
+
+        // 没有端口覆盖时不修改 docker-compose.yml
+        assert!(
+            port_manager
+                .plan_port_overrides_for_content(OVERRIDE_COMPOSE, &[])
+                .unwrap()
+                .compose_content
+                .is_none()
+        );
+    }
 }
@@ -0,0 +1,253 @@
+//! 服务停止检查
+//!
+//! 冷备份、升级等操作要求持续运行的服务（restart 策略为 always、unless-stopped、on-failure）
+//! 处于停止状态，一次性任务（restart: no）和失败的容器不影响操作。[`ServiceGate`] 按 restart 策略
+//! 对容器分组，统一输出检查结果，供各命令判断能否继续。
+
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use client_core::config::AppConfig;
+use client_core::container::DockerManager;
+use client_core::container::orphans::OrphanContainer;
+use tracing::{error, info, warn};
+
+use super::DockerService;
+use super::health_check::{ContainerInfo, HealthReport};
+
+/// 按 restart 策略分组的容器状态
+#[derive(Debug, Clone, Default)]
+pub struct ServiceGateReport {
+    /// 仍在运行的持续服务，操作前必须停止
+    pub persistent_running: Vec<ContainerInfo>,
+    /// 运行中的一次性任务，可以忽略
+    pub oneshot_running: Vec<ContainerInfo>,
+    /// 已完成的容器，可以忽略
+    pub completed: Vec<ContainerInfo>,
+    /// 失败的容器，不影响操作
+    pub failed: Vec<ContainerInfo>,
+    /// 运行中的孤立容器（服务已不在 compose 文件中），不影响操作，但可能仍在写入数据
+    pub orphans_running: Vec<OrphanContainer>,
+}
+
+impl ServiceGateReport {
+    /// 从健康检查报告分组
+    pub fn from_health_report(report: &HealthReport) -> Self {
+        let (persistent_running, oneshot_running) = report
+            .get_running_containers()
+            .into_iter()
+            .cloned()
+            .partition(|c| c.is_persistent_service());
+        Self {
+            persistent_running,
+            oneshot_running,
+            completed: report
+                .get_completed_containers()
+                .into_iter()
+                .cloned()
+                .collect(),
+            failed: report
+                .get_failed_containers()
+                .into_iter()
+                .cloned()
+                .collect(),
+            orphans_running: report
+                .orphans
+                .iter()
+                .filter(|orphan| orphan.is_running())
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// 持续服务是否均已停止
+    pub fn is_clear(&self) -> bool {
+        self.persistent_running.is_empty()
+    }
+
+    /// 是否可以继续操作：持续服务均已停止，或指定了 `force`
+    pub fn permits(&self, purpose: &str, force: bool) -> bool {
+        if self.is_clear() {
+            return true;
+        }
+        if force {
+            warn!(
+                "⚠️  已指定 --force，在 {} 个持续服务运行时继续{}，数据可能不一致",
+                self.persistent_running.len(),
+                purpose
+            );
+        }
+        force
+    }
+
+    /// 输出检查结果，`purpose` 为要执行的操作（如“冷备份”）
+    pub fn log_summary(&self, purpose: &str) {
+        if !self.is_clear() {
+            warn!("⚠️  持续服务仍在运行中！");
+            error!("❌ {}要求持续运行的服务必须处于停止状态", purpose);
+
+            info!(
+                "📝 发现 {} 个持续运行的服务:",
+                self.persistent_running.len()
+            );
+            display_containers(&self.persistent_running);
+
+            if !self.oneshot_running.is_empty() {
+                info!(
+                    "🔄 忽略 {} 个运行中的一次性任务:",
+                    self.oneshot_running.len()
+                );
+                display_containers(&self.oneshot_running);
+            }
+
+            info!("💡 请先停止持续运行的服务后再进行{}", purpose);
+        } else {
+            info!("✅ 所有持续服务已停止，可以进行{}", purpose);
+        }
+
+        let (oneshot_completed, other_completed): (Vec<_>, Vec<_>) =
+            self.completed.iter().cloned().partition(|c| c.is_oneshot());
+        if !oneshot_completed.is_empty() {
+            info!(
+                "🔄 忽略 {} 个已完成的一次性任务容器:",
+                oneshot_completed.len()
+            );
+            display_containers(&oneshot_completed);
+        }
+        if !other_completed.is_empty() {
+            info!("📝 发现 {} 个其他已完成容器:", other_completed.len());
+            display_containers(&other_completed);
+        }
+
+        if !self.failed.is_empty() {
+            warn!(
+                "⚠️  发现 {} 个失败的容器（不影响{}）:",
+                self.failed.len(),
+                purpose
+            );
+            display_containers(&self.failed);
+        }
+
+        if !self.orphans_running.is_empty() {
+            warn!(
+                "⚠️  发现 {} 个运行中的孤立容器（服务已不在compose文件中，停止服务时不会被停止）:",
+                self.orphans_running.len()
+            );
+            for orphan in &self.orphans_running {
+                warn!("   - {} (服务: {})", orphan.name, orphan.service);
+            }
+            warn!("💡 使用 'nuwax-cli docker-service cleanup-orphans' 删除孤立容器");
+        }
+    }
+}
+
+/// 检查持续服务是否已停止
+pub struct ServiceGate {
+    config: Arc<AppConfig>,
+    docker_manager: Arc<DockerManager>,
+}
+
+impl ServiceGate {
+    pub fn new(config: Arc<AppConfig>, docker_manager: Arc<DockerManager>) -> Self {
+        Self {
+            config,
+            docker_manager,
+        }
+    }
+
+    /// 检查容器状态并按 restart 策略分组
+    pub async fn inspect(&self) -> Result<ServiceGateReport> {
+        let docker_service = DockerService::new(self.config.clone(), self.docker_manager.clone())?;
+        let report = docker_service
+            .health_check()
+            .await
+            .map_err(|e| anyhow!("检查Docker服务状态失败: {}", e))?;
+
+        info!("📊 服务状态: {}", report.get_status_summary());
+        Ok(ServiceGateReport::from_health_report(&report))
+    }
+
+    /// 检查并输出结果，持续服务仍在运行且未指定 `force` 时返回错误
+    pub async fn ensure_stopped(&self, purpose: &str, force: bool) -> Result<ServiceGateReport> {
+        let report = self.inspect().await?;
+        report.log_summary(purpose);
+        if !report.permits(purpose, force) {
+            error!("有持续运行的服务，无法进行{}", purpose);
+            return Err(anyhow!("有持续运行的服务，无法进行{}", purpose));
+        }
+        Ok(report)
+    }
+}
+
+fn display_containers(containers: &[ContainerInfo]) {
+    for container in containers {
+        info!(
+            "   - {} (状态: {}, restart: {})",
+            container.name,
+            container.status.display_name(),
+            container.get_restart_display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker_service::health_check::{ContainerStatus, RestartPolicy};
+
+    fn container(
+        name: &str,
+        status: ContainerStatus,
+        restart: Option<RestartPolicy>,
+    ) -> ContainerInfo {
+        ContainerInfo {
+            name: name.to_string(),
+            status,
+            image: String::new(),
+            ports: Vec::new(),
+            uptime: None,
+            health: None,
+            is_oneshot: false,
+            restart,
+        }
+    }
+
+    #[test]
+    fn test_gate_groups_by_restart_policy() {
+        let mut report = HealthReport::default();
+        report.add_container(container(
+            "backend",
+            ContainerStatus::Running,
+            Some(RestartPolicy::Always),
+        ));
+        report.add_container(container(
+            "init",
+            ContainerStatus::Running,
+            Some(RestartPolicy::No),
+        ));
+        report.add_container(container(
+            "migrate",
+            ContainerStatus::Completed,
+            Some(RestartPolicy::No),
+        ));
+        report.add_container(container("worker", ContainerStatus::Stopped, None));
+
+        let gate = ServiceGateReport::from_health_report(&report);
+        let names = |containers: &[ContainerInfo]| -> Vec<String> {
+            containers.iter().map(|c| c.name.clone()).collect()
+        };
+        assert_eq!(names(&gate.persistent_running), ["backend"]);
+        assert_eq!(names(&gate.oneshot_running), ["init"]);
+        assert_eq!(names(&gate.completed), ["migrate"]);
+        assert_eq!(names(&gate.failed), ["worker"]);
+
+        assert!(!gate.is_clear());
+        assert!(!gate.permits("冷备份", false));
+        assert!(gate.permits("冷备份", true));
+
+        // 未知 restart 策略的运行中容器按持续服务处理
+        let mut unknown = HealthReport::default();
+        unknown.add_container(container("redis", ContainerStatus::Running, None));
+        assert!(!ServiceGateReport::from_health_report(&unknown).is_clear());
+    }
+}
@@ -0,0 +1,198 @@
+//! # 补丁升级影响的服务
+//!
+//! 补丁只改动了部分服务挂载的文件时，不需要停止整个服务栈。按 docker-compose.yml 中各服务的
+//! 绑定挂载目录把补丁改动的路径归属到服务，再加上直接或间接依赖这些服务的服务，升级时只停止并
+//! 重建这些服务，其余服务（通常包括数据库）保持运行。
+//!
+//! 以下情况仍然重启全部服务：
+//! - 有改动的路径不在任何服务的挂载目录中（如 docker-compose.yml、.env、数据目录迁移脚本）
+//! - 受影响的服务涵盖了全部服务
+
+use super::compose_parser::DockerComposeParser;
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+/// 补丁升级需要重启的服务范围
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceImpact {
+    /// 重启全部服务
+    All {
+        /// 无法只重启部分服务的原因
+        reason: String,
+    },
+    /// 只重启部分服务
+    Partial {
+        /// 需要停止并重建的服务
+        restart: Vec<String>,
+        /// 保持运行的服务
+        untouched: Vec<String>,
+    },
+}
+
+impl ServiceImpact {
+    /// 只重启部分服务时返回需要重启的服务
+    pub fn partial_services(&self) -> Option<&[String]> {
+        match self {
+            ServiceImpact::All { .. } => None,
+            ServiceImpact::Partial { restart, .. } => Some(restart),
+        }
+    }
+}
+
+/// 按补丁改动的路径（相对 docker 目录）计算需要重启的服务
+pub fn analyze_service_impact(
+    parser: &DockerComposeParser,
+    changed_files: &[PathBuf],
+) -> ServiceImpact {
+    let mounts: Vec<(String, PathBuf)> = parser
+        .service_bind_mounts()
+        .into_iter()
+        .flat_map(|(service, mounts)| {
+            mounts
+                .into_iter()
+                .filter_map(|mount| relative_mount_path(&mount))
+                .map(move |mount| (service.clone(), mount))
+        })
+        .collect();
+
+    let mut affected = BTreeSet::new();
+    for changed in changed_files {
+        let changed = normalize_relative(changed);
+        // 改动的文件位于挂载目录内，或改动的目录包含挂载目录
+        let owners: Vec<&String> = mounts
+            .iter()
+            .filter(|(_, mount)| changed.starts_with(mount) || mount.starts_with(&changed))
+            .map(|(service, _)| service)
+            .collect();
+        if owners.is_empty() {
+            return ServiceImpact::All {
+                reason: format!("{} 不属于任何服务的挂载目录", changed.display()),
+            };
+        }
+        affected.extend(owners.into_iter().cloned());
+    }
+
+    // 依赖受影响服务的服务也需要重启
+    let dependencies = parser.service_dependencies();
+    loop {
+        let dependents: Vec<String> = dependencies
+            .iter()
+            .filter(|(service, deps)| {
+                !affected.contains(*service) && deps.iter().any(|dep| affected.contains(dep))
+            })
+            .map(|(service, _)| service.clone())
+            .collect();
+        if dependents.is_empty() {
+            break;
+        }
+        affected.extend(dependents);
+    }
+
+    let untouched: Vec<String> = dependencies
+        .keys()
+        .filter(|service| !affected.contains(*service))
+        .cloned()
+        .collect();
+    if untouched.is_empty() {
+        return ServiceImpact::All {
+            reason: "补丁影响了全部服务".to_string(),
+        };
+    }
+    ServiceImpact::Partial {
+        restart: affected.into_iter().collect(),
+        untouched,
+    }
+}
+
+/// docker 目录内的挂载路径，绝对路径和指向 docker 目录之外的路径返回 None
+fn relative_mount_path(mount: &str) -> Option<PathBuf> {
+    let path = Path::new(mount);
+    if path.is_absolute() || mount.starts_with("../") {
+        return None;
+    }
+    let path = normalize_relative(path);
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+/// 去掉 `./` 和末尾的 `/`，便于按路径组件比较
+fn normalize_relative(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPOSE: &str = r#"
+services:
+  mysql:
+    image: mysql:8
+    volumes:
+      - ./data/mysql:/var/lib/mysql
+      - ./config/init_mysql.sql:/docker-entrypoint-initdb.d/init.sql
+  backend:
+    image: backend
+    depends_on: [mysql]
+    volumes:
+      - ./app/backend:/app
+  frontend:
+    image: frontend
+    depends_on:
+      backend:
+        condition: service_started
+    volumes:
+      - type: bind
+        source: ./app/frontend
+        target: /usr/share/nginx/html
+  worker:
+    image: worker
+    volumes:
+      - ./app/worker/:/app
+      - /var/run/docker.sock:/var/run/docker.sock
+"#;
+
+    fn impact(changed: &[&str]) -> ServiceImpact {
+        let parser = DockerComposeParser::from_yaml_str(COMPOSE).unwrap();
+        let changed: Vec<PathBuf> = changed.iter().map(PathBuf::from).collect();
+        analyze_service_impact(&parser, &changed)
+    }
+
+    #[test]
+    fn test_analyze_service_impact() {
+        assert_eq!(
+            impact(&["app/frontend/index.html", "./app/frontend/assets"]),
+            ServiceImpact::Partial {
+                restart: vec!["frontend".to_string()],
+                untouched: vec![
+                    "backend".to_string(),
+                    "mysql".to_string(),
+                    "worker".to_string()
+                ],
+            }
+        );
+
+        // 依赖受影响服务的服务一并重启
+        let ServiceImpact::Partial { restart, untouched } = impact(&["app/backend/app.jar"]) else {
+            panic!("expected partial impact");
+        };
+        assert_eq!(restart, ["backend", "frontend"]);
+        assert_eq!(untouched, ["mysql", "worker"]);
+
+        // 改动的目录包含多个服务的挂载目录
+        let ServiceImpact::Partial { restart, .. } = impact(&["app/worker", "app/backend"]) else {
+            panic!("expected partial impact");
+        };
+        assert_eq!(restart, ["backend", "frontend", "worker"]);
+
+        assert!(matches!(
+            impact(&["app/frontend/index.html", "docker-compose.yml"]),
+            ServiceImpact::All { reason } if reason.contains("docker-compose.yml")
+        ));
+        assert!(matches!(
+            impact(&["config/init_mysql.sql", "app/worker/run.sh"]),
+            ServiceImpact::All { .. }
+        ));
+    }
+}
@@ -1,13 +1,248 @@
 // Docker 服务生命周期管理模块
 // 用于处理服务启动、停止、重启等生命周期操作
 
-/// 占位符模块 - 后续扩展服务管理功能
-#[allow(dead_code)]
-pub struct ServiceManager;
+use crate::docker_service::compose_parser::DockerComposeParser;
+use anyhow::Result;
+use client_core::config::ServiceStopConfig;
+use client_core::constants::timeout;
+use client_core::container::{ContainerExitState, DockerManager};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use tokio::time::{Duration, Instant, sleep};
+use tracing::{debug, info, warn};
+
+/// SIGKILL 导致的容器退出码
+const SIGKILL_EXIT_CODE: i32 = 137;
+
+/// 单个服务的停止结果
+#[derive(Debug, Clone)]
+pub struct ServiceStopResult {
+    pub service: String,
+    pub containers: Vec<ContainerExitState>,
+    /// 等待超时后仍有容器在运行
+    pub timed_out: bool,
+}
+
+impl ServiceStopResult {
+    /// 服务的所有容器是否都已正常退出
+    pub fn is_clean(&self) -> bool {
+        !self.timed_out
+            && self
+                .containers
+                .iter()
+                .all(ContainerExitState::exited_cleanly)
+    }
+}
+
+/// 服务生命周期管理器：按依赖关系有序停止服务
+pub struct ServiceManager {
+    docker_manager: Arc<DockerManager>,
+    stop_config: ServiceStopConfig,
+}
 
 impl ServiceManager {
-    #[allow(dead_code)]
-    pub fn new() -> Self {
-        Self
+    pub fn new(docker_manager: Arc<DockerManager>, stop_config: ServiceStopConfig) -> Self {
+        Self {
+            docker_manager,
+            stop_config,
+        }
+    }
+
+    /// 根据 docker-compose 的 depends_on 和配置计算服务停止顺序
+    pub fn stop_order(&self) -> Result<Vec<String>> {
+        let compose_file = self.docker_manager.get_compose_file().to_path_buf();
+        let parser = DockerComposeParser::from_file(&compose_file)
+            .map_err(|e| anyhow::anyhow!("解析 docker-compose 文件失败: {e}"))?;
+        Ok(resolve_stop_order(
+            &parser.service_dependencies(),
+            &self.stop_config.order,
+        ))
+    }
+
+    /// 按停止顺序逐个停止正在运行的服务，每个服务的容器退出后再停止下一个
+    pub async fn stop_services_gracefully(&self) -> Result<Vec<ServiceStopResult>> {
+        self.stop_in_order(None).await
+    }
+
+    /// 按停止顺序只停止指定的服务，其余服务保持运行
+    pub async fn stop_selected_services(
+        &self,
+        services: &[String],
+    ) -> Result<Vec<ServiceStopResult>> {
+        self.stop_in_order(Some(services)).await
+    }
+
+    /// 按依赖顺序重建指定的服务（被依赖的服务先启动），不启动也不重建其他服务
+    pub async fn recreate_selected_services(&self, services: &[String]) -> Result<()> {
+        let mut order = self.stop_order()?;
+        order.reverse();
+        for service in order.iter().filter(|service| services.contains(service)) {
+            info!("▶️ 重建服务 {}...", service);
+            self.docker_manager.recreate_service(service).await?;
+        }
+        Ok(())
+    }
+
+    async fn stop_in_order(&self, only: Option<&[String]>) -> Result<Vec<ServiceStopResult>> {
+        self.docker_manager.check_prerequisites().await?;
+
+        let mut order = self.stop_order()?;
+        if let Some(only) = only {
+            order.retain(|service| only.contains(service));
+        }
+        info!("🛑 服务停止顺序: {}", order.join(" -> "));
+
+        let mut results = Vec::new();
+        for service in order {
+            let before = self
+                .docker_manager
+                .get_service_exit_states(&service)
+                .await?;
+            if !before.iter().any(ContainerExitState::is_running) {
+                debug!("服务 {} 未运行，跳过", service);
+                continue;
+            }
+
+            let grace_period = self.stop_config.grace_period_for(&service);
+            info!("🛑 停止服务 {} (宽限期 {} 秒)...", service, grace_period);
+            self.docker_manager
+                .stop_service_with_timeout(&service, grace_period)
+                .await?;
+
+            let result = self
+                .wait_for_exit(&service, grace_period + timeout::STOP_EXIT_WAIT_MARGIN)
+                .await?;
+            report_stop_result(&result);
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    /// 等待服务的所有容器退出
+    async fn wait_for_exit(&self, service: &str, timeout_secs: u64) -> Result<ServiceStopResult> {
+        let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+        loop {
+            let containers = self.docker_manager.get_service_exit_states(service).await?;
+            let running = containers.iter().any(ContainerExitState::is_running);
+            if !running || Instant::now() >= deadline {
+                return Ok(ServiceStopResult {
+                    service: service.to_string(),
+                    containers,
+                    timed_out: running,
+                });
+            }
+            sleep(Duration::from_secs(timeout::SERVICE_CHECK_INTERVAL)).await;
+        }
+    }
+}
+
+fn report_stop_result(result: &ServiceStopResult) {
+    if result.is_clean() {
+        info!("✅ 服务 {} 已正常退出", result.service);
+        return;
+    }
+    if result.timed_out {
+        warn!("⚠️ 等待服务 {} 退出超时", result.service);
+    }
+    for container in result
+        .containers
+        .iter()
+        .filter(|container| !container.exited_cleanly())
+    {
+        if container.exit_code == SIGKILL_EXIT_CODE {
+            warn!(
+                "⚠️ 容器 {} 被强制终止，可能未完成数据落盘，可在 [docker.stop.grace_periods] 中为服务 {} 增大宽限期",
+                container.name, result.service
+            );
+        } else {
+            warn!(
+                "⚠️ 容器 {} 未正常退出 (状态: {}, 退出码: {})",
+                container.name, container.status, container.exit_code
+            );
+        }
+    }
+}
+
+/// 计算服务停止顺序
+///
+/// 先按配置的顺序停止，其余服务在所有依赖它的服务停止后才停止；存在循环依赖时按名称顺序停止剩余服务
+pub fn resolve_stop_order(
+    dependencies: &BTreeMap<String, Vec<String>>,
+    configured: &[String],
+) -> Vec<String> {
+    let mut order: Vec<String> = Vec::new();
+    for service in configured {
+        if dependencies.contains_key(service) && !order.contains(service) {
+            order.push(service.clone());
+        }
+    }
+
+    let stopped: HashSet<&String> = order.iter().collect();
+    let mut remaining: Vec<&String> = dependencies
+        .keys()
+        .filter(|service| !stopped.contains(service))
+        .collect();
+    while !remaining.is_empty() {
+        let ready: Vec<&String> = remaining
+            .iter()
+            .filter(|service| {
+                !remaining
+                    .iter()
+                    .any(|other| other != *service && dependencies[*other].contains(**service))
+            })
+            .copied()
+            .collect();
+        let batch = if ready.is_empty() {
+            remaining.clone()
+        } else {
+            ready
+        };
+        remaining.retain(|service| !batch.contains(service));
+        order.extend(batch.into_iter().cloned());
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependencies(entries: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        entries
+            .iter()
+            .map(|(service, deps)| {
+                (
+                    service.to_string(),
+                    deps.iter().map(|dep| dep.to_string()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_stop_order() {
+        let deps = dependencies(&[
+            ("backend", &["mysql", "redis"]),
+            ("frontend", &["backend"]),
+            ("mysql", &[]),
+            ("redis", &[]),
+        ]);
+        assert_eq!(
+            resolve_stop_order(&deps, &[]),
+            vec!["frontend", "backend", "mysql", "redis"]
+        );
+
+        // 配置的服务优先停止，不存在的服务被忽略
+        let configured = vec!["redis".to_string(), "unknown".to_string()];
+        assert_eq!(
+            resolve_stop_order(&deps, &configured),
+            vec!["redis", "frontend", "backend", "mysql"]
+        );
+
+        // 循环依赖不会死循环
+        let cyclic = dependencies(&[("a", &["b"]), ("b", &["a"]), ("c", &["a"])]);
+        assert_eq!(resolve_stop_order(&cyclic, &[]), vec!["c", "a", "b"]);
     }
 }
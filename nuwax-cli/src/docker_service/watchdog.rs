@@ -0,0 +1,189 @@
+//! 服务看门狗
+//!
+//! 按健康检查结果跟踪常驻服务的连续失败次数，达到阈值后重启服务；
+//! 同一服务多次重启之间按指数退避，服务恢复健康后重置计数。
+
+use super::health_check::ContainerInfo;
+use bollard::models::HealthStatusEnum;
+use client_core::config::WatchdogConfig;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 看门狗对一次观测结果的处理
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchdogAction {
+    /// 服务健康
+    Healthy,
+    /// 服务恢复健康（此前曾失败或被重启过）
+    Recovered,
+    /// 服务不健康，但尚未达到重启阈值
+    Failing { failures: u32 },
+    /// 需要重启服务，`attempt` 为连续重启次数（从 1 开始）
+    Restart { failures: u32, attempt: u32 },
+    /// 已达到重启阈值，但仍在退避期内
+    BackingOff { failures: u32, remaining: Duration },
+}
+
+#[derive(Debug, Default)]
+struct ServiceState {
+    failures: u32,
+    restarts: u32,
+    next_restart_at: Option<Instant>,
+}
+
+/// 看门狗状态机
+#[derive(Debug)]
+pub struct ServiceWatchdog {
+    failure_threshold: u32,
+    restart_backoff: Duration,
+    max_restart_backoff: Duration,
+    states: HashMap<String, ServiceState>,
+}
+
+impl ServiceWatchdog {
+    pub fn new(config: &WatchdogConfig) -> Self {
+        Self {
+            failure_threshold: config.failure_threshold.max(1),
+            restart_backoff: Duration::from_secs(config.restart_backoff_secs),
+            max_restart_backoff: Duration::from_secs(
+                config
+                    .max_restart_backoff_secs
+                    .max(config.restart_backoff_secs),
+            ),
+            states: HashMap::new(),
+        }
+    }
+
+    /// 记录一次观测结果并返回需要执行的操作
+    ///
+    /// 返回 `Restart` 时视为已执行重启，下一次重启需等待退避时间
+    pub fn observe(&mut self, service: &str, healthy: bool, now: Instant) -> WatchdogAction {
+        let state = self.states.entry(service.to_string()).or_default();
+
+        if healthy {
+            let recovered = state.failures > 0 || state.restarts > 0;
+            *state = ServiceState::default();
+            return if recovered {
+                WatchdogAction::Recovered
+            } else {
+                WatchdogAction::Healthy
+            };
+        }
+
+        state.failures += 1;
+        let failures = state.failures;
+        if failures < self.failure_threshold {
+            return WatchdogAction::Failing { failures };
+        }
+
+        if let Some(next_restart_at) = state.next_restart_at {
+            if now < next_restart_at {
+                return WatchdogAction::BackingOff {
+                    failures,
+                    remaining: next_restart_at - now,
+                };
+            }
+        }
+
+        state.restarts += 1;
+        let attempt = state.restarts;
+        let backoff = self
+            .restart_backoff
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(self.max_restart_backoff);
+        state.next_restart_at = Some(now + backoff);
+
+        WatchdogAction::Restart { failures, attempt }
+    }
+}
+
+/// 判断容器是否健康：必须在运行，且 Docker 健康检查没有报告不健康
+pub fn is_container_healthy(container: &ContainerInfo) -> bool {
+    container.status.is_running() && container.health != Some(HealthStatusEnum::UNHEALTHY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> WatchdogConfig {
+        WatchdogConfig {
+            failure_threshold: 2,
+            restart_backoff_secs: 10,
+            max_restart_backoff_secs: 25,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_restart_after_threshold_with_backoff() {
+        let mut watchdog = ServiceWatchdog::new(&config());
+        let start = Instant::now();
+
+        assert_eq!(
+            watchdog.observe("backend", false, start),
+            WatchdogAction::Failing { failures: 1 }
+        );
+        assert_eq!(
+            watchdog.observe("backend", false, start),
+            WatchdogAction::Restart {
+                failures: 2,
+                attempt: 1
+            }
+        );
+        assert_eq!(
+            watchdog.observe("backend", false, start + Duration::from_secs(4)),
+            WatchdogAction::BackingOff {
+                failures: 3,
+                remaining: Duration::from_secs(6)
+            }
+        );
+        assert_eq!(
+            watchdog.observe("backend", false, start + Duration::from_secs(10)),
+            WatchdogAction::Restart {
+                failures: 4,
+                attempt: 2
+            }
+        );
+        // 第二次重启后退避翻倍为 20 秒
+        assert!(matches!(
+            watchdog.observe("backend", false, start + Duration::from_secs(29)),
+            WatchdogAction::BackingOff { .. }
+        ));
+        assert!(matches!(
+            watchdog.observe("backend", false, start + Duration::from_secs(30)),
+            WatchdogAction::Restart { attempt: 3, .. }
+        ));
+        // 第三次重启后退避被限制为最大 25 秒
+        assert!(matches!(
+            watchdog.observe("backend", false, start + Duration::from_secs(55)),
+            WatchdogAction::Restart { attempt: 4, .. }
+        ));
+    }
+
+    #[test]
+    fn test_recovery_resets_state() {
+        let mut watchdog = ServiceWatchdog::new(&config());
+        let start = Instant::now();
+
+        assert_eq!(
+            watchdog.observe("frontend", true, start),
+            WatchdogAction::Healthy
+        );
+        watchdog.observe("frontend", false, start);
+        watchdog.observe("frontend", false, start);
+        assert_eq!(
+            watchdog.observe("frontend", true, start),
+            WatchdogAction::Recovered
+        );
+        assert_eq!(
+            watchdog.observe("frontend", false, start),
+            WatchdogAction::Failing { failures: 1 }
+        );
+        // 各服务独立计数
+        assert_eq!(
+            watchdog.observe("mysql", false, start),
+            WatchdogAction::Failing { failures: 1 }
+        );
+    }
+}
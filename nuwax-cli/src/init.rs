@@ -1,17 +1,23 @@
 use anyhow::Result;
 use client_core::{
-    ClientRegisterRequest, api::ApiClient, config::AppConfig, constants::config, database::Database,
+    ClientRegisterRequest,
+    api::ApiClient,
+    config::AppConfig,
+    constants::{config, docker},
+    database::Database,
 };
+use std::path::Path;
 use tracing::{info, warn};
 
 /// 运行独立的初始化流程
-pub async fn run_init(force: bool) -> Result<()> {
+pub async fn run_init(force: bool, config_path: &Path) -> Result<()> {
     info!("🦆 Nuwax Cli ent 初始化");
     info!("======================");
 
     // 检查是否已经初始化过
     if !force
-        && (client_core::constants::config::get_config_file_path().exists()
+        && (config_path.exists()
+            || client_core::constants::config::get_config_file_path().exists()
             || config::get_database_path().exists())
     {
         warn!("⚠️  检测到已存在的配置文件或数据库文件");
@@ -23,22 +29,33 @@ pub async fn run_init(force: bool) -> Result<()> {
     info!("📋 步骤 1: 创建配置文件和目录结构");
 
     // 创建默认配置
-    let config = AppConfig::default();
-    config.save_to_file("config.toml")?;
-    info!("   ✅ 创建配置文件: config.toml");
+    let mut config = AppConfig::default();
+    // 通过 --work-dir 指定的工作目录写入配置，后续命令无需重复指定
+    let work_dir = docker::get_docker_work_dir();
+    if work_dir != Path::new(".").join(docker::DOCKER_DIR_NAME) {
+        config.docker.work_dir = Some(work_dir.to_string_lossy().to_string());
+    }
+    config.save_to_file(config_path)?;
+    info!("   ✅ 创建配置文件: {}", config_path.display());
 
     // 创建必要的目录结构
-    std::fs::create_dir_all("docker")?;
-    std::fs::create_dir_all(&config.backup.storage_dir)?;
+    std::fs::create_dir_all(&work_dir)?;
+    std::fs::create_dir_all(config.get_backup_dir())?;
     config.ensure_cache_dirs()?;
     info!("   ✅ 创建目录结构:");
-    info!("      - docker/                (Docker服务文件目录)");
+    info!(
+        "      - {}                (Docker服务文件目录)",
+        work_dir.display()
+    );
     info!(
         "      - {}         (备份存储目录)",
-        config.backup.storage_dir
+        config.get_backup_dir().display()
+    );
+    info!("      - {}    (缓存目录)", config.get_cache_dir().display());
+    info!(
+        "      - {} (下载缓存目录)",
+        config.get_download_dir().display()
     );
-    info!("      - {}    (缓存目录)", config.cache.cache_dir);
-    info!("      - {} (下载缓存目录)", config.cache.download_dir);
 
     info!("📋 步骤 2: 初始化数据库");
 
@@ -94,7 +111,10 @@ pub async fn run_init(force: bool) -> Result<()> {
     );
     info!("");
     info!("💡 提示:");
-    info!("   - 配置文件: config.toml (可手动编辑修改配置)");
+    info!(
+        "   - 配置文件: {} (可手动编辑修改配置)",
+        config_path.display()
+    );
     info!(
         "   - 数据库文件: {} (存储操作历史和备份记录)",
         db_path.display()
@@ -1,4 +1,5 @@
 // 私有模块声明
+pub mod api; // 公开库调用接口（供图形界面使用）
 mod app;
 mod cli;
 mod commands;
@@ -11,14 +12,27 @@ mod utils;
 
 // 通过 pub use 精确控制对外暴露的接口
 pub use app::CliApp;
-pub use cli::{Cli, Commands};
-pub use commands::{run_diff_sql, run_status_details, show_client_version}; // 导出status相关函数和diff-sql函数
+pub use cli::{CheckUpdateCommand, Cli, Commands};
+// 导出status相关函数、diff-sql函数以及远程/批量操作函数
+pub use commands::{
+    CommandExitCode, run_approve_command, run_diff_sql, run_fleet_command, run_patch_command, run_paths, run_remote_command, run_replay, run_self_test, run_status_details, show_client_version, sweep_stale_temp_artifacts,
+};
 pub use docker_service::{
     ContainerStatus, DockerService, DockerServiceManager, get_architecture_suffix,
     get_system_architecture, health_check
 };
 pub use init::run_init;
-pub use utils::{extract_docker_service, setup_logging}; // 导出解压函数和匹配器
+pub use utils::{
+    ExtractProgress, LogOptions,
+    completion_notify::{long_operation_name, notify_completion},
+    event_output::{EventFormat, spawn_event_renderer},
+    extract_docker_service, extract_docker_service_with_events,
+    extract_docker_service_with_progress, log_rotation::LogRotation,
+    patch_conflicts::ConflictPolicy,
+    progress_bars::{progress_bars_supported, spawn_progress_renderer},
+    prompt::{PromptMode, set_prompt_mode}, setup_logging,
+    setup_logging_with_options, telemetry::TelemetryGuard,
+}; // 导出解压函数和匹配器
 
 // 重新导出核心功能
 pub use client_core::{config_manager::ConfigManager, database_manager::DatabaseManager};
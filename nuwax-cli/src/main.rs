@@ -1,21 +1,100 @@
 use clap::Parser;
 use client_core::DuckError;
-use nuwax_cli::{Cli, CliApp, Commands, run_diff_sql, run_init, setup_logging};
-use tracing::{error, info};
+use client_core::config::AppConfig;
+use client_core::constants::docker;
+use client_core::events::EventSender;
+use client_core::http_trace;
+use client_core::legacy_migration::migrate_legacy_layout;
+use client_core::paths::{self, PathArgs, PathSource, Paths, ResolvedPath};
+use client_core::upgrade_session;
+use nuwax_cli::project_info::version_info::CLI_VERSION;
+use nuwax_cli::{
+    CheckUpdateCommand, Cli, CliApp, CommandExitCode, Commands, LogOptions, PromptMode,
+    TelemetryGuard, long_operation_name, notify_completion, progress_bars_supported,
+    run_approve_command, run_diff_sql, run_fleet_command, run_init, run_patch_command, run_paths,
+    run_remote_command, run_replay, run_self_test, set_prompt_mode, setup_logging_with_options,
+    spawn_event_renderer, spawn_progress_renderer, sweep_stale_temp_artifacts,
+};
+use std::path::Path;
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() {
     // 解析命令行参数
     let cli = Cli::parse();
 
+    // 确认提示的处理方式：--yes 自动确认，--no-input 禁止交互输入
+    set_prompt_mode(PromptMode::from_flags(cli.yes, cli.no_input));
+
+    // 配置文件：--config 优先，其次为 NUWAX_CONFIG 环境变量。
+    // 不在当前目录时切换到其所在目录，从 cron 或其他目录运行时部署中的相对路径仍然有效
+    let mut config_file = paths::resolve_config_file(cli.config.as_deref());
+    if let Err(e) = enter_config_dir(&mut config_file) {
+        eprintln!(
+            "❌ 无法进入配置文件所在目录（配置文件 {}，来自{}）: {}",
+            config_file.path.display(),
+            config_file.source,
+            e
+        );
+        exit_with_failure(None);
+    }
+
+    // 遥测和工作目录配置来自 config.toml，需在初始化前读取（配置不存在时忽略）
+    let file_config = AppConfig::load_from_file(&config_file.path).ok();
+
+    // 解析各路径：命令行参数优先，其次为环境变量和配置文件
+    let paths = Paths::resolve(
+        config_file.clone(),
+        &PathArgs {
+            work_dir: cli.work_dir.clone(),
+            log_file: cli.log_file.clone(),
+        },
+        file_config.as_ref(),
+    );
+    if paths.docker_dir.source != PathSource::Default {
+        docker::set_docker_work_dir(paths.docker_dir.path.clone());
+    }
+
     // 设置日志记录
-    setup_logging(cli.verbose);
+    let (telemetry, redaction) = file_config
+        .map(|config| (Some(config.telemetry), Some(config.log_redaction)))
+        .unwrap_or_default();
+    let telemetry_guard = setup_logging_with_options(
+        cli.verbose,
+        LogOptions {
+            log_file: cli.log_file.clone(),
+            rotation: cli.log_rotation,
+            max_files: cli.log_max_files,
+            split: cli.log_split,
+            telemetry,
+            redaction,
+            // `check-update check --quiet` 只通过退出码返回结果
+            quiet: matches!(
+                cli.command,
+                Commands::CheckUpdate(CheckUpdateCommand::Check { quiet: true, .. })
+            ),
+        },
+    );
+
+    // 旧版 duck-cli 的数据库和缓存目录迁移到新的布局，没有旧文件时不做改动
+    if let Err(e) = migrate_legacy_layout(Path::new("."), &config_file.path) {
+        warn!("⚠️ 迁移旧版 duck-cli 文件失败: {}", e);
+    }
+
+    // HTTP 调试记录：未指定文件时写入日志目录下的默认文件
+    if let Some(path) = &cli.debug_http {
+        let path = path.clone().unwrap_or_else(|| paths.http_debug_log_path());
+        match http_trace::enable_http_trace(&path) {
+            Ok(_) => info!("🔍 HTTP 调试记录已开启: {}", path.display()),
+            Err(e) => warn!("⚠️ 开启 HTTP 调试记录失败: {}", e),
+        }
+    }
 
     // `init` 命令是特例，它不需要预先加载配置
     if let Commands::Init { force } = cli.command {
-        if let Err(e) = run_init(force).await {
+        if let Err(e) = run_init(force, &config_file.path).await {
             error!("❌ 初始化失败: {}", e);
-            std::process::exit(1);
+            exit_with_failure(telemetry_guard);
         }
         return;
     }
@@ -26,7 +105,7 @@ async fn main() {
         nuwax_cli::show_client_version();
 
         // 尝试初始化应用显示完整状态
-        match CliApp::new_with_auto_config().await {
+        match CliApp::new_with_config_path(&config_file).await {
             Ok(app) => {
                 // 应用初始化成功，显示完整状态信息
                 if let Err(e) = nuwax_cli::run_status_details(&app).await {
@@ -58,17 +137,86 @@ async fn main() {
         old_version,
         new_version,
         output,
+        dialect,
     } = cli.command
     {
-        if let Err(e) = run_diff_sql(old_sql, new_sql, old_version, new_version, output).await {
+        if let Err(e) =
+            run_diff_sql(old_sql, new_sql, old_version, new_version, output, dialect).await
+        {
             error!("❌ SQL差异对比失败: {}", e);
-            std::process::exit(1);
+            exit_with_failure(telemetry_guard);
+        }
+        return;
+    }
+
+    // `remote` 命令特殊处理：操作的是远程主机，不需要本地配置和数据库
+    if let Commands::Remote { args, command } = cli.command {
+        if let Err(e) = run_remote_command(args, command).await {
+            error!("❌ 远程操作失败: {}", e);
+            exit_with_failure(telemetry_guard);
+        }
+        return;
+    }
+
+    // `fleet` 命令特殊处理：同样只操作远程主机
+    if let Commands::Fleet(fleet_cmd) = cli.command {
+        if let Err(e) = run_fleet_command(fleet_cmd).await {
+            error!("❌ 批量操作失败: {}", e);
+            exit_with_failure(telemetry_guard);
+        }
+        return;
+    }
+
+    // `patch` 命令特殊处理：只检查清单文件，不需要本地配置和数据库
+    if let Commands::Patch(patch_cmd) = cli.command {
+        if let Err(e) = run_patch_command(patch_cmd).await {
+            error!("❌ 补丁清单检查失败: {}", e);
+            exit_with_failure(telemetry_guard);
+        }
+        return;
+    }
+
+    // `replay` 命令特殊处理：只使用会话文件中录制的数据，不需要本地配置和数据库
+    if let Commands::Replay { session } = &cli.command {
+        if let Err(e) = run_replay(session).await {
+            error!("❌ 回放失败: {}", e);
+            exit_with_failure(telemetry_guard);
+        }
+        return;
+    }
+
+    // `self-test` 命令特殊处理：只使用临时目录，用于验证尚未初始化的安装
+    if let Commands::SelfTest { skip_docker } = cli.command {
+        if let Err(e) = run_self_test(skip_docker).await {
+            error!("❌ {}", e);
+            exit_with_failure(telemetry_guard);
+        }
+        return;
+    }
+
+    // `paths` 命令特殊处理：只显示路径解析结果，不需要数据库
+    if let Commands::Paths = cli.command {
+        run_paths(&paths);
+        return;
+    }
+
+    // `approve` 命令特殊处理：审批可以在没有部署的机器上进行，不需要本地配置和数据库
+    if let Commands::Approve {
+        command,
+        plan,
+        key,
+        approver,
+    } = cli.command
+    {
+        if let Err(e) = run_approve_command(command, plan, key, approver).await {
+            error!("❌ 审批失败: {}", e);
+            exit_with_failure(telemetry_guard);
         }
         return;
     }
 
     // 对于其他所有命令，我们需要加载配置并初始化App
-    let mut app = match CliApp::new_with_config_path(&cli.config).await {
+    let mut app = match CliApp::new_with_config_path(&config_file).await {
         Ok(app) => app,
         Err(e) => {
             // 检查错误的根本原因是否是ConfigNotFound
@@ -85,18 +233,104 @@ async fn main() {
             }
 
             if is_config_not_found {
-                error!("❌ 配置文件 '{}' 未找到。", cli.config.display());
+                error!("❌ 配置文件 '{}' 未找到。", config_file.path.display());
                 error!("👉 请先运行 'nuwax-cli init' 命令来创建配置文件。");
             } else {
                 error!("❌ 应用初始化失败: {}", e);
             }
-            std::process::exit(1);
+            exit_with_failure(telemetry_guard);
+        }
+    };
+
+    // 清理之前中断的升级、备份遗留的临时文件
+    sweep_stale_temp_artifacts(&app.config);
+
+    // 订阅操作事件：由独立任务渲染，命令结束后释放发送端并等待剩余事件输出。
+    // 未指定 --events 时在终端上显示进度条，非终端环境保持周期性的日志输出
+    let event_renderer = match cli.events {
+        Some(format) => {
+            let (events, receiver) = EventSender::channel();
+            app.events = events;
+            Some(spawn_event_renderer(receiver, format))
+        }
+        None if progress_bars_supported() => {
+            let (events, receiver) = EventSender::channel();
+            app.events = events;
+            Some(spawn_progress_renderer(receiver))
         }
+        None => None,
     };
 
-    // 运行命令
-    if let Err(e) = app.run_command(cli.command).await {
+    // 录制升级会话：命令结束后（无论成功与否）写入会话文件
+    if cli.record.is_some() {
+        upgrade_session::start_recording(CLI_VERSION, std::env::args().collect());
+    }
+
+    // 运行命令，长时间操作结束时按配置发送完成通知
+    let operation = long_operation_name(&cli.command);
+    let started = std::time::Instant::now();
+    let result = app.run_command(cli.command).await;
+    if let Some(operation) = operation {
+        notify_completion(
+            &app.config.notifications,
+            operation,
+            started.elapsed(),
+            &result,
+        )
+        .await;
+    }
+    if let Some(record) = &cli.record {
+        match upgrade_session::save_recording(record) {
+            Ok(_) => info!("🎞️ 升级会话已保存: {}", record.display()),
+            Err(e) => warn!("⚠️ 保存升级会话失败: {}", e),
+        }
+    }
+    if let Some(renderer) = event_renderer {
+        app.events = EventSender::default();
+        let _ = renderer.await;
+    }
+
+    if let Err(e) = result {
+        // 容器内命令的退出码原样返回，便于脚本判断
+        if let Some(CommandExitCode(code)) = e.downcast_ref::<CommandExitCode>() {
+            exit_with_code(telemetry_guard, *code);
+        }
         error!("❌ 操作失败: {}", e);
-        std::process::exit(1);
+        exit_with_failure(telemetry_guard);
     }
 }
+
+/// 配置文件位于其他目录时切换到该目录，并把配置文件路径改为绝对路径
+///
+/// 部署的 docker、data、backups 等相对路径，以及其他参数和环境变量中的相对路径，都以该目录为准
+fn enter_config_dir(config_file: &mut ResolvedPath) -> std::io::Result<()> {
+    let Some(dir) = config_file
+        .path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+    else {
+        return Ok(());
+    };
+    let dir = dir.canonicalize()?;
+    if dir == std::env::current_dir()?.canonicalize()? {
+        return Ok(());
+    }
+    std::env::set_current_dir(&dir)?;
+    if let Some(file_name) = config_file.path.file_name() {
+        config_file.path = dir.join(file_name);
+    }
+    Ok(())
+}
+
+/// 以失败状态退出进程
+fn exit_with_failure(telemetry_guard: Option<TelemetryGuard>) -> ! {
+    exit_with_code(telemetry_guard, 1)
+}
+
+/// 以指定退出码退出进程
+///
+/// `std::process::exit` 不会执行析构，需先关闭遥测导出器以刷新未发送的数据
+fn exit_with_code(telemetry_guard: Option<TelemetryGuard>, code: i32) -> ! {
+    drop(telemetry_guard);
+    std::process::exit(code);
+}
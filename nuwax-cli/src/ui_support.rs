@@ -144,7 +144,7 @@ where
 
     // 调用真正的nuwax-cli init逻辑
     use crate::init::run_init;
-    if let Err(e) = run_init(true).await {
+    if let Err(e) = run_init(true, Path::new(client_core::paths::DEFAULT_CONFIG_FILE)).await {
         // 恢复原始目录
         std::env::set_current_dir(current_dir)?;
         return Err(e.into());
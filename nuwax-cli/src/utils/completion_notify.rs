@@ -0,0 +1,123 @@
+//! 长时间操作完成通知
+//!
+//! 下载、升级和备份可能持续数小时，命令结束时按 `[notifications]` 配置发送桌面通知
+//! 或响终端铃声，提醒离开终端的操作人员。只在交互式终端会话中通知，
+//! 定时任务、图形界面调用和 `--no-input` 时不通知。
+
+use super::prompt;
+use crate::cli::{
+    AutoBackupCommand, AutoUpgradeDeployCommand, Commands, DockerServiceCommand, UpgradeCommand,
+};
+use anyhow::Result;
+use client_core::config::NotificationsConfig;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// 需要完成通知的命令及其显示名称，其他命令返回 None
+pub fn long_operation_name(command: &Commands) -> Option<&'static str> {
+    match command {
+        Commands::Upgrade {
+            command: None,
+            args,
+        } if !args.check => Some("下载服务包"),
+        Commands::Upgrade {
+            command: Some(UpgradeCommand::Phase { .. }),
+            ..
+        } => Some("升级"),
+        Commands::Upgrade {
+            command: Some(UpgradeCommand::Rollback { .. }),
+            ..
+        } => Some("升级回滚"),
+        Commands::Backup { command: None, .. } => Some("备份"),
+        Commands::AutoBackup(AutoBackupCommand::Run) => Some("备份"),
+        Commands::AutoUpgradeDeploy(AutoUpgradeDeployCommand::Run { .. }) => Some("自动升级部署"),
+        Commands::DockerService(DockerServiceCommand::RelocateData { .. }) => Some("数据迁移"),
+        _ => None,
+    }
+}
+
+/// 命令结束后按配置发送通知，耗时未超过 `min_duration_secs` 或非交互式会话时不通知
+///
+/// 通知失败只记录警告，不影响命令结果
+pub async fn notify_completion(
+    config: &NotificationsConfig,
+    operation: &str,
+    elapsed: Duration,
+    result: &Result<()>,
+) {
+    if !config.enabled()
+        || elapsed < Duration::from_secs(config.min_duration_secs)
+        || !prompt::can_prompt()
+    {
+        return;
+    }
+
+    if config.bell && std::io::stderr().is_terminal() {
+        let mut stderr = std::io::stderr();
+        let _ = stderr.write_all(b"\x07").and_then(|_| stderr.flush());
+    }
+
+    if config.desktop {
+        let (summary, body) = completion_message(operation, elapsed, result);
+        // 部分平台的通知接口是阻塞调用，放到阻塞线程中执行
+        let shown = tokio::task::spawn_blocking(move || {
+            notify_rust::Notification::new()
+                .appname("nuwax-cli")
+                .summary(&summary)
+                .body(&body)
+                .show()
+                .map(|_| ())
+        })
+        .await;
+        match shown {
+            Ok(Ok(())) => debug!("已发送桌面通知"),
+            Ok(Err(e)) => warn!("⚠️ 发送桌面通知失败: {}", e),
+            Err(e) => warn!("⚠️ 发送桌面通知失败: {}", e),
+        }
+    }
+}
+
+/// 生成通知的标题和正文
+fn completion_message(operation: &str, elapsed: Duration, result: &Result<()>) -> (String, String) {
+    let elapsed = format_elapsed(elapsed);
+    match result {
+        Ok(()) => (
+            format!("nuwax-cli {operation}完成"),
+            format!("{operation}已完成，耗时 {elapsed}"),
+        ),
+        Err(e) => (
+            format!("nuwax-cli {operation}失败"),
+            format!("{operation}在 {elapsed} 后失败: {e}"),
+        ),
+    }
+}
+
+/// 格式化耗时，如 `1时05分`、`3分12秒`
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    if seconds >= 3600 {
+        format!("{}时{:02}分", seconds / 3600, seconds % 3600 / 60)
+    } else if seconds >= 60 {
+        format!("{}分{:02}秒", seconds / 60, seconds % 60)
+    } else {
+        format!("{seconds}秒")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completion_message() {
+        let (summary, body) = completion_message("备份", Duration::from_secs(3725), &Ok(()));
+        assert_eq!(summary, "nuwax-cli 备份完成");
+        assert_eq!(body, "备份已完成，耗时 1时02分");
+
+        let failed = Err(anyhow::anyhow!("磁盘空间不足"));
+        let (summary, body) = completion_message("下载服务包", Duration::from_secs(75), &failed);
+        assert_eq!(summary, "nuwax-cli 下载服务包失败");
+        assert_eq!(body, "下载服务包在 1分15秒 后失败: 磁盘空间不足");
+    }
+}
@@ -0,0 +1,291 @@
+//! .env 与服务包模板的差异
+//!
+//! 新版本服务包的 .env 模板可能新增必填变量、删除废弃变量或修改默认值。
+//! 升级前对比用户的 .env 与模板，变量说明来自随模板发布的 `.env.meta.toml`：
+//!
+//! ```toml
+//! [OPENAI_API_KEY]
+//! description = "模型服务的 API Key"
+//! required = true
+//! ```
+
+use crate::utils::env_manager::{EnvManager, is_secret_key};
+use anyhow::{Context, Result};
+use client_core::archive_format::ArchiveFormat;
+use client_core::constants::docker::{ENV_FILE_NAME, ENV_META_FILE_NAME};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// 模板变量说明
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EnvVarMeta {
+    #[serde(default)]
+    pub description: Option<String>,
+    /// 服务启动必须设置的变量
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// .env 模板：变量默认值和说明
+#[derive(Debug, Default)]
+pub struct EnvTemplate {
+    pub variables: HashMap<String, String>,
+    pub meta: BTreeMap<String, EnvVarMeta>,
+}
+
+impl EnvTemplate {
+    /// 从 .env 和 `.env.meta.toml` 的内容解析模板
+    pub fn parse(env_content: &str, meta_content: Option<&str>) -> Result<Self> {
+        let mut env_manager = EnvManager::new();
+        env_manager.load_str(env_content)?;
+        let variables = env_manager
+            .get_all_variables()
+            .iter()
+            .map(|(key, var)| (key.clone(), var.value.clone()))
+            .collect();
+        let meta = match meta_content {
+            Some(content) => toml::from_str(content)
+                .with_context(|| format!("无法解析模板说明文件 {ENV_META_FILE_NAME}"))?,
+            None => BTreeMap::new(),
+        };
+        Ok(Self { variables, meta })
+    }
+
+    /// 从服务包或 .env 模板文件加载，模板文件同目录的 `.env.meta.toml` 作为变量说明
+    pub fn load(path: &Path) -> Result<Self> {
+        if ArchiveFormat::detect(path).is_ok() {
+            return Self::load_from_package(path);
+        }
+        let env_content = fs::read_to_string(path)
+            .with_context(|| format!("无法读取 .env 模板: {}", path.display()))?;
+        let meta_path = path.with_file_name(ENV_META_FILE_NAME);
+        let meta_content = if meta_path.exists() {
+            Some(fs::read_to_string(&meta_path)?)
+        } else {
+            None
+        };
+        Self::parse(&env_content, meta_content.as_deref())
+    }
+
+    /// 从服务包中的 `docker/.env` 和 `docker/.env.meta.toml` 加载
+    pub fn load_from_package(package_path: &Path) -> Result<Self> {
+        let files = crate::utils::read_package_text_files(
+            package_path,
+            &[ENV_FILE_NAME, ENV_META_FILE_NAME],
+        )?;
+        let env_content = files
+            .get(ENV_FILE_NAME)
+            .with_context(|| format!("服务包中没有 .env 模板: {}", package_path.display()))?;
+        Self::parse(
+            env_content,
+            files.get(ENV_META_FILE_NAME).map(String::as_str),
+        )
+    }
+
+    fn meta(&self, key: &str) -> Option<&EnvVarMeta> {
+        self.meta.get(key)
+    }
+}
+
+/// 变量差异类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvChangeKind {
+    /// 模板新增的变量
+    Added,
+    /// 模板已删除的变量
+    Removed,
+    /// 模板默认值与当前值不同
+    Changed,
+}
+
+impl fmt::Display for EnvChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvChangeKind::Added => write!(f, "新增"),
+            EnvChangeKind::Removed => write!(f, "删除"),
+            EnvChangeKind::Changed => write!(f, "修改"),
+        }
+    }
+}
+
+/// 单个变量的差异
+#[derive(Debug, Clone)]
+pub struct EnvChange {
+    pub key: String,
+    pub kind: EnvChangeKind,
+    /// 用户 .env 中的值
+    pub current: Option<String>,
+    /// 模板中的默认值
+    pub template: Option<String>,
+    pub description: Option<String>,
+    pub required: bool,
+}
+
+impl EnvChange {
+    /// 用于显示的值，密钥变量只显示是否设置
+    pub fn display_value(&self, value: Option<&str>) -> String {
+        match value {
+            None => "-".to_string(),
+            Some("") => "\"\"".to_string(),
+            Some(_) if is_secret_key(&self.key) => "******".to_string(),
+            Some(value) => value.to_string(),
+        }
+    }
+
+    /// 将变更写入 .env：新增和修改使用模板值，删除移除变量
+    pub fn apply(&self, env_manager: &mut EnvManager) {
+        match (self.kind, &self.template) {
+            (EnvChangeKind::Removed, _) => {
+                env_manager.remove_variable(&self.key);
+            }
+            (_, Some(value)) => env_manager.set_or_add_variable(&self.key, value),
+            (_, None) => {}
+        }
+    }
+}
+
+/// 对比当前 .env 变量与模板，按变更类型和变量名排序
+///
+/// 用户改过的值（端口、密码等）很常见，只有在模板默认值本身变化时才报告修改：
+/// 提供 `previous`（当前版本的模板）时比较新旧模板的默认值，否则比较当前值与新模板
+pub fn diff_env(
+    current: &HashMap<String, String>,
+    template: &EnvTemplate,
+    previous: Option<&EnvTemplate>,
+) -> Vec<EnvChange> {
+    let keys: BTreeSet<&String> = current.keys().chain(template.variables.keys()).collect();
+    let mut changes: Vec<EnvChange> = keys
+        .into_iter()
+        .filter_map(|key| {
+            let current_value = current.get(key);
+            let template_value = template.variables.get(key);
+            let kind = match (current_value, template_value) {
+                (None, Some(_)) => EnvChangeKind::Added,
+                (Some(_), None) => EnvChangeKind::Removed,
+                (Some(current_value), Some(template_value)) => {
+                    let baseline = previous
+                        .map(|previous| previous.variables.get(key))
+                        .unwrap_or(Some(current_value));
+                    match baseline {
+                        Some(baseline)
+                            if baseline != template_value && current_value != template_value =>
+                        {
+                            EnvChangeKind::Changed
+                        }
+                        _ => return None,
+                    }
+                }
+                (None, None) => return None,
+            };
+            let meta = template
+                .meta(key)
+                .or_else(|| previous.and_then(|previous| previous.meta(key)));
+            Some(EnvChange {
+                key: key.clone(),
+                kind,
+                current: current_value.cloned(),
+                template: template_value.cloned(),
+                description: meta.and_then(|meta| meta.description.clone()),
+                required: meta.is_some_and(|meta| meta.required),
+            })
+        })
+        .collect();
+    changes.sort_by_key(|change| change.kind as u8);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const META: &str = r#"
+[OPENAI_API_KEY]
+description = "模型服务的 API Key"
+required = true
+
+[LOG_LEVEL]
+description = "日志级别"
+"#;
+
+    fn current() -> HashMap<String, String> {
+        [
+            ("FRONTEND_HOST_PORT", "8080"),
+            ("LOG_LEVEL", "info"),
+            ("LEGACY_FLAG", "1"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn test_diff_env() {
+        let template = EnvTemplate::parse(
+            "FRONTEND_HOST_PORT=80\nLOG_LEVEL=warn\nOPENAI_API_KEY=\n",
+            Some(META),
+        )
+        .unwrap();
+
+        let changes = diff_env(&current(), &template, None);
+        let summary: Vec<(&str, EnvChangeKind)> = changes
+            .iter()
+            .map(|change| (change.key.as_str(), change.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("OPENAI_API_KEY", EnvChangeKind::Added),
+                ("LEGACY_FLAG", EnvChangeKind::Removed),
+                ("FRONTEND_HOST_PORT", EnvChangeKind::Changed),
+                ("LOG_LEVEL", EnvChangeKind::Changed),
+            ]
+        );
+        assert!(changes[0].required);
+        assert_eq!(changes[0].display_value(Some("sk-123")), "******");
+        assert_eq!(changes[3].description.as_deref(), Some("日志级别"));
+
+        // 有旧模板时只报告默认值变化的变量，用户自定义的端口不算修改
+        let previous = EnvTemplate::parse(
+            "FRONTEND_HOST_PORT=80\nLOG_LEVEL=info\nLEGACY_FLAG=1\n",
+            None,
+        )
+        .unwrap();
+        let changed: Vec<&str> = diff_env(&current(), &template, Some(&previous))
+            .iter()
+            .filter(|change| change.kind == EnvChangeKind::Changed)
+            .map(|change| change.key.as_str())
+            .collect();
+        assert_eq!(changed, ["LOG_LEVEL"]);
+    }
+
+    #[test]
+    fn test_apply_env_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let env_path = temp_dir.path().join(".env");
+        fs::write(
+            &env_path,
+            "FRONTEND_HOST_PORT=8080\nLEGACY_FLAG=1\nLOG_LEVEL=info",
+        )
+        .unwrap();
+        let template =
+            EnvTemplate::parse("FRONTEND_HOST_PORT=80\nLOG_LEVEL=warn\nNEW_VAR=x", None).unwrap();
+
+        let mut env_manager = EnvManager::new();
+        env_manager.load(&env_path).unwrap();
+        for change in diff_env(&current(), &template, None)
+            .iter()
+            .filter(|change| change.key != "FRONTEND_HOST_PORT")
+        {
+            change.apply(&mut env_manager);
+        }
+        env_manager.save().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&env_path).unwrap(),
+            "FRONTEND_HOST_PORT=8080\nLOG_LEVEL=warn\nNEW_VAR=x"
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use log::{debug, info};
 use regex::Regex;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
@@ -58,6 +60,12 @@ impl EnvManager {
         Ok(())
     }
 
+    /// 从字符串加载 .env 内容（不关联文件，无法保存）
+    pub fn load_str(&mut self, content: &str) -> Result<()> {
+        self.file_path = None;
+        self.parse_content(content)
+    }
+
     /// 解析 .env 文件内容
     fn parse_content(&mut self, content: &str) -> Result<()> {
         self.lines.clear();
@@ -221,6 +229,46 @@ impl EnvManager {
         Ok(())
     }
 
+    /// 设置变量的值，变量不存在时追加到文件末尾
+    pub fn set_or_add_variable(&mut self, key: &str, value: &str) {
+        if self.set_variable(key, value).is_ok() {
+            return;
+        }
+        debug!("添加变量: {key} = {value}");
+        let var = Variable {
+            key: key.to_string(),
+            value: value.to_string(),
+            quote_type: QuoteType::None,
+            has_comment: false,
+            line_index: self.lines.len(),
+        };
+        self.lines.push(LineType::Variable(var.clone()));
+        self.variables.insert(key.to_string(), var);
+    }
+
+    /// 删除一个变量，变量不存在时返回 false
+    pub fn remove_variable(&mut self, key: &str) -> bool {
+        let Some(removed) = self.variables.remove(key) else {
+            return false;
+        };
+        debug!("删除变量: {key}");
+        self.lines.remove(removed.line_index);
+        for var in self.variables.values_mut() {
+            if var.line_index > removed.line_index {
+                var.line_index -= 1;
+            }
+        }
+        true
+    }
+
+    /// 设置密钥变量的值（不在日志中输出值）
+    fn set_secret_value(&mut self, key: &str, value: String) {
+        if let Some(var) = self.variables.get_mut(key) {
+            debug!("设置密钥变量: {key}");
+            var.value = value;
+        }
+    }
+
     /// 获取所有变量的不可变引用
     pub fn get_all_variables(&self) -> &HashMap<String, Variable> {
         &self.variables
@@ -274,6 +322,184 @@ pub fn load_env_variables(env_path: &Path) -> Result<HashMap<String, String>> {
     Ok(result)
 }
 
+/// 被视为密钥的变量名片段（不区分大小写）
+const SECRET_KEY_PATTERNS: [&str; 5] = ["PASSWORD", "SECRET", "ACCESS_KEY", "JWT", "TOKEN"];
+
+/// 模板中常见的占位值（不区分大小写）
+const PLACEHOLDER_VALUES: [&str; 11] = [
+    "changeme",
+    "change_me",
+    "change-me",
+    "password",
+    "secret",
+    "123456",
+    "root",
+    "admin",
+    "minioadmin",
+    "xxx",
+    "todo",
+];
+
+/// 已生成密钥的记录文件名（位于 data 目录，升级时保留）
+pub const GENERATED_SECRETS_FILE_NAME: &str = ".generated-secrets.json";
+
+/// 生成的密钥长度
+const GENERATED_SECRET_LENGTH: usize = 32;
+
+/// 变量名是否表示密钥
+pub fn is_secret_key(key: &str) -> bool {
+    let key = key.to_uppercase();
+    SECRET_KEY_PATTERNS
+        .iter()
+        .any(|pattern| key.contains(pattern))
+}
+
+/// 值是否为模板占位值（空值、常见默认值、`your_xxx`、`<xxx>`）
+pub fn is_placeholder_value(value: &str) -> bool {
+    let value = value.trim().to_lowercase();
+    value.is_empty()
+        || PLACEHOLDER_VALUES.contains(&value.as_str())
+        || value.starts_with("your_")
+        || value.starts_with("your-")
+        || (value.starts_with('<') && value.ends_with('>'))
+}
+
+/// 生成随机密钥（大小写字母和数字）
+pub fn generate_secret() -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    std::iter::repeat_with(uuid::Uuid::new_v4)
+        .flat_map(|uuid| uuid.into_bytes())
+        .take(GENERATED_SECRET_LENGTH)
+        .map(|byte| ALPHABET[byte as usize % ALPHABET.len()] as char)
+        .collect()
+}
+
+/// 已生成密钥的记录
+///
+/// 全量升级会用服务包中的模板覆盖 .env，记录保存在会被保留的 data 目录中，
+/// 用于在升级后恢复原先生成的值，避免与已初始化的数据库等服务不一致
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GeneratedSecrets {
+    pub secrets: BTreeMap<String, GeneratedSecret>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedSecret {
+    pub value: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl GeneratedSecrets {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("无法读取密钥记录: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("无法解析密钥记录: {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("无法写入密钥记录: {}", path.display()))?;
+        // 记录中包含明文密钥，只允许所有者读写
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+/// 密钥初始化方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretBootstrapMode {
+    /// 只恢复已记录的密钥，不生成新密钥（已有数据的部署）
+    RestoreOnly,
+    /// 为占位值生成新密钥（首次部署）
+    Generate,
+    /// 重新生成所有占位值和此前生成过的密钥
+    Regenerate,
+}
+
+/// 密钥初始化结果
+#[derive(Debug, Default)]
+pub struct SecretBootstrapReport {
+    /// 新生成的密钥
+    pub generated: Vec<String>,
+    /// 从记录中恢复的密钥
+    pub restored: Vec<String>,
+    /// 仍为占位值但未生成的密钥
+    pub skipped: Vec<String>,
+}
+
+/// 检测 .env 中仍为占位值的密钥，生成或恢复随机值并写回 .env
+///
+/// 生成的密钥记录在 `record_path` 中；用户自行设置的值（非占位值且不在记录中）不会被修改
+pub fn bootstrap_secrets(
+    env_path: &Path,
+    record_path: &Path,
+    mode: SecretBootstrapMode,
+) -> Result<SecretBootstrapReport> {
+    let mut env_manager = EnvManager::new();
+    env_manager.load(env_path)?;
+    let mut record = GeneratedSecrets::load(record_path)?;
+    let mut report = SecretBootstrapReport::default();
+
+    let mut keys: Vec<String> = env_manager
+        .get_all_variables()
+        .keys()
+        .filter(|key| is_secret_key(key))
+        .cloned()
+        .collect();
+    keys.sort();
+
+    for key in keys {
+        let current = env_manager.variables[&key].value.clone();
+        let recorded = record.secrets.get(&key).map(|secret| secret.value.clone());
+        let placeholder = is_placeholder_value(&current);
+
+        let regenerate = mode == SecretBootstrapMode::Regenerate
+            && (placeholder || recorded.as_deref() == Some(current.as_str()));
+        if !regenerate && !placeholder {
+            continue;
+        }
+
+        match recorded {
+            Some(value) if !regenerate => {
+                env_manager.set_secret_value(&key, value);
+                report.restored.push(key);
+            }
+            _ if mode == SecretBootstrapMode::RestoreOnly => report.skipped.push(key),
+            _ => {
+                let value = generate_secret();
+                env_manager.set_secret_value(&key, value.clone());
+                record.secrets.insert(
+                    key.clone(),
+                    GeneratedSecret {
+                        value,
+                        generated_at: Utc::now(),
+                    },
+                );
+                report.generated.push(key);
+            }
+        }
+    }
+
+    if !report.generated.is_empty() {
+        record.save(record_path)?;
+    }
+    if !report.generated.is_empty() || !report.restored.is_empty() {
+        env_manager.save()?;
+    }
+    Ok(report)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -359,4 +585,83 @@ KEY3='single_quoted'"#;
             QuoteType::Double
         );
     }
+
+    #[test]
+    fn test_set_or_add_variable() {
+        let temp_file = NamedTempFile::new().unwrap();
+        fs::write(temp_file.path(), "KEY1=VALUE1\n# A comment").unwrap();
+
+        let mut manager = EnvManager::new();
+        manager.load(temp_file.path()).unwrap();
+        manager.set_or_add_variable("KEY1", "changed");
+        manager.set_or_add_variable("MYSQL_HOST_PORT", "23306");
+        manager.save().unwrap();
+
+        let final_content = fs::read_to_string(temp_file.path()).unwrap();
+        assert_eq!(
+            final_content,
+            "KEY1=changed\n# A comment\nMYSQL_HOST_PORT=23306"
+        );
+    }
+
+    #[test]
+    fn test_placeholder_detection() {
+        assert!(is_secret_key("MYSQL_ROOT_PASSWORD"));
+        assert!(is_secret_key("minio_secret_key"));
+        assert!(is_secret_key("JWT_SIGNING_KEY"));
+        assert!(!is_secret_key("FRONTEND_HOST_PORT"));
+
+        assert!(is_placeholder_value(""));
+        assert!(is_placeholder_value("ChangeMe"));
+        assert!(is_placeholder_value("your_jwt_secret"));
+        assert!(is_placeholder_value("<password>"));
+        assert!(!is_placeholder_value("k3Jd9sLq"));
+
+        let secret = generate_secret();
+        assert_eq!(secret.len(), GENERATED_SECRET_LENGTH);
+        assert!(secret.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(secret, generate_secret());
+    }
+
+    #[test]
+    fn test_bootstrap_secrets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let env_path = temp_dir.path().join(".env");
+        let record_path = temp_dir.path().join("data/.generated-secrets.json");
+        let template = "MYSQL_ROOT_PASSWORD=changeme\nJWT_SECRET=\"\"\nREDIS_PASSWORD=custom-value\nFRONTEND_HOST_PORT=80";
+        fs::write(&env_path, template).unwrap();
+
+        // 已有数据的部署不生成新密钥
+        let report =
+            bootstrap_secrets(&env_path, &record_path, SecretBootstrapMode::RestoreOnly).unwrap();
+        assert_eq!(report.skipped, ["JWT_SECRET", "MYSQL_ROOT_PASSWORD"]);
+        assert!(!record_path.exists());
+
+        // 首次部署生成密钥，用户自定义的值保持不变
+        let report =
+            bootstrap_secrets(&env_path, &record_path, SecretBootstrapMode::Generate).unwrap();
+        assert_eq!(report.generated, ["JWT_SECRET", "MYSQL_ROOT_PASSWORD"]);
+        let variables = load_env_variables(&env_path).unwrap();
+        let mysql_password = variables["MYSQL_ROOT_PASSWORD"].clone();
+        assert_ne!(mysql_password, "changeme");
+        assert_eq!(variables["REDIS_PASSWORD"], "custom-value");
+
+        // 全量升级覆盖 .env 后恢复原先生成的值
+        fs::write(&env_path, template).unwrap();
+        let report =
+            bootstrap_secrets(&env_path, &record_path, SecretBootstrapMode::RestoreOnly).unwrap();
+        assert_eq!(report.restored, ["JWT_SECRET", "MYSQL_ROOT_PASSWORD"]);
+        assert_eq!(
+            load_env_variables(&env_path).unwrap()["MYSQL_ROOT_PASSWORD"],
+            mysql_password
+        );
+
+        // 重新生成只替换此前生成的密钥
+        let report =
+            bootstrap_secrets(&env_path, &record_path, SecretBootstrapMode::Regenerate).unwrap();
+        assert_eq!(report.generated, ["JWT_SECRET", "MYSQL_ROOT_PASSWORD"]);
+        let variables = load_env_variables(&env_path).unwrap();
+        assert_ne!(variables["MYSQL_ROOT_PASSWORD"], mysql_password);
+        assert_eq!(variables["REDIS_PASSWORD"], "custom-value");
+    }
 }
@@ -0,0 +1,189 @@
+//! 操作事件的命令行输出
+//!
+//! `--events human` 将事件渲染为日志，`--events json` 每行向标准输出写入一个 JSON 事件，
+//! 供脚本和外部前端逐行解析。
+
+use client_core::events::{
+    EventReceiver, EventSender, OperationEvent, OperationKind, PipelinePhase,
+};
+use client_core::pipeline_progress::PipelineProgress;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// 总体进度条的宽度（字符数）
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+/// 事件输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventFormat {
+    /// 人类可读的日志输出
+    Human,
+    /// 每行一个 JSON 事件
+    Json,
+}
+
+impl std::str::FromStr for EventFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "human" => Ok(EventFormat::Human),
+            "json" => Ok(EventFormat::Json),
+            _ => Err(format!("无效的事件格式: {s}（支持 human、json）")),
+        }
+    }
+}
+
+/// 启动事件渲染任务，所有发送端释放后任务结束
+pub fn spawn_event_renderer(mut receiver: EventReceiver, format: EventFormat) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            match format {
+                EventFormat::Json => match serde_json::to_string(&event) {
+                    Ok(line) => println!("{line}"),
+                    Err(e) => warn!("⚠️ 序列化操作事件失败: {}", e),
+                },
+                EventFormat::Human => render_human(&event),
+            }
+        }
+    })
+}
+
+/// 启动升级流水线的事件转发任务：原样转发到 `outer`，并追加按阶段权重汇总的总体进度事件
+///
+/// 未订阅事件时总体进度直接渲染为日志中的进度条
+pub fn spawn_pipeline_forwarder(mut receiver: EventReceiver, outer: EventSender) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut pipeline = PipelineProgress::new();
+        while let Some(event) = receiver.recv().await {
+            let overall = pipeline.observe(&event);
+            outer.emit(event);
+            if let Some(overall) = overall {
+                if outer.is_enabled() {
+                    outer.emit(overall);
+                } else {
+                    render_human(&overall);
+                }
+            }
+        }
+    })
+}
+
+fn render_human(event: &OperationEvent) {
+    let message = format_human(event);
+    match event {
+        OperationEvent::Warning { .. } | OperationEvent::Retry { .. } => warn!("{}", message),
+        // 进度只在每 10% 输出一次，避免刷屏
+        OperationEvent::Progress { .. } => {
+            if event
+                .percentage()
+                .is_none_or(|percentage| percentage as u64 % 10 == 0)
+            {
+                info!("{}", message);
+            }
+        }
+        // 总体进度每 5% 输出一次
+        OperationEvent::PipelineProgress { percent, .. } => {
+            if *percent as u64 % 5 == 0 {
+                info!("{}", message);
+            }
+        }
+        _ => info!("{}", message),
+    }
+}
+
+/// 将事件格式化为一行可读文本
+pub fn format_human(event: &OperationEvent) -> String {
+    let operation = event.operation().display_name();
+    match event {
+        OperationEvent::PhaseStarted { phase, .. } => {
+            format!("▶ [{operation}] {}", phase_name(event.operation(), phase))
+        }
+        OperationEvent::PhaseCompleted { phase, .. } => {
+            format!(
+                "✔ [{operation}] {} 完成",
+                phase_name(event.operation(), phase)
+            )
+        }
+        OperationEvent::Progress { current, total, .. } => match event.percentage() {
+            Some(percentage) => {
+                format!("⏳ [{operation}] {percentage:.0}% ({current}/{total})")
+            }
+            None => format!("⏳ [{operation}] 已处理 {current}"),
+        },
+        OperationEvent::Warning { message, .. } => format!("⚠️ [{operation}] {message}"),
+        OperationEvent::Retry {
+            attempt,
+            max_attempts,
+            reason,
+            ..
+        } => format!("🔁 [{operation}] 第 {attempt}/{max_attempts} 次重试: {reason}"),
+        OperationEvent::PipelineProgress {
+            phase,
+            phase_percent,
+            percent,
+        } => {
+            let filled = (*percent / 100.0 * PROGRESS_BAR_WIDTH as f64) as usize;
+            let filled = filled.min(PROGRESS_BAR_WIDTH);
+            format!(
+                "📊 [{operation}] [{}{}] {percent:.0}% · {} {phase_percent:.0}%",
+                "█".repeat(filled),
+                "░".repeat(PROGRESS_BAR_WIDTH - filled),
+                phase.display_name()
+            )
+        }
+    }
+}
+
+/// 阶段显示名称，升级流水线的阶段标识转换为中文名称
+fn phase_name(operation: OperationKind, phase: &str) -> &str {
+    match operation {
+        OperationKind::Upgrade => phase
+            .parse::<PipelinePhase>()
+            .map(|phase| phase.display_name())
+            .unwrap_or(phase),
+        _ => phase,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client_core::events::OperationKind;
+
+    #[test]
+    fn test_format_human() {
+        let progress = OperationEvent::Progress {
+            operation: OperationKind::Download,
+            current: 50,
+            total: 200,
+        };
+        assert_eq!(format_human(&progress), "⏳ [下载] 25% (50/200)");
+
+        let retry = OperationEvent::Retry {
+            operation: OperationKind::Download,
+            attempt: 2,
+            max_attempts: 4,
+            reason: "连接超时".to_string(),
+        };
+        assert_eq!(format_human(&retry), "🔁 [下载] 第 2/4 次重试: 连接超时");
+
+        let overall = OperationEvent::PipelineProgress {
+            phase: PipelinePhase::Backup,
+            phase_percent: 50.0,
+            percent: 50.0,
+        };
+        assert_eq!(
+            format_human(&overall),
+            "📊 [升级] [██████████░░░░░░░░░░] 50% · 备份 50%"
+        );
+        let started = OperationEvent::PhaseStarted {
+            operation: OperationKind::Upgrade,
+            phase: "deploy".to_string(),
+        };
+        assert_eq!(format_human(&started), "▶ [升级] 部署");
+
+        assert_eq!("JSON".parse::<EventFormat>(), Ok(EventFormat::Json));
+        assert!("xml".parse::<EventFormat>().is_err());
+    }
+}
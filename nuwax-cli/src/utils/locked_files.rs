@@ -0,0 +1,261 @@
+//! 解压时被其他进程占用的文件
+//!
+//! Windows 上文件被占用（如日志在编辑器中打开）时，删除或覆盖会失败并返回“拒绝访问”
+//! 或共享冲突。解压时按退避间隔重试，仍被占用的文件把新内容写到旁边的暂存文件，
+//! 解压完成后统一报告，而不是中途中止；也可以登记为系统重启时替换。
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// 被占用文件的新内容暂存文件名后缀
+pub const PENDING_SUFFIX: &str = ".nuwax-pending";
+
+/// 文件被占用时的重试间隔（逐次加倍）
+const RETRY_DELAYS: [Duration; 5] = [
+    Duration::from_millis(100),
+    Duration::from_millis(200),
+    Duration::from_millis(400),
+    Duration::from_millis(800),
+    Duration::from_millis(1600),
+];
+
+/// 是否为文件被其他进程占用导致的错误
+///
+/// 对应 Windows 的 `ERROR_ACCESS_DENIED`、`ERROR_SHARING_VIOLATION`、`ERROR_LOCK_VIOLATION`
+/// 和 `ERROR_USER_MAPPED_FILE`；其他平台删除打开中的文件不会失败，始终返回 false。
+pub fn is_locked_error(error: &io::Error) -> bool {
+    cfg!(windows) && matches!(error.raw_os_error(), Some(5 | 32 | 33 | 1224))
+}
+
+/// 执行文件操作，文件被占用时按退避间隔重试，其他错误直接返回
+pub fn retry_locked<T>(path: &Path, mut operation: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut delays = RETRY_DELAYS.iter();
+    loop {
+        match operation() {
+            Err(e) if is_locked_error(&e) => match delays.next() {
+                Some(delay) => {
+                    debug!(
+                        "文件被占用，{} 毫秒后重试: {} - {}",
+                        delay.as_millis(),
+                        path.display(),
+                        e
+                    );
+                    std::thread::sleep(*delay);
+                }
+                None => return Err(e),
+            },
+            result => return result,
+        }
+    }
+}
+
+/// 被占用文件的新内容暂存位置：同目录下追加 [`PENDING_SUFFIX`]
+pub fn pending_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(PENDING_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// 无法替换的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedFile {
+    /// 被占用的文件
+    pub path: PathBuf,
+    /// 新内容的暂存文件；旧文件不在新版本中、只是无法删除时为 None
+    pub staged: Option<PathBuf>,
+}
+
+/// 解压过程中收集被占用的文件，可在多个解压线程间共享
+#[derive(Debug, Default)]
+pub struct LockedFiles {
+    files: Mutex<BTreeMap<PathBuf, Option<PathBuf>>>,
+}
+
+impl LockedFiles {
+    /// 记录无法删除或覆盖的文件，同一文件以有暂存内容的记录为准
+    pub fn record(&self, path: &Path, staged: Option<PathBuf>) {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = files.entry(path.to_path_buf()).or_default();
+        if staged.is_some() {
+            *entry = staged;
+        }
+    }
+
+    pub fn into_report(self) -> LockedFileReport {
+        let files = self.files.into_inner().unwrap_or_else(|e| e.into_inner());
+        LockedFileReport {
+            files: files
+                .into_iter()
+                .map(|(path, staged)| LockedFile { path, staged })
+                .collect(),
+        }
+    }
+}
+
+/// 解压结束后仍被占用、未能替换的文件
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LockedFileReport {
+    pub files: Vec<LockedFile>,
+}
+
+impl LockedFileReport {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// 合并补丁链中后续步骤的报告，同一文件以后一步为准
+    pub fn merge(&mut self, other: LockedFileReport) {
+        for file in other.files {
+            self.files.retain(|existing| existing.path != file.path);
+            self.files.push(file);
+        }
+    }
+
+    /// 输出无法替换的文件列表
+    pub fn log_summary(&self) {
+        warn!("🔒 {} 个文件被其他程序占用，未能替换:", self.files.len());
+        for file in &self.files {
+            match &file.staged {
+                Some(staged) => warn!(
+                    "   - {}（新内容已暂存到 {}）",
+                    file.path.display(),
+                    staged.display()
+                ),
+                None => warn!("   - {}（旧文件未能删除）", file.path.display()),
+            }
+        }
+    }
+
+    /// 登记为系统重启时替换：暂存文件覆盖原文件，没有暂存内容的旧文件在重启时删除
+    ///
+    /// 只支持 Windows，通常需要管理员权限。返回登记成功的文件数量
+    pub fn schedule_on_reboot(&self) -> Result<usize> {
+        for file in &self.files {
+            move_on_reboot(&file.path, file.staged.as_deref()).map_err(|e| {
+                anyhow::anyhow!("登记重启时替换失败 {}: {}", file.path.display(), e)
+            })?;
+            info!("🔁 已登记重启时替换: {}", file.path.display());
+        }
+        Ok(self.files.len())
+    }
+}
+
+/// 在系统重启时用 `staged` 替换 `path`，`staged` 为 None 时删除 `path`
+#[cfg(windows)]
+fn move_on_reboot(path: &Path, staged: Option<&Path>) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::{
+        MOVEFILE_DELAY_UNTIL_REBOOT, MOVEFILE_REPLACE_EXISTING, MoveFileExW,
+    };
+
+    fn wide(path: &Path) -> Vec<u16> {
+        path.as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect()
+    }
+
+    // 重启时把暂存文件移动到原位置；没有暂存文件时把原文件“移动”到空路径，即删除
+    let (from, to, flags) = match staged {
+        Some(staged) => (
+            wide(staged),
+            Some(wide(path)),
+            MOVEFILE_DELAY_UNTIL_REBOOT | MOVEFILE_REPLACE_EXISTING,
+        ),
+        None => (wide(path), None, MOVEFILE_DELAY_UNTIL_REBOOT),
+    };
+    let to_ptr = to.as_ref().map_or(std::ptr::null(), |to| to.as_ptr());
+    // SAFETY: 两个路径都是以 0 结尾的 UTF-16 字符串，调用期间保持有效
+    if unsafe { MoveFileExW(from.as_ptr(), to_ptr, flags) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn move_on_reboot(_path: &Path, _staged: Option<&Path>) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "仅 Windows 支持重启时替换文件",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locked_files_report() {
+        let locked = LockedFiles::default();
+        locked.record(Path::new("docker/logs/app.log"), None);
+        locked.record(
+            Path::new("docker/logs/app.log"),
+            Some(pending_path(Path::new("docker/logs/app.log"))),
+        );
+        locked.record(Path::new("docker/config/old.conf"), None);
+
+        let mut report = locked.into_report();
+        assert_eq!(report.len(), 2);
+        assert_eq!(
+            report.files[1],
+            LockedFile {
+                path: PathBuf::from("docker/logs/app.log"),
+                staged: Some(PathBuf::from("docker/logs/app.log.nuwax-pending")),
+            }
+        );
+
+        let next = LockedFiles::default();
+        next.record(Path::new("docker/config/old.conf"), Some("new".into()));
+        report.merge(next.into_report());
+        assert_eq!(report.len(), 2);
+        assert!(
+            report
+                .files
+                .iter()
+                .any(|file| file.staged == Some(PathBuf::from("new")))
+        );
+    }
+
+    #[test]
+    fn test_retry_locked_passes_through_other_errors() {
+        let mut calls = 0;
+        let result: io::Result<()> = retry_locked(Path::new("missing"), || {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_locked_file_detected_after_retries() {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("app.log");
+        std::fs::write(&path, "log").unwrap();
+
+        // 不共享任何访问权限地打开文件，模拟被其他程序占用
+        let handle = std::fs::OpenOptions::new()
+            .read(true)
+            .share_mode(0)
+            .open(&path)
+            .unwrap();
+        let error = retry_locked(&path, || std::fs::remove_file(&path)).unwrap_err();
+        assert!(is_locked_error(&error));
+
+        drop(handle);
+        retry_locked(&path, || std::fs::remove_file(&path)).unwrap();
+        assert!(!path.exists());
+    }
+}
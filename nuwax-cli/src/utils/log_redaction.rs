@@ -0,0 +1,86 @@
+//! # 日志脱敏输出
+//!
+//! 在终端和日志文件的写出端按 `[log_redaction]` 配置遮盖令牌、密码和签名 URL 参数，
+//! 库代码不需要关心日志内容是否包含敏感信息。规则见 [`client_core::redact::LogRedactor`]。
+//!
+//! tracing 的 fmt 层每条日志格式化完成后一次性写出，因此按整条日志处理，不会因拆分写入漏掉匹配。
+
+use client_core::config::LogRedactionConfig;
+use client_core::redact::LogRedactor;
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// 按配置创建脱敏器，关闭脱敏时返回 None
+pub fn build_redactor(config: Option<&LogRedactionConfig>) -> Option<Arc<LogRedactor>> {
+    match config {
+        Some(config) if !config.enabled => None,
+        Some(config) => Some(Arc::new(LogRedactor::new(&config.extra_keys))),
+        None => Some(Arc::new(LogRedactor::default())),
+    }
+}
+
+/// 写出前遮盖敏感信息的日志输出
+pub struct RedactingWriter<W: Write> {
+    inner: W,
+    redactor: Option<Arc<LogRedactor>>,
+}
+
+impl<W: Write> RedactingWriter<W> {
+    pub fn new(inner: W, redactor: Option<Arc<LogRedactor>>) -> Self {
+        Self { inner, redactor }
+    }
+
+    fn redact<'a>(&self, buf: &'a [u8]) -> Cow<'a, [u8]> {
+        let (Some(redactor), Ok(text)) = (&self.redactor, std::str::from_utf8(buf)) else {
+            return Cow::Borrowed(buf);
+        };
+        match redactor.redact(text) {
+            Cow::Borrowed(_) => Cow::Borrowed(buf),
+            Cow::Owned(text) => Cow::Owned(text.into_bytes()),
+        }
+    }
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // 脱敏后长度会变化，整段写出后按原长度返回
+        let redacted = self.redact(buf);
+        self.inner.write_all(&redacted)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let redacted = self.redact(buf);
+        self.inner.write_all(&redacted)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacting_writer() {
+        let redactor = build_redactor(None);
+        let mut writer = RedactingWriter::new(Vec::new(), redactor);
+        writer
+            .write_all(b"INFO download https://cdn.example.com/a.zip?token=abc123\n")
+            .unwrap();
+        let output = String::from_utf8(writer.inner).unwrap();
+        assert!(!output.contains("abc123"), "{output}");
+        assert!(output.contains("https://cdn.example.com/a.zip"), "{output}");
+
+        let disabled = LogRedactionConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        let mut writer = RedactingWriter::new(Vec::new(), build_redactor(Some(&disabled)));
+        writer.write_all(b"password=secret\n").unwrap();
+        assert_eq!(writer.inner, b"password=secret\n");
+    }
+}
@@ -0,0 +1,309 @@
+//! # 日志文件轮转
+//!
+//! 为长期运行的调度进程提供有上限的日志文件输出：
+//! - 按大小轮转：`duck.log` 写满后依次重命名为 `duck.log.1`、`duck.log.2` ...
+//! - 按时间轮转：基于 tracing-appender 的 `RollingFileAppender`，文件名带日期后缀
+//!
+//! 两种方式都只保留最近 N 个文件，避免日志占满磁盘。
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+/// 默认单个日志文件大小上限（50MB）
+pub const DEFAULT_LOG_MAX_BYTES: u64 = 50 * 1024 * 1024;
+
+/// 默认保留的日志文件数量
+pub const DEFAULT_LOG_MAX_FILES: usize = 5;
+
+/// 按时间轮转时清理过期文件的最小间隔
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 日志轮转策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    /// 不轮转，始终追加到同一个文件
+    Never,
+    /// 每小时轮转
+    Hourly,
+    /// 每天轮转
+    Daily,
+    /// 文件达到指定字节数后轮转
+    Size(u64),
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self::Size(DEFAULT_LOG_MAX_BYTES)
+    }
+}
+
+impl FromStr for LogRotation {
+    type Err = String;
+
+    /// 支持 `never`、`hourly`、`daily`，或带单位的大小（如 `50MB`、`512KB`、`1GB`）
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().to_ascii_lowercase();
+        match value.as_str() {
+            "never" | "none" => return Ok(Self::Never),
+            "hourly" | "hour" => return Ok(Self::Hourly),
+            "daily" | "day" => return Ok(Self::Daily),
+            _ => {}
+        }
+
+        let (number, multiplier) = if let Some(n) = value.strip_suffix("gb") {
+            (n, 1024 * 1024 * 1024)
+        } else if let Some(n) = value.strip_suffix("mb") {
+            (n, 1024 * 1024)
+        } else if let Some(n) = value.strip_suffix("kb") {
+            (n, 1024)
+        } else {
+            (value.strip_suffix('b').unwrap_or(&value), 1)
+        };
+
+        match number.trim().parse::<u64>() {
+            Ok(size) if size > 0 => Ok(Self::Size(size * multiplier)),
+            _ => Err(format!(
+                "无效的日志轮转策略: {s}（支持 never、hourly、daily 或如 50MB 的大小）"
+            )),
+        }
+    }
+}
+
+/// 打开带轮转的日志输出
+pub fn open_log_writer(
+    path: &Path,
+    rotation: LogRotation,
+    max_files: usize,
+) -> io::Result<Box<dyn Write + Send>> {
+    let max_files = max_files.max(1);
+    match rotation {
+        LogRotation::Never => Ok(Box::new(
+            OpenOptions::new().create(true).append(true).open(path)?,
+        )),
+        LogRotation::Size(max_bytes) => Ok(Box::new(SizeRotatingWriter::new(
+            path.to_path_buf(),
+            max_bytes,
+            max_files,
+        )?)),
+        LogRotation::Hourly => TimeRotatingWriter::new(path, Rotation::HOURLY, max_files)
+            .map(|w| Box::new(w) as Box<dyn Write + Send>),
+        LogRotation::Daily => TimeRotatingWriter::new(path, Rotation::DAILY, max_files)
+            .map(|w| Box::new(w) as Box<dyn Write + Send>),
+    }
+}
+
+/// 按大小轮转的日志文件
+struct SizeRotatingWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl SizeRotatingWriter {
+    fn new(path: PathBuf, max_bytes: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            written,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_os_string();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+
+    /// `duck.log.{N-1}` 被丢弃，其余依次后移，当前文件变为 `duck.log.1`
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.max_files > 1 {
+            let _ = fs::remove_file(self.rotated_path(self.max_files - 1));
+            for index in (1..self.max_files - 1).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+        } else {
+            // 只保留一个文件时直接截断
+            self.file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// 按时间轮转的日志文件，在 `RollingFileAppender` 之上增加保留数量限制
+struct TimeRotatingWriter {
+    inner: RollingFileAppender,
+    directory: PathBuf,
+    file_name: String,
+    max_files: usize,
+    last_prune: Instant,
+}
+
+impl TimeRotatingWriter {
+    fn new(path: &Path, rotation: Rotation, max_files: usize) -> io::Result<Self> {
+        let directory = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "日志文件路径无效"))?;
+        fs::create_dir_all(&directory)?;
+
+        let writer = Self {
+            inner: RollingFileAppender::new(rotation, &directory, &file_name),
+            directory,
+            file_name,
+            max_files,
+            last_prune: Instant::now(),
+        };
+        prune_rotated_files(&writer.directory, &writer.file_name, writer.max_files);
+        Ok(writer)
+    }
+}
+
+impl Write for TimeRotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if self.last_prune.elapsed() >= PRUNE_INTERVAL {
+            self.last_prune = Instant::now();
+            prune_rotated_files(&self.directory, &self.file_name, self.max_files);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 删除 `{file_name}.{日期}` 形式的旧日志，只保留最新的 `max_files` 个
+///
+/// 日期后缀按字典序即为时间顺序
+fn prune_rotated_files(directory: &Path, file_name: &str, max_files: usize) {
+    let prefix = format!("{file_name}.");
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+
+    let mut rotated: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .collect();
+    if rotated.len() <= max_files {
+        return;
+    }
+
+    rotated.sort();
+    for path in &rotated[..rotated.len() - max_files] {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_log_rotation() {
+        assert_eq!("daily".parse::<LogRotation>(), Ok(LogRotation::Daily));
+        assert_eq!("Hourly".parse::<LogRotation>(), Ok(LogRotation::Hourly));
+        assert_eq!("never".parse::<LogRotation>(), Ok(LogRotation::Never));
+        assert_eq!(
+            "50MB".parse::<LogRotation>(),
+            Ok(LogRotation::Size(50 * 1024 * 1024))
+        );
+        assert_eq!(
+            "512kb".parse::<LogRotation>(),
+            Ok(LogRotation::Size(512 * 1024))
+        );
+        assert_eq!("4096".parse::<LogRotation>(), Ok(LogRotation::Size(4096)));
+        assert!("weekly".parse::<LogRotation>().is_err());
+        assert!("0MB".parse::<LogRotation>().is_err());
+    }
+
+    #[test]
+    fn test_size_rotation_keeps_max_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("duck.log");
+        let mut writer = open_log_writer(&path, LogRotation::Size(10), 3).unwrap();
+
+        for line in ["aaaaaaaa\n", "bbbbbbbb\n", "cccccccc\n", "dddddddd\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "dddddddd\n");
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("duck.log.1")).unwrap(),
+            "cccccccc\n"
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("duck.log.2")).unwrap(),
+            "bbbbbbbb\n"
+        );
+        assert!(!temp_dir.path().join("duck.log.3").exists());
+    }
+
+    #[test]
+    fn test_prune_rotated_files() {
+        let temp_dir = TempDir::new().unwrap();
+        for date in ["2025-01-01", "2025-01-02", "2025-01-03"] {
+            fs::write(temp_dir.path().join(format!("duck.log.{date}")), "").unwrap();
+        }
+        fs::write(temp_dir.path().join("other.log"), "").unwrap();
+
+        prune_rotated_files(temp_dir.path(), "duck.log", 2);
+
+        assert!(!temp_dir.path().join("duck.log.2025-01-01").exists());
+        assert!(temp_dir.path().join("duck.log.2025-01-02").exists());
+        assert!(temp_dir.path().join("duck.log.2025-01-03").exists());
+        assert!(temp_dir.path().join("other.log").exists());
+    }
+}
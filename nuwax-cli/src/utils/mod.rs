@@ -1,12 +1,42 @@
 use anyhow::Result;
-use client_core::{constants::docker::get_docker_work_dir, upgrade_strategy::UpgradeStrategy};
+use client_core::{
+    archive_format::ArchiveFormat,
+    config::{LogRedactionConfig, TelemetryConfig},
+    constants::docker::{PRESERVED_DIR_NAMES, get_docker_work_dir},
+    constants::{legacy, logging},
+    events::{EventSender, OperationKind, ProgressReporter},
+    safe_path::resolve_entry_path,
+    symlink::SymlinkExtractor,
+    upgrade_strategy::UpgradeStrategy,
+};
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use std::time::Instant;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+use walkdir::WalkDir;
 use zip::read::ZipFile;
 
+use locked_files::{LockedFileReport, LockedFiles, is_locked_error, pending_path, retry_locked};
+use log_redaction::RedactingWriter;
+use patch_conflicts::ConflictPolicy;
+
 // 导入匹配器模块
+pub mod completion_notify;
+pub mod env_diff;
 pub mod env_manager;
+pub mod event_output;
+pub mod locked_files;
+pub mod log_redaction;
+pub mod log_rotation;
+pub mod network_diagnostics;
+pub mod patch_conflicts;
+pub mod progress_bars;
+pub mod prompt;
+pub mod telemetry;
+
+use log_rotation::{DEFAULT_LOG_MAX_FILES, LogRotation, open_log_writer};
+use telemetry::{BoxedLayer, TelemetryGuard, init_telemetry};
 
 // 重新导出匹配器模块
 // pub use matcher::*;
@@ -79,10 +109,17 @@ fn should_skip_file(file_name: &str) -> bool {
 ///
 /// ### 命令行参数
 /// - `-v, --verbose`：启用详细日志模式（DEBUG 级别）
+/// - `--log-file`：日志文件路径，优先于 `NUWAX_LOG_FILE`
+/// - `--log-rotation`：日志轮转策略（`never`、`hourly`、`daily` 或如 `50MB` 的大小，默认 50MB）
+/// - `--log-max-files`：保留的日志文件数量（默认 5）
+/// - `--log-split`：额外输出下载和升级的独立日志文件
+/// - `--events`：输出操作事件（`human` 写入日志，`json` 每行一个事件写入标准输出）
 ///
 /// ### 环境变量
 /// - `RUST_LOG`：标准的 Rust 日志级别控制（如 `debug`, `info`, `warn`, `error`）
-/// - `DUCK_LOG_FILE`：日志文件路径，设置后日志输出到文件而非终端
+/// - `NUWAX_LOG_FILE`：日志文件路径，设置后日志输出到文件而非终端
+/// - `NUWAX_LOG_ROTATION` / `NUWAX_LOG_MAX_FILES` / `NUWAX_LOG_SPLIT`：与上述命令行参数对应
+/// - 旧版 duck-cli 的 `DUCK_LOG_*` 仍然有效，`NUWAX_LOG_*` 优先
 ///
 /// ## 使用示例
 ///
@@ -94,7 +131,10 @@ fn should_skip_file(file_name: &str) -> bool {
 /// nuwax-cli -v auto-backup status
 ///
 /// # 日志输出到文件
-/// DUCK_LOG_FILE=duck.log nuwax-cli auto-backup status
+/// NUWAX_LOG_FILE=nuwax.log nuwax-cli auto-backup status
+///
+/// # 日志按天轮转，保留 14 天，并拆分下载/升级日志
+/// nuwax-cli --log-file logs/nuwax.log --log-rotation daily --log-max-files 14 --log-split auto-upgrade-deploy run
 ///
 /// # 使用 RUST_LOG 控制特定模块的日志级别
 /// RUST_LOG=duck_cli::commands::auto_backup=debug nuwax-cli auto-backup status
@@ -162,6 +202,20 @@ pub fn copy_with_progress<R: Read, W: Write>(
 fn force_extract_file(
     entry: &mut ZipFile<std::fs::File>,
     target_path: &std::path::Path,
+    locked: &LockedFiles,
+) -> Result<()> {
+    let is_dir = entry.is_dir();
+    force_extract_entry(entry, is_dir, target_path, locked)
+}
+
+/// 强制覆盖文件/目录的通用实现，适用于任意可读的归档条目（zip / tar）
+///
+/// 文件被其他程序占用时按退避间隔重试，仍被占用则把新内容写到暂存文件并记录到 `locked`
+fn force_extract_entry<R: Read>(
+    entry: &mut R,
+    is_dir: bool,
+    target_path: &std::path::Path,
+    locked: &LockedFiles,
 ) -> Result<()> {
     // 如果目标存在，先彻底删除
     if target_path.exists() {
@@ -170,7 +224,12 @@ fn force_extract_file(
             std::fs::remove_dir_all(target_path)?;
         } else {
             info!("🗑️  强制删除文件: {}", target_path.display());
-            std::fs::remove_file(target_path)?;
+            if let Err(e) = retry_locked(target_path, || std::fs::remove_file(target_path)) {
+                if is_dir || !is_locked_error(&e) {
+                    return Err(e.into());
+                }
+                return stage_locked_entry(entry, target_path, locked);
+            }
         }
     }
 
@@ -182,16 +241,23 @@ fn force_extract_file(
     }
 
     // 创建新文件/目录
-    if entry.is_dir() {
+    if is_dir {
         std::fs::create_dir_all(target_path).map_err(|e| {
             error!("❌ 目录创建失败: {} - 错误: {}", target_path.display(), e);
             e
         })?;
     } else {
-        let mut outfile = std::fs::File::create(target_path).map_err(|e| {
-            error!("❌ 文件创建失败: {} - 错误: {}", target_path.display(), e);
-            e
-        })?;
+        let mut outfile = match retry_locked(target_path, || std::fs::File::create(target_path)) {
+            Ok(outfile) => outfile,
+            // 删除后仍处于待删除状态的文件也会拒绝创建
+            Err(e) if is_locked_error(&e) => {
+                return stage_locked_entry(entry, target_path, locked);
+            }
+            Err(e) => {
+                error!("❌ 文件创建失败: {} - 错误: {}", target_path.display(), e);
+                return Err(e.into());
+            }
+        };
         std::io::copy(entry, &mut outfile).map_err(|e| {
             error!("❌ 文件写入失败: {} - 错误: {}", target_path.display(), e);
             e
@@ -201,13 +267,92 @@ fn force_extract_file(
     Ok(())
 }
 
+/// 目标文件被占用时，把条目内容写到同目录下的暂存文件，解压结束后统一报告
+fn stage_locked_entry<R: Read>(
+    entry: &mut R,
+    target_path: &std::path::Path,
+    locked: &LockedFiles,
+) -> Result<()> {
+    let staged = pending_path(target_path);
+    let mut outfile = std::fs::File::create(&staged)?;
+    std::io::copy(entry, &mut outfile)?;
+    warn!(
+        "🔒 文件被占用，新内容已暂存: {} -> {}",
+        target_path.display(),
+        staged.display()
+    );
+    locked.record(target_path, Some(staged));
+    Ok(())
+}
+
+/// 删除目录，被占用的文件留在原处并记录到 `locked`，其他错误直接返回
+fn remove_dir_tolerant(dir: &std::path::Path, locked: &LockedFiles) -> Result<()> {
+    let Err(e) = retry_locked(dir, || std::fs::remove_dir_all(dir)) else {
+        return Ok(());
+    };
+    if !is_locked_error(&e) {
+        return Err(e.into());
+    }
+
+    // 逐个删除，被占用文件所在的目录因非空而保留
+    for entry in WalkDir::new(dir).contents_first(true) {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type().is_dir() {
+            let _ = std::fs::remove_dir(path);
+        } else if let Err(e) = std::fs::remove_file(path) {
+            if !is_locked_error(&e) {
+                return Err(e.into());
+            }
+            warn!("🔒 文件被占用，未能删除: {}", path.display());
+            locked.record(path, None);
+        }
+    }
+    Ok(())
+}
+
+/// ZIP 条目为符号链接时返回链接目标（链接目标以条目内容的形式保存）
+fn zip_symlink_target(entry: &mut ZipFile<std::fs::File>) -> Result<Option<PathBuf>> {
+    let is_symlink = entry
+        .unix_mode()
+        .is_some_and(|mode| mode & 0o170000 == 0o120000);
+    if !is_symlink {
+        return Ok(None);
+    }
+    let mut target = String::new();
+    entry.read_to_string(&mut target)?;
+    Ok(Some(PathBuf::from(target)))
+}
+
+/// 解压单个 ZIP 条目：符号链接经过校验后重建，其他条目强制覆盖
+fn extract_zip_entry(
+    entry: &mut ZipFile<std::fs::File>,
+    target_path: &std::path::Path,
+    links: &mut SymlinkExtractor,
+    locked: &LockedFiles,
+) -> Result<()> {
+    match zip_symlink_target(entry)? {
+        Some(link_target) => {
+            links.create(target_path, &link_target)?;
+        }
+        None => force_extract_file(entry, target_path, locked)?,
+    }
+    Ok(())
+}
+
+/// 将 ZIP 条目安全地解压到 `base` 下的 `relative_path`（拒绝路径遍历）
 fn handle_extraction(
     entry: &mut ZipFile<std::fs::File>,
-    dst: &std::path::Path,
+    base: &std::path::Path,
+    relative_path: &str,
+    links: &mut SymlinkExtractor,
+    locked: &LockedFiles,
     extracted_files: &mut usize,
     extracted_size: &mut u64,
 ) -> Result<()> {
-    force_extract_file(entry, dst)?;
+    let dst = resolve_entry_path(base, relative_path)?;
+    ensure_parent_dir(&dst)?;
+    extract_zip_entry(entry, &dst, links, locked)?;
     *extracted_files += 1;
     *extracted_size += entry.size();
     Ok(())
@@ -239,8 +384,15 @@ fn is_upload_directory_path(path: &std::path::Path) -> bool {
         .any(|component| EXCLUDE_DIRS.iter().any(|d| component.as_os_str() == *d))
 }
 
+/// 策略为保留现有内容时，补丁跳过受保护目录中的路径
+fn keep_protected(path: &std::path::Path, conflict_policy: ConflictPolicy) -> bool {
+    !conflict_policy.replaces_protected() && is_upload_directory_path(path)
+}
+
 /// 安全删除 docker 目录，保留 upload 目录
-fn safe_remove_docker_directory(output_dir: &std::path::Path) -> Result<()> {
+///
+/// 被其他程序占用的文件无法删除时保留在原处，记录到 `locked` 后继续
+fn safe_remove_docker_directory(output_dir: &std::path::Path, locked: &LockedFiles) -> Result<()> {
     if !output_dir.exists() {
         return Ok(());
     }
@@ -257,16 +409,10 @@ fn safe_remove_docker_directory(output_dir: &std::path::Path) -> Result<()> {
         let file_name = entry.file_name();
 
         // 跳过 [upload, project_workspace, project_zips, project_nginx, project_init, data] 目录
-        const EXCLUDE_DIRS: [&str; 7] = [
-            "upload",
-            "project_workspace",
-            "project_zips",
-            "project_nginx",
-            "project_init",
-            "uv_cache",
-            "data",
-        ];
-        if EXCLUDE_DIRS.iter().any(|d| file_name.as_os_str() == *d) {
+        if PRESERVED_DIR_NAMES
+            .iter()
+            .any(|d| file_name.as_os_str() == *d)
+        {
             info!("🛡️ 保留目录: {}", path.display());
             continue;
         }
@@ -274,10 +420,16 @@ fn safe_remove_docker_directory(output_dir: &std::path::Path) -> Result<()> {
         // 删除其他文件或目录
         if path.is_dir() {
             info!("🗑️ 删除目录: {}", path.display());
-            std::fs::remove_dir_all(&path)?;
+            remove_dir_tolerant(&path, locked)?;
         } else {
             info!("🗑️ 删除文件: {}", path.display());
-            std::fs::remove_file(&path)?;
+            if let Err(e) = retry_locked(&path, || std::fs::remove_file(&path)) {
+                if !is_locked_error(&e) {
+                    return Err(e.into());
+                }
+                warn!("🔒 文件被占用，未能删除: {}", path.display());
+                locked.record(&path, None);
+            }
         }
     }
 
@@ -285,23 +437,776 @@ fn safe_remove_docker_directory(output_dir: &std::path::Path) -> Result<()> {
     Ok(())
 }
 
+/// 解压进度信息
+#[derive(Debug, Clone)]
+pub struct ExtractProgress {
+    pub extracted_files: usize,
+    pub total_files: usize,
+    pub extracted_bytes: u64,
+    pub total_bytes: u64,
+    pub percentage: f64,
+}
+
+/// 解压进度回调
+pub type ExtractProgressCallback<'a> = &'a (dyn Fn(ExtractProgress) + Send + Sync);
+
+/// 并行解压的最大工作线程数（避免在核数很多的机器上过度争抢磁盘）
+const MAX_EXTRACT_WORKERS: usize = 8;
+
+/// 待解压的文件条目
+struct ExtractTask {
+    index: usize,
+    target_path: std::path::PathBuf,
+    size: u64,
+    /// 去掉顶层 docker/ 前缀后的相对路径
+    relative_path: String,
+    crc32: u32,
+    unix_mode: Option<u32>,
+}
+
+/// 全量解压：先串行创建目录，再由有界工作线程池并行解压文件
+///
+/// 每个工作线程独立打开 ZIP 文件，通过共享的原子下标领取任务，
+/// 任一线程出错后其余线程尽快退出，并返回第一个错误。
+fn extract_full_package(
+    zip_path: &std::path::Path,
+    archive: &mut zip::ZipArchive<std::fs::File>,
+    output_dir: &std::path::Path,
+    progress_callback: Option<ExtractProgressCallback<'_>>,
+    locked: &LockedFiles,
+) -> Result<(usize, u64)> {
+    use std::collections::BTreeSet;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+    // 第一阶段：扫描条目，确定需要创建的目录和需要解压的文件
+    let mut dirs: BTreeSet<std::path::PathBuf> = BTreeSet::new();
+    let mut tasks: Vec<ExtractTask> = Vec::new();
+    let mut symlinks: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let file_name = file.name().to_string();
+
+        // 跳过系统文件和临时文件
+        if should_skip_file(&file_name) {
+            info!("⏩ 跳过文件: {}", file_name);
+            continue;
+        }
+
+        // 处理路径：移除可能的顶层docker目录前缀
+        let clean_path = file_name.strip_prefix("docker/").unwrap_or(&file_name);
+        let target_path = resolve_entry_path(output_dir, clean_path)?;
+
+        // 检查是否为 upload 目录路径
+        if is_upload_directory_path(&target_path) {
+            // 如果 upload 目录已存在，跳过解压以保护用户数据
+            // 如果 upload 目录不存在，正常解压以创建目录结构
+            if target_path.exists() {
+                info!(
+                    "🛡️ 保护现有 upload 目录，跳过解压: {}",
+                    target_path.display()
+                );
+                continue;
+            } else {
+                info!("📁 创建新的 upload 目录结构: {}", target_path.display());
+            }
+        }
+
+        if file.is_dir() {
+            dirs.insert(target_path);
+        } else if let Some(link_target) = zip_symlink_target(&mut file)? {
+            // 符号链接在文件解压完成后统一创建
+            symlinks.push((target_path, link_target));
+        } else {
+            if let Some(parent) = target_path.parent() {
+                dirs.insert(parent.to_path_buf());
+            }
+            tasks.push(ExtractTask {
+                index: i,
+                target_path,
+                size: file.size(),
+                relative_path: clean_path.trim_start_matches('/').to_string(),
+                crc32: file.crc32(),
+                unix_mode: file.unix_mode(),
+            });
+        }
+    }
+
+    // 第二阶段：先创建全部目录，避免工作线程之间的目录创建竞争
+    for dir in &dirs {
+        if dir.is_file() {
+            std::fs::remove_file(dir)?;
+        }
+        std::fs::create_dir_all(dir).map_err(|e| {
+            error!("❌ 目录创建失败: {} - 错误: {}", dir.display(), e);
+            e
+        })?;
+    }
+
+    let total_files = tasks.len();
+    let total_bytes: u64 = tasks.iter().map(|t| t.size).sum();
+    let workers = num_cpus::get()
+        .clamp(1, MAX_EXTRACT_WORKERS)
+        .min(total_files.max(1));
+
+    info!(
+        "🚀 开始解压 {} 个文件 ({:.1} MB, {} 个并行线程)...",
+        total_files,
+        total_bytes as f64 / 1024.0 / 1024.0,
+        workers
+    );
+
+    // 内容寻址存储中已有相同文件时直接硬链接，无需解压
+    let store = client_core::package_store::registered_package_store();
+    let lookup = match store.map(|store| store.lookup()) {
+        Some(Ok(lookup)) if !lookup.is_empty() => Some(lookup),
+        Some(Err(e)) => {
+            warn!("⚠️ 读取服务包存储失败，全部文件将正常解压: {}", e);
+            None
+        }
+        _ => None,
+    };
+
+    // 第三阶段：并行解压文件
+    let next_task = AtomicUsize::new(0);
+    let linked_files = AtomicUsize::new(0);
+    let extracted_files = AtomicUsize::new(0);
+    let extracted_bytes = AtomicU64::new(0);
+    let last_logged_percent = AtomicUsize::new(0);
+    let aborted = AtomicBool::new(false);
+    let first_error: Mutex<Option<anyhow::Error>> = Mutex::new(None);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                let result = (|| -> Result<()> {
+                    let mut archive = zip::ZipArchive::new(std::fs::File::open(zip_path)?)?;
+                    loop {
+                        if aborted.load(Ordering::Relaxed) {
+                            return Ok(());
+                        }
+                        let i = next_task.fetch_add(1, Ordering::Relaxed);
+                        let Some(task) = tasks.get(i) else {
+                            return Ok(());
+                        };
+
+                        let linked = match (store, &lookup) {
+                            (Some(store), Some(lookup)) => {
+                                match lookup.find(&task.relative_path, task.size, task.crc32) {
+                                    Some((hash, info)) => store.link_object(
+                                        hash,
+                                        info,
+                                        &task.target_path,
+                                        task.unix_mode,
+                                    )?,
+                                    None => false,
+                                }
+                            }
+                            _ => false,
+                        };
+                        if linked {
+                            linked_files.fetch_add(1, Ordering::Relaxed);
+                        } else {
+                            let mut entry = archive.by_index(task.index)?;
+                            // 强制覆盖：先删除再解压（彻底解决 Directory not empty 错误）
+                            force_extract_file(&mut entry, &task.target_path, locked)?;
+                        }
+
+                        let files = extracted_files.fetch_add(1, Ordering::Relaxed) + 1;
+                        let bytes =
+                            extracted_bytes.fetch_add(task.size, Ordering::Relaxed) + task.size;
+                        let percentage = (files * 100) / total_files;
+
+                        // 每解压10%的文件显示进度（有进度回调时由回调方展示）
+                        let bucket = percentage / 10 * 10;
+                        if progress_callback.is_none()
+                            && bucket > last_logged_percent.fetch_max(bucket, Ordering::Relaxed)
+                        {
+                            info!(
+                                "📁 解压进度: {}% ({}/{} 文件, {:.1} MB)",
+                                bucket,
+                                files,
+                                total_files,
+                                bytes as f64 / 1024.0 / 1024.0
+                            );
+                        }
+
+                        if let Some(callback) = progress_callback {
+                            callback(ExtractProgress {
+                                extracted_files: files,
+                                total_files,
+                                extracted_bytes: bytes,
+                                total_bytes,
+                                percentage: files as f64 * 100.0 / total_files as f64,
+                            });
+                        }
+                    }
+                })();
+
+                if let Err(e) = result {
+                    aborted.store(true, Ordering::Relaxed);
+                    let mut slot = first_error.lock().unwrap_or_else(|p| p.into_inner());
+                    if slot.is_none() {
+                        *slot = Some(e);
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(e) = first_error.into_inner().unwrap_or_else(|p| p.into_inner()) {
+        return Err(e);
+    }
+
+    let linked_files = linked_files.into_inner();
+    if linked_files > 0 {
+        info!("🔗 {} 个未变化的文件直接从服务包存储硬链接", linked_files);
+    }
+
+    let mut links = SymlinkExtractor::new(output_dir);
+    for (link_path, link_target) in &symlinks {
+        links.create(link_path, link_target)?;
+    }
+    links.finish()?;
+
+    Ok((extracted_files.into_inner(), extracted_bytes.into_inner()))
+}
+
+/// 将全量解压后的工作目录存入服务包存储，失败时只记录警告
+///
+/// 跳过用户数据目录和会被原地修改的 .env 文件
+fn ingest_into_package_store(work_dir: &Path, version: &client_core::version::Version) {
+    let Some(store) = client_core::package_store::registered_package_store() else {
+        return;
+    };
+    let ingest_start = Instant::now();
+    let result = store.ingest_dir(work_dir, &version.to_string(), |relative| {
+        relative == ".env" || is_upload_directory_path(Path::new(relative))
+    });
+    match result {
+        Ok(report) => info!(
+            "🗄️ 服务包已存入内容寻址存储: 新增 {} 个对象, 复用 {} 个 ({:.1} MB), 耗时 {:.2} 秒",
+            report.stored,
+            report.reused,
+            report.saved_bytes as f64 / 1024.0 / 1024.0,
+            ingest_start.elapsed().as_secs_f64()
+        ),
+        Err(e) => warn!("⚠️ 服务包存入内容寻址存储失败: {}", e),
+    }
+}
+
+/// 清理即将被替换或删除的文件/目录（跳过upload目录）
+fn cleanup_patch_targets(
+    patch_info: &client_core::api_types::PatchPackageInfo,
+    work_dir: &std::path::Path,
+    conflict_policy: ConflictPolicy,
+) -> Result<()> {
+    let upgrade_change_file_or_dir = patch_info
+        .get_changed_files()
+        .iter()
+        .map(|path| resolve_entry_path(work_dir, path))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+    for file_or_dir in upgrade_change_file_or_dir {
+        if keep_protected(&file_or_dir, conflict_policy) {
+            info!("🛡️ 保护 upload 目录，跳过删除: {}", file_or_dir.display());
+            continue;
+        }
+
+        if file_or_dir.is_file() {
+            std::fs::remove_file(file_or_dir)?;
+        } else if file_or_dir.is_dir() {
+            std::fs::remove_dir_all(file_or_dir)?;
+        } else {
+            info!("文件/目录不存在，跳过: {}", file_or_dir.display());
+        }
+    }
+    Ok(())
+}
+
+/// 处理补丁的删除操作（跳过upload目录）
+fn apply_patch_deletes(
+    delete: &client_core::api_types::ReplaceOperations,
+    work_dir: &std::path::Path,
+    conflict_policy: ConflictPolicy,
+) -> Result<()> {
+    for file in &delete.files {
+        let path = resolve_entry_path(work_dir, file)?;
+        if keep_protected(&path, conflict_policy) {
+            info!("🛡️ 保护 upload 目录，跳过删除文件: {}", path.display());
+            continue;
+        }
+        info!("🗑️ 删除文件: {}", path.display());
+        if path.is_file() {
+            std::fs::remove_file(&path)?;
+        } else if path.exists() {
+            std::fs::remove_file(&path).or_else(|_| std::fs::remove_dir_all(&path))?;
+        } else {
+            info!("文件不存在，跳过: {}", path.display());
+        }
+    }
+    // 删除目录（跳过upload目录）
+    for dir in &delete.directories {
+        let path = resolve_entry_path(work_dir, dir)?;
+        if keep_protected(&path, conflict_policy) {
+            info!("🛡️ 保护 upload 目录，跳过删除目录: {}", path.display());
+            continue;
+        }
+        info!("🗑️ 删除目录: {}", path.display());
+        if path.is_dir() {
+            std::fs::remove_dir_all(&path)?;
+        } else if path.exists() {
+            std::fs::remove_file(&path).or_else(|_| std::fs::remove_dir_all(&path))?;
+        } else {
+            info!("目录不存在，跳过: {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// 规范化 tar 条目路径：统一分隔符并去掉前导的 `./`
+fn normalize_tar_entry_name(entry: &tar::Entry<'_, Box<dyn Read>>) -> Result<String> {
+    let raw = entry.path()?.to_string_lossy().replace('\\', "/");
+    Ok(raw.trim_start_matches("./").to_string())
+}
+
+/// 将单个 tar 条目写入目标路径（目录、普通文件、符号链接）
+///
+/// `base` 为解压根目录，用于校验链接条目不会指向目录之外
+fn unpack_tar_entry(
+    entry: &mut tar::Entry<'_, Box<dyn Read>>,
+    base: &std::path::Path,
+    target_path: &std::path::Path,
+    links: &mut SymlinkExtractor,
+    locked: &LockedFiles,
+) -> Result<bool> {
+    let entry_type = entry.header().entry_type();
+    if entry_type.is_dir() {
+        std::fs::create_dir_all(target_path)?;
+        Ok(false)
+    } else if entry_type.is_file() {
+        force_extract_entry(entry, false, target_path, locked)?;
+        Ok(true)
+    } else if entry_type.is_symlink() || entry_type.is_hard_link() {
+        let link_name = entry
+            .link_name()?
+            .ok_or_else(|| anyhow::anyhow!("链接条目缺少目标: {}", target_path.display()))?
+            .to_path_buf();
+        if target_path.is_dir() {
+            std::fs::remove_dir_all(target_path)?;
+        } else if target_path.symlink_metadata().is_ok() {
+            std::fs::remove_file(target_path)?;
+        }
+        ensure_parent_dir(target_path)?;
+
+        if entry_type.is_symlink() {
+            links.create(target_path, &link_name)?;
+        } else {
+            // 硬链接目标以归档根目录为基准，同样需要去掉顶层 docker/ 前缀
+            let link_str = link_name.to_string_lossy().replace('\\', "/");
+            let link_str = link_str.trim_start_matches("./");
+            let link_str = link_str.strip_prefix("docker/").unwrap_or(link_str);
+            let source = resolve_entry_path(base, link_str)?;
+            std::fs::hard_link(&source, target_path)?;
+        }
+        Ok(true)
+    } else {
+        info!("⏩ 跳过不支持的条目类型: {}", target_path.display());
+        Ok(false)
+    }
+}
+
+/// 全量解压 tar 系列服务包（tar 只能顺序读取，因此逐条解压）
+fn extract_tar_full_package(
+    package_path: &std::path::Path,
+    format: ArchiveFormat,
+    output_dir: &std::path::Path,
+    progress_callback: Option<ExtractProgressCallback<'_>>,
+    locked: &LockedFiles,
+) -> Result<(usize, u64)> {
+    let mut archive = format.open_tar(package_path)?;
+    let mut extracted_files = 0;
+    let mut extracted_size = 0u64;
+    let mut links = SymlinkExtractor::new(output_dir);
+
+    info!("🚀 开始顺序解压 {} 服务包...", format);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let file_name = normalize_tar_entry_name(&entry)?;
+        if file_name.is_empty() || file_name == "docker" || file_name == "docker/" {
+            continue;
+        }
+
+        // 跳过系统文件和临时文件
+        if should_skip_file(&file_name) {
+            info!("⏩ 跳过文件: {}", file_name);
+            continue;
+        }
+
+        // 处理路径：移除可能的顶层docker目录前缀
+        let clean_path = file_name.strip_prefix("docker/").unwrap_or(&file_name);
+        let target_path = resolve_entry_path(output_dir, clean_path)?;
+
+        // 检查是否为 upload 目录路径
+        if is_upload_directory_path(&target_path) {
+            if target_path.exists() {
+                info!(
+                    "🛡️ 保护现有 upload 目录，跳过解压: {}",
+                    target_path.display()
+                );
+                continue;
+            } else {
+                info!("📁 创建新的 upload 目录结构: {}", target_path.display());
+            }
+        }
+
+        let size = entry.size();
+        if unpack_tar_entry(&mut entry, output_dir, &target_path, &mut links, locked)? {
+            extracted_files += 1;
+            extracted_size += size;
+
+            if progress_callback.is_none() && extracted_files % 1000 == 0 {
+                info!(
+                    "📁 解压进度: {} 个文件, {:.1} MB",
+                    extracted_files,
+                    extracted_size as f64 / 1024.0 / 1024.0
+                );
+            }
+
+            // tar 无法预知条目总数，total 字段为 0
+            if let Some(callback) = progress_callback {
+                callback(ExtractProgress {
+                    extracted_files,
+                    total_files: 0,
+                    extracted_bytes: extracted_size,
+                    total_bytes: 0,
+                    percentage: 0.0,
+                });
+            }
+        }
+    }
+    links.finish()?;
+
+    Ok((extracted_files, extracted_size))
+}
+
+/// 增量解压 tar 系列补丁包：单次遍历，只解压 replace 中声明的文件和目录
+fn extract_tar_patch_package(
+    package_path: &std::path::Path,
+    format: ArchiveFormat,
+    work_dir: &std::path::Path,
+    replace: &client_core::api_types::ReplaceOperations,
+    conflict_policy: ConflictPolicy,
+    locked: &LockedFiles,
+) -> Result<(usize, u64)> {
+    use std::collections::HashSet;
+
+    let replace_files: HashSet<String> = replace
+        .files
+        .iter()
+        .map(|f| f.trim_start_matches('/').to_string())
+        .collect();
+    let replace_dirs: Vec<String> = replace
+        .directories
+        .iter()
+        .map(|d| d.trim_start_matches('/').trim_end_matches('/').to_string())
+        .collect();
+
+    // 清理即将替换的目录（保护目录已存在时跳过）
+    for dir in &replace_dirs {
+        let target_dir = resolve_entry_path(work_dir, dir)?;
+        if keep_protected(&target_dir, conflict_policy) && target_dir.exists() {
+            info!("🛡️ 保护现有目录，跳过目录替换: {}", target_dir.display());
+            continue;
+        }
+        if target_dir.exists() {
+            info!("🗑️  强制删除目录: {}", target_dir.display());
+            std::fs::remove_dir_all(&target_dir)?;
+        }
+    }
+
+    let mut archive = format.open_tar(package_path)?;
+    let mut found_files: HashSet<String> = HashSet::new();
+    let mut extracted_files = 0;
+    let mut extracted_size = 0u64;
+    let mut links = SymlinkExtractor::new(work_dir);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let file_name = normalize_tar_entry_name(&entry)?;
+        let Some(relative) = file_name.strip_prefix("docker/") else {
+            continue;
+        };
+        let relative = relative.trim_end_matches('/');
+        if relative.is_empty() {
+            continue;
+        }
+
+        let is_replace_file = replace_files.contains(relative);
+        let in_replace_dir = replace_dirs
+            .iter()
+            .any(|d| relative == d || relative.starts_with(&format!("{d}/")));
+        if !is_replace_file && !in_replace_dir {
+            continue;
+        }
+
+        let dst = resolve_entry_path(work_dir, relative)?;
+        if keep_protected(&dst, conflict_policy)
+            && dst.exists()
+            && !entry.header().entry_type().is_dir()
+        {
+            info!("🛡️ 保护现有目录，跳过替换: {}", dst.display());
+            if is_replace_file {
+                found_files.insert(relative.to_string());
+            }
+            continue;
+        }
+
+        let size = entry.size();
+        if unpack_tar_entry(&mut entry, work_dir, &dst, &mut links, locked)? {
+            extracted_files += 1;
+            extracted_size += size;
+        }
+        if is_replace_file {
+            found_files.insert(relative.to_string());
+        }
+    }
+    links.finish()?;
+
+    // 与 zip 行为保持一致：声明替换但压缩包中不存在的文件视为错误
+    if let Some(missing) = replace_files.iter().find(|f| !found_files.contains(*f)) {
+        return Err(anyhow::anyhow!("在压缩包中找不到文件 docker/{}", missing));
+    }
+
+    Ok((extracted_files, extracted_size))
+}
+
+/// 解压 tar.gz / tar.zst 格式的Docker服务包
+fn extract_tar_service_package(
+    package_path: &std::path::Path,
+    format: ArchiveFormat,
+    upgrade_strategy: &UpgradeStrategy,
+    conflict_policy: ConflictPolicy,
+    progress_callback: Option<ExtractProgressCallback<'_>>,
+    locked: &LockedFiles,
+) -> Result<()> {
+    let extract_start = Instant::now();
+
+    match upgrade_strategy {
+        UpgradeStrategy::FullUpgrade { target_version, .. } => {
+            let output_dir = get_docker_work_dir();
+            let output_dir = output_dir.as_path();
+            if output_dir.exists() {
+                safe_remove_docker_directory(output_dir, locked)?;
+            } else {
+                std::fs::create_dir_all(output_dir)?;
+            }
+
+            let (extracted_files, extracted_size) = extract_tar_full_package(
+                package_path,
+                format,
+                output_dir,
+                progress_callback,
+                locked,
+            )?;
+
+            let elapsed = extract_start.elapsed();
+            info!("🎉 Docker服务包解压完成!");
+            info!("   📁 解压文件: {} 个", extracted_files);
+            info!(
+                "   📏 总数据量: {:.1} MB",
+                extracted_size as f64 / 1024.0 / 1024.0
+            );
+            info!("   ⏱️  耗时: {:.2} 秒", elapsed.as_secs_f64());
+
+            ingest_into_package_store(output_dir, target_version);
+        }
+        UpgradeStrategy::PatchUpgrade { patch_info, .. } => {
+            let work_dir = get_docker_work_dir();
+            cleanup_patch_targets(patch_info, &work_dir, conflict_policy)?;
+
+            let operations = patch_info.operations.clone();
+            if let Some(replace) = &operations.replace {
+                let (extracted_files, extracted_size) =
+                    extract_tar_patch_package(
+                        package_path,
+                        format,
+                        &work_dir,
+                        replace,
+                        conflict_policy,
+                        locked,
+                    )?;
+                info!(
+                    "📁 补丁解压完成: {} 个文件, {:.1} MB",
+                    extracted_files,
+                    extracted_size as f64 / 1024.0 / 1024.0
+                );
+            }
+            if let Some(delete) = &operations.delete {
+                apply_patch_deletes(delete, &work_dir, conflict_policy)?;
+            }
+        }
+        UpgradeStrategy::PatchChainUpgrade { .. } => {
+            return Err(anyhow::anyhow!("补丁链需按补丁逐个解压"));
+        }
+        UpgradeStrategy::NoUpgrade { .. } => {
+            return Err(anyhow::anyhow!("无需升级,不支持的解压操作"));
+        }
+    }
+
+    Ok(())
+}
+
+/// 列出服务包中的条目名称（跳过系统文件和临时文件），用于升级前的变更预览
+///
+/// tar.gz/tar.zst 格式需要顺序读完整个包，大文件会耗时较长
+pub fn list_package_entries(package_path: &std::path::Path) -> Result<Vec<String>> {
+    let format = ArchiveFormat::detect(package_path)?;
+    let names = if format.is_tar() {
+        let mut archive = format.open_tar(package_path)?;
+        let mut names = Vec::new();
+        for entry in archive.entries()? {
+            names.push(normalize_tar_entry_name(&entry?)?);
+        }
+        names
+    } else {
+        let archive = zip::ZipArchive::new(std::fs::File::open(package_path)?)?;
+        archive.file_names().map(str::to_string).collect()
+    };
+
+    Ok(names
+        .into_iter()
+        .filter(|name| !should_skip_file(name))
+        .collect())
+}
+
+/// 读取服务包中指定的文本文件，`names` 为相对于 docker 目录的路径
+///
+/// 返回以相对路径为键的文件内容，服务包中不存在的文件不会出现在结果中
+pub fn read_package_text_files(
+    package_path: &std::path::Path,
+    names: &[&str],
+) -> Result<std::collections::HashMap<String, String>> {
+    let mut files = std::collections::HashMap::new();
+    let format = ArchiveFormat::detect(package_path)?;
+    if format.is_tar() {
+        let mut archive = format.open_tar(package_path)?;
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let file_name = normalize_tar_entry_name(&entry)?;
+            let clean_path = file_name.strip_prefix("docker/").unwrap_or(&file_name);
+            if let Some(name) = names.iter().find(|name| **name == clean_path) {
+                let mut content = String::new();
+                entry.read_to_string(&mut content)?;
+                files.insert(name.to_string(), content);
+                if files.len() == names.len() {
+                    break;
+                }
+            }
+        }
+    } else {
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(package_path)?)?;
+        for name in names {
+            for candidate in [format!("docker/{name}"), name.to_string()] {
+                if let Ok(mut file) = archive.by_name(&candidate) {
+                    let mut content = String::new();
+                    file.read_to_string(&mut content)?;
+                    files.insert(name.to_string(), content);
+                    break;
+                }
+            }
+        }
+    }
+    Ok(files)
+}
+
 /// 解压Docker服务包 - 简化版本
 pub async fn extract_docker_service(
     zip_path: &std::path::Path,
     upgrade_strategy: &UpgradeStrategy,
-) -> Result<()> {
+    conflict_policy: ConflictPolicy,
+) -> Result<LockedFileReport> {
+    extract_docker_service_with_progress(zip_path, upgrade_strategy, conflict_policy, None).await
+}
+
+/// 解压Docker服务包，并将阶段和进度上报到操作事件通道
+pub async fn extract_docker_service_with_events(
+    zip_path: &std::path::Path,
+    upgrade_strategy: &UpgradeStrategy,
+    conflict_policy: ConflictPolicy,
+    events: &EventSender,
+) -> Result<LockedFileReport> {
+    const PHASE: &str = "解压服务包";
+
+    if !events.is_enabled() {
+        return extract_docker_service(zip_path, upgrade_strategy, conflict_policy).await;
+    }
+
+    events.phase_started(OperationKind::Extract, PHASE);
+    let reporter: Mutex<Option<ProgressReporter>> = Mutex::new(None);
+    let callback = |progress: ExtractProgress| {
+        let mut reporter = reporter.lock().unwrap_or_else(|e| e.into_inner());
+        reporter
+            .get_or_insert_with(|| {
+                events.progress_reporter(OperationKind::Extract, progress.total_bytes)
+            })
+            .update(progress.extracted_bytes);
+    };
+    let locked = extract_docker_service_with_progress(
+        zip_path,
+        upgrade_strategy,
+        conflict_policy,
+        Some(&callback),
+    )
+    .await?;
+    events.phase_completed(OperationKind::Extract, PHASE);
+    Ok(locked)
+}
+
+/// 解压Docker服务包，并通过回调汇报全量解压进度
+///
+/// `conflict_policy` 决定补丁是否修改受保护目录（upload/、data/ 等）中已存在的内容。
+/// 被其他程序占用而无法替换的文件不会中止解压，而是在返回的报告中列出
+pub async fn extract_docker_service_with_progress(
+    zip_path: &std::path::Path,
+    upgrade_strategy: &UpgradeStrategy,
+    conflict_policy: ConflictPolicy,
+    progress_callback: Option<ExtractProgressCallback<'_>>,
+) -> Result<LockedFileReport> {
     let extract_start = Instant::now();
+    let locked = LockedFiles::default();
 
     info!("📦 开始解压Docker服务包: {}", zip_path.display());
 
-    // 检查ZIP文件是否存在
+    // 检查服务包文件是否存在
     if !zip_path.exists() {
         return Err(anyhow::anyhow!(format!(
-            "ZIP文件不存在: {}",
+            "服务包文件不存在: {}",
             zip_path.display()
         )));
     }
 
+    // 解压前确认 docker 目录可写，解压后的内容至少和服务包一样大
+    let archive_size = std::fs::metadata(zip_path)?.len();
+    client_core::fs_probe::check_writable(&get_docker_work_dir(), Some(archive_size))?;
+
+    // 识别服务包格式（魔术字节优先，其次扩展名）
+    let format = ArchiveFormat::detect(zip_path)?;
+    if format.is_tar() {
+        info!("✅ 检测到 {} 格式服务包", format);
+        extract_tar_service_package(
+            zip_path,
+            format,
+            upgrade_strategy,
+            conflict_policy,
+            progress_callback,
+            &locked,
+        )?;
+        return Ok(locked.into_report());
+    }
+
     // 打开ZIP文件
     let file = std::fs::File::open(zip_path)?;
     let mut archive = zip::ZipArchive::new(file)?;
@@ -309,82 +1214,25 @@ pub async fn extract_docker_service(
     info!("✅ ZIP文件打开成功，包含 {} 个文件", archive.len());
 
     match upgrade_strategy {
-        UpgradeStrategy::FullUpgrade { .. } => {
+        UpgradeStrategy::FullUpgrade { target_version, .. } => {
             // 目标解压目录
-            let output_dir = std::path::Path::new("docker");
+            let output_dir = get_docker_work_dir();
+            let output_dir = output_dir.as_path();
             // 如果目标目录已存在，安全清理它（保留upload目录）
             if output_dir.exists() {
-                safe_remove_docker_directory(output_dir)?;
+                safe_remove_docker_directory(output_dir, &locked)?;
             } else {
                 // 创建输出目录
                 std::fs::create_dir_all(output_dir)?;
             }
 
-            // 统计解压进度
-            let mut extracted_files = 0;
-            let mut extracted_size = 0u64;
-            let total_files = archive.len();
-
-            info!("🚀 开始解压 {} 个文件...", total_files);
-
-            for i in 0..archive.len() {
-                let mut file = archive.by_index(i)?;
-                let file_name = file.name().to_string();
-
-                // 跳过系统文件和临时文件
-                if should_skip_file(&file_name) {
-                    info!("⏩ 跳过文件: {}", file_name);
-                    continue;
-                }
-
-                // 处理路径：移除可能的顶层docker目录前缀
-                let clean_path = if file_name.starts_with("docker/") {
-                    // 如果ZIP内已有docker/前缀，移除它
-                    file_name.strip_prefix("docker/").unwrap_or(&file_name)
-                } else {
-                    &file_name
-                };
-
-                let target_path = output_dir.join(clean_path);
-
-                // 检查是否为 upload 目录路径
-                if is_upload_directory_path(&target_path) {
-                    // 如果 upload 目录已存在，跳过解压以保护用户数据
-                    // 如果 upload 目录不存在，正常解压以创建目录结构
-                    if target_path.exists() {
-                        info!(
-                            "🛡️ 保护现有 upload 目录，跳过解压: {}",
-                            target_path.display()
-                        );
-                        continue;
-                    } else {
-                        info!("📁 创建新的 upload 目录结构: {}", target_path.display());
-                    }
-                }
-
-                if file.is_dir() {
-                    // 创建目录
-                    std::fs::create_dir_all(&target_path)?;
-                } else {
-                    // 强制覆盖：先删除再解压（彻底解决 Directory not empty 错误）
-                    force_extract_file(&mut file, &target_path)?;
-
-                    extracted_files += 1;
-                    extracted_size += file.size();
-
-                    // 每解压10%的文件显示进度
-                    if extracted_files % (total_files / 10).max(1) == 0 {
-                        let percentage = (extracted_files * 100) / total_files;
-                        info!(
-                            "📁 解压进度: {}% ({}/{} 文件, {:.1} MB)",
-                            percentage,
-                            extracted_files,
-                            total_files,
-                            extracted_size as f64 / 1024.0 / 1024.0
-                        );
-                    }
-                }
-            }
+            let (extracted_files, extracted_size) = extract_full_package(
+                zip_path,
+                &mut archive,
+                output_dir,
+                progress_callback,
+                &locked,
+            )?;
 
             let elapsed = extract_start.elapsed();
             info!("🎉 Docker服务包解压完成!");
@@ -394,6 +1242,8 @@ pub async fn extract_docker_service(
                 extracted_size as f64 / 1024.0 / 1024.0
             );
             info!("   ⏱️  耗时: {:.2} 秒", elapsed.as_secs_f64());
+
+            ingest_into_package_store(output_dir, target_version);
         }
         UpgradeStrategy::PatchUpgrade {
             patch_info,
@@ -401,34 +1251,15 @@ pub async fn extract_docker_service(
             ..
         } => {
             // 增量升级：根据操作的文件和目录进行操作
-            let change_files = patch_info.get_changed_files();
             let work_dir = get_docker_work_dir();
-            let upgrade_change_file_or_dir = change_files
-                .iter()
-                .map(|path| work_dir.join(path))
-                .collect::<Vec<_>>();
-
-            // 清理即将被替换或删除的文件/目录（跳过upload目录）
-            for file_or_dir in upgrade_change_file_or_dir {
-                if is_upload_directory_path(&file_or_dir) {
-                    info!("🛡️ 保护 upload 目录，跳过删除: {}", file_or_dir.display());
-                    continue;
-                }
-
-                if file_or_dir.is_file() {
-                    std::fs::remove_file(file_or_dir)?;
-                } else if file_or_dir.is_dir() {
-                    std::fs::remove_dir_all(file_or_dir)?;
-                } else {
-                    info!("文件/目录不存在，跳过: {}", file_or_dir.display());
-                }
-            }
+            cleanup_patch_targets(patch_info, &work_dir, conflict_policy)?;
 
             let operations = patch_info.operations.clone();
             // 统计解压进度
             let mut extracted_files = 0;
             let mut extracted_size = 0u64;
             let total_files = archive.len();
+            let mut links = SymlinkExtractor::new(&work_dir);
 
             info!("🚀 开始解压 {} 个文件...", total_files);
 
@@ -446,10 +1277,10 @@ pub async fn extract_docker_service(
                         .by_name(&zip_path)
                         .map_err(|e| anyhow::anyhow!("在压缩包中找不到文件 {}: {}", zip_path, e))?;
 
-                    let dst = work_dir.join(&file);
+                    let dst = resolve_entry_path(&work_dir, &file)?;
 
-                    // 检查是否为保护目录路径
-                    if is_upload_directory_path(&dst) {
+                    // 检查是否为保护目录路径（冲突策略为替换时按补丁覆盖）
+                    if keep_protected(&dst, conflict_policy) {
                         // 如果保护目录已存在，跳过解压以保护用户数据
                         if dst.exists() {
                             info!("🛡️ 保护现有目录，跳过替换: {}", dst.display());
@@ -460,7 +1291,7 @@ pub async fn extract_docker_service(
                     }
 
                     // 强制覆盖：先删除再解压（彻底解决 Directory not empty 错误）
-                    force_extract_file(&mut entry, &dst)?;
+                    extract_zip_entry(&mut entry, &dst, &mut links, &locked)?;
 
                     extracted_files += 1;
                     extracted_size += entry.size();
@@ -472,8 +1303,8 @@ pub async fn extract_docker_service(
                     info!("📁 处理目录: {} -> {}", dir, zip_dir_path);
 
                     // 清理现有目录（跳过保护目录）
-                    let target_dir = work_dir.join(&dir);
-                    if is_upload_directory_path(&target_dir) && target_dir.exists() {
+                    let target_dir = resolve_entry_path(&work_dir, &dir)?;
+                    if keep_protected(&target_dir, conflict_policy) && target_dir.exists() {
                         info!("🛡️ 保护现有目录，跳过目录替换: {}", target_dir.display());
                         continue;
                     }
@@ -498,12 +1329,12 @@ pub async fn extract_docker_service(
                                 continue;
                             }
 
-                            let dst = target_dir.join(relative_path);
-                            ensure_parent_dir(&dst)?;
-
                             handle_extraction(
                                 &mut entry,
-                                &dst,
+                                &target_dir,
+                                relative_path,
+                                &mut links,
+                                &locked,
                                 &mut extracted_files,
                                 &mut extracted_size,
                             )?;
@@ -511,48 +1342,97 @@ pub async fn extract_docker_service(
                     }
                 }
             }
-            if let Some(delete) = operations.delete {
-                // 处理删除操作（跳过upload目录）
-                for file in delete.files {
-                    let path = work_dir.join(file);
-                    if is_upload_directory_path(&path) {
-                        info!("🛡️ 保护 upload 目录，跳过删除文件: {}", path.display());
-                        continue;
-                    }
-                    info!("🗑️ 删除文件: {}", path.display());
-                    if path.is_file() {
-                        std::fs::remove_file(&path)?;
-                    } else if path.exists() {
-                        std::fs::remove_file(&path).or_else(|_| std::fs::remove_dir_all(&path))?;
-                    } else {
-                        info!("文件不存在，跳过: {}", path.display());
-                    }
-                }
-                // 删除目录（跳过upload目录）
-                for dir in delete.directories {
-                    let path = work_dir.join(dir);
-                    if is_upload_directory_path(&path) {
-                        info!("🛡️ 保护 upload 目录，跳过删除目录: {}", path.display());
-                        continue;
-                    }
-                    info!("🗑️ 删除目录: {}", path.display());
-                    if path.is_dir() {
-                        std::fs::remove_dir_all(&path)?;
-                    } else if path.exists() {
-                        std::fs::remove_file(&path).or_else(|_| std::fs::remove_dir_all(&path))?;
-                    } else {
-                        info!("目录不存在，跳过: {}", path.display());
-                    }
-                }
+            links.finish()?;
+            if let Some(delete) = &operations.delete {
+                apply_patch_deletes(delete, &work_dir, conflict_policy)?;
             }
         }
+        UpgradeStrategy::PatchChainUpgrade { .. } => {
+            return Err(anyhow::anyhow!("补丁链需按补丁逐个解压"));
+        }
         UpgradeStrategy::NoUpgrade { .. } => {
             // 无需升级,不应该走到这里的解压逻辑
             return Err(anyhow::anyhow!("无需升级,不支持的解压操作"));
         }
     }
 
-    Ok(())
+    Ok(locked.into_report())
+}
+
+/// 日志输出选项
+///
+/// 命令行参数优先，未指定时读取对应的环境变量：
+/// - `NUWAX_LOG_FILE`：日志文件路径
+/// - `NUWAX_LOG_ROTATION`：轮转策略（`never`、`hourly`、`daily` 或如 `50MB` 的大小）
+/// - `NUWAX_LOG_MAX_FILES`：保留的日志文件数量
+/// - `NUWAX_LOG_SPLIT`：为 `1`/`true` 时额外输出下载和升级的独立日志文件
+///
+/// 同名的旧版 `DUCK_LOG_*` 环境变量仍然有效
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// 日志文件路径，为空时输出到终端
+    pub log_file: Option<PathBuf>,
+    /// 轮转策略
+    pub rotation: Option<LogRotation>,
+    /// 保留的日志文件数量
+    pub max_files: Option<usize>,
+    /// 是否为下载和升级输出独立的日志文件
+    pub split: bool,
+    /// OTLP 遥测配置（来自 config.toml 的 `[telemetry]`）
+    pub telemetry: Option<TelemetryConfig>,
+    /// 不向终端输出日志（日志文件和遥测不受影响）
+    pub quiet: bool,
+    /// 日志脱敏配置（来自 config.toml 的 `[log_redaction]`），为空时按默认规则脱敏
+    pub redaction: Option<LogRedactionConfig>,
+}
+
+impl LogOptions {
+    /// 用环境变量补全未通过命令行指定的选项
+    pub fn with_env_fallback(mut self) -> Self {
+        if self.log_file.is_none() {
+            self.log_file = log_env_var("LOG_FILE").map(PathBuf::from);
+        }
+        if self.rotation.is_none() {
+            self.rotation = log_env_var("LOG_ROTATION").and_then(|v| v.parse().ok());
+        }
+        if self.max_files.is_none() {
+            self.max_files = log_env_var("LOG_MAX_FILES").and_then(|v| v.parse().ok());
+        }
+        if !self.split {
+            self.split = log_env_var("LOG_SPLIT")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false);
+        }
+        self
+    }
+}
+
+/// 读取日志环境变量 `NUWAX_<name>`，未设置时兼容旧版 duck-cli 的 `DUCK_<name>`
+pub fn log_env_var(name: &str) -> Option<String> {
+    std::env::var(format!("{}{name}", logging::ENV_PREFIX))
+        .or_else(|_| std::env::var(format!("{}{name}", legacy::ENV_PREFIX)))
+        .ok()
+}
+
+/// 写入下载日志文件的模块
+const DOWNLOAD_LOG_TARGETS: [&str; 2] = ["client_core::downloader", "nuwax_cli::commands::update"];
+
+/// 写入升级日志文件的模块
+const UPGRADE_LOG_TARGETS: [&str; 6] = [
+    "client_core::upgrade",
+    "client_core::patch_executor",
+    "client_core::sql_diff",
+    "client_core::mysql_executor",
+    "nuwax_cli::commands::auto_upgrade_deploy",
+    "nuwax_cli::commands::docker_service",
+];
+
+/// 当前生效的日志文件路径
+static ACTIVE_LOG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// 获取当前生效的日志文件路径（日志输出到终端时为 None）
+pub fn active_log_file() -> Option<&'static Path> {
+    ACTIVE_LOG_FILE.get().map(PathBuf::as_path)
 }
 
 /// 设置日志记录系统
@@ -564,8 +1444,21 @@ pub async fn extract_docker_service(
 /// - 默认输出到stderr，避免与程序输出混淆
 /// - 终端输出简洁格式，文件输出详细格式
 pub fn setup_logging(verbose: bool) {
+    let _ = setup_logging_with_options(verbose, LogOptions::default());
+}
+
+/// 按指定选项设置日志记录系统
+///
+/// 输出到文件时默认按 50MB 大小轮转并保留 5 个文件。
+/// 终端和文件日志写出前按 `[log_redaction]` 遮盖敏感信息，遥测导出不经过脱敏。
+/// 配置了 OTLP 地址时返回遥测守卫，调用方需持有到进程退出前以便刷新数据。
+pub fn setup_logging_with_options(verbose: bool, options: LogOptions) -> Option<TelemetryGuard> {
     #[allow(unused_imports)]
-    use tracing_subscriber::{EnvFilter, fmt, util::SubscriberInitExt};
+    use tracing_subscriber::{
+        EnvFilter, Layer, filter::Targets, fmt, layer::SubscriberExt, util::SubscriberInitExt,
+    };
+
+    let options = options.with_env_fallback();
 
     // 根据verbose参数和环境变量确定日志级别
     let default_level = if verbose { "debug" } else { "info" };
@@ -576,33 +1469,109 @@ pub fn setup_logging(verbose: bool) {
         .add_directive("tokio=warn".parse().unwrap())
         .add_directive("hyper=warn".parse().unwrap());
 
-    // 检查环境变量，决定是否输出到文件
-    if let Ok(log_file) = std::env::var("DUCK_LOG_FILE") {
+    let mut layers: Vec<BoxedLayer> = Vec::new();
+    let redactor = log_redaction::build_redactor(options.redaction.as_ref());
+
+    // 检查是否输出到文件
+    if let Some(log_file) = options.log_file {
+        let rotation = options.rotation.unwrap_or_default();
+        let max_files = options.max_files.unwrap_or(DEFAULT_LOG_MAX_FILES);
+
         // 输出到文件 - 使用详细格式便于调试
-        let file = std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(log_file)
-            .expect("Failed to create log file");
-
-        fmt()
-            .with_env_filter(env_filter)
-            .with_writer(file)
-            .with_target(true)
-            .with_thread_names(true)
-            .with_line_number(true)
-            .init();
-    } else {
+        let writer =
+            open_log_writer(&log_file, rotation, max_files).expect("Failed to create log file");
+        layers.push(
+            fmt::layer()
+                .with_writer(Mutex::new(RedactingWriter::new(writer, redactor.clone())))
+                .with_ansi(false)
+                .with_target(true)
+                .with_thread_names(true)
+                .with_line_number(true)
+                .with_filter(env_filter)
+                .boxed(),
+        );
+
+        // 按模块拆分的下载/升级日志文件
+        if options.split {
+            let level = if verbose {
+                tracing::Level::DEBUG
+            } else {
+                tracing::Level::INFO
+            };
+            for (suffix, targets) in [
+                ("download", &DOWNLOAD_LOG_TARGETS[..]),
+                ("upgrade", &UPGRADE_LOG_TARGETS[..]),
+            ] {
+                let path = split_log_file_path(&log_file, suffix);
+                let writer =
+                    open_log_writer(&path, rotation, max_files).expect("Failed to create log file");
+                let filter = targets.iter().fold(Targets::new(), |filter, target| {
+                    filter.with_target(*target, level)
+                });
+                layers.push(
+                    fmt::layer()
+                        .with_writer(Mutex::new(RedactingWriter::new(writer, redactor.clone())))
+                        .with_ansi(false)
+                        .with_target(true)
+                        .with_line_number(true)
+                        .with_filter(filter)
+                        .boxed(),
+                );
+            }
+        }
+
+        let _ = ACTIVE_LOG_FILE.set(log_file);
+    } else if !options.quiet {
         // 输出到终端 - 使用简洁格式，用户友好
-        fmt()
-            .with_env_filter(env_filter)
-            .with_target(false) // 不显示模块路径
-            .with_thread_names(false) // 不显示线程名
-            .with_line_number(false) // 不显示行号
-            .without_time() // 不显示时间戳
-            .compact() // 使用紧凑格式
-            .init();
+        layers.push(
+            fmt::layer()
+                .with_writer(move || {
+                    // 与进度条交替输出
+                    RedactingWriter::new(progress_bars::TerminalLogWriter, redactor.clone())
+                })
+                .with_target(false) // 不显示模块路径
+                .with_thread_names(false) // 不显示线程名
+                .with_line_number(false) // 不显示行号
+                .without_time() // 不显示时间戳
+                .compact() // 使用紧凑格式
+                .with_filter(env_filter)
+                .boxed(),
+        );
+    }
+
+    // OTLP 遥测导出
+    let mut telemetry_guard = None;
+    let mut telemetry_error = None;
+    if let Some(config) = &options.telemetry {
+        match init_telemetry(config) {
+            Ok(Some((telemetry_layers, guard))) => {
+                layers.extend(telemetry_layers);
+                telemetry_guard = Some(guard);
+            }
+            Ok(None) => {}
+            Err(e) => telemetry_error = Some(e),
+        }
     }
+
+    tracing_subscriber::registry().with(layers).init();
+
+    if let Some(e) = telemetry_error {
+        tracing::warn!("⚠️ 遥测导出未启用: {}", e);
+    }
+    telemetry_guard
+}
+
+/// 由主日志文件路径生成拆分日志文件路径，如 `nuwax.log` -> `nuwax-download.log`
+fn split_log_file_path(log_file: &Path, suffix: &str) -> PathBuf {
+    let stem = log_file
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "nuwax-cli".to_string());
+    let file_name = match log_file.extension() {
+        Some(ext) => format!("{stem}-{suffix}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{suffix}"),
+    };
+    log_file.with_file_name(file_name)
 }
 
 /// 为库使用提供的简化日志初始化
@@ -0,0 +1,53 @@
+//! 下载或 API 请求失败后的网络诊断输出
+
+use client_core::connectivity::{
+    CheckOutcome, ConnectivityReport, Diagnosis, ProbeKind, diagnose_connectivity,
+};
+use tracing::{info, warn};
+
+/// 诊断到 `url` 的连通性并输出结论和建议
+pub async fn report_connectivity(url: &str, kind: ProbeKind) -> ConnectivityReport {
+    info!("🩺 正在诊断网络连通性...");
+    let report = diagnose_connectivity(url, kind).await;
+    print_report(&report);
+    report
+}
+
+fn print_report(report: &ConnectivityReport) {
+    info!("   目标: {}", report.url);
+    if let (Some(host), Some(port)) = (&report.host, report.port) {
+        info!("   主机: {}:{}", host, port);
+    }
+    print_check("DNS 解析", report.dns.as_ref());
+    print_check("TCP 连接", report.tcp.as_ref());
+    print_check("HTTP 请求", report.http.as_ref());
+    if report.proxies.is_empty() {
+        info!("   代理: 未配置");
+    } else {
+        for (name, value) in &report.proxies {
+            info!("   代理: {}={}", name, value);
+        }
+        if report.uses_proxy() {
+            info!("   💡 已配置代理，直连的 DNS/TCP 检查失败不影响经代理的下载");
+        }
+    }
+
+    if report.diagnosis == Diagnosis::Reachable {
+        info!("🩺 诊断结论: {}", report.diagnosis.summary());
+    } else {
+        warn!("🩺 诊断结论: {}", report.diagnosis.summary());
+    }
+    for suggestion in report.diagnosis.suggestions() {
+        info!("   💡 {}", suggestion);
+    }
+}
+
+fn print_check(name: &str, check: Option<&CheckOutcome>) {
+    match check {
+        Some(check) if check.ok => {
+            info!("   ✅ {}: {} ({} ms)", name, check.detail, check.elapsed_ms)
+        }
+        Some(check) => warn!("   ❌ {}: {} ({} ms)", name, check.detail, check.elapsed_ms),
+        None => info!("   ⏭️ {}: 未执行", name),
+    }
+}
@@ -0,0 +1,329 @@
+//! 补丁与受保护目录的冲突处理
+//!
+//! 补丁要替换或删除 upload/、data/ 等受保护目录中已存在的内容时，默认会跳过以保护用户数据，
+//! 但这可能留下半升级的状态。升级前列出这些冲突，由 `--on-conflict` 或交互选择决定：
+//! 保留现有内容、直接替换，还是先备份再替换。
+
+use crate::utils::prompt;
+use anyhow::Result;
+use client_core::api_types::PatchOperations;
+use client_core::safe_path::resolve_entry_path;
+use client_core::upgrade_strategy::UpgradeStrategy;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// 受保护路径冲突的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// 保留现有内容，跳过补丁中的对应操作
+    Keep,
+    /// 按补丁替换或删除
+    Replace,
+    /// 先备份现有内容，再按补丁替换或删除
+    BackupAndReplace,
+}
+
+impl ConflictPolicy {
+    /// 是否按补丁修改受保护路径
+    pub fn replaces_protected(self) -> bool {
+        !matches!(self, ConflictPolicy::Keep)
+    }
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "keep" => Ok(ConflictPolicy::Keep),
+            "replace" => Ok(ConflictPolicy::Replace),
+            "backup-and-replace" => Ok(ConflictPolicy::BackupAndReplace),
+            _ => Err(format!(
+                "无效的冲突处理策略: {s}（支持 keep、replace、backup-and-replace）"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictPolicy::Keep => write!(f, "保留现有内容"),
+            ConflictPolicy::Replace => write!(f, "直接替换"),
+            ConflictPolicy::BackupAndReplace => write!(f, "备份后替换"),
+        }
+    }
+}
+
+/// 补丁对受保护路径的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictAction {
+    Replace,
+    Delete,
+}
+
+impl fmt::Display for ConflictAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictAction::Replace => write!(f, "替换"),
+            ConflictAction::Delete => write!(f, "删除"),
+        }
+    }
+}
+
+/// 补丁要修改的、已存在的受保护路径
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtectedConflict {
+    /// 补丁中声明的相对路径
+    pub relative: String,
+    /// 工作目录下的实际路径
+    pub path: PathBuf,
+    pub action: ConflictAction,
+}
+
+/// 冲突及选定的处理策略
+#[derive(Debug, Clone)]
+pub struct ConflictResolution {
+    pub policy: ConflictPolicy,
+    pub conflicts: Vec<ProtectedConflict>,
+}
+
+impl ConflictResolution {
+    /// 没有冲突时的默认结果
+    pub fn none() -> Self {
+        Self {
+            policy: ConflictPolicy::Keep,
+            conflicts: Vec::new(),
+        }
+    }
+
+    /// 策略为备份后替换时，把冲突路径复制到 `backup_dir` 下（保持相对路径），返回备份目录
+    pub fn backup_if_requested(&self, backup_dir: &Path) -> Result<Option<PathBuf>> {
+        if self.policy != ConflictPolicy::BackupAndReplace || self.conflicts.is_empty() {
+            return Ok(None);
+        }
+        for conflict in &self.conflicts {
+            let target = resolve_entry_path(backup_dir, &conflict.relative)?;
+            copy_path(&conflict.path, &target)?;
+        }
+        info!(
+            "💾 已备份 {} 项受保护内容到: {}",
+            self.conflicts.len(),
+            backup_dir.display()
+        );
+        Ok(Some(backup_dir.to_path_buf()))
+    }
+
+    /// 输出最终处理结果，保留现有内容时列出被跳过的操作
+    pub fn report_outcome(&self) {
+        if self.conflicts.is_empty() {
+            return;
+        }
+        if self.policy.replaces_protected() {
+            info!(
+                "✅ 已按补丁处理 {} 项受保护路径 ({})",
+                self.conflicts.len(),
+                self.policy
+            );
+            return;
+        }
+        warn!(
+            "⚠️ 已跳过 {} 项受保护路径的补丁操作，对应文件仍为旧版本:",
+            self.conflicts.len()
+        );
+        for conflict in &self.conflicts {
+            warn!("   [跳过{}] {}", conflict.action, conflict.relative);
+        }
+    }
+}
+
+/// 路径是否位于受保护目录中
+pub fn is_protected_path(path: &Path) -> bool {
+    super::is_upload_directory_path(path)
+}
+
+/// 列出补丁要替换或删除的、已存在的受保护路径
+pub fn find_protected_conflicts(
+    operations: &[&PatchOperations],
+    work_dir: &Path,
+) -> Result<Vec<ProtectedConflict>> {
+    let mut conflicts: Vec<ProtectedConflict> = Vec::new();
+    for operations in operations {
+        let replace = operations
+            .replace
+            .iter()
+            .flat_map(|r| r.files.iter().chain(&r.directories))
+            .map(|p| (p, ConflictAction::Replace));
+        let delete = operations
+            .delete
+            .iter()
+            .flat_map(|d| d.files.iter().chain(&d.directories))
+            .map(|p| (p, ConflictAction::Delete));
+
+        for (relative, action) in replace.chain(delete) {
+            let path = resolve_entry_path(work_dir, relative)?;
+            if !is_protected_path(&path) || !path.exists() {
+                continue;
+            }
+            if conflicts.iter().any(|c| c.path == path) {
+                continue;
+            }
+            conflicts.push(ProtectedConflict {
+                relative: relative.trim_start_matches('/').to_string(),
+                path,
+                action,
+            });
+        }
+    }
+    Ok(conflicts)
+}
+
+/// 升级策略中所有补丁的操作，全量升级和无需升级时为空
+pub fn patch_operations(strategy: &UpgradeStrategy) -> Vec<&PatchOperations> {
+    match strategy {
+        UpgradeStrategy::PatchUpgrade { patch_info, .. } => vec![&patch_info.operations],
+        UpgradeStrategy::PatchChainUpgrade { steps, .. } => steps
+            .iter()
+            .map(|step| &step.patch_info.operations)
+            .collect(),
+        UpgradeStrategy::FullUpgrade { .. } | UpgradeStrategy::NoUpgrade { .. } => Vec::new(),
+    }
+}
+
+/// 列出升级策略中的受保护路径冲突，并确定处理策略
+///
+/// 指定了 `requested` 时直接使用；否则在终端中询问，非交互环境下保留现有内容
+pub fn resolve_conflicts(
+    strategy: &UpgradeStrategy,
+    work_dir: &Path,
+    requested: Option<ConflictPolicy>,
+) -> Result<ConflictResolution> {
+    let conflicts = find_protected_conflicts(&patch_operations(strategy), work_dir)?;
+    if conflicts.is_empty() {
+        return Ok(ConflictResolution::none());
+    }
+
+    warn!(
+        "⚠️ 补丁要修改 {} 项受保护路径中已存在的内容:",
+        conflicts.len()
+    );
+    for conflict in &conflicts {
+        warn!("   [{}] {}", conflict.action, conflict.relative);
+    }
+
+    let policy = match requested {
+        Some(policy) => policy,
+        None if !prompt::assume_yes() && prompt::can_prompt() => prompt_policy()?,
+        None => {
+            warn!(
+                "⚠️ 无法询问且未指定 --on-conflict，保留现有内容，可使用 --on-conflict replace|backup-and-replace 按补丁替换"
+            );
+            ConflictPolicy::Keep
+        }
+    };
+    info!("🛡️ 受保护路径冲突处理策略: {}", policy);
+
+    Ok(ConflictResolution { policy, conflicts })
+}
+
+fn prompt_policy() -> Result<ConflictPolicy> {
+    let input = prompt::input(
+        "如何处理以上受保护路径? [k]保留现有内容 / [r]直接替换 / [b]备份后替换 (默认 k): ",
+    )?;
+    Ok(match input.to_ascii_lowercase().as_str() {
+        "r" | "replace" => ConflictPolicy::Replace,
+        "b" | "backup-and-replace" => ConflictPolicy::BackupAndReplace,
+        _ => ConflictPolicy::Keep,
+    })
+}
+
+/// 复制文件或目录，目录按原结构递归复制
+fn copy_path(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if src.is_dir() {
+        std::fs::create_dir_all(dst)?;
+        for entry in std::fs::read_dir(src)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+    } else {
+        if let Some(parent) = dst.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(src, dst)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client_core::api_types::ReplaceOperations;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_and_backup_protected_conflicts() {
+        let temp_dir = TempDir::new().unwrap();
+        let work_dir = temp_dir.path().join("docker");
+        std::fs::create_dir_all(work_dir.join("upload/images")).unwrap();
+        std::fs::write(work_dir.join("upload/images/logo.png"), "user").unwrap();
+        std::fs::create_dir_all(work_dir.join("data")).unwrap();
+
+        let operations = PatchOperations {
+            replace: Some(ReplaceOperations {
+                files: vec![
+                    "upload/images/logo.png".to_string(),
+                    "upload/new.png".to_string(),
+                    "app/app.jar".to_string(),
+                ],
+                directories: vec!["data".to_string()],
+            }),
+            delete: Some(ReplaceOperations {
+                files: vec![],
+                directories: vec!["upload/images".to_string()],
+            }),
+        };
+
+        let conflicts = find_protected_conflicts(&[&operations], &work_dir).unwrap();
+        let summary: Vec<(&str, ConflictAction)> = conflicts
+            .iter()
+            .map(|c| (c.relative.as_str(), c.action))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("upload/images/logo.png", ConflictAction::Replace),
+                ("data", ConflictAction::Replace),
+                ("upload/images", ConflictAction::Delete),
+            ]
+        );
+
+        let backup_dir = temp_dir.path().join("backup");
+        let keep = ConflictResolution {
+            policy: ConflictPolicy::Keep,
+            conflicts: conflicts.clone(),
+        };
+        assert!(keep.backup_if_requested(&backup_dir).unwrap().is_none());
+
+        let backup = ConflictResolution {
+            policy: ConflictPolicy::BackupAndReplace,
+            conflicts,
+        };
+        backup.backup_if_requested(&backup_dir).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(backup_dir.join("upload/images/logo.png")).unwrap(),
+            "user"
+        );
+        assert!(backup_dir.join("data").is_dir());
+    }
+
+    #[test]
+    fn test_conflict_policy_from_str() {
+        assert_eq!("keep".parse(), Ok(ConflictPolicy::Keep));
+        assert_eq!(
+            "Backup-And-Replace".parse(),
+            Ok(ConflictPolicy::BackupAndReplace)
+        );
+        assert!("overwrite".parse::<ConflictPolicy>().is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! 终端进度条
+//!
+//! 标准输出和标准错误都是终端且未指定 `--events` 时，下载、解压、备份等操作的进度事件
+//! 渲染为 indicatif 进度条，每种操作一条，升级流水线另有一条总体进度条。
+//! 日志经 [`TerminalLogWriter`] 写出，写入前暂时擦除进度条，两者不会互相覆盖。
+//! 非终端环境或指定了 `--events` 时不使用进度条，进度按原来的方式输出为周期性的日志行。
+
+use super::event_output::format_human;
+use client_core::events::{EventReceiver, OperationEvent, OperationKind};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{self, IsTerminal, Write};
+use std::sync::OnceLock;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// 进度条所在的多进度条容器，渲染任务启动后设置
+static MULTI_PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+
+/// 字节类进度（下载、解压）的模板
+const BYTES_TEMPLATE: &str =
+    "{prefix} {msg:20!} [{bar:30}] {bytes}/{total_bytes} {bytes_per_sec} 剩余 {eta}";
+/// 计数类进度（备份、补丁等）的模板
+const COUNT_TEMPLATE: &str = "{prefix} {msg:20!} [{bar:30}] {pos}/{len}";
+/// 总量未知时的模板
+const UNKNOWN_TEMPLATE: &str = "{prefix} {msg:20!} {spinner} {pos}";
+/// 升级总体进度的模板
+const OVERALL_TEMPLATE: &str = "{prefix} {msg:20!} [{bar:30}] {pos}%";
+
+/// 当前环境是否使用进度条：标准输出和标准错误都是终端
+pub fn progress_bars_supported() -> bool {
+    io::stdout().is_terminal() && io::stderr().is_terminal()
+}
+
+/// 启动进度条渲染任务，所有发送端释放后清除进度条并结束
+pub fn spawn_progress_renderer(mut receiver: EventReceiver) -> JoinHandle<()> {
+    let multi = MULTI_PROGRESS
+        .get_or_init(|| MultiProgress::with_draw_target(ProgressDrawTarget::stderr()))
+        .clone();
+    tokio::spawn(async move {
+        let mut bars = ProgressBars::new(multi);
+        while let Some(event) = receiver.recv().await {
+            bars.handle(&event);
+        }
+        bars.clear();
+    })
+}
+
+/// 终端日志输出：进度条显示期间先擦除进度条再写日志，写完后重绘
+pub struct TerminalLogWriter;
+
+impl Write for TerminalLogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match MULTI_PROGRESS.get() {
+            Some(multi) => multi.suspend(|| io::stdout().write(buf)),
+            None => io::stdout().write(buf),
+        }
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match MULTI_PROGRESS.get() {
+            Some(multi) => multi.suspend(|| io::stdout().write_all(buf)),
+            None => io::stdout().write_all(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+struct ProgressBars {
+    multi: MultiProgress,
+    /// 各操作的进度条，按首次出现的顺序排列
+    bars: Vec<(OperationKind, ProgressBar)>,
+    /// 升级流水线的总体进度条
+    overall: Option<ProgressBar>,
+}
+
+impl ProgressBars {
+    fn new(multi: MultiProgress) -> Self {
+        Self {
+            multi,
+            bars: Vec::new(),
+            overall: None,
+        }
+    }
+
+    fn handle(&mut self, event: &OperationEvent) {
+        match event {
+            OperationEvent::PhaseStarted { operation, phase } => {
+                if *operation == OperationKind::Upgrade {
+                    info!("{}", format_human(event));
+                } else {
+                    // 同一操作进入新阶段时从头开始，总量由后续进度事件给出
+                    let bar = self.bar(*operation);
+                    bar.reset();
+                    bar.unset_length();
+                    bar.set_style(style_for(*operation, 0));
+                    bar.set_message(phase.clone());
+                }
+            }
+            OperationEvent::PhaseCompleted { operation, .. } => {
+                if let Some(index) = self.bars.iter().position(|(kind, _)| kind == operation) {
+                    let (_, bar) = self.bars.remove(index);
+                    bar.finish_and_clear();
+                    self.multi.remove(&bar);
+                }
+                info!("{}", format_human(event));
+            }
+            OperationEvent::Progress {
+                operation,
+                current,
+                total,
+            } => {
+                let bar = self.bar(*operation);
+                if *total > 0 && bar.length() != Some(*total) {
+                    bar.set_length(*total);
+                    bar.set_style(style_for(*operation, *total));
+                }
+                bar.set_position(*current);
+            }
+            OperationEvent::Warning { .. } | OperationEvent::Retry { .. } => {
+                warn!("{}", format_human(event));
+            }
+            OperationEvent::PipelineProgress { phase, percent, .. } => {
+                let overall = self.overall.get_or_insert_with(|| {
+                    let bar = self.multi.insert(0, ProgressBar::new(100));
+                    bar.set_style(template(OVERALL_TEMPLATE));
+                    bar.set_prefix(OperationKind::Upgrade.display_name());
+                    bar
+                });
+                overall.set_message(phase.display_name());
+                overall.set_position(percent.clamp(0.0, 100.0) as u64);
+            }
+        }
+    }
+
+    /// 获取操作的进度条，不存在时创建
+    fn bar(&mut self, operation: OperationKind) -> ProgressBar {
+        if let Some((_, bar)) = self.bars.iter().find(|(kind, _)| *kind == operation) {
+            return bar.clone();
+        }
+        let bar = self.multi.add(ProgressBar::no_length());
+        bar.set_style(style_for(operation, 0));
+        bar.set_prefix(operation.display_name());
+        self.bars.push((operation, bar.clone()));
+        bar
+    }
+
+    fn clear(&mut self) {
+        for (_, bar) in self.bars.drain(..) {
+            bar.finish_and_clear();
+        }
+        if let Some(overall) = self.overall.take() {
+            overall.finish_and_clear();
+        }
+        let _ = self.multi.clear();
+    }
+}
+
+fn style_for(operation: OperationKind, total: u64) -> ProgressStyle {
+    if total == 0 {
+        return template(UNKNOWN_TEMPLATE);
+    }
+    match operation {
+        OperationKind::Download | OperationKind::Extract | OperationKind::Relocate => {
+            template(BYTES_TEMPLATE)
+        }
+        OperationKind::Backup | OperationKind::Patch | OperationKind::Upgrade => {
+            template(COUNT_TEMPLATE)
+        }
+    }
+}
+
+fn template(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("█▉░")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_are_valid() {
+        for template in [
+            BYTES_TEMPLATE,
+            COUNT_TEMPLATE,
+            UNKNOWN_TEMPLATE,
+            OVERALL_TEMPLATE,
+        ] {
+            assert!(ProgressStyle::with_template(template).is_ok(), "{template}");
+        }
+    }
+
+    #[test]
+    fn test_bars_follow_phases() {
+        let mut bars =
+            ProgressBars::new(MultiProgress::with_draw_target(ProgressDrawTarget::hidden()));
+        bars.handle(&OperationEvent::PhaseStarted {
+            operation: OperationKind::Download,
+            phase: "下载服务包".to_string(),
+        });
+        bars.handle(&OperationEvent::Progress {
+            operation: OperationKind::Download,
+            current: 50,
+            total: 200,
+        });
+        let (_, bar) = &bars.bars[0];
+        assert_eq!(bar.length(), Some(200));
+        assert_eq!(bar.position(), 50);
+
+        bars.handle(&OperationEvent::PhaseCompleted {
+            operation: OperationKind::Download,
+            phase: "下载服务包".to_string(),
+        });
+        assert!(bars.bars.is_empty());
+    }
+}
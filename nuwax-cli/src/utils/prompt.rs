@@ -0,0 +1,138 @@
+//! 确认提示和交互输入
+//!
+//! 全局 `--yes` 对所有确认提示自动回答“是”，`--no-input` 禁止任何交互输入。
+//! 两者都未指定时只在标准输入是终端时询问；无法询问时立即返回错误，
+//! 避免在 CI、图形界面等没有终端的环境中阻塞等待输入。
+
+use anyhow::Result;
+use std::io::{IsTerminal, Write};
+use std::sync::OnceLock;
+use tracing::info;
+
+/// 需要用户确认或输入时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PromptMode {
+    /// 在终端中询问
+    #[default]
+    Interactive,
+    /// 所有确认提示自动回答“是”
+    AssumeYes,
+    /// 禁止交互输入，需要输入时报错
+    NoInput,
+}
+
+impl PromptMode {
+    pub fn from_flags(yes: bool, no_input: bool) -> Self {
+        if yes {
+            PromptMode::AssumeYes
+        } else if no_input {
+            PromptMode::NoInput
+        } else {
+            PromptMode::Interactive
+        }
+    }
+}
+
+static PROMPT_MODE: OnceLock<PromptMode> = OnceLock::new();
+
+/// 设置全局提示方式，只在启动时设置一次
+pub fn set_prompt_mode(mode: PromptMode) {
+    let _ = PROMPT_MODE.set(mode);
+}
+
+pub fn prompt_mode() -> PromptMode {
+    PROMPT_MODE.get().copied().unwrap_or_default()
+}
+
+/// 是否指定了 `--yes`
+pub fn assume_yes() -> bool {
+    prompt_mode() == PromptMode::AssumeYes
+}
+
+/// 是否可以在终端中询问用户
+pub fn can_prompt() -> bool {
+    unavailable_reason(prompt_mode(), std::io::stdin().is_terminal()).is_none()
+}
+
+/// 请求用户确认，`--yes` 时直接确认
+///
+/// 无法询问时返回错误，提示使用 `--yes`
+pub fn confirm(prompt: &str) -> Result<bool> {
+    let mode = prompt_mode();
+    if mode == PromptMode::AssumeYes {
+        info!("{} (--yes 已确认)", prompt);
+        return Ok(true);
+    }
+    if let Some(reason) = unavailable_reason(mode, std::io::stdin().is_terminal()) {
+        return Err(anyhow::anyhow!(
+            "需要确认“{prompt}”，但{reason}，可使用 --yes 自动确认"
+        ));
+    }
+    Ok(is_yes(&read_line(&format!("{prompt} (y/N): "))?))
+}
+
+/// 读取一行输入（已去除首尾空白）
+///
+/// `--yes` 无法代替需要输入的内容，无法询问时始终返回错误
+pub fn input(prompt: &str) -> Result<String> {
+    let reason = match prompt_mode() {
+        PromptMode::AssumeYes => Some("--yes 无法代替需要输入的内容"),
+        mode => unavailable_reason(mode, std::io::stdin().is_terminal()),
+    };
+    if let Some(reason) = reason {
+        return Err(anyhow::anyhow!("需要输入“{}”，但{}", prompt.trim(), reason));
+    }
+    read_line(prompt)
+}
+
+fn read_line(prompt: &str) -> Result<String> {
+    print!("\n{prompt}");
+    std::io::stdout().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+/// 无法询问用户的原因，可以询问时为 None
+fn unavailable_reason(mode: PromptMode, stdin_is_terminal: bool) -> Option<&'static str> {
+    match mode {
+        PromptMode::NoInput => Some("已指定 --no-input"),
+        _ if !stdin_is_terminal => Some("标准输入不是终端"),
+        _ => None,
+    }
+}
+
+fn is_yes(input: &str) -> bool {
+    input.eq_ignore_ascii_case("y") || input.eq_ignore_ascii_case("yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_mode_from_flags() {
+        assert_eq!(
+            PromptMode::from_flags(false, false),
+            PromptMode::Interactive
+        );
+        assert_eq!(PromptMode::from_flags(true, false), PromptMode::AssumeYes);
+        assert_eq!(PromptMode::from_flags(false, true), PromptMode::NoInput);
+    }
+
+    #[test]
+    fn test_unavailable_reason() {
+        assert_eq!(unavailable_reason(PromptMode::Interactive, true), None);
+        assert!(unavailable_reason(PromptMode::Interactive, false).is_some());
+        assert!(unavailable_reason(PromptMode::NoInput, true).is_some());
+    }
+
+    #[test]
+    fn test_is_yes() {
+        assert!(is_yes("y"));
+        assert!(is_yes("YES"));
+        assert!(!is_yes(""));
+        assert!(!is_yes("n"));
+    }
+}
@@ -0,0 +1,124 @@
+//! # OpenTelemetry 导出
+//!
+//! 在 `config.toml` 的 `[telemetry]` 中配置 `otlp_endpoint` 后，通过 OTLP/HTTP 导出：
+//! - **traces**：各命令阶段的 span（`phase.download`、`phase.backup`、`phase.extract`、
+//!   `phase.sql_upgrade`、`phase.start` 等，由 `#[tracing::instrument]` 标注）
+//! - **metrics**：target 为 [`METRICS_TARGET`] 的事件，字段按 tracing-opentelemetry
+//!   约定命名（`monotonic_counter.*`、`histogram.*`），如下载吞吐量、备份大小、升级耗时和失败次数
+//!
+//! 导出功能需要启用 `otel` feature 构建，未启用时配置的地址会被忽略并给出警告。
+
+use client_core::config::TelemetryConfig;
+#[cfg(feature = "otel")]
+use client_core::constants::telemetry::METRICS_TARGET;
+use tracing_subscriber::{Layer, Registry};
+
+/// 可装入订阅者的日志/遥测层
+pub type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// 遥测导出守卫，drop 时刷新并关闭导出器
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    tracer_provider: opentelemetry_sdk::trace::SdkTracerProvider,
+    #[cfg(feature = "otel")]
+    meter_provider: opentelemetry_sdk::metrics::SdkMeterProvider,
+}
+
+#[cfg(feature = "otel")]
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        let tracer_provider = self.tracer_provider.clone();
+        let meter_provider = self.meter_provider.clone();
+        // 导出器使用阻塞 HTTP 客户端，不能在异步上下文中关闭
+        let _ = std::thread::spawn(move || {
+            let _ = tracer_provider.shutdown();
+            let _ = meter_provider.shutdown();
+        })
+        .join();
+    }
+}
+
+/// 根据配置创建 OTLP 导出层；未配置地址时返回 `Ok(None)`
+#[cfg(feature = "otel")]
+pub fn init_telemetry(
+    config: &TelemetryConfig,
+) -> anyhow::Result<Option<(Vec<BoxedLayer>, TelemetryGuard)>> {
+    use opentelemetry::KeyValue;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::Resource;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing::Level;
+    use tracing_subscriber::filter::{Targets, filter_fn};
+
+    let Some(endpoint) = config.endpoint() else {
+        return Ok(None);
+    };
+    let endpoint = endpoint.trim_end_matches('/').to_string();
+    let service_name = config.service_name.clone();
+
+    // 阻塞 HTTP 客户端不能在 tokio 运行时中创建，放到独立线程初始化
+    let (tracer_provider, meter_provider) = std::thread::spawn(move || {
+        let resource = Resource::builder()
+            .with_service_name(service_name)
+            .with_attribute(KeyValue::new("service.version", env!("CARGO_PKG_VERSION")))
+            .build();
+
+        let span_exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{endpoint}/v1/traces"))
+            .build()?;
+        let tracer_provider = SdkTracerProvider::builder()
+            .with_batch_exporter(span_exporter)
+            .with_resource(resource.clone())
+            .build();
+
+        let metric_exporter = MetricExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{endpoint}/v1/metrics"))
+            .build()?;
+        let meter_provider = SdkMeterProvider::builder()
+            .with_periodic_exporter(metric_exporter)
+            .with_resource(resource)
+            .build();
+
+        anyhow::Ok((tracer_provider, meter_provider))
+    })
+    .join()
+    .map_err(|_| anyhow::anyhow!("初始化 OTLP 导出器时线程异常退出"))??;
+
+    // 只导出本项目的 span 和错误事件，避免普通日志变成大量 span 事件
+    let trace_layer = tracing_opentelemetry::layer()
+        .with_tracer(tracer_provider.tracer("nuwax-cli"))
+        .with_filter(filter_fn(|metadata| {
+            let own_target = metadata.target().starts_with("nuwax_cli")
+                || metadata.target().starts_with("client_core");
+            own_target && (metadata.is_span() || *metadata.level() == Level::ERROR)
+        }))
+        .boxed();
+    let metrics_layer = tracing_opentelemetry::MetricsLayer::new(meter_provider.clone())
+        .with_filter(Targets::new().with_target(METRICS_TARGET, Level::TRACE))
+        .boxed();
+
+    Ok(Some((
+        vec![trace_layer, metrics_layer],
+        TelemetryGuard {
+            tracer_provider,
+            meter_provider,
+        },
+    )))
+}
+
+/// 未启用 `otel` feature 时的占位实现
+#[cfg(not(feature = "otel"))]
+pub fn init_telemetry(
+    config: &TelemetryConfig,
+) -> anyhow::Result<Option<(Vec<BoxedLayer>, TelemetryGuard)>> {
+    match config.endpoint() {
+        Some(endpoint) => Err(anyhow::anyhow!(
+            "当前构建未启用 otel 功能，已忽略遥测地址: {endpoint}"
+        )),
+        None => Ok(None),
+    }
+}
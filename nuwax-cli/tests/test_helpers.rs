@@ -47,14 +47,10 @@ pub const ENHANCED_MANIFEST_JSON: &str = r#"
                 "delete": {
                     "files": [
                         "old.jar",
-                        "config/application.yml",
-                        "docker-compose.yml",
-                        "scripts/start.sh"
+                        "config/legacy.yml"
                     ],
                     "directories": [
-                        "front/",
-                        "plugins/",
-                        "templates/"
+                        "old/"
                     ]
                 }
             },
@@ -67,15 +63,11 @@ pub const ENHANCED_MANIFEST_JSON: &str = r#"
             "operations": {
                 "delete": {
                     "files": [
-                        "app.jar",
-                        "config/application.yml",
-                        "docker-compose.yml",
-                        "scripts/start.sh"
+                        "old.jar",
+                        "config/legacy.yml"
                     ],
                     "directories": [
-                        "front/",
-                        "plugins/",
-                        "templates/"
+                        "old/"
                     ]
                 },
                 "replace": {